@@ -1,9 +1,22 @@
 use clap::Args;
-use std::time::Duration;
+use std::{net::SocketAddr, path::PathBuf, time::Duration};
 use url::Url;
 
 use xlayer_builder::args::BuilderArgs;
-use xlayer_monitor::FullLinkMonitorArgs;
+#[cfg(feature = "block-publisher")]
+use xlayer_flashblocks::block_publisher::BlockPublisher;
+use xlayer_legacy_rpc::{
+    parse_lookup_order, parse_method_name_mapping, parse_method_pattern, LookupOrder,
+    ReceiptCompatProfile, RoutingStrategyKind,
+};
+use xlayer_monitor::{
+    FullLinkMonitorArgs, TelemetryConfig, DEFAULT_SLOW_QUERY_CAPACITY,
+    DEFAULT_TX_TIMESTAMPS_RETENTION,
+};
+use xlayer_rpc::{
+    timeout::parse_method_timeout, FinalityTagPolicy, HeavyCallLimits, TxWalConfig,
+    ACCOUNT_METHODS, HEAVY_METHODS,
+};
 
 /// X Layer specific configuration flags
 #[derive(Debug, Clone, Args, PartialEq, Eq, Default)]
@@ -17,10 +30,50 @@ pub struct XLayerArgs {
     #[command(flatten)]
     pub legacy: LegacyRpcArgs,
 
+    /// Pending tag proxy configuration
+    #[command(flatten)]
+    pub pending: PendingRpcArgs,
+
     /// Full link monitor configuration
     #[command(flatten)]
     pub monitor: FullLinkMonitorArgs,
 
+    /// RPC method policy configuration
+    #[command(flatten)]
+    pub rpc_policy: RpcPolicyArgs,
+
+    /// Heavy RPC method limiter configuration
+    #[command(flatten)]
+    pub heavy_limit: HeavyCallLimitArgs,
+
+    /// Slow RPC query log configuration
+    #[command(flatten)]
+    pub slow_query: SlowQueryArgs,
+
+    /// Transaction timestamp configuration
+    #[command(flatten)]
+    pub tx_timestamps: TxTimestampArgs,
+
+    /// Safe and finalized block tag mapping
+    #[command(flatten)]
+    pub finality_tags: FinalityTagArgs,
+
+    /// Forwarded transaction log configuration
+    #[command(flatten)]
+    pub tx_wal: TxWalArgs,
+
+    /// Opt-in telemetry configuration
+    #[command(flatten)]
+    pub telemetry: TelemetryArgs,
+
+    /// Canonical block publisher configuration
+    #[command(flatten)]
+    pub block_publisher: BlockPublisherArgs,
+
+    /// Flashblock preconfirmation configuration
+    #[command(flatten)]
+    pub preconf: PreconfArgs,
+
     /// Enable custom flashblocks subscription
     #[arg(
         long = "xlayer.flashblocks-subscription",
@@ -37,6 +90,105 @@ pub struct XLayerArgs {
     )]
     pub flashblocks_subscription_max_addresses: usize,
 
+    /// JSON file scoping flashblocks subscriptions by API key
+    #[arg(
+        long = "xlayer.flashblocks-subscription-keys",
+        value_name = "FILE",
+        help = "JSON file with the flashblocks subscriptions allowed per API key, passed as the third eth_subscribe param (subscriptions are not scoped by default)",
+        requires = "enable_flashblocks_subscription"
+    )]
+    pub flashblocks_subscription_keys: Option<PathBuf>,
+
+    /// Upstream node whose pending transactions are merged into newPendingTransactions
+    #[arg(
+        long = "xlayer.pending-tx-feed-url",
+        value_name = "WS_URL",
+        help = "WebSocket endpoint of a node, e.g. the sequencer, whose newPendingTransactions feed is merged into the local subscriptions (disabled by default)",
+        requires = "enable_flashblocks_subscription"
+    )]
+    pub pending_tx_feed_url: Option<String>,
+
+    /// Lag behind the tip beyond which flashblocks subscriptions skip enriching pending blocks
+    #[arg(
+        long = "xlayer.flashblocks-subscription-max-lag",
+        help = "Skip streaming pending blocks whose timestamp lags the clock by more than this, e.g. while syncing, until a pending block is near the tip again, 0s streams every block",
+        value_name = "DURATION",
+        default_value = "0s",
+        value_parser = humantime::parse_duration,
+        requires = "enable_flashblocks_subscription"
+    )]
+    pub flashblocks_subscription_max_lag: Duration,
+
+    /// Serve transactions of the pending flashblock from eth_getTransactionByHash
+    #[arg(
+        long = "xlayer.rpc.flashblock-tx-inclusion",
+        help = "Return transactions of the pending flashblock from eth_getTransactionByHash with the pending block number and hash and a non-standard `flashblock` field (disabled by default)",
+        default_value = "false"
+    )]
+    pub flashblock_tx_inclusion: bool,
+
+    /// Set the number of cached eth_call/eth_estimateGas results against finalized blocks
+    #[arg(
+        long = "xlayer.rpc.call-cache-size",
+        help = "Maximum number of cached eth_call/eth_estimateGas results against finalized blocks, 0 disables the cache",
+        default_value = "0"
+    )]
+    pub call_cache_size: u64,
+
+    /// Serve xlayer_call, which applies a field mask to the result of a wrapped call
+    #[arg(
+        long = "xlayer.rpc.response-fields",
+        help = "Serve xlayer_call(method, params, mask), returning the result of the wrapped call with only the included or without the excluded fields (disabled by default)",
+        default_value = "false"
+    )]
+    pub response_fields: bool,
+
+    /// Window in which duplicate eth_sendRawTransaction submissions are answered locally
+    #[arg(
+        long = "xlayer.rpc.tx-dedup-window",
+        help = "Window in which duplicate eth_sendRawTransaction submissions return the original hash without being re-validated or forwarded, 0s disables deduplication",
+        value_name = "DURATION",
+        default_value = "0s",
+        value_parser = humantime::parse_duration
+    )]
+    pub tx_dedup_window: Duration,
+
+    /// Execution timeouts of locally served RPC methods
+    #[arg(
+        long = "xlayer.rpc.method-timeouts",
+        help = "Execution timeouts of locally served RPC methods as <method>=<duration>, e.g. eth_getLogs=30s, use * for all other methods",
+        value_name = "METHOD=DURATION",
+        value_delimiter = ',',
+        value_parser = parse_method_timeout
+    )]
+    pub method_timeouts: Vec<(String, Duration)>,
+
+    /// Minimum effective priority fee of pool transactions
+    #[arg(
+        long = "xlayer.txpool-min-priority-fee",
+        help = "Reject transactions of any origin whose effective priority fee per gas at the current base fee is below this value in wei, 0 accepts any fee",
+        value_name = "WEI",
+        default_value = "0"
+    )]
+    pub txpool_min_priority_fee: u128,
+
+    /// Address of the HTTP health and readiness probes
+    #[arg(
+        long = "xlayer.health-addr",
+        help = "Serve HTTP GET /healthz and /readyz on this address, independent of the JSON-RPC server (disabled by default)",
+        value_name = "ADDR"
+    )]
+    pub health_addr: Option<SocketAddr>,
+
+    /// Number of latest blocks the RPC caches are warmed from on startup
+    #[arg(
+        long = "xlayer.cache-warmup-blocks",
+        help = "On startup, warm the block hash index, fee history and fee parameters from this many latest blocks, reporting the node as not ready until done, 0 disables the warmup",
+        value_name = "BLOCKS",
+        default_value = "0"
+    )]
+    pub cache_warmup_blocks: u64,
+
     #[arg(
         long = "xlayer.sequencer-mode",
         help = "Enable sequencer mode for the node (default: false, i.e., RPC mode). This flag can be used by various business logic components to determine node behavior.",
@@ -49,7 +201,50 @@ impl XLayerArgs {
     /// Validate all X Layer configurations
     pub fn validate(&self) -> Result<(), String> {
         self.legacy.validate()?;
+        self.pending.validate()?;
         self.monitor.validate()?;
+        self.rpc_policy.validate()?;
+        self.heavy_limit.validate()?;
+        self.tx_timestamps.validate()?;
+        self.finality_tags.validate()?;
+        self.tx_wal.validate()?;
+        self.telemetry.validate()?;
+        self.block_publisher.validate()?;
+        self.validate_features()
+    }
+
+    /// Rejects the flags of subsystems left out of the build by the cargo features of the node.
+    pub fn validate_features(&self) -> Result<(), String> {
+        let disabled = [
+            (
+                "legacy-rpc",
+                cfg!(feature = "legacy-rpc"),
+                self.legacy.legacy_rpc_url.is_some(),
+                "--rpc.legacy-url",
+            ),
+            (
+                "monitor",
+                cfg!(feature = "monitor"),
+                self.monitor.enable
+                    || !self.slow_query.threshold.is_zero()
+                    || self.tx_timestamps.enable
+                    || self.telemetry.config().is_some(),
+                "--xlayer.full-link-monitor.*, --xlayer.rpc.slow-query-*, --xlayer.rpc.tx-timestamps and --xlayer.telemetry",
+            ),
+            (
+                "flashblocks",
+                cfg!(feature = "flashblocks"),
+                self.enable_flashblocks_subscription
+                    || self.flashblock_tx_inclusion
+                    || self.preconf.relay,
+                "--xlayer.flashblocks-subscription, --xlayer.rpc.flashblock-tx-inclusion and --xlayer.preconf.*",
+            ),
+        ];
+        for (feature, built, configured, flags) in disabled {
+            if configured && !built {
+                return Err(format!("{flags} require a build with the {feature} feature"));
+            }
+        }
         Ok(())
     }
 
@@ -107,6 +302,103 @@ pub struct LegacyRpcArgs {
         requires = "legacy_rpc_url"
     )]
     pub legacy_rpc_timeout: Duration,
+
+    /// How receipts served by the legacy node are reconciled with local receipts
+    #[arg(
+        long = "rpc.legacy-receipt-profile",
+        value_name = "PROFILE",
+        default_value_t = ReceiptCompatProfile::Passthrough,
+        help = "How legacy receipts are reconciled with local OP receipts: passthrough, fill (normalize formatting and fill missing OP fields) or strict (fill and omit fields unknown to the local node)"
+    )]
+    pub legacy_receipt_profile: ReceiptCompatProfile,
+
+    /// Strategy deciding which node serves a request
+    #[arg(
+        long = "rpc.legacy-routing-strategy",
+        value_name = "STRATEGY",
+        default_value_t = RoutingStrategyKind::Cutoff,
+        help = "Which node serves routable requests: cutoff (legacy before the cutoff block, local after), local (local only) or shadow (local, comparing a sample of --rpc.legacy-shadow-sample with the legacy node)"
+    )]
+    pub legacy_routing_strategy: RoutingStrategyKind,
+
+    /// Number of blocks on each side of the cutoff whose requests are shadow compared
+    #[arg(
+        long = "rpc.legacy-shadow-window",
+        value_name = "BLOCKS",
+        default_value = "0",
+        help = "Serve requests for blocks within this distance of the cutoff from both the local and the legacy node and log mismatches, 0 disables the comparison"
+    )]
+    pub legacy_shadow_window: u64,
+
+    /// Compare one in N eligible requests
+    #[arg(long = "rpc.legacy-shadow-sample", value_name = "N", default_value = "1")]
+    pub legacy_shadow_sample: u64,
+
+    /// Skip the legacy node for `eth_getLogs` filters whose addresses were all deployed after the
+    /// cutoff block
+    #[arg(
+        long = "rpc.legacy-skip-new-contracts",
+        help = "Skip the legacy half of eth_getLogs when every filtered address has no code at the cutoff block, requires the local state of the genesis block (disabled by default)",
+        default_value = "false"
+    )]
+    pub legacy_skip_new_contracts: bool,
+
+    /// Names of custom methods on the legacy node
+    #[arg(
+        long = "rpc.legacy-method-names",
+        help = "Names of custom methods on the legacy node as <method>=<legacy method>, e.g. eth_getInternalTransactions=zkevm_getInternalTransactions, mapped methods the local node can't serve are forwarded under their legacy name",
+        value_name = "METHOD=LEGACY_METHOD",
+        value_delimiter = ',',
+        value_parser = parse_method_name_mapping
+    )]
+    pub legacy_method_names: Vec<(String, String)>,
+
+    /// Patterns of methods not implemented locally that are forwarded to the legacy node
+    #[arg(
+        long = "rpc.legacy-forward-methods",
+        help = "Methods forwarded to the legacy node when the local node doesn't implement them, as method names or prefixes ending in *, e.g. zkevm_*",
+        value_name = "PATTERN",
+        value_delimiter = ',',
+        value_parser = parse_method_pattern
+    )]
+    pub legacy_forward_methods: Vec<String>,
+
+    /// Number of most recent blocks whose transaction hashes are indexed locally
+    #[arg(
+        long = "rpc.legacy-tx-index-window",
+        value_name = "BLOCKS",
+        default_value = "0",
+        help = "Number of most recent blocks whose transaction hashes are indexed locally, e.g. the transaction lookup pruning distance, 0 if all blocks are indexed"
+    )]
+    pub legacy_tx_index_window: u64,
+
+    /// Number of blocks below the index window scanned for transactions missed by the index
+    #[arg(
+        long = "rpc.legacy-tx-scan-blocks",
+        value_name = "BLOCKS",
+        default_value = "0",
+        help = "Scan up to this many local blocks below the transaction index window for transactions missed by the index before falling back to the legacy node, 0 disables the scan"
+    )]
+    pub legacy_tx_scan_blocks: u64,
+
+    /// Order of the nodes asked for hash lookups, per method
+    #[arg(
+        long = "rpc.legacy-lookup-order",
+        help = "Order in which the local and legacy node serve a hash lookup method as <method>=<local-first|legacy-first>, e.g. eth_getTransactionReceipt=legacy-first while the local node backfills, methods left out are served local first",
+        value_name = "METHOD=ORDER",
+        value_delimiter = ',',
+        value_parser = parse_lookup_order
+    )]
+    pub legacy_lookup_order: Vec<(String, LookupOrder)>,
+
+    /// Safety margin added to gas estimates of the legacy node
+    #[arg(
+        long = "rpc.legacy-estimate-gas-margin",
+        value_name = "PERCENT",
+        help = "Raise eth_estimateGas results of the legacy node by this percentage, capped at the call's gas and the latest local block gas limit, and reject legacy estimates above them (disabled by default)",
+        requires = "legacy_rpc_url"
+    )]
+    pub legacy_estimate_gas_margin: Option<u64>,
 }
 
 impl LegacyRpcArgs {
@@ -131,141 +423,780 @@ impl LegacyRpcArgs {
             }
         }
 
+        let shadow_strategy = self.legacy_routing_strategy == RoutingStrategyKind::Shadow;
+        if shadow_strategy && self.legacy_rpc_url.is_none() {
+            return Err("Shadow routing strategy requires a legacy RPC URL".to_string());
+        }
+
+        if (self.legacy_shadow_window > 0 || shadow_strategy) && self.legacy_shadow_sample == 0 {
+            return Err("Legacy shadow sample must be greater than zero".to_string());
+        }
+
+        if self.legacy_tx_scan_blocks > 0 && self.legacy_tx_index_window == 0 {
+            return Err("Legacy transaction scan requires a transaction index window".to_string());
+        }
+
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use clap::{Args, Parser};
+/// X Layer pending tag proxy arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq, Default)]
+pub struct PendingRpcArgs {
+    /// Flashblocks enabled node serving `pending` tag reads for this replica
+    #[arg(long = "xlayer.pending-rpc-url", value_name = "URL")]
+    pub pending_rpc_url: Option<String>,
 
-    /// A helper type to parse Args more easily
-    #[derive(Parser)]
-    struct CommandParser<T: Args> {
-        #[command(flatten)]
-        args: T,
-    }
+    /// Timeout for proxied pending tag requests
+    #[arg(
+        long = "xlayer.pending-rpc-timeout",
+        value_name = "DURATION",
+        default_value = "5s",
+        value_parser = humantime::parse_duration,
+        requires = "pending_rpc_url"
+    )]
+    pub pending_rpc_timeout: Duration,
+}
 
-    #[test]
-    fn test_xlayer_args_default() {
-        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
-        assert!(args.validate().is_ok());
-    }
+impl PendingRpcArgs {
+    /// Validate pending tag proxy configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(url_str) = &self.pending_rpc_url {
+            Url::parse(url_str)
+                .map_err(|e| format!("Invalid pending RPC URL '{url_str}': {e:?}"))?;
 
-    #[test]
-    fn test_legacy_rpc_disabled_by_default() {
-        let args = LegacyRpcArgs::default();
-        assert!(args.legacy_rpc_url.is_none());
-        assert!(args.validate().is_ok());
+            if self.pending_rpc_timeout.is_zero() {
+                return Err("Pending RPC timeout must be greater than zero".to_string());
+            }
+        }
+        Ok(())
     }
+}
 
-    #[test]
-    fn test_legacy_rpc_valid_http_url() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("http://localhost:8545".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(30),
-        };
-        assert!(args.validate().is_ok());
-    }
+/// X Layer RPC method policy arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq, Default)]
+pub struct RpcPolicyArgs {
+    /// Disable account-bearing methods (eth_accounts, eth_sign, eth_sendTransaction, ...)
+    #[arg(
+        long = "xlayer.rpc.disable-account-methods",
+        help = "Reject account-bearing methods (eth_accounts, eth_sign, eth_sendTransaction, ...) with a clear error",
+        default_value = "false"
+    )]
+    pub disable_account_methods: bool,
 
-    #[test]
-    fn test_legacy_rpc_valid_https_url() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("https://mainnet.infura.io/v3/YOUR-PROJECT-ID".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(30),
-        };
-        assert!(args.validate().is_ok());
-    }
+    /// Additional methods to reject
+    #[arg(long = "xlayer.rpc.disabled-methods", value_name = "METHODS", value_delimiter = ',')]
+    pub disabled_methods: Vec<String>,
+}
 
-    #[test]
-    fn test_legacy_rpc_valid_url_with_port() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("http://192.168.1.100:8545".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(30),
-        };
-        assert!(args.validate().is_ok());
+impl RpcPolicyArgs {
+    /// Validate RPC policy configuration
+    pub fn validate(&self) -> Result<(), String> {
+        for method in &self.disabled_methods {
+            if method.split_once('_').is_none_or(|(ns, name)| ns.is_empty() || name.is_empty()) {
+                return Err(format!(
+                    "Invalid disabled method '{method}', expected <namespace>_<method>"
+                ));
+            }
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_legacy_rpc_invalid_url_format() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("not-a-valid-url".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(30),
-        };
-        let result = args.validate();
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("Invalid legacy RPC URL"));
+    /// Returns all methods that should be rejected.
+    pub fn disabled_methods(&self) -> Vec<String> {
+        let mut methods = self.disabled_methods.clone();
+        if self.disable_account_methods {
+            methods.extend(ACCOUNT_METHODS.iter().map(|m| m.to_string()));
+        }
+        methods
     }
+}
 
-    #[test]
-    fn test_legacy_rpc_empty_url() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(30),
-        };
-        let result = args.validate();
-        assert!(result.is_err());
-    }
+/// X Layer per-client heavy RPC method limiter arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+pub struct HeavyCallLimitArgs {
+    /// Maximum concurrent heavy calls per client (0 = disabled)
+    #[arg(
+        long = "xlayer.rpc.heavy-max-concurrent",
+        help = "Maximum concurrent heavy calls (eth_getLogs, debug traces, ...) per client, 0 disables the limiter",
+        default_value = "0"
+    )]
+    pub max_concurrent: usize,
 
-    #[test]
-    fn test_legacy_rpc_invalid_scheme() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("ftp://example.com".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(30),
-        };
-        // This should pass validation (URL is valid, even if scheme is unusual)
-        assert!(args.validate().is_ok());
+    /// Maximum heavy calls per client waiting for a slot
+    #[arg(long = "xlayer.rpc.heavy-max-queued", default_value = "16")]
+    pub max_queued: usize,
+
+    /// Maximum time a queued heavy call waits for a slot
+    #[arg(
+        long = "xlayer.rpc.heavy-queue-timeout",
+        value_name = "DURATION",
+        default_value = "5s",
+        value_parser = humantime::parse_duration
+    )]
+    pub queue_timeout: Duration,
+
+    /// Methods subject to the limit, defaults to the built-in heavy method list
+    #[arg(long = "xlayer.rpc.heavy-methods", value_name = "METHODS", value_delimiter = ',')]
+    pub methods: Vec<String>,
+
+    /// Identify clients by the last `X-Forwarded-For` address, only safe behind a reverse proxy
+    #[arg(long = "xlayer.rpc.trust-forwarded-for", default_value = "false")]
+    pub trust_forwarded_for: bool,
+}
+
+impl Default for HeavyCallLimitArgs {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 0,
+            max_queued: 16,
+            queue_timeout: Duration::from_secs(5),
+            methods: Vec::new(),
+            trust_forwarded_for: false,
+        }
     }
+}
 
-    #[test]
-    fn test_legacy_rpc_zero_timeout() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("http://localhost:8545".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(0),
-        };
-        let result = args.validate();
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("timeout must be greater than zero"));
+impl HeavyCallLimitArgs {
+    /// Validate heavy call limiter configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_concurrent > 0 && self.queue_timeout.is_zero() {
+            return Err("Heavy call queue timeout must be greater than zero".to_string());
+        }
+        Ok(())
     }
 
-    #[test]
-    fn test_legacy_rpc_reasonable_timeout() {
-        let args = LegacyRpcArgs {
-            legacy_rpc_url: Some("http://localhost:8545".to_string()),
-            legacy_rpc_timeout: Duration::from_secs(60),
+    /// Returns the limits applied by the heavy call limiter.
+    pub fn limits(&self) -> HeavyCallLimits {
+        let methods = if self.methods.is_empty() {
+            HEAVY_METHODS.iter().map(|m| m.to_string()).collect()
+        } else {
+            self.methods.clone()
         };
-        assert!(args.validate().is_ok());
+        HeavyCallLimits {
+            max_concurrent: self.max_concurrent,
+            max_queued: self.max_queued,
+            queue_timeout: self.queue_timeout,
+            methods,
+        }
     }
+}
 
-    #[test]
-    fn test_legacy_rpc_parse_with_url() {
-        let args = CommandParser::<XLayerArgs>::parse_from([
-            "reth",
-            "--rpc.legacy-url",
-            "http://localhost:8545",
-            "--rpc.legacy-timeout",
-            "30s",
-        ])
-        .args;
-
-        assert_eq!(args.legacy.legacy_rpc_url, Some("http://localhost:8545".to_string()));
-        assert_eq!(args.legacy.legacy_rpc_timeout, Duration::from_secs(30));
-        assert!(args.validate().is_ok());
-    }
+/// X Layer slow RPC query log arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+pub struct SlowQueryArgs {
+    /// Latency above which calls are recorded in the slow query log (0s = disabled)
+    #[arg(
+        long = "xlayer.rpc.slow-query-threshold",
+        help = "Latency above which RPC calls are logged and retained for xlayer_getSlowQueries, 0s disables the slow query log",
+        value_name = "DURATION",
+        default_value = "0s",
+        value_parser = humantime::parse_duration
+    )]
+    pub threshold: Duration,
 
-    #[test]
-    fn test_legacy_rpc_parse_url_only_uses_default_timeout() {
-        let args = CommandParser::<XLayerArgs>::parse_from([
-            "reth",
-            "--rpc.legacy-url",
-            "http://localhost:8545",
-        ])
-        .args;
+    /// Number of slow queries retained
+    #[arg(long = "xlayer.rpc.slow-query-capacity", default_value_t = DEFAULT_SLOW_QUERY_CAPACITY)]
+    pub capacity: usize,
+}
 
-        assert_eq!(args.legacy.legacy_rpc_url, Some("http://localhost:8545".to_string()));
-        assert_eq!(args.legacy.legacy_rpc_timeout, Duration::from_secs(30)); // default
-        assert!(args.validate().is_ok());
+impl Default for SlowQueryArgs {
+    fn default() -> Self {
+        Self { threshold: Duration::ZERO, capacity: DEFAULT_SLOW_QUERY_CAPACITY }
+    }
+}
+
+/// X Layer transaction timestamp arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+pub struct TxTimestampArgs {
+    /// Serve xlayer_getTransactionTimestamps
+    #[arg(
+        long = "xlayer.rpc.tx-timestamps",
+        help = "Timestamp transactions submitted to this node when received, included in a flashblock built by this sequencer and added to the canonical chain, served by xlayer_getTransactionTimestamps (disabled by default)",
+        default_value = "false"
+    )]
+    pub enable: bool,
+
+    /// Time the timestamps of a transaction are retained after it was received
+    #[arg(
+        long = "xlayer.rpc.tx-timestamps-retention",
+        value_name = "DURATION",
+        default_value = "1h",
+        value_parser = humantime::parse_duration
+    )]
+    pub retention: Duration,
+}
+
+impl Default for TxTimestampArgs {
+    fn default() -> Self {
+        Self { enable: false, retention: DEFAULT_TX_TIMESTAMPS_RETENTION }
+    }
+}
+
+impl TxTimestampArgs {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.enable && self.retention.is_zero() {
+            return Err("--xlayer.rpc.tx-timestamps-retention must be greater than 0".into());
+        }
+        Ok(())
+    }
+}
+
+/// X Layer safe and finalized block tag mapping arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq, Default)]
+pub struct FinalityTagArgs {
+    /// Depth below the latest block treated as safe while the node has no safe head
+    #[arg(
+        long = "xlayer.rpc.safe-tag-depth",
+        help = "Treat the block this many blocks below the latest block as `safe` while the node has no safe head, e.g. on replicas without L1 finality data",
+        value_name = "BLOCKS"
+    )]
+    pub safe_depth: Option<u64>,
+
+    /// Depth below the latest block treated as finalized while the node has no finalized head
+    #[arg(
+        long = "xlayer.rpc.finalized-tag-depth",
+        help = "Treat the block this many blocks below the latest block as `finalized` while the node has no finalized head, e.g. on replicas without L1 finality data",
+        value_name = "BLOCKS"
+    )]
+    pub finalized_depth: Option<u64>,
+}
+
+impl FinalityTagArgs {
+    /// Validate block tag mapping configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(safe), Some(finalized)) = (self.safe_depth, self.finalized_depth)
+            && finalized < safe
+        {
+            return Err(format!(
+                "Finalized tag depth ({finalized}) must not be lower than safe tag depth ({safe})"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the block tag mapping policy.
+    pub fn policy(&self) -> FinalityTagPolicy {
+        FinalityTagPolicy { safe_depth: self.safe_depth, finalized_depth: self.finalized_depth }
+    }
+}
+
+/// X Layer forwarded transaction log arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+pub struct TxWalArgs {
+    /// Directory of the forwarded transaction log (disabled if not set)
+    #[arg(
+        long = "xlayer.rpc.tx-wal-dir",
+        help = "Append raw transactions submitted with eth_sendRawTransaction and their outcome to a log in this directory, for replay with `xlayer-reth-tools replay-wal` (disabled by default)",
+        value_name = "DIR"
+    )]
+    pub dir: Option<PathBuf>,
+
+    /// Size in bytes above which the log file is rotated
+    #[arg(
+        long = "xlayer.rpc.tx-wal-max-file-size",
+        value_name = "BYTES",
+        default_value = "67108864"
+    )]
+    pub max_file_size: u64,
+
+    /// Maximum number of log files kept, including the current one
+    #[arg(long = "xlayer.rpc.tx-wal-max-files", default_value = "16")]
+    pub max_files: usize,
+}
+
+impl Default for TxWalArgs {
+    fn default() -> Self {
+        Self { dir: None, max_file_size: 64 * 1024 * 1024, max_files: 16 }
+    }
+}
+
+impl TxWalArgs {
+    /// Validate forwarded transaction log configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.dir.is_some() && (self.max_file_size == 0 || self.max_files == 0) {
+            return Err(
+                "Transaction log max file size and max files must be greater than zero".to_string()
+            );
+        }
+        Ok(())
+    }
+
+    /// Returns the log configuration, `None` if the log is disabled.
+    pub fn config(&self) -> Option<TxWalConfig> {
+        self.dir.clone().map(|dir| TxWalConfig {
+            dir,
+            max_file_size: self.max_file_size,
+            max_files: self.max_files,
+        })
+    }
+}
+
+/// X Layer telemetry arguments, nothing is reported without the opt-in flag
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+pub struct TelemetryArgs {
+    /// Opt in to telemetry reporting
+    #[arg(
+        long = "xlayer.telemetry",
+        help = "Opt in to periodically report anonymous node statistics (version, chain id, head height, flashblock lag, legacy routing share) to --xlayer.telemetry.url (disabled by default)",
+        default_value = "false"
+    )]
+    pub telemetry_enabled: bool,
+
+    /// Endpoint the telemetry reports are posted to
+    #[arg(long = "xlayer.telemetry.url", value_name = "URL", requires = "telemetry_enabled")]
+    pub telemetry_url: Option<String>,
+
+    /// Interval between telemetry reports
+    #[arg(
+        long = "xlayer.telemetry.interval",
+        value_name = "DURATION",
+        default_value = "5m",
+        value_parser = humantime::parse_duration
+    )]
+    pub telemetry_interval: Duration,
+}
+
+impl Default for TelemetryArgs {
+    fn default() -> Self {
+        Self {
+            telemetry_enabled: false,
+            telemetry_url: None,
+            telemetry_interval: Duration::from_secs(300),
+        }
+    }
+}
+
+impl TelemetryArgs {
+    /// Validate telemetry configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.telemetry_enabled {
+            return Ok(());
+        }
+        let Some(url_str) = &self.telemetry_url else {
+            return Err("--xlayer.telemetry requires --xlayer.telemetry.url".to_string());
+        };
+        let url =
+            Url::parse(url_str).map_err(|e| format!("Invalid telemetry URL '{url_str}': {e:?}"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Telemetry URL '{url_str}' must use http or https"));
+        }
+        if self.telemetry_interval.is_zero() {
+            return Err("Telemetry interval must be greater than zero".to_string());
+        }
+        Ok(())
+    }
+
+    /// Returns the reporter configuration, `None` unless telemetry is opted in.
+    pub fn config(&self) -> Option<TelemetryConfig> {
+        let url = self.telemetry_url.clone().filter(|_| self.telemetry_enabled)?;
+        Some(TelemetryConfig { url, interval: self.telemetry_interval })
+    }
+}
+
+/// X Layer canonical block publisher arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq)]
+pub struct BlockPublisherArgs {
+    /// NATS server the canonical blocks and reorgs are published to
+    #[arg(
+        long = "xlayer.block-publisher.nats-url",
+        value_name = "URL",
+        help = "Publish a message per canonical block and per reorg to the NATS server at this URL, e.g. nats://127.0.0.1:4222 (disabled by default)"
+    )]
+    pub nats_url: Option<String>,
+
+    /// Prefix of the subjects the messages are published to
+    #[arg(
+        long = "xlayer.block-publisher.subject-prefix",
+        value_name = "PREFIX",
+        help = "Blocks are published to <PREFIX>.blocks and reorgs to <PREFIX>.reorgs",
+        default_value = "xlayer"
+    )]
+    pub subject_prefix: String,
+}
+
+impl Default for BlockPublisherArgs {
+    fn default() -> Self {
+        Self { nats_url: None, subject_prefix: "xlayer".to_string() }
+    }
+}
+
+impl BlockPublisherArgs {
+    /// Validate block publisher configuration
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(url_str) = &self.nats_url else { return Ok(()) };
+        if cfg!(not(feature = "block-publisher")) {
+            return Err(
+                "Block publisher requires a build with the block-publisher feature".to_string()
+            );
+        }
+        let url = Url::parse(url_str)
+            .map_err(|e| format!("Invalid block publisher NATS URL '{url_str}': {e:?}"))?;
+        if url.scheme() != "nats" || !url.has_host() {
+            return Err(format!(
+                "Block publisher NATS URL '{url_str}' must be nats://<host>:<port>"
+            ));
+        }
+        let prefix = &self.subject_prefix;
+        if prefix.is_empty()
+            || prefix.split('.').any(|token| {
+                token.is_empty()
+                    || token.contains(|c: char| c.is_whitespace() || c == '*' || c == '>')
+            })
+        {
+            return Err(format!(
+                "Block publisher subject prefix '{prefix}' must be dot separated tokens without whitespace or wildcards"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the publisher, `None` if publishing is disabled.
+    #[cfg(feature = "block-publisher")]
+    pub fn publisher(&self) -> Option<BlockPublisher> {
+        let url = Url::parse(self.nats_url.as_deref()?).ok()?;
+        Some(BlockPublisher::new(url, self.subject_prefix.clone()))
+    }
+}
+
+/// X Layer flashblock preconfirmation arguments
+#[derive(Debug, Clone, Args, PartialEq, Eq, Default)]
+pub struct PreconfArgs {
+    /// Relay the preconfirmations signed by the sequencer for the transactions of flashblocks
+    #[arg(
+        long = "xlayer.preconf.relay",
+        help = "Relay the preconfirmations the sequencer signs for the transactions of its flashblocks, served over xlayer_getPreconfirmation and the flashblocks subscription, requires --flashblocks-url (disabled by default)",
+        default_value = "false"
+    )]
+    pub relay: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{Args, Parser};
+
+    /// A helper type to parse Args more easily
+    #[derive(Parser)]
+    struct CommandParser<T: Args> {
+        #[command(flatten)]
+        args: T,
+    }
+
+    #[test]
+    fn test_xlayer_args_default() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_disabled_by_default() {
+        let args = LegacyRpcArgs::default();
+        assert!(args.legacy_rpc_url.is_none());
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_valid_http_url() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("http://localhost:8545".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_valid_https_url() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("https://mainnet.infura.io/v3/YOUR-PROJECT-ID".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_valid_url_with_port() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("http://192.168.1.100:8545".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_invalid_url_format() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("not-a-valid-url".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let result = args.validate();
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid legacy RPC URL"));
+    }
+
+    #[test]
+    fn test_legacy_rpc_empty_url() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            ..Default::default()
+        };
+        let result = args.validate();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_legacy_rpc_invalid_scheme() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("ftp://example.com".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            ..Default::default()
+        };
+        // This should pass validation (URL is valid, even if scheme is unusual)
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_zero_timeout() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("http://localhost:8545".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(0),
+            ..Default::default()
+        };
+        let result = args.validate();
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("timeout must be greater than zero"));
+    }
+
+    #[test]
+    fn test_legacy_rpc_reasonable_timeout() {
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("http://localhost:8545".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(60),
+            ..Default::default()
+        };
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_with_url() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-timeout",
+            "30s",
+            "--rpc.legacy-receipt-profile",
+            "fill",
+        ])
+        .args;
+
+        assert_eq!(args.legacy.legacy_rpc_url, Some("http://localhost:8545".to_string()));
+        assert_eq!(args.legacy.legacy_rpc_timeout, Duration::from_secs(30));
+        assert_eq!(args.legacy.legacy_receipt_profile, ReceiptCompatProfile::Fill);
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_shadow_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-shadow-window",
+            "1000",
+            "--rpc.legacy-shadow-sample",
+            "10",
+        ])
+        .args;
+        assert_eq!(args.legacy.legacy_shadow_window, 1000);
+        assert_eq!(args.legacy.legacy_shadow_sample, 10);
+        assert!(args.validate().is_ok());
+
+        let args = LegacyRpcArgs {
+            legacy_shadow_window: 1000,
+            legacy_shadow_sample: 0,
+            ..Default::default()
+        };
+        assert!(args.validate().unwrap_err().contains("shadow sample"));
+    }
+
+    #[test]
+    fn test_legacy_rpc_routing_strategy_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.legacy.legacy_routing_strategy, RoutingStrategyKind::Cutoff);
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-routing-strategy",
+            "shadow",
+        ])
+        .args;
+        assert_eq!(args.legacy.legacy_routing_strategy, RoutingStrategyKind::Shadow);
+        assert!(args.validate().is_ok());
+
+        let args = LegacyRpcArgs {
+            legacy_routing_strategy: RoutingStrategyKind::Shadow,
+            ..Default::default()
+        };
+        assert!(args.validate().unwrap_err().contains("legacy RPC URL"));
+
+        let args = LegacyRpcArgs {
+            legacy_rpc_url: Some("http://localhost:8545".to_string()),
+            legacy_rpc_timeout: Duration::from_secs(30),
+            legacy_routing_strategy: RoutingStrategyKind::Shadow,
+            ..Default::default()
+        };
+        assert!(args.validate().unwrap_err().contains("shadow sample"));
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_url_only_uses_default_timeout() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+        ])
+        .args;
+
+        assert_eq!(args.legacy.legacy_rpc_url, Some("http://localhost:8545".to_string()));
+        assert_eq!(args.legacy.legacy_rpc_timeout, Duration::from_secs(30)); // default
+        assert_eq!(args.legacy.legacy_receipt_profile, ReceiptCompatProfile::Passthrough);
+        assert!(!args.legacy.legacy_skip_new_contracts);
+        assert!(args.legacy.legacy_method_names.is_empty());
+        assert!(args.legacy.legacy_forward_methods.is_empty());
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_method_names() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-method-names",
+            "eth_getInternalTransactions=zkevm_getInternalTransactions,eth_getBlockInternalTransactions=zkevm_getBlockInternalTransactions",
+        ])
+        .args;
+
+        assert_eq!(
+            args.legacy.legacy_method_names,
+            [
+                (
+                    "eth_getInternalTransactions".to_string(),
+                    "zkevm_getInternalTransactions".to_string()
+                ),
+                (
+                    "eth_getBlockInternalTransactions".to_string(),
+                    "zkevm_getBlockInternalTransactions".to_string()
+                ),
+            ]
+        );
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_forward_methods() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-forward-methods",
+            "zkevm_*,eth_getInternalTransactions",
+        ])
+        .args;
+        assert_eq!(
+            args.legacy.legacy_forward_methods,
+            ["zkevm_*".to_string(), "eth_getInternalTransactions".to_string()]
+        );
+
+        let result = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--rpc.legacy-forward-methods",
+            "*",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_tx_lookup() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-tx-index-window",
+            "100000",
+            "--rpc.legacy-tx-scan-blocks",
+            "2000",
+        ])
+        .args;
+        assert_eq!(args.legacy.legacy_tx_index_window, 100000);
+        assert_eq!(args.legacy.legacy_tx_scan_blocks, 2000);
+        assert!(args.validate().is_ok());
+
+        let args = LegacyRpcArgs { legacy_tx_scan_blocks: 2000, ..Default::default() };
+        assert!(args.validate().unwrap_err().contains("index window"));
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_estimate_gas_margin() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.legacy.legacy_estimate_gas_margin, None);
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-estimate-gas-margin",
+            "20",
+        ])
+        .args;
+        assert_eq!(args.legacy.legacy_estimate_gas_margin, Some(20));
+
+        let res = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--rpc.legacy-estimate-gas-margin",
+            "20",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_legacy_rpc_parse_lookup_order() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.legacy.legacy_lookup_order.is_empty());
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--rpc.legacy-lookup-order",
+            "eth_getTransactionReceipt=legacy-first,eth_getBlockByHash=local-first",
+        ])
+        .args;
+        assert_eq!(
+            args.legacy.legacy_lookup_order,
+            vec![
+                ("eth_getTransactionReceipt".to_string(), LookupOrder::LegacyFirst),
+                ("eth_getBlockByHash".to_string(), LookupOrder::LocalFirst),
+            ]
+        );
+
+        // Only hash lookups fall back between the nodes
+        for order in ["eth_getBalance=legacy-first", "eth_getTransactionReceipt=legacy"] {
+            let res = CommandParser::<XLayerArgs>::try_parse_from([
+                "reth",
+                "--rpc.legacy-lookup-order",
+                order,
+            ]);
+            assert!(res.is_err());
+        }
     }
 
     #[test]
@@ -279,13 +1210,21 @@ mod tests {
             "--xlayer.flashblocks-subscription",
             "--xlayer.flashblocks-subscription-max-addresses",
             "2000",
+            "--xlayer.flashblocks-subscription-keys",
+            "/etc/xlayer/subscription-keys.json",
+            "--xlayer.rpc.flashblock-tx-inclusion",
         ])
         .args;
 
         assert!(args.enable_flashblocks_subscription);
+        assert!(args.flashblock_tx_inclusion);
         assert!(args.legacy.legacy_rpc_url.is_some());
         assert_eq!(args.legacy.legacy_rpc_timeout, Duration::from_secs(45));
         assert_eq!(args.flashblocks_subscription_max_addresses, 2000);
+        assert_eq!(
+            args.flashblocks_subscription_keys,
+            Some(PathBuf::from("/etc/xlayer/subscription-keys.json"))
+        );
         assert!(args.validate().is_ok());
     }
 
@@ -295,6 +1234,7 @@ mod tests {
             legacy: LegacyRpcArgs {
                 legacy_rpc_url: Some("invalid-url".to_string()),
                 legacy_rpc_timeout: Duration::from_secs(30),
+                ..Default::default()
             },
             ..Default::default()
         };
@@ -303,4 +1243,375 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("Invalid legacy RPC URL"));
     }
+
+    #[test]
+    fn test_rpc_policy_disabled_by_default() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.rpc_policy.disabled_methods().is_empty());
+    }
+
+    #[test]
+    fn test_rpc_policy_parse() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.disable-account-methods",
+            "--xlayer.rpc.disabled-methods",
+            "debug_traceCall,admin_peers",
+        ])
+        .args;
+
+        assert!(args.validate().is_ok());
+        let methods = args.rpc_policy.disabled_methods();
+        assert!(methods.contains(&"eth_sign".to_string()));
+        assert!(methods.contains(&"eth_sendTransaction".to_string()));
+        assert!(methods.contains(&"debug_traceCall".to_string()));
+        assert!(methods.contains(&"admin_peers".to_string()));
+    }
+
+    #[test]
+    fn test_rpc_policy_invalid_method() {
+        let args = RpcPolicyArgs {
+            disable_account_methods: false,
+            disabled_methods: vec!["sign".to_string()],
+        };
+        let result = args.validate();
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("Invalid disabled method"));
+    }
+
+    #[test]
+    fn test_heavy_limit_args() {
+        let args = CommandParser::<HeavyCallLimitArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.heavy-max-concurrent",
+            "4",
+            "--xlayer.rpc.heavy-methods",
+            "eth_getLogs,debug_traceCall",
+            "--xlayer.rpc.trust-forwarded-for",
+        ])
+        .args;
+        assert!(args.validate().is_ok());
+        assert!(args.trust_forwarded_for);
+
+        let limits = args.limits();
+        assert_eq!(limits.max_concurrent, 4);
+        assert_eq!(limits.queue_timeout, Duration::from_secs(5));
+        assert_eq!(limits.methods, vec!["eth_getLogs", "debug_traceCall"]);
+    }
+
+    #[test]
+    fn test_method_timeouts_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.method-timeouts",
+            "eth_getLogs=30s,*=2m",
+        ])
+        .args;
+        assert_eq!(
+            args.method_timeouts,
+            vec![
+                ("eth_getLogs".to_string(), Duration::from_secs(30)),
+                ("*".to_string(), Duration::from_secs(120)),
+            ]
+        );
+
+        let res = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--xlayer.rpc.method-timeouts",
+            "eth_getLogs",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_slow_query_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.slow_query, SlowQueryArgs::default());
+
+        let args = CommandParser::<SlowQueryArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.slow-query-threshold",
+            "500ms",
+            "--xlayer.rpc.slow-query-capacity",
+            "64",
+        ])
+        .args;
+        assert_eq!(args.threshold, Duration::from_millis(500));
+        assert_eq!(args.capacity, 64);
+    }
+
+    #[test]
+    fn test_tx_timestamp_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.tx_timestamps, TxTimestampArgs::default());
+
+        let args = CommandParser::<TxTimestampArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.tx-timestamps",
+            "--xlayer.rpc.tx-timestamps-retention",
+            "30m",
+        ])
+        .args;
+        assert!(args.enable);
+        assert_eq!(args.retention, Duration::from_secs(1800));
+        assert!(args.validate().is_ok());
+
+        let args = TxTimestampArgs { enable: true, retention: Duration::ZERO };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_pending_rpc_args() {
+        let args = CommandParser::<PendingRpcArgs>::parse_from([
+            "reth",
+            "--xlayer.pending-rpc-url",
+            "http://flashblocks-rpc:8545",
+        ])
+        .args;
+        assert!(args.validate().is_ok());
+        assert_eq!(args.pending_rpc_timeout, Duration::from_secs(5));
+
+        let args = PendingRpcArgs {
+            pending_rpc_url: Some("not-a-url".to_string()),
+            pending_rpc_timeout: Duration::from_secs(5),
+        };
+        assert!(args.validate().unwrap_err().contains("Invalid pending RPC URL"));
+    }
+
+    #[test]
+    fn test_finality_tag_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(!args.finality_tags.policy().is_enabled());
+
+        let args = CommandParser::<FinalityTagArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.safe-tag-depth",
+            "10",
+            "--xlayer.rpc.finalized-tag-depth",
+            "100",
+        ])
+        .args;
+        assert!(args.validate().is_ok());
+        assert_eq!(
+            args.policy(),
+            FinalityTagPolicy { safe_depth: Some(10), finalized_depth: Some(100) }
+        );
+
+        let args = FinalityTagArgs { safe_depth: Some(100), finalized_depth: Some(10) };
+        assert!(args.validate().unwrap_err().contains("must not be lower"));
+    }
+
+    #[test]
+    fn test_txpool_min_priority_fee() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.txpool_min_priority_fee, 0);
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.txpool-min-priority-fee",
+            "100000000",
+        ])
+        .args;
+        assert_eq!(args.txpool_min_priority_fee, 100_000_000);
+    }
+
+    #[test]
+    fn test_pending_tx_feed_url() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.pending_tx_feed_url.is_none());
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.flashblocks-subscription",
+            "--xlayer.pending-tx-feed-url",
+            "ws://sequencer:8546",
+        ])
+        .args;
+        assert_eq!(args.pending_tx_feed_url.as_deref(), Some("ws://sequencer:8546"));
+
+        // The feed is merged by the flashblocks subscription handler
+        let res = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--xlayer.pending-tx-feed-url",
+            "ws://sequencer:8546",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_flashblocks_subscription_max_lag() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.flashblocks_subscription_max_lag, Duration::ZERO);
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.flashblocks-subscription",
+            "--xlayer.flashblocks-subscription-max-lag",
+            "30s",
+        ])
+        .args;
+        assert_eq!(args.flashblocks_subscription_max_lag, Duration::from_secs(30));
+
+        let res = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--xlayer.flashblocks-subscription-max-lag",
+            "30s",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_cache_warmup_blocks() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.cache_warmup_blocks, 0);
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.cache-warmup-blocks",
+            "1024",
+        ])
+        .args;
+        assert_eq!(args.cache_warmup_blocks, 1024);
+    }
+
+    #[test]
+    fn test_health_addr() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.health_addr.is_none());
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.health-addr",
+            "0.0.0.0:8080",
+        ])
+        .args;
+        assert_eq!(args.health_addr, Some("0.0.0.0:8080".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_tx_wal_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert_eq!(args.tx_wal, TxWalArgs::default());
+        assert!(args.tx_wal.config().is_none());
+
+        let args = CommandParser::<TxWalArgs>::parse_from([
+            "reth",
+            "--xlayer.rpc.tx-wal-dir",
+            "/data/tx-wal",
+            "--xlayer.rpc.tx-wal-max-files",
+            "4",
+        ])
+        .args;
+        assert!(args.validate().is_ok());
+        assert_eq!(
+            args.config(),
+            Some(TxWalConfig {
+                dir: PathBuf::from("/data/tx-wal"),
+                max_file_size: 64 * 1024 * 1024,
+                max_files: 4,
+            })
+        );
+
+        let args = TxWalArgs { max_files: 0, ..args };
+        assert!(args.validate().is_err());
+    }
+
+    #[test]
+    fn test_telemetry_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.telemetry.config().is_none());
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.telemetry",
+            "--xlayer.telemetry.url",
+            "https://telemetry.example.com/report",
+        ])
+        .args;
+        assert!(args.validate().is_ok());
+        let config = args.telemetry.config().unwrap();
+        assert_eq!(config.url, "https://telemetry.example.com/report");
+        assert_eq!(config.interval, Duration::from_secs(300));
+
+        // Opting in requires an endpoint
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth", "--xlayer.telemetry"]).args;
+        assert!(args.validate().is_err());
+
+        // An endpoint alone doesn't opt in
+        let res = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--xlayer.telemetry.url",
+            "https://telemetry.example.com/report",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_preconf_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(!args.preconf.relay);
+
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth", "--xlayer.preconf.relay"]).args;
+        assert!(args.preconf.relay);
+
+        // Replicas never hold the sequencer's preconfirmation key
+        let res = CommandParser::<XLayerArgs>::try_parse_from([
+            "reth",
+            "--xlayer.preconf.secret-key",
+            "0x7a3233fcd52c19f9ffce062fd620a8888930b086fba48cfea8fc14aac98a4dce",
+        ]);
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_feature_flags() {
+        // Holds for every combination of the node features
+        for (flags, built) in [
+            (&["--rpc.legacy-url", "http://127.0.0.1:8545"][..], cfg!(feature = "legacy-rpc")),
+            (&["--xlayer.rpc.tx-timestamps"][..], cfg!(feature = "monitor")),
+            (&["--xlayer.flashblocks-subscription"][..], cfg!(feature = "flashblocks")),
+        ] {
+            let args =
+                CommandParser::<XLayerArgs>::parse_from(std::iter::once(&"reth").chain(flags)).args;
+            assert_eq!(args.validate_features().is_ok(), built, "{flags:?}");
+        }
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.validate_features().is_ok());
+    }
+
+    #[test]
+    #[cfg(feature = "block-publisher")]
+    fn test_block_publisher_args() {
+        let args = CommandParser::<XLayerArgs>::parse_from(["reth"]).args;
+        assert!(args.validate().is_ok());
+        assert!(args.block_publisher.publisher().is_none());
+
+        let args = CommandParser::<XLayerArgs>::parse_from([
+            "reth",
+            "--xlayer.block-publisher.nats-url",
+            "nats://127.0.0.1:4222",
+            "--xlayer.block-publisher.subject-prefix",
+            "xlayer.mainnet",
+        ])
+        .args;
+        assert!(args.validate().is_ok());
+        assert!(args.block_publisher.publisher().is_some());
+
+        for (url, prefix) in [
+            ("http://127.0.0.1:4222", "xlayer"),
+            ("nats://127.0.0.1:4222", ""),
+            ("nats://127.0.0.1:4222", "xlayer.*"),
+            ("nats://127.0.0.1:4222", "xlayer..blocks"),
+        ] {
+            let args = CommandParser::<XLayerArgs>::parse_from([
+                "reth",
+                "--xlayer.block-publisher.nats-url",
+                url,
+                "--xlayer.block-publisher.subject-prefix",
+                prefix,
+            ])
+            .args;
+            assert!(args.validate().is_err(), "{url} {prefix}");
+        }
+    }
 }
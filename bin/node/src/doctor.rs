@@ -0,0 +1,253 @@
+//! `xlayer-reth-node doctor`: environment self-check before launch.
+//!
+//! Checks that the datadir was initialized with the genesis of the given chain, that the legacy
+//! RPC endpoint is reachable and serves the same chain, and that the flashblocks websocket source
+//! accepts connections. Checks whose configuration is not given are skipped.
+
+use std::{fmt, time::Duration};
+
+use alloy_consensus::BlockHeader;
+use clap::Parser;
+use eyre::{bail, eyre, WrapErr};
+use reth_chainspec::EthChainSpec;
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_commands::common::{AccessRights, CliNodeTypes, Environment, EnvironmentArgs};
+use reth_optimism_chainspec::OpChainSpec;
+use reth_optimism_node::OpNode;
+use reth_provider::BlockHashReader;
+use url::Url;
+use xlayer_chainspec::XLayerChainSpecParser;
+
+use crate::args::LegacyRpcArgs;
+
+/// Name of the subcommand.
+pub const DOCTOR_COMMAND: &str = "doctor";
+
+/// Timeout for the flashblocks websocket handshake.
+const FLASHBLOCKS_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Checks the node environment before launch
+#[derive(Debug, Parser)]
+#[command(name = DOCTOR_COMMAND)]
+pub struct DoctorCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    #[command(flatten)]
+    legacy: LegacyRpcArgs,
+
+    /// Flashblocks websocket source
+    #[arg(long = "flashblocks-url", value_name = "FLASHBLOCKS_URL")]
+    flashblocks_url: Option<Url>,
+}
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CheckStatus {
+    Pass,
+    Fail,
+    Skip,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Pass => "PASS",
+            Self::Fail => "FAIL",
+            Self::Skip => "SKIP",
+        })
+    }
+}
+
+/// Result of a single check.
+#[derive(Debug)]
+struct CheckResult {
+    name: &'static str,
+    status: CheckStatus,
+    detail: String,
+}
+
+impl CheckResult {
+    fn from_result(name: &'static str, result: eyre::Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self { name, status: CheckStatus::Pass, detail },
+            Err(err) => Self { name, status: CheckStatus::Fail, detail: format!("{err:#}") },
+        }
+    }
+
+    fn skipped(name: &'static str, reason: &str) -> Self {
+        Self { name, status: CheckStatus::Skip, detail: reason.to_string() }
+    }
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> DoctorCommand<C> {
+    /// Runs all checks, prints the report and returns whether all checks passed.
+    pub async fn execute<N>(self) -> bool
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec>,
+    {
+        let chain = self.env.chain.clone();
+
+        let mut report = vec![CheckResult::from_result("genesis", self.check_genesis::<N>())];
+
+        report.push(match &self.legacy.legacy_rpc_url {
+            Some(url) => CheckResult::from_result(
+                "legacy-rpc",
+                check_legacy_rpc(url, self.legacy.legacy_rpc_timeout, &chain).await,
+            ),
+            None => CheckResult::skipped("legacy-rpc", "--rpc.legacy-url not set"),
+        });
+
+        report.push(match &self.flashblocks_url {
+            Some(url) => {
+                CheckResult::from_result("flashblocks-ws", check_flashblocks_ws(url).await)
+            }
+            None => CheckResult::skipped("flashblocks-ws", "--flashblocks-url not set"),
+        });
+
+        for check in &report {
+            println!("{:<4}  {:<14}  {}", check.status, check.name, check.detail);
+        }
+        report.iter().all(|check| check.status != CheckStatus::Fail)
+    }
+
+    /// Checks that the datadir holds the genesis block of the chain.
+    fn check_genesis<N>(&self) -> eyre::Result<String>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec>,
+    {
+        let Environment { provider_factory, data_dir, .. } =
+            self.env.init::<N>(AccessRights::RO).wrap_err("failed to open datadir")?;
+
+        let number = self.env.chain.genesis_header().number();
+        let expected = self.env.chain.genesis_hash();
+        match provider_factory.block_hash(number)? {
+            Some(hash) if hash == expected => {
+                Ok(format!("block {number} {hash} in {}", data_dir.data_dir().display()))
+            }
+            Some(hash) => {
+                bail!("genesis mismatch at block {number}: datadir {hash}, chain {expected}")
+            }
+            None => bail!("datadir has no block {number}, run `init` first"),
+        }
+    }
+}
+
+/// Checks that the legacy endpoint answers `eth_chainId` with the chain id of the chain.
+async fn check_legacy_rpc(
+    url: &str,
+    timeout: Duration,
+    chain: &OpChainSpec,
+) -> eyre::Result<String> {
+    let response: serde_json::Value = reqwest::Client::new()
+        .post(url)
+        .timeout(timeout)
+        .json(
+            &serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": []}),
+        )
+        .send()
+        .await
+        .wrap_err_with(|| format!("{url} unreachable"))?
+        .json()
+        .await
+        .wrap_err("invalid JSON-RPC response")?;
+
+    let chain_id = parse_chain_id(&response)?;
+    let expected = chain.chain().id();
+    if chain_id != expected {
+        bail!("{url} serves chain id {chain_id}, expected {expected}");
+    }
+    Ok(format!("{url} chain id {chain_id}"))
+}
+
+/// Parses the chain id out of an `eth_chainId` response.
+fn parse_chain_id(response: &serde_json::Value) -> eyre::Result<u64> {
+    if let Some(error) = response.get("error") {
+        bail!("eth_chainId failed: {error}");
+    }
+    let result = response
+        .get("result")
+        .and_then(|result| result.as_str())
+        .ok_or_else(|| eyre!("missing eth_chainId result"))?;
+    u64::from_str_radix(result.trim_start_matches("0x"), 16)
+        .wrap_err_with(|| format!("invalid chain id {result}"))
+}
+
+/// Checks that the flashblocks source accepts websocket connections.
+async fn check_flashblocks_ws(url: &Url) -> eyre::Result<String> {
+    let (mut stream, _) = tokio::time::timeout(
+        FLASHBLOCKS_CONNECT_TIMEOUT,
+        tokio_tungstenite::connect_async(url.as_str()),
+    )
+    .await
+    .map_err(|_| eyre!("{url} handshake timed out"))?
+    .wrap_err_with(|| format!("{url} unreachable"))?;
+    let _ = stream.close(None).await;
+    Ok(format!("{url} connected"))
+}
+
+/// Runs the `doctor` subcommand and returns the exit code.
+pub fn run(command: DoctorCommand<XLayerChainSpecParser>) -> i32 {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("Failed to start runtime: {err}");
+            return 1;
+        }
+    };
+    if runtime.block_on(command.execute::<OpNode>()) {
+        0
+    } else {
+        1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_doctor_args() {
+        let command = DoctorCommand::<XLayerChainSpecParser>::parse_from([
+            "doctor",
+            "--chain",
+            "xlayer-testnet",
+            "--rpc.legacy-url",
+            "http://localhost:8545",
+            "--flashblocks-url",
+            "ws://localhost:1111",
+        ]);
+        assert_eq!(command.legacy.legacy_rpc_url.as_deref(), Some("http://localhost:8545"));
+        assert_eq!(command.flashblocks_url, Some(Url::parse("ws://localhost:1111").unwrap()));
+    }
+
+    #[test]
+    fn test_doctor_node_subcommand() {
+        let cli = crate::NodeCli::try_parse_from([
+            "xlayer-reth-node",
+            "doctor",
+            "--chain",
+            "xlayer-testnet",
+            "--flashblocks-url",
+            "ws://localhost:1111",
+        ])
+        .unwrap();
+        assert!(
+            matches!(cli, crate::NodeCli::Doctor(command) if command.flashblocks_url.is_some())
+        );
+
+        let cli = crate::NodeCli::try_parse_from(["xlayer-reth-node", "node"]).unwrap();
+        assert!(matches!(cli, crate::NodeCli::Reth(_)));
+        assert!(crate::NodeCli::command().find_subcommand(DOCTOR_COMMAND).is_some());
+    }
+
+    #[test]
+    fn test_parse_chain_id() {
+        let response = serde_json::json!({"jsonrpc": "2.0", "id": 1, "result": "0xc4"});
+        assert_eq!(parse_chain_id(&response).unwrap(), 196);
+
+        let response = serde_json::json!({"jsonrpc": "2.0", "id": 1, "error": {"code": -32601}});
+        assert!(parse_chain_id(&response).is_err());
+        assert!(parse_chain_id(&serde_json::json!({"result": "mainnet"})).is_err());
+    }
+}
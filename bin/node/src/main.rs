@@ -1,33 +1,104 @@
 #![allow(missing_docs, rustdoc::missing_crate_level_docs)]
 
 mod args;
+mod config_info;
+mod doctor;
 mod payload;
+#[cfg(feature = "monitor")]
+mod telemetry;
+mod txpool;
 
 use payload::XLayerPayloadServiceBuilder;
+#[cfg(feature = "monitor")]
+use telemetry::NodeTelemetrySource;
+use txpool::XLayerPoolBuilder;
 
 use args::XLayerArgs;
-use clap::Parser;
+use clap::{Args as _, Command, CommandFactory, FromArgMatches};
+use config_info::{node_flags, EffectiveConfig, EffectiveConfigApiServer, EffectiveConfigRpc};
+use doctor::{DoctorCommand, DOCTOR_COMMAND};
 use either::Either;
 use std::sync::Arc;
 use tracing::info;
 
 use op_alloy_network::Optimism;
+#[cfg(feature = "flashblocks")]
 use reth::rpc::eth::EthApiTypes;
 use reth::{
     builder::{DebugNodeLauncher, EngineNodeLauncher, Node, NodeHandle, TreeConfig},
     providers::providers::BlockchainProvider,
 };
+use reth_chain_state::ForkChoiceSubscriptions;
 use reth_node_api::FullNodeComponents;
 use reth_optimism_cli::Cli;
 use reth_optimism_node::{args::RollupArgs, OpNode};
 use reth_rpc_server_types::RethRpcModule;
-
-use xlayer_chainspec::XLayerChainSpecParser;
+#[cfg(feature = "monitor")]
+use reth_transaction_pool::TransactionPool;
+#[cfg(not(all(feature = "legacy-rpc", feature = "monitor")))]
+use tower::layer::util::Identity;
+
+use xlayer_builder::flashblocks::{
+    build_stats::{payload_build_stats, PayloadBuildStatsApiServer, PayloadBuildStatsRpc},
+    config_info::{BuilderConfigApiServer, BuilderConfigInfo, BuilderConfigRpc},
+    handoff::{sequencer_handoff, SequencerHandoffApiServer, SequencerHandoffRpc},
+};
+use xlayer_chainspec::{apply_network_defaults, resolve_genesis_number, XLayerChainSpecParser};
+use xlayer_flashblocks::canon_mux::{CanonStateMux, CANON_STATE_MUX_CAPACITY};
+use xlayer_flashblocks::deposits::{DepositIndex, DepositsApiServer, DepositsRpc};
+#[cfg(feature = "flashblocks")]
 use xlayer_flashblocks::handler::FlashblocksService;
-use xlayer_flashblocks::subscription::FlashblocksPubSub;
-use xlayer_legacy_rpc::{layer::LegacyRpcRouterLayer, LegacyRpcRouterConfig};
-use xlayer_monitor::{start_monitor_handle, RpcMonitorLayer, XLayerMonitor};
-use xlayer_rpc::xlayer_ext::{XlayerRpcExt, XlayerRpcExtApiServer};
+use xlayer_flashblocks::l1_status::{L1StatusApiServer, L1StatusRpc};
+#[cfg(feature = "flashblocks")]
+use xlayer_flashblocks::preconf::{PreconfirmationApiServer, PreconfirmationRpc, Preconfirmations};
+#[cfg(feature = "flashblocks")]
+use xlayer_flashblocks::transactions::{FlashblockTransactions, FlashblockTransactionsApiServer};
+#[cfg(feature = "flashblocks")]
+use xlayer_flashblocks::{
+    pending_txs::UpstreamPendingTxFeed, scopes::SubscriptionScopes, subscription::FlashblocksPubSub,
+};
+#[cfg(not(feature = "legacy-rpc"))]
+use xlayer_legacy_rpc::RecentBlocks;
+#[cfg(feature = "legacy-rpc")]
+use xlayer_legacy_rpc::{
+    admin::{LegacyRpcAdmin, LegacyRpcAdminApiServer},
+    layer::LegacyRpcRouterLayer,
+    LegacyRpcRouterConfig, LocalGenesis, ShadowCompareConfig, TxLookupConfig,
+};
+use xlayer_legacy_rpc::{
+    invalidation::InvalidationBus,
+    middleware::ClientAddrLayer,
+    pending::{PendingRpcProxyConfig, PendingRpcProxyLayer},
+};
+#[cfg(feature = "monitor")]
+use xlayer_monitor::{
+    init_tx_timestamps, start_monitor_handle, start_tx_timestamps_handle, RpcMonitorLayer,
+    SlowQueryApiServer, SlowQueryLog, SlowQueryRpc, TelemetryReporter, TxTimestampsApiServer,
+    TxTimestampsRpc, XLayerMonitor,
+};
+use xlayer_rpc::{
+    gas_oracle::GasOracleConfigInfo,
+    xlayer_ext::{XlayerHealthApiServer, XlayerRpcExt, XlayerRpcExtApiServer},
+    CacheWarmup, CallCacheControlLayer, EthCallCacheLayer, ExecutionWitnessApiServer,
+    ExecutionWitnessRpc, FeeParamsApiServer, FeeParamsFeed, FeeParamsRpc, FinalityTagLayer,
+    GasOracleConfigApiServer, GasOracleConfigRpc, HealthProbes, HeavyCallLimitLayer,
+    MethodTimeoutLayer, MethodTimeouts, ResponseFieldsLayer, RpcPolicy, RpcPolicyLayer,
+    SenderNonceApiServer, SenderNonceRpc, TxDedupLayer, TxWalLayer, XlayerBundleGas,
+    XlayerBundleGasApiServer, XlayerFeeApiServer, XlayerFees,
+};
+
+/// Subcommands opening a datadir, which default to the datadir of the XLayer network.
+const DATADIR_COMMANDS: &[&str] = &[
+    "init",
+    "init-state",
+    "import-op",
+    "import-receipts-op",
+    "db",
+    "stage",
+    "prune",
+    "re-execute",
+    DOCTOR_COMMAND,
+];
 
 #[global_allocator]
 static ALLOC: reth_cli_util::allocator::Allocator = reth_cli_util::allocator::new_allocator();
@@ -43,6 +114,44 @@ struct Args {
     pub xlayer_args: XLayerArgs,
 }
 
+/// Command line of the node: the reth CLI extended with the X Layer subcommands.
+#[derive(Debug)]
+enum NodeCli {
+    Reth(Box<Cli<XLayerChainSpecParser, Args>>),
+    Doctor(DoctorCommand<XLayerChainSpecParser>),
+}
+
+impl NodeCli {
+    /// Returns the reth command extended with the X Layer subcommands.
+    fn command() -> Command {
+        Cli::<XLayerChainSpecParser, Args>::command()
+            .subcommand(DoctorCommand::<XLayerChainSpecParser>::command())
+    }
+
+    /// Parses the command line, exiting with the usage on errors.
+    fn parse_from<I, T>(args: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        Self::try_parse_from(args).unwrap_or_else(|err| err.exit())
+    }
+
+    fn try_parse_from<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = Self::command().try_get_matches_from(args)?;
+        match matches.subcommand() {
+            Some((DOCTOR_COMMAND, doctor)) => {
+                DoctorCommand::from_arg_matches(doctor).map(Self::Doctor)
+            }
+            _ => Cli::from_arg_matches(&matches).map(|cli| Self::Reth(Box::new(cli))),
+        }
+    }
+}
+
 fn main() {
     xlayer_version::init_version!();
 
@@ -57,7 +166,12 @@ fn main() {
 
     XLayerArgs::validate_init_command();
 
-    Cli::<XLayerChainSpecParser, Args>::parse()
+    let cli_args = apply_network_defaults(std::env::args_os(), DATADIR_COMMANDS);
+    let cli = match NodeCli::parse_from(cli_args) {
+        NodeCli::Reth(cli) => *cli,
+        NodeCli::Doctor(command) => std::process::exit(doctor::run(command)),
+    };
+    cli
         .run(|builder, args| async move {
             info!(message = "starting custom X Layer node");
 
@@ -67,53 +181,232 @@ fn main() {
                 std::process::exit(1);
             }
 
+            // Validate builder and flashblocks configuration, reporting every issue at once
+            if let Err(e) =
+                args.xlayer_args.builder.validate(args.rollup_args.flashblocks_url.as_ref())
+            {
+                eprintln!("X Layer configuration error: {e}");
+                std::process::exit(1);
+            }
+
             // Initialize global tracer if full link monitor is enabled
+            #[cfg(feature = "monitor")]
             if args.xlayer_args.monitor.enable {
                 use std::path::PathBuf;
                 use xlayer_trace_monitor::init_global_tracer;
                 let output_path = PathBuf::from(&args.xlayer_args.monitor.output_path);
                 init_global_tracer(true, Some(output_path));
                 info!(target: "xlayer::monitor", "Global tracer initialized with output path: {}", args.xlayer_args.monitor.output_path);
+                xlayer_monitor::init_trace_queue(
+                    args.xlayer_args.monitor.queue_capacity,
+                    args.xlayer_args.monitor.overflow_policy,
+                );
+
+                // Track RPC acceptance to flashblock inclusion latency on the sequencer
+                if args.xlayer_args.sequencer_mode {
+                    xlayer_monitor::init_ingress_tracker();
+                }
             }
 
+            // Configuration of the gas price oracle behind the eth API, set by the `--gpo.*` flags
+            let gas_oracle_config = builder.config().rpc.gas_price_oracle.gas_price_oracle_config();
+            let gas_oracle_config_rpc = GasOracleConfigRpc::new(&gas_oracle_config);
+
             let op_node = OpNode::new(args.rollup_args.clone());
 
-            let genesis_block = builder.config().chain.genesis().number.unwrap_or_default();
+            let genesis_block = resolve_genesis_number(builder.config().chain.genesis())?;
             info!("X Layer genesis block = {}", genesis_block);
+            #[cfg(feature = "legacy-rpc")]
+            let local_genesis = LocalGenesis {
+                number: genesis_block,
+                parent_hash: builder.config().chain.genesis_header().parent_hash,
+            };
 
             // Clone xlayer_args early to avoid partial move issues
             let xlayer_args = args.xlayer_args.clone();
+            #[cfg(feature = "flashblocks")]
             let datadir = builder.config().datadir().clone();
 
+            #[cfg(feature = "legacy-rpc")]
             let legacy_config = LegacyRpcRouterConfig {
                 enabled: xlayer_args.legacy.legacy_rpc_url.is_some(),
                 legacy_endpoint: xlayer_args.legacy.legacy_rpc_url.unwrap_or_default(),
                 cutoff_block: genesis_block,
+                strategy: xlayer_args.legacy.legacy_routing_strategy,
                 timeout: xlayer_args.legacy.legacy_rpc_timeout,
+                receipt_profile: xlayer_args.legacy.legacy_receipt_profile,
+                shadow: ShadowCompareConfig {
+                    window: xlayer_args.legacy.legacy_shadow_window,
+                    sample: xlayer_args.legacy.legacy_shadow_sample,
+                },
+                skip_new_contracts: xlayer_args.legacy.legacy_skip_new_contracts,
+                method_names: xlayer_args.legacy.legacy_method_names.into_iter().collect(),
+                forward_methods: xlayer_args.legacy.legacy_forward_methods,
+                tx_lookup: TxLookupConfig {
+                    index_window: xlayer_args.legacy.legacy_tx_index_window,
+                    scan_blocks: xlayer_args.legacy.legacy_tx_scan_blocks,
+                },
+                lookup_order: xlayer_args.legacy.legacy_lookup_order.into_iter().collect(),
+                estimate_gas_margin: xlayer_args.legacy.legacy_estimate_gas_margin,
+            };
+
+            // Proxy pending tag reads only on replicas without local flashblocks
+            let pending_config = match xlayer_args.pending.pending_rpc_url.clone() {
+                Some(endpoint)
+                    if xlayer_args.sequencer_mode || args.rollup_args.flashblocks_url.is_some() =>
+                {
+                    tracing::warn!(%endpoint, "Ignoring pending RPC proxy, node serves pending data locally");
+                    None
+                }
+                Some(endpoint) => Some(PendingRpcProxyConfig {
+                    endpoint,
+                    timeout: xlayer_args.pending.pending_rpc_timeout,
+                }),
+                None => None,
             };
 
             // For X Layer full link monitor
-            let monitor = XLayerMonitor::new(
-                xlayer_args.monitor,
-                xlayer_args.builder.flashblocks.enabled,
-                xlayer_args.sequencer_mode,
-            );
+            #[cfg(feature = "monitor")]
+            let (monitor, monitor_layer, slow_queries, tx_timestamps, telemetry_reporter) = {
+                let monitor = XLayerMonitor::new(
+                    xlayer_args.monitor,
+                    xlayer_args.builder.flashblocks.enabled,
+                    xlayer_args.sequencer_mode,
+                );
+
+                let slow_queries = SlowQueryLog::new(
+                    xlayer_args.slow_query.threshold,
+                    xlayer_args.slow_query.capacity,
+                );
+                let mut monitor_layer =
+                    RpcMonitorLayer::new(monitor.clone()).with_slow_queries(slow_queries.clone());
+
+                // Opt-in transaction timestamps, recorded at the RPC ingress
+                let tx_timestamps = xlayer_args
+                    .tx_timestamps
+                    .enable
+                    .then(|| init_tx_timestamps(xlayer_args.tx_timestamps.retention));
+                if let Some(tx_timestamps) = tx_timestamps {
+                    monitor_layer = monitor_layer.with_tx_timestamps(tx_timestamps);
+                }
+
+                // Opt-in telemetry, counting the routes of RPC calls for the legacy routing share
+                let telemetry_reporter = xlayer_args.telemetry.config().map(|config| {
+                    TelemetryReporter::new(
+                        config,
+                        builder.config().chain.chain.id(),
+                        xlayer_args.sequencer_mode,
+                    )
+                });
+                if let Some(reporter) = &telemetry_reporter {
+                    monitor_layer = monitor_layer.with_route_counters(reporter.route_counters());
+                }
+                (monitor, monitor_layer, slow_queries, tx_timestamps, telemetry_reporter)
+            };
+            #[cfg(not(feature = "monitor"))]
+            let monitor_layer = Identity::new();
+
+            let rpc_policy = RpcPolicy::new(xlayer_args.rpc_policy.disabled_methods());
+
+            let heavy_limit_layer = HeavyCallLimitLayer::new(xlayer_args.heavy_limit.limits());
+            let tx_dedup_layer = TxDedupLayer::new(xlayer_args.tx_dedup_window);
+            // Only replicas forward submitted transactions to the sequencer
+            let tx_wal_config = match xlayer_args.tx_wal.config() {
+                Some(config) if xlayer_args.sequencer_mode => {
+                    tracing::warn!(dir = %config.dir.display(), "Ignoring transaction log, sequencer doesn't forward transactions");
+                    None
+                }
+                config => config,
+            };
+            let tx_wal_layer = TxWalLayer::new(tx_wal_config)?;
+            let call_cache_layer = EthCallCacheLayer::new(xlayer_args.call_cache_size);
+            let call_cache = call_cache_layer.cache();
+            let finality_tag_layer = FinalityTagLayer::new(xlayer_args.finality_tags.policy());
+            let finality_tags = finality_tag_layer.tags();
+            let pending_layer = PendingRpcProxyLayer::new(pending_config);
+            #[cfg(feature = "legacy-rpc")]
+            let (
+                legacy_layer,
+                legacy_config_handle,
+                recent_blocks,
+                legacy_handoff,
+                legacy_handoff_check,
+            ) = {
+                let legacy_layer = LegacyRpcRouterLayer::new(legacy_config);
+                let legacy_config_handle = legacy_layer.config_handle();
+                let recent_blocks = legacy_layer.recent_blocks();
+                // Legacy routing is suspended if the legacy node doesn't end at the local genesis,
+                // the status reports whether routing is enabled too
+                let legacy_handoff = Some(legacy_layer.handoff());
+                let legacy_handoff_check = legacy_layer.check_handoff(local_genesis);
+                (
+                    legacy_layer,
+                    legacy_config_handle,
+                    recent_blocks,
+                    legacy_handoff,
+                    legacy_handoff_check,
+                )
+            };
+            // Without legacy routing the recent block index only serves the block hash lookups
+            #[cfg(not(feature = "legacy-rpc"))]
+            let (legacy_layer, recent_blocks, legacy_handoff) =
+                (Identity::new(), Arc::new(RecentBlocks::default()), None);
+            let timeout_layer =
+                MethodTimeoutLayer::new(MethodTimeouts::new(xlayer_args.method_timeouts));
+            let response_fields_layer = ResponseFieldsLayer::new(xlayer_args.response_fields);
 
             let add_ons = op_node.add_ons().with_rpc_middleware((
-                RpcMonitorLayer::new(monitor.clone()),    // Execute first
-                LegacyRpcRouterLayer::new(legacy_config), // Execute second
+                response_fields_layer,                 // Execute first, wrapped calls pass all other layers
+                RpcPolicyLayer::new(rpc_policy),       // Execute second
+                heavy_limit_layer,                     // Execute third
+                tx_dedup_layer,                        // Execute fourth
+                tx_wal_layer,                          // Execute fifth, logs submissions not deduplicated
+                monitor_layer,                         // Execute sixth
+                call_cache_layer,                      // Execute seventh
+                finality_tag_layer,                    // Execute eighth, maps call cache lookups too
+                pending_layer,                         // Execute ninth
+                legacy_layer,                          // Execute tenth
+                timeout_layer,                         // Execute eleventh, bounds local calls only
             ));
+            // Attach the client address to the requests, limits apply per address, and mark the
+            // requests opting out of the call cache
+            let add_ons = add_ons.with_http_middleware(
+                tower::ServiceBuilder::new()
+                    .layer(ClientAddrLayer::new(xlayer_args.heavy_limit.trust_forwarded_for))
+                    .layer(CallCacheControlLayer),
+            );
+
+            // Share one canonical state subscription between the node components and the RPC
+            // modules, spawned by the first of them to start
+            let canon_state = CanonStateMux::new(CANON_STATE_MUX_CAPACITY);
 
             // Create the X Layer payload service builder
             // It handles both flashblocks and default modes internally
             let payload_builder = XLayerPayloadServiceBuilder::new(
                 args.xlayer_args.builder.clone(),
                 args.rollup_args.compute_pending_block,
+                canon_state.clone(),
             )?;
+            let builder_config_rpc = payload_builder.builder_config().map(BuilderConfigRpc::new);
+            let effective_config_rpc = EffectiveConfigRpc::new(EffectiveConfig {
+                flags: node_flags(
+                    Cli::<XLayerChainSpecParser, Args>::command(),
+                    &Args::augment_args(Command::new("node")),
+                ),
+                builder: payload_builder.builder_config().map(BuilderConfigInfo::from),
+                gas_oracle: GasOracleConfigInfo::from(&gas_oracle_config),
+            });
+
+            // Create the transaction pool builder, enforcing the priority fee floor
+            let pool_builder = XLayerPoolBuilder {
+                min_priority_fee: args.xlayer_args.txpool_min_priority_fee,
+                enable_tx_conditional: args.rollup_args.enable_tx_conditional,
+                canon_state: canon_state.clone(),
+            };
 
             let NodeHandle { node, node_exit_future } = builder
                 .with_types_and_provider::<OpNode, BlockchainProvider<_>>()
-                .with_components(op_node.components().payload(payload_builder))
+                .with_components(op_node.components().pool(pool_builder).payload(payload_builder))
                 .with_add_ons(add_ons)
                 .on_component_initialized(move |_ctx| {
                     // TODO: Initialize X Layer components here
@@ -121,9 +414,83 @@ fn main() {
                 })
                 .extend_rpc_modules(move |ctx| {
                     let new_op_eth_api = Arc::new(ctx.registry.eth_api().clone());
+                    let flashblocks_sequencer = args.xlayer_args.builder.flashblocks.enabled;
+
+                    #[cfg(feature = "legacy-rpc")]
+                    ctx.node().task_executor().spawn(legacy_handoff_check);
+
+                    canon_state.spawn(ctx.provider(), ctx.node().task_executor());
+
+                    // Invalidate the caches derived from recent blocks as the chain changes
+                    let fees = XlayerFees::new(new_op_eth_api.clone());
+                    let mut invalidation = InvalidationBus::new()
+                        .with_handler("recent_blocks", recent_blocks.clone())
+                        .with_handler("priority_fee", Arc::new(fees.clone()));
+                    if let Some(call_cache) = call_cache {
+                        call_cache.set_provider(ctx.provider().clone());
+                        invalidation = invalidation.with_handler("call_cache", call_cache);
+                    }
+                    ctx.node().task_executor().spawn(invalidation.run(
+                        canon_state.subscribe("invalidation").into_stream(),
+                        ctx.provider().finalized_block_stream(),
+                    ));
+
+                    // Stream the fee parameters as they change with new blocks
+                    let fee_params = Arc::new(FeeParamsFeed::new(
+                        new_op_eth_api.clone(),
+                        args.xlayer_args.txpool_min_priority_fee,
+                    ));
+                    ctx.modules
+                        .merge_configured(FeeParamsRpc::new(fee_params.subscribe()).into_rpc())?;
+                    ctx.node().task_executor().spawn(
+                        fee_params.clone().run(canon_state.subscribe("fee_params").into_stream()),
+                    );
+
+                    // Index the latest deposits for the bridge deposit lookups
+                    let deposits = Arc::new(DepositIndex::default());
+                    ctx.node()
+                        .task_executor()
+                        .spawn(deposits.clone().run(canon_state.subscribe("deposits")));
+                    ctx.modules.merge_configured(
+                        DepositsRpc::new(ctx.provider().clone(), deposits).into_rpc(),
+                    )?;
+
+                    // Record the sender and fee of accepted transactions as recovered by txpool
+                    // validation, instead of recovering them again at the RPC ingress
+                    #[cfg(feature = "monitor")]
+                    if let Some(tracker) = xlayer_monitor::ingress_tracker() {
+                        let mut validated = ctx.pool().new_transactions_listener();
+                        ctx.node().task_executor().spawn(async move {
+                            while let Some(event) = validated.recv().await {
+                                let tx = &event.transaction;
+                                tracker.on_validated(*tx.hash(), tx.sender(), tx.max_fee_per_gas());
+                            }
+                        });
+                    }
+
+                    // Warm the caches from the latest blocks, the node isn't ready until done
+                    let warmup = (xlayer_args.cache_warmup_blocks > 0).then(|| {
+                        let warmup = Arc::new(CacheWarmup::new(xlayer_args.cache_warmup_blocks));
+                        ctx.node().task_executor().spawn(warmup.clone().run(
+                            ctx.provider().clone(),
+                            recent_blocks,
+                            new_op_eth_api.clone(),
+                            fee_params,
+                        ));
+                        info!(target: "reth::cli", blocks = xlayer_args.cache_warmup_blocks, "xlayer cache warmup started");
+                        warmup
+                    });
+
+                    #[cfg(feature = "block-publisher")]
+                    if let Some(publisher) = xlayer_args.block_publisher.publisher() {
+                        let consumer = canon_state.subscribe("block_publisher");
+                        ctx.node().task_executor().spawn(publisher.run(consumer));
+                        info!(target: "reth::cli", "xlayer canonical block publisher enabled");
+                    }
 
                     // Initialize flashblocks RPC service if not in flashblocks sequencer mode
-                    if !args.xlayer_args.builder.flashblocks.enabled {
+                    #[cfg(feature = "flashblocks")]
+                    if !flashblocks_sequencer {
                         if let Some(flashblock_rx) = new_op_eth_api.subscribe_received_flashblocks()
                         {
                             let service = FlashblocksService::new(
@@ -137,18 +504,76 @@ fn main() {
                             info!(target: "reth::cli", "xlayer flashblocks service initialized");
                         }
 
+                        // Relay the preconfirmations the sequencer signs for its flashblocks
+                        let preconfs = match (
+                            xlayer_args.preconf.relay,
+                            args.rollup_args.flashblocks_url.clone(),
+                        ) {
+                            (true, Some(url)) => {
+                                let preconfs = Arc::new(Preconfirmations::default());
+                                ctx.node().task_executor().spawn(preconfs.clone().run(url));
+                                ctx.modules.merge_configured(
+                                    PreconfirmationRpc::new(preconfs.clone()).into_rpc(),
+                                )?;
+                                info!(target: "reth::cli", "xlayer flashblock preconfirmation relay enabled");
+                                Some(preconfs)
+                            }
+                            (true, None) => {
+                                tracing::warn!(
+                                    "Ignoring preconfirmation relay, node doesn't consume flashblocks"
+                                );
+                                None
+                            }
+                            (false, _) => None,
+                        };
+
+                        if xlayer_args.flashblock_tx_inclusion
+                            && let Some(pending_blocks_rx) = new_op_eth_api.pending_block_rx()
+                        {
+                            ctx.modules.add_or_replace_if_module_configured(
+                                RethRpcModule::Eth,
+                                FlashblockTransactions::new(
+                                    ctx.registry.eth_api().clone(),
+                                    pending_blocks_rx,
+                                )
+                                .into_rpc(),
+                            )?;
+                            info!(target: "reth::cli", "xlayer flashblock transaction lookups enabled");
+                        }
+
                         if xlayer_args.enable_flashblocks_subscription
                             && let Some(pending_blocks_rx) = new_op_eth_api.pending_block_rx()
                         {
                             let eth_pubsub = ctx.registry.eth_handlers().pubsub.clone();
 
-                            let flashblocks_pubsub = FlashblocksPubSub::new(
+                            let mut flashblocks_pubsub = FlashblocksPubSub::new(
                                 eth_pubsub,
+                                ctx.provider().clone(),
+                                canon_state,
                                 pending_blocks_rx,
                                 Box::new(ctx.node().task_executor().clone()),
                                 new_op_eth_api.converter().clone(),
                                 xlayer_args.flashblocks_subscription_max_addresses,
                             );
+                            if let Some(path) = &xlayer_args.flashblocks_subscription_keys {
+                                flashblocks_pubsub = flashblocks_pubsub
+                                    .with_scopes(SubscriptionScopes::load(path)?);
+                                info!(target: "reth::cli", path = %path.display(), "xlayer flashblocks subscriptions scoped by api key");
+                            }
+                            if let Some(url) = &xlayer_args.pending_tx_feed_url {
+                                let feed = UpstreamPendingTxFeed::new(url.clone());
+                                ctx.node().task_executor().spawn(feed.clone().run());
+                                flashblocks_pubsub = flashblocks_pubsub.with_pending_tx_feed(feed);
+                                info!(target: "reth::cli", %url, "xlayer upstream pending transaction feed enabled");
+                            }
+                            if let Some(preconfs) = preconfs {
+                                flashblocks_pubsub = flashblocks_pubsub.with_preconfirmations(preconfs);
+                            }
+                            let max_lag = xlayer_args.flashblocks_subscription_max_lag;
+                            if !max_lag.is_zero() {
+                                flashblocks_pubsub = flashblocks_pubsub.with_enrichment_max_lag(max_lag);
+                                info!(target: "reth::cli", max_lag = ?max_lag, "xlayer flashblocks enrichment load shedding enabled");
+                            }
                             ctx.modules.add_or_replace_if_module_configured(
                                 RethRpcModule::Eth,
                                 flashblocks_pubsub.into_rpc(),
@@ -157,13 +582,115 @@ fn main() {
                         }
                     }
 
+                    // Override fee suggestions to follow the sequencer
+                    ctx.modules.add_or_replace_if_module_configured(
+                        RethRpcModule::Eth,
+                        fees.into_rpc(),
+                    )?;
+
+                    // Serve the L1 data availability status
+                    ctx.modules
+                        .merge_configured(L1StatusRpc::new(ctx.provider().clone()).into_rpc())?;
+
+                    // Resolve transactions by sender and nonce
+                    ctx.modules.merge_configured(
+                        SenderNonceRpc::new(
+                            ctx.provider().clone(),
+                            ctx.pool().clone(),
+                            genesis_block,
+                        )
+                        .into_rpc(),
+                    )?;
+
+                    // Serve the upstream execution witnesses where the debug API is exposed
+                    ctx.modules.merge_if_module_configured(
+                        RethRpcModule::Debug,
+                        ExecutionWitnessRpc::new(ctx.registry.debug_api(), genesis_block)
+                            .into_rpc(),
+                    )?;
+
+                    // Estimate dependent transaction bundles sequentially
+                    ctx.modules.merge_configured(XlayerBundleGasApiServer::<Optimism>::into_rpc(
+                        XlayerBundleGas::new(new_op_eth_api.clone()),
+                    ))?;
+
                     // Register X Layer RPC
-                    let xlayer_rpc = XlayerRpcExt { backend: new_op_eth_api };
+                    let xlayer_rpc = XlayerRpcExt {
+                        backend: new_op_eth_api,
+                        finality_tags,
+                        legacy_handoff,
+                        warmup,
+                    };
                     ctx.modules.merge_configured(XlayerRpcExtApiServer::<Optimism>::into_rpc(
-                        xlayer_rpc,
+                        xlayer_rpc.clone(),
                     ))?;
+                    // Serve HTTP health probes outside of the JSON-RPC server and its middleware
+                    if let Some(addr) = xlayer_args.health_addr {
+                        // Replicas consuming flashblocks are only ready with a pending flashblock
+                        let require_flashblocks =
+                            !flashblocks_sequencer && args.rollup_args.flashblocks_url.is_some();
+                        let probes =
+                            HealthProbes::bind(addr, xlayer_rpc.clone(), require_flashblocks)?;
+                        ctx.node().task_executor().spawn(probes.serve());
+                    }
+                    ctx.modules.merge_configured(XlayerHealthApiServer::into_rpc(xlayer_rpc))?;
                     info!(target: "reth::cli", "xlayer rpc extension enabled");
 
+                    #[cfg(feature = "monitor")]
+                    if let Some(tx_timestamps) = tx_timestamps {
+                        ctx.modules
+                            .merge_configured(TxTimestampsRpc::new(tx_timestamps).into_rpc())?;
+                        info!(target: "reth::cli", "xlayer transaction timestamps enabled");
+                    }
+
+                    // Register payload build diagnostics on the flashblocks sequencer
+                    if flashblocks_sequencer {
+                        ctx.modules.merge_configured(
+                            PayloadBuildStatsRpc::new(payload_build_stats()).into_rpc(),
+                        )?;
+
+                        // Register the sequencer failover API, allows pausing flashblocks
+                        ctx.modules.merge_if_module_configured(
+                            RethRpcModule::Admin,
+                            SequencerHandoffRpc::new(
+                                ctx.provider().clone(),
+                                ctx.pool().clone(),
+                                sequencer_handoff(),
+                            )
+                            .into_rpc(),
+                        )?;
+                    }
+                    if let Some(builder_config_rpc) = builder_config_rpc {
+                        ctx.modules.merge_if_module_configured(
+                            RethRpcModule::Debug,
+                            builder_config_rpc.into_rpc(),
+                        )?;
+                    }
+                    ctx.modules.merge_if_module_configured(
+                        RethRpcModule::Debug,
+                        gas_oracle_config_rpc.into_rpc(),
+                    )?;
+                    ctx.modules.merge_if_module_configured(
+                        RethRpcModule::Debug,
+                        effective_config_rpc.into_rpc(),
+                    )?;
+
+                    // Register legacy RPC router admin API, allows reloading the routing config
+                    #[cfg(feature = "legacy-rpc")]
+                    ctx.modules.merge_if_module_configured(
+                        RethRpcModule::Admin,
+                        LegacyRpcAdmin::new(legacy_config_handle).into_rpc(),
+                    )?;
+
+                    // Register the slow query log admin API
+                    #[cfg(feature = "monitor")]
+                    if slow_queries.is_enabled() {
+                        ctx.modules.merge_if_module_configured(
+                            RethRpcModule::Admin,
+                            SlowQueryRpc::new(slow_queries).into_rpc(),
+                        )?;
+                    }
+
                     info!(message = "X Layer RPC modules initialized");
                     Ok(())
                 })
@@ -197,6 +724,7 @@ fn main() {
                 .await?;
 
             // Start X Layer full link monitor handle
+            #[cfg(feature = "monitor")]
             start_monitor_handle(
                 node.tasks(),
                 monitor.clone(),
@@ -205,6 +733,24 @@ fn main() {
                 node.add_ons_handle.engine_events.new_listener(),
             );
 
+            #[cfg(feature = "monitor")]
+            if let Some(tx_timestamps) = tx_timestamps {
+                start_tx_timestamps_handle(
+                    node.tasks(),
+                    tx_timestamps,
+                    node.add_ons_handle.engine_events.new_listener(),
+                );
+            }
+
+            #[cfg(feature = "monitor")]
+            if let Some(reporter) = telemetry_reporter {
+                let source = NodeTelemetrySource::new(
+                    node.provider().clone(),
+                    Arc::new(node.add_ons_handle.eth_api().clone()),
+                );
+                node.task_executor.spawn(reporter.run(source));
+            }
+
             node_exit_future.await
         })
         .unwrap();
@@ -4,11 +4,13 @@ use reth_node_builder::{components::BasicPayloadServiceBuilder, BuilderContext};
 use reth_optimism_evm::OpEvmConfig;
 use reth_optimism_node::node::OpPayloadBuilder;
 use reth_optimism_payload_builder::config::{OpDAConfig, OpGasLimitConfig};
+use reth_optimism_primitives::OpPrimitives;
 use xlayer_builder::{
     args::BuilderArgs,
     flashblocks::{BuilderConfig, FlashblocksServiceBuilder},
     traits::{NodeBounds, PoolBounds},
 };
+use xlayer_flashblocks::canon_mux::CanonStateMux;
 
 /// Payload builder strategy for X Layer.
 enum XLayerPayloadServiceBuilderInner {
@@ -28,12 +30,14 @@ impl XLayerPayloadServiceBuilder {
     pub fn new(
         xlayer_builder_args: BuilderArgs,
         compute_pending_block: bool,
+        canon_state: CanonStateMux<OpPrimitives>,
     ) -> eyre::Result<Self> {
         Self::with_config(
             xlayer_builder_args,
             compute_pending_block,
             OpDAConfig::default(),
             OpGasLimitConfig::default(),
+            canon_state,
         )
     }
 
@@ -42,11 +46,13 @@ impl XLayerPayloadServiceBuilder {
         compute_pending_block: bool,
         da_config: OpDAConfig,
         gas_limit_config: OpGasLimitConfig,
+        canon_state: CanonStateMux<OpPrimitives>,
     ) -> eyre::Result<Self> {
         let builder = if xlayer_builder_args.flashblocks.enabled {
             let builder_config = BuilderConfig::try_from(xlayer_builder_args)?;
             XLayerPayloadServiceBuilderInner::Flashblocks(Box::new(FlashblocksServiceBuilder(
                 builder_config,
+                canon_state,
             )))
         } else {
             let payload_builder = OpPayloadBuilder::new(compute_pending_block)
@@ -59,6 +65,14 @@ impl XLayerPayloadServiceBuilder {
 
         Ok(Self { builder })
     }
+
+    /// Returns the configuration of the flashblocks builder, `None` on follower/RPC nodes.
+    pub fn builder_config(&self) -> Option<&BuilderConfig> {
+        match &self.builder {
+            XLayerPayloadServiceBuilderInner::Flashblocks(builder) => Some(&builder.0),
+            XLayerPayloadServiceBuilderInner::Default(_) => None,
+        }
+    }
 }
 
 impl<Node, Pool> PayloadServiceBuilder<Node, Pool, OpEvmConfig> for XLayerPayloadServiceBuilder
@@ -0,0 +1,37 @@
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reth_provider::BlockNumReader;
+use xlayer_monitor::TelemetrySource;
+use xlayer_rpc::PendingFlashBlockProvider;
+
+/// Telemetry statistics of the running node.
+pub(crate) struct NodeTelemetrySource<P, E> {
+    provider: P,
+    eth_api: Arc<E>,
+}
+
+impl<P, E> NodeTelemetrySource<P, E> {
+    pub(crate) fn new(provider: P, eth_api: Arc<E>) -> Self {
+        Self { provider, eth_api }
+    }
+}
+
+impl<P, E> TelemetrySource for NodeTelemetrySource<P, E>
+where
+    P: BlockNumReader + Send + Sync,
+    E: PendingFlashBlockProvider + Send + Sync,
+{
+    fn head_number(&self) -> Option<u64> {
+        self.provider.best_block_number().ok()
+    }
+
+    /// Block timestamps have a resolution of one second, so does the lag.
+    fn flashblock_lag(&self) -> Option<Duration> {
+        let timestamp = self.eth_api.pending_flashblock_timestamp()?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?;
+        Some(now.saturating_sub(Duration::from_secs(timestamp)))
+    }
+}
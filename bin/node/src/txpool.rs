@@ -0,0 +1,300 @@
+//! Transaction pool with the X Layer priority fee floor.
+//!
+//! The `--txpool.minimum-priority-fee` check of reth exempts local transactions, i.e. everything
+//! submitted over RPC, which is where zero tip spam reaches the sequencer. The floor is enforced
+//! by wrapping the OP transaction validator, for transactions of any origin, and rejected
+//! transactions get the error message of the legacy sequencer.
+
+use std::{
+    any::Any,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use alloy_consensus::BlockHeader as _;
+use reth::{primitives::SealedBlock, providers::BlockReaderIdExt};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_node_builder::{
+    components::{create_blob_store, PoolBuilder, TxPoolBuilder},
+    BuilderContext,
+};
+use reth_optimism_primitives::OpPrimitives;
+use reth_optimism_txpool::{
+    maintain::maintain_transaction_pool_conditional, OpPooledTransaction, OpTransactionValidator,
+};
+use reth_transaction_pool::{
+    blobstore::DiskFileBlobStore,
+    error::{InvalidPoolTransactionError, PoolTransactionError},
+    CoinbaseTipOrdering, Pool, PoolTransaction, TransactionOrigin, TransactionValidationOutcome,
+    TransactionValidationTaskExecutor, TransactionValidator,
+};
+use tracing::{debug, info};
+use xlayer_builder::traits::NodeBounds;
+use xlayer_flashblocks::canon_mux::CanonStateMux;
+
+/// Rejection message of the legacy sequencer for underpaying transactions.
+pub const PRIORITY_FEE_TOO_LOW_MESSAGE: &str = "gas price too low";
+
+/// Priority fee floor metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_txpool_fee_floor")]
+struct FeeFloorMetrics {
+    /// Transactions rejected for a priority fee cap below the floor
+    rejected_priority_fee: Counter,
+    /// Transactions rejected for a fee cap that can't pay the base fee and the floor
+    rejected_fee_cap: Counter,
+}
+
+/// Reason a transaction pays less than the priority fee floor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FeeFloorRejection {
+    /// The priority fee cap, or gas price of legacy transactions, is below the floor
+    PriorityFee,
+    /// The fee cap leaves less than the floor once the base fee is paid
+    FeeCap,
+}
+
+/// Checks the fees of a transaction against the floor at the given base fee.
+fn check_fee_floor(
+    priority_fee_cap: u128,
+    max_fee: u128,
+    base_fee: u128,
+    floor: u128,
+) -> Option<FeeFloorRejection> {
+    if priority_fee_cap < floor {
+        Some(FeeFloorRejection::PriorityFee)
+    } else if max_fee < base_fee.saturating_add(floor) {
+        Some(FeeFloorRejection::FeeCap)
+    } else {
+        None
+    }
+}
+
+/// Pool error of transactions paying less than the priority fee floor.
+#[derive(Debug)]
+pub struct PriorityFeeTooLow {
+    /// Minimum effective priority fee per gas
+    pub floor: u128,
+}
+
+impl fmt::Display for PriorityFeeTooLow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(PRIORITY_FEE_TOO_LOW_MESSAGE)
+    }
+}
+
+impl std::error::Error for PriorityFeeTooLow {}
+
+impl PoolTransactionError for PriorityFeeTooLow {
+    fn is_bad_transaction(&self) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Transaction validator rejecting transactions whose effective priority fee is below a floor.
+///
+/// The effective priority fee is taken at the base fee of the current head. A zero floor disables
+/// the check, every call then goes straight to the inner validator.
+#[derive(Debug)]
+pub struct PriorityFeeFloorValidator<V> {
+    inner: V,
+    floor: u128,
+    base_fee: Arc<AtomicU64>,
+    metrics: FeeFloorMetrics,
+}
+
+impl<V> PriorityFeeFloorValidator<V> {
+    pub fn new(inner: V, floor: u128) -> Self {
+        Self {
+            inner,
+            floor,
+            base_fee: Arc::new(AtomicU64::new(0)),
+            metrics: FeeFloorMetrics::default(),
+        }
+    }
+
+    /// Sets the base fee of the current head, until the next head block is seen.
+    pub fn with_base_fee(self, base_fee: u64) -> Self {
+        self.base_fee.store(base_fee, Ordering::Relaxed);
+        self
+    }
+
+    fn check<T: PoolTransaction>(&self, transaction: &T) -> Option<FeeFloorRejection> {
+        if self.floor == 0 {
+            return None;
+        }
+        let max_fee = transaction.max_fee_per_gas();
+        let priority_fee_cap = transaction.max_priority_fee_per_gas().unwrap_or(max_fee);
+        let base_fee = self.base_fee.load(Ordering::Relaxed) as u128;
+        let rejection = check_fee_floor(priority_fee_cap, max_fee, base_fee, self.floor)?;
+        match rejection {
+            FeeFloorRejection::PriorityFee => self.metrics.rejected_priority_fee.increment(1),
+            FeeFloorRejection::FeeCap => self.metrics.rejected_fee_cap.increment(1),
+        }
+        debug!(target: "xlayer::txpool", hash = %transaction.hash(), ?rejection, floor = self.floor, "Rejected transaction below the priority fee floor");
+        Some(rejection)
+    }
+}
+
+impl<V> TransactionValidator for PriorityFeeFloorValidator<V>
+where
+    V: TransactionValidator,
+{
+    type Transaction = V::Transaction;
+    type Block = V::Block;
+
+    async fn validate_transaction(
+        &self,
+        origin: TransactionOrigin,
+        transaction: Self::Transaction,
+    ) -> TransactionValidationOutcome<Self::Transaction> {
+        if self.check(&transaction).is_some() {
+            return TransactionValidationOutcome::Invalid(
+                transaction,
+                InvalidPoolTransactionError::other(PriorityFeeTooLow { floor: self.floor }),
+            );
+        }
+        self.inner.validate_transaction(origin, transaction).await
+    }
+
+    fn on_new_head_block(&self, new_tip_block: &SealedBlock<Self::Block>) {
+        if let Some(base_fee) = new_tip_block.header().base_fee_per_gas() {
+            self.base_fee.store(base_fee, Ordering::Relaxed);
+        }
+        self.inner.on_new_head_block(new_tip_block)
+    }
+}
+
+/// Builds the OP transaction pool with the priority fee floor.
+///
+/// Mirrors the pool of `OpPoolBuilder`, which has no hook for its validator, so the node keeps a
+/// single pool type whether or not a floor is set. Interop transactions are not supported, X Layer
+/// doesn't run a supervisor.
+#[derive(Debug, Clone)]
+pub struct XLayerPoolBuilder {
+    /// Minimum effective priority fee per gas, zero to accept any fee
+    pub min_priority_fee: u128,
+    /// Whether transaction conditionals are enabled, see `--rollup.enable-tx-conditional`
+    pub enable_tx_conditional: bool,
+    /// Canonical state notifications shared with the other node components
+    pub canon_state: CanonStateMux<OpPrimitives>,
+}
+
+/// X Layer transaction pool
+pub type XLayerTransactionPool<Client> = Pool<
+    TransactionValidationTaskExecutor<
+        PriorityFeeFloorValidator<OpTransactionValidator<Client, OpPooledTransaction>>,
+    >,
+    CoinbaseTipOrdering<OpPooledTransaction>,
+    DiskFileBlobStore,
+>;
+
+impl<Node> PoolBuilder<Node> for XLayerPoolBuilder
+where
+    Node: NodeBounds,
+{
+    type Pool = XLayerTransactionPool<Node::Provider>;
+
+    async fn build_pool(self, ctx: &BuilderContext<Node>) -> eyre::Result<Self::Pool> {
+        let blob_store = create_blob_store(ctx)?;
+        let floor = self.min_priority_fee;
+        // The floor applies at the base fee of the head from the first transaction on, not only
+        // once the next block is seen
+        let base_fee = ctx
+            .provider()
+            .latest_header()?
+            .and_then(|header| header.base_fee_per_gas())
+            .unwrap_or_default();
+        let validator = TransactionValidationTaskExecutor::eth_builder(ctx.provider().clone())
+            .no_eip4844()
+            .with_head_timestamp(ctx.head().timestamp)
+            .with_max_tx_input_bytes(ctx.config().txpool.max_tx_input_bytes)
+            .kzg_settings(ctx.kzg_settings()?)
+            .set_tx_fee_cap(ctx.config().rpc.rpc_tx_fee_cap)
+            .with_max_tx_gas_limit(ctx.config().txpool.max_tx_gas_limit)
+            .with_minimum_priority_fee(ctx.config().txpool.minimum_priority_fee)
+            .with_additional_tasks(ctx.config().txpool.additional_validation_tasks)
+            .build_with_tasks(ctx.task_executor().clone(), blob_store.clone())
+            .map(|validator| {
+                let validator = OpTransactionValidator::new(validator)
+                    .require_l1_data_gas_fee(!ctx.config().dev.dev);
+                PriorityFeeFloorValidator::new(validator, floor).with_base_fee(base_fee)
+            });
+
+        let transaction_pool = TxPoolBuilder::new(ctx)
+            .with_validator(validator)
+            .build_and_spawn_maintenance_task(blob_store, ctx.pool_config())?;
+        info!(target: "reth::cli", min_priority_fee = floor, base_fee, "Transaction pool initialized");
+
+        if self.enable_tx_conditional {
+            self.canon_state.spawn(ctx.provider(), ctx.task_executor());
+            let chain_events =
+                Box::pin(self.canon_state.subscribe("txpool_conditional").into_stream());
+            ctx.task_executor().spawn_critical(
+                "Op txpool conditional maintenance task",
+                Box::pin(maintain_transaction_pool_conditional(
+                    transaction_pool.clone(),
+                    chain_events,
+                )),
+            );
+            debug!(target: "reth::cli", "Spawned txpool conditional maintenance task");
+        }
+
+        Ok(transaction_pool)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fee_floor() {
+        let gwei = 1_000_000_000;
+        // 1559 transaction with a tip of 0.1 gwei over a base fee of 0.01 gwei
+        assert_eq!(check_fee_floor(gwei / 10, gwei, gwei / 100, gwei / 10), None);
+        // Zero tip
+        assert_eq!(
+            check_fee_floor(0, gwei, gwei / 100, gwei / 10),
+            Some(FeeFloorRejection::PriorityFee)
+        );
+        // Tip capped by the fee cap once the base fee is paid
+        assert_eq!(
+            check_fee_floor(gwei, gwei / 10, gwei / 100, gwei / 10),
+            Some(FeeFloorRejection::FeeCap)
+        );
+        // Legacy gas price, the tip is what remains above the base fee
+        assert_eq!(check_fee_floor(gwei, gwei, gwei / 2, gwei / 2), None);
+        assert_eq!(
+            check_fee_floor(gwei, gwei, gwei / 2 + 1, gwei / 2),
+            Some(FeeFloorRejection::FeeCap)
+        );
+    }
+
+    #[test]
+    fn test_seeded_base_fee() {
+        let gwei = 1_000_000_000;
+        let validator = PriorityFeeFloorValidator::new((), gwei / 10).with_base_fee(gwei);
+        assert_eq!(validator.base_fee.load(Ordering::Relaxed), gwei);
+        // A tip above the floor that can't be paid on top of the seeded base fee
+        let base_fee = validator.base_fee.load(Ordering::Relaxed) as u128;
+        assert_eq!(
+            check_fee_floor(gwei / 10, gwei, base_fee, validator.floor),
+            Some(FeeFloorRejection::FeeCap)
+        );
+    }
+
+    #[test]
+    fn test_rejection_message() {
+        let err = PriorityFeeTooLow { floor: 1 };
+        assert_eq!(err.to_string(), PRIORITY_FEE_TOO_LOW_MESSAGE);
+        assert!(!err.is_bad_transaction());
+    }
+}
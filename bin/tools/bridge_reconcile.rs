@@ -0,0 +1,419 @@
+//! Command that reconciles the deposits and withdrawals of a block range with bridge events.
+//!
+//! The bridge operations team checks that every bridge deposit reached L2 and every withdrawal
+//! initiated on L2 is known to the bridge. The command scans the range for user deposit
+//! transactions and the `MessagePassed` events of the `L2ToL1MessagePasser`, and compares them
+//! with the bridge contract events given as JSONL, one event per line:
+//!
+//! ```json
+//! {"kind": "deposit", "sourceHash": "0x...", "block": 42810000}
+//! {"kind": "withdrawal", "withdrawalHash": "0x..."}
+//! ```
+//!
+//! Deposits are identified by their source hash, withdrawals by their withdrawal hash. Bridge
+//! events with a `block` outside the range are ignored, events without one are expected in the
+//! range. The report lists the entries without a counterpart on the other side.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use alloy_consensus::TxReceipt;
+use alloy_primitives::{address, Address, Log, B256, U256};
+use alloy_sol_types::{sol, SolEvent};
+use clap::{Parser, ValueEnum};
+use eyre::{eyre, Result, WrapErr};
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_commands::common::{AccessRights, CliNodeTypes, Environment, EnvironmentArgs};
+use reth_node_core::version::version_metadata;
+use reth_optimism_chainspec::OpChainSpec;
+use reth_optimism_primitives::{OpPrimitives, OpTransactionSigned};
+use reth_provider::{BlockNumReader, ReceiptProvider, TransactionsProvider};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Predeploy emitting the withdrawals initiated on L2.
+const L2_TO_L1_MESSAGE_PASSER: Address = address!("0x4200000000000000000000000000000000000016");
+
+/// Sender of the L1 info deposit transactions, which aren't bridge deposits.
+const L1_INFO_DEPOSITOR: Address = address!("0xDeaDDEaDDeAdDeAdDEAdDEaddeAddEAdDEAd0001");
+
+sol! {
+    event MessagePassed(
+        uint256 indexed nonce,
+        address indexed sender,
+        address indexed target,
+        uint256 value,
+        uint256 gasLimit,
+        bytes data,
+        bytes32 withdrawalHash
+    );
+}
+
+/// Reconciles the deposits and withdrawals of a block range with bridge contract events.
+#[derive(Debug, Parser)]
+pub struct BridgeReconcileCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    /// First block of the range (inclusive)
+    #[arg(long, value_name = "BLOCK")]
+    from: u64,
+
+    /// Last block of the range (inclusive)
+    #[arg(long, value_name = "BLOCK")]
+    to: u64,
+
+    /// JSONL file of the bridge contract events to reconcile against
+    #[arg(long, value_name = "FILE")]
+    bridge_events: PathBuf,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value = "csv")]
+    format: ReportFormat,
+
+    /// The path to write the report, stdout if not set
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// Format of the reconciliation report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    Csv,
+    Json,
+}
+
+/// Kind of a bridged transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum TransferKind {
+    Deposit,
+    Withdrawal,
+}
+
+/// Event of the bridge contracts, read from the bridge events file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum BridgeEvent {
+    #[serde(rename_all = "camelCase")]
+    Deposit { source_hash: B256, block: Option<u64> },
+    #[serde(rename_all = "camelCase")]
+    Withdrawal { withdrawal_hash: B256, block: Option<u64> },
+}
+
+impl BridgeEvent {
+    /// Returns the kind and identifier of the transfer.
+    const fn key(&self) -> (TransferKind, B256) {
+        match self {
+            Self::Deposit { source_hash, .. } => (TransferKind::Deposit, *source_hash),
+            Self::Withdrawal { withdrawal_hash, .. } => {
+                (TransferKind::Withdrawal, *withdrawal_hash)
+            }
+        }
+    }
+
+    /// Returns the L2 block of the event, if known.
+    const fn block(&self) -> Option<u64> {
+        match self {
+            Self::Deposit { block, .. } | Self::Withdrawal { block, .. } => *block,
+        }
+    }
+}
+
+/// Deposit or withdrawal found in the scanned blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChainTransfer {
+    kind: TransferKind,
+    /// Source hash of a deposit, withdrawal hash of a withdrawal
+    id: B256,
+    block: u64,
+    tx_hash: B256,
+    /// Minted value of a deposit, withdrawn value of a withdrawal
+    value: U256,
+}
+
+/// Side a transfer is missing from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Unmatched {
+    /// Found on chain without a bridge event
+    MissingBridgeEvent,
+    /// Bridge event without a transfer on chain
+    MissingOnChain,
+}
+
+/// Entry of the reconciliation report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportEntry {
+    status: Unmatched,
+    kind: TransferKind,
+    id: B256,
+    block: Option<u64>,
+    tx_hash: Option<B256>,
+    value: Option<U256>,
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> BridgeReconcileCommand<C> {
+    /// Execute `bridge-reconcile` command
+    pub async fn execute<N>(self) -> Result<()>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec, Primitives = OpPrimitives>,
+    {
+        info!(target: "reth::cli", "{} ({}) starting", version_metadata().name_client, version_metadata().short_version);
+
+        if self.from > self.to {
+            return Err(eyre!("--from {} is after --to {}", self.from, self.to));
+        }
+        let events = read_bridge_events(&self.bridge_events)?;
+
+        let Environment { provider_factory, .. } = self.env.init::<N>(AccessRights::RO)?;
+        let provider = provider_factory.provider()?;
+        let last_block =
+            provider.last_block_number().wrap_err("Failed to get latest block number")?;
+        if self.to > last_block {
+            return Err(eyre!("--to {} is after the last block {last_block}", self.to));
+        }
+
+        let mut transfers = Vec::new();
+        for number in self.from..=self.to {
+            let transactions = provider
+                .transactions_by_block(number.into())?
+                .ok_or_else(|| eyre!("Transactions of block {number} not found"))?;
+            let receipts = provider
+                .receipts_by_block(number.into())?
+                .ok_or_else(|| eyre!("Receipts of block {number} not found"))?;
+            for (tx, receipt) in transactions.iter().zip(&receipts) {
+                transfers.extend(deposit(number, tx));
+                transfers.extend(
+                    receipt.logs().iter().filter_map(|log| withdrawal(number, tx.tx_hash(), log)),
+                );
+            }
+        }
+
+        let report = reconcile(transfers, events, self.from..=self.to);
+        info!(target: "xlayer::bridge_reconcile", from = self.from, to = self.to, unmatched = report.len(), "Reconciled bridge transfers");
+
+        match &self.output {
+            Some(path) => {
+                let file = File::create(path)
+                    .wrap_err_with(|| format!("Failed to create {}", path.display()))?;
+                write_report(io::BufWriter::new(file), &report, self.format)
+            }
+            None => write_report(io::stdout().lock(), &report, self.format),
+        }
+    }
+}
+
+/// Reads the bridge events file, skipping empty lines.
+fn read_bridge_events(path: &Path) -> Result<Vec<BridgeEvent>> {
+    let file = File::open(path).wrap_err_with(|| format!("Failed to open {}", path.display()))?;
+    let mut events = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        events.push(serde_json::from_str(&line).wrap_err_with(|| {
+            format!("Invalid bridge event on line {} of {}", index + 1, path.display())
+        })?);
+    }
+    Ok(events)
+}
+
+/// Returns the user deposit of a transaction, `None` for other transactions.
+fn deposit(block: u64, tx: &OpTransactionSigned) -> Option<ChainTransfer> {
+    let deposit = tx.as_deposit()?;
+    (deposit.from != L1_INFO_DEPOSITOR).then(|| ChainTransfer {
+        kind: TransferKind::Deposit,
+        id: deposit.source_hash,
+        block,
+        tx_hash: tx.tx_hash(),
+        value: U256::from(deposit.mint),
+    })
+}
+
+/// Returns the withdrawal initiated by a log, `None` for other logs.
+fn withdrawal(block: u64, tx_hash: B256, log: &Log) -> Option<ChainTransfer> {
+    if log.address != L2_TO_L1_MESSAGE_PASSER {
+        return None;
+    }
+    let event = MessagePassed::decode_log_data(&log.data).ok()?;
+    Some(ChainTransfer {
+        kind: TransferKind::Withdrawal,
+        id: event.withdrawalHash,
+        block,
+        tx_hash,
+        value: event.value,
+    })
+}
+
+/// Returns the transfers without a bridge event and the bridge events within `range` without a
+/// transfer, in block order.
+fn reconcile(
+    transfers: Vec<ChainTransfer>,
+    events: Vec<BridgeEvent>,
+    range: std::ops::RangeInclusive<u64>,
+) -> Vec<ReportEntry> {
+    let mut events: HashMap<_, _> = events
+        .into_iter()
+        .filter(|event| event.block().is_none_or(|block| range.contains(&block)))
+        .map(|event| (event.key(), event))
+        .collect();
+
+    let mut report = Vec::new();
+    for transfer in transfers {
+        if events.remove(&(transfer.kind, transfer.id)).is_none() {
+            report.push(ReportEntry {
+                status: Unmatched::MissingBridgeEvent,
+                kind: transfer.kind,
+                id: transfer.id,
+                block: Some(transfer.block),
+                tx_hash: Some(transfer.tx_hash),
+                value: Some(transfer.value),
+            });
+        }
+    }
+    let mut missing: Vec<_> = events.into_values().collect();
+    missing.sort_unstable_by_key(|event| (event.block(), event.key().1));
+    report.extend(missing.into_iter().map(|event| ReportEntry {
+        status: Unmatched::MissingOnChain,
+        kind: event.key().0,
+        id: event.key().1,
+        block: event.block(),
+        tx_hash: None,
+        value: None,
+    }));
+    report
+}
+
+/// Writes the report as CSV with a header line, or as a JSON array.
+fn write_report(
+    mut writer: impl Write,
+    report: &[ReportEntry],
+    format: ReportFormat,
+) -> Result<()> {
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, report)?;
+            writeln!(writer)?;
+        }
+        ReportFormat::Csv => {
+            writeln!(writer, "status,kind,id,block,tx_hash,value")?;
+            for entry in report {
+                let status = match entry.status {
+                    Unmatched::MissingBridgeEvent => "missing_bridge_event",
+                    Unmatched::MissingOnChain => "missing_on_chain",
+                };
+                let kind = match entry.kind {
+                    TransferKind::Deposit => "deposit",
+                    TransferKind::Withdrawal => "withdrawal",
+                };
+                writeln!(
+                    writer,
+                    "{status},{kind},{},{},{},{}",
+                    entry.id,
+                    entry.block.map(|block| block.to_string()).unwrap_or_default(),
+                    entry.tx_hash.map(|hash| hash.to_string()).unwrap_or_default(),
+                    entry.value.map(|value| value.to_string()).unwrap_or_default(),
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Bytes, LogData};
+
+    fn transfer(kind: TransferKind, id: u8, block: u64) -> ChainTransfer {
+        ChainTransfer {
+            kind,
+            id: B256::repeat_byte(id),
+            block,
+            tx_hash: B256::with_last_byte(id),
+            value: U256::from(id),
+        }
+    }
+
+    #[test]
+    fn test_withdrawal_from_message_passed() {
+        let event = MessagePassed {
+            nonce: U256::from(1),
+            sender: Address::with_last_byte(1),
+            target: Address::with_last_byte(2),
+            value: U256::from(100),
+            gasLimit: U256::from(21_000),
+            data: Bytes::new(),
+            withdrawalHash: B256::repeat_byte(7),
+        };
+        let log = Log { address: L2_TO_L1_MESSAGE_PASSER, data: event.encode_log_data() };
+
+        let passed = withdrawal(10, B256::with_last_byte(1), &log).unwrap();
+        assert_eq!(passed.kind, TransferKind::Withdrawal);
+        assert_eq!(passed.id, B256::repeat_byte(7));
+        assert_eq!(passed.value, U256::from(100));
+
+        let other = Log { address: Address::with_last_byte(1), ..log };
+        assert!(withdrawal(10, B256::ZERO, &other).is_none());
+        let empty = Log { address: L2_TO_L1_MESSAGE_PASSER, data: LogData::empty() };
+        assert!(withdrawal(10, B256::ZERO, &empty).is_none());
+    }
+
+    #[test]
+    fn test_reconcile() {
+        let transfers = vec![
+            transfer(TransferKind::Deposit, 1, 10),
+            transfer(TransferKind::Deposit, 2, 11),
+            transfer(TransferKind::Withdrawal, 3, 12),
+        ];
+        let events: Vec<BridgeEvent> = [
+            r#"{"kind": "deposit", "sourceHash": "0x0101010101010101010101010101010101010101010101010101010101010101"}"#,
+            r#"{"kind": "withdrawal", "withdrawalHash": "0x0303030303030303030303030303030303030303030303030303030303030303", "block": 12}"#,
+            r#"{"kind": "withdrawal", "withdrawalHash": "0x0404040404040404040404040404040404040404040404040404040404040404", "block": 13}"#,
+            r#"{"kind": "withdrawal", "withdrawalHash": "0x0505050505050505050505050505050505050505050505050505050505050505", "block": 99}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let report = reconcile(transfers, events, 10..=20);
+        assert_eq!(
+            report,
+            vec![
+                ReportEntry {
+                    status: Unmatched::MissingBridgeEvent,
+                    kind: TransferKind::Deposit,
+                    id: B256::repeat_byte(2),
+                    block: Some(11),
+                    tx_hash: Some(B256::with_last_byte(2)),
+                    value: Some(U256::from(2)),
+                },
+                ReportEntry {
+                    status: Unmatched::MissingOnChain,
+                    kind: TransferKind::Withdrawal,
+                    id: B256::repeat_byte(4),
+                    block: Some(13),
+                    tx_hash: None,
+                    value: None,
+                },
+            ]
+        );
+
+        let mut csv = Vec::new();
+        write_report(&mut csv, &report, ReportFormat::Csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "status,kind,id,block,tx_hash,value");
+        assert!(lines[1].starts_with("missing_bridge_event,deposit,0x0202"), "{}", lines[1]);
+        assert!(lines[2].ends_with(",13,,"), "{}", lines[2]);
+    }
+}
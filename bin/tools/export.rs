@@ -2,11 +2,16 @@
 //!
 //! This implementation:
 //! - Reads blocks from the database
+//! - Resolves the export range from block numbers, block hashes or timestamps
+//! - Starts at the genesis block of the chain spec, which is not 0 for chains initialized with
+//!   `legacyXLayerBlock`, and refuses ranges covering the synthetic blocks before it
 //! - Encodes blocks to RLP format
+//! - Optionally writes the receipts of the blocks to a second file, for `import --no-exec`
 //! - Writes to a file (supports gzip compression)
 //! - Handles interrupts gracefully (Ctrl+C)
 
-use alloy_consensus::BlockHeader;
+use alloy_consensus::{BlockHeader, TxReceipt};
+use alloy_primitives::B256;
 use alloy_rlp::Encodable;
 use clap::Parser;
 use eyre::{eyre, Result, WrapErr};
@@ -16,12 +21,13 @@ use reth_cli::chainspec::ChainSpecParser;
 use reth_cli_commands::common::{AccessRights, Environment, EnvironmentArgs};
 use reth_node_core::version::version_metadata;
 use reth_optimism_chainspec::OpChainSpec;
+use reth_optimism_primitives::{OpPrimitives, OpReceipt};
 use reth_provider::BlockNumReader;
-use reth_storage_api::BlockReader;
+use reth_storage_api::{BlockReader, HeaderProvider, ReceiptProvider};
 use std::{
     fs::File,
     io::Write,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -41,14 +47,47 @@ pub struct ExportCommand<C: ChainSpecParser> {
     #[arg(long = "exported-data", value_name = "EXPORTED_DATA", verbatim_doc_comment)]
     output_path: PathBuf,
 
-    /// The starting block number (inclusive).
-    #[arg(long, value_name = "START_BLOCK", default_value = "0")]
-    start_block: u64,
+    /// The path to write the receipts of the exported blocks, not exported if not set.
+    ///
+    /// One RLP list of receipts per block, as read by `import --no-exec --receipts-data`.
+    /// If the file ends with .gz, it will be gzip compressed.
+    #[arg(long = "receipts-data", value_name = "RECEIPTS_DATA", verbatim_doc_comment)]
+    receipts_path: Option<PathBuf>,
+
+    /// The starting block number (inclusive). Defaults to the genesis block of the chain spec.
+    #[arg(long, value_name = "START_BLOCK")]
+    start_block: Option<u64>,
 
     /// The ending block number (inclusive). If not specified, exports to the latest block.
     #[arg(long, value_name = "END_BLOCK")]
     end_block: Option<u64>,
 
+    /// The hash of the starting block (inclusive).
+    ///
+    /// Conflicts with --start-block and --start-timestamp.
+    #[arg(long, value_name = "START_HASH", conflicts_with_all = ["start_block", "start_timestamp"])]
+    start_hash: Option<B256>,
+
+    /// The hash of the ending block (inclusive).
+    ///
+    /// Conflicts with --end-block and --end-timestamp.
+    #[arg(long, value_name = "END_HASH", conflicts_with_all = ["end_block", "end_timestamp"])]
+    end_hash: Option<B256>,
+
+    /// Unix timestamp (seconds) of the start of the export range.
+    ///
+    /// The export starts at the first block whose timestamp is at or after this value.
+    /// Takes precedence over --start-block.
+    #[arg(long, value_name = "START_TIMESTAMP")]
+    start_timestamp: Option<u64>,
+
+    /// Unix timestamp (seconds) of the end of the export range.
+    ///
+    /// The export ends at the last block whose timestamp is at or before this value.
+    /// Conflicts with --end-block.
+    #[arg(long, value_name = "END_TIMESTAMP", conflicts_with = "end_block")]
+    end_timestamp: Option<u64>,
+
     /// Batch size for reading blocks from database.
     #[arg(long, value_name = "BATCH_SIZE", default_value = "100000")]
     batch_size: u64,
@@ -58,7 +97,10 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
     /// Execute `export` command
     pub async fn execute<N>(self) -> Result<()>
     where
-        N: reth_cli_commands::common::CliNodeTypes<ChainSpec = C::ChainSpec>,
+        N: reth_cli_commands::common::CliNodeTypes<
+            ChainSpec = C::ChainSpec,
+            Primitives = OpPrimitives,
+        >,
     {
         info!(target: "reth::cli", "{} ({}) starting", version_metadata().name_client, version_metadata().short_version);
         info!(target: "reth::cli", "Exporting blockchain to file: {}", self.output_path.display());
@@ -69,33 +111,26 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
         let provider = provider_factory.provider()?;
         let latest_block =
             provider.last_block_number().wrap_err("Failed to get latest block number")?;
-        let end_block = self.end_block.unwrap_or(latest_block);
-        if end_block > latest_block {
-            return Err(eyre!(
-                "End block ({}) is greater than latest block ({})",
-                end_block,
-                latest_block
-            ));
-        }
 
-        // Get the genesis block number from the chain spec and validate the start block
+        // Get the genesis block number from the chain spec
         let genesis_block_number = provider.chain_spec().genesis_header().number();
-        if self.start_block < genesis_block_number {
-            return Err(eyre!(
-                "Start block ({}) is less than genesis block ({})",
-                self.start_block,
-                genesis_block_number
-            ));
-        }
-        let start_block = self.start_block;
-
-        if start_block > end_block {
-            return Err(eyre!(
-                "Start block ({}) is greater than end block ({})",
-                start_block,
-                end_block
-            ));
-        }
+
+        let end_block = if let Some(hash) = self.end_hash {
+            resolve_block_hash(&provider, hash)?
+        } else if let Some(timestamp) = self.end_timestamp {
+            last_block_at_or_before(&provider, timestamp, genesis_block_number, latest_block)?
+        } else {
+            self.end_block.unwrap_or(latest_block)
+        };
+        let start_block = if let Some(hash) = self.start_hash {
+            resolve_block_hash(&provider, hash)?
+        } else if let Some(timestamp) = self.start_timestamp {
+            first_block_at_or_after(&provider, timestamp, genesis_block_number, latest_block)?
+        } else {
+            self.start_block.unwrap_or(genesis_block_number)
+        };
+
+        validate_export_range(start_block, end_block, genesis_block_number, latest_block)?;
 
         let total_blocks = end_block - start_block + 1;
         info!(
@@ -115,23 +150,11 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
         })
         .wrap_err("Failed to set interrupt handler")?;
 
-        // Open output file
-        let output_file = File::create(&self.output_path).wrap_err_with(|| {
-            format!("Failed to create output file: {}", self.output_path.display())
-        })?;
-
-        // Determine if we should use gzip compression
-        let use_compression = self.output_path.extension().and_then(|s| s.to_str()) == Some("gz");
-
-        let mut writer: Box<dyn Write> = if use_compression {
-            info!(target: "reth::cli", "Using gzip compression");
-            Box::new(flate2::write::GzEncoder::new(output_file, flate2::Compression::default()))
-        } else {
-            Box::new(output_file)
-        };
-
         // Export blocks in batches - wrap in closure to handle cleanup on error
         let export_result = (|| -> Result<()> {
+            let mut writer = create_output(&self.output_path)?;
+            let mut receipts_writer =
+                self.receipts_path.as_deref().map(create_output).transpose()?;
             let mut current_block = start_block;
             let mut exported_blocks = 0u64;
 
@@ -162,6 +185,22 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
                     }
                 }
 
+                if let Some(receipts_writer) = &mut receipts_writer {
+                    let receipts = provider.receipts_by_block_range(current_block..=batch_end)?;
+                    if receipts.len() as u64 != batch_end - current_block + 1 {
+                        return Err(eyre!(
+                            "Missing receipts in block range {current_block} to {batch_end}"
+                        ));
+                    }
+                    let receipts_rlp: Vec<u8> =
+                        receipts.iter().flat_map(|receipts| encode_receipts(receipts)).collect();
+                    receipts_writer.write_all(&receipts_rlp).wrap_err_with(|| {
+                        format!(
+                            "Failed to write receipts of block range {current_block} to {batch_end}"
+                        )
+                    })?;
+                }
+
                 exported_blocks += batch_end - current_block + 1;
 
                 // Log progress periodically
@@ -178,8 +217,11 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
                 current_block = batch_end + 1;
             }
 
-            // Flush and close the writer
+            // Flush and close the writers
             writer.flush().wrap_err("Failed to flush output file")?;
+            if let Some(receipts_writer) = &mut receipts_writer {
+                receipts_writer.flush().wrap_err("Failed to flush receipts file")?;
+            }
 
             if shutdown.load(Ordering::SeqCst) {
                 warn!(
@@ -205,11 +247,13 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
             Ok(())
         })();
 
-        // If an error occurred, remove the output file
+        // If an error occurred, remove the output files
         if let Err(e) = export_result {
-            warn!(target: "reth::cli", "Removing incomplete output file: {}", self.output_path.display());
-            if let Err(remove_err) = std::fs::remove_file(&self.output_path) {
-                warn!(target: "reth::cli", "Failed to remove output file: {}", remove_err);
+            for path in std::iter::once(&self.output_path).chain(&self.receipts_path) {
+                warn!(target: "reth::cli", "Removing incomplete output file: {}", path.display());
+                if let Err(remove_err) = std::fs::remove_file(path) {
+                    warn!(target: "reth::cli", "Failed to remove output file: {}", remove_err);
+                }
             }
             return Err(e);
         }
@@ -217,3 +261,172 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ExportCommand<C> {
         Ok(())
     }
 }
+
+/// Creates an output file, gzip compressed if it ends with .gz.
+fn create_output(path: &Path) -> Result<Box<dyn Write>> {
+    let file = File::create(path)
+        .wrap_err_with(|| format!("Failed to create output file: {}", path.display()))?;
+    Ok(if path.extension().and_then(|s| s.to_str()) == Some("gz") {
+        info!(target: "reth::cli", "Using gzip compression for {}", path.display());
+        Box::new(flate2::write::GzEncoder::new(file, flate2::Compression::default()))
+    } else {
+        Box::new(file)
+    })
+}
+
+/// Encodes the receipts of a block as an RLP list of receipts with their blooms.
+pub(crate) fn encode_receipts(receipts: &[OpReceipt]) -> Vec<u8> {
+    let receipts: Vec<_> =
+        receipts.iter().map(|receipt| receipt.clone().into_with_bloom()).collect();
+    alloy_rlp::encode(receipts)
+}
+
+/// Validates the export range against the genesis block and the database tip.
+///
+/// Blocks below the genesis block of a chain initialized with `legacyXLayerBlock` are synthetic
+/// placeholders, they are never part of an export.
+fn validate_export_range(start: u64, end: u64, genesis: u64, latest: u64) -> Result<()> {
+    if start < genesis || end < genesis {
+        return Err(eyre!(
+            "Export range {start} to {end} covers synthetic blocks before the genesis block ({genesis})"
+        ));
+    }
+    if end > latest {
+        return Err(eyre!("End block ({end}) is greater than latest block ({latest})"));
+    }
+    if start > end {
+        return Err(eyre!("Start block ({start}) is greater than end block ({end})"));
+    }
+    Ok(())
+}
+
+/// Resolves a block hash to its block number, failing if the block is unknown.
+fn resolve_block_hash<P: BlockNumReader>(provider: &P, hash: B256) -> Result<u64> {
+    let number = provider
+        .block_number(hash)
+        .wrap_err_with(|| format!("Failed to look up block hash {hash}"))?
+        .ok_or_else(|| eyre!("Block hash {hash} not found in database"))?;
+    info!(target: "reth::cli", "Resolved block hash {} to block {}", hash, number);
+    Ok(number)
+}
+
+/// Returns the first block in `[low, high]` whose timestamp is at or after `timestamp`.
+fn first_block_at_or_after<P>(provider: &P, timestamp: u64, low: u64, high: u64) -> Result<u64>
+where
+    P: HeaderProvider,
+    P::Header: BlockHeader,
+{
+    let number =
+        partition_point(low, high, |number| Ok(header_timestamp(provider, number)? < timestamp))?;
+    if number > high {
+        return Err(eyre!("No block found with timestamp at or after {timestamp}"));
+    }
+    info!(target: "reth::cli", "Resolved start timestamp {} to block {}", timestamp, number);
+    Ok(number)
+}
+
+/// Returns the last block in `[low, high]` whose timestamp is at or before `timestamp`.
+fn last_block_at_or_before<P>(provider: &P, timestamp: u64, low: u64, high: u64) -> Result<u64>
+where
+    P: HeaderProvider,
+    P::Header: BlockHeader,
+{
+    let first_after =
+        partition_point(low, high, |number| Ok(header_timestamp(provider, number)? <= timestamp))?;
+    if first_after == low {
+        return Err(eyre!("No block found with timestamp at or before {timestamp}"));
+    }
+    let number = first_after - 1;
+    info!(target: "reth::cli", "Resolved end timestamp {} to block {}", timestamp, number);
+    Ok(number)
+}
+
+/// Reads the timestamp of the header at `number`.
+fn header_timestamp<P>(provider: &P, number: u64) -> Result<u64>
+where
+    P: HeaderProvider,
+    P::Header: BlockHeader,
+{
+    provider
+        .header_by_number(number)
+        .wrap_err_with(|| format!("Failed to read header {number}"))?
+        .map(|header| header.timestamp())
+        .ok_or_else(|| eyre!("Header {number} not found in database"))
+}
+
+/// Binary search over the inclusive range `[low, high]`.
+///
+/// `pred` must be monotonic: true for a (possibly empty) prefix of the range and false
+/// afterwards. Returns the first number for which `pred` is false, or `high + 1` if it
+/// holds for the whole range.
+fn partition_point(
+    mut low: u64,
+    high: u64,
+    mut pred: impl FnMut(u64) -> Result<bool>,
+) -> Result<u64> {
+    let mut high = high.checked_add(1).ok_or_else(|| eyre!("Block range overflow"))?;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid)? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(low)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{partition_point, validate_export_range, ExportCommand};
+    use clap::{error::ErrorKind, Parser};
+    use xlayer_chainspec::XLayerChainSpecParser;
+
+    #[test]
+    fn test_block_hash_conflicts() {
+        let hash = format!("{:#x}", alloy_primitives::B256::with_last_byte(1));
+        for (by_hash, conflicting) in [
+            (["--start-hash", hash.as_str()], ["--start-block", "1"]),
+            (["--start-hash", hash.as_str()], ["--start-timestamp", "1"]),
+            (["--end-hash", hash.as_str()], ["--end-block", "1"]),
+            (["--end-hash", hash.as_str()], ["--end-timestamp", "1"]),
+        ] {
+            let args =
+                [&["export", "--exported-data", "blocks.rlp"][..], &by_hash[..], &conflicting[..]];
+            let err =
+                ExportCommand::<XLayerChainSpecParser>::try_parse_from(args.concat()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::ArgumentConflict, "{by_hash:?} {conflicting:?}");
+        }
+    }
+
+    #[test]
+    fn test_partition_point() {
+        let timestamps = [10u64, 20, 20, 30, 40];
+        let first_at_or_after =
+            |ts: u64| partition_point(0, 4, |n| Ok(timestamps[n as usize] < ts)).unwrap();
+        assert_eq!(first_at_or_after(5), 0);
+        assert_eq!(first_at_or_after(20), 1);
+        assert_eq!(first_at_or_after(25), 3);
+        assert_eq!(first_at_or_after(41), 5);
+
+        let first_after =
+            |ts: u64| partition_point(0, 4, |n| Ok(timestamps[n as usize] <= ts)).unwrap();
+        assert_eq!(first_after(5), 0);
+        assert_eq!(first_after(20), 3);
+        assert_eq!(first_after(40), 5);
+    }
+
+    #[test]
+    fn test_validate_export_range() {
+        assert!(validate_export_range(100, 200, 100, 200).is_ok());
+        assert!(validate_export_range(150, 150, 100, 200).is_ok());
+        assert!(validate_export_range(0, 200, 0, 200).is_ok());
+
+        // Synthetic blocks before a non-zero genesis
+        assert!(validate_export_range(0, 200, 100, 200).is_err());
+        assert!(validate_export_range(99, 200, 100, 200).is_err());
+
+        assert!(validate_export_range(100, 201, 100, 200).is_err());
+        assert!(validate_export_range(150, 149, 100, 200).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+//! Command that prints the fork id of a chain.
+//!
+//! Operators compare the output against the fork schedule of the op-node rollup configuration and
+//! the fork ids announced by peers before enabling a hardfork. Without `--timestamp`, the fork id
+//! is computed for the current time.
+
+use std::{
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_consensus::BlockHeader;
+use alloy_primitives::hex;
+use clap::Parser;
+use eyre::{eyre, Result};
+use reth_chainspec::{EthChainSpec, ForkCondition, Hardforks};
+use reth_cli::chainspec::ChainSpecParser;
+use reth_optimism_chainspec::OpChainSpec;
+use xlayer_chainspec::{activation_boundaries, fork_id_at, verify_activation_boundaries};
+
+/// Prints the fork id of a chain at a block and timestamp.
+#[derive(Debug, Parser)]
+pub struct ForkIdCommand<C: ChainSpecParser> {
+    /// The chain this node is running, a built-in chain or the path to a genesis file
+    #[arg(
+        long,
+        value_name = "CHAIN_OR_PATH",
+        default_value = C::default_value(),
+        value_parser = C::parser()
+    )]
+    chain: Arc<C::ChainSpec>,
+
+    /// Block number of the head, defaults to the genesis block
+    #[arg(long, value_name = "NUMBER")]
+    block: Option<u64>,
+
+    /// Timestamp of the head, defaults to now
+    #[arg(long, value_name = "TIMESTAMP")]
+    timestamp: Option<u64>,
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ForkIdCommand<C> {
+    /// Execute `forkid` command
+    pub fn execute(self) -> Result<()> {
+        let spec = &self.chain;
+        let genesis = spec.genesis_header();
+        let number = self.block.unwrap_or(genesis.number());
+        let timestamp = match self.timestamp {
+            Some(timestamp) => timestamp,
+            None => SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        };
+
+        let fork_id = fork_id_at(spec, number, timestamp);
+        println!("chain:      {}", spec.chain_id());
+        println!("genesis:    {} ({})", genesis.number(), spec.genesis_hash());
+        println!("head:       block {number}, timestamp {timestamp}");
+        println!("fork hash:  0x{}", hex::encode(fork_id.hash.0));
+        println!("fork next:  {}", fork_id.next);
+
+        println!("\nhardforks:");
+        for (fork, condition) in spec.forks_iter() {
+            let activation = match condition {
+                ForkCondition::Block(block) => format!("block {block}"),
+                ForkCondition::TTD { fork_block: Some(block), .. } => format!("block {block}"),
+                ForkCondition::TTD { .. } => "total difficulty".to_string(),
+                ForkCondition::Timestamp(timestamp) => format!("timestamp {timestamp}"),
+                ForkCondition::Never => "never".to_string(),
+            };
+            let active = if condition.active_at_timestamp_or_number(timestamp, number) {
+                "active"
+            } else {
+                "pending"
+            };
+            println!("  {:<16} {activation:<24} {active}", fork.name());
+        }
+
+        let boundaries = activation_boundaries(spec);
+        if !boundaries.is_empty() {
+            println!("\nactivation boundaries:");
+        }
+        for boundary in boundaries {
+            println!(
+                "  {:<16} 0x{} -> 0x{}",
+                boundary.fork,
+                hex::encode(boundary.before.hash.0),
+                hex::encode(boundary.at.hash.0)
+            );
+        }
+
+        verify_activation_boundaries(spec).map_err(|err| eyre!("invalid fork schedule: {err}"))
+    }
+}
@@ -33,6 +33,7 @@ use std::{
     },
 };
 use tracing::{debug, info, warn};
+use xlayer_chainspec::LEGACY_XLAYER_BLOCK_KEY;
 
 // The keccak256 of empty bytes is the well-known value 0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470.
 const EMPTY_CODE_HASH: B256 =
@@ -170,7 +171,7 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> GenGenesisCommand<C> {
         // Update the config with legacyXLayerBlock set to genesis block number (latest + 1)
         let mut config = template_genesis.config;
         config.extra_fields.insert(
-            "legacyXLayerBlock".to_string(),
+            LEGACY_XLAYER_BLOCK_KEY.to_string(),
             serde_json::Value::Number(serde_json::Number::from(genesis_block_number)),
         );
 
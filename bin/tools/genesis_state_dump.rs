@@ -0,0 +1,184 @@
+//! Command that converts a genesis file into a state dump for `init-state`.
+//!
+//! The X Layer chain specs ship without their alloc, the state is loaded with `init-state` from a
+//! JSONL dump. The mainnet genesis holds millions of accounts, so the alloc is streamed from the
+//! genesis file to the dump one account at a time instead of being parsed into memory. The dump
+//! starts with the state root of the genesis header of the chain, which `init-state` checks after
+//! inserting the accounts in chunks. The dump is written next to the output and only moved in place
+//! once the whole genesis was converted and matches the chain.
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy_genesis::GenesisAccount;
+use alloy_primitives::{Address, B256};
+use clap::Parser;
+use eyre::{eyre, Result, WrapErr};
+use reth_chainspec::EthChainSpec;
+use reth_cli::chainspec::ChainSpecParser;
+use reth_optimism_chainspec::OpChainSpec;
+use serde::Serialize;
+use tracing::info;
+use xlayer_chainspec::parse_genesis_streaming;
+
+/// Number of accounts between progress logs.
+const PROGRESS_INTERVAL: u64 = 1_000_000;
+
+/// Converts the alloc of a genesis file into a state dump accepted by `init-state`.
+#[derive(Debug, Parser)]
+pub struct GenesisStateDumpCommand<C: ChainSpecParser> {
+    /// The chain this node is running, a built-in chain or the path to a genesis file
+    #[arg(
+        long,
+        value_name = "CHAIN_OR_PATH",
+        default_value = C::default_value(),
+        value_parser = C::parser()
+    )]
+    chain: Arc<C::ChainSpec>,
+
+    /// The genesis file holding the alloc of the chain
+    #[arg(long, value_name = "FILE")]
+    genesis: PathBuf,
+
+    /// The path to write the JSONL state dump
+    #[arg(long, value_name = "FILE")]
+    output: PathBuf,
+}
+
+/// First line of the state dump.
+#[derive(Serialize)]
+struct DumpRoot {
+    root: B256,
+}
+
+/// Account line of the state dump.
+#[derive(Serialize)]
+struct DumpAccount<'a> {
+    address: Address,
+    #[serde(flatten)]
+    account: &'a GenesisAccount,
+}
+
+/// Path the dump is written to before it is moved to `output`.
+fn partial_path(output: &Path) -> PathBuf {
+    let mut path = OsString::from(output.as_os_str());
+    path.push(".partial");
+    path.into()
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> GenesisStateDumpCommand<C> {
+    /// Execute `genesis-state-dump` command
+    pub fn execute(self) -> Result<()> {
+        let partial = partial_path(&self.output);
+        let accounts = match self.write_dump(&partial) {
+            Ok(accounts) => accounts,
+            Err(err) => {
+                let _ = fs::remove_file(&partial);
+                return Err(err);
+            }
+        };
+        fs::rename(&partial, &self.output)
+            .wrap_err_with(|| format!("Failed to move the dump to {}", self.output.display()))?;
+
+        let root = self.chain.genesis_header().state_root;
+        info!(target: "xlayer::genesis_state_dump", accounts, %root, output = %self.output.display(), "State dump written");
+        Ok(())
+    }
+
+    /// Writes the dump to `path` and returns the number of accounts.
+    fn write_dump(&self, path: &Path) -> Result<u64> {
+        let root = self.chain.genesis_header().state_root;
+        let input = File::open(&self.genesis)
+            .wrap_err_with(|| format!("Failed to open {}", self.genesis.display()))?;
+        let output =
+            File::create(path).wrap_err_with(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(output);
+
+        serde_json::to_writer(&mut writer, &DumpRoot { root })?;
+        writer.write_all(b"\n")?;
+
+        let mut written = 0u64;
+        let (genesis, accounts) =
+            parse_genesis_streaming(BufReader::new(input), |address, account| {
+                serde_json::to_writer(&mut writer, &DumpAccount { address, account: &account })?;
+                writer.write_all(b"\n")?;
+                written += 1;
+                if written % PROGRESS_INTERVAL == 0 {
+                    info!(target: "xlayer::genesis_state_dump", accounts = written, "Converting alloc");
+                }
+                Ok(())
+            })
+            .wrap_err_with(|| format!("Failed to parse {}", self.genesis.display()))?;
+
+        let chain_id = self.chain.chain_id();
+        if genesis.config.chain_id != chain_id {
+            return Err(eyre!(
+                "genesis file is for chain {}, expected chain {chain_id}",
+                genesis.config.chain_id
+            ));
+        }
+
+        writer.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+        Ok(accounts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::U256;
+    use reth_optimism_chainspec::OP_MAINNET;
+    use xlayer_chainspec::XLayerChainSpecParser;
+
+    #[test]
+    fn test_dump_account_line() {
+        let account = GenesisAccount::default().with_balance(U256::from(1));
+        let line = serde_json::to_value(DumpAccount {
+            address: Address::with_last_byte(1),
+            account: &account,
+        })
+        .unwrap();
+        assert_eq!(line["address"], "0x0000000000000000000000000000000000000001");
+        assert_eq!(line["balance"], "0x1");
+    }
+
+    #[test]
+    fn test_chain_mismatch_leaves_no_output() {
+        let dir = std::env::temp_dir().join(format!("genesis-state-dump-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let genesis = dir.join("genesis.json");
+        fs::write(
+            &genesis,
+            r#"{
+                "alloc": { "0x0000000000000000000000000000000000000001": { "balance": "0x1" } },
+                "config": { "chainId": 196 },
+                "nonce": "0x0",
+                "timestamp": "0x0",
+                "extraData": "0x",
+                "gasLimit": "0x1000000",
+                "difficulty": "0x0",
+                "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                "coinbase": "0x0000000000000000000000000000000000000000"
+            }"#,
+        )
+        .unwrap();
+        let output = dir.join("state.jsonl");
+
+        let command = GenesisStateDumpCommand::<XLayerChainSpecParser> {
+            chain: OP_MAINNET.clone(),
+            genesis,
+            output: output.clone(),
+        };
+        let err = command.execute().unwrap_err();
+        assert!(err.to_string().contains("expected chain 10"), "{err}");
+        assert!(!output.exists());
+        assert!(!partial_path(&output).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -6,19 +6,62 @@
 //! - Skips genesis block (block 0)
 //! - Only imports blocks that are missing from the database
 //! - Handles interrupts gracefully (Ctrl+C)
+//! - Checks that the archive starts at or after the genesis block of the chain spec, which is not 0
+//!   for chains initialized with `legacyXLayerBlock`, and that its first block links to the genesis
+//!   header or to a block already in the database
+//!
+//! With `--no-exec --trusted`, blocks are inserted directly into the database without running
+//! the pipeline. RLP block archives carry no receipts, so their receipts are read from a second
+//! archive written by `export --receipts-data`, one RLP list of receipts per block. The header
+//! chain linkage, the transaction roots, the receipts roots and the logs blooms are validated,
+//! senders are recovered, blocks and receipts are written, and the headers/bodies/sender-recovery
+//! stage checkpoints are advanced.
 
+use alloy_consensus::{BlockHeader, ReceiptWithBloom, TxReceipt};
+use alloy_primitives::{logs_bloom, B256};
+use alloy_rlp::Decodable;
 use clap::Parser;
-use eyre::{eyre, Result};
-use reth_chainspec::ChainSpecProvider;
+use eyre::{eyre, Result, WrapErr};
+use reth_chainspec::{ChainSpecProvider, EthChainSpec};
 use reth_cli::chainspec::ChainSpecParser;
 use reth_cli_commands::{
     common::{AccessRights, CliNodeComponents, CliNodeTypes, Environment, EnvironmentArgs},
     import_core::{import_blocks_from_file, ImportConfig},
 };
+use reth_db::DatabaseEnv;
 use reth_node_core::version::version_metadata;
+use reth_node_types::{BlockTy, NodeTypesWithDBAdapter};
 use reth_optimism_chainspec::OpChainSpec;
-use std::{path::PathBuf, sync::Arc};
-use tracing::info;
+use reth_optimism_consensus::calculate_receipt_root_no_memo_optimism;
+use reth_optimism_primitives::{OpBlock, OpPrimitives, OpReceipt};
+use reth_primitives_traits::{Block, BlockBody, SealedBlock};
+use reth_provider::{
+    BlockHashReader, BlockNumReader, BlockWriter, ExecutionOutcome, OriginalValuesKnown,
+    ProviderFactory, StageCheckpointWriter, StateWriter,
+};
+use reth_stages_types::{StageCheckpoint, StageId};
+use std::{
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use tracing::{info, warn};
+
+/// Default number of bytes read from the archive per chunk in `--no-exec` mode.
+const DEFAULT_NO_EXEC_CHUNK_LEN: u64 = 1024 * 1024 * 1024;
+
+/// Number of bytes read from the archive at a time while decoding its first block.
+const FIRST_BLOCK_READ_LEN: u64 = 1024 * 1024;
+
+/// Number of blocks written per database transaction in `--no-exec` mode.
+const NO_EXEC_COMMIT_BLOCKS: usize = 10_000;
+
+/// Stages whose checkpoints are advanced by the `--no-exec` import.
+const NO_EXEC_STAGES: [StageId; 3] = [StageId::Headers, StageId::Bodies, StageId::SenderRecovery];
 
 /// Syncs RLP encoded blocks from a file, similar to go-ethereum's import command.
 #[derive(Debug, Parser)]
@@ -34,11 +77,40 @@ pub struct ImportCommand<C: ChainSpecParser> {
     #[arg(long, value_name = "CHUNK_LEN", verbatim_doc_comment)]
     chunk_len: Option<u64>,
 
+    /// Inserts headers, bodies and receipts directly without executing blocks.
+    ///
+    /// Only the header chain linkage, transaction roots, receipts roots and logs blooms are
+    /// validated and senders are recovered. Intended for importing legacy history from a trusted
+    /// archive, requires --trusted and --receipts-data.
+    #[arg(
+        long,
+        requires_all = ["trusted", "receipts_path"],
+        conflicts_with = "no_state",
+        verbatim_doc_comment
+    )]
+    no_exec: bool,
+
+    /// Acknowledges that the archive is trusted and skipping execution is acceptable.
+    #[arg(long, requires = "no_exec")]
+    trusted: bool,
+
     /// The path to a block file for import.
     ///
     /// Blocks should be RLP encoded. The file can be gzip compressed if it ends with .gz
     #[arg(long = "exported-data", value_name = "EXPORTED_DATA", verbatim_doc_comment)]
     path: PathBuf,
+
+    /// The path to the receipts of the blocks, read with --no-exec.
+    ///
+    /// One RLP list of receipts per block, in the order of the blocks, as written by
+    /// `export --receipts-data`. The file can be gzip compressed if it ends with .gz
+    #[arg(
+        long = "receipts-data",
+        value_name = "RECEIPTS_DATA",
+        requires = "no_exec",
+        verbatim_doc_comment
+    )]
+    receipts_path: Option<PathBuf>,
 }
 
 impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ImportCommand<C> {
@@ -48,7 +120,7 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ImportCommand<C> {
         components: impl FnOnce(Arc<N::ChainSpec>) -> Comp,
     ) -> Result<()>
     where
-        N: CliNodeTypes<ChainSpec = C::ChainSpec>,
+        N: CliNodeTypes<ChainSpec = C::ChainSpec, Primitives = OpPrimitives>,
         Comp: CliNodeComponents<N>,
     {
         info!(target: "reth::cli", "{} ({}) starting", version_metadata().name_client, version_metadata().short_version);
@@ -56,6 +128,12 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ImportCommand<C> {
 
         let Environment { provider_factory, config, .. } = self.env.init::<N>(AccessRights::RW)?;
 
+        self.check_archive_start::<N>(&provider_factory)?;
+
+        if self.no_exec {
+            return self.import_without_execution::<N>(provider_factory);
+        }
+
         let components = components(provider_factory.chain_spec());
 
         let import_config = ImportConfig { no_state: self.no_state, chunk_len: self.chunk_len };
@@ -95,3 +173,496 @@ impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ImportCommand<C> {
         Ok(())
     }
 }
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ImportCommand<C> {
+    /// Checks that the first block of the archive links to the genesis block of the chain spec or
+    /// to a block already in the database.
+    fn check_archive_start<N>(
+        &self,
+        provider_factory: &ProviderFactory<NodeTypesWithDBAdapter<N, Arc<DatabaseEnv>>>,
+    ) -> Result<()>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec>,
+    {
+        let Some(first) = read_first_block::<BlockTy<N>>(open_archive(&self.path)?)? else {
+            return Err(eyre!("Import file {} contains no blocks", self.path.display()));
+        };
+
+        let chain_spec = provider_factory.chain_spec();
+        let genesis_number = chain_spec.genesis_header().number();
+        let first = SealedBlock::seal_slow(first);
+        let header = first.header();
+        let number = header.number();
+        let parent_hash = if number > genesis_number {
+            provider_factory.provider()?.block_hash(number - 1)?
+        } else {
+            None
+        };
+        validate_archive_start(
+            number,
+            header.parent_hash(),
+            first.hash(),
+            genesis_number,
+            chain_spec.genesis_hash(),
+            parent_hash,
+        )?;
+
+        info!(target: "reth::cli", "Archive starts at block {} (genesis block {})", number, genesis_number);
+        Ok(())
+    }
+
+    /// Imports blocks and their receipts from trusted archives without executing them.
+    fn import_without_execution<N>(
+        &self,
+        provider_factory: ProviderFactory<NodeTypesWithDBAdapter<N, Arc<DatabaseEnv>>>,
+    ) -> Result<()>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec, Primitives = OpPrimitives>,
+    {
+        warn!(target: "reth::cli", "Importing without execution, blocks from the archive are trusted");
+
+        let receipts_path = self
+            .receipts_path
+            .as_ref()
+            .ok_or_else(|| eyre!("--no-exec requires --receipts-data"))?;
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let shutdown_clone = Arc::clone(&shutdown);
+        ctrlc::set_handler(move || {
+            warn!(target: "reth::cli", "Received interrupt signal, shutting down gracefully...");
+            shutdown_clone.store(true, Ordering::SeqCst);
+        })
+        .wrap_err("Failed to set interrupt handler")?;
+
+        let chunk_len = self.chunk_len.unwrap_or(DEFAULT_NO_EXEC_CHUNK_LEN);
+        let mut blocks = RlpReader::new(open_archive(&self.path)?, chunk_len);
+        let mut receipts = RlpReader::new(open_archive(receipts_path)?, chunk_len);
+        let chain_spec = provider_factory.chain_spec();
+
+        // The first imported block must extend the current database tip
+        let (mut tip_number, mut tip_hash) = {
+            let provider = provider_factory.provider()?;
+            let tip_number = provider.last_block_number()?;
+            let tip_hash = provider
+                .block_hash(tip_number)?
+                .ok_or_else(|| eyre!("Missing hash for database tip {tip_number}"))?;
+            (tip_number, tip_hash)
+        };
+        info!(target: "reth::cli", "Database tip is block {} ({})", tip_number, tip_hash);
+
+        let mut done = false;
+        let mut imported_blocks = 0u64;
+        let mut imported_txns = 0u64;
+        let mut skipped_blocks = 0u64;
+
+        while !done && !shutdown.load(Ordering::SeqCst) {
+            let provider_rw = provider_factory.database_provider_rw()?;
+            let first_block = tip_number + 1;
+            let mut batch_receipts = Vec::new();
+
+            while batch_receipts.len() < NO_EXEC_COMMIT_BLOCKS {
+                let Some(block) = blocks.next::<OpBlock>()? else {
+                    done = true;
+                    break;
+                };
+                let number = block.header().number();
+                let block_receipts = receipts
+                    .next::<Vec<ReceiptWithBloom<OpReceipt>>>()?
+                    .ok_or_else(|| eyre!("Receipts archive ends before block {number}"))?;
+                if number <= tip_number {
+                    // Already present in the database
+                    skipped_blocks += 1;
+                    continue;
+                }
+
+                let sealed = SealedBlock::seal_slow(block);
+                validate_trusted_block(&sealed, tip_number, tip_hash)?;
+                let block_receipts: Vec<OpReceipt> =
+                    block_receipts.into_iter().map(|receipt| receipt.receipt).collect();
+                validate_block_receipts(&sealed, &block_receipts, &chain_spec)?;
+
+                let hash = sealed.hash();
+                imported_txns += sealed.body().transactions().len() as u64;
+                let recovered = sealed
+                    .try_recover()
+                    .map_err(|e| eyre!("Failed to recover senders for block {number}: {e}"))?;
+                provider_rw.insert_block(recovered)?;
+                batch_receipts.push(block_receipts);
+
+                tip_number = number;
+                tip_hash = hash;
+                imported_blocks += 1;
+            }
+
+            if batch_receipts.is_empty() {
+                continue;
+            }
+
+            let outcome =
+                ExecutionOutcome::new(Default::default(), batch_receipts, first_block, Vec::new());
+            provider_rw.write_state(&outcome, OriginalValuesKnown::Yes)?;
+            for stage in NO_EXEC_STAGES {
+                provider_rw.save_stage_checkpoint(stage, StageCheckpoint::new(tip_number))?;
+            }
+            provider_rw.commit()?;
+
+            info!(
+                target: "reth::cli",
+                "Imported {} blocks ({} transactions), tip is block {}",
+                imported_blocks,
+                imported_txns,
+                tip_number
+            );
+        }
+
+        if shutdown.load(Ordering::SeqCst) {
+            return Err(eyre!(
+                "Import was interrupted. Imported {} blocks, tip is block {}",
+                imported_blocks,
+                tip_number
+            ));
+        }
+
+        if receipts.next::<Vec<ReceiptWithBloom<OpReceipt>>>()?.is_some() {
+            return Err(eyre!("Receipts archive has receipts past the last block"));
+        }
+
+        info!(
+            target: "reth::cli",
+            "Import complete! Imported {} blocks, {} transactions ({} already present)",
+            imported_blocks,
+            imported_txns,
+            skipped_blocks
+        );
+        info!(
+            target: "reth::cli",
+            "State was not derived; transaction lookup index requires the TransactionLookup stage"
+        );
+
+        Ok(())
+    }
+}
+
+/// Opens an archive, decompressing it if it ends with .gz.
+fn open_archive(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path)
+        .wrap_err_with(|| format!("Failed to open import file: {}", path.display()))?;
+    Ok(if path.extension().and_then(|s| s.to_str()) == Some("gz") {
+        Box::new(flate2::read::GzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// Decodes consecutive RLP items of an archive, reading it a chunk at a time.
+///
+/// The buffer only holds the bytes read and not yet decoded, an item spanning several chunks is
+/// decoded once all of its bytes are read.
+struct RlpReader<R> {
+    reader: R,
+    chunk_len: u64,
+    buf: Vec<u8>,
+    /// Number of decoded bytes at the start of the buffer
+    pos: usize,
+    eof: bool,
+}
+
+impl<R: Read> RlpReader<R> {
+    fn new(reader: R, chunk_len: u64) -> Self {
+        Self { reader, chunk_len: chunk_len.max(1), buf: Vec::new(), pos: 0, eof: false }
+    }
+
+    /// Decodes the next item, `None` at the end of the archive.
+    fn next<T: Decodable>(&mut self) -> Result<Option<T>> {
+        loop {
+            let mut cursor = &self.buf[self.pos..];
+            if cursor.is_empty() && self.eof {
+                return Ok(None);
+            }
+            if !cursor.is_empty() {
+                match T::decode(&mut cursor) {
+                    Ok(item) => {
+                        self.pos = self.buf.len() - cursor.len();
+                        return Ok(Some(item));
+                    }
+                    // Incomplete item, read the next chunk
+                    Err(alloy_rlp::Error::InputTooShort) if !self.eof => {}
+                    Err(e) => return Err(eyre!("Failed to decode item from archive: {e}")),
+                }
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Drops the decoded bytes and appends the next chunk of the archive.
+    fn fill(&mut self) -> Result<()> {
+        self.buf.drain(..self.pos);
+        self.pos = 0;
+        let read = self
+            .reader
+            .by_ref()
+            .take(self.chunk_len)
+            .read_to_end(&mut self.buf)
+            .wrap_err("Failed to read import file")?;
+        self.eof = read == 0;
+        Ok(())
+    }
+}
+
+/// Decodes the first block of the archive, returning `None` if the archive is empty.
+fn read_first_block<B: Decodable>(mut reader: impl Read) -> Result<Option<B>> {
+    let mut buf = Vec::new();
+    loop {
+        let read = reader
+            .by_ref()
+            .take(FIRST_BLOCK_READ_LEN)
+            .read_to_end(&mut buf)
+            .wrap_err("Failed to read import file")?;
+        if buf.is_empty() {
+            return Ok(None);
+        }
+        match B::decode(&mut buf.as_slice()) {
+            Ok(block) => return Ok(Some(block)),
+            Err(alloy_rlp::Error::InputTooShort) if read > 0 => continue,
+            Err(e) => return Err(eyre!("Failed to decode first block from archive: {e}")),
+        }
+    }
+}
+
+/// Validates that the first block of an archive extends the genesis block or the block before it
+/// in the database.
+///
+/// Chains initialized with `legacyXLayerBlock` start at a non-zero genesis block, the blocks
+/// before it are synthetic and must not be imported. An archive may include the genesis block
+/// itself, in which case its hash must match the genesis hash of the chain spec.
+fn validate_archive_start(
+    number: u64,
+    parent_hash: B256,
+    block_hash: B256,
+    genesis_number: u64,
+    genesis_hash: B256,
+    db_parent_hash: Option<B256>,
+) -> Result<()> {
+    if number < genesis_number {
+        return Err(eyre!(
+            "Archive starts at block {number}, before the genesis block ({genesis_number}), blocks before the genesis block are synthetic"
+        ));
+    }
+    if number == genesis_number {
+        if block_hash != genesis_hash {
+            return Err(eyre!(
+                "Archive genesis block {number} hash {block_hash} does not match the chain spec genesis hash {genesis_hash}"
+            ));
+        }
+        return Ok(());
+    }
+
+    let expected = if number == genesis_number + 1 { Some(genesis_hash) } else { db_parent_hash };
+    match expected {
+        Some(expected) if expected == parent_hash => Ok(()),
+        Some(expected) => Err(eyre!(
+            "Archive block {number} parent hash {parent_hash} does not match block {} hash {expected}",
+            number - 1
+        )),
+        None => Err(eyre!(
+            "Archive starts at block {number} but block {} is not in the database",
+            number - 1
+        )),
+    }
+}
+
+/// Validates that a trusted block extends the current tip and that its body matches its header.
+fn validate_trusted_block<B: Block>(
+    block: &SealedBlock<B>,
+    parent_number: u64,
+    parent_hash: B256,
+) -> Result<()> {
+    let header = block.header();
+    let number = header.number();
+    if number != parent_number + 1 {
+        return Err(eyre!("Block {number} does not follow block {parent_number}"));
+    }
+    if header.parent_hash() != parent_hash {
+        return Err(eyre!(
+            "Block {number} parent hash {} does not match block {parent_number} hash {parent_hash}",
+            header.parent_hash()
+        ));
+    }
+    let tx_root = block.body().calculate_tx_root();
+    if tx_root != header.transactions_root() {
+        return Err(eyre!(
+            "Block {number} transactions root mismatch: header {}, computed {tx_root}",
+            header.transactions_root()
+        ));
+    }
+    Ok(())
+}
+
+/// Validates that the receipts of a trusted block match its transactions and header.
+fn validate_block_receipts(
+    block: &SealedBlock<OpBlock>,
+    receipts: &[OpReceipt],
+    chain_spec: &OpChainSpec,
+) -> Result<()> {
+    let header = block.header();
+    let number = header.number();
+    let transactions = block.body().transactions().len();
+    if receipts.len() != transactions {
+        return Err(eyre!(
+            "Block {number} has {transactions} transactions but {} receipts",
+            receipts.len()
+        ));
+    }
+    let receipts_root =
+        calculate_receipt_root_no_memo_optimism(receipts, chain_spec, header.timestamp());
+    if receipts_root != header.receipts_root() {
+        return Err(eyre!(
+            "Block {number} receipts root mismatch: header {}, computed {receipts_root}",
+            header.receipts_root()
+        ));
+    }
+    let bloom = logs_bloom(receipts.iter().flat_map(|receipt| receipt.logs()));
+    if bloom != header.logs_bloom() {
+        return Err(eyre!("Block {number} logs bloom doesn't match its receipts"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::encode_receipts;
+    use alloy_consensus::{proofs::calculate_transaction_root, Header, Receipt, Sealed};
+    use alloy_primitives::{Address, Log, TxKind};
+    use op_alloy_consensus::{OpDepositReceipt, OpTxEnvelope, TxDeposit};
+    use reth_optimism_chainspec::OP_MAINNET;
+
+    /// Chain of `len` blocks after `parent`, each with a deposit and its receipt.
+    fn fixture(parent: B256, first: u64, len: u64) -> Vec<(OpBlock, Vec<OpReceipt>)> {
+        let mut parent_hash = parent;
+        (first..first + len)
+            .map(|number| {
+                let tx = OpTxEnvelope::Deposit(Sealed::new(TxDeposit {
+                    source_hash: B256::with_last_byte(number as u8),
+                    from: Address::with_last_byte(1),
+                    to: TxKind::Call(Address::with_last_byte(2)),
+                    gas_limit: 21_000,
+                    ..Default::default()
+                }));
+                let log = Log::new_unchecked(
+                    Address::with_last_byte(2),
+                    vec![B256::with_last_byte(number as u8)],
+                    Default::default(),
+                );
+                let receipts = vec![OpReceipt::Deposit(OpDepositReceipt {
+                    inner: Receipt {
+                        status: true.into(),
+                        cumulative_gas_used: 21_000,
+                        logs: vec![log],
+                    },
+                    deposit_nonce: Some(number),
+                    deposit_receipt_version: Some(1),
+                })];
+                let timestamp = 1_800_000_000 + number * 2;
+                let header = Header {
+                    parent_hash,
+                    number,
+                    timestamp,
+                    transactions_root: calculate_transaction_root(std::slice::from_ref(&tx)),
+                    receipts_root: calculate_receipt_root_no_memo_optimism(
+                        &receipts,
+                        &**OP_MAINNET,
+                        timestamp,
+                    ),
+                    logs_bloom: logs_bloom(receipts.iter().flat_map(|receipt| receipt.logs())),
+                    ..Default::default()
+                };
+                parent_hash = header.hash_slow();
+                let body = alloy_consensus::BlockBody {
+                    transactions: vec![tx],
+                    ommers: Vec::new(),
+                    withdrawals: None,
+                };
+                (OpBlock::new(header, body), receipts)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_no_exec_archive_round_trip() {
+        let genesis = B256::repeat_byte(0x01);
+        let chain = fixture(genesis, 101, 5);
+        let blocks_rlp: Vec<u8> =
+            chain.iter().flat_map(|(block, _)| alloy_rlp::encode(block)).collect();
+        let receipts_rlp: Vec<u8> =
+            chain.iter().flat_map(|(_, receipts)| encode_receipts(receipts)).collect();
+
+        // Chunks smaller than a block, every item spans several chunks
+        let mut blocks = RlpReader::new(blocks_rlp.as_slice(), 7);
+        let mut receipts = RlpReader::new(receipts_rlp.as_slice(), 7);
+        let (mut tip_number, mut tip_hash) = (100, genesis);
+        for (expected_block, expected_receipts) in &chain {
+            let block = SealedBlock::seal_slow(blocks.next::<OpBlock>().unwrap().unwrap());
+            assert_eq!(block.hash(), expected_block.header.hash_slow());
+            validate_trusted_block(&block, tip_number, tip_hash).unwrap();
+
+            let block_receipts: Vec<OpReceipt> = receipts
+                .next::<Vec<ReceiptWithBloom<OpReceipt>>>()
+                .unwrap()
+                .unwrap()
+                .into_iter()
+                .map(|receipt| receipt.receipt)
+                .collect();
+            assert_eq!(&block_receipts, expected_receipts);
+            validate_block_receipts(&block, &block_receipts, &OP_MAINNET).unwrap();
+
+            (tip_number, tip_hash) = (block.header().number(), block.hash());
+        }
+        assert!(blocks.next::<OpBlock>().unwrap().is_none());
+        assert!(receipts.next::<Vec<ReceiptWithBloom<OpReceipt>>>().unwrap().is_none());
+        // Only the undecoded bytes are buffered
+        assert!(blocks.buf.len() < blocks_rlp.len());
+    }
+
+    #[test]
+    fn test_block_receipts_mismatch() {
+        let chain = fixture(B256::ZERO, 1, 2);
+        let block = SealedBlock::seal_slow(chain[0].0.clone());
+        assert!(validate_block_receipts(&block, &chain[0].1, &OP_MAINNET).is_ok());
+        assert!(validate_block_receipts(&block, &chain[1].1, &OP_MAINNET).is_err());
+        assert!(validate_block_receipts(&block, &[], &OP_MAINNET).is_err());
+    }
+
+    #[test]
+    fn test_truncated_archive() {
+        let chain = fixture(B256::ZERO, 1, 1);
+        let rlp = alloy_rlp::encode(&chain[0].0);
+        let mut reader = RlpReader::new(&rlp[..rlp.len() - 1], 4);
+        assert!(reader.next::<OpBlock>().is_err());
+    }
+
+    #[test]
+    fn test_validate_archive_start() {
+        let genesis = B256::repeat_byte(0x01);
+        let other = B256::repeat_byte(0x02);
+
+        // First block after a non-zero genesis
+        assert!(validate_archive_start(101, genesis, B256::ZERO, 100, genesis, None).is_ok());
+        assert!(validate_archive_start(101, other, B256::ZERO, 100, genesis, None).is_err());
+
+        // Archive including the genesis block
+        assert!(validate_archive_start(100, B256::ZERO, genesis, 100, genesis, None).is_ok());
+        assert!(validate_archive_start(100, B256::ZERO, other, 100, genesis, None).is_err());
+
+        // Synthetic blocks before the genesis block
+        assert!(validate_archive_start(0, B256::ZERO, other, 100, genesis, None).is_err());
+        assert!(validate_archive_start(99, B256::ZERO, other, 100, genesis, None).is_err());
+
+        // Continuation of the chain in the database
+        assert!(validate_archive_start(200, other, B256::ZERO, 100, genesis, Some(other)).is_ok());
+        assert!(
+            validate_archive_start(200, genesis, B256::ZERO, 100, genesis, Some(other)).is_err()
+        );
+        assert!(validate_archive_start(200, other, B256::ZERO, 100, genesis, None).is_err());
+    }
+}
@@ -8,14 +8,39 @@ use reth_optimism_node::OpNode;
 use reth_tracing::{RethTracer, Tracer};
 use std::{process::ExitCode, sync::Arc};
 use tracing::{error, info};
-use xlayer_chainspec::XLayerChainSpecParser;
+use xlayer_chainspec::{apply_network_defaults, XLayerChainSpecParser};
 
+mod bridge_reconcile;
 mod export;
+mod forkid;
 mod gen_genesis;
+mod genesis_state_dump;
 mod import;
+mod replay_wal;
+mod state_diff;
+mod validate_genesis;
+mod verify_receipts;
+use bridge_reconcile::BridgeReconcileCommand;
 use export::ExportCommand;
+use forkid::ForkIdCommand;
 use gen_genesis::GenGenesisCommand;
+use genesis_state_dump::GenesisStateDumpCommand;
 use import::ImportCommand;
+use replay_wal::ReplayWalCommand;
+use state_diff::StateDiffCommand;
+use validate_genesis::ValidateGenesisInitCommand;
+use verify_receipts::VerifyReceiptsCommand;
+
+/// Subcommands opening a datadir, which default to the datadir of the XLayer network.
+const DATADIR_COMMANDS: &[&str] = &[
+    "import",
+    "export",
+    "gen-genesis",
+    "validate-genesis-init",
+    "state-diff",
+    "bridge-reconcile",
+    "verify-receipts",
+];
 
 #[global_allocator]
 static ALLOC: reth_cli_util::allocator::Allocator = reth_cli_util::allocator::new_allocator();
@@ -37,6 +62,20 @@ enum Commands {
     Export(ExportCommand<XLayerChainSpecParser>),
     /// Generate a genesis file from an existing database
     GenGenesis(GenGenesisCommand<XLayerChainSpecParser>),
+    /// Validate the genesis initialization of a data directory without modifying it
+    ValidateGenesisInit(ValidateGenesisInitCommand<XLayerChainSpecParser>),
+    /// Re-submit transactions of the forwarded transaction log that were never included
+    ReplayWal(ReplayWalCommand),
+    /// Print the fork id and hardfork schedule of a chain
+    Forkid(ForkIdCommand<XLayerChainSpecParser>),
+    /// Print the account and storage changes of a block range
+    StateDiff(StateDiffCommand<XLayerChainSpecParser>),
+    /// Convert the alloc of a genesis file into a state dump for `init-state`
+    GenesisStateDump(GenesisStateDumpCommand<XLayerChainSpecParser>),
+    /// Report the deposits and withdrawals of a block range without a matching bridge event
+    BridgeReconcile(BridgeReconcileCommand<XLayerChainSpecParser>),
+    /// Re-execute a block range and verify the stored receipts, logs and blooms
+    VerifyReceipts(VerifyReceiptsCommand<XLayerChainSpecParser>),
 }
 
 #[tokio::main]
@@ -55,7 +94,7 @@ async fn main() -> ExitCode {
     // Initialize tracing
     let _guard = RethTracer::new().init().expect("Failed to initialize tracing");
 
-    let cli = Cli::parse();
+    let cli = Cli::parse_from(apply_network_defaults(std::env::args_os(), DATADIR_COMMANDS));
 
     match cli.command {
         Commands::Import(cmd) => {
@@ -96,5 +135,78 @@ async fn main() -> ExitCode {
                 }
             }
         }
+        Commands::ValidateGenesisInit(cmd) => {
+            info!(target: "xlayer::validate_genesis", "XLayer Reth Genesis Validation starting");
+
+            match cmd.execute::<OpNode>().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!(target: "xlayer::validate_genesis", "Error: {:#?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::ReplayWal(cmd) => {
+            info!(target: "xlayer::replay_wal", "XLayer Reth WAL Replay starting");
+
+            match cmd.execute().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!(target: "xlayer::replay_wal", "Error: {:#?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::Forkid(cmd) => match cmd.execute() {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(e) => {
+                error!(target: "xlayer::forkid", "Error: {:#?}", e);
+                ExitCode::FAILURE
+            }
+        },
+        Commands::StateDiff(cmd) => {
+            info!(target: "xlayer::state_diff", "XLayer Reth State Diff starting");
+
+            match cmd.execute::<OpNode>().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!(target: "xlayer::state_diff", "Error: {:#?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::GenesisStateDump(cmd) => {
+            info!(target: "xlayer::genesis_state_dump", "XLayer Reth Genesis State Dump starting");
+
+            match cmd.execute() {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!(target: "xlayer::genesis_state_dump", "Error: {:#?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::BridgeReconcile(cmd) => {
+            info!(target: "xlayer::bridge_reconcile", "XLayer Reth Bridge Reconciliation starting");
+
+            match cmd.execute::<OpNode>().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!(target: "xlayer::bridge_reconcile", "Error: {:#?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        Commands::VerifyReceipts(cmd) => {
+            info!(target: "xlayer::verify_receipts", "XLayer Reth Receipts Verification starting");
+
+            match cmd.execute::<OpNode>().await {
+                Ok(_) => ExitCode::SUCCESS,
+                Err(e) => {
+                    error!(target: "xlayer::verify_receipts", "Error: {:#?}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,185 @@
+//! Command that re-submits forwarded transactions which never got included.
+//!
+//! Replicas started with `--xlayer.rpc.tx-wal-dir` log every `eth_sendRawTransaction` submission.
+//! After a sequencer incident, the transactions accepted in the affected period may have been
+//! lost. This command reads the log, skips transactions that have a receipt on the given node and
+//! re-submits the others.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::B256;
+use clap::Parser;
+use eyre::{eyre, Result};
+use jsonrpsee::{core::client::ClientT, http_client::HttpClientBuilder, rpc_params};
+use serde_json::Value;
+use tracing::{info, warn};
+use xlayer_rpc::tx_wal::{read_wal, TxOutcome, TxWalRecord};
+
+/// Re-submits logged transactions that have no receipt.
+#[derive(Debug, Parser)]
+pub struct ReplayWalCommand {
+    /// Directory of the transaction log, as passed to `--xlayer.rpc.tx-wal-dir`
+    #[arg(long = "wal-dir", value_name = "DIR")]
+    wal_dir: PathBuf,
+
+    /// RPC endpoint used to check for receipts and to re-submit transactions
+    #[arg(long = "rpc-url", value_name = "URL")]
+    rpc_url: String,
+
+    /// Only replay transactions submitted within this duration before now, e.g. 2h
+    #[arg(long = "since", value_name = "DURATION", value_parser = humantime::parse_duration)]
+    since: Option<Duration>,
+
+    /// Also replay submissions that were rejected, e.g. while the sequencer was unreachable
+    #[arg(long = "include-rejected", default_value = "false")]
+    include_rejected: bool,
+
+    /// Only list the transactions that would be re-submitted
+    #[arg(long = "dry-run", default_value = "false")]
+    dry_run: bool,
+}
+
+impl ReplayWalCommand {
+    /// Returns the records to replay, oldest first and once per transaction.
+    ///
+    /// Transactions are replayed if a submission was accepted or has no logged outcome, i.e. the
+    /// node stopped while forwarding it.
+    fn candidates(&self, records: Vec<TxWalRecord>, now: u64) -> Vec<TxWalRecord> {
+        let since = self.since.map(|since| now.saturating_sub(since.as_millis() as u64));
+        let records: Vec<_> = records
+            .into_iter()
+            .filter(|record| since.is_none_or(|since| record.timestamp >= since))
+            .collect();
+
+        // Submissions without outcome and whether a submission was accepted, per transaction
+        let mut submissions = HashMap::<_, (usize, bool)>::new();
+        for record in &records {
+            let (pending, accepted) = submissions.entry(record.hash).or_default();
+            match record.outcome {
+                TxOutcome::Submitted => *pending += 1,
+                TxOutcome::Accepted => {
+                    *pending = pending.saturating_sub(1);
+                    *accepted = true;
+                }
+                TxOutcome::Rejected => *pending = pending.saturating_sub(1),
+            }
+        }
+
+        let mut seen = HashSet::new();
+        records
+            .into_iter()
+            .filter(|record| {
+                let (pending, accepted) = submissions[&record.hash];
+                self.include_rejected || accepted || pending > 0
+            })
+            .filter(|record| seen.insert(record.hash))
+            .collect()
+    }
+
+    /// Execute `replay-wal` command
+    pub async fn execute(self) -> Result<()> {
+        let records = read_wal(&self.wal_dir)
+            .map_err(|err| eyre!("failed to read {}: {err}", self.wal_dir.display()))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+        let candidates = self.candidates(records, now);
+        info!(target: "xlayer::replay_wal", "Checking {} logged transactions", candidates.len());
+
+        let client = HttpClientBuilder::default().build(&self.rpc_url)?;
+        let (mut included, mut resubmitted, mut failed) = (0, 0, 0);
+        for record in candidates {
+            let receipt: Option<Value> =
+                client.request("eth_getTransactionReceipt", rpc_params![record.hash]).await?;
+            if receipt.is_some() {
+                included += 1;
+                continue;
+            }
+
+            if self.dry_run {
+                info!(target: "xlayer::replay_wal", tx = %record.hash, timestamp = record.timestamp, "Would re-submit transaction");
+                resubmitted += 1;
+                continue;
+            }
+            match client
+                .request::<B256, _>("eth_sendRawTransaction", rpc_params![&record.raw])
+                .await
+            {
+                Ok(_) => {
+                    info!(target: "xlayer::replay_wal", tx = %record.hash, "Re-submitted transaction");
+                    resubmitted += 1;
+                }
+                Err(err) => {
+                    // E.g. a replacement transaction with the same nonce was included
+                    warn!(target: "xlayer::replay_wal", tx = %record.hash, %err, "Failed to re-submit transaction");
+                    failed += 1;
+                }
+            }
+        }
+
+        info!(
+            target: "xlayer::replay_wal",
+            included,
+            resubmitted,
+            failed,
+            dry_run = self.dry_run,
+            "Replay finished"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{keccak256, Bytes};
+
+    fn command(args: &[&str]) -> ReplayWalCommand {
+        let mut argv =
+            vec!["replay-wal", "--wal-dir", "/wal", "--rpc-url", "http://localhost:8545"];
+        argv.extend_from_slice(args);
+        ReplayWalCommand::parse_from(argv)
+    }
+
+    fn record(nonce: u8, timestamp: u64, outcome: TxOutcome) -> TxWalRecord {
+        let raw = Bytes::from(vec![0x02, nonce]);
+        TxWalRecord { timestamp, hash: keccak256(&raw), raw, outcome, error: None }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let records = vec![
+            record(1, 1_000, TxOutcome::Accepted),
+            record(2, 5_000, TxOutcome::Rejected),
+            record(3, 8_000, TxOutcome::Accepted),
+            // Resubmission by the wallet
+            record(3, 9_000, TxOutcome::Accepted),
+        ];
+
+        let candidates = command(&[]).candidates(records.clone(), 10_000);
+        assert_eq!(candidates, [records[0].clone(), records[2].clone()]);
+
+        let candidates = command(&["--since", "6s"]).candidates(records.clone(), 10_000);
+        assert_eq!(candidates, [records[2].clone()]);
+
+        let candidates = command(&["--include-rejected"]).candidates(records.clone(), 10_000);
+        assert_eq!(candidates.len(), 3);
+    }
+
+    #[test]
+    fn test_candidates_with_submitted_records() {
+        let records = vec![
+            record(1, 1_000, TxOutcome::Submitted),
+            record(1, 1_010, TxOutcome::Accepted),
+            record(2, 2_000, TxOutcome::Submitted),
+            record(2, 2_010, TxOutcome::Rejected),
+            // The node stopped while forwarding the submission
+            record(3, 3_000, TxOutcome::Submitted),
+        ];
+
+        let candidates = command(&[]).candidates(records.clone(), 10_000);
+        assert_eq!(candidates, [records[0].clone(), records[4].clone()]);
+    }
+}
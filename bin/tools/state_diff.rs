@@ -0,0 +1,210 @@
+//! Command that prints the state changes of a block range.
+//!
+//! Incident responders use it to answer "what changed in these blocks" without ad-hoc scripts
+//! against the database. The command walks the account and storage changesets of the range, which
+//! hold the value of every changed account and slot before each block, and compares the oldest
+//! value of each with the state at the end of the range.
+//!
+//! Changesets are pruned on nodes with history pruning, the range must be within the retained
+//! history.
+
+use std::collections::{BTreeMap, HashSet};
+
+use alloy_primitives::{Address, B256, U256};
+use clap::Parser;
+use eyre::{eyre, Result, WrapErr};
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_commands::common::{AccessRights, CliNodeTypes, Environment, EnvironmentArgs};
+use reth_db_api::models::AccountBeforeTx;
+use reth_node_core::version::version_metadata;
+use reth_optimism_chainspec::OpChainSpec;
+use reth_primitives_traits::{Account, StorageEntry};
+use reth_provider::{BlockNumReader, ChangeSetReader, StorageChangeSetReader};
+use reth_storage_api::{AccountReader, StateProvider};
+use tracing::info;
+
+/// Prints the account and storage changes between the start of one block and the end of another.
+#[derive(Debug, Parser)]
+pub struct StateDiffCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    /// First block of the range (inclusive), its changes are included
+    #[arg(long, value_name = "BLOCK")]
+    from: u64,
+
+    /// Last block of the range (inclusive)
+    #[arg(long, value_name = "BLOCK")]
+    to: u64,
+
+    /// Only print changes of these accounts, may be repeated
+    #[arg(long = "address", value_name = "ADDRESS")]
+    addresses: Vec<Address>,
+}
+
+/// Changes of an account over the range, holding the values before the range.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct AccountChange {
+    /// Account before the range, `None` if the account info didn't change. The inner `None` is an
+    /// account created in the range.
+    info: Option<Option<Account>>,
+    /// Changed slots and their values before the range
+    storage: BTreeMap<B256, U256>,
+}
+
+/// Collects the changed accounts and slots of changesets walked in block order, keeping the
+/// oldest value of each. Without `addresses`, changes of every account are kept.
+fn collect_changes(
+    accounts: impl IntoIterator<Item = AccountBeforeTx>,
+    storage: impl IntoIterator<Item = (Address, StorageEntry)>,
+    addresses: &HashSet<Address>,
+) -> BTreeMap<Address, AccountChange> {
+    let included = |address: &Address| addresses.is_empty() || addresses.contains(address);
+    let mut changes = BTreeMap::<Address, AccountChange>::new();
+    for AccountBeforeTx { address, info } in accounts {
+        if included(&address) {
+            changes.entry(address).or_default().info.get_or_insert(info);
+        }
+    }
+    for (address, entry) in storage {
+        if included(&address) {
+            changes.entry(address).or_default().storage.entry(entry.key).or_insert(entry.value);
+        }
+    }
+    changes
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> StateDiffCommand<C> {
+    /// Execute `state-diff` command
+    pub async fn execute<N>(self) -> Result<()>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec>,
+    {
+        info!(target: "reth::cli", "{} ({}) starting", version_metadata().name_client, version_metadata().short_version);
+
+        if self.from > self.to {
+            return Err(eyre!("--from {} is after --to {}", self.from, self.to));
+        }
+
+        let Environment { provider_factory, .. } = self.env.init::<N>(AccessRights::RO)?;
+        let provider = provider_factory.provider()?;
+        let last_block =
+            provider.last_block_number().wrap_err("Failed to get latest block number")?;
+        if self.to > last_block {
+            return Err(eyre!("--to {} is after the last block {last_block}", self.to));
+        }
+
+        let addresses: HashSet<Address> = self.addresses.iter().copied().collect();
+        let mut accounts = Vec::new();
+        let mut storage = Vec::new();
+        for number in self.from..=self.to {
+            accounts.extend(provider.account_block_changeset(number)?);
+            storage.extend(
+                provider
+                    .storage_changeset(number)?
+                    .into_iter()
+                    .map(|(key, entry)| (key.address(), entry)),
+            );
+        }
+        let changes = collect_changes(accounts, storage, &addresses);
+
+        // State at the end of the range
+        let state = provider_factory
+            .history_by_block_number(self.to)
+            .wrap_err_with(|| format!("State at block {} is not available", self.to))?;
+
+        println!("state diff of blocks {} to {}", self.from, self.to);
+        let mut changed = 0;
+        for (address, change) in &changes {
+            let lines = diff_lines(&*state, *address, change)?;
+            if lines.is_empty() {
+                continue;
+            }
+            changed += 1;
+            println!("\naccount {address}");
+            for line in lines {
+                println!("  {line}");
+            }
+        }
+        println!("\n{changed} accounts changed");
+        Ok(())
+    }
+}
+
+/// Returns the changed fields of the account, comparing the values before the range with the
+/// state at its end. Values changed and restored within the range are omitted.
+fn diff_lines(
+    state: &dyn StateProvider,
+    address: Address,
+    change: &AccountChange,
+) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    if let Some(before) = change.info {
+        let after = state.basic_account(&address)?;
+        match (before, after) {
+            (None, Some(_)) => lines.push("created".to_string()),
+            (Some(_), None) => lines.push("destroyed".to_string()),
+            _ => {}
+        }
+        let before = before.unwrap_or_default();
+        let after = after.unwrap_or_default();
+        if before.balance != after.balance {
+            lines.push(format!("balance: {} -> {}", before.balance, after.balance));
+        }
+        if before.nonce != after.nonce {
+            lines.push(format!("nonce: {} -> {}", before.nonce, after.nonce));
+        }
+        if before.bytecode_hash != after.bytecode_hash {
+            lines.push(format!(
+                "code hash: {} -> {}",
+                before.get_bytecode_hash(),
+                after.get_bytecode_hash()
+            ));
+        }
+    }
+    for (slot, before) in &change.storage {
+        let after = state.storage(address, *slot)?.unwrap_or_default();
+        if *before != after {
+            lines.push(format!("storage {slot}: {before:#x} -> {after:#x}"));
+        }
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(nonce: u64) -> Account {
+        Account { nonce, balance: U256::from(nonce), bytecode_hash: None }
+    }
+
+    fn slot(key: u8, value: u64) -> StorageEntry {
+        StorageEntry { key: B256::with_last_byte(key), value: U256::from(value) }
+    }
+
+    #[test]
+    fn test_collect_changes_keeps_oldest_values() {
+        let alice = Address::with_last_byte(1);
+        let bob = Address::with_last_byte(2);
+        let accounts = [
+            AccountBeforeTx { address: alice, info: Some(account(1)) },
+            AccountBeforeTx { address: bob, info: None },
+            AccountBeforeTx { address: alice, info: Some(account(2)) },
+        ];
+        let storage = [(alice, slot(1, 10)), (alice, slot(1, 11)), (bob, slot(2, 20))];
+
+        let changes = collect_changes(accounts.clone(), storage, &HashSet::new());
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[&alice].info, Some(Some(account(1))));
+        assert_eq!(
+            changes[&alice].storage,
+            BTreeMap::from([(B256::with_last_byte(1), U256::from(10))])
+        );
+        assert_eq!(changes[&bob].info, Some(None));
+
+        let changes = collect_changes(accounts, [(bob, slot(2, 20))], &HashSet::from([bob]));
+        assert_eq!(changes.keys().collect::<Vec<_>>(), [&bob]);
+        assert_eq!(changes[&bob].storage.len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+//! Command that validates the genesis initialization of a data directory.
+//!
+//! X Layer chains start from a non-zero genesis block (`legacyXLayerBlock`), the blocks before it
+//! are synthetic placeholders written by the genesis initialization. This command replays the
+//! invariants that the initialization must have established on a freshly initialized data
+//! directory and reports every discrepancy without modifying the database:
+//! - The canonical hash at the genesis number matches the chain spec genesis hash
+//! - The genesis header is stored and matches the chain spec genesis header
+//! - The headers of the pre-genesis range `0..genesis` are present, range by range
+//! - The `HeaderNumbers` mapping exists for blocks `0`, `genesis - 1` and `genesis` and points
+//!   at their numbers
+//! - Every stage checkpoint equals the genesis number
+
+use alloy_consensus::BlockHeader;
+use alloy_primitives::B256;
+use clap::Parser;
+use eyre::{eyre, Result};
+use reth_chainspec::{ChainSpecProvider, EthChainSpec};
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_commands::common::{AccessRights, CliNodeTypes, Environment, EnvironmentArgs};
+use reth_node_core::version::version_metadata;
+use reth_optimism_chainspec::OpChainSpec;
+use reth_provider::{
+    errors::provider::ProviderResult, BlockHashReader, BlockNumReader, HeaderProvider,
+    StageCheckpointReader,
+};
+use reth_stages_types::StageId;
+use tracing::{error, info};
+
+/// Blocks of the pre-genesis ranges whose headers are counted at once, the block range of a
+/// static file.
+const HEADER_RANGE_SIZE: u64 = 500_000;
+
+/// Validates the genesis initialization of a data directory without modifying it.
+#[derive(Debug, Parser)]
+pub struct ValidateGenesisInitCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> ValidateGenesisInitCommand<C> {
+    /// Execute `validate-genesis-init` command
+    pub async fn execute<N>(self) -> Result<()>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec>,
+    {
+        info!(target: "reth::cli", "{} ({}) starting", version_metadata().name_client, version_metadata().short_version);
+
+        let Environment { provider_factory, .. } = self.env.init::<N>(AccessRights::RO)?;
+        let provider = provider_factory.provider()?;
+
+        let chain_spec = provider.chain_spec();
+        let genesis_number = chain_spec.genesis_header().number();
+        let genesis_hash = chain_spec.genesis_hash();
+        info!(
+            target: "reth::cli",
+            "Validating genesis initialization for block {} ({})",
+            genesis_number,
+            genesis_hash
+        );
+
+        let discrepancies = validate_genesis_init(&provider, genesis_number, genesis_hash)?;
+        if discrepancies.is_empty() {
+            info!(target: "reth::cli", "Genesis initialization is valid");
+            return Ok(());
+        }
+
+        for discrepancy in &discrepancies {
+            error!(target: "reth::cli", "Genesis initialization discrepancy: {}", discrepancy);
+        }
+        Err(eyre!("Found {} genesis initialization discrepancies", discrepancies.len()))
+    }
+}
+
+/// Returns the discrepancies of the genesis initialization of a database.
+fn validate_genesis_init<P>(
+    provider: &P,
+    genesis_number: u64,
+    genesis_hash: B256,
+) -> ProviderResult<Vec<String>>
+where
+    P: BlockHashReader + BlockNumReader + HeaderProvider + StageCheckpointReader,
+{
+    let mut discrepancies = Vec::new();
+
+    match provider.block_hash(genesis_number)? {
+        Some(hash) if hash == genesis_hash => {}
+        Some(hash) => discrepancies.push(format!(
+            "canonical hash at genesis block {genesis_number} is {hash}, expected {genesis_hash}"
+        )),
+        None => discrepancies
+            .push(format!("no canonical hash stored for genesis block {genesis_number}")),
+    }
+
+    match provider.sealed_header(genesis_number)? {
+        Some(header) if header.hash() == genesis_hash => {}
+        Some(header) => discrepancies.push(format!(
+            "stored genesis header hashes to {}, expected {genesis_hash}",
+            header.hash()
+        )),
+        None => discrepancies.push(format!("genesis header {genesis_number} not found")),
+    }
+
+    // The synthetic headers below the genesis block, counted one static file range at a time
+    let mut headers = 0;
+    for start in (0..genesis_number).step_by(HEADER_RANGE_SIZE as usize) {
+        let end = (start + HEADER_RANGE_SIZE).min(genesis_number);
+        let count = provider.headers_range(start..end)?.len() as u64;
+        if count != end - start {
+            discrepancies.push(format!(
+                "pre-genesis range {start}..={} holds {count} of {} headers",
+                end - 1,
+                end - start
+            ));
+        }
+        headers += count;
+    }
+    if headers != genesis_number {
+        discrepancies.push(format!(
+            "found {headers} headers below genesis block {genesis_number}, expected {genesis_number}"
+        ));
+    }
+
+    // The boundaries of the pre-genesis range and the genesis block
+    let mut boundaries = vec![0, genesis_number.saturating_sub(1), genesis_number];
+    boundaries.dedup();
+    for number in boundaries {
+        let Some(hash) = provider.block_hash(number)? else {
+            discrepancies.push(format!("no canonical hash stored for boundary block {number}"));
+            continue;
+        };
+        match provider.block_number(hash)? {
+            Some(mapped) if mapped == number => {}
+            Some(mapped) => discrepancies
+                .push(format!("HeaderNumbers maps the hash {hash} of block {number} to {mapped}")),
+            None => discrepancies
+                .push(format!("HeaderNumbers mapping missing for block {number} ({hash})")),
+        }
+    }
+
+    for stage in StageId::ALL {
+        match provider.get_stage_checkpoint(stage)? {
+            Some(checkpoint) if checkpoint.block_number == genesis_number => {}
+            Some(checkpoint) => discrepancies.push(format!(
+                "stage {stage} checkpoint is {}, expected genesis block {genesis_number}",
+                checkpoint.block_number
+            )),
+            None => discrepancies.push(format!("stage {stage} has no checkpoint")),
+        }
+    }
+
+    Ok(discrepancies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_genesis::Genesis;
+    use reth_db_common::init::init_genesis;
+    use reth_optimism_node::OpNode;
+    use reth_provider::test_utils::create_test_provider_factory_with_node_types;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_validate_non_zero_genesis() {
+        let genesis: Genesis = serde_json::from_value(serde_json::json!({
+            "config": { "chainId": 196, "londonBlock": 0 },
+            "number": "0x10",
+            "gasLimit": "0x1000000",
+            "difficulty": "0x0",
+            "alloc": {}
+        }))
+        .unwrap();
+        let chain_spec = Arc::new(OpChainSpec::from(genesis));
+        let genesis_hash = chain_spec.genesis_hash();
+        let factory = create_test_provider_factory_with_node_types::<OpNode>(chain_spec);
+        init_genesis(&factory).unwrap();
+
+        let provider = factory.provider().unwrap();
+        assert_eq!(
+            validate_genesis_init(&provider, 16, genesis_hash).unwrap(),
+            Vec::<String>::new()
+        );
+
+        // A datadir validated against another genesis reports the mismatches
+        let discrepancies = validate_genesis_init(&provider, 17, genesis_hash).unwrap();
+        assert!(discrepancies.iter().any(|d| d.contains("no canonical hash stored for genesis")));
+        assert!(discrepancies.iter().any(|d| d.contains("expected genesis block 17")));
+    }
+}
@@ -0,0 +1,497 @@
+//! Command that re-executes a block range and verifies the stored receipts.
+//!
+//! Stored receipts are only checked against their header when a block is first executed. After a
+//! `--no-exec` import, which inserts blocks without executing them, or on suspected storage
+//! corruption, the command re-executes every block of the range on the state of its parent and
+//! compares the recomputed receipts with the stored ones: their number, and per transaction the
+//! status, the cumulative gas used and the logs. The receipts root and logs bloom of the header
+//! are checked against the recomputed receipts as well.
+//!
+//! Blocks are executed in parallel, one database transaction per chunk of the range. Executing a
+//! block needs the state of its parent, so the range must be within the history retained by the
+//! node.
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    ops::RangeInclusive,
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use alloy_consensus::{BlockHeader as _, TxReceipt};
+use alloy_primitives::{logs_bloom, Bloom, Log, B256};
+use clap::{Parser, ValueEnum};
+use eyre::{eyre, Result, WrapErr};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use reth_chainspec::{ChainSpecProvider, EthChainSpec};
+use reth_cli::chainspec::ChainSpecParser;
+use reth_cli_commands::common::{AccessRights, CliNodeTypes, Environment, EnvironmentArgs};
+use reth_evm::{execute::Executor as _, ConfigureEvm as _};
+use reth_node_core::version::version_metadata;
+use reth_optimism_chainspec::OpChainSpec;
+use reth_optimism_consensus::calculate_receipt_root_no_memo_optimism;
+use reth_optimism_evm::OpEvmConfig;
+use reth_optimism_primitives::{OpPrimitives, OpReceipt};
+use reth_provider::{BlockNumReader, ReceiptProvider};
+use reth_revm::database::StateProviderDatabase;
+use reth_storage_api::{BlockReader, TransactionVariant};
+use serde::Serialize;
+use tracing::info;
+
+/// Number of verified blocks between progress logs.
+const PROGRESS_INTERVAL: u64 = 10_000;
+
+/// Re-executes a block range and reports the stored receipts diverging from the recomputed ones.
+#[derive(Debug, Parser)]
+pub struct VerifyReceiptsCommand<C: ChainSpecParser> {
+    #[command(flatten)]
+    env: EnvironmentArgs<C>,
+
+    /// First block of the range (inclusive), must be after the genesis block
+    #[arg(long, value_name = "BLOCK")]
+    from: u64,
+
+    /// Last block of the range (inclusive)
+    #[arg(long, value_name = "BLOCK")]
+    to: u64,
+
+    /// Number of blocks verified in parallel, defaults to the number of CPUs
+    #[arg(long, value_name = "JOBS")]
+    jobs: Option<usize>,
+
+    /// Number of consecutive blocks verified by a job at a time
+    #[arg(long, value_name = "BLOCKS", default_value = "1000")]
+    chunk_size: u64,
+
+    /// Format of the report
+    #[arg(long, value_enum, default_value = "csv")]
+    format: ReportFormat,
+
+    /// The path to write the report, stdout if not set
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+/// Format of the verification report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ReportFormat {
+    Csv,
+    Json,
+}
+
+/// Value that differs between the stored and the recomputed receipts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum DivergentField {
+    /// The block failed to execute
+    Execution,
+    ReceiptCount,
+    Status,
+    CumulativeGasUsed,
+    Logs,
+    /// Fields of the receipt besides the status, gas and logs, e.g. the deposit nonce
+    Receipt,
+    LogsBloom,
+    ReceiptsRoot,
+}
+
+impl DivergentField {
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Execution => "execution",
+            Self::ReceiptCount => "receipt_count",
+            Self::Status => "status",
+            Self::CumulativeGasUsed => "cumulative_gas_used",
+            Self::Logs => "logs",
+            Self::Receipt => "receipt",
+            Self::LogsBloom => "logs_bloom",
+            Self::ReceiptsRoot => "receipts_root",
+        }
+    }
+}
+
+/// Divergence between a stored value and the value recomputed by re-execution.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Divergence {
+    block: u64,
+    /// Index of the transaction, `None` for block level values
+    tx_index: Option<usize>,
+    field: DivergentField,
+    stored: String,
+    recomputed: String,
+}
+
+impl Divergence {
+    fn new(
+        block: u64,
+        tx_index: Option<usize>,
+        field: DivergentField,
+        stored: impl ToString,
+        recomputed: impl ToString,
+    ) -> Self {
+        Self {
+            block,
+            tx_index,
+            field,
+            stored: stored.to_string(),
+            recomputed: recomputed.to_string(),
+        }
+    }
+}
+
+/// Summary of the verification, the JSON report.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Report {
+    from: u64,
+    to: u64,
+    divergent_blocks: usize,
+    divergences: Vec<Divergence>,
+}
+
+impl<C: ChainSpecParser<ChainSpec = OpChainSpec>> VerifyReceiptsCommand<C> {
+    /// Execute `verify-receipts` command
+    pub async fn execute<N>(self) -> Result<()>
+    where
+        N: CliNodeTypes<ChainSpec = C::ChainSpec, Primitives = OpPrimitives>,
+    {
+        info!(target: "reth::cli", "{} ({}) starting", version_metadata().name_client, version_metadata().short_version);
+
+        if self.from > self.to {
+            return Err(eyre!("--from {} is after --to {}", self.from, self.to));
+        }
+        if self.chunk_size == 0 {
+            return Err(eyre!("--chunk-size must be greater than 0"));
+        }
+
+        let Environment { provider_factory, .. } = self.env.init::<N>(AccessRights::RO)?;
+        let chain_spec = provider_factory.chain_spec();
+        let provider = provider_factory.provider()?;
+        let genesis_block = chain_spec.genesis_header().number();
+        if self.from <= genesis_block {
+            return Err(eyre!(
+                "--from {} must be after the genesis block {genesis_block}",
+                self.from
+            ));
+        }
+        let last_block =
+            provider.last_block_number().wrap_err("Failed to get latest block number")?;
+        if self.to > last_block {
+            return Err(eyre!("--to {} is after the last block {last_block}", self.to));
+        }
+        drop(provider);
+
+        let evm_config = OpEvmConfig::optimism(chain_spec.clone());
+        let mut pool = rayon::ThreadPoolBuilder::new();
+        if let Some(jobs) = self.jobs {
+            pool = pool.num_threads(jobs);
+        }
+        let pool = pool.build()?;
+
+        let started_at = Instant::now();
+        let total_blocks = self.to - self.from + 1;
+        let verified = AtomicU64::new(0);
+        let verify_chunk = |range: RangeInclusive<u64>| -> Result<Vec<Divergence>> {
+            let provider = provider_factory.provider()?;
+            let mut divergences = Vec::new();
+            for number in range {
+                let block = provider
+                    .recovered_block(number.into(), TransactionVariant::WithHash)?
+                    .ok_or_else(|| eyre!("Block {number} not found"))?;
+                let stored = provider
+                    .receipts_by_block(number.into())?
+                    .ok_or_else(|| eyre!("Receipts of block {number} not found"))?;
+                let state = provider_factory
+                    .history_by_block_number(number - 1)
+                    .wrap_err_with(|| format!("State at block {} is not available", number - 1))?;
+
+                match evm_config.executor(StateProviderDatabase::new(&state)).execute(&block) {
+                    Ok(output) => {
+                        let recomputed = &output.result.receipts;
+                        divergences.extend(compare_receipts(number, &stored, recomputed));
+                        let receipts_root = calculate_receipt_root_no_memo_optimism(
+                            recomputed,
+                            &chain_spec,
+                            block.timestamp(),
+                        );
+                        divergences.extend(compare_roots(
+                            number,
+                            (block.receipts_root(), block.logs_bloom()),
+                            (receipts_root, logs_bloom(recomputed.iter().flat_map(|r| r.logs()))),
+                        ));
+                    }
+                    Err(err) => divergences.push(Divergence::new(
+                        number,
+                        None,
+                        DivergentField::Execution,
+                        "",
+                        err,
+                    )),
+                }
+
+                let verified = verified.fetch_add(1, Ordering::Relaxed) + 1;
+                if verified.is_multiple_of(PROGRESS_INTERVAL) {
+                    let progress = (verified as f64 / total_blocks as f64) * 100.0;
+                    info!(target: "xlayer::verify_receipts", "Verified {verified} blocks ({progress:.2}%)");
+                }
+            }
+            Ok(divergences)
+        };
+
+        let chunks = chunks(self.from..=self.to, self.chunk_size);
+        let divergences =
+            pool.install(|| chunks.into_par_iter().map(verify_chunk).collect::<Result<Vec<_>>>())?;
+        let divergences: Vec<Divergence> = divergences.into_iter().flatten().collect();
+
+        let mut divergent_blocks: Vec<u64> = divergences.iter().map(|d| d.block).collect();
+        divergent_blocks.dedup();
+        let report = Report {
+            from: self.from,
+            to: self.to,
+            divergent_blocks: divergent_blocks.len(),
+            divergences,
+        };
+        info!(
+            target: "xlayer::verify_receipts",
+            from = self.from,
+            to = self.to,
+            blocks = total_blocks,
+            divergent_blocks = report.divergent_blocks,
+            divergences = report.divergences.len(),
+            elapsed = ?started_at.elapsed(),
+            "Verified receipts"
+        );
+
+        match &self.output {
+            Some(path) => {
+                let file = File::create(path)
+                    .wrap_err_with(|| format!("Failed to create {}", path.display()))?;
+                write_report(io::BufWriter::new(file), &report, self.format)?;
+            }
+            None => write_report(io::stdout().lock(), &report, self.format)?,
+        }
+
+        if report.divergent_blocks > 0 {
+            return Err(eyre!(
+                "{} of {total_blocks} blocks diverge from re-execution",
+                report.divergent_blocks
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Splits the range into consecutive chunks of up to `size` blocks.
+fn chunks(range: RangeInclusive<u64>, size: u64) -> Vec<RangeInclusive<u64>> {
+    let end = *range.end();
+    range
+        .step_by(size as usize)
+        .map(|start| start..=start.saturating_add(size - 1).min(end))
+        .collect()
+}
+
+/// Returns the divergences between the stored and the recomputed receipts of a block.
+fn compare_receipts(block: u64, stored: &[OpReceipt], recomputed: &[OpReceipt]) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    if stored.len() != recomputed.len() {
+        divergences.push(Divergence::new(
+            block,
+            None,
+            DivergentField::ReceiptCount,
+            stored.len(),
+            recomputed.len(),
+        ));
+    }
+
+    for (index, (stored, recomputed)) in stored.iter().zip(recomputed).enumerate() {
+        if stored == recomputed {
+            continue;
+        }
+        let divergent = |field, stored: String, recomputed: String| {
+            Divergence::new(block, Some(index), field, stored, recomputed)
+        };
+        let count = divergences.len();
+        if stored.status() != recomputed.status() {
+            divergences.push(divergent(
+                DivergentField::Status,
+                stored.status().to_string(),
+                recomputed.status().to_string(),
+            ));
+        }
+        if stored.cumulative_gas_used() != recomputed.cumulative_gas_used() {
+            divergences.push(divergent(
+                DivergentField::CumulativeGasUsed,
+                stored.cumulative_gas_used().to_string(),
+                recomputed.cumulative_gas_used().to_string(),
+            ));
+        }
+        if let Some((stored, recomputed)) = first_divergent_log(stored.logs(), recomputed.logs()) {
+            divergences.push(divergent(DivergentField::Logs, stored, recomputed));
+        }
+        if divergences.len() == count {
+            divergences.push(divergent(
+                DivergentField::Receipt,
+                format!("{stored:?}"),
+                format!("{recomputed:?}"),
+            ));
+        }
+    }
+    divergences
+}
+
+/// Returns the first differing log of two receipts, `missing` where one of them has fewer logs.
+fn first_divergent_log(stored: &[Log], recomputed: &[Log]) -> Option<(String, String)> {
+    let describe = |log: Option<&Log>| {
+        log.map_or_else(
+            || "missing".to_string(),
+            |log| serde_json::to_string(log).unwrap_or_default(),
+        )
+    };
+    (0..stored.len().max(recomputed.len()))
+        .find(|index| stored.get(*index) != recomputed.get(*index))
+        .map(|index| (describe(stored.get(index)), describe(recomputed.get(index))))
+}
+
+/// Returns the divergences between the receipts root and logs bloom of the header and the ones of
+/// the recomputed receipts.
+fn compare_roots(
+    block: u64,
+    (stored_root, stored_bloom): (B256, Bloom),
+    (recomputed_root, recomputed_bloom): (B256, Bloom),
+) -> Vec<Divergence> {
+    let mut divergences = Vec::new();
+    if stored_root != recomputed_root {
+        divergences.push(Divergence::new(
+            block,
+            None,
+            DivergentField::ReceiptsRoot,
+            stored_root,
+            recomputed_root,
+        ));
+    }
+    if stored_bloom != recomputed_bloom {
+        divergences.push(Divergence::new(
+            block,
+            None,
+            DivergentField::LogsBloom,
+            stored_bloom,
+            recomputed_bloom,
+        ));
+    }
+    divergences
+}
+
+/// Writes the divergences as CSV with a header line, or the report as JSON.
+fn write_report(mut writer: impl Write, report: &Report, format: ReportFormat) -> Result<()> {
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, report)?;
+            writeln!(writer)?;
+        }
+        ReportFormat::Csv => {
+            writeln!(writer, "block,tx_index,field,stored,recomputed")?;
+            for divergence in &report.divergences {
+                writeln!(
+                    writer,
+                    "{},{},{},{},{}",
+                    divergence.block,
+                    divergence.tx_index.map(|index| index.to_string()).unwrap_or_default(),
+                    divergence.field.as_str(),
+                    csv_field(&divergence.stored),
+                    csv_field(&divergence.recomputed),
+                )?;
+            }
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Quotes a CSV field containing separators or quotes.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{Eip658Value, Receipt};
+    use alloy_primitives::{Address, Bytes};
+
+    fn receipt(success: bool, cumulative_gas_used: u64, logs: usize) -> OpReceipt {
+        let log = Log::new_unchecked(Address::with_last_byte(1), vec![], Bytes::new());
+        OpReceipt::Eip1559(Receipt {
+            status: Eip658Value::Eip658(success),
+            cumulative_gas_used,
+            logs: vec![log; logs],
+        })
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(chunks(1..=10, 4), [1..=4, 5..=8, 9..=10]);
+        assert_eq!(chunks(5..=5, 1000), [5..=5]);
+    }
+
+    #[test]
+    fn test_compare_receipts() {
+        let stored = [receipt(true, 21_000, 1), receipt(true, 42_000, 0)];
+        assert!(compare_receipts(7, &stored, &stored).is_empty());
+
+        let recomputed = [receipt(true, 21_000, 2), receipt(false, 50_000, 0)];
+        let divergences = compare_receipts(7, &stored, &recomputed);
+        let fields: Vec<_> = divergences.iter().map(|d| (d.tx_index, d.field)).collect();
+        assert_eq!(
+            fields,
+            [
+                (Some(0), DivergentField::Logs),
+                (Some(1), DivergentField::Status),
+                (Some(1), DivergentField::CumulativeGasUsed),
+            ]
+        );
+        assert_eq!(divergences[0].stored, "missing");
+        assert_eq!(divergences[2].recomputed, "50000");
+
+        let divergences = compare_receipts(7, &stored, &stored[..1]);
+        assert_eq!(divergences, [Divergence::new(7, None, DivergentField::ReceiptCount, 2, 1)]);
+    }
+
+    #[test]
+    fn test_compare_roots() {
+        let root = B256::with_last_byte(1);
+        assert!(compare_roots(7, (root, Bloom::ZERO), (root, Bloom::ZERO)).is_empty());
+
+        let divergences = compare_roots(7, (root, Bloom::ZERO), (B256::ZERO, Bloom::ZERO));
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].field, DivergentField::ReceiptsRoot);
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let report = Report {
+            from: 1,
+            to: 10,
+            divergent_blocks: 1,
+            divergences: vec![Divergence::new(
+                7,
+                Some(0),
+                DivergentField::Logs,
+                r#"{"address":"0x01"}"#,
+                "missing",
+            )],
+        };
+        let mut csv = Vec::new();
+        write_report(&mut csv, &report, ReportFormat::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "block,tx_index,field,stored,recomputed\n7,0,logs,\"{\"\"address\"\":\"\"0x01\"\"}\",missing\n"
+        );
+    }
+}
@@ -1,5 +1,7 @@
 pub use op::{BuilderArgs, FlashblocksArgs};
+pub use validation::BuilderArgsError;
 use reth_optimism_cli::chainspec::OpChainSpecParser;
 pub type Cli = reth_optimism_cli::Cli<OpChainSpecParser, BuilderArgs>;
 
 mod op;
+mod validation;
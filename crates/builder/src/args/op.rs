@@ -25,6 +25,31 @@ pub struct BuilderArgs {
     #[arg(long = "builder.max_gas_per_txn")]
     pub max_gas_per_txn: Option<u64>,
 
+    /// Bridge contracts whose calls are selected ahead of the pool ordering
+    #[arg(long = "builder.priority-bridge-contracts", value_delimiter = ',')]
+    pub priority_bridge_contracts: Vec<Address>,
+
+    /// Gas limit of bridge transactions selected ahead of the pool ordering per block
+    #[arg(long = "builder.priority-bridge-gas")]
+    pub priority_bridge_gas: Option<u64>,
+
+    /// Sequencer fee collection senders whose transactions are selected ahead of the pool
+    /// ordering
+    #[arg(long = "builder.priority-fee-collectors", value_delimiter = ',')]
+    pub priority_fee_collectors: Vec<Address>,
+
+    /// Gas limit of fee collection transactions selected ahead of the pool ordering per block
+    #[arg(long = "builder.priority-fee-collection-gas")]
+    pub priority_fee_collection_gas: Option<u64>,
+
+    /// Operator allowlisted senders whose transactions are selected ahead of the pool ordering
+    #[arg(long = "builder.priority-allowlist", value_delimiter = ',')]
+    pub priority_allowlist: Vec<Address>,
+
+    /// Gas limit of allowlisted transactions selected ahead of the pool ordering per block
+    #[arg(long = "builder.priority-allowlist-gas")]
+    pub priority_allowlist_gas: Option<u64>,
+
     /// Signals whether to log pool transaction events
     #[arg(long = "builder.log-pool-transactions", default_value = "false")]
     pub log_pool_transactions: bool,
@@ -160,6 +185,20 @@ pub struct FlashblocksArgs {
         default_value = "false"
     )]
     pub replay_from_persistence_file: bool,
+
+    /// Re-execute every canonical block with the standard block executor and compare its state
+    /// and receipts roots. Debug only, doubles the execution load of the node
+    #[arg(
+        long = "flashblocks.validate-canonical-blocks",
+        env = "FLASHBLOCKS_VALIDATE_CANONICAL_BLOCKS",
+        default_value = "false"
+    )]
+    pub validate_canonical_blocks: bool,
+
+    /// Key signing a preconfirmation of every transaction of a published flashblock, carried in
+    /// the flashblock message and relayed by replicas
+    #[arg(long = "flashblocks.preconf-secret-key", env = "FLASHBLOCKS_PRECONF_SECRET_KEY")]
+    pub preconf_signer: Option<Signer>,
 }
 
 impl Default for FlashblocksArgs {
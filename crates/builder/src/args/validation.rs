@@ -0,0 +1,174 @@
+//! Startup validation of the builder arguments.
+//!
+//! Misconfigured flashblocks arguments otherwise surface one at a time, either as a terse parse
+//! error when the builder config is created or as a builder that silently produces no
+//! flashblocks. Every argument is checked up front and all issues are reported together, each
+//! naming the flag to change.
+
+use std::net::IpAddr;
+
+use url::Url;
+
+use super::BuilderArgs;
+
+/// Issues found in the builder arguments.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("invalid builder configuration:\n  - {}", .0.join("\n  - "))]
+pub struct BuilderArgsError(pub Vec<String>);
+
+impl BuilderArgs {
+    /// Validates the builder arguments, along with the flashblocks websocket source of replicas
+    /// if any.
+    pub fn validate(&self, flashblocks_url: Option<&Url>) -> Result<(), BuilderArgsError> {
+        let mut issues = Vec::new();
+
+        if self.chain_block_time == 0 {
+            issues.push("--rollup.chain-block-time must be greater than 0".to_string());
+        }
+        if self.max_gas_per_txn == Some(0) {
+            issues.push(
+                "--builder.max_gas_per_txn must be greater than 0, omit it to disable the limit"
+                    .to_string(),
+            );
+        }
+        if self.flashblocks.enabled {
+            self.validate_flashblocks(&mut issues);
+        }
+
+        if let Some(url) = flashblocks_url
+            && !matches!(url.scheme(), "ws" | "wss")
+        {
+            issues
+                .push(format!("--flashblocks-url `{url}` must be a ws:// or wss:// websocket URL"));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(BuilderArgsError(issues))
+        }
+    }
+
+    fn validate_flashblocks(&self, issues: &mut Vec<String>) {
+        let flashblocks = &self.flashblocks;
+        let interval = flashblocks.flashblocks_block_time;
+
+        if flashblocks.flashblocks_addr.parse::<IpAddr>().is_err() {
+            issues.push(format!(
+                "--flashblocks.addr `{}` must be an IP address, e.g. 0.0.0.0 to listen on all interfaces",
+                flashblocks.flashblocks_addr
+            ));
+        }
+
+        if interval == 0 {
+            issues.push("--flashblocks.block-time must be greater than 0".to_string());
+        } else {
+            if interval > self.chain_block_time {
+                issues.push(format!(
+                    "--flashblocks.block-time ({interval}ms) exceeds --rollup.chain-block-time ({}ms), no flashblock would be built within a block",
+                    self.chain_block_time
+                ));
+            }
+            if flashblocks.flashblocks_end_buffer_ms >= interval {
+                issues.push(format!(
+                    "--flashblocks.end-buffer-ms ({}ms) must be shorter than --flashblocks.block-time ({interval}ms)",
+                    flashblocks.flashblocks_end_buffer_ms
+                ));
+            }
+            if flashblocks.flashblocks_send_offset_ms.unsigned_abs() >= interval {
+                issues.push(format!(
+                    "--flashblocks.send-offset-ms ({}ms) must be shorter than --flashblocks.block-time ({interval}ms)",
+                    flashblocks.flashblocks_send_offset_ms
+                ));
+            }
+        }
+
+        if flashblocks.ws_subscriber_limit == Some(0) {
+            issues.push(
+                "--flashblocks.ws-subscriber-limit must be greater than 0, no client could subscribe"
+                    .to_string(),
+            );
+        }
+
+        let p2p = &flashblocks.p2p;
+        if p2p.p2p_enabled && p2p.p2p_port == flashblocks.flashblocks_port {
+            issues.push(format!(
+                "--flashblocks.p2p_port ({}) must differ from --flashblocks.port",
+                p2p.p2p_port
+            ));
+        }
+        if !p2p.p2p_enabled && (p2p.p2p_send_full_payload || p2p.p2p_process_full_payload) {
+            issues.push(
+                "--flashblocks.p2p_send_full_payload and --flashblocks.p2p_process_full_payload require --flashblocks.p2p_enabled"
+                    .to_string(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flashblocks_args() -> BuilderArgs {
+        let mut args = BuilderArgs::default();
+        args.flashblocks.enabled = true;
+        args
+    }
+
+    #[test]
+    fn test_default_args_are_valid() {
+        assert!(BuilderArgs::default().validate(None).is_ok());
+        assert!(flashblocks_args().validate(None).is_ok());
+
+        let url = Url::parse("wss://sequencer:1111").unwrap();
+        assert!(BuilderArgs::default().validate(Some(&url)).is_ok());
+    }
+
+    #[test]
+    fn test_all_issues_are_reported() {
+        let mut args = flashblocks_args();
+        args.flashblocks.flashblocks_addr = "localhost".to_string();
+        args.flashblocks.flashblocks_block_time = 2000;
+        args.flashblocks.flashblocks_end_buffer_ms = 2000;
+        args.flashblocks.ws_subscriber_limit = Some(0);
+        let url = Url::parse("http://sequencer:1111").unwrap();
+
+        let err = args.validate(Some(&url)).unwrap_err();
+        assert_eq!(err.0.len(), 5, "{err}");
+        let message = err.to_string();
+        for flag in [
+            "--flashblocks.addr",
+            "--flashblocks.block-time (2000ms) exceeds",
+            "--flashblocks.end-buffer-ms",
+            "--flashblocks.ws-subscriber-limit",
+            "--flashblocks-url",
+        ] {
+            assert!(message.contains(flag), "missing {flag} in {message}");
+        }
+    }
+
+    #[test]
+    fn test_flashblocks_args_ignored_when_disabled() {
+        let mut args = BuilderArgs::default();
+        args.flashblocks.flashblocks_block_time = 0;
+        assert!(args.validate(None).is_ok());
+
+        args.flashblocks.enabled = true;
+        assert_eq!(
+            args.validate(None).unwrap_err().0,
+            ["--flashblocks.block-time must be greater than 0"]
+        );
+    }
+
+    #[test]
+    fn test_p2p_args() {
+        let mut args = flashblocks_args();
+        args.flashblocks.p2p.p2p_send_full_payload = true;
+        assert!(args.validate(None).unwrap_err().0[0].contains("require --flashblocks.p2p_enabled"));
+
+        args.flashblocks.p2p.p2p_enabled = true;
+        args.flashblocks.p2p.p2p_port = args.flashblocks.flashblocks_port;
+        assert!(args.validate(None).unwrap_err().0[0].contains("must differ"));
+    }
+}
@@ -4,6 +4,8 @@ use alloy_primitives::{Address, TxHash};
 use reth_payload_util::PayloadTransactions;
 use reth_transaction_pool::{PoolTransaction, ValidPoolTransaction};
 
+use crate::flashblocks::priority_lanes::{PriorityLaneQueue, PriorityLanes};
+
 pub(super) struct BestFlashblocksTxs<T, I>
 where
     T: PoolTransaction,
@@ -14,6 +16,8 @@ where
     // Transactions that were already commited to the state. Using them again would cause NonceTooLow
     // so we skip them
     commited_transactions: HashSet<TxHash>,
+    // Transactions selected ahead of the pool ordering
+    priority_lanes: PriorityLaneQueue<T>,
 }
 
 impl<T, I> BestFlashblocksTxs<T, I>
@@ -22,7 +26,18 @@ where
     I: Iterator<Item = Arc<ValidPoolTransaction<T>>>,
 {
     pub(super) fn new(inner: reth_payload_util::BestPayloadTransactions<T, I>) -> Self {
-        Self { inner, current_flashblock_number: 0, commited_transactions: Default::default() }
+        Self {
+            inner,
+            current_flashblock_number: 0,
+            commited_transactions: Default::default(),
+            priority_lanes: PriorityLaneQueue::new(PriorityLanes::default()),
+        }
+    }
+
+    /// Selects transactions of the given lanes ahead of the pool ordering
+    pub(super) fn with_priority_lanes(mut self, lanes: PriorityLanes) -> Self {
+        self.priority_lanes = PriorityLaneQueue::new(lanes);
+        self
     }
 
     /// Replaces current iterator with new one. We use it on new flashblock building, to refresh
@@ -36,6 +51,16 @@ where
         self.current_flashblock_number = current_flashblock_number;
     }
 
+    /// Replaces the priority lane transactions for the next flashblock. `candidates` are the
+    /// pending transactions of the lane senders.
+    pub(super) fn refresh_priority_lanes(
+        &mut self,
+        candidates: Vec<Arc<ValidPoolTransaction<T>>>,
+        base_fee: u64,
+    ) {
+        self.priority_lanes.refresh(candidates, &self.commited_transactions, base_fee);
+    }
+
     /// Remove transaction from next iteration and it already in the state
     pub(super) fn mark_commited(&mut self, txs: Vec<TxHash>) {
         self.commited_transactions.extend(txs);
@@ -50,10 +75,15 @@ where
     type Transaction = T;
 
     fn next(&mut self, ctx: ()) -> Option<Self::Transaction> {
+        if let Some(tx) = self.priority_lanes.next() {
+            return Some(tx.transaction.clone());
+        }
         loop {
             let tx = self.inner.next(ctx)?;
-            // Skip transaction we already included
-            if self.commited_transactions.contains(tx.hash()) {
+            // Skip transaction we already included or selected through a priority lane
+            if self.commited_transactions.contains(tx.hash())
+                || self.priority_lanes.is_selected(tx.hash())
+            {
                 continue;
             }
             return Some(tx);
@@ -62,6 +92,7 @@ where
 
     /// Proxy to inner iterator
     fn mark_invalid(&mut self, sender: Address, nonce: u64) {
+        self.priority_lanes.remove_sender(sender, nonce);
         self.inner.mark_invalid(sender, nonce);
     }
 }
@@ -0,0 +1,273 @@
+//! Diagnostics of recent payload builds.
+//!
+//! Every flashblock iteration of a payload records how transaction selection went, so that
+//! operators can tell why a block ended up sparse or was interrupted without digging through
+//! debug logs. The stats of the most recent payloads are kept in a short ring buffer and served
+//! over `xlayer_getPayloadBuildStats`.
+
+use std::{collections::VecDeque, sync::LazyLock, time::Duration};
+
+use alloy_primitives::U64;
+use alloy_rpc_types_engine::PayloadId;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::flashblocks::utils::execution::TxnExecutionResult;
+
+/// Number of payloads whose build stats are retained.
+const MAX_PAYLOAD_BUILD_STATS: usize = 64;
+
+static PAYLOAD_BUILD_STATS: LazyLock<PayloadBuildStatsStore> =
+    LazyLock::new(|| PayloadBuildStatsStore::new(MAX_PAYLOAD_BUILD_STATS));
+
+/// Returns the global payload build stats store.
+pub fn payload_build_stats() -> &'static PayloadBuildStatsStore {
+    &PAYLOAD_BUILD_STATS
+}
+
+/// Outcome of transaction selection for a flashblock iteration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSelectionStats {
+    /// Transactions pulled from the pool
+    pub considered: u64,
+    /// Transactions included in the payload
+    pub included: u64,
+    /// Transactions skipped because the gas limit was reached
+    pub skipped_gas: u64,
+    /// Transactions skipped because the DA limit was reached
+    pub skipped_da: u64,
+    /// Transactions skipped because of a nonce gap with the current state
+    pub skipped_nonce: u64,
+    /// Transactions skipped by builder policy, e.g. unmet conditionals or excessive gas usage
+    pub skipped_policy: u64,
+    /// Transactions skipped because they failed validation against the current state
+    pub skipped_invalid: u64,
+}
+
+impl TxSelectionStats {
+    /// Records a transaction skipped for the given reason.
+    pub(crate) fn record_skipped(&mut self, result: &TxnExecutionResult) {
+        match result {
+            TxnExecutionResult::TransactionGasLimitExceeded(..) => self.skipped_gas += 1,
+            TxnExecutionResult::TransactionDALimitExceeded
+            | TxnExecutionResult::BlockDALimitExceeded(..) => self.skipped_da += 1,
+            TxnExecutionResult::NonceTooLow => self.skipped_nonce += 1,
+            TxnExecutionResult::SequencerTransaction
+            | TxnExecutionResult::ConditionalMismatch
+            | TxnExecutionResult::InteropFailed
+            | TxnExecutionResult::MaxGasUsageExceeded
+            | TxnExecutionResult::RevertedAndExcluded => self.skipped_policy += 1,
+            TxnExecutionResult::InternalError(_) => self.skipped_invalid += 1,
+            TxnExecutionResult::EvmError
+            | TxnExecutionResult::Success
+            | TxnExecutionResult::Reverted => {}
+        }
+    }
+
+    fn add(&mut self, other: &Self) {
+        self.considered += other.considered;
+        self.included += other.included;
+        self.skipped_gas += other.skipped_gas;
+        self.skipped_da += other.skipped_da;
+        self.skipped_nonce += other.skipped_nonce;
+        self.skipped_policy += other.skipped_policy;
+        self.skipped_invalid += other.skipped_invalid;
+    }
+}
+
+/// Stats of a single flashblock iteration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashblockIterationStats {
+    /// Flashblock index within the payload
+    pub index: u64,
+    /// Time spent building the flashblock in milliseconds
+    pub duration_ms: u64,
+    /// Whether the iteration was aborted before the flashblock was sealed, e.g. by a new payload
+    /// job or an execution error
+    pub interrupted: bool,
+    /// Transaction selection outcome
+    pub txs: TxSelectionStats,
+}
+
+impl FlashblockIterationStats {
+    pub fn new(index: u64, duration: Duration, interrupted: bool, txs: TxSelectionStats) -> Self {
+        Self { index, duration_ms: duration.as_millis() as u64, interrupted, txs }
+    }
+}
+
+/// Build stats of a payload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PayloadBuildStats {
+    pub payload_id: PayloadId,
+    pub block_number: u64,
+    /// Transaction selection outcome summed over all iterations
+    pub txs: TxSelectionStats,
+    pub iterations: Vec<FlashblockIterationStats>,
+}
+
+/// Identifies the payload to return build stats for.
+///
+/// Payload ids are 8 byte hex strings, anything else is parsed as a block number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PayloadBuildStatsId {
+    PayloadId(PayloadId),
+    BlockNumber(U64),
+}
+
+/// Ring buffer of the most recent payload build stats.
+#[derive(Debug)]
+pub struct PayloadBuildStatsStore {
+    capacity: usize,
+    payloads: Mutex<VecDeque<PayloadBuildStats>>,
+}
+
+impl PayloadBuildStatsStore {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity, payloads: Mutex::new(VecDeque::with_capacity(capacity)) }
+    }
+
+    /// Appends a flashblock iteration to the stats of the given payload.
+    pub fn record_iteration(
+        &self,
+        payload_id: PayloadId,
+        block_number: u64,
+        iteration: FlashblockIterationStats,
+    ) {
+        let mut payloads = self.payloads.lock();
+        if let Some(stats) = payloads.iter_mut().rev().find(|s| s.payload_id == payload_id) {
+            stats.txs.add(&iteration.txs);
+            stats.iterations.push(iteration);
+            return;
+        }
+
+        if payloads.len() >= self.capacity {
+            payloads.pop_front();
+        }
+        payloads.push_back(PayloadBuildStats {
+            payload_id,
+            block_number,
+            txs: iteration.txs,
+            iterations: vec![iteration],
+        });
+    }
+
+    /// Returns the stats of the given payload, or of the most recent payload built for the given
+    /// block number.
+    pub fn get(&self, id: PayloadBuildStatsId) -> Option<PayloadBuildStats> {
+        let payloads = self.payloads.lock();
+        payloads
+            .iter()
+            .rev()
+            .find(|s| match id {
+                PayloadBuildStatsId::PayloadId(payload_id) => s.payload_id == payload_id,
+                PayloadBuildStatsId::BlockNumber(number) => s.block_number == number.to::<u64>(),
+            })
+            .cloned()
+    }
+}
+
+/// Payload build diagnostics API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait PayloadBuildStatsApi {
+    /// Returns how the construction of a recently built payload proceeded.
+    #[method(name = "getPayloadBuildStats")]
+    async fn get_payload_build_stats(
+        &self,
+        id: PayloadBuildStatsId,
+    ) -> RpcResult<Option<PayloadBuildStats>>;
+}
+
+/// Payload build diagnostics served from a [`PayloadBuildStatsStore`]
+#[derive(Debug, Clone, Copy)]
+pub struct PayloadBuildStatsRpc {
+    store: &'static PayloadBuildStatsStore,
+}
+
+impl PayloadBuildStatsRpc {
+    pub fn new(store: &'static PayloadBuildStatsStore) -> Self {
+        Self { store }
+    }
+}
+
+#[async_trait]
+impl PayloadBuildStatsApiServer for PayloadBuildStatsRpc {
+    async fn get_payload_build_stats(
+        &self,
+        id: PayloadBuildStatsId,
+    ) -> RpcResult<Option<PayloadBuildStats>> {
+        Ok(self.store.get(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B64;
+
+    fn iteration(index: u64, included: u64) -> FlashblockIterationStats {
+        let txs = TxSelectionStats { considered: included + 1, included, ..Default::default() };
+        FlashblockIterationStats::new(index, Duration::from_millis(100), false, txs)
+    }
+
+    #[test]
+    fn test_record_iterations() {
+        let store = PayloadBuildStatsStore::new(2);
+        let first = PayloadId::new([1; 8]);
+        store.record_iteration(first, 10, iteration(1, 2));
+        store.record_iteration(first, 10, iteration(2, 3));
+
+        let stats = store.get(PayloadBuildStatsId::PayloadId(first)).unwrap();
+        assert_eq!(stats.iterations.len(), 2);
+        assert_eq!(stats.txs.considered, 7);
+        assert_eq!(stats.txs.included, 5);
+
+        // Oldest payload is evicted once the buffer is full
+        store.record_iteration(PayloadId::new([2; 8]), 11, iteration(1, 1));
+        store.record_iteration(PayloadId::new([3; 8]), 11, iteration(1, 4));
+        assert!(store.get(PayloadBuildStatsId::PayloadId(first)).is_none());
+
+        // Lookup by block number returns the most recent payload
+        let stats = store.get(PayloadBuildStatsId::BlockNumber(U64::from(11))).unwrap();
+        assert_eq!(stats.payload_id, PayloadId::new([3; 8]));
+    }
+
+    #[test]
+    fn test_record_skipped() {
+        let mut stats = TxSelectionStats::default();
+        stats.record_skipped(&TxnExecutionResult::TransactionGasLimitExceeded(1, 2, 3));
+        stats.record_skipped(&TxnExecutionResult::BlockDALimitExceeded(1, 2, 3));
+        stats.record_skipped(&TxnExecutionResult::NonceTooLow);
+        stats.record_skipped(&TxnExecutionResult::MaxGasUsageExceeded);
+        stats.record_skipped(&TxnExecutionResult::Success);
+        assert_eq!(
+            stats,
+            TxSelectionStats {
+                skipped_gas: 1,
+                skipped_da: 1,
+                skipped_nonce: 1,
+                skipped_policy: 1,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_id_deserialize() {
+        let id: PayloadBuildStatsId = serde_json::from_str(r#""0x0102030405060708""#).unwrap();
+        assert_eq!(
+            id,
+            PayloadBuildStatsId::PayloadId(PayloadId(B64::new([1, 2, 3, 4, 5, 6, 7, 8])))
+        );
+
+        let id: PayloadBuildStatsId = serde_json::from_str(r#""0x10""#).unwrap();
+        assert_eq!(id, PayloadBuildStatsId::BlockNumber(U64::from(16)));
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
     flashblocks::{
         best_txs::BestFlashblocksTxs,
+        build_stats::{payload_build_stats, FlashblockIterationStats, TxSelectionStats},
         builder_tx::FlashblocksBuilderTx,
         context::FlashblocksBuilderCtx,
         generator::{BlockCell, BuildArguments, PayloadBuilder},
+        handoff::sequencer_handoff,
+        preconf::{sign_flashblock, Preconfirmation},
+        priority_lanes::LaneRecipientSenders,
         timing::FlashblockScheduler,
         utils::{
             cache::FlashblockPayloadsCache, execution::ExecutionInfo, wspub::WebSocketPublisher,
@@ -19,7 +23,7 @@ use alloy_consensus::{
 };
 use alloy_eips::{eip7685::EMPTY_REQUESTS_HASH, merge::BEACON_NONCE, Encodable2718};
 use alloy_evm::block::BlockExecutionResult;
-use alloy_primitives::{Address, BlockHash, B256, U256};
+use alloy_primitives::{Address, BlockHash, TxHash, B256, U256};
 use eyre::WrapErr as _;
 use op_alloy_rpc_types_engine::{
     OpFlashblockPayload, OpFlashblockPayloadBase, OpFlashblockPayloadDelta,
@@ -212,6 +216,8 @@ pub(super) struct FlashblocksBuilder<Pool, Client, Tasks> {
     pub builder_tx: FlashblocksBuilderTx,
     /// Tokio task metrics for monitoring spawned tasks
     pub task_metrics: Arc<FlashblocksTaskMetrics>,
+    /// Senders of pool transactions calling a priority lane recipient
+    pub lane_recipient_senders: LaneRecipientSenders,
 }
 
 impl<Pool, Client, Tasks> FlashblocksBuilder<Pool, Client, Tasks> {
@@ -230,6 +236,7 @@ impl<Pool, Client, Tasks> FlashblocksBuilder<Pool, Client, Tasks> {
         ws_pub: Arc<WebSocketPublisher>,
         metrics: Arc<BuilderMetrics>,
         task_metrics: Arc<FlashblocksTaskMetrics>,
+        lane_recipient_senders: LaneRecipientSenders,
     ) -> Self {
         Self {
             evm_config,
@@ -244,6 +251,7 @@ impl<Pool, Client, Tasks> FlashblocksBuilder<Pool, Client, Tasks> {
             metrics,
             builder_tx,
             task_metrics,
+            lane_recipient_senders,
         }
     }
 }
@@ -432,11 +440,23 @@ where
         );
 
         // not emitting flashblock if no_tx_pool in FCU, it's just syncing
-        // For X Layer - skip if replaying
-        if !ctx.attributes().no_tx_pool && !rebuild_external_payload {
-            let flashblock_byte_size =
-                self.ws_pub.publish(&fb_payload).map_err(PayloadBuilderError::other)?;
+        // For X Layer - skip if replaying or paused for a sequencer handoff
+        if !ctx.attributes().no_tx_pool
+            && !rebuild_external_payload
+            && !sequencer_handoff().is_paused()
+        {
+            let preconfs = self.sign_preconfirmations(&ctx, &fb_payload, &new_tx_hashes);
+            let flashblock_byte_size = self
+                .ws_pub
+                .publish_preconfirmed(&fb_payload, &preconfs)
+                .map_err(PayloadBuilderError::other)?;
             ctx.metrics.flashblock_byte_size_histogram.record(flashblock_byte_size as f64);
+            sequencer_handoff().record_flashblock(
+                fb_payload.payload_id,
+                ctx.block_number(),
+                fb_payload.index,
+                info.executed_transactions.len(),
+            );
 
             // For X Layer, full link monitoring support
             crate::flashblocks::utils::monitor::monitor(
@@ -531,7 +551,8 @@ where
         // Create best_transaction iterator
         let mut best_txs = BestFlashblocksTxs::new(BestPayloadTransactions::new(
             self.pool.best_transactions_with_attributes(ctx.best_transaction_attributes()),
-        ));
+        ))
+        .with_priority_lanes(self.config.priority_lanes.clone());
 
         let (tx, rx) = std::sync::mpsc::sync_channel((expected_flashblocks + 1) as usize);
         tokio::spawn(self.task_metrics.flashblock_timer.instrument(flashblock_scheduler.run(
@@ -690,20 +711,44 @@ where
             ),
             flashblock_index,
         );
+        best_txs.refresh_priority_lanes(
+            self.config.priority_lanes.candidates(&self.pool, &self.lane_recipient_senders),
+            ctx.base_fee(),
+        );
         let transaction_pool_fetch_time = best_txs_start_time.elapsed();
         ctx.metrics.transaction_pool_fetch_duration.record(transaction_pool_fetch_time);
         ctx.metrics.transaction_pool_fetch_gauge.set(transaction_pool_fetch_time);
 
         let tx_execution_start_time = Instant::now();
-        ctx.execute_best_transactions(
-            info,
-            state,
-            best_txs,
-            target_gas_for_batch.min(ctx.block_gas_limit()),
-            target_da_for_batch,
-            target_da_footprint_for_batch,
-        )
-        .wrap_err("failed to execute best transactions")?;
+        let mut selection = TxSelectionStats::default();
+        let exec_result = ctx
+            .execute_best_transactions(
+                info,
+                state,
+                best_txs,
+                target_gas_for_batch.min(ctx.block_gas_limit()),
+                target_da_for_batch,
+                target_da_footprint_for_batch,
+                &mut selection,
+            )
+            .wrap_err("failed to execute best transactions");
+        // For X Layer, record the iteration for payload build diagnostics
+        let record_iteration = |interrupted: bool| {
+            payload_build_stats().record_iteration(
+                ctx.payload_id(),
+                ctx.block_number(),
+                FlashblockIterationStats::new(
+                    flashblock_index,
+                    flashblock_build_start_time.elapsed(),
+                    interrupted,
+                    selection,
+                ),
+            );
+        };
+        if exec_result.is_err() {
+            record_iteration(true);
+        }
+        exec_result?;
         // Extract last transactions
         let new_transactions = fb_state
             .slice_new_transactions(&info.executed_transactions)
@@ -715,6 +760,7 @@ where
         // We got block cancelled, we won't need anything from the block at this point
         // Caution: this assume that block cancel token only cancelled when new FCU is received
         if block_cancel.is_cancelled() {
+            record_iteration(true);
             return Ok(None);
         }
 
@@ -752,10 +798,22 @@ where
                 fb_payload.index = flashblock_index;
                 fb_payload.base = None;
 
-                let flashblock_byte_size = self
-                    .ws_pub
-                    .publish(&fb_payload)
-                    .wrap_err("failed to publish flashblock via websocket")?;
+                // For X Layer - the block is still built while paused for a sequencer handoff,
+                // only its flashblocks are not published
+                if !sequencer_handoff().is_paused() {
+                    let preconfs = self.sign_preconfirmations(ctx, &fb_payload, &new_tx_hashes);
+                    let flashblock_byte_size = self
+                        .ws_pub
+                        .publish_preconfirmed(&fb_payload, &preconfs)
+                        .wrap_err("failed to publish flashblock via websocket")?;
+                    ctx.metrics.flashblock_byte_size_histogram.record(flashblock_byte_size as f64);
+                    sequencer_handoff().record_flashblock(
+                        fb_payload.payload_id,
+                        ctx.block_number(),
+                        flashblock_index,
+                        info.executed_transactions.len(),
+                    );
+                }
                 self.built_fb_payload_tx
                     .try_send(fb_payload)
                     .wrap_err("failed to send built payload to handler")?;
@@ -763,7 +821,7 @@ where
 
                 // Record flashblock build duration
                 ctx.metrics.flashblock_build_duration.record(flashblock_build_start_time.elapsed());
-                ctx.metrics.flashblock_byte_size_histogram.record(flashblock_byte_size as f64);
+                record_iteration(false);
                 ctx.metrics
                     .flashblock_num_tx_histogram
                     .record(info.executed_transactions.len() as f64);
@@ -880,6 +938,19 @@ where
         resolve_payload.set(payload);
     }
 
+    /// For X Layer - signs the preconfirmations of the new transactions of a flashblock about to
+    /// be published, none without a preconfirmation key.
+    fn sign_preconfirmations(
+        &self,
+        ctx: &FlashblocksBuilderCtx,
+        fb_payload: &OpFlashblockPayload,
+        tx_hashes: &[TxHash],
+    ) -> Vec<Preconfirmation> {
+        self.config.flashblocks.preconf_signer.as_ref().map_or_else(Vec::new, |signer| {
+            sign_flashblock(signer, ctx.chain_id(), fb_payload, tx_hashes, &ctx.metrics)
+        })
+    }
+
     /// Do some logging and metric recording when we stop build flashblocks
     fn record_flashblocks_metrics(
         &self,
@@ -1139,9 +1210,7 @@ where
         recovered_block: Arc::new(recovered_block),
         execution_output: Arc::new(execution_output),
         trie_updates: either::Either::Left(
-            trie_updates_to_cache
-                .clone()
-                .unwrap_or_else(|| Arc::new(TrieUpdates::default())),
+            trie_updates_to_cache.clone().unwrap_or_else(|| Arc::new(TrieUpdates::default())),
         ),
         hashed_state: either::Either::Left(Arc::new(hashed_state)),
     };
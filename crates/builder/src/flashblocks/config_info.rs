@@ -0,0 +1,168 @@
+//! Effective builder configuration, served over `xlayer_getBuilderConfig`.
+//!
+//! Reports the configuration the builder runs with after defaults and derived values are
+//! applied, so operators can check a deployment without reconstructing it from flags and
+//! environment variables. The builder and preconfirmation signers are reported by address only.
+
+use std::net::SocketAddr;
+
+use alloy_primitives::Address;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::flashblocks::BuilderConfig;
+
+/// Effective configuration of the flashblocks builder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuilderConfigInfo {
+    pub builder_signer: Option<Address>,
+    pub block_time_ms: u64,
+    pub block_time_leeway_ms: u64,
+    pub max_gas_per_txn: Option<u64>,
+    pub priority_lanes: Vec<PriorityLaneInfo>,
+    pub flashblocks: FlashblocksConfigInfo,
+}
+
+/// Priority lane of the builder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityLaneInfo {
+    pub name: String,
+    pub senders: usize,
+    pub recipients: usize,
+    pub gas_budget: Option<u64>,
+}
+
+/// Flashblocks specific configuration of the builder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashblocksConfigInfo {
+    pub ws_addr: SocketAddr,
+    pub interval_ms: u64,
+    /// Number of flashblocks built per block
+    pub flashblocks_per_block: u64,
+    pub disable_state_root: bool,
+    pub disable_async_calculate_state_root: bool,
+    pub number_contract_address: Option<Address>,
+    pub send_offset_ms: i64,
+    pub end_buffer_ms: u64,
+    pub p2p_enabled: bool,
+    pub p2p_port: u16,
+    pub p2p_known_peers: Option<String>,
+    pub p2p_max_peer_count: u32,
+    pub p2p_send_full_payload: bool,
+    pub p2p_process_full_payload: bool,
+    pub ws_subscriber_limit: Option<u16>,
+    pub replay_from_persistence_file: bool,
+    pub validate_canonical_blocks: bool,
+    pub preconf_signer: Option<Address>,
+}
+
+impl From<&BuilderConfig> for BuilderConfigInfo {
+    fn from(config: &BuilderConfig) -> Self {
+        let flashblocks = &config.flashblocks;
+        Self {
+            builder_signer: config.builder_signer.as_ref().map(|signer| signer.address),
+            block_time_ms: config.block_time.as_millis() as u64,
+            block_time_leeway_ms: config.block_time_leeway.as_millis() as u64,
+            max_gas_per_txn: config.max_gas_per_txn,
+            priority_lanes: config
+                .priority_lanes
+                .lanes()
+                .iter()
+                .map(|lane| PriorityLaneInfo {
+                    name: lane.name.to_string(),
+                    senders: lane.senders.len(),
+                    recipients: lane.recipients.len(),
+                    gas_budget: lane.gas_budget,
+                })
+                .collect(),
+            flashblocks: FlashblocksConfigInfo {
+                ws_addr: flashblocks.ws_addr,
+                interval_ms: flashblocks.interval.as_millis() as u64,
+                flashblocks_per_block: config.flashblocks_per_block(),
+                disable_state_root: flashblocks.disable_state_root,
+                disable_async_calculate_state_root: flashblocks.disable_async_calculate_state_root,
+                number_contract_address: flashblocks.number_contract_address,
+                send_offset_ms: flashblocks.send_offset_ms,
+                end_buffer_ms: flashblocks.end_buffer_ms,
+                p2p_enabled: flashblocks.p2p_enabled,
+                p2p_port: flashblocks.p2p_port,
+                p2p_known_peers: flashblocks.p2p_known_peers.clone(),
+                p2p_max_peer_count: flashblocks.p2p_max_peer_count,
+                p2p_send_full_payload: flashblocks.p2p_send_full_payload,
+                p2p_process_full_payload: flashblocks.p2p_process_full_payload,
+                ws_subscriber_limit: flashblocks.ws_subscriber_limit,
+                replay_from_persistence_file: flashblocks.replay_from_persistence_file,
+                validate_canonical_blocks: flashblocks.validate_canonical_blocks,
+                preconf_signer: flashblocks.preconf_signer.as_ref().map(|signer| signer.address),
+            },
+        }
+    }
+}
+
+/// Builder configuration debug API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait BuilderConfigApi {
+    /// Returns the effective configuration of the flashblocks builder.
+    #[method(name = "getBuilderConfig")]
+    async fn get_builder_config(&self) -> RpcResult<BuilderConfigInfo>;
+}
+
+/// Serves the configuration the builder was started with.
+#[derive(Debug, Clone)]
+pub struct BuilderConfigRpc {
+    info: BuilderConfigInfo,
+}
+
+impl BuilderConfigRpc {
+    pub fn new(config: &BuilderConfig) -> Self {
+        Self { info: config.into() }
+    }
+}
+
+#[async_trait]
+impl BuilderConfigApiServer for BuilderConfigRpc {
+    async fn get_builder_config(&self) -> RpcResult<BuilderConfigInfo> {
+        Ok(self.info.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::BuilderArgs;
+
+    #[test]
+    fn test_builder_config_info() {
+        let mut args = BuilderArgs::default();
+        args.chain_block_time = 1000;
+        args.flashblocks.flashblocks_block_time = 200;
+        args.priority_fee_collectors = vec![Address::repeat_byte(1)];
+        args.priority_fee_collection_gas = Some(100_000);
+        let config = BuilderConfig::try_from(args).unwrap();
+
+        let info = BuilderConfigInfo::from(&config);
+        assert_eq!(info.block_time_ms, 1000);
+        assert_eq!(info.flashblocks.interval_ms, 200);
+        assert_eq!(info.flashblocks.flashblocks_per_block, 5);
+        assert_eq!(
+            info.priority_lanes,
+            [PriorityLaneInfo {
+                name: "fee_collection".to_string(),
+                senders: 1,
+                recipients: 0,
+                gas_budget: Some(100_000),
+            }]
+        );
+
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(value["flashblocks"]["wsAddr"], "127.0.0.1:1111");
+        assert_eq!(value["builderSigner"], serde_json::Value::Null);
+        assert_eq!(value["flashblocks"]["preconfSigner"], serde_json::Value::Null);
+    }
+}
@@ -1,5 +1,8 @@
 use crate::{
-    flashblocks::utils::execution::{ExecutionInfo, TxnExecutionResult},
+    flashblocks::{
+        build_stats::TxSelectionStats,
+        utils::execution::{ExecutionInfo, TxnExecutionResult},
+    },
     metrics::BuilderMetrics,
     signer::Signer,
     traits::PayloadTxsBounds,
@@ -458,9 +461,11 @@ impl FlashblocksBuilderCtx {
         Ok(())
     }
 
-    /// Executes the given best transactions and updates the execution info.
+    /// Executes the given best transactions and updates the execution info. The outcome of
+    /// transaction selection is recorded into `selection`.
     ///
     /// Returns `Ok(Some(())` if the job was cancelled.
+    #[allow(clippy::too_many_arguments)]
     pub(super) fn execute_best_transactions(
         &self,
         info: &mut ExecutionInfo,
@@ -469,6 +474,7 @@ impl FlashblocksBuilderCtx {
         block_gas_limit: u64,
         block_da_limit: Option<u64>,
         block_da_footprint_limit: Option<u64>,
+        selection: &mut TxSelectionStats,
     ) -> Result<Option<()>, PayloadBuilderError> {
         let execute_txs_start_time = Instant::now();
         let mut num_txs_considered = 0;
@@ -502,7 +508,10 @@ impl FlashblocksBuilderCtx {
             let tx_da_size = tx.estimated_da_size();
             let tx = tx.into_consensus();
             let tx_hash = tx.tx_hash();
-            let log_txn = |result: TxnExecutionResult| {
+            num_txs_considered += 1;
+            selection.considered += 1;
+            let mut log_txn = |result: TxnExecutionResult| {
+                selection.record_skipped(&result);
                 debug!(
                     target: "payload_builder",
                     id = ?self.payload_id(),
@@ -513,12 +522,11 @@ impl FlashblocksBuilderCtx {
                 );
             };
 
-            num_txs_considered += 1;
-
             // TODO: ideally we should get this from the txpool stream
             if let Some(conditional) = conditional
                 && !conditional.matches_block_attributes(&block_attr)
             {
+                log_txn(TxnExecutionResult::ConditionalMismatch);
                 best_txs.mark_invalid(tx.signer(), tx.nonce());
                 continue;
             }
@@ -644,6 +652,7 @@ impl FlashblocksBuilderCtx {
             // append sender and transaction to the respective lists
             info.executed_senders.push(tx.signer());
             info.executed_transactions.push(tx.into_inner());
+            selection.included += 1;
         }
 
         let payload_transaction_simulation_time = execute_txs_start_time.elapsed();
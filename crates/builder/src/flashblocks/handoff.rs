@@ -0,0 +1,249 @@
+//! Sequencer handoff coordination, served over `xlayer_sequencerStatus` and
+//! `xlayer_prepareSequencerHandoff`.
+//!
+//! External failover tooling switching the active sequencer to a standby first pauses flashblock
+//! production on the active one, so that no flashblocks are preconfirmed that the standby doesn't
+//! build on, then hands over once both report the same unsafe head. While paused, full blocks are
+//! still built for the engine, and shared with peers over p2p, but no flashblock is published to
+//! flashblocks subscribers. The pause lifts by itself after the
+//! requested duration so a failed handoff doesn't stall the chain.
+
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_consensus::BlockHeader as _;
+use alloy_primitives::B256;
+use alloy_rpc_types_engine::PayloadId;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::{error::INTERNAL_ERROR_CODE, ErrorObject},
+};
+use parking_lot::Mutex;
+use reth_provider::{BlockNumReader, HeaderProvider};
+use reth_transaction_pool::TransactionPool;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Longest pause of flashblock production accepted by `xlayer_prepareSequencerHandoff`.
+const MAX_HANDOFF_PAUSE: Duration = Duration::from_secs(60);
+
+static SEQUENCER_HANDOFF: LazyLock<SequencerHandoff> = LazyLock::new(SequencerHandoff::default);
+
+/// Returns the global sequencer handoff state.
+pub fn sequencer_handoff() -> &'static SequencerHandoff {
+    &SEQUENCER_HANDOFF
+}
+
+/// The flashblock most recently published by the builder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InFlightFlashblock {
+    pub payload_id: PayloadId,
+    pub block_number: u64,
+    /// Flashblock index within the payload
+    pub index: u64,
+    /// Transactions of the payload up to this flashblock
+    pub transactions: u64,
+    /// Publication time in milliseconds since the Unix epoch
+    pub published_at_ms: u64,
+}
+
+/// Pause of flashblock production and the last published flashblock.
+#[derive(Debug, Default)]
+pub struct SequencerHandoff {
+    paused_until: Mutex<Option<(Instant, SystemTime)>>,
+    in_flight: Mutex<Option<InFlightFlashblock>>,
+}
+
+impl SequencerHandoff {
+    /// Pauses flashblock production for the given duration, capped at [`MAX_HANDOFF_PAUSE`]. A
+    /// zero duration resumes production.
+    pub fn pause(&self, duration: Duration) {
+        let duration = duration.min(MAX_HANDOFF_PAUSE);
+        let mut paused_until = self.paused_until.lock();
+        if duration.is_zero() {
+            *paused_until = None;
+            info!(target: "payload_builder", "Flashblock production resumed");
+        } else {
+            *paused_until = Some((Instant::now() + duration, SystemTime::now() + duration));
+            info!(target: "payload_builder", ?duration, "Flashblock production paused for sequencer handoff");
+        }
+    }
+
+    /// Returns `true` while flashblock production is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused_until_ms().is_some()
+    }
+
+    /// Returns the end of the pause in milliseconds since the Unix epoch, `None` if not paused.
+    fn paused_until_ms(&self) -> Option<u64> {
+        let paused_until = self.paused_until.lock();
+        let (deadline, until) = (*paused_until)?;
+        (Instant::now() < deadline).then(|| unix_ms(until))
+    }
+
+    /// Records a flashblock published by the builder.
+    pub(crate) fn record_flashblock(
+        &self,
+        payload_id: PayloadId,
+        block_number: u64,
+        index: u64,
+        transactions: usize,
+    ) {
+        *self.in_flight.lock() = Some(InFlightFlashblock {
+            payload_id,
+            block_number,
+            index,
+            transactions: transactions as u64,
+            published_at_ms: unix_ms(SystemTime::now()),
+        });
+    }
+
+    fn in_flight(&self) -> Option<InFlightFlashblock> {
+        self.in_flight.lock().clone()
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Unsafe head of the sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnsafeHead {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+}
+
+/// Transaction pool summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxPoolSummary {
+    pub pending: u64,
+    pub queued: u64,
+}
+
+/// Status of the sequencer as reported to failover tooling.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencerStatus {
+    pub unsafe_head: UnsafeHead,
+    /// Flashblock most recently published, `None` before the first one
+    pub in_flight_flashblock: Option<InFlightFlashblock>,
+    pub txpool: TxPoolSummary,
+    /// Whether flashblock production is paused
+    pub paused: bool,
+    /// End of the pause in milliseconds since the Unix epoch
+    pub paused_until_ms: Option<u64>,
+}
+
+/// Sequencer failover admin API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait SequencerHandoffApi {
+    /// Returns the unsafe head, the in-flight flashblock and a txpool summary of the sequencer.
+    #[method(name = "sequencerStatus")]
+    async fn sequencer_status(&self) -> RpcResult<SequencerStatus>;
+
+    /// Pauses flashblock production for `pause_ms` milliseconds, at most 60 seconds, ahead of a
+    /// sequencer handoff and returns the resulting status. Zero resumes production.
+    #[method(name = "prepareSequencerHandoff")]
+    async fn prepare_sequencer_handoff(&self, pause_ms: u64) -> RpcResult<SequencerStatus>;
+}
+
+/// Serves the sequencer handoff API from the node provider and pool.
+#[derive(Debug, Clone)]
+pub struct SequencerHandoffRpc<Provider, Pool> {
+    provider: Provider,
+    pool: Pool,
+    handoff: &'static SequencerHandoff,
+}
+
+impl<Provider, Pool> SequencerHandoffRpc<Provider, Pool> {
+    pub fn new(provider: Provider, pool: Pool, handoff: &'static SequencerHandoff) -> Self {
+        Self { provider, pool, handoff }
+    }
+}
+
+impl<Provider, Pool> SequencerHandoffRpc<Provider, Pool>
+where
+    Provider: BlockNumReader + HeaderProvider,
+    Pool: TransactionPool,
+{
+    fn status(&self) -> RpcResult<SequencerStatus> {
+        let internal = |e: String| ErrorObject::owned(INTERNAL_ERROR_CODE, e, None::<()>);
+        let number = self.provider.best_block_number().map_err(|e| internal(e.to_string()))?;
+        let header = self
+            .provider
+            .sealed_header(number)
+            .map_err(|e| internal(e.to_string()))?
+            .ok_or_else(|| internal(format!("header of unsafe head {number} not found")))?;
+        let pool_size = self.pool.pool_size();
+        let paused_until_ms = self.handoff.paused_until_ms();
+
+        Ok(SequencerStatus {
+            unsafe_head: UnsafeHead { number, hash: header.hash(), timestamp: header.timestamp() },
+            in_flight_flashblock: self.handoff.in_flight(),
+            txpool: TxPoolSummary {
+                pending: pool_size.pending as u64,
+                queued: pool_size.queued as u64,
+            },
+            paused: paused_until_ms.is_some(),
+            paused_until_ms,
+        })
+    }
+}
+
+#[async_trait]
+impl<Provider, Pool> SequencerHandoffApiServer for SequencerHandoffRpc<Provider, Pool>
+where
+    Provider: BlockNumReader + HeaderProvider + Clone + 'static,
+    Pool: TransactionPool + Clone + 'static,
+{
+    async fn sequencer_status(&self) -> RpcResult<SequencerStatus> {
+        self.status()
+    }
+
+    async fn prepare_sequencer_handoff(&self, pause_ms: u64) -> RpcResult<SequencerStatus> {
+        self.handoff.pause(Duration::from_millis(pause_ms));
+        self.status()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pause_and_resume() {
+        let handoff = SequencerHandoff::default();
+        assert!(!handoff.is_paused());
+
+        handoff.pause(Duration::from_secs(10));
+        assert!(handoff.is_paused());
+
+        handoff.pause(Duration::ZERO);
+        assert!(!handoff.is_paused());
+
+        // Pauses are capped
+        handoff.pause(Duration::from_secs(3600));
+        let until = handoff.paused_until_ms().unwrap();
+        assert!(until <= unix_ms(SystemTime::now() + MAX_HANDOFF_PAUSE));
+    }
+
+    #[test]
+    fn test_record_flashblock() {
+        let handoff = SequencerHandoff::default();
+        assert!(handoff.in_flight().is_none());
+
+        let payload_id = PayloadId::new([1; 8]);
+        handoff.record_flashblock(payload_id, 10, 0, 1);
+        handoff.record_flashblock(payload_id, 10, 1, 5);
+        let in_flight = handoff.in_flight().unwrap();
+        assert_eq!((in_flight.block_number, in_flight.index, in_flight.transactions), (10, 1, 5));
+    }
+}
@@ -9,17 +9,24 @@ use core::{
 use reth_optimism_payload_builder::config::{OpDAConfig, OpGasLimitConfig};
 
 mod best_txs;
+pub mod build_stats;
 mod builder;
 pub(crate) mod builder_tx;
+pub mod config_info;
 mod context;
 mod generator;
 mod handler;
 mod handler_ctx;
+pub mod handoff;
+pub mod preconf;
+pub mod priority_lanes;
 mod service;
 mod timing;
 pub(crate) mod utils;
+mod validation;
 
 pub use context::FlashblocksBuilderCtx;
+pub use priority_lanes::{PriorityLane, PriorityLanes};
 pub use service::FlashblocksServiceBuilder;
 pub use utils::{cache::FlashblockPayloadsCache, wspub::WebSocketPublisher};
 
@@ -80,6 +87,12 @@ pub struct FlashblocksConfig {
 
     /// Whether to replay from the persistence file on startup
     pub replay_from_persistence_file: bool,
+
+    /// Whether to re-execute canonical blocks with the standard executor and compare their roots
+    pub validate_canonical_blocks: bool,
+
+    /// Key signing the preconfirmations of the transactions of published flashblocks
+    pub preconf_signer: Option<Signer>,
 }
 
 impl Default for FlashblocksConfig {
@@ -101,6 +114,8 @@ impl Default for FlashblocksConfig {
             p2p_process_full_payload: false,
             ws_subscriber_limit: None,
             replay_from_persistence_file: false,
+            validate_canonical_blocks: false,
+            preconf_signer: None,
         }
     }
 }
@@ -144,6 +159,9 @@ pub struct BuilderConfig {
     /// Maximum gas a transaction can use before being excluded.
     pub max_gas_per_txn: Option<u64>,
 
+    /// Transactions selected ahead of the pool ordering.
+    pub priority_lanes: PriorityLanes,
+
     /// Configuration values that are specific to the flashblocks builder.
     pub flashblocks: FlashblocksConfig,
 }
@@ -164,6 +182,7 @@ impl core::fmt::Debug for BuilderConfig {
             .field("gas_limit_config", &self.gas_limit_config)
             .field("flashblocks", &self.flashblocks)
             .field("max_gas_per_txn", &self.max_gas_per_txn)
+            .field("priority_lanes", &self.priority_lanes)
             .finish()
     }
 }
@@ -178,6 +197,7 @@ impl Default for BuilderConfig {
             gas_limit_config: OpGasLimitConfig::default(),
             flashblocks: FlashblocksConfig::default(),
             max_gas_per_txn: None,
+            priority_lanes: PriorityLanes::default(),
         }
     }
 }
@@ -196,6 +216,7 @@ impl TryFrom<BuilderArgs> for BuilderConfig {
         let disable_async_calculate_state_root =
             args.flashblocks.flashblocks_disable_async_calculate_state_root;
         let number_contract_address = args.flashblocks.flashblocks_number_contract_address;
+        let priority_lanes = PriorityLanes::from(&args);
 
         Ok(Self {
             builder_signer: args.builder_signer,
@@ -204,6 +225,7 @@ impl TryFrom<BuilderArgs> for BuilderConfig {
             da_config: Default::default(),
             gas_limit_config: Default::default(),
             max_gas_per_txn: args.max_gas_per_txn,
+            priority_lanes,
             flashblocks: FlashblocksConfig {
                 ws_addr,
                 interval,
@@ -221,6 +243,8 @@ impl TryFrom<BuilderArgs> for BuilderConfig {
                 p2p_process_full_payload: args.flashblocks.p2p.p2p_process_full_payload,
                 ws_subscriber_limit: args.flashblocks.ws_subscriber_limit,
                 replay_from_persistence_file: args.flashblocks.replay_from_persistence_file,
+                validate_canonical_blocks: args.flashblocks.validate_canonical_blocks,
+                preconf_signer: args.flashblocks.preconf_signer,
             },
         })
     }
@@ -0,0 +1,189 @@
+//! Signed preconfirmations of flashblock inclusion.
+//!
+//! Exchanges want evidence of sub-second inclusion they can verify and present later. With a
+//! preconfirmation key configured, the builder signs a commitment for every transaction of a
+//! flashblock when it publishes the flashblock: the chain id, the block number, the flashblock
+//! index, the transaction hash and a state hint, the block hash of the flashblock, which commits
+//! to the state and receipts after it. The signatures are carried in a `preconfirmations` field
+//! of the published flashblock message, next to the payload fields, so consumers decoding only
+//! the payload are unaffected. Replicas relay them without holding the key.
+//!
+//! The signed digest is the keccak256 hash of [`PRECONF_DOMAIN`] followed by the big endian chain
+//! id, block number and flashblock index and the transaction hash and state hint. The signature is
+//! a 65 byte `r || s || v` secp256k1 signature over the digest, without message prefix, so the
+//! signer is recovered with `ecrecover(digest, v, r, s)`.
+
+use alloy_primitives::{keccak256, Address, Bytes, TxHash, B256, U64};
+use op_alloy_rpc_types_engine::OpFlashblockPayload;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{metrics::BuilderMetrics, signer::Signer};
+
+/// Domain separator of the preconfirmation digest.
+pub const PRECONF_DOMAIN: &[u8] = b"xlayer-preconfirmation-v1";
+
+/// Inclusion of a transaction by a flashblock, as committed to by the sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreconfCommitment {
+    pub chain_id: U64,
+    pub block_number: U64,
+    pub flashblock_index: U64,
+    pub tx_hash: TxHash,
+    /// Block hash of the flashblock
+    pub state_hint: B256,
+}
+
+impl PreconfCommitment {
+    /// Returns the digest signed by the sequencer.
+    pub fn digest(&self) -> B256 {
+        let mut buf = Vec::with_capacity(PRECONF_DOMAIN.len() + 3 * 8 + 2 * 32);
+        buf.extend_from_slice(PRECONF_DOMAIN);
+        buf.extend_from_slice(&self.chain_id.to::<u64>().to_be_bytes());
+        buf.extend_from_slice(&self.block_number.to::<u64>().to_be_bytes());
+        buf.extend_from_slice(&self.flashblock_index.to::<u64>().to_be_bytes());
+        buf.extend_from_slice(self.tx_hash.as_slice());
+        buf.extend_from_slice(self.state_hint.as_slice());
+        keccak256(buf)
+    }
+}
+
+/// Signed preconfirmation of a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preconfirmation {
+    #[serde(flatten)]
+    pub commitment: PreconfCommitment,
+    /// Address of the preconfirmation key
+    pub signer: Address,
+    /// `r || s || v` signature of the commitment digest
+    pub signature: Bytes,
+}
+
+/// Flashblock message published over the websocket, the payload with the preconfirmations of its
+/// transactions.
+#[derive(Debug, Serialize)]
+pub(crate) struct FlashblockMessage<'a> {
+    #[serde(flatten)]
+    pub payload: &'a OpFlashblockPayload,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub preconfirmations: &'a [Preconfirmation],
+}
+
+/// Signs a preconfirmation of every transaction of the flashblock, transactions failing to be
+/// signed are skipped.
+pub(crate) fn sign_flashblock(
+    signer: &Signer,
+    chain_id: u64,
+    payload: &OpFlashblockPayload,
+    tx_hashes: &[TxHash],
+    metrics: &BuilderMetrics,
+) -> Vec<Preconfirmation> {
+    tx_hashes
+        .iter()
+        .filter_map(|tx_hash| {
+            let commitment = PreconfCommitment {
+                chain_id: U64::from(chain_id),
+                block_number: U64::from(payload.metadata.block_number),
+                flashblock_index: U64::from(payload.index),
+                tx_hash: *tx_hash,
+                state_hint: payload.diff.block_hash,
+            };
+            let signature = match signer.sign_message(commitment.digest()) {
+                Ok(signature) => signature,
+                Err(err) => {
+                    metrics.preconfirmation_sign_failures.increment(1);
+                    warn!(target: "payload_builder", %tx_hash, %err, "Failed to sign preconfirmation");
+                    return None;
+                }
+            };
+            metrics.preconfirmations_signed.increment(1);
+            Some(Preconfirmation {
+                commitment,
+                signer: signer.address,
+                signature: Bytes::copy_from_slice(&signature.as_bytes()),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{b256, Signature};
+    use op_alloy_rpc_types_engine::{OpFlashblockPayloadDelta, OpFlashblockPayloadMetadata};
+    use reth_payload_builder::PayloadId;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_sign_flashblock() {
+        let signer = Signer::try_from_secret(B256::repeat_byte(0x42)).unwrap();
+        let payload = OpFlashblockPayload {
+            payload_id: PayloadId::new([1; 8]),
+            index: 3,
+            base: None,
+            diff: OpFlashblockPayloadDelta {
+                block_hash: B256::repeat_byte(7),
+                ..Default::default()
+            },
+            metadata: OpFlashblockPayloadMetadata {
+                block_number: 100,
+                new_account_balances: BTreeMap::new(),
+                receipts: BTreeMap::new(),
+            },
+        };
+        let tx_hash = b256!("0x5c504ed432cb51138bcf09aa5e8a410dd4a1e204ef84bfed1be16dfba1b22060");
+
+        let preconfs =
+            sign_flashblock(&signer, 196, &payload, &[tx_hash], &BuilderMetrics::default());
+        let [preconf] = preconfs.as_slice() else { panic!("expected one preconfirmation") };
+        assert_eq!(preconf.commitment.block_number, U64::from(100));
+        assert_eq!(preconf.commitment.flashblock_index, U64::from(3));
+        assert_eq!(preconf.commitment.state_hint, B256::repeat_byte(7));
+        assert_eq!(preconf.signer, signer.address);
+        let signature = Signature::try_from(preconf.signature.as_ref()).unwrap();
+        assert_eq!(
+            signature.recover_address_from_prehash(&preconf.commitment.digest()).unwrap(),
+            signer.address
+        );
+
+        // The preconfirmations ride along the payload fields of the published message
+        let message = serde_json::to_value(FlashblockMessage {
+            payload: &payload,
+            preconfirmations: &preconfs,
+        })
+        .unwrap();
+        assert_eq!(message["index"], 3);
+        assert_eq!(message["preconfirmations"][0]["txHash"], tx_hash.to_string());
+        let message =
+            serde_json::to_value(FlashblockMessage { payload: &payload, preconfirmations: &[] })
+                .unwrap();
+        assert_eq!(message, serde_json::to_value(&payload).unwrap());
+    }
+
+    #[test]
+    fn test_preconfirmation_serde() {
+        let commitment = PreconfCommitment {
+            chain_id: U64::from(196),
+            block_number: U64::from(100),
+            flashblock_index: U64::from(3),
+            tx_hash: B256::repeat_byte(1),
+            state_hint: B256::repeat_byte(2),
+        };
+        let value = serde_json::to_value(Preconfirmation {
+            commitment,
+            signer: Address::repeat_byte(3),
+            signature: Bytes::from(vec![0; 65]),
+        })
+        .unwrap();
+        assert_eq!(value["blockNumber"], "0x64");
+        assert_eq!(value["flashblockIndex"], "0x3");
+        assert_eq!(value["stateHint"], B256::repeat_byte(2).to_string());
+        assert!(value["signature"].as_str().unwrap().starts_with("0x0000"));
+
+        // The digest commits to every field
+        let other = PreconfCommitment { flashblock_index: U64::from(4), ..commitment };
+        assert_ne!(commitment.digest(), other.digest());
+    }
+}
@@ -0,0 +1,412 @@
+//! Priority lanes for bridge and system transactions.
+//!
+//! Under congestion, bridge claims and sequencer fee collection compete with regular traffic on
+//! tip and can be delayed for many blocks. Transactions matching a lane are selected ahead of the
+//! general pool ordering, in lane order, until the gas budget of the lane for the block is spent.
+//! Transactions beyond the budget fall back to the general ordering.
+//!
+//! Lane senders are known up front, the senders calling lane recipients are tracked from the pool
+//! events by [`LaneRecipientSenders`], so selecting the lane candidates never scans the pool.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use alloy_consensus::Transaction;
+use alloy_primitives::{Address, TxHash};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_transaction_pool::{PoolTransaction, TransactionPool, ValidPoolTransaction};
+
+use crate::args::BuilderArgs;
+
+/// Transactions selected into a priority lane, matched by sender or recipient.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriorityLane {
+    /// Name of the lane, used as metrics label
+    pub name: &'static str,
+    /// Senders whose transactions belong to the lane
+    pub senders: HashSet<Address>,
+    /// Contracts whose calls belong to the lane
+    pub recipients: HashSet<Address>,
+    /// Gas limit of the lane transactions selected per block, unlimited if unset
+    pub gas_budget: Option<u64>,
+}
+
+impl PriorityLane {
+    fn matches(&self, sender: Address, to: Option<Address>) -> bool {
+        self.senders.contains(&sender) || to.is_some_and(|to| self.recipients.contains(&to))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.senders.is_empty() && self.recipients.is_empty()
+    }
+}
+
+/// Ordered set of priority lanes. Earlier lanes take precedence.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PriorityLanes {
+    lanes: Vec<PriorityLane>,
+}
+
+impl PriorityLanes {
+    /// Creates the lanes, dropping those without senders and recipients.
+    pub fn new(lanes: impl IntoIterator<Item = PriorityLane>) -> Self {
+        Self { lanes: lanes.into_iter().filter(|lane| !lane.is_empty()).collect() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lanes.is_empty()
+    }
+
+    pub fn lanes(&self) -> &[PriorityLane] {
+        &self.lanes
+    }
+
+    /// Returns the index of the first lane the transaction belongs to.
+    fn lane_of(&self, sender: Address, to: Option<Address>) -> Option<usize> {
+        self.lanes.iter().position(|lane| lane.matches(sender, to))
+    }
+
+    /// Returns the recipients of all lanes.
+    fn recipients(&self) -> HashSet<Address> {
+        self.lanes.iter().flat_map(|lane| lane.recipients.iter().copied()).collect()
+    }
+
+    /// Returns the pending transactions of the lane senders and of the senders calling a lane
+    /// recipient.
+    pub(super) fn candidates<P: TransactionPool>(
+        &self,
+        pool: &P,
+        recipient_senders: &LaneRecipientSenders,
+    ) -> Vec<Arc<ValidPoolTransaction<P::Transaction>>> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut senders: HashSet<Address> =
+            self.lanes.iter().flat_map(|lane| lane.senders.iter().copied()).collect();
+        senders.extend(recipient_senders.senders(pool));
+
+        senders
+            .into_iter()
+            .flat_map(|sender| pool.get_pending_transactions_by_sender(sender))
+            .collect()
+    }
+}
+
+/// Senders of pool transactions calling a lane recipient.
+///
+/// Senders are added from the pool events and dropped once they have no transaction left in the
+/// pool.
+#[derive(Debug, Clone, Default)]
+pub(super) struct LaneRecipientSenders {
+    senders: Arc<Mutex<HashSet<Address>>>,
+}
+
+impl LaneRecipientSenders {
+    /// Adds the senders of the pool transactions calling a lane recipient. Returns the future
+    /// following the pool events, `None` if no lane has recipients.
+    pub(super) fn track<P: TransactionPool>(
+        &self,
+        lanes: &PriorityLanes,
+        pool: &P,
+    ) -> Option<impl Future<Output = ()> + Send + 'static> {
+        let recipients = lanes.recipients();
+        if recipients.is_empty() {
+            return None;
+        }
+
+        let mut events = pool.new_transactions_listener();
+        // Transactions added before the listener was installed
+        for tx in pool.pending_transactions().into_iter().chain(pool.queued_transactions()) {
+            self.observe(&recipients, tx.sender(), tx.transaction.to());
+        }
+
+        let this = self.clone();
+        Some(async move {
+            while let Some(event) = events.recv().await {
+                let tx = &event.transaction;
+                this.observe(&recipients, tx.sender(), tx.transaction.to());
+            }
+        })
+    }
+
+    /// Adds the sender if the transaction calls a lane recipient.
+    fn observe(&self, recipients: &HashSet<Address>, sender: Address, to: Option<Address>) {
+        if to.is_some_and(|to| recipients.contains(&to)) {
+            self.senders.lock().expect("lane senders lock poisoned").insert(sender);
+        }
+    }
+
+    /// Returns the tracked senders, dropping those without transactions in the pool.
+    fn senders<P: TransactionPool>(&self, pool: &P) -> Vec<Address> {
+        let mut senders = self.senders.lock().expect("lane senders lock poisoned");
+        senders.retain(|sender| !pool.get_transactions_by_sender(*sender).is_empty());
+        senders.iter().copied().collect()
+    }
+}
+
+impl From<&BuilderArgs> for PriorityLanes {
+    fn from(args: &BuilderArgs) -> Self {
+        Self::new([
+            PriorityLane {
+                name: "bridge",
+                recipients: args.priority_bridge_contracts.iter().copied().collect(),
+                gas_budget: args.priority_bridge_gas,
+                ..Default::default()
+            },
+            PriorityLane {
+                name: "fee_collection",
+                senders: args.priority_fee_collectors.iter().copied().collect(),
+                gas_budget: args.priority_fee_collection_gas,
+                ..Default::default()
+            },
+            PriorityLane {
+                name: "allowlist",
+                senders: args.priority_allowlist.iter().copied().collect(),
+                gas_budget: args.priority_allowlist_gas,
+                ..Default::default()
+            },
+        ])
+    }
+}
+
+/// Priority lane metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "flashblock_builder_priority_lane")]
+struct PriorityLaneMetrics {
+    /// Number of transactions selected ahead of the pool ordering
+    selected_txs: Counter,
+    /// Gas limit of the transactions selected ahead of the pool ordering
+    selected_gas: Counter,
+    /// Number of transactions left to the pool ordering because the lane budget was spent
+    budget_exhausted_txs: Counter,
+}
+
+/// Lane transaction waiting to be selected.
+struct QueuedTx<T: PoolTransaction> {
+    lane: usize,
+    tx: Arc<ValidPoolTransaction<T>>,
+}
+
+/// Selection state of the priority lanes over the flashblocks of a block.
+pub(super) struct PriorityLaneQueue<T: PoolTransaction> {
+    lanes: PriorityLanes,
+    metrics: Vec<PriorityLaneMetrics>,
+    /// Gas limit of the transactions selected per lane in the current block
+    gas_used: Vec<u64>,
+    /// Lane transactions of the current flashblock, in selection order
+    queue: Vec<QueuedTx<T>>,
+    /// Transactions selected in the current flashblock, skipped by the pool ordering
+    selected: HashSet<TxHash>,
+}
+
+impl<T: PoolTransaction> PriorityLaneQueue<T> {
+    pub(super) fn new(lanes: PriorityLanes) -> Self {
+        let metrics = lanes
+            .lanes
+            .iter()
+            .map(|lane| PriorityLaneMetrics::new_with_labels(&[("lane", lane.name)]))
+            .collect();
+        let gas_used = vec![0; lanes.lanes.len()];
+        Self { lanes, metrics, gas_used, queue: Vec::new(), selected: HashSet::new() }
+    }
+
+    /// Replaces the queued transactions with the lane transactions among `candidates`.
+    ///
+    /// Per sender, transactions are taken in nonce order skipping the committed ones, and only
+    /// as long as they belong to a lane and pay the base fee, so a lane transaction never waits
+    /// on a regular one.
+    pub(super) fn refresh(
+        &mut self,
+        candidates: Vec<Arc<ValidPoolTransaction<T>>>,
+        committed: &HashSet<TxHash>,
+        base_fee: u64,
+    ) {
+        self.selected.clear();
+
+        let mut by_sender: HashMap<Address, Vec<Arc<ValidPoolTransaction<T>>>> = HashMap::new();
+        for tx in candidates {
+            if !committed.contains(tx.hash()) {
+                by_sender.entry(tx.sender()).or_default().push(tx);
+            }
+        }
+
+        let mut queue = Vec::new();
+        for (sender, mut txs) in by_sender {
+            txs.sort_by_key(|tx| tx.nonce());
+            let arrival = txs[0].timestamp;
+            let mut min_lane = 0;
+            for tx in txs {
+                if tx.transaction.max_fee_per_gas() < base_fee as u128 {
+                    break;
+                }
+                let Some(lane) = self.lanes.lane_of(sender, tx.transaction.to()) else { break };
+                // A transaction never takes precedence over a lower nonce of the same sender
+                min_lane = min_lane.max(lane);
+                queue.push((arrival, QueuedTx { lane: min_lane, tx }));
+            }
+        }
+        // Lanes in precedence order, then senders in arrival order with ascending nonces. The
+        // queue is popped from the back.
+        queue.sort_by_key(|(arrival, queued)| {
+            std::cmp::Reverse((queued.lane, *arrival, queued.tx.sender(), queued.tx.nonce()))
+        });
+        self.queue = queue.into_iter().map(|(_, queued)| queued).collect();
+    }
+
+    /// Returns the next lane transaction within the budget of its lane.
+    pub(super) fn next(&mut self) -> Option<Arc<ValidPoolTransaction<T>>> {
+        while let Some(QueuedTx { lane, tx }) = self.queue.pop() {
+            let gas_limit = tx.gas_limit();
+            let gas_used = self.gas_used[lane].saturating_add(gas_limit);
+            if self.lanes.lanes[lane].gas_budget.is_some_and(|budget| gas_used > budget) {
+                self.metrics[lane].budget_exhausted_txs.increment(1);
+                // Later transactions of the sender depend on this one
+                self.remove_sender(tx.sender(), tx.nonce());
+                continue;
+            }
+            self.gas_used[lane] = gas_used;
+            self.metrics[lane].selected_txs.increment(1);
+            self.metrics[lane].selected_gas.increment(gas_limit);
+            self.selected.insert(*tx.hash());
+            return Some(tx);
+        }
+        None
+    }
+
+    /// Returns whether the transaction was already selected in the current flashblock.
+    pub(super) fn is_selected(&self, hash: &TxHash) -> bool {
+        self.selected.contains(hash)
+    }
+
+    /// Drops the queued transactions of the sender from the given nonce on.
+    pub(super) fn remove_sender(&mut self, sender: Address, nonce: u64) {
+        self.queue.retain(|queued| queued.tx.sender() != sender || queued.tx.nonce() < nonce);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flashblocks::utils::mock::{
+        MockFbTransaction, MockFbTransactionFactory, MockValidFbTx,
+    };
+    use reth_transaction_pool::test_utils::MockTransaction;
+
+    fn lane(name: &'static str, sender: Address, gas_budget: Option<u64>) -> PriorityLane {
+        PriorityLane { name, senders: HashSet::from([sender]), gas_budget, ..Default::default() }
+    }
+
+    fn tx(f: &mut MockFbTransactionFactory, sender: Address, nonce: u64) -> Arc<MockValidFbTx> {
+        f.validated_arc(MockFbTransaction {
+            inner: MockTransaction::eip1559()
+                .with_sender(sender)
+                .with_nonce(nonce)
+                .with_gas_limit(21_000),
+        })
+    }
+
+    #[test]
+    fn test_empty_lanes_are_dropped() {
+        let lanes = PriorityLanes::new([
+            PriorityLane { name: "bridge", ..Default::default() },
+            lane("allowlist", Address::repeat_byte(1), None),
+        ]);
+        assert_eq!(lanes.lanes.len(), 1);
+        assert_eq!(lanes.lanes[0].name, "allowlist");
+        assert!(PriorityLanes::new([PriorityLane::default()]).is_empty());
+    }
+
+    #[test]
+    fn test_recipient_senders() {
+        let bridge = Address::repeat_byte(0xbb);
+        let lanes = PriorityLanes::new([PriorityLane {
+            name: "bridge",
+            recipients: HashSet::from([bridge]),
+            ..Default::default()
+        }]);
+        let recipient_senders = LaneRecipientSenders::default();
+        let (claimer, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        recipient_senders.observe(&lanes.recipients(), claimer, Some(bridge));
+        recipient_senders.observe(&lanes.recipients(), other, Some(Address::repeat_byte(3)));
+        recipient_senders.observe(&lanes.recipients(), other, None);
+        assert_eq!(*recipient_senders.senders.lock().unwrap(), HashSet::from([claimer]));
+    }
+
+    #[test]
+    fn test_lane_precedence_and_nonce_order() {
+        let mut f = MockFbTransactionFactory::default();
+        let (system, operator) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut queue = PriorityLaneQueue::new(PriorityLanes::new([
+            lane("fee_collection", system, None),
+            lane("allowlist", operator, None),
+        ]));
+
+        let operator_tx = tx(&mut f, operator, 0);
+        let system_txs = [tx(&mut f, system, 1), tx(&mut f, system, 0)];
+        let regular_tx = tx(&mut f, Address::repeat_byte(3), 0);
+        queue.refresh(
+            vec![operator_tx.clone(), system_txs[0].clone(), system_txs[1].clone(), regular_tx],
+            &HashSet::new(),
+            0,
+        );
+
+        assert_eq!(queue.next().unwrap().hash(), system_txs[1].hash());
+        assert_eq!(queue.next().unwrap().hash(), system_txs[0].hash());
+        assert_eq!(queue.next().unwrap().hash(), operator_tx.hash());
+        assert!(queue.next().is_none());
+        assert!(queue.is_selected(operator_tx.hash()));
+    }
+
+    #[test]
+    fn test_committed_and_invalid_transactions_are_skipped() {
+        let mut f = MockFbTransactionFactory::default();
+        let sender = Address::repeat_byte(1);
+        let mut queue =
+            PriorityLaneQueue::new(PriorityLanes::new([lane("allowlist", sender, None)]));
+        let txs = [tx(&mut f, sender, 0), tx(&mut f, sender, 1), tx(&mut f, sender, 2)];
+
+        queue.refresh(txs.to_vec(), &HashSet::from([*txs[0].hash()]), 0);
+        assert_eq!(queue.next().unwrap().hash(), txs[1].hash());
+        queue.remove_sender(sender, 1);
+        assert!(queue.next().is_none());
+
+        // Selections only hold for the flashblock they were made in
+        queue.refresh(Vec::new(), &HashSet::new(), 0);
+        assert!(!queue.is_selected(txs[1].hash()));
+    }
+
+    #[test]
+    fn test_gas_budget_spans_flashblocks() {
+        let mut f = MockFbTransactionFactory::default();
+        let sender = Address::repeat_byte(1);
+        let mut queue =
+            PriorityLaneQueue::new(PriorityLanes::new([lane("bridge", sender, Some(50_000))]));
+        let txs = [tx(&mut f, sender, 0), tx(&mut f, sender, 1), tx(&mut f, sender, 2)];
+
+        queue.refresh(txs[..1].to_vec(), &HashSet::new(), 0);
+        assert!(queue.next().is_some());
+
+        // The second transaction fits the remaining budget, the third is left to the pool
+        queue.refresh(txs[1..].to_vec(), &HashSet::from([*txs[0].hash()]), 0);
+        assert_eq!(queue.next().unwrap().hash(), txs[1].hash());
+        assert!(queue.next().is_none());
+        assert!(!queue.is_selected(txs[2].hash()));
+    }
+
+    #[test]
+    fn test_underpriced_transactions_stop_the_lane() {
+        let mut f = MockFbTransactionFactory::default();
+        let sender = Address::repeat_byte(1);
+        let mut queue =
+            PriorityLaneQueue::new(PriorityLanes::new([lane("allowlist", sender, None)]));
+        let txs = [tx(&mut f, sender, 0), tx(&mut f, sender, 1)];
+
+        queue.refresh(txs.to_vec(), &HashSet::new(), u64::MAX);
+        assert!(queue.next().is_none());
+    }
+}
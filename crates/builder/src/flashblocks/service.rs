@@ -5,11 +5,13 @@ use crate::{
         generator::BlockPayloadJobGenerator,
         handler::FlashblocksPayloadHandler,
         handler_ctx::FlashblockHandlerContext,
+        priority_lanes::LaneRecipientSenders,
         utils::{
             cache::FlashblockPayloadsCache,
             p2p::{Message, AGENT_VERSION, FLASHBLOCKS_STREAM_PROTOCOL},
             wspub::WebSocketPublisher,
         },
+        validation::validate_canonical_blocks,
         BuilderConfig,
     },
     metrics::{tokio::FlashblocksTaskMetrics, BuilderMetrics},
@@ -22,10 +24,13 @@ use reth_basic_payload_builder::BasicPayloadJobGeneratorConfig;
 use reth_node_api::NodeTypes;
 use reth_node_builder::{components::PayloadServiceBuilder, BuilderContext};
 use reth_optimism_evm::OpEvmConfig;
+use reth_optimism_primitives::OpPrimitives;
 use reth_payload_builder::{PayloadBuilderHandle, PayloadBuilderService};
-use reth_provider::CanonStateSubscriptions;
+use xlayer_legacy_rpc::canon_mux::CanonStateMux;
 
-pub struct FlashblocksServiceBuilder(pub BuilderConfig);
+/// Flashblocks payload service builder, following the canonical chain through the shared
+/// canonical state notifications.
+pub struct FlashblocksServiceBuilder(pub BuilderConfig, pub CanonStateMux<OpPrimitives>);
 
 impl FlashblocksServiceBuilder {
     fn spawn_payload_builder_service<Node, Pool>(
@@ -118,6 +123,12 @@ impl FlashblocksServiceBuilder {
         )
         .wrap_err("failed to create ws publisher")?
         .into();
+
+        let lane_recipient_senders = LaneRecipientSenders::default();
+        if let Some(tracker) = lane_recipient_senders.track(&self.0.priority_lanes, &pool) {
+            ctx.task_executor().spawn(Box::pin(tracker));
+        }
+
         let payload_builder = FlashblocksBuilder::new(
             OpEvmConfig::optimism(ctx.chain_spec()),
             pool,
@@ -131,6 +142,7 @@ impl FlashblocksServiceBuilder {
             ws_pub.clone(),
             metrics.clone(),
             task_metrics.clone(),
+            lane_recipient_senders,
         );
         let payload_job_config = BasicPayloadJobGeneratorConfig::default();
 
@@ -143,8 +155,11 @@ impl FlashblocksServiceBuilder {
             self.0.block_time_leeway,
         );
 
-        let (payload_service, payload_builder_handle) =
-            PayloadBuilderService::new(payload_generator, ctx.provider().canonical_state_stream());
+        self.1.spawn(ctx.provider(), ctx.task_executor());
+        let (payload_service, payload_builder_handle) = PayloadBuilderService::new(
+            payload_generator,
+            Box::pin(self.1.subscribe("payload_builder").into_stream()),
+        );
 
         let handler_ctx = FlashblockHandlerContext::new(
             &ctx.provider().clone(),
@@ -179,6 +194,15 @@ impl FlashblocksServiceBuilder {
             Box::pin(task_metrics.payload_handler.instrument(payload_handler.run())),
         );
 
+        if self.0.flashblocks.validate_canonical_blocks {
+            ctx.task_executor().spawn(Box::pin(validate_canonical_blocks(
+                ctx.provider().clone(),
+                OpEvmConfig::optimism(ctx.chain_spec()),
+                metrics.clone(),
+                self.1.subscribe("canonical_block_validation").into_stream(),
+            )));
+        }
+
         // Spawn the tokio metrics collector (records metrics every second)
         task_metrics.clone().spawn_metrics_collector(Duration::from_secs(1));
 
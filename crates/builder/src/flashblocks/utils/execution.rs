@@ -12,6 +12,7 @@ pub enum TxnExecutionResult {
     #[display("TransactionGasLimitExceeded: total_gas_used={_0} tx_gas_limit={_1}")]
     TransactionGasLimitExceeded(u64, u64, u64),
     SequencerTransaction,
+    ConditionalMismatch,
     NonceTooLow,
     InteropFailed,
     #[display("InternalError({_0})")]
@@ -1,15 +1,20 @@
 use alloy_primitives::B256;
-use xlayer_trace_monitor::{from_b256, get_global_tracer, TransactionProcessId};
+use xlayer_monitor::{ingress_tracker, trace_transaction, tx_timestamps};
+use xlayer_trace_monitor::TransactionProcessId;
 
 pub(crate) fn monitor(block_number: u64, tx_hashes: Vec<B256>) {
     // For X Layer. Log transaction execution end even for failed transactions
-    if let Some(tracer) = get_global_tracer() {
-        for tx_hash in tx_hashes.iter() {
-            tracer.log_transaction(
-                from_b256(*tx_hash),
-                TransactionProcessId::SeqTxExecutionEnd,
-                Some(block_number),
-            );
-        }
+    for tx_hash in tx_hashes.iter() {
+        trace_transaction(*tx_hash, TransactionProcessId::SeqTxExecutionEnd, Some(block_number));
+    }
+
+    // For X Layer. Measure RPC acceptance to flashblock inclusion latency
+    if let Some(tracker) = ingress_tracker() {
+        tracker.on_included(&tx_hashes);
+    }
+
+    // For X Layer. Timestamp the flashblock inclusion for xlayer_getTransactionTimestamps
+    if let Some(timestamps) = tx_timestamps() {
+        timestamps.on_flashblock_included(&tx_hashes);
     }
 }
@@ -24,7 +24,11 @@ use tokio_tungstenite::{
 };
 use tracing::{debug, info, trace, warn};
 
-use crate::{metrics::tokio::MonitoredTask, metrics::BuilderMetrics};
+use crate::{
+    flashblocks::preconf::{FlashblockMessage, Preconfirmation},
+    metrics::tokio::MonitoredTask,
+    metrics::BuilderMetrics,
+};
 
 /// A WebSockets publisher that accepts connections from client websockets and broadcasts to them
 /// updates about new flashblocks. It maintains a count of sent messages and active subscriptions.
@@ -66,6 +70,15 @@ impl WebSocketPublisher {
     }
 
     pub fn publish(&self, payload: &OpFlashblockPayload) -> io::Result<usize> {
+        self.publish_preconfirmed(payload, &[])
+    }
+
+    /// Publishes a flashblock with the signed preconfirmations of its transactions.
+    pub fn publish_preconfirmed(
+        &self,
+        payload: &OpFlashblockPayload,
+        preconfirmations: &[Preconfirmation],
+    ) -> io::Result<usize> {
         // Serialize the payload to a UTF-8 string
         // serialize only once, then just copy around only a pointer
         // to the serialized data for each subscription.
@@ -76,9 +89,10 @@ impl WebSocketPublisher {
             id = %payload.payload_id,
             index = payload.index,
             base = payload.base.is_some(),
+            preconfirmations = preconfirmations.len(),
         );
 
-        let serialized = serde_json::to_string(payload)?;
+        let serialized = serde_json::to_string(&FlashblockMessage { payload, preconfirmations })?;
         let utf8_bytes = Utf8Bytes::from(serialized);
         let size = utf8_bytes.len();
         // Send the serialized payload to all subscribers
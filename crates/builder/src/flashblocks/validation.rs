@@ -0,0 +1,161 @@
+//! Debug validation of canonical blocks built from flashblocks.
+//!
+//! Blocks are built flashblock by flashblock, reusing the cached reads and the trie updates of the
+//! previous flashblock. A bug in that incremental path produces a block other nodes can't import,
+//! which is only noticed once they fail to follow the chain. When enabled, every committed
+//! canonical block is re-executed on its parent state with the standard block executor, and the
+//! resulting state root and receipts root are compared against the header.
+//!
+//! Re-execution runs next to block building and roughly doubles the execution load, the check is
+//! meant for debugging deployments. Notifications missed while a validation lags behind the chain
+//! are skipped.
+
+use crate::{metrics::BuilderMetrics, traits::ClientBounds};
+use std::{sync::Arc, time::Instant};
+
+use alloy_consensus::BlockHeader as _;
+use alloy_primitives::B256;
+use eyre::WrapErr as _;
+use futures::{Stream, StreamExt};
+use reth::revm::database::StateProviderDatabase;
+use reth_evm::{execute::Executor as _, ConfigureEvm as _};
+use reth_optimism_consensus::calculate_receipt_root_no_memo_optimism;
+use reth_optimism_evm::OpEvmConfig;
+use reth_optimism_primitives::{OpBlock, OpPrimitives};
+use reth_primitives_traits::RecoveredBlock;
+use reth_provider::{CanonStateNotification, HashedPostStateProvider as _, StateRootProvider as _};
+
+/// Roots of a block, as committed in its header or computed by re-executing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlockRoots {
+    state_root: B256,
+    receipts_root: B256,
+}
+
+impl BlockRoots {
+    /// Returns the names of the roots that differ from the expected ones.
+    fn mismatches(&self, expected: &Self) -> Vec<&'static str> {
+        let mut mismatches = Vec::new();
+        if self.state_root != expected.state_root {
+            mismatches.push("state_root");
+        }
+        if self.receipts_root != expected.receipts_root {
+            mismatches.push("receipts_root");
+        }
+        mismatches
+    }
+}
+
+/// Re-executes the canonical blocks until the notifications end.
+pub(crate) async fn validate_canonical_blocks<Client>(
+    client: Client,
+    evm_config: OpEvmConfig,
+    metrics: Arc<BuilderMetrics>,
+    notifications: impl Stream<Item = CanonStateNotification<OpPrimitives>>,
+) where
+    Client: ClientBounds + 'static,
+{
+    tracing::info!(target: "payload_builder", "canonical block validation started");
+    let mut notifications = std::pin::pin!(notifications);
+    while let Some(notification) = notifications.next().await {
+        let chain = notification.committed();
+        for block in chain.blocks_iter() {
+            let block = block.clone();
+            let client = client.clone();
+            let evm_config = evm_config.clone();
+            let metrics = metrics.clone();
+            // Execution and state root calculation are blocking
+            let validation = tokio::task::spawn_blocking(move || {
+                validate_block(&client, &evm_config, &metrics, &block)
+            });
+            match validation.await {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => {
+                    tracing::warn!(target: "payload_builder", %err, "failed to validate canonical block");
+                }
+                Err(err) => {
+                    tracing::warn!(target: "payload_builder", %err, "canonical block validation panicked");
+                }
+            }
+        }
+    }
+    tracing::debug!(target: "payload_builder", "canonical state stream closed, block validation stopped");
+}
+
+/// Re-executes a block on its parent state and compares the roots with its header.
+fn validate_block<Client>(
+    client: &Client,
+    evm_config: &OpEvmConfig,
+    metrics: &BuilderMetrics,
+    block: &RecoveredBlock<OpBlock>,
+) -> eyre::Result<()>
+where
+    Client: ClientBounds,
+{
+    let start = Instant::now();
+    let number = block.number();
+    let state_provider = client
+        .state_by_block_hash(block.parent_hash())
+        .wrap_err_with(|| format!("failed to get parent state of block {number}"))?;
+
+    let output = evm_config
+        .executor(StateProviderDatabase::new(&state_provider))
+        .execute(block)
+        .wrap_err_with(|| format!("failed to execute block {number}"))?;
+
+    let receipts_root = calculate_receipt_root_no_memo_optimism(
+        &output.result.receipts,
+        &client.chain_spec(),
+        block.timestamp(),
+    );
+    let hashed_state = state_provider.hashed_post_state(&output.state);
+    let state_root = state_provider
+        .state_root(hashed_state)
+        .wrap_err_with(|| format!("failed to calculate state root of block {number}"))?;
+
+    metrics.canonical_block_validation_duration.record(start.elapsed());
+    metrics.canonical_blocks_validated_count.increment(1);
+
+    let executed = BlockRoots { state_root, receipts_root };
+    let expected =
+        BlockRoots { state_root: block.state_root(), receipts_root: block.receipts_root() };
+    let mismatches = executed.mismatches(&expected);
+    if mismatches.is_empty() {
+        tracing::debug!(target: "payload_builder", number, hash = %block.hash(), "canonical block roots match re-execution");
+        return Ok(());
+    }
+
+    metrics.canonical_block_divergence_count.increment(1);
+    tracing::error!(
+        target: "payload_builder",
+        number,
+        hash = %block.hash(),
+        ?mismatches,
+        expected_state_root = %expected.state_root,
+        executed_state_root = %executed.state_root,
+        expected_receipts_root = %expected.receipts_root,
+        executed_receipts_root = %executed.receipts_root,
+        "canonical block diverges from standard execution"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_mismatches() {
+        let roots = BlockRoots {
+            state_root: B256::with_last_byte(1),
+            receipts_root: B256::with_last_byte(2),
+        };
+        assert!(roots.mismatches(&roots).is_empty());
+
+        let executed = BlockRoots { state_root: B256::with_last_byte(3), ..roots };
+        assert_eq!(executed.mismatches(&roots), ["state_root"]);
+
+        let executed = BlockRoots { state_root: B256::ZERO, receipts_root: B256::ZERO };
+        assert_eq!(executed.mismatches(&roots), ["state_root", "receipts_root"]);
+    }
+}
@@ -32,6 +32,12 @@ pub struct BuilderMetrics {
     pub invalid_built_blocks_count: Counter,
     /// Number of invalid synced blocks
     pub invalid_synced_blocks_count: Counter,
+    /// Number of canonical blocks re-executed by the validation task
+    pub canonical_blocks_validated_count: Counter,
+    /// Number of canonical blocks whose roots diverge from their re-execution
+    pub canonical_block_divergence_count: Counter,
+    /// Histogram of the duration of canonical block re-execution
+    pub canonical_block_validation_duration: Histogram,
     /// Histogram of fetching transactions from the pool duration
     pub transaction_pool_fetch_duration: Histogram,
     /// Latest time taken to fetch tx from the pool
@@ -94,6 +100,10 @@ pub struct BuilderMetrics {
     pub flashblocks_time_drift: Histogram,
     /// Time offset we used for first flashblock
     pub first_flashblock_time_offset: Histogram,
+    /// Number of preconfirmations signed for published flashblocks
+    pub preconfirmations_signed: Counter,
+    /// Number of preconfirmations that failed to be signed
+    pub preconfirmation_sign_failures: Counter,
 }
 
 impl BuilderMetrics {
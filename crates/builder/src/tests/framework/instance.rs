@@ -47,6 +47,7 @@ use std::{
 use tokio::{sync::oneshot, task::JoinHandle};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tokio_util::sync::CancellationToken;
+use xlayer_legacy_rpc::canon_mux::{CanonStateMux, CANON_STATE_MUX_CAPACITY};
 
 /// Represents a type that emulates a local in-process instance of the OP builder node.
 /// This node uses IPC as the communication channel for the RPC server Engine API.
@@ -113,12 +114,12 @@ impl LocalInstance {
             .with_database(create_test_db(config.clone()))
             .with_launch_context(task_manager.executor())
             .with_types::<OpNode>()
-            .with_components(
-                op_node
-                    .components()
-                    .pool(pool_component(&rollup_args))
-                    .payload(FlashblocksServiceBuilder(builder_config)),
-            )
+            .with_components(op_node.components().pool(pool_component(&rollup_args)).payload(
+                FlashblocksServiceBuilder(
+                    builder_config,
+                    CanonStateMux::new(CANON_STATE_MUX_CAPACITY),
+                ),
+            ))
             .with_add_ons(addons)
             .on_rpc_started(move |_, _| {
                 let _ = rpc_ready_tx.send(());
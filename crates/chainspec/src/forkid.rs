@@ -0,0 +1,208 @@
+//! Fork ids of a chain at its hardfork activation boundaries.
+//!
+//! Peers and the consensus layer only agree on the fork schedule if they derive the same
+//! [`ForkId`] at every block. X Layer chains start from a non-zero genesis with most hardforks
+//! active at genesis, which are not part of the fork hash, so the ids only change at the
+//! hardforks activated after genesis. [`activation_boundaries`] computes the fork ids right
+//! before and at each of these activations and [`verify_activation_boundaries`] checks the
+//! invariants they must satisfy. The vectors of the built-in chains are pinned by tests, a
+//! changed fork schedule or genesis fails them instead of splitting the network.
+
+use alloy_consensus::BlockHeader as _;
+use reth_chainspec::{EthChainSpec, ForkCondition, ForkId, Hardforks, Head};
+use reth_optimism_chainspec::OpChainSpec;
+
+/// Fork ids around the activation of a hardfork activated after genesis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActivationBoundary {
+    /// Name of the hardfork
+    pub fork: &'static str,
+    /// Activation block number or timestamp
+    pub condition: ForkCondition,
+    /// Fork id of the last head before the activation
+    pub before: ForkId,
+    /// Fork id of the first head with the hardfork active
+    pub at: ForkId,
+}
+
+/// Returns the fork id of the chain at the given block and timestamp.
+pub fn fork_id_at(spec: &OpChainSpec, number: u64, timestamp: u64) -> ForkId {
+    spec.fork_id(&Head { number, timestamp, ..Default::default() })
+}
+
+/// Returns the boundaries of the hardforks activated after genesis, in activation order.
+pub fn activation_boundaries(spec: &OpChainSpec) -> Vec<ActivationBoundary> {
+    let genesis = spec.genesis_header();
+    let (genesis_number, genesis_timestamp) = (genesis.number(), genesis.timestamp());
+    spec.forks_iter()
+        .filter_map(|(fork, condition)| {
+            // Timestamp activations are checked at the genesis block, block activations at the
+            // genesis timestamp, X Layer chains don't mix both after genesis
+            let (before, at) = match condition {
+                ForkCondition::Block(block)
+                | ForkCondition::TTD { fork_block: Some(block), .. }
+                    if block > genesis_number =>
+                {
+                    (
+                        fork_id_at(spec, block - 1, genesis_timestamp),
+                        fork_id_at(spec, block, genesis_timestamp),
+                    )
+                }
+                ForkCondition::Timestamp(timestamp) if timestamp > genesis_timestamp => (
+                    fork_id_at(spec, genesis_number, timestamp - 1),
+                    fork_id_at(spec, genesis_number, timestamp),
+                ),
+                _ => return None,
+            };
+            Some(ActivationBoundary { fork: fork.name(), condition, before, at })
+        })
+        .collect()
+}
+
+/// Checks that the fork id only changes at the activation boundaries, by the activation.
+pub fn verify_activation_boundaries(spec: &OpChainSpec) -> Result<(), String> {
+    let genesis = spec.genesis_header();
+    let genesis_id = fork_id_at(spec, genesis.number(), genesis.timestamp());
+    let boundaries = activation_boundaries(spec);
+    match boundaries.first() {
+        Some(first) if first.before != genesis_id => {
+            return Err(format!(
+                "fork id changes between genesis and the activation of {}",
+                first.fork
+            ));
+        }
+        None if genesis_id.next != 0 => {
+            return Err(format!("genesis fork id announces a fork at {}", genesis_id.next));
+        }
+        _ => {}
+    }
+
+    let mut previous: Option<&ActivationBoundary> = None;
+    for boundary in &boundaries {
+        let activation = match boundary.condition {
+            ForkCondition::Timestamp(value) | ForkCondition::Block(value) => value,
+            ForkCondition::TTD { fork_block: Some(value), .. } => value,
+            _ => return Err(format!("{} has no activation point", boundary.fork)),
+        };
+        if boundary.before.next != activation {
+            return Err(format!(
+                "fork id before {} announces {} instead of {activation}",
+                boundary.fork, boundary.before.next
+            ));
+        }
+        // Hardforks sharing an activation share a boundary
+        let shared = previous.is_some_and(|previous| previous.at == boundary.at);
+        if !shared && boundary.at.hash != boundary.before.hash + activation {
+            return Err(format!("fork hash doesn't change at the activation of {}", boundary.fork));
+        }
+        if let Some(previous) = previous.filter(|_| !shared)
+            && previous.at != boundary.before
+        {
+            return Err(format!(
+                "fork id changes between the activations of {} and {}",
+                previous.fork, boundary.fork
+            ));
+        }
+        previous = Some(boundary);
+    }
+
+    if let Some(last) = boundaries.last()
+        && last.at.next != 0
+    {
+        return Err(format!("fork id after {} announces another fork", last.fork));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        XLAYER_DEVNET, XLAYER_MAINNET, XLAYER_MAINNET_JOVIAN_TIMESTAMP, XLAYER_TESTNET,
+        XLAYER_TESTNET_JOVIAN_TIMESTAMP,
+    };
+    use alloy_primitives::hex;
+    use reth_chainspec::ForkHash;
+    use reth_optimism_forks::{OpHardfork, OpHardforks};
+
+    fn fork_id(hash: [u8; 4], next: u64) -> ForkId {
+        ForkId { hash: ForkHash(hash), next }
+    }
+
+    #[test]
+    fn test_xlayer_mainnet_fork_ids() {
+        let spec = &XLAYER_MAINNET;
+        let genesis = spec.genesis_header();
+        let before = fork_id(hex!("953f3833"), XLAYER_MAINNET_JOVIAN_TIMESTAMP);
+        let after = fork_id(hex!("aeb08a55"), 0);
+
+        assert_eq!(fork_id_at(spec, genesis.number(), genesis.timestamp()), before);
+        assert_eq!(
+            activation_boundaries(spec),
+            [ActivationBoundary {
+                fork: "Jovian",
+                condition: ForkCondition::Timestamp(XLAYER_MAINNET_JOVIAN_TIMESTAMP),
+                before,
+                at: after,
+            }]
+        );
+        verify_activation_boundaries(spec).unwrap();
+    }
+
+    #[test]
+    fn test_xlayer_testnet_fork_ids() {
+        let spec = &XLAYER_TESTNET;
+        let genesis = spec.genesis_header();
+        let before = fork_id(hex!("6c7ee7ce"), XLAYER_TESTNET_JOVIAN_TIMESTAMP);
+        let after = fork_id(hex!("20b30a29"), 0);
+
+        assert_eq!(fork_id_at(spec, genesis.number(), genesis.timestamp()), before);
+        assert_eq!(
+            activation_boundaries(spec),
+            [ActivationBoundary {
+                fork: "Jovian",
+                condition: ForkCondition::Timestamp(XLAYER_TESTNET_JOVIAN_TIMESTAMP),
+                before,
+                at: after,
+            }]
+        );
+        verify_activation_boundaries(spec).unwrap();
+    }
+
+    #[test]
+    fn test_xlayer_devnet_fork_ids() {
+        // All hardforks, Jovian included, are active at the devnet genesis
+        let spec = &XLAYER_DEVNET;
+        let genesis = spec.genesis_header();
+        assert!(activation_boundaries(spec).is_empty());
+        assert_eq!(fork_id_at(spec, genesis.number(), genesis.timestamp()).next, 0);
+        verify_activation_boundaries(spec).unwrap();
+    }
+
+    #[test]
+    fn test_first_holocene_block() {
+        // Holocene and later hardforks apply from the genesis block on, the first block built on
+        // top of it already uses the Holocene header rules
+        for spec in [&XLAYER_MAINNET, &XLAYER_TESTNET, &XLAYER_DEVNET] {
+            let genesis = spec.genesis_header();
+            for timestamp in [genesis.timestamp(), genesis.timestamp() + 1] {
+                assert!(spec.is_holocene_active_at_timestamp(timestamp));
+                assert!(spec.is_isthmus_active_at_timestamp(timestamp));
+            }
+            assert!(spec.fork(OpHardfork::Holocene).active_at_timestamp(0));
+        }
+    }
+
+    #[test]
+    fn test_jovian_boundary() {
+        for (spec, jovian) in [
+            (&XLAYER_MAINNET, XLAYER_MAINNET_JOVIAN_TIMESTAMP),
+            (&XLAYER_TESTNET, XLAYER_TESTNET_JOVIAN_TIMESTAMP),
+        ] {
+            assert!(!spec.is_jovian_active_at_timestamp(jovian - 1));
+            assert!(spec.is_jovian_active_at_timestamp(jovian));
+            let number = spec.genesis_header().number();
+            assert_ne!(fork_id_at(spec, number, jovian - 1), fork_id_at(spec, number, jovian));
+        }
+    }
+}
@@ -0,0 +1,183 @@
+//! Streaming parser of genesis files.
+//!
+//! The allocs of the X Layer genesis files hold millions of accounts. Parsing them into a
+//! [`Genesis`] materializes the whole alloc map, which dominates the memory of the process. The
+//! streaming parser hands every alloc entry to a callback as it is read and returns the genesis
+//! without its alloc, so only one account is held at a time.
+
+use std::{fmt, io::Read};
+
+use alloy_genesis::{Genesis, GenesisAccount};
+use alloy_primitives::Address;
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, Visitor},
+    Deserializer,
+};
+use serde_json::{Map, Value};
+
+/// Key of the alloc in a genesis file.
+const ALLOC_KEY: &str = "alloc";
+
+/// Parses a genesis file, calling `on_account` with every alloc entry in file order instead of
+/// collecting them. Returns the genesis with an empty alloc and the number of alloc entries.
+///
+/// An error returned by `on_account` aborts parsing and is returned as is.
+pub fn parse_genesis_streaming<R, F>(reader: R, on_account: F) -> eyre::Result<(Genesis, u64)>
+where
+    R: Read,
+    F: FnMut(Address, GenesisAccount) -> eyre::Result<()>,
+{
+    let mut visitor = GenesisVisitor { on_account, accounts: 0, error: None };
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let fields = match (&mut deserializer).deserialize_map(&mut visitor) {
+        Ok(fields) => fields,
+        Err(err) => return Err(visitor.error.take().unwrap_or_else(|| err.into())),
+    };
+    deserializer.end()?;
+
+    let genesis = serde_json::from_value(Value::Object(fields))?;
+    Ok((genesis, visitor.accounts))
+}
+
+/// Collects the top level fields of the genesis, streaming the alloc entries.
+struct GenesisVisitor<F> {
+    on_account: F,
+    accounts: u64,
+    /// Error returned by the callback, serde errors can't carry it
+    error: Option<eyre::Report>,
+}
+
+impl<'de, F> Visitor<'de> for &mut GenesisVisitor<F>
+where
+    F: FnMut(Address, GenesisAccount) -> eyre::Result<()>,
+{
+    type Value = Map<String, Value>;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a genesis object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut fields = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == ALLOC_KEY {
+                map.next_value_seed(AllocSeed(&mut *self))?;
+            } else {
+                let value = map.next_value()?;
+                fields.insert(key, value);
+            }
+        }
+        Ok(fields)
+    }
+}
+
+/// Streams the entries of the alloc to the callback.
+struct AllocSeed<'a, F>(&'a mut GenesisVisitor<F>);
+
+impl<'de, F> DeserializeSeed<'de> for AllocSeed<'_, F>
+where
+    F: FnMut(Address, GenesisAccount) -> eyre::Result<()>,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, F> Visitor<'de> for AllocSeed<'_, F>
+where
+    F: FnMut(Address, GenesisAccount) -> eyre::Result<()>,
+{
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a map of addresses to genesis accounts")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<(), E> {
+        // `"alloc": null`
+        Ok(())
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let visitor = self.0;
+        while let Some((address, account)) = map.next_entry::<Address, GenesisAccount>()? {
+            if let Err(err) = (visitor.on_account)(address, account) {
+                visitor.error = Some(err);
+                return Err(de::Error::custom("genesis account callback failed"));
+            }
+            visitor.accounts += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{B256, U256};
+
+    const GENESIS: &str = r#"{
+        "config": { "chainId": 196 },
+        "alloc": {
+            "0x0000000000000000000000000000000000000001": { "balance": "0x1" },
+            "0x0000000000000000000000000000000000000002": {
+                "balance": "0x2",
+                "nonce": "0x1",
+                "code": "0x6000",
+                "storage": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001": "0x0000000000000000000000000000000000000000000000000000000000000002"
+                }
+            }
+        },
+        "nonce": "0x0",
+        "timestamp": "0x0",
+        "extraData": "0x",
+        "gasLimit": "0x1000000",
+        "difficulty": "0x0",
+        "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+        "coinbase": "0x0000000000000000000000000000000000000000",
+        "number": "0x3039"
+    }"#;
+
+    #[test]
+    fn test_parse_genesis_streaming() {
+        let mut accounts = Vec::new();
+        let (genesis, count) = parse_genesis_streaming(GENESIS.as_bytes(), |address, account| {
+            accounts.push((address, account));
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(count, 2);
+        assert!(genesis.alloc.is_empty());
+        assert_eq!(genesis.config.chain_id, 196);
+        assert_eq!(genesis.number, Some(12345));
+
+        // Same accounts as the regular parser, in file order
+        let expected: Genesis = serde_json::from_str(GENESIS).unwrap();
+        assert_eq!(accounts, expected.alloc.into_iter().collect::<Vec<_>>());
+        assert_eq!(accounts[1].1.balance, U256::from(2));
+        assert_eq!(
+            accounts[1].1.storage.as_ref().unwrap()[&B256::with_last_byte(1)],
+            B256::with_last_byte(2)
+        );
+    }
+
+    #[test]
+    fn test_parse_genesis_streaming_errors() {
+        // Callback errors are returned as is
+        let err = parse_genesis_streaming(GENESIS.as_bytes(), |_, _| Err(eyre::eyre!("disk full")))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+
+        // Invalid accounts fail
+        let invalid = GENESIS.replace("\"0x1\" }", "\"one\" }");
+        assert!(parse_genesis_streaming(invalid.as_bytes(), |_, _| Ok(())).is_err());
+
+        // Trailing data fails
+        let trailing = format!("{GENESIS} {{}}");
+        assert!(parse_genesis_streaming(trailing.as_bytes(), |_, _| Ok(())).is_err());
+    }
+}
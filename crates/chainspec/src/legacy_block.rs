@@ -0,0 +1,121 @@
+//! Genesis block number of chains migrated from a legacy chain.
+//!
+//! X Layer chains start on top of the last block of their legacy chain, given by the
+//! `legacyXLayerBlock` field of the genesis config. The genesis file may also carry the top level
+//! `number`, both must agree. Every chain spec, named or parsed from a genesis file, resolves the
+//! genesis block number through [`resolve_genesis_number`].
+
+use alloy_genesis::Genesis;
+use eyre::bail;
+use tracing::debug;
+
+/// Key of the legacy block in the genesis config.
+pub const LEGACY_XLAYER_BLOCK_KEY: &str = "legacyXLayerBlock";
+
+/// Upper bound of `legacyXLayerBlock`, anything above is certainly a typo.
+const MAX_LEGACY_XLAYER_BLOCK: u64 = u64::MAX / 2;
+
+/// Returns the validated `legacyXLayerBlock` of the genesis config, `None` if not set.
+pub fn legacy_xlayer_block(genesis: &Genesis) -> eyre::Result<Option<u64>> {
+    let Some(value) = genesis.config.extra_fields.get(LEGACY_XLAYER_BLOCK_KEY) else {
+        return Ok(None);
+    };
+    let Some(legacy_block) = value.as_u64() else {
+        bail!("{LEGACY_XLAYER_BLOCK_KEY} must be an unsigned integer, got {value}");
+    };
+    if legacy_block == 0 || legacy_block >= MAX_LEGACY_XLAYER_BLOCK {
+        bail!(
+            "{LEGACY_XLAYER_BLOCK_KEY} {legacy_block} is out of range, expected a block number between 1 and {MAX_LEGACY_XLAYER_BLOCK}"
+        );
+    }
+    Ok(Some(legacy_block))
+}
+
+/// Returns the genesis block number: `legacyXLayerBlock` if set, the `number` of the genesis
+/// otherwise.
+///
+/// A `number` of zero is a placeholder of genesis files predating the legacy block and yields to
+/// it. Any other `number` different from `legacyXLayerBlock` is an error, as the two would
+/// describe different genesis blocks.
+pub fn resolve_genesis_number(genesis: &Genesis) -> eyre::Result<u64> {
+    match (legacy_xlayer_block(genesis)?, genesis.number) {
+        (Some(legacy_block), Some(number)) if number != 0 && number != legacy_block => bail!(
+            "genesis number {number} conflicts with {LEGACY_XLAYER_BLOCK_KEY} {legacy_block}, they must be equal or number must be omitted"
+        ),
+        (Some(legacy_block), _) => Ok(legacy_block),
+        (None, number) => Ok(number.unwrap_or_default()),
+    }
+}
+
+/// Sets the `number` of the genesis to its resolved genesis block number.
+pub fn apply_legacy_xlayer_block(genesis: &mut Genesis) -> eyre::Result<()> {
+    let number = resolve_genesis_number(genesis)?;
+    if genesis.number.unwrap_or_default() != number {
+        debug!("Overriding genesis.number from {:?} to {number}", genesis.number);
+        genesis.number = Some(number);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn genesis(legacy_block: Option<Value>, number: Option<u64>) -> Genesis {
+        let mut genesis = Genesis::default();
+        if let Some(legacy_block) = legacy_block {
+            genesis.config.extra_fields.insert(LEGACY_XLAYER_BLOCK_KEY.to_string(), legacy_block);
+        }
+        genesis.number = number;
+        genesis
+    }
+
+    #[test]
+    fn test_resolve_genesis_number() {
+        // Without legacy block, the number is used as is
+        assert_eq!(resolve_genesis_number(&genesis(None, None)).unwrap(), 0);
+        assert_eq!(resolve_genesis_number(&genesis(None, Some(100))).unwrap(), 100);
+
+        // The legacy block overrides a missing or zero number and agrees with an equal one
+        for number in [None, Some(0), Some(12345)] {
+            assert_eq!(
+                resolve_genesis_number(&genesis(Some(12345.into()), number)).unwrap(),
+                12345,
+                "number {number:?}"
+            );
+        }
+
+        // A different number conflicts
+        let err = resolve_genesis_number(&genesis(Some(12345.into()), Some(100))).unwrap_err();
+        assert!(err.to_string().contains("conflicts"), "{err}");
+    }
+
+    #[test]
+    fn test_legacy_xlayer_block_validation() {
+        for (value, message) in [
+            (Value::from(0), "out of range"),
+            (Value::from(u64::MAX), "out of range"),
+            (Value::from(-1), "must be an unsigned integer"),
+            (Value::from("12345"), "must be an unsigned integer"),
+        ] {
+            let err = resolve_genesis_number(&genesis(Some(value.clone()), None)).unwrap_err();
+            assert!(err.to_string().contains(message), "{value}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_apply_legacy_xlayer_block() {
+        let mut with_legacy = genesis(Some(12345.into()), Some(0));
+        apply_legacy_xlayer_block(&mut with_legacy).unwrap();
+        assert_eq!(with_legacy.number, Some(12345));
+
+        let mut without_legacy = genesis(None, None);
+        apply_legacy_xlayer_block(&mut without_legacy).unwrap();
+        assert_eq!(without_legacy.number, None);
+
+        let mut conflicting = genesis(Some(12345.into()), Some(100));
+        assert!(apply_legacy_xlayer_block(&mut conflicting).is_err());
+        assert_eq!(conflicting.number, Some(100));
+    }
+}
@@ -2,11 +2,26 @@
 //!
 //! This crate provides chain specifications for XLayer mainnet and testnet networks.
 
+mod forkid;
+mod genesis_alloc;
+mod legacy_block;
+mod network_defaults;
 mod parser;
 mod xlayer_devnet;
 mod xlayer_mainnet;
 mod xlayer_testnet;
 
+pub use forkid::{
+    activation_boundaries, fork_id_at, verify_activation_boundaries, ActivationBoundary,
+};
+pub use genesis_alloc::parse_genesis_streaming;
+pub use legacy_block::{
+    apply_legacy_xlayer_block, legacy_xlayer_block, resolve_genesis_number, LEGACY_XLAYER_BLOCK_KEY,
+};
+pub use network_defaults::{
+    apply_network_defaults, network_defaults, NetworkDefaults, NETWORK_DEFAULTS_ENV,
+    XLAYER_DEVNET_DEFAULTS, XLAYER_MAINNET_DEFAULTS, XLAYER_TESTNET_DEFAULTS,
+};
 pub use parser::XLayerChainSpecParser;
 pub use xlayer_devnet::XLAYER_DEVNET;
 pub use xlayer_mainnet::XLAYER_MAINNET;
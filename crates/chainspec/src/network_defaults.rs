@@ -0,0 +1,235 @@
+//! Chain-aware defaults for running several XLayer networks on one host.
+//!
+//! Reth defaults every network to the same p2p ports, so a mainnet and a testnet node on one host
+//! collide unless every port is set by hand. When `--chain` selects an XLayer network, by name or
+//! by a genesis of an XLayer chain id, [`apply_network_defaults`] adds the datadir, p2p ports and
+//! metrics endpoint of that network to the command line, for each flag the command line doesn't
+//! set itself. Setting `XLAYER_NETWORK_DEFAULTS=false` keeps the reth defaults.
+
+use std::{ffi::OsString, fs::File, io::BufReader, path::Path};
+
+use alloy_chains::Chain;
+use serde::Deserialize;
+
+use crate::{
+    xlayer_devnet::XLAYER_DEVNET_CHAIN_ID, xlayer_mainnet::XLAYER_MAINNET_CHAIN_ID,
+    xlayer_testnet::XLAYER_TESTNET_CHAIN_ID,
+};
+
+/// Environment variable disabling the network defaults when set to `false`.
+pub const NETWORK_DEFAULTS_ENV: &str = "XLAYER_NETWORK_DEFAULTS";
+
+/// Subcommand running the node, the only one listening on the p2p and metrics ports.
+const NODE_COMMAND: &str = "node";
+
+/// Default datadir and ports of an XLayer network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkDefaults {
+    /// Subfolder of the reth data directory
+    pub datadir: &'static str,
+    /// Port of the p2p listener and the discv4 discovery
+    pub p2p_port: u16,
+    /// Port of the discv5 discovery
+    pub discv5_port: u16,
+    /// Port of the metrics endpoint, served on localhost
+    pub metrics_port: u16,
+}
+
+/// Defaults of XLayer mainnet, the reth ports so existing mainnet nodes keep theirs.
+pub const XLAYER_MAINNET_DEFAULTS: NetworkDefaults = NetworkDefaults {
+    datadir: "xlayer-mainnet",
+    p2p_port: 30303,
+    discv5_port: 9200,
+    metrics_port: 9001,
+};
+
+/// Defaults of XLayer testnet.
+pub const XLAYER_TESTNET_DEFAULTS: NetworkDefaults = NetworkDefaults {
+    datadir: "xlayer-testnet",
+    p2p_port: 30313,
+    discv5_port: 9210,
+    metrics_port: 9011,
+};
+
+/// Defaults of XLayer devnet.
+pub const XLAYER_DEVNET_DEFAULTS: NetworkDefaults = NetworkDefaults {
+    datadir: "xlayer-devnet",
+    p2p_port: 30323,
+    discv5_port: 9220,
+    metrics_port: 9021,
+};
+
+/// Returns the defaults of the XLayer network with the given chain id.
+pub fn network_defaults(chain_id: u64) -> Option<NetworkDefaults> {
+    match chain_id {
+        XLAYER_MAINNET_CHAIN_ID => Some(XLAYER_MAINNET_DEFAULTS),
+        XLAYER_TESTNET_CHAIN_ID => Some(XLAYER_TESTNET_DEFAULTS),
+        XLAYER_DEVNET_CHAIN_ID => Some(XLAYER_DEVNET_DEFAULTS),
+        _ => None,
+    }
+}
+
+/// Adds the defaults of the XLayer network selected by `--chain` to the command line `args`.
+///
+/// The datadir is defaulted for the subcommands in `datadir_commands` and the p2p ports and
+/// metrics endpoint for the `node` subcommand. A datadir created under the reth default name of
+/// the chain is kept, so nodes initialized before these defaults don't start from scratch.
+pub fn apply_network_defaults<I, T>(args: I, datadir_commands: &[&str]) -> Vec<OsString>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString>,
+{
+    let mut args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    if std::env::var(NETWORK_DEFAULTS_ENV).is_ok_and(|value| value == "false") {
+        return args;
+    }
+
+    let Some(command) = args.get(1).and_then(|command| command.to_str()) else { return args };
+    let is_node = command == NODE_COMMAND;
+    if !is_node && !datadir_commands.contains(&command) {
+        return args;
+    }
+    let Some(chain_id) = flag_value(&args, "--chain").as_deref().and_then(chain_id) else {
+        return args;
+    };
+    let Some(defaults) = network_defaults(chain_id) else { return args };
+
+    let mut extra = Vec::new();
+    if !has_flag(&args, "--datadir")
+        && let Some(base) = reth_node_core::dirs::data_dir()
+    {
+        let datadir = base.join(defaults.datadir);
+        let legacy = base.join(Chain::from_id(chain_id).to_string());
+        if datadir.exists() || !legacy.exists() {
+            extra.push(("--datadir", datadir.into_os_string()));
+        }
+    }
+    if is_node && !has_flag(&args, "--with-unused-ports") {
+        extra.extend([
+            ("--port", defaults.p2p_port.to_string().into()),
+            ("--discovery.port", defaults.p2p_port.to_string().into()),
+            ("--discovery.v5.port", defaults.discv5_port.to_string().into()),
+            ("--metrics", format!("127.0.0.1:{}", defaults.metrics_port).into()),
+        ]);
+    }
+
+    for (flag, value) in extra {
+        if !has_flag(&args, flag) {
+            args.extend([flag.into(), value]);
+        }
+    }
+    args
+}
+
+/// Returns whether `flag` is given on the command line, as `--flag value` or `--flag=value`.
+fn has_flag(args: &[OsString], flag: &str) -> bool {
+    args.iter()
+        .filter_map(|arg| arg.to_str())
+        .any(|arg| arg == flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+}
+
+/// Returns the value of `flag` on the command line.
+fn flag_value(args: &[OsString], flag: &str) -> Option<String> {
+    let mut iter = args.iter().filter_map(|arg| arg.to_str());
+    while let Some(arg) = iter.next() {
+        if arg == flag {
+            return iter.next().map(str::to_owned);
+        }
+        if let Some(value) = arg.strip_prefix(flag).and_then(|rest| rest.strip_prefix('=')) {
+            return Some(value.to_owned());
+        }
+    }
+    None
+}
+
+/// Returns the chain id of a `--chain` value, an XLayer chain name or a genesis file or JSON.
+///
+/// Only the chain id of the genesis is deserialized, the alloc of a mainnet genesis is skipped.
+fn chain_id(chain: &str) -> Option<u64> {
+    #[derive(Deserialize)]
+    struct GenesisChainId {
+        config: ChainConfig,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ChainConfig {
+        chain_id: u64,
+    }
+
+    let genesis: GenesisChainId = match chain {
+        "xlayer-mainnet" => return Some(XLAYER_MAINNET_CHAIN_ID),
+        "xlayer-testnet" => return Some(XLAYER_TESTNET_CHAIN_ID),
+        "xlayer-devnet" => return Some(XLAYER_DEVNET_CHAIN_ID),
+        _ if chain.trim_start().starts_with('{') => serde_json::from_str(chain).ok()?,
+        _ if Path::new(chain).is_file() => {
+            serde_json::from_reader(BufReader::new(File::open(chain).ok()?)).ok()?
+        }
+        _ => return None,
+    };
+    Some(genesis.config.chain_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATADIR_COMMANDS: &[&str] = &["init"];
+
+    fn apply(args: &[&str]) -> Vec<String> {
+        apply_network_defaults(args.iter().copied(), DATADIR_COMMANDS)
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_chain_id() {
+        assert_eq!(chain_id("xlayer-mainnet"), Some(196));
+        assert_eq!(chain_id("xlayer-testnet"), Some(1952));
+        assert_eq!(chain_id(r#"{"config":{"chainId":195},"alloc":{"0x00":{}}}"#), Some(195));
+        assert_eq!(chain_id("optimism"), None);
+
+        let path = std::env::temp_dir().join("xlayer-network-defaults-genesis.json");
+        std::fs::write(&path, r#"{"config":{"chainId":1952}}"#).unwrap();
+        assert_eq!(chain_id(path.to_str().unwrap()), Some(1952));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_apply_network_defaults() {
+        let args = apply(&["xlayer-reth-node", "node", "--chain", "xlayer-testnet"]);
+        assert_eq!(flag_value(&to_os(&args), "--port").as_deref(), Some("30313"));
+        assert_eq!(flag_value(&to_os(&args), "--discovery.port").as_deref(), Some("30313"));
+        assert_eq!(flag_value(&to_os(&args), "--discovery.v5.port").as_deref(), Some("9210"));
+        assert_eq!(flag_value(&to_os(&args), "--metrics").as_deref(), Some("127.0.0.1:9011"));
+
+        // Flags of the command line are kept
+        let args = apply(&[
+            "xlayer-reth-node",
+            "node",
+            "--chain=xlayer-mainnet",
+            "--datadir",
+            "/data",
+            "--port=30400",
+            "--metrics",
+            "0.0.0.0:9300",
+        ]);
+        assert_eq!(args.iter().filter(|arg| arg.starts_with("--port")).count(), 1);
+        assert_eq!(args.iter().filter(|arg| *arg == "--metrics").count(), 1);
+        assert_eq!(args.iter().filter(|arg| *arg == "--datadir").count(), 1);
+        assert_eq!(flag_value(&to_os(&args), "--discovery.port").as_deref(), Some("30303"));
+
+        // Other subcommands only get the datadir, unknown ones and other chains nothing
+        let args = apply(&["xlayer-reth-node", "init", "--chain", "xlayer-devnet", "--datadir=/d"]);
+        assert_eq!(args.len(), 5);
+        let args = ["xlayer-reth-node", "db", "--chain", "xlayer-devnet"];
+        assert_eq!(apply(&args), args);
+        let args = ["xlayer-reth-node", "node", "--chain", "optimism"];
+        assert_eq!(apply(&args), args);
+    }
+
+    fn to_os(args: &[String]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+}
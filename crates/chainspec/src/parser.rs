@@ -1,11 +1,15 @@
 //! XLayer chain specification parser
 
-use crate::{XLAYER_DEVNET, XLAYER_MAINNET, XLAYER_TESTNET};
+use crate::{
+    legacy_block::apply_legacy_xlayer_block, xlayer_devnet::XLAYER_DEVNET_CHAIN_ID,
+    xlayer_mainnet::XLAYER_MAINNET_CHAIN_ID, xlayer_testnet::XLAYER_TESTNET_CHAIN_ID,
+    XLAYER_DEVNET, XLAYER_MAINNET, XLAYER_TESTNET,
+};
 use alloy_genesis::Genesis;
+use eyre::{bail, WrapErr};
 use reth_cli::chainspec::ChainSpecParser;
 use reth_optimism_chainspec::{generated_chain_value_parser, OpChainSpec};
 use std::sync::Arc;
-use tracing::debug;
 
 /// XLayer chain specification parser
 ///
@@ -51,13 +55,29 @@ fn parse_genesis(s: &str) -> eyre::Result<Genesis> {
     // XLayer extension: If legacyXLayerBlock is specified in config, override genesis.number
     // This allows XLayer to migrate from a legacy chain by setting the genesis
     // block number to match the legacy chain's starting block.
-    if let Some(legacy_block_value) = genesis.config.extra_fields.get("legacyXLayerBlock")
-        && let Some(legacy_block) = legacy_block_value.as_u64()
-    {
-        debug!("Overriding genesis.number from {:?} to {legacy_block}", genesis.number);
-        genesis.number = Some(legacy_block);
-    }
+    apply_legacy_xlayer_block(&mut genesis)?;
+
+    Ok(genesis)
+}
 
+/// Parse the genesis overriding a named XLayer chain, given via the `env` environment variable.
+///
+/// The genesis must belong to the named chain, so that a misconfigured override can't silently
+/// initialize a different network.
+fn parse_genesis_override(
+    chain: &str,
+    env: &str,
+    expected_chain_id: u64,
+    s: &str,
+) -> eyre::Result<Genesis> {
+    let genesis =
+        parse_genesis(s).wrap_err_with(|| format!("Invalid {chain} genesis override {env}={s}"))?;
+    if genesis.config.chain_id != expected_chain_id {
+        bail!(
+            "Genesis override {env}={s} has chain id {}, but {chain} has chain id {expected_chain_id}",
+            genesis.config.chain_id
+        );
+    }
     Ok(genesis)
 }
 
@@ -72,21 +92,39 @@ fn xlayer_chain_value_parser(s: &str) -> eyre::Result<Arc<OpChainSpec>> {
         "xlayer-mainnet" => {
             // Support environment variable override for genesis path
             if let Ok(genesis_path) = std::env::var("XLAYER_MAINNET_GENESIS") {
-                return Ok(Arc::new(parse_genesis(&genesis_path)?.into()));
+                let genesis = parse_genesis_override(
+                    s,
+                    "XLAYER_MAINNET_GENESIS",
+                    XLAYER_MAINNET_CHAIN_ID,
+                    &genesis_path,
+                )?;
+                return Ok(Arc::new(genesis.into()));
             }
             Ok(XLAYER_MAINNET.clone())
         }
         "xlayer-testnet" => {
             // Support environment variable override for genesis path
             if let Ok(genesis_path) = std::env::var("XLAYER_TESTNET_GENESIS") {
-                return Ok(Arc::new(parse_genesis(&genesis_path)?.into()));
+                let genesis = parse_genesis_override(
+                    s,
+                    "XLAYER_TESTNET_GENESIS",
+                    XLAYER_TESTNET_CHAIN_ID,
+                    &genesis_path,
+                )?;
+                return Ok(Arc::new(genesis.into()));
             }
             Ok(XLAYER_TESTNET.clone())
         }
         "xlayer-devnet" => {
             // Support environment variable override for genesis path
             if let Ok(genesis_path) = std::env::var("XLAYER_DEVNET_GENESIS") {
-                return Ok(Arc::new(parse_genesis(&genesis_path)?.into()));
+                let genesis = parse_genesis_override(
+                    s,
+                    "XLAYER_DEVNET_GENESIS",
+                    XLAYER_DEVNET_CHAIN_ID,
+                    &genesis_path,
+                )?;
+                return Ok(Arc::new(genesis.into()));
             }
             Ok(XLAYER_DEVNET.clone())
         }
@@ -240,4 +278,75 @@ mod tests {
             "genesis.number should remain unchanged when legacyXLayerBlock is not present"
         );
     }
+
+    fn genesis_json(chain_id: u64, legacy_block: serde_json::Value) -> String {
+        serde_json::json!({
+            "config": {
+                "chainId": chain_id,
+                "legacyXLayerBlock": legacy_block
+            },
+            "nonce": "0x0",
+            "timestamp": "0x0",
+            "extraData": "0x",
+            "gasLimit": "0x1000000",
+            "difficulty": "0x0",
+            "mixHash": "0x0000000000000000000000000000000000000000000000000000000000000000",
+            "coinbase": "0x0000000000000000000000000000000000000000",
+            "alloc": {}
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_legacy_xlayer_block_validation() {
+        assert!(parse_genesis(&genesis_json(196, 12345.into())).is_ok());
+
+        let err = parse_genesis(&genesis_json(196, 0.into())).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+
+        let err = parse_genesis(&genesis_json(196, u64::MAX.into())).unwrap_err();
+        assert!(err.to_string().contains("out of range"), "{err}");
+
+        let err = parse_genesis(&genesis_json(196, "12345".into())).unwrap_err();
+        assert!(err.to_string().contains("must be an unsigned integer"), "{err}");
+
+        let mut conflicting: serde_json::Value =
+            serde_json::from_str(&genesis_json(196, 12345.into())).unwrap();
+        conflicting["number"] = "0x64".into();
+        let err = parse_genesis(&conflicting.to_string()).unwrap_err();
+        assert!(err.to_string().contains("conflicts"), "{err}");
+    }
+
+    #[test]
+    fn test_genesis_override_chain_id_mismatch() {
+        let testnet_genesis = genesis_json(XLAYER_TESTNET_CHAIN_ID, 12345.into());
+
+        assert!(parse_genesis_override(
+            "xlayer-testnet",
+            "XLAYER_TESTNET_GENESIS",
+            XLAYER_TESTNET_CHAIN_ID,
+            &testnet_genesis,
+        )
+        .is_ok());
+
+        // A testnet genesis must not initialize mainnet
+        let err = parse_genesis_override(
+            "xlayer-mainnet",
+            "XLAYER_MAINNET_GENESIS",
+            XLAYER_MAINNET_CHAIN_ID,
+            &testnet_genesis,
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("has chain id 1952, but xlayer-mainnet has chain id 196"));
+
+        // Invalid overrides name the variable
+        let err = parse_genesis_override(
+            "xlayer-testnet",
+            "XLAYER_TESTNET_GENESIS",
+            XLAYER_TESTNET_CHAIN_ID,
+            &genesis_json(XLAYER_TESTNET_CHAIN_ID, 0.into()),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("XLAYER_TESTNET_GENESIS"));
+    }
 }
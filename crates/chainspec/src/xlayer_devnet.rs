@@ -1,6 +1,6 @@
 //! XLayer Devnet chain specification
 
-use crate::XLAYER_DEVNET_HARDFORKS;
+use crate::{apply_legacy_xlayer_block, XLAYER_DEVNET_HARDFORKS};
 use alloy_chains::Chain;
 use alloy_primitives::{B256, U256};
 
@@ -67,7 +67,7 @@ pub(crate) static XLAYER_DEVNET_STATE_ROOT: Lazy<B256> = Lazy::new(|| {
 });
 
 /// X Layer devnet chain id as specified in the published `genesis.json`.
-const XLAYER_DEVNET_CHAIN_ID: u64 = 195;
+pub(crate) const XLAYER_DEVNET_CHAIN_ID: u64 = 195;
 
 /// X Layer devnet EIP-1559 parameters.
 ///
@@ -82,8 +82,10 @@ const XLAYER_DEVNET_BASE_FEE_PARAMS: BaseFeeParams =
 /// The X Layer devnet spec
 pub static XLAYER_DEVNET: Lazy<Arc<OpChainSpec>> = Lazy::new(|| {
     // Minimal genesis contains empty alloc field for fast loading
-    let genesis = serde_json::from_str(include_str!("../res/genesis/xlayer-devnet.json"))
+    let mut genesis = serde_json::from_str(include_str!("../res/genesis/xlayer-devnet.json"))
         .expect("Can't deserialize X Layer Devnet genesis json");
+    apply_legacy_xlayer_block(&mut genesis)
+        .expect("X Layer Devnet genesis number must match legacyXLayerBlock");
     let hardforks = XLAYER_DEVNET_HARDFORKS.clone();
 
     // Build genesis header using standard helper, then override state_root with pre-computed value
@@ -1,6 +1,6 @@
 //! XLayer Mainnet chain specification
 
-use crate::XLAYER_MAINNET_HARDFORKS;
+use crate::{apply_legacy_xlayer_block, XLAYER_MAINNET_HARDFORKS};
 use alloy_chains::Chain;
 use alloy_primitives::{b256, B256, U256};
 use once_cell::sync::Lazy;
@@ -26,7 +26,7 @@ pub(crate) const XLAYER_MAINNET_STATE_ROOT: B256 =
     b256!("5d335834cb1c1c20a1f44f964b16cd409aa5d10891d5c6cf26f1f2c26726efcf");
 
 /// X Layer mainnet chain id as specified in the published `genesis.json`.
-const XLAYER_MAINNET_CHAIN_ID: u64 = 196;
+pub(crate) const XLAYER_MAINNET_CHAIN_ID: u64 = 196;
 
 /// X Layer mainnet EIP-1559 parameters.
 ///
@@ -45,8 +45,10 @@ const XLAYER_MAINNET_BASE_FEE_PARAMS: BaseFeeParams =
 /// The X Layer mainnet spec
 pub static XLAYER_MAINNET: Lazy<Arc<OpChainSpec>> = Lazy::new(|| {
     // Minimal genesis contains empty alloc field for fast loading
-    let genesis = serde_json::from_str(include_str!("../res/genesis/xlayer-mainnet.json"))
+    let mut genesis = serde_json::from_str(include_str!("../res/genesis/xlayer-mainnet.json"))
         .expect("Can't deserialize X Layer Mainnet genesis json");
+    apply_legacy_xlayer_block(&mut genesis)
+        .expect("X Layer Mainnet genesis number must match legacyXLayerBlock");
     let hardforks = XLAYER_MAINNET_HARDFORKS.clone();
 
     // Build genesis header using standard helper, then override state_root with pre-computed value
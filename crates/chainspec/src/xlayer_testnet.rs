@@ -1,6 +1,6 @@
 //! XLayer Testnet chain specification
 
-use crate::XLAYER_TESTNET_HARDFORKS;
+use crate::{apply_legacy_xlayer_block, XLAYER_TESTNET_HARDFORKS};
 use alloy_chains::Chain;
 use alloy_primitives::{b256, B256, U256};
 use once_cell::sync::Lazy;
@@ -26,7 +26,7 @@ pub(crate) const XLAYER_TESTNET_STATE_ROOT: B256 =
     b256!("3de62c8ade3d3adaa88d48a3ffeebd7c8b6c5b81906d706c22f02f0d2dd3b8fa");
 
 /// X Layer testnet chain id from the published `genesis-testnet.json`.
-const XLAYER_TESTNET_CHAIN_ID: u64 = 1952;
+pub(crate) const XLAYER_TESTNET_CHAIN_ID: u64 = 1952;
 
 /// X Layer testnet EIP-1559 parameters.
 ///
@@ -41,8 +41,10 @@ const XLAYER_TESTNET_BASE_FEE_PARAMS: BaseFeeParams =
 /// The X Layer testnet spec
 pub static XLAYER_TESTNET: Lazy<Arc<OpChainSpec>> = Lazy::new(|| {
     // Minimal genesis contains empty alloc field for fast loading
-    let genesis = serde_json::from_str(include_str!("../res/genesis/xlayer-testnet.json"))
+    let mut genesis = serde_json::from_str(include_str!("../res/genesis/xlayer-testnet.json"))
         .expect("Can't deserialize X Layer Testnet genesis json");
+    apply_legacy_xlayer_block(&mut genesis)
+        .expect("X Layer Testnet genesis number must match legacyXLayerBlock");
     let hardforks = XLAYER_TESTNET_HARDFORKS.clone();
 
     // Build genesis header using standard helper, then override state_root with pre-computed value
@@ -0,0 +1,401 @@
+//! Client for the `flashblocks` subscription of an X Layer node.
+//!
+//! [`FlashblocksClient`] subscribes to the feed over WebSocket and turns its header and
+//! transaction notifications into a typed stream of [`FlashblockEvent`]s:
+//! - transactions are tagged with the number of the pending block that pre-confirmed them
+//! - the node re-sends the whole pending block to a new subscription, so after a reconnect the
+//!   transactions already delivered for the current block are dropped and the stream resumes where
+//!   it left off
+//! - a canonical reorg announced by the node, a pending block that is not extended by the next
+//!   block, or a block number going backwards, is reported as a reorg of the pre-confirmed
+//!   transactions
+//!
+//! The node keeps no state per subscriber, resumption is client side only: transactions of blocks
+//! sealed while the client was disconnected are not replayed.
+
+use std::{collections::HashSet, time::Duration};
+
+use alloy_primitives::{TxHash, B256};
+use alloy_rpc_types_eth::Header;
+use futures::{Stream, StreamExt};
+use jsonrpsee::{
+    core::{
+        client::{Subscription, SubscriptionClientT},
+        ClientError,
+    },
+    rpc_params,
+    ws_client::{WsClient, WsClientBuilder},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{debug, info, warn};
+use xlayer_flashblocks::{
+    pubsub::{EnrichedTransaction, FlashblocksFilter, FlashblocksReorg},
+    scopes::SubscriptionAuth,
+};
+
+/// Default delay before reconnecting after the subscription is lost.
+pub const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Number of events buffered for a slow consumer.
+const EVENT_BUFFER_SIZE: usize = 1024;
+
+/// Configuration of a [`FlashblocksClient`].
+#[derive(Debug, Clone)]
+pub struct FlashblocksClientConfig {
+    /// WebSocket endpoint of the node
+    pub url: String,
+    /// Subscription filter, headers are always requested to track the pending block
+    pub filter: FlashblocksFilter,
+    /// Delay before reconnecting after the subscription is lost
+    pub reconnect_delay: Duration,
+    /// API key of nodes that scope subscriptions by key
+    pub api_key: Option<String>,
+}
+
+impl FlashblocksClientConfig {
+    /// Creates a configuration for all transactions of the feed, without enrichment.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            filter: FlashblocksFilter::default(),
+            reconnect_delay: DEFAULT_RECONNECT_DELAY,
+            api_key: None,
+        }
+    }
+}
+
+/// Event of the flashblocks feed.
+///
+/// Transaction data and receipts are deserialized into `Tx` and `R`, they are only present if
+/// requested by the filter.
+#[derive(Debug, Clone)]
+pub enum FlashblockEvent<Tx = Value, R = Value> {
+    /// Header of the pending block, re-sent whenever a flashblock extends it
+    Header(Box<Header>),
+    /// Transaction pre-confirmed by the pending block `block_number`
+    Transaction { block_number: u64, transaction: EnrichedTransaction<Tx, R> },
+    /// Pre-confirmations of the pending blocks from `block_number` on were dropped
+    Reorg { block_number: u64 },
+    /// The connection was lost and the subscription re-established
+    Reconnected,
+}
+
+/// Notification of the `flashblocks` subscription, as sent by the node.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum Notification<Tx, R> {
+    Header { header: Box<Header> },
+    Transaction { transaction: EnrichedTransaction<Tx, R> },
+    Reorg { reorg: FlashblocksReorg },
+}
+
+/// Client of the `flashblocks` subscription.
+#[derive(Debug, Clone)]
+pub struct FlashblocksClient {
+    config: FlashblocksClientConfig,
+}
+
+impl FlashblocksClient {
+    pub fn new(config: FlashblocksClientConfig) -> Self {
+        Self { config }
+    }
+
+    /// Subscribes to the feed, reconnecting whenever the subscription is lost. The subscription
+    /// task ends when the returned stream is dropped.
+    pub fn subscribe<Tx, R>(self) -> impl Stream<Item = FlashblockEvent<Tx, R>>
+    where
+        Tx: DeserializeOwned + Send + 'static,
+        R: DeserializeOwned + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel(EVENT_BUFFER_SIZE);
+        tokio::spawn(self.run(tx));
+        ReceiverStream::new(rx)
+    }
+
+    async fn run<Tx, R>(self, events: mpsc::Sender<FlashblockEvent<Tx, R>>)
+    where
+        Tx: DeserializeOwned + Send + 'static,
+        R: DeserializeOwned + Send + 'static,
+    {
+        let mut tracker = PendingBlockTracker::default();
+        let mut connected = false;
+
+        loop {
+            match self.connect().await {
+                Ok((_client, mut subscription)) => {
+                    info!(
+                        target: "xlayer::flashblocks_client",
+                        url = %self.config.url,
+                        "Subscribed to flashblocks"
+                    );
+                    if connected && events.send(FlashblockEvent::Reconnected).await.is_err() {
+                        return;
+                    }
+                    connected = true;
+
+                    while let Some(notification) = subscription.next().await {
+                        let notification = match notification {
+                            Ok(notification) => notification,
+                            Err(err) => {
+                                warn!(
+                                    target: "xlayer::flashblocks_client",
+                                    %err,
+                                    "Invalid flashblocks notification"
+                                );
+                                continue;
+                            }
+                        };
+                        let notification = match serde_json::from_value(notification) {
+                            Ok(notification) => notification,
+                            Err(err) => {
+                                warn!(
+                                    target: "xlayer::flashblocks_client",
+                                    %err,
+                                    "Failed to decode flashblocks notification"
+                                );
+                                continue;
+                            }
+                        };
+                        for event in tracker.apply(notification) {
+                            if events.send(event).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    warn!(target: "xlayer::flashblocks_client", "Flashblocks subscription closed");
+                }
+                Err(err) => {
+                    warn!(
+                        target: "xlayer::flashblocks_client",
+                        %err,
+                        url = %self.config.url,
+                        "Failed to subscribe to flashblocks"
+                    );
+                }
+            }
+
+            if events.is_closed() {
+                return;
+            }
+            tokio::time::sleep(self.config.reconnect_delay).await;
+        }
+    }
+
+    /// Connects to the node and subscribes to the feed. The subscription lives as long as the
+    /// returned client.
+    async fn connect(&self) -> Result<(WsClient, Subscription<Value>), ClientError> {
+        let client = WsClientBuilder::default().build(&self.config.url).await?;
+        let filter = FlashblocksFilter { header_info: true, ..self.config.filter.clone() };
+        let params = match &self.config.api_key {
+            Some(api_key) => {
+                rpc_params!["flashblocks", filter, SubscriptionAuth { api_key: api_key.clone() }]
+            }
+            None => rpc_params!["flashblocks", filter],
+        };
+        let subscription = client.subscribe("eth_subscribe", params, "eth_unsubscribe").await?;
+        Ok((client, subscription))
+    }
+}
+
+/// Tracks the pending block across notifications and reconnects.
+#[derive(Debug, Default)]
+struct PendingBlockTracker {
+    /// Number and hash of the latest header of the pending block
+    block: Option<(u64, B256)>,
+    /// Transactions delivered for the pending block
+    delivered: HashSet<TxHash>,
+}
+
+impl PendingBlockTracker {
+    fn apply<Tx, R>(&mut self, notification: Notification<Tx, R>) -> Vec<FlashblockEvent<Tx, R>> {
+        match notification {
+            Notification::Header { header } => {
+                let (number, hash) = (header.number, header.hash);
+                let mut events = Vec::with_capacity(2);
+                match self.block {
+                    Some((current, _)) if number < current => {
+                        events.push(FlashblockEvent::Reorg { block_number: number });
+                        self.delivered.clear();
+                    }
+                    Some((current, current_hash))
+                        if number == current + 1 && header.parent_hash != current_hash =>
+                    {
+                        events.push(FlashblockEvent::Reorg { block_number: current });
+                        self.delivered.clear();
+                    }
+                    Some((current, _)) if number == current => {}
+                    _ => self.delivered.clear(),
+                }
+                self.block = Some((number, hash));
+                events.push(FlashblockEvent::Header(header));
+                events
+            }
+            Notification::Transaction { transaction } => {
+                let Some((block_number, _)) = self.block else {
+                    debug!(
+                        target: "xlayer::flashblocks_client",
+                        tx_hash = %transaction.tx_hash,
+                        "Transaction before the first header"
+                    );
+                    return Vec::new();
+                };
+                if !self.delivered.insert(transaction.tx_hash) {
+                    return Vec::new();
+                }
+                vec![FlashblockEvent::Transaction { block_number, transaction }]
+            }
+            Notification::Reorg { reorg } => {
+                let block_number = reorg.first_replaced();
+                if self.block.is_none_or(|(current, _)| current < block_number) {
+                    return Vec::new();
+                }
+                // The next header starts a new pending block without being reported again
+                self.block = None;
+                self.delivered.clear();
+                vec![FlashblockEvent::Reorg { block_number }]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestEvent = FlashblockEvent<Value, Value>;
+
+    fn header(number: u64, hash: u8, parent: u8) -> Notification<Value, Value> {
+        Notification::Header {
+            header: Box::new(Header {
+                hash: B256::repeat_byte(hash),
+                inner: alloy_consensus::Header {
+                    number,
+                    parent_hash: B256::repeat_byte(parent),
+                    ..Default::default()
+                },
+                total_difficulty: None,
+                size: None,
+            }),
+        }
+    }
+
+    fn transaction(hash: u8) -> Notification<Value, Value> {
+        Notification::Transaction {
+            transaction: EnrichedTransaction {
+                tx_hash: TxHash::repeat_byte(hash),
+                tx_data: None,
+                receipt: None,
+                preconfirmation: None,
+            },
+        }
+    }
+
+    fn summarize(events: Vec<TestEvent>) -> Vec<String> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                FlashblockEvent::Header(header) => format!("header {}", header.number),
+                FlashblockEvent::Transaction { block_number, transaction } => {
+                    format!("tx {} in {block_number}", transaction.tx_hash[0])
+                }
+                FlashblockEvent::Reorg { block_number } => format!("reorg {block_number}"),
+                FlashblockEvent::Reconnected => "reconnected".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_transactions_follow_pending_block() {
+        let mut tracker = PendingBlockTracker::default();
+        assert!(tracker.apply(transaction(1)).is_empty());
+
+        let mut events = tracker.apply(header(10, 0xa1, 0x09));
+        events.extend(tracker.apply(transaction(1)));
+        // Next flashblock of the same block, re-sent transactions are dropped
+        events.extend(tracker.apply(header(10, 0xa2, 0x09)));
+        events.extend(tracker.apply(transaction(1)));
+        events.extend(tracker.apply(transaction(2)));
+        events.extend(tracker.apply(header(11, 0xb1, 0xa2)));
+        events.extend(tracker.apply(transaction(3)));
+
+        assert_eq!(
+            summarize(events),
+            ["header 10", "tx 1 in 10", "header 10", "tx 2 in 10", "header 11", "tx 3 in 11"]
+        );
+    }
+
+    #[test]
+    fn test_reorg_signaling() {
+        let mut tracker = PendingBlockTracker::default();
+        tracker.apply(header(10, 0xa1, 0x09));
+        tracker.apply(transaction(1));
+
+        // Block 11 doesn't extend the last pending block 10
+        let events = tracker.apply(header(11, 0xb1, 0xff));
+        assert_eq!(summarize(events), ["reorg 10", "header 11"]);
+
+        // Pending block number going backwards
+        let mut events = tracker.apply(header(9, 0x91, 0x08));
+        events.extend(tracker.apply(transaction(1)));
+        assert_eq!(summarize(events), ["reorg 9", "header 9", "tx 1 in 9"]);
+    }
+
+    #[test]
+    fn test_node_reorg_notification() {
+        let reorg = |common_ancestor: u64| {
+            serde_json::from_value::<Notification<Value, Value>>(serde_json::json!({
+                "type": "reorg",
+                "reorg": {
+                    "oldTip": {"number": format!("{:#x}", common_ancestor + 1), "hash": B256::ZERO},
+                    "newTip": {"number": format!("{:#x}", common_ancestor + 1), "hash": B256::ZERO},
+                    "commonAncestor": {"number": format!("{common_ancestor:#x}"), "hash": B256::ZERO}
+                }
+            }))
+            .unwrap()
+        };
+
+        let mut tracker = PendingBlockTracker::default();
+        tracker.apply(header(10, 0xa1, 0x09));
+        tracker.apply(transaction(1));
+
+        // Blocks after the pending block are not pre-confirmed
+        assert!(tracker.apply(reorg(10)).is_empty());
+
+        let mut events = tracker.apply(reorg(9));
+        events.extend(tracker.apply(header(10, 0xa3, 0x08)));
+        events.extend(tracker.apply(transaction(1)));
+        assert_eq!(summarize(events), ["reorg 10", "header 10", "tx 1 in 10"]);
+    }
+
+    #[test]
+    fn test_notification_decoding() {
+        let notification: Notification<Value, Value> = serde_json::from_value(serde_json::json!({
+            "type": "transaction",
+            "transaction": {
+                "txHash": "0x0101010101010101010101010101010101010101010101010101010101010101",
+                "receipt": {"status": "0x1"}
+            }
+        }))
+        .unwrap();
+        let Notification::Transaction { transaction } = notification else {
+            panic!("expected transaction notification");
+        };
+        assert_eq!(transaction.tx_hash, TxHash::repeat_byte(1));
+        assert_eq!(transaction.tx_data, None);
+        assert_eq!(transaction.receipt, Some(serde_json::json!({"status": "0x1"})));
+
+        // Events of later schema versions carry their version
+        let notification: Notification<Value, Value> = serde_json::from_value(serde_json::json!({
+            "type": "transaction",
+            "schemaVersion": 2,
+            "transaction": {
+                "txHash": "0x0101010101010101010101010101010101010101010101010101010101010101"
+            }
+        }))
+        .unwrap();
+        assert!(matches!(notification, Notification::Transaction { .. }));
+    }
+}
@@ -0,0 +1,173 @@
+//! Compares converting the receipts of a 200 transaction flashblock into RPC form per
+//! transaction and subscriber with converting them once, shared by all subscribers through
+//! [`FlashblockReceipts`].
+//!
+//! Run with `cargo bench -p xlayer-flashblocks --bench receipts`.
+
+use std::hint::black_box;
+
+use alloy_consensus::{Block, BlockBody, Header, Receipt, Signed, TxEip1559};
+use alloy_primitives::{hex, Address, Bytes, Log, Sealed, Signature, TxKind, B256, U256};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use op_alloy_consensus::{OpDepositReceipt, TxDeposit};
+use reth_optimism_chainspec::OP_MAINNET;
+use reth_optimism_primitives::{OpBlock, OpPrimitives, OpReceipt, OpTransactionSigned};
+use reth_optimism_rpc::eth::receipt::OpReceiptConverter;
+use reth_primitives_traits::RecoveredBlock;
+use reth_provider::test_utils::MockEthProvider;
+use reth_rpc_convert::transaction::ReceiptConverter;
+use xlayer_flashblocks::receipts::{receipt_inputs, FlashblockReceipts};
+
+/// Transactions per flashblock, the L1 info deposit included.
+const FLASHBLOCK_TXS: usize = 200;
+
+/// Subscriber counts benchmarked.
+const SUBSCRIBERS: &[usize] = &[1, 10, 50];
+
+/// Calldata of the L1 info deposit, receipts can't be converted without it.
+const L1_INFO: &[u8] = &hex!(
+    "3db6be2b0000146b000f79c500000000000000040000000066d052e700000000013ad8a
+    3000000000000000000000000000000000000000000000000000000003ef12787000000
+    00000000000000000000000000000000000000000000000000000000012fdf87b89884a
+    61e74b322bbcf60386f543bfae7827725efaaf0ab1de2294a5900000000000000000000
+    00006887246668a3b87f54deb3b94ba47a6f63f32985
+    00000000
+    0000000000000000
+    0190"
+);
+
+const L1_INFO_DEPOSITOR: Address = Address::new(hex!("deaddeaddeaddeaddeaddeaddeaddeaddead0001"));
+const L1_BLOCK: Address = Address::new(hex!("4200000000000000000000000000000000000015"));
+const TRANSFER_TOPIC: B256 =
+    B256::new(hex!("ddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"));
+
+/// Returns a flashblock of token transfers following the L1 info deposit, and its receipts.
+fn flashblock() -> (RecoveredBlock<OpBlock>, Vec<OpReceipt>) {
+    let deposit = TxDeposit {
+        source_hash: B256::ZERO,
+        from: L1_INFO_DEPOSITOR,
+        to: TxKind::Call(L1_BLOCK),
+        mint: 0,
+        value: U256::ZERO,
+        gas_limit: 1_000_000,
+        is_system_transaction: false,
+        input: Bytes::from_static(L1_INFO),
+    };
+    let mut cumulative_gas_used = 50_000;
+    let mut transactions = vec![OpTransactionSigned::Deposit(Sealed::new(deposit))];
+    let mut senders = vec![L1_INFO_DEPOSITOR];
+    let mut receipts = vec![OpReceipt::Deposit(OpDepositReceipt {
+        inner: Receipt { status: true.into(), cumulative_gas_used, logs: Vec::new() },
+        deposit_nonce: Some(1),
+        deposit_receipt_version: Some(1),
+    })];
+
+    let token = Address::with_last_byte(0xaa);
+    for i in 1..FLASHBLOCK_TXS {
+        let sender = Address::with_last_byte(i as u8);
+        let recipient = Address::with_last_byte(i as u8 + 1).into_word();
+        let amount = Bytes::from(U256::from(i).to_be_bytes_vec());
+        let tx = TxEip1559 {
+            chain_id: 196,
+            nonce: i as u64,
+            gas_limit: 100_000,
+            max_fee_per_gas: 2_000_000_000,
+            max_priority_fee_per_gas: 1_000_000,
+            to: TxKind::Call(token),
+            value: U256::ZERO,
+            access_list: Default::default(),
+            input: [hex!("a9059cbb").as_slice(), recipient.as_slice(), &amount[..]].concat().into(),
+        };
+        transactions.push(OpTransactionSigned::Eip1559(Signed::new_unhashed(
+            tx,
+            Signature::test_signature(),
+        )));
+        senders.push(sender);
+
+        cumulative_gas_used += 52_000;
+        let log =
+            Log::new_unchecked(token, vec![TRANSFER_TOPIC, sender.into_word(), recipient], amount);
+        receipts.push(OpReceipt::Eip1559(Receipt {
+            status: true.into(),
+            cumulative_gas_used,
+            logs: vec![log],
+        }));
+    }
+
+    let header = Header {
+        number: 1_000_000,
+        timestamp: 1_760_000_000,
+        gas_limit: 30_000_000,
+        gas_used: cumulative_gas_used,
+        base_fee_per_gas: Some(1_000_000),
+        ..Default::default()
+    };
+    let block =
+        Block { header, body: BlockBody { transactions, ommers: Vec::new(), withdrawals: None } };
+    (RecoveredBlock::new_unhashed(block, senders), receipts)
+}
+
+fn bench_receipts(c: &mut Criterion) {
+    let (block, receipts) = flashblock();
+    let sealed_block = block.sealed_block();
+    let converter = OpReceiptConverter::new(
+        MockEthProvider::<OpPrimitives>::new().with_chain_spec(OP_MAINNET.as_ref().clone()),
+    );
+    // Sanity check of the fixture, every receipt converts
+    let converted = converter
+        .convert_receipts_with_block(
+            receipt_inputs::<OpPrimitives>(&block, &receipts),
+            sealed_block,
+        )
+        .unwrap();
+    assert_eq!(converted.len(), FLASHBLOCK_TXS);
+
+    let mut group = c.benchmark_group(format!("flashblock_receipts_{FLASHBLOCK_TXS}_txs"));
+    for &subscribers in SUBSCRIBERS {
+        // One conversion call per transaction and subscriber
+        group.bench_with_input(
+            BenchmarkId::new("per_transaction", subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                b.iter(|| {
+                    for _ in 0..subscribers {
+                        for input in receipt_inputs::<OpPrimitives>(&block, &receipts) {
+                            let receipt = converter
+                                .convert_receipts_with_block(vec![input], sealed_block)
+                                .unwrap();
+                            black_box(receipt);
+                        }
+                    }
+                })
+            },
+        );
+
+        // One conversion call per flashblock, subscribers clone the receipts they are sent
+        group.bench_with_input(
+            BenchmarkId::new("shared", subscribers),
+            &subscribers,
+            |b, &subscribers| {
+                b.iter(|| {
+                    let shared = FlashblockReceipts::default();
+                    for _ in 0..subscribers {
+                        let converted = shared
+                            .get_or_convert(block.hash(), receipts.len(), || {
+                                converter.convert_receipts_with_block(
+                                    receipt_inputs::<OpPrimitives>(&block, &receipts),
+                                    sealed_block,
+                                )
+                            })
+                            .unwrap();
+                        for idx in 0..FLASHBLOCK_TXS {
+                            black_box(converted.get(idx).cloned());
+                        }
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_receipts);
+criterion_main!(benches);
@@ -0,0 +1,501 @@
+//! Deposit transaction lookups for the bridge frontend, served as
+//! `xlayer_getDepositTransactions(query, pageToken, pageSize)`.
+//!
+//! The bridge frontend follows a deposit from its L1 transaction to its inclusion on L2. Deposit
+//! transactions are derived from L1 by the rollup node and don't reference the L1 transaction that
+//! initiated them, only their source hash: the hash of the L1 block and the index of the
+//! `TransactionDeposited` log, both read from the receipt of the L1 transaction. The method finds
+//! a deposit by its source hash, by the L1 block hash and log index it is derived from, or lists
+//! the deposits of an L2 block range.
+//!
+//! User deposits are told apart from the L1 info deposit and the upgrade deposits of hardfork
+//! blocks by the domain of their source hash: only the source hash of a user deposit derives from
+//! the L1 origin of its block and the index of a log of that L1 block.
+//!
+//! Source hash lookups are answered from an index of the user deposits of the latest canonical
+//! blocks, fed from the canonical state notifications, deposits reverted by a reorg are removed.
+//! A deposit missing from the index isn't derived yet, or was included before the indexed blocks,
+//! in which case a block range query finds it. Block range listings are paginated following the
+//! shared [`pagination`](xlayer_legacy_rpc::pagination) conventions, a page ends early with a
+//! token at the next block to scan after [`MAX_BLOCKS_PER_PAGE`] blocks.
+//!
+//! Every deposit reports the L1 origin of its L2 block, read from the L1 info deposit transaction
+//! of the block, and its inclusion status following the safe and finalized heads of the node.
+
+use std::sync::Arc;
+
+use alloy_consensus::{Transaction as _, TxReceipt as _};
+use alloy_primitives::{Address, B256, U256, U64};
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use moka::{policy::EvictionPolicy, sync::Cache};
+use op_alloy_consensus::{TxDeposit, UserDepositSource};
+use reth_chain_state::CanonStateNotification;
+use reth_optimism_primitives::{OpPrimitives, OpReceipt, OpTransactionSigned};
+use reth_primitives_traits::BlockBody as _;
+use reth_rpc_server_types::result::{internal_rpc_err, invalid_params_rpc_err};
+use reth_storage_api::{
+    errors::provider::ProviderResult, BlockHashReader, BlockIdReader, ReceiptProvider,
+    TransactionsProvider,
+};
+use reth_tracing::tracing::debug;
+use serde::{Deserialize, Serialize};
+use xlayer_legacy_rpc::pagination::{
+    decode_page_token, encode_page_token, PageLimits, PageParamError,
+};
+
+use crate::{
+    canon_mux::CanonStateConsumer,
+    finality::{l1_origin_from_calldata, FinalityHead, L1Origin},
+};
+
+/// Default number of indexed deposits.
+pub const DEPOSIT_INDEX_CAPACITY: u64 = 100_000;
+
+/// Page size used if the request has none.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Largest accepted page size.
+pub const MAX_PAGE_SIZE: usize = 1_000;
+
+/// Page size bounds of `xlayer_getDepositTransactions`.
+pub const DEPOSITS_PAGE_LIMITS: PageLimits = PageLimits::new(DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE);
+
+/// Blocks a page scans before it ends early.
+pub const MAX_BLOCKS_PER_PAGE: u64 = 1_000;
+
+/// Log indexes of the L1 origin searched for the log a deposit is derived from.
+const MAX_L1_LOG_INDEX: u64 = 1 << 16;
+
+/// Deposits to look up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DepositQuery {
+    /// The deposit with this source hash
+    #[serde(rename_all = "camelCase")]
+    SourceHash { source_hash: B256 },
+    /// The deposit derived from a `TransactionDeposited` log of an L1 block
+    #[serde(rename_all = "camelCase")]
+    L1Log { l1_block_hash: B256, l1_log_index: U64 },
+    /// The deposits of an L2 block range, both ends inclusive
+    #[serde(rename_all = "camelCase")]
+    BlockRange { from_block: U64, to_block: U64 },
+}
+
+/// Deposit transaction included on L2.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositTransaction {
+    pub hash: B256,
+    pub source_hash: B256,
+    pub from: Address,
+    /// `None` for contract creations
+    pub to: Option<Address>,
+    /// ETH minted on L2
+    pub mint: U256,
+    pub value: U256,
+    pub gas: U64,
+    pub block_number: U64,
+    pub block_hash: B256,
+    pub transaction_index: U64,
+    /// L1 origin of the including block
+    pub l1_origin: Option<L1Origin>,
+    /// Index of the `TransactionDeposited` log in the L1 origin block
+    pub l1_log_index: U64,
+    /// Whether the execution succeeded, a failed deposit still mints
+    pub success: bool,
+    /// Latest head at or after the including block
+    pub inclusion: FinalityHead,
+}
+
+/// Page of deposits returned by `xlayer_getDepositTransactions`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DepositsPage {
+    pub deposits: Vec<DepositTransaction>,
+    /// Token of the next page, `None` once the range is exhausted
+    pub next_page_token: Option<String>,
+}
+
+/// User deposit of a block.
+struct UserDeposit<'a> {
+    /// Index of the transaction in the block
+    index: usize,
+    /// Index of the log in the L1 origin block
+    l1_log_index: u64,
+    deposit: &'a TxDeposit,
+}
+
+/// Returns the user deposits of the transactions of a block. Deposits lead the block, starting
+/// with the L1 info deposit, and user deposits are derived from the logs of the L1 origin in log
+/// order, so the log index of each deposit is searched from the one of the previous deposit on.
+fn user_deposits(txs: &[OpTransactionSigned]) -> Vec<UserDeposit<'_>> {
+    let Some(l1_origin) = txs.first().and_then(|tx| l1_origin_from_calldata(tx.input())) else {
+        return Vec::new();
+    };
+
+    let mut deposits = Vec::new();
+    let mut next_log_index = 0;
+    for (index, tx) in txs.iter().enumerate().skip(1) {
+        let Some(deposit) = tx.as_deposit().map(|deposit| deposit.inner()) else { break };
+        let l1_log_index = (next_log_index..MAX_L1_LOG_INDEX).find(|log_index| {
+            UserDepositSource::new(l1_origin.hash, *log_index).source_hash() == deposit.source_hash
+        });
+        // Deposits of other source hash domains, e.g. upgrade deposits
+        let Some(l1_log_index) = l1_log_index else { continue };
+        next_log_index = l1_log_index + 1;
+        deposits.push(UserDeposit { index, l1_log_index, deposit });
+    }
+    deposits
+}
+
+/// Where a page of a block range listing continues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageToken {
+    /// At the first deposit of the block
+    Block(u64),
+    /// After the deposit at this transaction index of the block
+    After { block: u64, index: u64 },
+}
+
+impl PageToken {
+    fn parse(token: &str) -> Option<Self> {
+        match decode_page_token(token)?.as_slice() {
+            [block] => Some(Self::Block(*block)),
+            [block, index] => Some(Self::After { block: *block, index: *index }),
+            _ => None,
+        }
+    }
+
+    /// Block the page starts scanning at.
+    fn block(&self) -> u64 {
+        match self {
+            Self::Block(block) | Self::After { block, .. } => *block,
+        }
+    }
+}
+
+impl std::fmt::Display for PageToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = match self {
+            Self::Block(block) => encode_page_token(&[*block]),
+            Self::After { block, index } => encode_page_token(&[*block, *index]),
+        };
+        f.write_str(&token)
+    }
+}
+
+/// Index of the user deposits of the latest canonical blocks by source hash.
+pub struct DepositIndex {
+    blocks: Cache<B256, u64>,
+}
+
+impl Default for DepositIndex {
+    fn default() -> Self {
+        Self::new(DEPOSIT_INDEX_CAPACITY)
+    }
+}
+
+impl DepositIndex {
+    /// Creates an index of up to `capacity` deposits.
+    pub fn new(capacity: u64) -> Self {
+        let blocks =
+            Cache::builder().max_capacity(capacity).eviction_policy(EvictionPolicy::lru()).build();
+        Self { blocks }
+    }
+
+    /// Returns the block including the deposit with this source hash.
+    pub fn block(&self, source_hash: &B256) -> Option<u64> {
+        self.blocks.get(source_hash)
+    }
+
+    /// Applies a canonical state notification, removing reverted deposits before indexing the
+    /// committed ones.
+    pub fn apply(&self, notification: &CanonStateNotification<OpPrimitives>) {
+        if let Some(reverted) = notification.reverted() {
+            for block in reverted.blocks().values() {
+                for user in user_deposits(block.body().transactions()) {
+                    self.blocks.invalidate(&user.deposit.source_hash);
+                }
+            }
+        }
+        for (number, block) in notification.committed().blocks() {
+            for user in user_deposits(block.body().transactions()) {
+                self.blocks.insert(user.deposit.source_hash, *number);
+            }
+        }
+    }
+
+    /// Keeps the index in sync with the canonical chain until the notifications end.
+    pub async fn run(self: Arc<Self>, mut canon_state: CanonStateConsumer<OpPrimitives>) {
+        while let Some(notification) = canon_state.recv().await {
+            self.apply(&notification);
+        }
+        debug!(target: "flashblocks", "canonical state stream closed, deposit index stopped");
+    }
+}
+
+/// Deposit transaction lookup API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait DepositsApi {
+    /// Returns a page of the deposit transactions matching the query, in block order. A source
+    /// hash or L1 log lookup returns no deposit until the deposit is included, and takes no page
+    /// token.
+    #[method(name = "getDepositTransactions")]
+    async fn get_deposit_transactions(
+        &self,
+        query: DepositQuery,
+        page_token: Option<String>,
+        page_size: Option<usize>,
+    ) -> RpcResult<DepositsPage>;
+}
+
+/// Serves deposit lookups from the deposit index and the blocks of the node.
+#[derive(Clone)]
+pub struct DepositsRpc<Provider> {
+    provider: Provider,
+    index: Arc<DepositIndex>,
+}
+
+impl<Provider> DepositsRpc<Provider> {
+    pub fn new(provider: Provider, index: Arc<DepositIndex>) -> Self {
+        Self { provider, index }
+    }
+}
+
+impl<Provider> DepositsRpc<Provider>
+where
+    Provider: TransactionsProvider<Transaction = OpTransactionSigned>
+        + ReceiptProvider<Receipt = OpReceipt>
+        + BlockHashReader
+        + BlockIdReader,
+{
+    /// Returns a page of the deposits of the blocks in `from..=to` continuing at `token`, only
+    /// the one with `source_hash` if set.
+    fn deposits(
+        &self,
+        from: u64,
+        to: u64,
+        source_hash: Option<B256>,
+        token: Option<PageToken>,
+        page_size: usize,
+    ) -> ProviderResult<DepositsPage> {
+        let safe = self.provider.safe_block_number()?;
+        let finalized = self.provider.finalized_block_number()?;
+        let inclusion = |number: u64| {
+            if finalized.is_some_and(|finalized| number <= finalized) {
+                FinalityHead::Finalized
+            } else if safe.is_some_and(|safe| number <= safe) {
+                FinalityHead::Safe
+            } else {
+                FinalityHead::Unsafe
+            }
+        };
+        let after = match token {
+            Some(PageToken::After { block, index }) => Some((block, index)),
+            _ => None,
+        };
+
+        let start = token.map_or(from, |token| token.block());
+        let mut page = DepositsPage::default();
+        for number in start..=to {
+            if number - start == MAX_BLOCKS_PER_PAGE {
+                page.next_page_token = Some(PageToken::Block(number).to_string());
+                return Ok(page);
+            }
+            let Some(txs) = self.provider.transactions_by_block(number.into())? else { continue };
+            let matching: Vec<_> = user_deposits(&txs)
+                .into_iter()
+                .filter(|user| source_hash.is_none_or(|hash| user.deposit.source_hash == hash))
+                .filter(|user| after.is_none_or(|after| (number, user.index as u64) > after))
+                .collect();
+            if matching.is_empty() {
+                continue;
+            }
+
+            let l1_origin = txs.first().and_then(|tx| l1_origin_from_calldata(tx.input()));
+            let block_hash = self.provider.block_hash(number)?.unwrap_or_default();
+            let receipts = self.provider.receipts_by_block(number.into())?.unwrap_or_default();
+            for UserDeposit { index, l1_log_index, deposit } in matching {
+                if page.deposits.len() == page_size {
+                    let last = page.deposits.last().map(|last| PageToken::After {
+                        block: last.block_number.to(),
+                        index: last.transaction_index.to(),
+                    });
+                    page.next_page_token = last.map(|last| last.to_string());
+                    return Ok(page);
+                }
+                page.deposits.push(DepositTransaction {
+                    hash: txs[index].tx_hash(),
+                    source_hash: deposit.source_hash,
+                    from: deposit.from,
+                    to: deposit.to.to().copied(),
+                    mint: U256::from(deposit.mint),
+                    value: deposit.value,
+                    gas: U64::from(deposit.gas_limit),
+                    block_number: U64::from(number),
+                    block_hash,
+                    transaction_index: U64::from(index),
+                    l1_origin,
+                    l1_log_index: U64::from(l1_log_index),
+                    success: receipts.get(index).is_some_and(|receipt| receipt.status()),
+                    inclusion: inclusion(number),
+                });
+            }
+        }
+        Ok(page)
+    }
+
+    /// Returns the indexed deposit with `source_hash`.
+    fn deposit(&self, source_hash: B256) -> ProviderResult<DepositsPage> {
+        match self.index.block(&source_hash) {
+            Some(number) => self.deposits(number, number, Some(source_hash), None, MAX_PAGE_SIZE),
+            None => Ok(DepositsPage::default()),
+        }
+    }
+}
+
+#[async_trait]
+impl<Provider> DepositsApiServer for DepositsRpc<Provider>
+where
+    Provider: TransactionsProvider<Transaction = OpTransactionSigned>
+        + ReceiptProvider<Receipt = OpReceipt>
+        + BlockHashReader
+        + BlockIdReader
+        + Clone
+        + 'static,
+{
+    async fn get_deposit_transactions(
+        &self,
+        query: DepositQuery,
+        page_token: Option<String>,
+        page_size: Option<usize>,
+    ) -> RpcResult<DepositsPage> {
+        let page_size = DEPOSITS_PAGE_LIMITS.page_size(page_size)?;
+        let token = page_token
+            .map(|token| PageToken::parse(&token).ok_or(PageParamError::InvalidToken))
+            .transpose()?;
+
+        let this = self.clone();
+        let lookup = match query {
+            DepositQuery::BlockRange { from_block, to_block } => {
+                let (from, to) = (from_block.to::<u64>(), to_block.to::<u64>());
+                if from > to {
+                    return Err(invalid_params_rpc_err("fromBlock is after toBlock"));
+                }
+                if token.is_some_and(|token| !(from..=to).contains(&token.block())) {
+                    return Err(invalid_params_rpc_err("Page token outside of the block range"));
+                }
+                tokio::task::spawn_blocking(move || this.deposits(from, to, None, token, page_size))
+                    .await
+            }
+            // Lookups return a single deposit
+            _ if token.is_some() => return Err(PageParamError::InvalidToken.into()),
+            DepositQuery::SourceHash { source_hash } => {
+                tokio::task::spawn_blocking(move || this.deposit(source_hash)).await
+            }
+            DepositQuery::L1Log { l1_block_hash, l1_log_index } => {
+                let source_hash =
+                    UserDepositSource::new(l1_block_hash, l1_log_index.to()).source_hash();
+                tokio::task::spawn_blocking(move || this.deposit(source_hash)).await
+            }
+        };
+        match lookup {
+            Ok(Ok(page)) => Ok(page),
+            Ok(Err(err)) => Err(internal_rpc_err(err.to_string())),
+            Err(err) => Err(internal_rpc_err(err.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finality::PACKED_L1_INFO_LEN;
+    use alloy_primitives::{Sealed, TxKind};
+    use op_alloy_consensus::{OpTxEnvelope, UpgradeDepositSource};
+
+    fn deposit(from: Address, source_hash: B256) -> OpTransactionSigned {
+        OpTxEnvelope::Deposit(Sealed::new(TxDeposit {
+            source_hash,
+            from,
+            to: TxKind::Create,
+            mint: 1_000,
+            ..Default::default()
+        }))
+    }
+
+    /// L1 info deposit of a block of the epoch of `l1_block_hash`.
+    fn l1_info(l1_block_hash: B256) -> OpTransactionSigned {
+        let mut input = vec![0; PACKED_L1_INFO_LEN];
+        input[100..132].copy_from_slice(l1_block_hash.as_slice());
+        OpTxEnvelope::Deposit(Sealed::new(TxDeposit {
+            source_hash: B256::repeat_byte(0x01),
+            from: Address::with_last_byte(1),
+            input: input.into(),
+            ..Default::default()
+        }))
+    }
+
+    #[test]
+    fn test_user_deposits() {
+        let l1_block_hash = B256::repeat_byte(0xab);
+        let user = |log_index| UserDepositSource::new(l1_block_hash, log_index).source_hash();
+        let upgrade = UpgradeDepositSource { intent: "Upgrade: test".to_string() }.source_hash();
+        let txs = vec![
+            l1_info(l1_block_hash),
+            deposit(Address::with_last_byte(2), user(3)),
+            deposit(Address::with_last_byte(3), user(7)),
+            // Upgrade deposits aren't user deposits whatever their sender
+            deposit(Address::with_last_byte(4), upgrade),
+            deposit(Address::with_last_byte(5), user(8)),
+        ];
+
+        let deposits: Vec<_> = user_deposits(&txs)
+            .iter()
+            .map(|user| (user.index, user.l1_log_index, user.deposit.source_hash))
+            .collect();
+        assert_eq!(deposits, vec![(1, 3, user(3)), (2, 7, user(7)), (4, 8, user(8))]);
+
+        // Deposits of another L1 origin and blocks without L1 info aren't user deposits
+        assert!(user_deposits(&[l1_info(B256::repeat_byte(0xcd)), txs[1].clone()]).is_empty());
+        assert!(user_deposits(&txs[1..]).is_empty());
+    }
+
+    #[test]
+    fn test_page_token() {
+        for token in [PageToken::Block(16), PageToken::After { block: 16, index: 2 }] {
+            assert_eq!(PageToken::parse(&token.to_string()), Some(token));
+        }
+        assert_eq!(PageToken::After { block: 16, index: 2 }.to_string(), "10-2");
+        assert_eq!(PageToken::parse("10-2-3"), None);
+        assert_eq!(PageToken::parse("0x10"), None);
+    }
+
+    #[test]
+    fn test_deposit_query_serde() {
+        let hash = B256::repeat_byte(0x22);
+        let query: DepositQuery =
+            serde_json::from_value(serde_json::json!({"sourceHash": hash})).unwrap();
+        assert_eq!(query, DepositQuery::SourceHash { source_hash: hash });
+
+        let query: DepositQuery =
+            serde_json::from_value(serde_json::json!({"l1BlockHash": hash, "l1LogIndex": "0x3"}))
+                .unwrap();
+        assert_eq!(query, DepositQuery::L1Log { l1_block_hash: hash, l1_log_index: U64::from(3) });
+
+        let query: DepositQuery =
+            serde_json::from_value(serde_json::json!({"fromBlock": "0x10", "toBlock": "0x20"}))
+                .unwrap();
+        assert_eq!(
+            query,
+            DepositQuery::BlockRange { from_block: U64::from(16), to_block: U64::from(32) }
+        );
+
+        assert!(
+            serde_json::from_value::<DepositQuery>(serde_json::json!({"l1TxHash": hash})).is_err()
+        );
+    }
+}
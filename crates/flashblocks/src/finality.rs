@@ -0,0 +1,182 @@
+//! Head progression events of the `flashblockFinality` subscription.
+//!
+//! Bridges and exchanges drive their confirmation logic off the unsafe, safe and finalized heads.
+//! Instead of polling the three block tags, a single subscription emits an event every time one of
+//! the heads advances. Safe head events carry the L1 origin of the block, decoded from its L1
+//! info deposit transaction.
+
+use alloy_consensus::{BlockHeader as _, Transaction as _};
+use alloy_primitives::{B256, U64};
+use futures::{stream, Stream, StreamExt};
+use reth_chain_state::ForkChoiceSubscriptions;
+use reth_primitives_traits::NodePrimitives;
+use reth_storage_api::TransactionsProvider;
+use reth_tracing::tracing::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::canon_mux::CanonStateConsumer;
+
+/// Selector of the Bedrock `setL1BlockValues` call, ABI encoded.
+pub(crate) const BEDROCK_L1_INFO_SELECTOR: [u8; 4] = [0x01, 0x5d, 0x8e, 0xb9];
+
+/// Length of the Bedrock L1 info calldata: selector and eight words.
+pub(crate) const BEDROCK_L1_INFO_LEN: usize = 4 + 8 * 32;
+
+/// Minimum length of the packed L1 info calldata introduced with Ecotone. Later forks only append
+/// fields, so the L1 block number and hash stay at the same offsets.
+pub(crate) const PACKED_L1_INFO_LEN: usize = 164;
+
+/// Head whose progression an event reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FinalityHead {
+    Unsafe,
+    Safe,
+    Finalized,
+}
+
+/// L1 block an L2 block was derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1Origin {
+    pub number: U64,
+    pub hash: B256,
+}
+
+/// Event emitted when one of the heads advances.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FinalityEvent {
+    pub head: FinalityHead,
+    pub number: U64,
+    pub hash: B256,
+    /// L1 origin of the block, only set for safe head events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub l1_origin: Option<L1Origin>,
+}
+
+impl FinalityEvent {
+    pub fn new(head: FinalityHead, number: u64, hash: B256, l1_origin: Option<L1Origin>) -> Self {
+        Self { head, number: U64::from(number), hash, l1_origin }
+    }
+}
+
+/// Decodes the L1 origin from the calldata of an L1 info deposit transaction.
+pub fn l1_origin_from_calldata(input: &[u8]) -> Option<L1Origin> {
+    let min_len = if input.starts_with(&BEDROCK_L1_INFO_SELECTOR) {
+        BEDROCK_L1_INFO_LEN
+    } else {
+        PACKED_L1_INFO_LEN
+    };
+    if input.len() < min_len {
+        return None;
+    }
+
+    // Both encodings place the L1 block number in bytes 28..36 and the hash in bytes 100..132:
+    // the right aligned first and fourth ABI words for Bedrock, and the packed fields following
+    // the scalars, sequence number and timestamp from Ecotone on.
+    Some(L1Origin {
+        number: U64::from(u64::from_be_bytes(input[28..36].try_into().ok()?)),
+        hash: B256::from_slice(&input[100..132]),
+    })
+}
+
+/// Returns the L1 origin of the given block, read from its L1 info deposit transaction.
+fn block_l1_origin<P>(provider: &P, hash: B256) -> Option<L1Origin>
+where
+    P: TransactionsProvider,
+    P::Transaction: alloy_consensus::Transaction,
+{
+    match provider.transactions_by_block(hash.into()) {
+        Ok(txs) => txs?.first().and_then(|tx| l1_origin_from_calldata(tx.input())),
+        Err(err) => {
+            warn!(target: "xlayer::flashblocks", %hash, %err, "Failed to read L1 info transaction");
+            None
+        }
+    }
+}
+
+/// Creates a stream of head progression events, following the unsafe head through the given
+/// canonical state consumer.
+pub fn finality_stream<N, P>(
+    provider: P,
+    canon_state: CanonStateConsumer<N>,
+) -> impl Stream<Item = FinalityEvent>
+where
+    N: NodePrimitives,
+    P: ForkChoiceSubscriptions<Header = N::BlockHeader>
+        + TransactionsProvider<Transaction = N::SignedTx>
+        + Clone
+        + 'static,
+{
+    let unsafe_heads = canon_state.into_stream().map(|notification| {
+        let tip = notification.tip();
+        FinalityEvent::new(FinalityHead::Unsafe, tip.header().number(), tip.hash(), None)
+    });
+    let safe_heads = provider.safe_block_stream().map({
+        let provider = provider.clone();
+        move |header| {
+            let l1_origin = block_l1_origin(&provider, header.hash());
+            FinalityEvent::new(FinalityHead::Safe, header.number(), header.hash(), l1_origin)
+        }
+    });
+    let finalized_heads = provider.finalized_block_stream().map(|header| {
+        FinalityEvent::new(FinalityHead::Finalized, header.number(), header.hash(), None)
+    });
+
+    stream::select(unsafe_heads, stream::select(safe_heads, finalized_heads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    #[test]
+    fn test_l1_origin_from_bedrock_calldata() {
+        let mut input = BEDROCK_L1_INFO_SELECTOR.to_vec();
+        let mut words = [[0u8; 32]; 8];
+        words[0][24..].copy_from_slice(&100u64.to_be_bytes());
+        words[3] = [0xab; 32];
+        words.iter().for_each(|word| input.extend_from_slice(word));
+
+        assert_eq!(
+            l1_origin_from_calldata(&input),
+            Some(L1Origin { number: U64::from(100), hash: B256::repeat_byte(0xab) })
+        );
+        assert_eq!(l1_origin_from_calldata(&input[..100]), None);
+    }
+
+    #[test]
+    fn test_l1_origin_from_ecotone_calldata() {
+        let input = hex!(
+            "440a5e20000f424000000000000000000000000300000000670d6d890000000000000125000000000000000000000000000000000000000000000000000000000000000700000000000000000000000000000000000000000000000000000000000000014bf9181db6e381d4384bbf69c48b0ee0eed23c6ca26143c6d2544f9d39997a590000000000000000000000007431310e026b69bfc676c0013e12a1a11411eec9"
+        );
+        assert_eq!(
+            l1_origin_from_calldata(&input),
+            Some(L1Origin {
+                number: U64::from(0x125),
+                hash: "0x4bf9181db6e381d4384bbf69c48b0ee0eed23c6ca26143c6d2544f9d39997a59"
+                    .parse()
+                    .unwrap(),
+            })
+        );
+        assert_eq!(l1_origin_from_calldata(&input[..PACKED_L1_INFO_LEN - 1]), None);
+    }
+
+    #[test]
+    fn test_finality_event_serde() {
+        let event = FinalityEvent::new(
+            FinalityHead::Safe,
+            16,
+            B256::repeat_byte(0x11),
+            Some(L1Origin { number: U64::from(8), hash: B256::repeat_byte(0x22) }),
+        );
+        let value = serde_json::to_value(&event).unwrap();
+        assert_eq!(value["head"], "safe");
+        assert_eq!(value["number"], "0x10");
+        assert_eq!(value["l1Origin"]["number"], "0x8");
+
+        let event = FinalityEvent::new(FinalityHead::Unsafe, 17, B256::ZERO, None);
+        assert!(serde_json::to_value(&event).unwrap().get("l1Origin").is_none());
+    }
+}
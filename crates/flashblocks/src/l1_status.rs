@@ -0,0 +1,258 @@
+//! L1 data availability status, served over `xlayer_getL1Status` and the `l1Status` subscription.
+//!
+//! Exchanges gate withdrawals on how far the chain has been posted to and derived from L1. The
+//! status reports the L1 origin of the unsafe head, the latest L1 block the sequencer processed,
+//! the safe head derived from L1 and its L1 origin, and the lag between the unsafe and safe heads,
+//! which is the part of the chain the batcher has yet to submit or the rollup node to derive.
+//!
+//! The data availability mode isn't recorded on L2. It is inferred from the L1 fee scalars of the
+//! L1 info deposit transaction: chains posting blobs charge for blob space with a non-zero blob
+//! base fee scalar, chains posting calldata leave it zero. Blocks before Ecotone always use
+//! calldata.
+
+use alloy_consensus::{BlockHeader as _, Transaction as _};
+use alloy_primitives::{B256, U64};
+use futures::{future::ready, stream, Stream, StreamExt};
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use reth_chain_state::ForkChoiceSubscriptions;
+use reth_primitives_traits::NodePrimitives;
+use reth_rpc_server_types::result::internal_rpc_err;
+use reth_storage_api::{
+    errors::provider::ProviderResult, BlockIdReader, BlockNumReader, HeaderProvider,
+    TransactionsProvider,
+};
+use reth_tracing::tracing::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    canon_mux::CanonStateConsumer,
+    finality::{
+        l1_origin_from_calldata, L1Origin, BEDROCK_L1_INFO_LEN, BEDROCK_L1_INFO_SELECTOR,
+        PACKED_L1_INFO_LEN,
+    },
+};
+
+/// Data availability mode of the batcher, inferred from the L1 fee scalars.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DaMode {
+    Blobs,
+    Calldata,
+    /// The block has no L1 info transaction
+    Unknown,
+}
+
+/// Returns the data availability mode of the calldata of an L1 info deposit transaction.
+pub fn da_mode_from_calldata(input: &[u8]) -> DaMode {
+    if input.starts_with(&BEDROCK_L1_INFO_SELECTOR) {
+        return if input.len() >= BEDROCK_L1_INFO_LEN { DaMode::Calldata } else { DaMode::Unknown };
+    }
+    if input.len() < PACKED_L1_INFO_LEN {
+        return DaMode::Unknown;
+    }
+    // The packed encoding starts with the base fee scalar and the blob base fee scalar
+    let blob_base_fee_scalar = u32::from_be_bytes([input[8], input[9], input[10], input[11]]);
+    if blob_base_fee_scalar == 0 {
+        DaMode::Calldata
+    } else {
+        DaMode::Blobs
+    }
+}
+
+/// Number and hash of an L2 block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRef {
+    pub number: U64,
+    pub hash: B256,
+}
+
+/// L1 data availability status of the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L1Status {
+    pub unsafe_head: BlockRef,
+    /// L1 origin of the unsafe head, the latest L1 block processed
+    pub l1_origin: Option<L1Origin>,
+    /// Head derived from L1, `None` until the rollup node reports one
+    pub safe_head: Option<BlockRef>,
+    pub safe_l1_origin: Option<L1Origin>,
+    /// Blocks between the safe and unsafe heads, not yet submitted to or derived from L1
+    pub submission_lag_blocks: Option<U64>,
+    /// Seconds between the timestamps of the safe and unsafe heads
+    pub submission_lag_secs: Option<U64>,
+    pub da_mode: DaMode,
+}
+
+impl L1Status {
+    /// Returns the fields of the status that only change with L1, excluding the unsafe head and
+    /// the lags that move with every block.
+    fn l1_view(&self) -> (Option<L1Origin>, Option<BlockRef>, DaMode) {
+        (self.l1_origin, self.safe_head, self.da_mode)
+    }
+}
+
+/// Returns the input of the L1 info deposit transaction of a block.
+fn l1_info_input<P>(provider: &P, number: u64) -> ProviderResult<Option<Vec<u8>>>
+where
+    P: TransactionsProvider,
+    P::Transaction: alloy_consensus::Transaction,
+{
+    let txs = provider.transactions_by_block(number.into())?;
+    Ok(txs.and_then(|txs| txs.first().map(|tx| tx.input().to_vec())))
+}
+
+/// Reads the L1 status at the current unsafe and safe heads.
+pub fn read_l1_status<P>(provider: &P) -> ProviderResult<L1Status>
+where
+    P: BlockNumReader + BlockIdReader + HeaderProvider + TransactionsProvider,
+    P::Transaction: alloy_consensus::Transaction,
+{
+    let unsafe_number = provider.best_block_number()?;
+    let unsafe_header = provider.sealed_header(unsafe_number)?;
+    let unsafe_input = l1_info_input(provider, unsafe_number)?;
+    let unsafe_head = BlockRef {
+        number: U64::from(unsafe_number),
+        hash: unsafe_header.as_ref().map(|header| header.hash()).unwrap_or_default(),
+    };
+
+    let mut status = L1Status {
+        unsafe_head,
+        l1_origin: unsafe_input.as_deref().and_then(l1_origin_from_calldata),
+        safe_head: None,
+        safe_l1_origin: None,
+        submission_lag_blocks: None,
+        submission_lag_secs: None,
+        da_mode: unsafe_input.as_deref().map_or(DaMode::Unknown, da_mode_from_calldata),
+    };
+
+    let Some(safe) = provider.safe_block_num_hash()? else { return Ok(status) };
+    status.safe_head = Some(BlockRef { number: U64::from(safe.number), hash: safe.hash });
+    status.safe_l1_origin =
+        l1_info_input(provider, safe.number)?.as_deref().and_then(l1_origin_from_calldata);
+    status.submission_lag_blocks = Some(U64::from(unsafe_number.saturating_sub(safe.number)));
+    if let (Some(unsafe_header), Some(safe_header)) =
+        (unsafe_header, provider.sealed_header(safe.number)?)
+    {
+        status.submission_lag_secs =
+            Some(U64::from(unsafe_header.timestamp().saturating_sub(safe_header.timestamp())));
+    }
+    Ok(status)
+}
+
+/// Creates a stream of L1 status updates, emitting the status whenever the L1 origin of the
+/// unsafe head, the safe head or the data availability mode changes.
+pub fn l1_status_stream<N, P>(
+    provider: P,
+    canon_state: CanonStateConsumer<N>,
+) -> impl Stream<Item = L1Status>
+where
+    N: NodePrimitives,
+    P: ForkChoiceSubscriptions<Header = N::BlockHeader>
+        + BlockNumReader
+        + BlockIdReader
+        + HeaderProvider
+        + TransactionsProvider<Transaction = N::SignedTx>
+        + Clone
+        + 'static,
+{
+    let unsafe_heads = canon_state.into_stream().map(|_| ());
+    let safe_heads = provider.safe_block_stream().map(|_| ());
+
+    let mut last = None;
+    stream::select(unsafe_heads, safe_heads).filter_map(move |()| {
+        let status = match read_l1_status(&provider) {
+            Ok(status) => Some(status),
+            Err(err) => {
+                warn!(target: "xlayer::flashblocks", %err, "Failed to read L1 status");
+                None
+            }
+        };
+        let status =
+            status.filter(|status| last.replace(status.l1_view()) != Some(status.l1_view()));
+        ready(status)
+    })
+}
+
+/// L1 data availability status API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait L1StatusApi {
+    /// Returns the L1 origin of the unsafe head, the safe head, the batcher submission lag and
+    /// the data availability mode.
+    #[method(name = "getL1Status")]
+    fn get_l1_status(&self) -> RpcResult<L1Status>;
+}
+
+/// Serves the L1 status from the node provider.
+#[derive(Debug, Clone)]
+pub struct L1StatusRpc<Provider> {
+    provider: Provider,
+}
+
+impl<Provider> L1StatusRpc<Provider> {
+    pub fn new(provider: Provider) -> Self {
+        Self { provider }
+    }
+}
+
+impl<Provider> L1StatusApiServer for L1StatusRpc<Provider>
+where
+    Provider: BlockNumReader
+        + BlockIdReader
+        + HeaderProvider
+        + TransactionsProvider
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    Provider::Transaction: alloy_consensus::Transaction,
+{
+    fn get_l1_status(&self) -> RpcResult<L1Status> {
+        read_l1_status(&self.provider).map_err(|err| internal_rpc_err(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::hex;
+
+    const ECOTONE_L1_INFO: [u8; 164] = hex!(
+        "440a5e20000f424000000000000000000000000300000000670d6d890000000000000125000000000000000000000000000000000000000000000000000000000000000700000000000000000000000000000000000000000000000000000000000000014bf9181db6e381d4384bbf69c48b0ee0eed23c6ca26143c6d2544f9d39997a590000000000000000000000007431310e026b69bfc676c0013e12a1a11411eec9"
+    );
+
+    #[test]
+    fn test_da_mode_from_calldata() {
+        // Zero blob base fee scalar
+        assert_eq!(da_mode_from_calldata(&ECOTONE_L1_INFO), DaMode::Calldata);
+
+        let mut blobs = ECOTONE_L1_INFO;
+        blobs[8..12].copy_from_slice(&810_949u32.to_be_bytes());
+        assert_eq!(da_mode_from_calldata(&blobs), DaMode::Blobs);
+
+        let mut bedrock = BEDROCK_L1_INFO_SELECTOR.to_vec();
+        bedrock.resize(BEDROCK_L1_INFO_LEN, 0);
+        assert_eq!(da_mode_from_calldata(&bedrock), DaMode::Calldata);
+
+        assert_eq!(da_mode_from_calldata(&ECOTONE_L1_INFO[..100]), DaMode::Unknown);
+        assert_eq!(da_mode_from_calldata(&[]), DaMode::Unknown);
+    }
+
+    #[test]
+    fn test_l1_status_serde() {
+        let status = L1Status {
+            unsafe_head: BlockRef { number: U64::from(20), hash: B256::repeat_byte(1) },
+            l1_origin: l1_origin_from_calldata(&ECOTONE_L1_INFO),
+            safe_head: Some(BlockRef { number: U64::from(16), hash: B256::repeat_byte(2) }),
+            safe_l1_origin: None,
+            submission_lag_blocks: Some(U64::from(4)),
+            submission_lag_secs: Some(U64::from(4)),
+            da_mode: DaMode::Blobs,
+        };
+        let value = serde_json::to_value(&status).unwrap();
+        assert_eq!(value["unsafeHead"]["number"], "0x14");
+        assert_eq!(value["l1Origin"]["number"], "0x125");
+        assert_eq!(value["submissionLagBlocks"], "0x4");
+        assert_eq!(value["daMode"], "blobs");
+        assert_eq!(serde_json::from_value::<L1Status>(value).unwrap(), status);
+    }
+}
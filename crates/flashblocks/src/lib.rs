@@ -1,8 +1,19 @@
 //! X-Layer flashblocks crate.
 
+#[cfg(feature = "block-publisher")]
+pub mod block_publisher;
+pub mod deposits;
+pub mod finality;
 pub mod handler;
+pub mod l1_status;
+pub mod pending_txs;
+pub mod preconf;
 pub mod pubsub;
+pub mod receipts;
+pub mod scopes;
+pub mod shedding;
 pub mod subscription;
+pub mod transactions;
 
 use reth_primitives_traits::NodePrimitives;
 use std::sync::Arc;
@@ -13,6 +24,8 @@ use reth_optimism_primitives as _;
 // Used by downstream crates
 use alloy_rpc_types_eth as _;
 
+pub use xlayer_legacy_rpc::canon_mux;
+
 mod consensus;
 pub use consensus::FlashBlockConsensusClient;
 
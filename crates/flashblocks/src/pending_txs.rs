@@ -0,0 +1,205 @@
+//! Upstream pending transaction feed of replicas.
+//!
+//! Transactions sent to a replica are forwarded to the sequencer, transactions sent to other nodes
+//! never reach the replica's pool, so `newPendingTransactions` subscribers on a replica only see a
+//! fraction of the sequencer's mempool and see it late. When an upstream node is configured, the
+//! replica keeps a `newPendingTransactions` subscription to it open and merges its notifications
+//! into the local ones. Transactions known to both nodes are delivered once per subscription.
+//!
+//! The upstream subscription requests full transactions, subscribers of hashes get the hash of
+//! each upstream transaction and subscribers of full transactions the upstream object as is.
+
+use std::{future::ready, sync::Arc, time::Duration};
+
+use alloy_primitives::TxHash;
+use futures::{stream, Stream, StreamExt};
+use jsonrpsee::{
+    core::{
+        client::{Subscription, SubscriptionClientT},
+        ClientError,
+    },
+    rpc_params,
+    ws_client::{WsClient, WsClientBuilder},
+};
+use moka::{policy::EvictionPolicy, sync::Cache};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_tracing::tracing::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::BroadcastStream;
+
+/// Delay before reconnecting after the upstream subscription is lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Number of upstream transactions buffered for slow subscribers, older ones are skipped.
+const FEED_CAPACITY: usize = 4096;
+
+/// Number of transaction hashes remembered per subscription to drop duplicates.
+const MAX_SEEN_TXHASH_CACHE_SIZE: u64 = 10_000;
+
+/// Pending transaction announced by the upstream node.
+#[derive(Debug, Clone)]
+pub struct UpstreamPendingTx {
+    pub hash: TxHash,
+    /// Transaction object, `None` if the upstream node only sent the hash
+    pub transaction: Option<Arc<Value>>,
+}
+
+/// Item of a merged full `newPendingTransactions` subscription.
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum PendingTransaction<T> {
+    /// Transaction of the local pool
+    Local(T),
+    /// Transaction of the upstream node, forwarded as received
+    Upstream(Arc<Value>),
+}
+
+/// Upstream pending transaction feed metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_pending_tx_feed")]
+struct PendingTxFeedMetrics {
+    /// Number of pending transactions received from the upstream node
+    received: Counter,
+    /// Number of upstream notifications that couldn't be decoded
+    invalid_notifications: Counter,
+    /// Number of times the upstream subscription was re-established
+    reconnects: Counter,
+}
+
+/// Subscription to the `newPendingTransactions` feed of an upstream node.
+#[derive(Debug, Clone)]
+pub struct UpstreamPendingTxFeed {
+    url: String,
+    sender: broadcast::Sender<UpstreamPendingTx>,
+}
+
+impl UpstreamPendingTxFeed {
+    /// Creates the feed of the node at the WebSocket `url`, nothing is received until
+    /// [`Self::run`] is spawned.
+    pub fn new(url: impl Into<String>) -> Self {
+        let (sender, _) = broadcast::channel(FEED_CAPACITY);
+        Self { url: url.into(), sender }
+    }
+
+    /// Returns the upstream transactions received from now on.
+    pub fn subscribe(&self) -> impl Stream<Item = UpstreamPendingTx> + use<> {
+        BroadcastStream::new(self.sender.subscribe()).filter_map(|res| ready(res.ok()))
+    }
+
+    /// Keeps the upstream subscription open, reconnecting whenever it is lost.
+    pub async fn run(self) {
+        let metrics = PendingTxFeedMetrics::default();
+        let mut connected = false;
+        loop {
+            match self.connect().await {
+                Ok((_client, mut subscription)) => {
+                    info!(target: "xlayer::pending_tx_feed", url = %self.url, "Subscribed to upstream pending transactions");
+                    if connected {
+                        metrics.reconnects.increment(1);
+                    }
+                    connected = true;
+
+                    while let Some(notification) = subscription.next().await {
+                        let Some(tx) = notification.ok().and_then(parse_notification) else {
+                            metrics.invalid_notifications.increment(1);
+                            debug!(target: "xlayer::pending_tx_feed", "Invalid upstream pending transaction notification");
+                            continue;
+                        };
+                        metrics.received.increment(1);
+                        // Fails only while nobody is subscribed
+                        let _ = self.sender.send(tx);
+                    }
+                    warn!(target: "xlayer::pending_tx_feed", url = %self.url, "Upstream pending transaction subscription closed");
+                }
+                Err(err) => {
+                    warn!(target: "xlayer::pending_tx_feed", url = %self.url, %err, "Failed to subscribe to upstream pending transactions");
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+
+    /// Connects to the upstream node and subscribes to full pending transactions. The subscription
+    /// lives as long as the returned client.
+    async fn connect(&self) -> Result<(WsClient, Subscription<Value>), ClientError> {
+        let client = WsClientBuilder::default().build(&self.url).await?;
+        let subscription = client
+            .subscribe(
+                "eth_subscribe",
+                rpc_params!["newPendingTransactions", true],
+                "eth_unsubscribe",
+            )
+            .await?;
+        Ok((client, subscription))
+    }
+}
+
+/// Decodes an upstream notification, either a full transaction or a bare hash.
+fn parse_notification(notification: Value) -> Option<UpstreamPendingTx> {
+    #[derive(Deserialize)]
+    struct TransactionHash {
+        hash: TxHash,
+    }
+
+    if notification.is_string() {
+        let hash = serde_json::from_value(notification).ok()?;
+        return Some(UpstreamPendingTx { hash, transaction: None });
+    }
+    let TransactionHash { hash } = serde_json::from_value(notification.clone()).ok()?;
+    Some(UpstreamPendingTx { hash, transaction: Some(Arc::new(notification)) })
+}
+
+/// Merges the local and upstream items of a subscription, keyed by transaction hash, dropping the
+/// items of transactions already delivered.
+pub fn merge_pending_transactions<T>(
+    local: impl Stream<Item = (TxHash, T)> + Unpin,
+    upstream: impl Stream<Item = (TxHash, T)> + Unpin,
+) -> impl Stream<Item = T> {
+    let seen: Cache<TxHash, ()> = Cache::builder()
+        .max_capacity(MAX_SEEN_TXHASH_CACHE_SIZE)
+        .eviction_policy(EvictionPolicy::lru())
+        .build();
+    stream::select(local, upstream).filter_map(move |(hash, item)| {
+        let unseen = !seen.contains_key(&hash);
+        if unseen {
+            seen.insert(hash, ());
+        }
+        ready(unseen.then_some(item))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_notification() {
+        let hash = TxHash::with_last_byte(1);
+        let tx = parse_notification(serde_json::json!(hash)).unwrap();
+        assert_eq!(tx.hash, hash);
+        assert!(tx.transaction.is_none());
+
+        let object = serde_json::json!({"hash": hash, "nonce": "0x1"});
+        let tx = parse_notification(object.clone()).unwrap();
+        assert_eq!(tx.hash, hash);
+        assert_eq!(tx.transaction.as_deref(), Some(&object));
+
+        assert!(parse_notification(serde_json::json!({"nonce": "0x1"})).is_none());
+        assert!(parse_notification(serde_json::json!("0x1")).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_merge_drops_duplicates() {
+        let hash = TxHash::with_last_byte;
+        let local = stream::iter([(hash(1), "local 1"), (hash(2), "local 2")]);
+        let upstream = stream::iter([(hash(2), "upstream 2"), (hash(3), "upstream 3")]);
+        let mut items = merge_pending_transactions(local, upstream).collect::<Vec<_>>().await;
+        items.sort_unstable();
+
+        // Transaction 2 is delivered once, by whichever stream announced it first
+        assert_eq!(items.len(), 3);
+        assert!(items.contains(&"local 1") && items.contains(&"upstream 3"));
+    }
+}
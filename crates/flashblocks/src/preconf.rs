@@ -0,0 +1,199 @@
+//! Preconfirmations of flashblock inclusion relayed by replicas, served over
+//! `xlayer_getPreconfirmation` and attached to the transactions of the `flashblocks` subscription.
+//!
+//! The sequencer's builder signs a preconfirmation of every transaction of a flashblock when it
+//! publishes the flashblock, see [`xlayer_builder::flashblocks::preconf`], and carries the
+//! signatures in the `preconfirmations` field of the flashblock message. Replicas never hold the
+//! preconfirmation key, they retain the preconfirmations received from the flashblocks endpoint
+//! and serve them as signed. Clients verify them against the published signer address.
+//!
+//! The flashblocks building the pending block are decoded without that field, so the relay keeps
+//! its own connection to the flashblocks endpoint. A transaction streamed by the `flashblocks`
+//! subscription before its preconfirmation arrived over that connection carries none.
+
+use std::sync::Arc;
+
+use alloy_consensus::BlockHeader as _;
+use alloy_primitives::{bytes::Bytes, TxHash};
+use futures::StreamExt;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use moka::sync::Cache;
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_optimism_flashblocks::PendingFlashBlock;
+use reth_primitives_traits::NodePrimitives;
+use reth_tracing::tracing::debug;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{
+    ws::{try_parse_message, FlashBlockDecoder},
+    FlashBlock, WsFlashBlockStream,
+};
+
+pub use xlayer_builder::flashblocks::preconf::{
+    PreconfCommitment, Preconfirmation, PRECONF_DOMAIN,
+};
+
+/// Number of transactions whose preconfirmations are retained.
+const MAX_PRECONFIRMATIONS: u64 = 100_000;
+
+/// Preconfirmation relay metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_preconf")]
+struct PreconfMetrics {
+    /// Number of preconfirmations received from the flashblocks endpoint
+    received: Counter,
+    /// Number of flashblock messages that couldn't be decoded
+    invalid_messages: Counter,
+}
+
+/// Flashblock message of the flashblocks endpoint, the payload with the preconfirmations of its
+/// transactions.
+#[derive(Deserialize)]
+struct FlashblockMessage {
+    #[serde(flatten)]
+    payload: FlashBlock,
+    #[serde(default)]
+    preconfirmations: Vec<Preconfirmation>,
+}
+
+/// Retains the preconfirmations signed by the sequencer for the transactions of recent
+/// flashblocks.
+pub struct Preconfirmations {
+    received: Cache<TxHash, Preconfirmation>,
+    metrics: PreconfMetrics,
+}
+
+impl Default for Preconfirmations {
+    fn default() -> Self {
+        Self { received: Cache::new(MAX_PRECONFIRMATIONS), metrics: PreconfMetrics::default() }
+    }
+}
+
+impl Preconfirmations {
+    /// Returns the preconfirmation of a transaction, if it was included by a recent flashblock.
+    pub fn get(&self, tx_hash: &TxHash) -> Option<Preconfirmation> {
+        self.received.get(tx_hash)
+    }
+
+    /// Returns the preconfirmation of a transaction of the pending block.
+    pub fn get_pending<N: NodePrimitives>(
+        &self,
+        pending_block: &PendingFlashBlock<N>,
+        tx_hash: &TxHash,
+    ) -> Option<Preconfirmation> {
+        let block_number = pending_block.block().header().number();
+        self.get(tx_hash)
+            .filter(|preconf| preconf.commitment.block_number.to::<u64>() == block_number)
+    }
+
+    /// Receives the preconfirmations of the flashblocks published at `url`, reconnecting whenever
+    /// the connection is lost.
+    pub async fn run(self: Arc<Self>, url: Url) {
+        let mut flashblocks = WsFlashBlockStream::new(url).with_decoder(Box::new(self.clone()));
+        while let Some(res) = flashblocks.next().await {
+            if let Err(err) = res {
+                debug!(target: "xlayer::flashblocks", %err, "Failed to receive preconfirmations");
+            }
+        }
+    }
+
+    /// Retains the preconfirmations of a flashblock. A transaction keeps the preconfirmation of
+    /// the first flashblock of a block that included it.
+    fn insert(&self, preconfs: Vec<Preconfirmation>) {
+        for preconf in preconfs {
+            let tx_hash = preconf.commitment.tx_hash;
+            let known = self.received.get(&tx_hash).is_some_and(|known| {
+                known.commitment.block_number == preconf.commitment.block_number
+            });
+            if !known {
+                self.metrics.received.increment(1);
+                self.received.insert(tx_hash, preconf);
+            }
+        }
+    }
+}
+
+impl FlashBlockDecoder for Arc<Preconfirmations> {
+    fn decode(&self, bytes: Bytes) -> eyre::Result<FlashBlock> {
+        let bytes = try_parse_message(bytes)?;
+        let message: FlashblockMessage = serde_json::from_slice(&bytes).map_err(|e| {
+            self.metrics.invalid_messages.increment(1);
+            eyre::eyre!("failed to parse message: {e}")
+        })?;
+        self.insert(message.preconfirmations);
+        Ok(message.payload)
+    }
+}
+
+/// Preconfirmation API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait PreconfirmationApi {
+    /// Returns the signed preconfirmation of a transaction included by a recent flashblock, null
+    /// if the node didn't receive one.
+    #[method(name = "getPreconfirmation")]
+    fn get_preconfirmation(&self, tx_hash: TxHash) -> RpcResult<Option<Preconfirmation>>;
+}
+
+/// Serves the preconfirmations relayed by this node.
+#[derive(Clone)]
+pub struct PreconfirmationRpc {
+    preconfs: Arc<Preconfirmations>,
+}
+
+impl PreconfirmationRpc {
+    pub fn new(preconfs: Arc<Preconfirmations>) -> Self {
+        Self { preconfs }
+    }
+}
+
+impl PreconfirmationApiServer for PreconfirmationRpc {
+    fn get_preconfirmation(&self, tx_hash: TxHash) -> RpcResult<Option<Preconfirmation>> {
+        Ok(self.preconfs.get(&tx_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, B256, U64};
+
+    fn preconf(block_number: u64, flashblock_index: u64) -> Preconfirmation {
+        Preconfirmation {
+            commitment: PreconfCommitment {
+                chain_id: U64::from(196),
+                block_number: U64::from(block_number),
+                flashblock_index: U64::from(flashblock_index),
+                tx_hash: B256::repeat_byte(1),
+                state_hint: B256::repeat_byte(2),
+            },
+            signer: Address::repeat_byte(3),
+            signature: alloy_primitives::Bytes::from(vec![0; 65]),
+        }
+    }
+
+    #[test]
+    fn test_relay_decodes_preconfirmations() {
+        let preconfs = Arc::new(Preconfirmations::default());
+        let payload = serde_json::to_value(FlashBlock::default()).unwrap();
+
+        // Messages without preconfirmations decode as plain flashblocks
+        let plain = Bytes::from(payload.to_string());
+        assert_eq!(preconfs.decode(plain).unwrap(), FlashBlock::default());
+        assert_eq!(preconfs.get(&B256::repeat_byte(1)), None);
+
+        let mut message = payload;
+        message["preconfirmations"] = serde_json::json!([preconf(100, 3)]);
+        let decoded = preconfs.decode(Bytes::from(message.to_string())).unwrap();
+        assert_eq!(decoded, FlashBlock::default());
+        assert_eq!(preconfs.get(&B256::repeat_byte(1)), Some(preconf(100, 3)));
+
+        // Later flashblocks of the same block keep the first preconfirmation
+        preconfs.insert(vec![preconf(100, 4)]);
+        assert_eq!(preconfs.get(&B256::repeat_byte(1)), Some(preconf(100, 3)));
+
+        // Re-inclusion in another block replaces it
+        preconfs.insert(vec![preconf(101, 0)]);
+        assert_eq!(preconfs.get(&B256::repeat_byte(1)), Some(preconf(101, 0)));
+    }
+}
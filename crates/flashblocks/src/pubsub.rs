@@ -1,4 +1,4 @@
-use alloy_primitives::{Address, TxHash};
+use alloy_primitives::{Address, TxHash, B256, U64};
 use alloy_rpc_types_eth::{
     pubsub::{Params as AlloyParams, SubscriptionKind as AlloySubscriptionKind},
     Header,
@@ -8,7 +8,22 @@ use reth_rpc_server_types::result::invalid_params_rpc_err;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
+use crate::preconf::Preconfirmation;
+
 const FLASHBLOCKS: &str = "flashblocks";
+const FLASHBLOCK_FINALITY: &str = "flashblockFinality";
+const L1_STATUS: &str = "l1Status";
+
+/// Schema version of the `flashblocks` events of subscribers that don't request one. Events of
+/// this version have no `schemaVersion` field.
+pub const FLASHBLOCKS_SCHEMA_VERSION_V1: u32 = 1;
+
+/// Schema version tagging every event with its `schemaVersion` and adding the sequencer's
+/// `preconfirmation` to transaction events.
+pub const FLASHBLOCKS_SCHEMA_VERSION_V2: u32 = 2;
+
+/// Latest schema version of the `flashblocks` events.
+pub const LATEST_FLASHBLOCKS_SCHEMA_VERSION: u32 = FLASHBLOCKS_SCHEMA_VERSION_V2;
 
 /// Subscription kind inclusive of flashblocks.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
@@ -20,6 +35,15 @@ pub enum FlashblockSubscriptionKind {
         serialize_with = "serialize_flashblocks"
     )]
     Flashblocks,
+    /// Unsafe, safe and finalized head progression.
+    #[serde(
+        deserialize_with = "deserialize_flashblock_finality",
+        serialize_with = "serialize_flashblock_finality"
+    )]
+    FlashblockFinality,
+    /// L1 data availability status updates.
+    #[serde(deserialize_with = "deserialize_l1_status", serialize_with = "serialize_l1_status")]
+    L1Status,
     /// Standard Ethereum subscription.
     Standard(AlloySubscriptionKind),
 }
@@ -29,12 +53,7 @@ fn deserialize_flashblocks<'de, D>(deserializer: D) -> Result<(), D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let s = <&str>::deserialize(deserializer)?;
-    if s == FLASHBLOCKS {
-        Ok(())
-    } else {
-        Err(serde::de::Error::custom(format!("expected '{FLASHBLOCKS}', got '{s}'")))
-    }
+    deserialize_kind(deserializer, FLASHBLOCKS)
 }
 
 /// Helper to serialize the unit variant as the string "flashblocks".
@@ -45,6 +64,54 @@ where
     serializer.serialize_str(FLASHBLOCKS)
 }
 
+/// Helper to deserialize the unit variant from the string "flashblockFinality".
+fn deserialize_flashblock_finality<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_kind(deserializer, FLASHBLOCK_FINALITY)
+}
+
+/// Helper to serialize the unit variant as the string "flashblockFinality".
+fn serialize_flashblock_finality<S>(serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(FLASHBLOCK_FINALITY)
+}
+
+/// Helper to deserialize the unit variant from the string "l1Status".
+fn deserialize_l1_status<'de, D>(deserializer: D) -> Result<(), D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_kind(deserializer, L1_STATUS)
+}
+
+/// Helper to serialize the unit variant as the string "l1Status".
+fn serialize_l1_status<S>(serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(L1_STATUS)
+}
+
+/// Deserializes a unit subscription kind from its name.
+///
+/// Deserializes into an owned string, since untagged enums buffer the input and may hand over
+/// owned strings which can't be borrowed as `&str`.
+fn deserialize_kind<'de, D>(deserializer: D, kind: &str) -> Result<(), D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    if s == kind {
+        Ok(())
+    } else {
+        Err(serde::de::Error::custom(format!("expected '{kind}', got '{s}'")))
+    }
+}
+
 /// Extended params that wraps Alloy's `Params` and adds flashblocks specific variants.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(untagged)]
@@ -58,12 +125,29 @@ pub enum FlashblockParams {
 impl FlashblockParams {
     /// Validates the flashblock params.
     pub fn validate(&self, max_subscribed_addresses: usize) -> Result<(), ErrorObject<'static>> {
-        if let FlashblockParams::FlashblocksFilter(filter) = self
-            && filter.sub_tx_filter.subscribe_addresses.len() > max_subscribed_addresses
-        {
-            return Err(invalid_params_rpc_err("too many subscribe addresses"));
+        match self {
+            FlashblockParams::FlashblocksFilter(filter) => {
+                filter.validate(max_subscribed_addresses)
+            }
+            FlashblockParams::Standard(_) => Ok(()),
+        }
+    }
+
+    /// Converts the params for a standard subscription kind.
+    ///
+    /// An empty object (e.g. `eth_subscribe("logs", {})`) also matches the default flashblocks
+    /// filter, so it is mapped back to an empty log filter. Returns `None` if the params are
+    /// flashblocks specific.
+    pub fn into_standard(self) -> Option<AlloyParams> {
+        match self {
+            FlashblockParams::Standard(params) => Some(params),
+            FlashblockParams::FlashblocksFilter(filter)
+                if filter == FlashblocksFilter::default() =>
+            {
+                Some(AlloyParams::Logs(Box::default()))
+            }
+            FlashblockParams::FlashblocksFilter(_) => None,
         }
-        Ok(())
     }
 }
 
@@ -76,6 +160,11 @@ pub struct FlashblocksFilter {
 
     /// Tx criterias to subscribe to new transactions in the stream.
     pub sub_tx_filter: SubTxFilter,
+
+    /// Schema version of the streamed events, version 1 if unset. Fields added to the events
+    /// later are only sent to subscribers requesting the version introducing them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
 }
 
 impl FlashblocksFilter {
@@ -83,6 +172,27 @@ impl FlashblocksFilter {
     pub fn requires_address_filtering(&self) -> bool {
         self.sub_tx_filter.has_address_filter()
     }
+
+    /// Returns the negotiated schema version of the streamed events.
+    pub fn schema_version(&self) -> u32 {
+        self.schema_version.unwrap_or(FLASHBLOCKS_SCHEMA_VERSION_V1)
+    }
+
+    /// Validates the filter against the subscribed addresses limit and the supported schema
+    /// versions.
+    pub fn validate(&self, max_subscribed_addresses: usize) -> Result<(), ErrorObject<'static>> {
+        if self.sub_tx_filter.subscribe_addresses.len() > max_subscribed_addresses {
+            return Err(invalid_params_rpc_err("too many subscribe addresses"));
+        }
+        let version = self.schema_version();
+        if !(FLASHBLOCKS_SCHEMA_VERSION_V1..=LATEST_FLASHBLOCKS_SCHEMA_VERSION).contains(&version) {
+            return Err(invalid_params_rpc_err(format!(
+                "unsupported schema version {version}, supported versions are \
+                 {FLASHBLOCKS_SCHEMA_VERSION_V1} to {LATEST_FLASHBLOCKS_SCHEMA_VERSION}"
+            )));
+        }
+        Ok(())
+    }
 }
 
 /// Criteria for filtering and enriching transaction subscription data.
@@ -109,7 +219,7 @@ impl SubTxFilter {
     }
 }
 
-/// Streaming flashblock event which is either a header or transaction message
+/// Streaming flashblock event which is either a header, transaction or reorg message
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "camelCase")]
 pub enum FlashblockStreamEvent<H, Tx, R> {
@@ -125,6 +235,8 @@ pub enum FlashblockStreamEvent<H, Tx, R> {
         block_number: u64,
         transaction: EnrichedTransaction<Tx, R>,
     },
+    /// Canonical reorg replacing blocks already streamed, sent before the replacement blocks
+    Reorg { reorg: FlashblocksReorg },
 }
 
 impl<H, Tx, R> FlashblockStreamEvent<H, Tx, R> {
@@ -133,10 +245,66 @@ impl<H, Tx, R> FlashblockStreamEvent<H, Tx, R> {
         match self {
             FlashblockStreamEvent::Header { block_number, .. } => *block_number,
             FlashblockStreamEvent::Transaction { block_number, .. } => *block_number,
+            FlashblockStreamEvent::Reorg { reorg } => reorg.new_tip.number.to(),
         }
     }
 }
 
+/// Flashblock event as sent to a subscriber, tagged with the negotiated schema version.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VersionedFlashblockEvent<H, Tx, R> {
+    /// Schema version of the event, unset for version 1 events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    #[serde(flatten)]
+    pub event: FlashblockStreamEvent<H, Tx, R>,
+}
+
+impl<H, Tx, R> VersionedFlashblockEvent<H, Tx, R> {
+    /// Tags the event with the schema version and strips the fields introduced by later versions,
+    /// version 1 events are sent as before versioning.
+    pub fn new(schema_version: u32, mut event: FlashblockStreamEvent<H, Tx, R>) -> Self {
+        if schema_version < FLASHBLOCKS_SCHEMA_VERSION_V2
+            && let FlashblockStreamEvent::Transaction { transaction, .. } = &mut event
+        {
+            transaction.preconfirmation = None;
+        }
+        let schema_version =
+            (schema_version > FLASHBLOCKS_SCHEMA_VERSION_V1).then_some(schema_version);
+        Self { schema_version, event }
+    }
+}
+
+/// Block referenced by a reorg event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReorgBlock {
+    pub number: U64,
+    pub hash: B256,
+}
+
+impl ReorgBlock {
+    pub fn new(number: u64, hash: B256) -> Self {
+        Self { number: U64::from(number), hash }
+    }
+}
+
+/// Canonical reorg, the blocks after the common ancestor up to the old tip were replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashblocksReorg {
+    pub old_tip: ReorgBlock,
+    pub new_tip: ReorgBlock,
+    pub common_ancestor: ReorgBlock,
+}
+
+impl FlashblocksReorg {
+    /// Returns the number of the first replaced block.
+    pub fn first_replaced(&self) -> u64 {
+        self.common_ancestor.number.to::<u64>() + 1
+    }
+}
+
 /// Transaction data with optional enrichment based on `FlashblocksFilter`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -151,4 +319,223 @@ pub struct EnrichedTransaction<Tx, R> {
     /// Transaction receipt (if `tx_receipt` is true in filter criteria).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub receipt: Option<R>,
+
+    /// Preconfirmation of the inclusion signed by the sequencer (if the node relays them, from
+    /// schema version 2).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preconfirmation: Option<Preconfirmation>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preconf::PreconfCommitment;
+    use alloy_primitives::Bytes;
+    use alloy_rpc_types_eth::Filter;
+
+    type SubscribeParams = (FlashblockSubscriptionKind, Option<FlashblockParams>);
+
+    /// Parses the raw `eth_subscribe` params, both from a borrowed string and an owned value.
+    fn parse(raw: &str) -> SubscribeParams {
+        let borrowed: SubscribeParams = serde_json::from_str(raw).unwrap();
+        let owned: SubscribeParams =
+            serde_json::from_value(serde_json::from_str(raw).unwrap()).unwrap();
+        assert_eq!(borrowed, owned);
+        borrowed
+    }
+
+    fn standard(raw: &str) -> (AlloySubscriptionKind, Option<AlloyParams>) {
+        let (kind, params) = parse(raw);
+        let FlashblockSubscriptionKind::Standard(kind) = kind else {
+            panic!("expected standard subscription kind for {raw}");
+        };
+        (kind, params.map(|p| p.into_standard().expect("standard params")))
+    }
+
+    #[test]
+    fn test_standard_kinds_without_params() {
+        // web3.js, ethers and viem all send the bare kind for these subscriptions
+        assert_eq!(standard(r#"["newHeads"]"#), (AlloySubscriptionKind::NewHeads, None));
+        assert_eq!(
+            standard(r#"["newPendingTransactions"]"#),
+            (AlloySubscriptionKind::NewPendingTransactions, None)
+        );
+        assert_eq!(standard(r#"["syncing"]"#), (AlloySubscriptionKind::Syncing, None));
+        assert_eq!(standard(r#"["logs"]"#), (AlloySubscriptionKind::Logs, None));
+    }
+
+    #[test]
+    fn test_standard_kinds_with_params() {
+        // viem `watchPendingTransactions` with full transactions
+        assert_eq!(
+            standard(r#"["newPendingTransactions", true]"#),
+            (AlloySubscriptionKind::NewPendingTransactions, Some(AlloyParams::Bool(true)))
+        );
+
+        // ethers v6 / viem `watchEvent`
+        let (kind, params) = standard(
+            r#"["logs", {"address": "0x5fbdb2315678afecb367f032d93f642f64180aa3", "topics": ["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef", null]}]"#,
+        );
+        assert_eq!(kind, AlloySubscriptionKind::Logs);
+        let Some(AlloyParams::Logs(filter)) = params else { panic!("expected log filter") };
+        assert!(filter
+            .address
+            .matches(&"0x5fbdb2315678afecb367f032d93f642f64180aa3".parse::<Address>().unwrap()));
+
+        // web3.js `subscribe("logs", {})` and ethers v5 with an empty topic list
+        assert_eq!(
+            standard(r#"["logs", {}]"#),
+            (AlloySubscriptionKind::Logs, Some(AlloyParams::Logs(Box::new(Filter::default()))))
+        );
+        let (_, params) = standard(r#"["logs", {"topics": []}]"#);
+        assert!(matches!(params, Some(AlloyParams::Logs(_))));
+
+        // Explicit null params
+        assert_eq!(standard(r#"["newHeads", null]"#), (AlloySubscriptionKind::NewHeads, None));
+    }
+
+    #[test]
+    fn test_flashblocks_kind() {
+        let (kind, params) = parse(
+            r#"["flashblocks", {"headerInfo": true, "subTxFilter": {"txInfo": true, "subscribeAddresses": ["0x5fbdb2315678afecb367f032d93f642f64180aa3"]}}]"#,
+        );
+        assert_eq!(kind, FlashblockSubscriptionKind::Flashblocks);
+        let Some(FlashblockParams::FlashblocksFilter(filter)) = params.clone() else {
+            panic!("expected flashblocks filter");
+        };
+        assert!(filter.header_info);
+        assert!(filter.sub_tx_filter.tx_info);
+        assert!(filter.requires_address_filtering());
+        assert!(params.unwrap().into_standard().is_none());
+    }
+
+    #[test]
+    fn test_filter_address_limit() {
+        let params: FlashblockParams = serde_json::from_str(
+            r#"{"subTxFilter": {"subscribeAddresses": ["0x5fbdb2315678afecb367f032d93f642f64180aa3", "0xe7f1725e7734ce288f8367e1bb143e90bb3f0512"]}}"#,
+        )
+        .unwrap();
+        assert!(params.validate(2).is_ok());
+        assert!(params.validate(1).is_err());
+        let FlashblockParams::FlashblocksFilter(filter) = params else {
+            panic!("expected flashblocks filter");
+        };
+        assert!(filter.validate(1).is_err());
+    }
+
+    #[test]
+    fn test_schema_version_negotiation() {
+        let (_, params) = parse(r#"["flashblocks", {"headerInfo": true, "schemaVersion": 2}]"#);
+        let Some(FlashblockParams::FlashblocksFilter(filter)) = params else {
+            panic!("expected flashblocks filter");
+        };
+        assert_eq!(filter.schema_version(), 2);
+        assert!(filter.validate(10).is_ok());
+
+        // Filters without version stream version 1 and serialize as before
+        assert_eq!(FlashblocksFilter::default().schema_version(), FLASHBLOCKS_SCHEMA_VERSION_V1);
+        assert_eq!(
+            serde_json::to_value(FlashblocksFilter::default()).unwrap()["schemaVersion"],
+            serde_json::Value::Null
+        );
+
+        for version in [0, LATEST_FLASHBLOCKS_SCHEMA_VERSION + 1] {
+            let filter = FlashblocksFilter { schema_version: Some(version), ..Default::default() };
+            assert!(filter.validate(10).is_err());
+        }
+    }
+
+    #[test]
+    fn test_versioned_event_serde() {
+        let event: FlashblockStreamEvent<(), (), ()> = FlashblockStreamEvent::Transaction {
+            block_number: 16,
+            transaction: EnrichedTransaction {
+                tx_hash: TxHash::with_last_byte(1),
+                tx_data: None,
+                receipt: None,
+                preconfirmation: None,
+            },
+        };
+        let v1_payload = serde_json::json!({
+            "type": "transaction",
+            "transaction": { "txHash": TxHash::with_last_byte(1) }
+        });
+
+        // Version 1 payloads are unchanged by versioning
+        assert_eq!(serde_json::to_value(&event).unwrap(), v1_payload);
+        let v1 = VersionedFlashblockEvent::new(FLASHBLOCKS_SCHEMA_VERSION_V1, event.clone());
+        assert_eq!(serde_json::to_value(&v1).unwrap(), v1_payload);
+
+        // Later versions only add the version tag to the version 1 fields
+        let v2 = VersionedFlashblockEvent::new(2, event);
+        let mut v2_payload = v1_payload.clone();
+        v2_payload["schemaVersion"] = 2.into();
+        assert_eq!(serde_json::to_value(&v2).unwrap(), v2_payload);
+
+        // The preconfirmation is only sent from version 2
+        let preconfirmation = Preconfirmation {
+            commitment: PreconfCommitment {
+                chain_id: U64::from(196),
+                block_number: U64::from(16),
+                flashblock_index: U64::from(1),
+                tx_hash: TxHash::with_last_byte(1),
+                state_hint: B256::with_last_byte(2),
+            },
+            signer: Address::with_last_byte(3),
+            signature: Bytes::from(vec![0; 65]),
+        };
+        let event: FlashblockStreamEvent<(), (), ()> = FlashblockStreamEvent::Transaction {
+            block_number: 16,
+            transaction: EnrichedTransaction {
+                tx_hash: TxHash::with_last_byte(1),
+                tx_data: None,
+                receipt: None,
+                preconfirmation: Some(preconfirmation),
+            },
+        };
+        let v1 = VersionedFlashblockEvent::new(FLASHBLOCKS_SCHEMA_VERSION_V1, event.clone());
+        assert_eq!(serde_json::to_value(&v1).unwrap(), v1_payload);
+        let v2 = serde_json::to_value(VersionedFlashblockEvent::new(2, event)).unwrap();
+        assert_eq!(v2["schemaVersion"], 2);
+        assert_eq!(v2["transaction"]["preconfirmation"]["flashblockIndex"], "0x1");
+        assert_eq!(
+            v2["transaction"]["preconfirmation"]["signer"],
+            Address::with_last_byte(3).to_string()
+        );
+
+        let reorg = FlashblocksReorg {
+            old_tip: ReorgBlock::new(17, B256::with_last_byte(1)),
+            new_tip: ReorgBlock::new(17, B256::with_last_byte(2)),
+            common_ancestor: ReorgBlock::new(15, B256::with_last_byte(3)),
+        };
+        let value = serde_json::to_value(VersionedFlashblockEvent::<(), (), ()>::new(
+            2,
+            FlashblockStreamEvent::Reorg { reorg },
+        ))
+        .unwrap();
+        assert_eq!(value["type"], "reorg");
+        assert_eq!(value["schemaVersion"], 2);
+        assert_eq!(value["reorg"]["commonAncestor"]["number"], "0xf");
+    }
+
+    #[test]
+    fn test_flashblock_finality_kind() {
+        assert_eq!(
+            parse(r#"["flashblockFinality"]"#),
+            (FlashblockSubscriptionKind::FlashblockFinality, None)
+        );
+        assert_eq!(
+            serde_json::to_string(&FlashblockSubscriptionKind::FlashblockFinality).unwrap(),
+            r#""flashblockFinality""#
+        );
+    }
+
+    #[test]
+    fn test_l1_status_kind() {
+        assert_eq!(parse(r#"["l1Status"]"#), (FlashblockSubscriptionKind::L1Status, None));
+        assert_eq!(
+            serde_json::to_string(&FlashblockSubscriptionKind::L1Status).unwrap(),
+            r#""l1Status""#
+        );
+    }
 }
@@ -0,0 +1,142 @@
+//! RPC receipts of the pending flashblock, shared by the flashblocks subscriptions.
+//!
+//! Subscribers requesting receipts need the RPC form of the receipts of the transactions they
+//! are sent. The receipts of a flashblock are converted in a single call the first time a
+//! subscriber needs them, and every subscriber indexes into the converted receipts by
+//! transaction index.
+
+use alloy_consensus::{transaction::TxHashRef as _, BlockHeader as _, TxReceipt as _};
+use alloy_primitives::B256;
+use reth_primitives_traits::{NodePrimitives, Recovered, RecoveredBlock, TransactionMeta};
+use reth_rpc_convert::transaction::ConvertReceiptInput;
+use reth_tracing::tracing::warn;
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+/// Returns the receipt conversion inputs of the transactions of the block, in block order.
+///
+/// Transactions without a receipt are skipped, the receipts of a flashblock may trail its
+/// transactions.
+pub fn receipt_inputs<'a, N: NodePrimitives>(
+    block: &'a RecoveredBlock<N::Block>,
+    receipts: &'a [N::Receipt],
+) -> Vec<ConvertReceiptInput<'a, N>> {
+    let header = block.header();
+    let block_hash = block.hash();
+    let mut cumulative_gas_used = 0;
+    let mut next_log_index = 0;
+
+    block
+        .transactions_with_sender()
+        .zip(receipts)
+        .enumerate()
+        .map(|(idx, ((sender, tx), receipt))| {
+            let input = ConvertReceiptInput {
+                receipt: receipt.clone(),
+                tx: Recovered::new_unchecked(tx, *sender),
+                gas_used: receipt.cumulative_gas_used() - cumulative_gas_used,
+                next_log_index,
+                meta: TransactionMeta {
+                    tx_hash: *tx.tx_hash(),
+                    index: idx as u64,
+                    block_hash,
+                    block_number: header.number(),
+                    base_fee: header.base_fee_per_gas(),
+                    excess_blob_gas: header.excess_blob_gas(),
+                    timestamp: header.timestamp(),
+                },
+            };
+            cumulative_gas_used = receipt.cumulative_gas_used();
+            next_log_index += receipt.logs().len();
+            input
+        })
+        .collect()
+}
+
+/// Converted receipts of a flashblock.
+#[derive(Debug)]
+struct Converted<R> {
+    block_hash: B256,
+    /// Number of receipts of the flashblock, later flashblocks of a block extend them
+    len: usize,
+    /// `None` if the conversion failed
+    receipts: Option<Arc<[R]>>,
+}
+
+/// RPC receipts of the latest flashblock, converted once for all subscribers.
+#[derive(Debug)]
+pub struct FlashblockReceipts<R> {
+    latest: Mutex<Option<Converted<R>>>,
+}
+
+impl<R> Default for FlashblockReceipts<R> {
+    fn default() -> Self {
+        Self { latest: Mutex::new(None) }
+    }
+}
+
+impl<R> FlashblockReceipts<R> {
+    /// Returns the RPC receipts of the flashblock, converting them with `convert` unless they
+    /// were converted for an earlier subscriber. Returns `None` if the conversion failed.
+    ///
+    /// Subscribers are sent the same flashblock concurrently, the lock is held while converting
+    /// so the receipts are converted once.
+    pub fn get_or_convert<E: Debug>(
+        &self,
+        block_hash: B256,
+        len: usize,
+        convert: impl FnOnce() -> Result<Vec<R>, E>,
+    ) -> Option<Arc<[R]>> {
+        let mut latest = self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(converted) = latest.as_ref()
+            && converted.block_hash == block_hash
+            && converted.len == len
+        {
+            return converted.receipts.clone();
+        }
+
+        let receipts = match convert() {
+            Ok(receipts) => Some(Arc::from(receipts)),
+            Err(err) => {
+                warn!(target: "xlayer::flashblocks", ?err, %block_hash, "Failed to convert flashblock receipts");
+                None
+            }
+        };
+        *latest = Some(Converted { block_hash, len, receipts: receipts.clone() });
+        receipts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_receipts_converted_once_per_flashblock() {
+        let receipts = FlashblockReceipts::<u64>::default();
+        let conversions = Cell::new(0);
+        let convert = |len: u64| {
+            conversions.set(conversions.get() + 1);
+            Ok::<_, ()>((0..len).collect())
+        };
+
+        let block_hash = B256::with_last_byte(1);
+        for _ in 0..3 {
+            let converted = receipts.get_or_convert(block_hash, 2, || convert(2)).unwrap();
+            assert_eq!(&*converted, &[0, 1]);
+        }
+        // The next flashblock of the block carries more receipts
+        let converted = receipts.get_or_convert(block_hash, 3, || convert(3)).unwrap();
+        assert_eq!(&*converted, &[0, 1, 2]);
+        assert_eq!(conversions.get(), 2);
+
+        // Failed conversions are not retried for the same flashblock
+        let block_hash = B256::with_last_byte(2);
+        assert!(receipts.get_or_convert(block_hash, 1, || Err("no l1 info")).is_none());
+        assert!(receipts.get_or_convert(block_hash, 1, || convert(1)).is_none());
+        assert_eq!(conversions.get(), 2);
+    }
+}
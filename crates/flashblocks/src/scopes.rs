@@ -0,0 +1,252 @@
+//! Per-key scoping of the flashblocks subscriptions.
+//!
+//! Partners get enriched transactions and receipts, while public clients may be limited to the
+//! headers of the pending block. Keys and their scopes are read from a JSON file:
+//!
+//! ```json
+//! {
+//!   "default": { "flashblocks": "headers" },
+//!   "keys": {
+//!     "<api key>": { "flashblocks": "full", "finality": true, "maxAddresses": 500 }
+//!   }
+//! }
+//! ```
+//!
+//! Clients pass their key as the third `eth_subscribe` parameter, `{"apiKey": "<api key>"}`. The
+//! slow query log only keeps the subscription kind of `eth_subscribe` calls, so keys aren't recorded.
+//! Subscriptions without a key get the `default` scope and are rejected if there is none. Standard
+//! `eth` subscriptions are not scoped.
+
+use std::{collections::HashMap, path::Path};
+
+use jsonrpsee::types::ErrorObject;
+use reth_rpc_server_types::result::invalid_params_rpc_err;
+use serde::{Deserialize, Serialize};
+
+use crate::pubsub::{FlashblockParams, FlashblockSubscriptionKind, FlashblocksFilter};
+
+/// Error code of subscriptions outside the scope of the client's key.
+pub const SUBSCRIPTION_UNAUTHORIZED_CODE: i32 = -32001;
+
+/// Credentials passed along a subscription request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SubscriptionAuth {
+    pub api_key: String,
+}
+
+/// Flashblocks data a key may subscribe to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FlashblocksAccess {
+    /// Pending block headers only
+    Headers,
+    /// Headers and transaction hashes, filtered by address
+    Transactions,
+    /// Transactions enriched with their data and receipts
+    Full,
+}
+
+/// Subscriptions a key may use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct SubscriptionScope {
+    /// Access to the `flashblocks` subscription, rejected if not set
+    #[serde(default)]
+    pub flashblocks: Option<FlashblocksAccess>,
+    /// Access to the `flashblockFinality` and `l1Status` subscriptions
+    #[serde(default)]
+    pub finality: bool,
+    /// Maximum number of subscribed addresses, capped by the node wide limit
+    #[serde(default)]
+    pub max_addresses: Option<usize>,
+}
+
+impl SubscriptionScope {
+    /// Validates a subscription request against the scope.
+    pub fn validate(
+        &self,
+        kind: FlashblockSubscriptionKind,
+        params: Option<&FlashblockParams>,
+        max_subscribed_addresses: usize,
+    ) -> Result<(), ErrorObject<'static>> {
+        match kind {
+            FlashblockSubscriptionKind::Flashblocks => match params {
+                Some(FlashblockParams::FlashblocksFilter(filter)) => {
+                    self.validate_filter(filter, max_subscribed_addresses)
+                }
+                _ if self.flashblocks.is_none() => {
+                    Err(unauthorized("api key does not include flashblocks subscriptions"))
+                }
+                _ => Err(invalid_params_rpc_err("invalid params for flashblocks")),
+            },
+            FlashblockSubscriptionKind::FlashblockFinality if !self.finality => {
+                Err(unauthorized("api key does not include flashblockFinality subscriptions"))
+            }
+            FlashblockSubscriptionKind::L1Status if !self.finality => {
+                Err(unauthorized("api key does not include l1Status subscriptions"))
+            }
+            FlashblockSubscriptionKind::FlashblockFinality
+            | FlashblockSubscriptionKind::L1Status
+            | FlashblockSubscriptionKind::Standard(_) => Ok(()),
+        }
+    }
+
+    /// Validates a `flashblocks` subscription filter against the scope.
+    pub fn validate_filter(
+        &self,
+        filter: &FlashblocksFilter,
+        max_subscribed_addresses: usize,
+    ) -> Result<(), ErrorObject<'static>> {
+        let Some(access) = self.flashblocks else {
+            return Err(unauthorized("api key does not include flashblocks subscriptions"));
+        };
+        let tx_filter = &filter.sub_tx_filter;
+        if access == FlashblocksAccess::Headers && tx_filter.has_address_filter() {
+            return Err(unauthorized("api key is limited to flashblock headers"));
+        }
+        if access != FlashblocksAccess::Full && (tx_filter.tx_info || tx_filter.tx_receipt) {
+            return Err(unauthorized("api key does not include transaction data and receipts"));
+        }
+        let max_addresses = self
+            .max_addresses
+            .map_or(max_subscribed_addresses, |max| max.min(max_subscribed_addresses));
+        if tx_filter.subscribe_addresses.len() > max_addresses {
+            return Err(unauthorized("too many subscribe addresses for api key"));
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if transactions are streamed, `false` for header only subscriptions.
+    pub fn includes_transactions(&self) -> bool {
+        self.flashblocks != Some(FlashblocksAccess::Headers)
+    }
+}
+
+/// Scopes of the API keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SubscriptionScopes {
+    /// Scope of subscriptions without a key, rejected if not set
+    #[serde(default)]
+    default: Option<SubscriptionScope>,
+    /// Scopes by API key
+    #[serde(default)]
+    keys: HashMap<String, SubscriptionScope>,
+}
+
+impl SubscriptionScopes {
+    /// Reads the scopes from a JSON file.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|err| eyre::eyre!("failed to read {}: {err}", path.display()))?;
+        serde_json::from_str(&contents)
+            .map_err(|err| eyre::eyre!("invalid subscription keys in {}: {err}", path.display()))
+    }
+
+    /// Returns the scope of the given credentials.
+    pub fn scope(
+        &self,
+        auth: Option<&SubscriptionAuth>,
+    ) -> Result<SubscriptionScope, ErrorObject<'static>> {
+        match auth {
+            Some(auth) => {
+                self.keys.get(&auth.api_key).copied().ok_or_else(|| unauthorized("unknown api key"))
+            }
+            None => self.default.ok_or_else(|| unauthorized("subscription requires an api key")),
+        }
+    }
+}
+
+fn unauthorized(message: &'static str) -> ErrorObject<'static> {
+    ErrorObject::owned(SUBSCRIPTION_UNAUTHORIZED_CODE, message, None::<()>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Address;
+
+    fn scopes() -> SubscriptionScopes {
+        serde_json::from_str(
+            r#"{
+                "default": {"flashblocks": "headers"},
+                "keys": {
+                    "partner": {"flashblocks": "full", "finality": true, "maxAddresses": 2},
+                    "indexer": {"flashblocks": "transactions"}
+                }
+            }"#,
+        )
+        .unwrap()
+    }
+
+    fn auth(api_key: &str) -> SubscriptionAuth {
+        SubscriptionAuth { api_key: api_key.to_string() }
+    }
+
+    fn filter(addresses: u8, tx_info: bool) -> FlashblocksFilter {
+        let mut filter = FlashblocksFilter { header_info: true, ..Default::default() };
+        filter.sub_tx_filter.tx_info = tx_info;
+        filter.sub_tx_filter.subscribe_addresses =
+            (0..addresses).map(Address::with_last_byte).collect();
+        filter
+    }
+
+    #[test]
+    fn test_scope_lookup() {
+        let scopes = scopes();
+        assert_eq!(scopes.scope(None).unwrap().flashblocks, Some(FlashblocksAccess::Headers));
+        assert!(scopes.scope(Some(&auth("partner"))).unwrap().finality);
+        let err = scopes.scope(Some(&auth("unknown"))).unwrap_err();
+        assert_eq!(err.code(), SUBSCRIPTION_UNAUTHORIZED_CODE);
+
+        let err = SubscriptionScopes::default().scope(None).unwrap_err();
+        assert_eq!(err.message(), "subscription requires an api key");
+    }
+
+    #[test]
+    fn test_flashblocks_access() {
+        let scopes = scopes();
+        let public = scopes.scope(None).unwrap();
+        assert!(public.validate_filter(&filter(0, false), 1000).is_ok());
+        assert!(public.validate_filter(&filter(1, false), 1000).is_err());
+        assert!(!public.includes_transactions());
+
+        let indexer = scopes.scope(Some(&auth("indexer"))).unwrap();
+        assert!(indexer.validate_filter(&filter(5, false), 1000).is_ok());
+        assert!(indexer.validate_filter(&filter(0, true), 1000).is_err());
+        // Capped by the node wide limit
+        assert!(indexer.validate_filter(&filter(5, false), 4).is_err());
+
+        let partner = scopes.scope(Some(&auth("partner"))).unwrap();
+        assert!(partner.validate_filter(&filter(2, true), 1000).is_ok());
+        assert!(partner.validate_filter(&filter(3, true), 1000).is_err());
+    }
+
+    #[test]
+    fn test_flashblocks_without_filter() {
+        let scope = SubscriptionScope { finality: true, ..Default::default() };
+        let kind = FlashblockSubscriptionKind::Flashblocks;
+        let err = scope.validate(kind, None, 1000).unwrap_err();
+        assert_eq!(err.code(), SUBSCRIPTION_UNAUTHORIZED_CODE);
+
+        let partner = scopes().scope(Some(&auth("partner"))).unwrap();
+        let err = partner.validate(kind, None, 1000).unwrap_err();
+        assert_ne!(err.code(), SUBSCRIPTION_UNAUTHORIZED_CODE);
+    }
+
+    #[test]
+    fn test_finality_access() {
+        let scopes = scopes();
+        for kind in
+            [FlashblockSubscriptionKind::FlashblockFinality, FlashblockSubscriptionKind::L1Status]
+        {
+            assert!(scopes.scope(None).unwrap().validate(kind, None, 1000).is_err());
+            assert!(scopes
+                .scope(Some(&auth("partner")))
+                .unwrap()
+                .validate(kind, None, 1000)
+                .is_ok());
+        }
+    }
+}
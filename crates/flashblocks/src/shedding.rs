@@ -0,0 +1,112 @@
+//! Load shedding of the flashblocks subscription enrichment.
+//!
+//! While the node syncs or catches up, pending blocks are built for heights the network moved past
+//! long ago, and converting their transactions and receipts for the subscribers is wasted work
+//! nobody reads as "pending". [`EnrichmentShedding`] skips the enrichment of pending blocks whose
+//! timestamp lags the wall clock by more than the configured threshold, and resumes once a pending
+//! block is near the tip again. The decision is shared by all subscriptions, so a burst is shed
+//! once and not per subscriber.
+
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use reth_tracing::tracing::info;
+
+/// Lag below which a pending block is near the tip and enrichment resumes.
+const NEAR_TIP_LAG: Duration = Duration::from_secs(2);
+
+/// Metrics of the enrichment load shedding.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_flashblocks_enrichment")]
+struct EnrichmentSheddingMetrics {
+    /// Number of pending blocks whose enrichment was skipped
+    skipped_blocks: Counter,
+    /// Whether enrichment is currently skipped, 1 while shedding
+    shedding: Gauge,
+}
+
+#[derive(Debug, Default)]
+struct SheddingState {
+    /// Whether the enrichment of pending blocks is skipped
+    shedding: bool,
+    /// Number of the last skipped block, each block is counted once for all flashblocks
+    last_skipped: Option<u64>,
+}
+
+/// Skips the enrichment of pending blocks far behind the tip.
+#[derive(Debug)]
+pub struct EnrichmentShedding {
+    max_lag: Duration,
+    state: Mutex<SheddingState>,
+    metrics: EnrichmentSheddingMetrics,
+}
+
+impl EnrichmentShedding {
+    /// Creates the load shedding for pending blocks lagging the wall clock by up to `max_lag`.
+    pub fn new(max_lag: Duration) -> Self {
+        Self { max_lag, state: Mutex::default(), metrics: EnrichmentSheddingMetrics::default() }
+    }
+
+    /// Returns whether the pending block with the given number and timestamp in seconds is
+    /// enriched.
+    pub fn should_enrich(&self, block_number: u64, timestamp: u64) -> bool {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.should_enrich_at(block_number, timestamp, now)
+    }
+
+    fn should_enrich_at(&self, block_number: u64, timestamp: u64, now: u64) -> bool {
+        let lag = Duration::from_secs(now.saturating_sub(timestamp));
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if state.shedding {
+            if lag <= NEAR_TIP_LAG.min(self.max_lag) {
+                state.shedding = false;
+                self.metrics.shedding.set(0.0);
+                info!(target: "xlayer::flashblocks", block_number, "Pending blocks near the tip, resuming enrichment");
+            }
+        } else if lag > self.max_lag {
+            state.shedding = true;
+            self.metrics.shedding.set(1.0);
+            info!(target: "xlayer::flashblocks", block_number, lag = ?lag, "Pending blocks behind the tip, skipping enrichment");
+        }
+
+        if state.shedding && state.last_skipped != Some(block_number) {
+            state.last_skipped = Some(block_number);
+            self.metrics.skipped_blocks.increment(1);
+        }
+        !state.shedding
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enrichment_shedding() {
+        let shedding = EnrichmentShedding::new(Duration::from_secs(10));
+        let now = 1_700_000_000;
+
+        assert!(shedding.should_enrich_at(100, now - 10, now));
+
+        // Lagging blocks are skipped until a block is near the tip, not just within the threshold
+        assert!(!shedding.should_enrich_at(101, now - 11, now));
+        assert!(!shedding.should_enrich_at(101, now - 11, now));
+        assert!(!shedding.should_enrich_at(102, now - 5, now));
+        assert_eq!(shedding.state.lock().unwrap().last_skipped, Some(102));
+
+        assert!(shedding.should_enrich_at(103, now - 2, now));
+        assert!(shedding.should_enrich_at(104, now - 9, now));
+
+        // Timestamps ahead of the local clock count as near the tip
+        let shedding = EnrichmentShedding::new(Duration::from_secs(1));
+        assert!(!shedding.should_enrich_at(100, now - 60, now));
+        assert!(shedding.should_enrich_at(101, now + 1, now));
+    }
+}
@@ -1,31 +1,57 @@
-use crate::pubsub::{
-    EnrichedTransaction, FlashblockParams, FlashblockStreamEvent, FlashblockSubscriptionKind,
-    FlashblocksFilter,
+use crate::{
+    canon_mux::{CanonStateConsumer, CanonStateMux},
+    finality::finality_stream,
+    l1_status::l1_status_stream,
+    pending_txs::{merge_pending_transactions, PendingTransaction, UpstreamPendingTxFeed},
+    preconf::Preconfirmations,
+    pubsub::{
+        EnrichedTransaction, FlashblockParams, FlashblockStreamEvent, FlashblockSubscriptionKind,
+        FlashblocksFilter, FlashblocksReorg, ReorgBlock, VersionedFlashblockEvent,
+    },
+    receipts::{receipt_inputs, FlashblockReceipts},
+    scopes::{SubscriptionAuth, SubscriptionScope, SubscriptionScopes},
+    shedding::EnrichmentShedding,
 };
 use alloy_consensus::{transaction::TxHashRef, BlockHeader as _, Transaction as _, TxReceipt as _};
 use alloy_json_rpc::RpcObject;
-use alloy_primitives::{Address, TxHash, U256};
-use alloy_rpc_types_eth::{Header, TransactionInfo};
-use futures::StreamExt;
+use alloy_primitives::{Address, TxHash, B256, U256};
+use alloy_rpc_types_eth::{
+    pubsub::{Params as AlloyParams, SubscriptionKind as AlloySubscriptionKind},
+    Header, TransactionInfo,
+};
+use futures::{
+    stream::{self, PollNext},
+    StreamExt,
+};
 use jsonrpsee::{
-    proc_macros::rpc, server::SubscriptionMessage, types::ErrorObject, PendingSubscriptionSink,
-    SubscriptionSink,
+    core::{server::ConnectionId, RpcResult},
+    proc_macros::rpc,
+    server::SubscriptionMessage,
+    types::{ErrorObject, SubscriptionId},
+    Extensions, PendingSubscriptionSink, SubscriptionSink,
 };
 use moka::policy::EvictionPolicy;
 use moka::sync::Cache;
+use reth_chain_state::{CanonStateNotification, ForkChoiceSubscriptions};
+use reth_execution_types::Chain;
 use reth_optimism_flashblocks::{PendingBlockRx, PendingFlashBlock};
-use reth_primitives_traits::{
-    NodePrimitives, Recovered, RecoveredBlock, SealedBlock, TransactionMeta,
-};
+use reth_primitives_traits::{NodePrimitives, Recovered, RecoveredBlock, SealedBlock};
 use reth_rpc::eth::pubsub::EthPubSub;
-use reth_rpc_convert::{transaction::ConvertReceiptInput, RpcConvert};
+use reth_rpc_convert::RpcConvert;
 use reth_rpc_eth_api::{EthApiTypes, RpcNodeCore, RpcReceipt, RpcTransaction};
-use reth_rpc_eth_types::utils::calculate_gas_used_and_next_log_index;
 use reth_rpc_server_types::result::{internal_rpc_err, invalid_params_rpc_err};
-use reth_storage_api::BlockNumReader;
+use reth_storage_api::{BlockIdReader, BlockNumReader, HeaderProvider, TransactionsProvider};
 use reth_tasks::TaskSpawner;
-use reth_tracing::tracing::{trace, warn};
-use std::{collections::HashSet, future::ready, sync::Arc};
+use reth_tracing::tracing::{debug, trace, warn};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    future::ready,
+    pin::pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::watch;
 use tokio_stream::{wrappers::WatchStream, Stream};
 
 const MAX_TXHASH_CACHE_SIZE: u64 = 10_000;
@@ -54,7 +80,8 @@ struct EnrichmentContext<'a, N: NodePrimitives, C> {
 /// Flashblocks pubsub RPC interface.
 #[rpc(server, namespace = "eth")]
 pub trait FlashblocksPubSubApi<T: RpcObject> {
-    /// Create an ethereum subscription for the given params
+    /// Create an ethereum subscription for the given params, flashblocks subscriptions are
+    /// scoped by the API key in `auth` if subscription keys are configured
     #[subscription(
         name = "subscribe" => "subscription",
         unsubscribe = "unsubscribe",
@@ -64,22 +91,124 @@ pub trait FlashblocksPubSubApi<T: RpcObject> {
         &self,
         kind: FlashblockSubscriptionKind,
         params: Option<FlashblockParams>,
+        auth: Option<SubscriptionAuth>,
     ) -> jsonrpsee::core::SubscriptionResult;
 }
 
+/// X Layer flashblocks subscription management interface.
+#[rpc(server, client, namespace = "xlayer")]
+pub trait FlashblocksSubscriptionApi {
+    /// Replaces the filter of an active `flashblocks` subscription opened on the same
+    /// connection.
+    ///
+    /// Returns `false` if no such subscription is active.
+    #[method(name = "updateSubscription", with_extensions)]
+    fn update_subscription(&self, id: String, filter: FlashblocksFilter) -> RpcResult<bool>;
+}
+
+/// Filters of the active `flashblocks` subscriptions by connection and subscription id.
+///
+/// Subscriptions can only be updated from the connection that opened them, subscription ids
+/// alone are guessable. Streams read their filter once per flashblock, so an update applies from
+/// the next flashblock on and never to a part of one.
+#[derive(Debug, Default)]
+pub struct FlashblocksFilters {
+    filters: Mutex<HashMap<(ConnectionId, SubscriptionId<'static>), ActiveFilter>>,
+}
+
+/// Filter of an active subscription and the scope it is limited to.
+#[derive(Debug)]
+struct ActiveFilter {
+    tx: watch::Sender<FlashblocksFilter>,
+    scope: Option<SubscriptionScope>,
+}
+
+impl FlashblocksFilters {
+    /// Registers the filter of a subscription, returns the receiver the stream reads it from.
+    fn register(
+        &self,
+        connection: ConnectionId,
+        id: SubscriptionId<'static>,
+        filter: FlashblocksFilter,
+        scope: Option<SubscriptionScope>,
+    ) -> watch::Receiver<FlashblocksFilter> {
+        let (tx, rx) = watch::channel(filter);
+        self.filters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert((connection, id), ActiveFilter { tx, scope });
+        rx
+    }
+
+    /// Removes the filter of a closed subscription.
+    fn remove(&self, connection: ConnectionId, id: SubscriptionId<'static>) {
+        self.filters.lock().unwrap_or_else(|e| e.into_inner()).remove(&(connection, id));
+    }
+
+    /// Replaces the filter of a subscription opened on `connection` within its scope, returns
+    /// `false` if no such subscription is active. The schema version of a subscription can't
+    /// change, filters without one keep the version of the subscription.
+    pub fn update(
+        &self,
+        connection: ConnectionId,
+        id: SubscriptionId<'static>,
+        mut filter: FlashblocksFilter,
+        max_subscribed_addresses: usize,
+    ) -> Result<bool, ErrorObject<'static>> {
+        let filters = self.filters.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(active) = filters.get(&(connection, id)) else {
+            return Ok(false);
+        };
+        let schema_version = active.tx.borrow().schema_version();
+        let requested = *filter.schema_version.get_or_insert(schema_version);
+        if requested != schema_version {
+            return Err(invalid_params_rpc_err(format!(
+                "subscription has schema version {schema_version}, which can't be updated"
+            )));
+        }
+        if let Some(scope) = &active.scope {
+            scope.validate_filter(&filter, max_subscribed_addresses)?;
+        }
+        active.tx.send_replace(filter);
+        Ok(true)
+    }
+
+    /// Returns the number of active subscriptions.
+    pub fn len(&self) -> usize {
+        self.filters.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns `true` if no subscription is active.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
 /// Optimism-specific Ethereum pubsub handler that extends standard subscriptions with flashblocks support.
 #[derive(Clone)]
-pub struct FlashblocksPubSub<Eth: EthApiTypes, N: NodePrimitives> {
+pub struct FlashblocksPubSub<Eth: EthApiTypes + RpcNodeCore, N: NodePrimitives> {
     /// Standard eth pubsub handler
     eth_pubsub: EthPubSub<Eth>,
     /// All nested flashblocks fields bundled together
     inner: Arc<FlashblocksPubSubInner<Eth, N>>,
+    /// Scopes of the API keys, flashblocks subscriptions are not scoped if unset
+    scopes: Option<Arc<SubscriptionScopes>>,
+    /// Upstream feed merged into `newPendingTransactions`, local transactions only if unset
+    pending_tx_feed: Option<UpstreamPendingTxFeed>,
+    /// Relayed preconfirmations attached to streamed transactions, none if unset
+    preconfs: Option<Arc<Preconfirmations>>,
+    /// Load shedding of the enrichment of lagging pending blocks, every block is enriched if unset
+    shedding: Option<Arc<EnrichmentShedding>>,
 }
 
 impl<Eth: EthApiTypes, N: NodePrimitives> FlashblocksPubSub<Eth, N>
 where
     Eth: RpcNodeCore<Primitives = N> + 'static,
-    Eth::Provider: BlockNumReader,
+    Eth::Provider: BlockNumReader
+        + BlockIdReader
+        + HeaderProvider
+        + ForkChoiceSubscriptions<Header = N::BlockHeader>
+        + TransactionsProvider<Transaction = N::SignedTx>,
     Eth::RpcConvert: RpcConvert<Primitives = N> + Clone,
 {
     /// Creates a new, shareable instance.
@@ -87,36 +216,83 @@ where
     /// Subscription tasks are spawned via [`tokio::task::spawn`]
     pub fn new(
         eth_pubsub: EthPubSub<Eth>,
+        provider: Eth::Provider,
+        canon_state: CanonStateMux<N>,
         pending_block_rx: PendingBlockRx<N>,
         subscription_task_spawner: Box<dyn TaskSpawner>,
         tx_converter: Eth::RpcConvert,
         max_subscribed_addresses: usize,
     ) -> Self {
         let inner = FlashblocksPubSubInner {
+            provider,
+            canon_state,
             pending_block_rx,
             subscription_task_spawner,
             tx_converter,
             max_subscribed_addresses,
+            filters: Arc::default(),
+            receipts: Arc::default(),
         };
-        Self { eth_pubsub, inner: Arc::new(inner) }
+        Self {
+            eth_pubsub,
+            inner: Arc::new(inner),
+            scopes: None,
+            pending_tx_feed: None,
+            preconfs: None,
+            shedding: None,
+        }
     }
 
-    /// Converts this `FlashblocksPubSub` into an RPC module.
+    /// Scopes the flashblocks subscriptions by API key.
+    pub fn with_scopes(mut self, scopes: SubscriptionScopes) -> Self {
+        self.scopes = Some(Arc::new(scopes));
+        self
+    }
+
+    /// Merges the pending transactions of an upstream node into `newPendingTransactions`.
+    pub fn with_pending_tx_feed(mut self, feed: UpstreamPendingTxFeed) -> Self {
+        self.pending_tx_feed = Some(feed);
+        self
+    }
+
+    /// Attaches the relayed preconfirmations to the transactions of flashblocks subscriptions.
+    pub fn with_preconfirmations(mut self, preconfs: Arc<Preconfirmations>) -> Self {
+        self.preconfs = Some(preconfs);
+        self
+    }
+
+    /// Skips the enrichment of pending blocks lagging the tip by more than `max_lag`, until a
+    /// pending block is near the tip again.
+    pub fn with_enrichment_max_lag(mut self, max_lag: Duration) -> Self {
+        self.shedding = Some(Arc::new(EnrichmentShedding::new(max_lag)));
+        self
+    }
+
+    /// Converts this `FlashblocksPubSub` into an RPC module, including
+    /// `xlayer_updateSubscription` which must be served alongside the subscriptions.
     pub fn into_rpc(self) -> jsonrpsee::RpcModule<()>
     where
         FlashblocksPubSub<Eth, N>: FlashblocksPubSubApiServer<RpcTransaction<Eth::NetworkTypes>>,
     {
-        <FlashblocksPubSub<Eth, N> as FlashblocksPubSubApiServer<
+        let mut module = <FlashblocksPubSub<Eth, N> as FlashblocksPubSubApiServer<
             RpcTransaction<Eth::NetworkTypes>,
-        >>::into_rpc(self)
-        .remove_context()
+        >>::into_rpc(self.clone())
+        .remove_context();
+        module
+            .merge(FlashblocksSubscriptionApiServer::into_rpc(self).remove_context())
+            .expect("xlayer and eth namespaces don't overlap");
+        module
     }
 
     pub fn new_flashblocks_stream(
         &self,
         filter: FlashblocksFilter,
     ) -> impl Stream<Item = FlashblockItem<N, Eth::RpcConvert>> {
-        self.inner.new_flashblocks_stream(filter)
+        self.inner.new_flashblocks_stream(
+            watch::channel(filter).1,
+            self.preconfs.clone(),
+            self.shedding.clone(),
+        )
     }
 
     async fn handle_accepted(
@@ -124,6 +300,7 @@ where
         accepted_sink: SubscriptionSink,
         kind: FlashblockSubscriptionKind,
         params: Option<FlashblockParams>,
+        scope: Option<SubscriptionScope>,
     ) -> Result<(), ErrorObject<'static>> {
         match kind {
             FlashblockSubscriptionKind::Flashblocks => {
@@ -131,23 +308,111 @@ where
                     return Err(invalid_params_rpc_err("invalid params for flashblocks"));
                 };
 
-                let fb_stream = self.new_flashblocks_stream(filter);
-                pipe_from_flashblocks_stream::<N, Eth, _>(accepted_sink, fb_stream).await
+                let connection = accepted_sink.connection_id();
+                let id = accepted_sink.subscription_id();
+                let schema_version = filter.schema_version();
+                let filter_rx = self.inner.filters.register(connection, id.clone(), filter, scope);
+                let include_transactions = scope.is_none_or(|scope| scope.includes_transactions());
+                let fb_stream = with_reorg_events(
+                    self.inner
+                        .new_flashblocks_stream(
+                            filter_rx,
+                            self.preconfs.clone(),
+                            self.shedding.clone(),
+                        )
+                        .filter(move |event| {
+                            ready(
+                                include_transactions
+                                    || !matches!(event, FlashblockStreamEvent::Transaction { .. }),
+                            )
+                        }),
+                    self.inner.canon_state.subscribe("flashblocks_reorg"),
+                )
+                .map(move |event| VersionedFlashblockEvent::new(schema_version, event));
+                let res = pipe_from_stream(accepted_sink, pin!(fb_stream)).await;
+                self.inner.filters.remove(connection, id);
+                res
+            }
+            FlashblockSubscriptionKind::FlashblockFinality => {
+                let heads = pin!(finality_stream(
+                    self.inner.provider.clone(),
+                    self.inner.canon_state.subscribe("flashblock_finality"),
+                ));
+                pipe_from_stream(accepted_sink, heads).await
+            }
+            FlashblockSubscriptionKind::L1Status => {
+                let statuses = pin!(l1_status_stream(
+                    self.inner.provider.clone(),
+                    self.inner.canon_state.subscribe("l1_status"),
+                ));
+                pipe_from_stream(accepted_sink, statuses).await
             }
             FlashblockSubscriptionKind::Standard(alloy_kind) => {
                 let standard_params = match params {
-                    Some(FlashblockParams::Standard(p)) => Some(p),
+                    Some(params) => Some(params.into_standard().ok_or_else(|| {
+                        invalid_params_rpc_err("invalid params for standard eth subscription")
+                    })?),
                     None => None,
-                    _ => {
-                        return Err(invalid_params_rpc_err(
-                            "invalid params for standard eth subscription",
-                        ))
-                    }
                 };
+                if alloy_kind == AlloySubscriptionKind::NewPendingTransactions
+                    && let Some(feed) = &self.pending_tx_feed
+                {
+                    return self
+                        .handle_pending_transactions(accepted_sink, standard_params, feed)
+                        .await;
+                }
                 self.eth_pubsub.handle_accepted(accepted_sink, alloy_kind, standard_params).await
             }
         }
     }
+
+    /// Streams the pending transactions of the local pool merged with the upstream feed.
+    async fn handle_pending_transactions(
+        &self,
+        accepted_sink: SubscriptionSink,
+        params: Option<AlloyParams>,
+        feed: &UpstreamPendingTxFeed,
+    ) -> Result<(), ErrorObject<'static>> {
+        let full = match params {
+            None => false,
+            Some(AlloyParams::Bool(full)) => full,
+            Some(_) => {
+                return Err(invalid_params_rpc_err("invalid params for newPendingTransactions"));
+            }
+        };
+
+        if !full {
+            let local =
+                self.eth_pubsub.pending_transaction_hashes_stream().map(|hash| (hash, hash));
+            let upstream = feed.subscribe().map(|tx| (tx.hash, tx.hash));
+            return pipe_from_stream(
+                accepted_sink,
+                merge_pending_transactions(pin!(local), pin!(upstream)),
+            )
+            .await;
+        }
+
+        let tx_converter = self.inner.tx_converter.clone();
+        let local = self.eth_pubsub.full_pending_transaction_stream().filter_map(move |event| {
+            let hash = *event.transaction.hash();
+            ready(match tx_converter.fill_pending(event.transaction.to_consensus()) {
+                Ok(tx) => Some((hash, PendingTransaction::Local(tx))),
+                Err(err) => {
+                    warn!(target: "xlayer::pending_tx_feed", %hash, %err, "Failed to convert pending transaction");
+                    None
+                }
+            })
+        });
+        // Upstream hashes without a transaction object can't be delivered in full
+        let upstream = feed.subscribe().filter_map(|tx| {
+            ready(
+                tx.transaction
+                    .map(|transaction| (tx.hash, PendingTransaction::Upstream(transaction))),
+            )
+        });
+        pipe_from_stream(accepted_sink, merge_pending_transactions(pin!(local), pin!(upstream)))
+            .await
+    }
 }
 
 #[async_trait::async_trait]
@@ -155,7 +420,11 @@ impl<Eth: EthApiTypes, N: NodePrimitives>
     FlashblocksPubSubApiServer<RpcTransaction<Eth::NetworkTypes>> for FlashblocksPubSub<Eth, N>
 where
     Eth: RpcNodeCore<Primitives = N> + 'static,
-    Eth::Provider: BlockNumReader,
+    Eth::Provider: BlockNumReader
+        + BlockIdReader
+        + HeaderProvider
+        + ForkChoiceSubscriptions<Header = N::BlockHeader>
+        + TransactionsProvider<Transaction = N::SignedTx>,
     Eth::RpcConvert: RpcConvert<Primitives = N> + Clone,
 {
     async fn subscribe(
@@ -163,6 +432,7 @@ where
         pending: PendingSubscriptionSink,
         kind: FlashblockSubscriptionKind,
         params: Option<FlashblockParams>,
+        auth: Option<SubscriptionAuth>,
     ) -> jsonrpsee::core::SubscriptionResult {
         if kind == FlashblockSubscriptionKind::Flashblocks {
             let Some(params) = &params else {
@@ -178,20 +448,88 @@ where
                 pending.reject(err).await;
                 return Ok(());
             }
+        } else if kind == FlashblockSubscriptionKind::FlashblockFinality {
+            if params.is_some() {
+                pending
+                    .reject(invalid_params_rpc_err(
+                        "flashblockFinality subscription does not take params",
+                    ))
+                    .await;
+                return Ok(());
+            }
+        } else if kind == FlashblockSubscriptionKind::L1Status {
+            if params.is_some() {
+                pending
+                    .reject(invalid_params_rpc_err("l1Status subscription does not take params"))
+                    .await;
+                return Ok(());
+            }
+        } else if params.clone().is_some_and(|params| params.into_standard().is_none()) {
+            pending
+                .reject(invalid_params_rpc_err("invalid params for standard eth subscription"))
+                .await;
+            return Ok(());
         }
 
+        let scope = match &self.scopes {
+            Some(scopes) if !matches!(kind, FlashblockSubscriptionKind::Standard(_)) => {
+                let scope = scopes.scope(auth.as_ref()).and_then(|scope| {
+                    scope.validate(kind, params.as_ref(), self.inner.max_subscribed_addresses)?;
+                    Ok(scope)
+                });
+                match scope {
+                    Ok(scope) => Some(scope),
+                    Err(err) => {
+                        pending.reject(err).await;
+                        return Ok(());
+                    }
+                }
+            }
+            _ => None,
+        };
+
         let sink = pending.accept().await?;
         let pubsub = self.clone();
         self.inner.subscription_task_spawner.spawn(Box::pin(async move {
-            let _ = pubsub.handle_accepted(sink, kind, params).await;
+            let _ = pubsub.handle_accepted(sink, kind, params, scope).await;
         }));
 
         Ok(())
     }
 }
 
+impl<Eth: EthApiTypes, N: NodePrimitives> FlashblocksSubscriptionApiServer
+    for FlashblocksPubSub<Eth, N>
+where
+    Eth: RpcNodeCore<Primitives = N> + 'static,
+{
+    fn update_subscription(
+        &self,
+        ext: &Extensions,
+        id: String,
+        filter: FlashblocksFilter,
+    ) -> RpcResult<bool> {
+        let max_subscribed_addresses = self.inner.max_subscribed_addresses;
+        filter.validate(max_subscribed_addresses)?;
+        // Calls made outside of a connection can't own a subscription
+        let Some(connection) = ext.get::<ConnectionId>().copied() else {
+            return Ok(false);
+        };
+        self.inner.filters.update(
+            connection,
+            SubscriptionId::Str(id.into()),
+            filter,
+            max_subscribed_addresses,
+        )
+    }
+}
+
 #[derive(Clone)]
-pub struct FlashblocksPubSubInner<Eth: EthApiTypes, N: NodePrimitives> {
+pub struct FlashblocksPubSubInner<Eth: EthApiTypes + RpcNodeCore, N: NodePrimitives> {
+    /// Provider of the safe and finalized heads
+    pub(crate) provider: Eth::Provider,
+    /// Shared canonical state notifications
+    pub(crate) canon_state: CanonStateMux<N>,
     /// Pending block receiver from flashblocks, if available
     pub(crate) pending_block_rx: PendingBlockRx<N>,
     /// The type that's used to spawn subscription tasks.
@@ -200,6 +538,11 @@ pub struct FlashblocksPubSubInner<Eth: EthApiTypes, N: NodePrimitives> {
     pub(crate) tx_converter: Eth::RpcConvert,
     /// Maximum number of subscribed addresses.
     pub(crate) max_subscribed_addresses: usize,
+    /// Filters of the active flashblocks subscriptions.
+    pub(crate) filters: Arc<FlashblocksFilters>,
+    /// RPC receipts of the pending flashblock, converted once for all subscribers.
+    pub(crate) receipts:
+        Arc<FlashblockReceipts<RpcReceipt<<Eth::RpcConvert as RpcConvert>::Network>>>,
 }
 
 impl<Eth: EthApiTypes, N: NodePrimitives> FlashblocksPubSubInner<Eth, N>
@@ -209,22 +552,42 @@ where
 {
     fn new_flashblocks_stream(
         &self,
-        filter: FlashblocksFilter,
+        filter_rx: watch::Receiver<FlashblocksFilter>,
+        preconfs: Option<Arc<Preconfirmations>>,
+        shedding: Option<Arc<EnrichmentShedding>>,
     ) -> impl Stream<Item = FlashblockItem<N, Eth::RpcConvert>> {
         let tx_converter = self.tx_converter.clone();
+        let receipts = self.receipts.clone();
         let txhash_cache = Cache::builder()
             .max_capacity(MAX_TXHASH_CACHE_SIZE)
             .eviction_policy(EvictionPolicy::lru())
             .build();
 
+        let mut position = PendingPosition::default();
+
         WatchStream::new(self.pending_block_rx.clone())
             .filter_map(move |pending_block_opt| {
                 ready(pending_block_opt.map(|pending_block| {
+                    let block = pending_block.block();
+                    let header = block.header();
+                    if !position.advance(header.number(), block.hash(), header.parent_hash()) {
+                        // Transactions of replaced blocks are streamed again when re-included
+                        debug!(target: "xlayer::flashblocks", number = header.number(), "Pending chain reorged, resetting streamed transactions");
+                        txhash_cache.invalidate_all();
+                    }
+                    if shedding.as_ref().is_some_and(|shedding| {
+                        !shedding.should_enrich(header.number(), header.timestamp())
+                    }) {
+                        return futures::stream::iter(Vec::new());
+                    }
+                    let filter = filter_rx.borrow().clone();
                     futures::stream::iter(Self::flashblock_to_stream_events(
                         &pending_block,
                         &filter,
                         &tx_converter,
+                        &receipts,
                         &txhash_cache,
+                        preconfs.as_deref(),
                     ))
                 }))
             })
@@ -236,7 +599,9 @@ where
         pending_block: &PendingFlashBlock<N>,
         filter: &FlashblocksFilter,
         tx_converter: &Eth::RpcConvert,
+        rpc_receipts: &FlashblockReceipts<RpcReceipt<<Eth::RpcConvert as RpcConvert>::Network>>,
         txhash_cache: &Cache<TxHash, ()>,
+        preconfs: Option<&Preconfirmations>,
     ) -> Vec<FlashblockItem<N, Eth::RpcConvert>> {
         let block = pending_block.block();
         let receipts = pending_block.receipts.as_ref();
@@ -258,12 +623,14 @@ where
 
         events.extend(
             Self::collect_transactions(
-                block,
+                pending_block,
                 filter,
                 receipts,
                 tx_converter,
+                rpc_receipts,
                 sealed_block,
                 txhash_cache,
+                preconfs,
             )
             .into_iter()
             .map(|transaction| FlashblockStreamEvent::Transaction { block_number, transaction }),
@@ -273,13 +640,18 @@ where
     }
 
     fn collect_transactions(
-        block: &RecoveredBlock<N::Block>,
+        pending_block: &PendingFlashBlock<N>,
         filter: &FlashblocksFilter,
         receipts: &[N::Receipt],
         tx_converter: &Eth::RpcConvert,
+        rpc_receipts: &FlashblockReceipts<RpcReceipt<<Eth::RpcConvert as RpcConvert>::Network>>,
         sealed_block: &SealedBlock<N::Block>,
         txhash_cache: &Cache<TxHash, ()>,
+        preconfs: Option<&Preconfirmations>,
     ) -> Vec<EnrichedTxItem<Eth::RpcConvert>> {
+        let block = pending_block.block();
+        // Receipts are converted on first use, shared with the other subscribers
+        let mut converted = None;
         block
             .transactions_with_sender()
             .enumerate()
@@ -317,9 +689,27 @@ where
                 };
 
                 let tx_data = Self::enrich_transaction_data(filter, &ctx);
-                let tx_receipt = Self::enrich_receipt(filter, receipt, receipts, &ctx);
+                let tx_receipt = if filter.sub_tx_filter.tx_receipt {
+                    converted
+                        .get_or_insert_with(|| {
+                            Self::convert_receipts(
+                                block,
+                                receipts,
+                                tx_converter,
+                                rpc_receipts,
+                                sealed_block,
+                            )
+                        })
+                        .as_ref()
+                        .and_then(|converted| converted.get(idx).cloned())
+                } else {
+                    None
+                };
 
-                Some(EnrichedTransaction { tx_hash, tx_data, receipt: tx_receipt })
+                let preconfirmation =
+                    preconfs.and_then(|preconfs| preconfs.get_pending(pending_block, &tx_hash));
+
+                Some(EnrichedTransaction { tx_hash, tx_data, receipt: tx_receipt, preconfirmation })
             })
             .collect()
     }
@@ -352,41 +742,18 @@ where
         Some(rpc_tx)
     }
 
-    /// Enrich receipt data if requested in filter
-    fn enrich_receipt(
-        filter: &FlashblocksFilter,
-        receipt: &N::Receipt,
+    /// Converts the receipts of all transactions of the flashblock in a single call, unless
+    /// another subscriber already did.
+    fn convert_receipts(
+        block: &RecoveredBlock<N::Block>,
         receipts: &[N::Receipt],
-        ctx: &EnrichmentContext<'_, N, Eth::RpcConvert>,
-    ) -> Option<RpcReceipt<<Eth::RpcConvert as RpcConvert>::Network>> {
-        if !filter.sub_tx_filter.tx_receipt {
-            return None;
-        }
-
-        let (gas_used, next_log_index) =
-            calculate_gas_used_and_next_log_index(ctx.idx as u64, receipts);
-
-        let receipt_input = ConvertReceiptInput {
-            receipt: receipt.clone(),
-            tx: Recovered::new_unchecked(ctx.tx, ctx.sender),
-            gas_used: receipt.cumulative_gas_used() - gas_used,
-            next_log_index,
-            meta: TransactionMeta {
-                tx_hash: ctx.tx_hash,
-                index: ctx.idx as u64,
-                block_hash: ctx.sealed_block.hash(),
-                block_number: ctx.sealed_block.header().number(),
-                base_fee: ctx.sealed_block.header().base_fee_per_gas(),
-                excess_blob_gas: ctx.sealed_block.header().excess_blob_gas(),
-                timestamp: ctx.sealed_block.header().timestamp(),
-            },
-        };
-
-        ctx.tx_converter
-            .convert_receipts_with_block(vec![receipt_input], ctx.sealed_block)
-            .ok()?
-            .into_iter()
-            .next()
+        tx_converter: &Eth::RpcConvert,
+        rpc_receipts: &FlashblockReceipts<RpcReceipt<<Eth::RpcConvert as RpcConvert>::Network>>,
+        sealed_block: &SealedBlock<N::Block>,
+    ) -> Option<Arc<[RpcReceipt<<Eth::RpcConvert as RpcConvert>::Network>]>> {
+        rpc_receipts.get_or_convert(sealed_block.hash(), receipts.len(), || {
+            tx_converter.convert_receipts_with_block(receipt_inputs(block, receipts), sealed_block)
+        })
     }
 
     fn is_address_in_transaction(
@@ -420,6 +787,84 @@ where
     }
 }
 
+/// Inserts a reorg event whenever a canonical reorg replaces blocks already streamed to the
+/// subscriber. Reorgs are polled first, so the event precedes the flashblocks of the replacement
+/// blocks received since.
+fn with_reorg_events<N, H, Tx, R>(
+    events: impl Stream<Item = FlashblockStreamEvent<H, Tx, R>>,
+    canon_state: CanonStateConsumer<N>,
+) -> impl Stream<Item = FlashblockStreamEvent<H, Tx, R>>
+where
+    N: NodePrimitives,
+{
+    let reorgs = canon_state.into_stream().filter_map(|notification| {
+        ready(match notification {
+            CanonStateNotification::Reorg { old, new } => {
+                Some(FlashblockStreamEvent::Reorg { reorg: reorg_event(&old, &new) })
+            }
+            CanonStateNotification::Commit { .. } => None,
+        })
+    });
+    filter_unseen_reorgs(stream::select_with_strategy(reorgs, events, |_: &mut ()| PollNext::Left))
+}
+
+/// Position of a subscription in the pending chain, the number, hash and parent hash of the last
+/// flashblock streamed.
+#[derive(Debug, Default)]
+struct PendingPosition {
+    last: Option<(u64, B256, B256)>,
+}
+
+impl PendingPosition {
+    /// Moves to the given flashblock and returns whether it extends the streamed chain, either as
+    /// the next flashblock of the same block or as the child of the last streamed block.
+    fn advance(&mut self, number: u64, hash: B256, parent_hash: B256) -> bool {
+        let extends = self.last.is_none_or(|(last_number, last_hash, last_parent_hash)| {
+            (number == last_number && parent_hash == last_parent_hash)
+                || (number == last_number + 1 && parent_hash == last_hash)
+        });
+        self.last = Some((number, hash, parent_hash));
+        extends
+    }
+}
+
+/// Drops reorg events that don't replace any block streamed before.
+///
+/// A reorg rewinds the streamed height to the common ancestor, nothing of the new branch has been
+/// streamed when its event is sent.
+fn filter_unseen_reorgs<H, Tx, R>(
+    events: impl Stream<Item = FlashblockStreamEvent<H, Tx, R>>,
+) -> impl Stream<Item = FlashblockStreamEvent<H, Tx, R>> {
+    let mut latest_streamed: Option<u64> = None;
+    events.filter(move |event| {
+        let keep = match event {
+            FlashblockStreamEvent::Reorg { reorg } => {
+                let replaces_streamed =
+                    latest_streamed.is_some_and(|latest| reorg.first_replaced() <= latest);
+                if replaces_streamed {
+                    latest_streamed = Some(reorg.common_ancestor.number.to());
+                }
+                replaces_streamed
+            }
+            event => {
+                latest_streamed = Some(event.block_number());
+                true
+            }
+        };
+        ready(keep)
+    })
+}
+
+/// Describes the reorg from the `old` to the `new` chain segment.
+pub(crate) fn reorg_event<N: NodePrimitives>(old: &Chain<N>, new: &Chain<N>) -> FlashblocksReorg {
+    let (old_tip, new_tip, fork) = (old.tip(), new.tip(), new.fork_block());
+    FlashblocksReorg {
+        old_tip: ReorgBlock::new(old_tip.header().number(), old_tip.hash()),
+        new_tip: ReorgBlock::new(new_tip.header().number(), new_tip.hash()),
+        common_ancestor: ReorgBlock::new(fork.number, fork.hash),
+    }
+}
+
 /// Helper to convert a serde error into an [`ErrorObject`]
 #[derive(Debug)]
 pub struct SubscriptionSerializeError(serde_json::Error);
@@ -443,16 +888,13 @@ impl From<SubscriptionSerializeError> for ErrorObject<'static> {
 }
 
 /// Pipes all stream items to the subscription sink.
-async fn pipe_from_flashblocks_stream<N, Eth, FbSt>(
+async fn pipe_from_stream<T, St>(
     sink: SubscriptionSink,
-    mut fb_stream: FbSt,
+    mut stream: St,
 ) -> Result<(), ErrorObject<'static>>
 where
-    N: NodePrimitives,
-    N::BlockHeader: alloy_consensus::BlockHeader,
-    Eth: EthApiTypes + RpcNodeCore<Primitives = N> + 'static,
-    Eth::RpcConvert: RpcConvert<Primitives = N> + Clone,
-    FbSt: Stream<Item = FlashblockItem<N, Eth::RpcConvert>> + Unpin,
+    T: Serialize,
+    St: Stream<Item = T> + Unpin,
 {
     loop {
         tokio::select! {
@@ -460,8 +902,8 @@ where
                 // connection dropped
                 break Ok(())
             },
-            maybe_fb_item = fb_stream.next() => {
-                let Some(item) = maybe_fb_item else {
+            maybe_item = stream.next() => {
+                let Some(item) = maybe_item else {
                     // stream ended
                     break Ok(());
                 };
@@ -491,3 +933,173 @@ fn extract_header_from_pending_block<N: NodePrimitives>(
         Some(U256::from(block.rlp_length())),
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scopes::FlashblocksAccess;
+
+    fn filter(address: Address) -> FlashblocksFilter {
+        let mut filter = FlashblocksFilter::default();
+        filter.sub_tx_filter.subscribe_addresses.insert(address);
+        filter
+    }
+
+    #[test]
+    fn test_update_filter() {
+        let filters = FlashblocksFilters::default();
+        let (conn, id) = (ConnectionId(1), SubscriptionId::Str("0x1".into()));
+        let rx = filters.register(conn, id.clone(), filter(Address::with_last_byte(1)), None);
+
+        assert!(filters.update(conn, id.clone(), filter(Address::with_last_byte(2)), 10).unwrap());
+        assert_eq!(*rx.borrow(), filter(Address::with_last_byte(2)));
+
+        // The schema version is fixed at subscribe
+        let upgraded = FlashblocksFilter { schema_version: Some(2), ..Default::default() };
+        assert!(filters.update(conn, id.clone(), upgraded, 10).is_err());
+        let explicit_v1 = FlashblocksFilter { schema_version: Some(1), ..Default::default() };
+        assert!(filters.update(conn, id.clone(), explicit_v1, 10).unwrap());
+        assert_eq!(rx.borrow().schema_version(), 1);
+
+        // Unknown and closed subscriptions
+        let unknown = SubscriptionId::Str("0x2".into());
+        assert!(!filters.update(conn, unknown, FlashblocksFilter::default(), 10).unwrap());
+        filters.remove(conn, id.clone());
+        assert!(filters.is_empty());
+        assert!(!filters.update(conn, id, FlashblocksFilter::default(), 10).unwrap());
+    }
+
+    #[test]
+    fn test_update_filter_keeps_schema_version() {
+        let filters = FlashblocksFilters::default();
+        let (conn, id) = (ConnectionId(1), SubscriptionId::Str("0x1".into()));
+        let v2 = FlashblocksFilter { schema_version: Some(2), ..Default::default() };
+        let rx = filters.register(conn, id.clone(), v2, None);
+
+        // Filters without version inherit the version of the subscription
+        assert!(filters.update(conn, id.clone(), filter(Address::with_last_byte(1)), 10).unwrap());
+        assert_eq!(rx.borrow().schema_version(), 2);
+        assert!(rx.borrow().requires_address_filtering());
+
+        let downgraded = FlashblocksFilter { schema_version: Some(1), ..Default::default() };
+        assert!(filters.update(conn, id, downgraded, 10).is_err());
+        assert_eq!(rx.borrow().schema_version(), 2);
+    }
+
+    #[test]
+    fn test_update_filter_from_other_connection() {
+        let filters = FlashblocksFilters::default();
+        let id = SubscriptionId::Str("0x1".into());
+        let rx = filters.register(ConnectionId(1), id.clone(), FlashblocksFilter::default(), None);
+
+        let update = filter(Address::with_last_byte(1));
+        assert!(!filters.update(ConnectionId(2), id, update, 10).unwrap());
+        assert_eq!(*rx.borrow(), FlashblocksFilter::default());
+    }
+
+    #[test]
+    fn test_update_filter_within_scope() {
+        let filters = FlashblocksFilters::default();
+        let (conn, id) = (ConnectionId(1), SubscriptionId::Str("0x1".into()));
+        let scope = SubscriptionScope {
+            flashblocks: Some(FlashblocksAccess::Headers),
+            ..Default::default()
+        };
+        let rx = filters.register(conn, id.clone(), FlashblocksFilter::default(), Some(scope));
+
+        // Header only subscriptions can't add addresses
+        assert!(filters.update(conn, id, filter(Address::with_last_byte(1)), 10).is_err());
+        assert_eq!(*rx.borrow(), FlashblocksFilter::default());
+    }
+
+    type TestEvent = FlashblockStreamEvent<alloy_consensus::Header, (), ()>;
+
+    fn transaction(block_number: u64) -> TestEvent {
+        let transaction = EnrichedTransaction {
+            tx_hash: TxHash::with_last_byte(1),
+            tx_data: None,
+            receipt: None,
+            preconfirmation: None,
+        };
+        FlashblockStreamEvent::Transaction { block_number, transaction }
+    }
+
+    fn reorg(common_ancestor: u64) -> TestEvent {
+        let block = |number| ReorgBlock::new(number, Default::default());
+        let reorg = FlashblocksReorg {
+            old_tip: block(common_ancestor + 2),
+            new_tip: block(common_ancestor + 1),
+            common_ancestor: block(common_ancestor),
+        };
+        FlashblockStreamEvent::Reorg { reorg }
+    }
+
+    #[tokio::test]
+    async fn test_reorgs_of_streamed_blocks() {
+        let events =
+            stream::iter([reorg(5), transaction(10), reorg(10), reorg(9), transaction(10)]);
+        let events = filter_unseen_reorgs(events)
+            .map(|event| match event {
+                FlashblockStreamEvent::Reorg { reorg } => {
+                    format!("reorg {}", reorg.first_replaced())
+                }
+                event => format!("tx {}", event.block_number()),
+            })
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events, ["tx 10", "reorg 10", "tx 10"]);
+    }
+
+    #[tokio::test]
+    async fn test_reorg_to_lower_height() {
+        // Block 11 is replaced by a shorter branch, a later reorg of the new block 10 is streamed
+        // while one replacing the never streamed block 11 of the new branch is not
+        let events = stream::iter([
+            transaction(11),
+            reorg(9),
+            transaction(10),
+            reorg(10),
+            reorg(9),
+            transaction(10),
+        ]);
+        let events = filter_unseen_reorgs(events)
+            .map(|event| match event {
+                FlashblockStreamEvent::Reorg { reorg } => {
+                    format!("reorg {}", reorg.first_replaced())
+                }
+                event => format!("tx {}", event.block_number()),
+            })
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(events, ["tx 11", "reorg 10", "tx 10", "reorg 10", "tx 10"]);
+    }
+
+    #[test]
+    fn test_pending_position_one_block_reorg() {
+        let hash = B256::with_last_byte;
+        let mut position = PendingPosition::default();
+
+        // Flashblocks of block 10 on top of block 9, then of block 11 on top of the sealed block 10
+        assert!(position.advance(10, hash(0xa0), hash(9)));
+        assert!(position.advance(10, hash(0xa1), hash(9)));
+        assert!(position.advance(11, hash(0xb0), hash(0xa1)));
+
+        // Block 10 is replaced, the sequencer rebuilds it on top of block 9
+        assert!(!position.advance(10, hash(0xc0), hash(9)));
+        assert!(position.advance(10, hash(0xc1), hash(9)));
+        assert!(position.advance(11, hash(0xd0), hash(0xc1)));
+
+        // Block 11 builds on a block 10 that was never streamed
+        assert!(!position.advance(11, hash(0xe0), hash(0xee)));
+        // Skipped heights don't extend the streamed chain either
+        assert!(!position.advance(13, hash(0xf0), hash(0xff)));
+    }
+
+    #[test]
+    fn test_reorg_serialization() {
+        let json = serde_json::to_value(reorg(9)).unwrap();
+        assert_eq!(json["type"], "reorg");
+        assert_eq!(json["reorg"]["oldTip"]["number"], "0xb");
+        assert_eq!(json["reorg"]["commonAncestor"]["number"], "0x9");
+    }
+}
@@ -0,0 +1,196 @@
+//! Flashblock aware `eth_getTransactionByHash`.
+//!
+//! A transaction included in the current flashblock is not canonical yet, so the default lookup
+//! returns it as a pool transaction without block information. This override returns it with the
+//! number and hash of the pending block, plus a non-standard `flashblock` field telling
+//! integrators that the inclusion is a pre-confirmation which lapses once the pending block
+//! expires.
+
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use alloy_consensus::{transaction::TxHashRef, BlockHeader as _};
+use alloy_json_rpc::RpcObject;
+use alloy_primitives::{TxHash, U64};
+use alloy_rpc_types_eth::TransactionInfo;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use reth_optimism_flashblocks::PendingBlockRx;
+use reth_primitives_traits::{NodePrimitives, Recovered};
+use reth_rpc_convert::RpcConvert;
+use reth_rpc_eth_api::{helpers::EthTransactions, EthApiTypes, RpcNodeCore, RpcTransaction};
+use serde::Serialize;
+
+/// Flashblock aware transaction lookups of the `eth` namespace
+#[rpc(server, namespace = "eth")]
+pub trait FlashblockTransactionsApi<T: RpcObject> {
+    /// Returns the transaction with the given hash, including transactions of the pending
+    /// flashblock.
+    #[method(name = "getTransactionByHash")]
+    async fn transaction_by_hash(
+        &self,
+        hash: TxHash,
+    ) -> RpcResult<Option<FlashblockTransaction<T>>>;
+}
+
+/// Pre-confirmation of a transaction by a flashblock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashblockInclusion {
+    /// Index of the latest flashblock of the pending block
+    pub index: U64,
+    /// Unix timestamp in milliseconds after which the pending block is stale
+    pub expires_at: U64,
+}
+
+impl FlashblockInclusion {
+    fn new(index: u64, expires_at: Instant) -> Self {
+        let expires_at = SystemTime::now() + expires_at.saturating_duration_since(Instant::now());
+        let expires_at = expires_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        Self { index: U64::from(index), expires_at: U64::from(expires_at as u64) }
+    }
+}
+
+/// Transaction with its flashblock inclusion, if it is only included in the pending block.
+#[derive(Debug, Clone, Serialize)]
+pub struct FlashblockTransaction<T> {
+    #[serde(flatten)]
+    pub transaction: T,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flashblock: Option<FlashblockInclusion>,
+}
+
+/// Transaction lookups that consult the pending flashblock before the default lookup.
+#[derive(Debug)]
+pub struct FlashblockTransactions<Eth, N: NodePrimitives> {
+    eth_api: Eth,
+    pending_block_rx: PendingBlockRx<N>,
+}
+
+impl<Eth, N> FlashblockTransactions<Eth, N>
+where
+    Eth: EthApiTypes + RpcNodeCore<Primitives = N> + 'static,
+    Eth::RpcConvert: RpcConvert<Primitives = N>,
+    N: NodePrimitives,
+{
+    pub fn new(eth_api: Eth, pending_block_rx: PendingBlockRx<N>) -> Self {
+        Self { eth_api, pending_block_rx }
+    }
+
+    /// Looks the transaction up in the pending flashblock, if it has not expired.
+    fn pending_transaction(
+        &self,
+        hash: TxHash,
+    ) -> Result<Option<FlashblockTransaction<RpcTransaction<Eth::NetworkTypes>>>, Eth::Error> {
+        let pending_block = self.pending_block_rx.borrow();
+        let Some(pending_block) = pending_block
+            .as_ref()
+            .filter(|pending_block| Instant::now() < pending_block.expires_at)
+        else {
+            return Ok(None);
+        };
+
+        let block = pending_block.block();
+        let Some((idx, (sender, tx))) =
+            block.transactions_with_sender().enumerate().find(|(_, (_, tx))| *tx.tx_hash() == hash)
+        else {
+            return Ok(None);
+        };
+
+        let sealed_block = block.sealed_block();
+        let transaction = self.eth_api.tx_resp_builder().fill(
+            Recovered::new_unchecked(tx.clone(), *sender),
+            TransactionInfo {
+                hash: Some(hash),
+                index: Some(idx as u64),
+                block_hash: Some(sealed_block.hash()),
+                block_number: Some(sealed_block.header().number()),
+                base_fee: sealed_block.header().base_fee_per_gas(),
+            },
+        )?;
+
+        Ok(Some(FlashblockTransaction {
+            transaction,
+            flashblock: Some(FlashblockInclusion::new(
+                pending_block.last_flashblock_index,
+                pending_block.expires_at,
+            )),
+        }))
+    }
+}
+
+#[async_trait]
+impl<Eth, N> FlashblockTransactionsApiServer<RpcTransaction<Eth::NetworkTypes>>
+    for FlashblockTransactions<Eth, N>
+where
+    Eth: EthTransactions + RpcNodeCore<Primitives = N> + 'static,
+    Eth::RpcConvert: RpcConvert<Primitives = N>,
+    N: NodePrimitives,
+{
+    async fn transaction_by_hash(
+        &self,
+        hash: TxHash,
+    ) -> RpcResult<Option<FlashblockTransaction<RpcTransaction<Eth::NetworkTypes>>>> {
+        if let Some(transaction) = self.pending_transaction(hash).map_err(Into::into)? {
+            return Ok(Some(transaction));
+        }
+
+        let Some(source) =
+            EthTransactions::transaction_by_hash(&self.eth_api, hash).await.map_err(Into::into)?
+        else {
+            return Ok(None);
+        };
+        let transaction = source
+            .into_transaction(self.eth_api.tx_resp_builder())
+            .map_err(|err| Eth::Error::from(err).into())?;
+        Ok(Some(FlashblockTransaction { transaction, flashblock: None }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_flashblock_transaction_serde() {
+        let transaction = FlashblockTransaction {
+            transaction: serde_json::json!({"hash": "0x01", "blockNumber": "0x10"}),
+            flashblock: Some(FlashblockInclusion {
+                index: U64::from(3),
+                expires_at: U64::from(1000),
+            }),
+        };
+        assert_eq!(
+            serde_json::to_value(&transaction).unwrap(),
+            serde_json::json!({
+                "hash": "0x01",
+                "blockNumber": "0x10",
+                "flashblock": {"index": "0x3", "expiresAt": "0x3e8"}
+            })
+        );
+
+        let transaction = FlashblockTransaction {
+            transaction: serde_json::json!({"hash": "0x01"}),
+            flashblock: None,
+        };
+        assert_eq!(
+            serde_json::to_value(&transaction).unwrap(),
+            serde_json::json!({"hash": "0x01"})
+        );
+    }
+
+    #[test]
+    fn test_flashblock_inclusion_expiry() {
+        let before = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+        let inclusion = FlashblockInclusion::new(2, Instant::now() + Duration::from_secs(1));
+        let expires_at = inclusion.expires_at.to::<u64>();
+        assert!(expires_at >= before + 1000);
+        assert!(expires_at < before + 2000);
+
+        // Expired pending blocks report the current time
+        let inclusion = FlashblockInclusion::new(2, Instant::now() - Duration::from_secs(1));
+        assert!(inclusion.expires_at.to::<u64>() >= before);
+    }
+}
@@ -30,7 +30,7 @@ pub(crate) fn decode_flashblock(bytes: Bytes) -> eyre::Result<FlashBlock> {
 /// then it assumes that it is JSON-encoded and returns it as-is.
 ///
 /// Otherwise, the `bytes` are passed through a brotli decompressor and returned.
-fn try_parse_message(bytes: Bytes) -> eyre::Result<Bytes> {
+pub(crate) fn try_parse_message(bytes: Bytes) -> eyre::Result<Bytes> {
     if bytes.trim_ascii_start().starts_with(b"{") {
         return Ok(bytes);
     }
@@ -1,6 +1,7 @@
 pub use stream::{WsConnect, WsFlashBlockStream};
 
 mod decoding;
+pub(crate) use decoding::try_parse_message;
 pub use decoding::FlashBlockDecoder;
 
 mod stream;
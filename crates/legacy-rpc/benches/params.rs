@@ -0,0 +1,102 @@
+//! Compares parsing routing params into `serde_json::Value` trees with the borrowed views of
+//! [`xlayer_legacy_rpc::params`].
+//!
+//! Allocations per parse are printed before each group, e.g. with
+//! `cargo bench -p xlayer-legacy-rpc --bench params`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use serde_json::Value;
+use xlayer_legacy_rpc::params::{as_str, parse_block_param, LogsFilterRef};
+
+/// Allocator counting the allocations made through it.
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const BLOCK_PARAMS: &str = r#"["0x1111111111111111111111111111111111111111","0x10"]"#;
+
+const LOGS_PARAMS: &str = r#"[{"fromBlock":"0x10","toBlock":"0x1f","address":["0x1111111111111111111111111111111111111111","0x2222222222222222222222222222222222222222"],"topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",null,"0x0000000000000000000000001111111111111111111111111111111111111111"]}]"#;
+
+/// Block number of the params, parsed through a `Value` tree.
+fn value_block_param(params: &str) -> Option<u64> {
+    let params: Value = serde_json::from_str(params).ok()?;
+    let number = params.get(1)?.as_str()?.strip_prefix("0x")?;
+    u64::from_str_radix(number, 16).ok()
+}
+
+/// Block range of the filter, parsed through a `Value` tree.
+fn value_logs_range(params: &str) -> Option<(u64, u64)> {
+    let params: Value = serde_json::from_str(params).ok()?;
+    let filter = params.get(0)?.as_object()?;
+    let block = |key| {
+        let number = filter.get(key)?.as_str()?.strip_prefix("0x")?;
+        u64::from_str_radix(number, 16).ok()
+    };
+    Some((block("fromBlock")?, block("toBlock")?))
+}
+
+/// Block range of the filter, parsed through [`LogsFilterRef`].
+fn borrowed_logs_range(params: &str) -> Option<(u64, u64)> {
+    let filter = LogsFilterRef::parse(params)?;
+    let block = |value| {
+        let number = as_str(value)?.strip_prefix("0x")?;
+        u64::from_str_radix(number, 16).ok()
+    };
+    Some((block(filter.from_block?)?, block(filter.to_block?)?))
+}
+
+/// Prints the number of allocations made by a single call of `f`.
+fn report_allocations<T>(name: &str, f: impl Fn() -> T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!("{name}: {allocations} allocations per parse");
+}
+
+fn block_param(c: &mut Criterion) {
+    report_allocations("block_param/value", || value_block_param(BLOCK_PARAMS));
+    report_allocations("block_param/borrowed", || parse_block_param(BLOCK_PARAMS, 1));
+
+    let mut group = c.benchmark_group("block_param");
+    group.bench_function("value", |b| b.iter(|| value_block_param(black_box(BLOCK_PARAMS))));
+    group.bench_function("borrowed", |b| b.iter(|| parse_block_param(black_box(BLOCK_PARAMS), 1)));
+    group.finish();
+}
+
+fn logs_filter(c: &mut Criterion) {
+    report_allocations("logs_filter/value", || value_logs_range(LOGS_PARAMS));
+    report_allocations("logs_filter/borrowed", || borrowed_logs_range(LOGS_PARAMS));
+
+    let mut group = c.benchmark_group("logs_filter");
+    group.bench_function("value", |b| b.iter(|| value_logs_range(black_box(LOGS_PARAMS))));
+    group.bench_function("borrowed", |b| b.iter(|| borrowed_logs_range(black_box(LOGS_PARAMS))));
+    group.finish();
+}
+
+criterion_group!(benches, block_param, logs_filter);
+criterion_main!(benches);
@@ -0,0 +1,182 @@
+//! Admin RPC for reloading the legacy routing configuration at runtime.
+
+use std::{collections::HashMap, time::Duration};
+
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    LegacyRpcRouterConfig, LegacyRpcRouterConfigHandle, LookupOrder, ReceiptCompatProfile,
+    RoutingStrategyKind,
+};
+
+/// Current legacy routing configuration as reported over RPC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyRpcConfigInfo {
+    pub enabled: bool,
+    pub legacy_endpoint: String,
+    pub cutoff_block: u64,
+    pub strategy: RoutingStrategyKind,
+    pub timeout_ms: u64,
+    pub receipt_profile: ReceiptCompatProfile,
+    pub lookup_order: HashMap<String, LookupOrder>,
+}
+
+impl From<&LegacyRpcRouterConfig> for LegacyRpcConfigInfo {
+    fn from(config: &LegacyRpcRouterConfig) -> Self {
+        Self {
+            enabled: config.enabled,
+            legacy_endpoint: config.legacy_endpoint.clone(),
+            cutoff_block: config.cutoff_block,
+            strategy: config.strategy,
+            timeout_ms: config.timeout.as_millis() as u64,
+            receipt_profile: config.receipt_profile,
+            lookup_order: config.lookup_order.clone(),
+        }
+    }
+}
+
+/// Partial update of the legacy routing configuration. Omitted fields keep their current value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct LegacyRpcConfigUpdate {
+    pub enabled: Option<bool>,
+    pub legacy_endpoint: Option<String>,
+    pub cutoff_block: Option<u64>,
+    pub strategy: Option<RoutingStrategyKind>,
+    pub timeout_ms: Option<u64>,
+    pub receipt_profile: Option<ReceiptCompatProfile>,
+    /// Replaces the lookup orders of all methods, methods left out are looked up local first
+    pub lookup_order: Option<HashMap<String, LookupOrder>>,
+}
+
+impl LegacyRpcConfigUpdate {
+    /// Applies the update on top of the given configuration.
+    pub fn apply(self, current: &LegacyRpcRouterConfig) -> LegacyRpcRouterConfig {
+        LegacyRpcRouterConfig {
+            enabled: self.enabled.unwrap_or(current.enabled),
+            legacy_endpoint: self
+                .legacy_endpoint
+                .unwrap_or_else(|| current.legacy_endpoint.clone()),
+            cutoff_block: self.cutoff_block.unwrap_or(current.cutoff_block),
+            strategy: self.strategy.unwrap_or(current.strategy),
+            timeout: self.timeout_ms.map(Duration::from_millis).unwrap_or(current.timeout),
+            receipt_profile: self.receipt_profile.unwrap_or(current.receipt_profile),
+            lookup_order: self.lookup_order.unwrap_or_else(|| current.lookup_order.clone()),
+            ..current.clone()
+        }
+    }
+}
+
+/// Admin API for the legacy RPC router
+#[rpc(server, client, namespace = "xlayer")]
+pub trait LegacyRpcAdminApi {
+    /// Returns the active legacy routing configuration.
+    #[method(name = "getLegacyRpcConfig")]
+    async fn get_legacy_rpc_config(&self) -> RpcResult<LegacyRpcConfigInfo>;
+
+    /// Updates the legacy routing configuration and returns the resulting configuration.
+    #[method(name = "updateLegacyRpcConfig")]
+    async fn update_legacy_rpc_config(
+        &self,
+        update: LegacyRpcConfigUpdate,
+    ) -> RpcResult<LegacyRpcConfigInfo>;
+}
+
+/// Legacy RPC admin implementation
+#[derive(Debug, Clone)]
+pub struct LegacyRpcAdmin {
+    config: LegacyRpcRouterConfigHandle,
+}
+
+impl LegacyRpcAdmin {
+    pub fn new(config: LegacyRpcRouterConfigHandle) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl LegacyRpcAdminApiServer for LegacyRpcAdmin {
+    async fn get_legacy_rpc_config(&self) -> RpcResult<LegacyRpcConfigInfo> {
+        Ok(LegacyRpcConfigInfo::from(self.config.current().as_ref()))
+    }
+
+    async fn update_legacy_rpc_config(
+        &self,
+        update: LegacyRpcConfigUpdate,
+    ) -> RpcResult<LegacyRpcConfigInfo> {
+        let config = update.apply(&self.config.current());
+        let info = LegacyRpcConfigInfo::from(&config);
+        self.config
+            .update(config)
+            .map_err(|e| ErrorObject::owned(INVALID_PARAMS_CODE, e, None::<()>))?;
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LegacyRpcRouterConfig {
+        LegacyRpcRouterConfig {
+            enabled: true,
+            legacy_endpoint: "http://legacy:8545".to_string(),
+            cutoff_block: 1_000,
+            lookup_order: HashMap::from([(
+                "eth_getTransactionReceipt".to_string(),
+                LookupOrder::LegacyFirst,
+            )]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_partial_update() {
+        let current = config();
+        let update = LegacyRpcConfigUpdate {
+            strategy: Some(RoutingStrategyKind::Shadow),
+            timeout_ms: Some(5_000),
+            ..Default::default()
+        };
+        let updated = update.apply(&current);
+        assert_eq!(updated.strategy, RoutingStrategyKind::Shadow);
+        assert_eq!(updated.timeout, Duration::from_secs(5));
+
+        // Fields left out of the update keep their current value
+        assert!(updated.enabled);
+        assert_eq!(updated.legacy_endpoint, current.legacy_endpoint);
+        assert_eq!(updated.cutoff_block, current.cutoff_block);
+        assert_eq!(updated.receipt_profile, current.receipt_profile);
+        assert_eq!(updated.lookup_order, current.lookup_order);
+
+        let unchanged = LegacyRpcConfigUpdate::default().apply(&current);
+        assert_eq!(LegacyRpcConfigInfo::from(&unchanged), LegacyRpcConfigInfo::from(&current));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_update_rejected() {
+        let admin = LegacyRpcAdmin::new(LegacyRpcRouterConfigHandle::new(config()));
+        for update in [
+            LegacyRpcConfigUpdate {
+                legacy_endpoint: Some("not a url".to_string()),
+                ..Default::default()
+            },
+            LegacyRpcConfigUpdate { timeout_ms: Some(0), ..Default::default() },
+        ] {
+            let err = admin.update_legacy_rpc_config(update).await.unwrap_err();
+            assert_eq!(err.code(), INVALID_PARAMS_CODE);
+        }
+        // The rejected updates leave the configuration unchanged
+        let info = admin.get_legacy_rpc_config().await.unwrap();
+        assert_eq!(info, LegacyRpcConfigInfo::from(&config()));
+
+        let update = LegacyRpcConfigUpdate { cutoff_block: Some(2_000), ..Default::default() };
+        assert_eq!(admin.update_legacy_rpc_config(update).await.unwrap().cutoff_block, 2_000);
+    }
+}
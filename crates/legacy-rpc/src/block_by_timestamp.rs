@@ -0,0 +1,351 @@
+//! Block lookup by timestamp, served as `xlayer_getBlockByTimestamp(timestamp, closest)`.
+//!
+//! Analytics and vesting dapps looking for the block at a date otherwise bisect the chain with
+//! dozens of `eth_getBlockByNumber` calls. This method runs the binary search next to the data:
+//! with `closest` set to `before` (the default) it returns the last block with a timestamp at or
+//! below `timestamp`, with `after` the first block with a timestamp at or above it, and `null` if
+//! there is no such block.
+//!
+//! Block timestamps never decrease, but legacy blocks may share one, so the search returns the
+//! last, respectively first, of the blocks of equal timestamp. When legacy routing is enabled,
+//! the timestamp of the first local block of the routing strategy, the cutoff block, decides which
+//! chain is searched: timestamps before it are searched on the legacy node, later ones on the
+//! local node.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use alloy_primitives::U64;
+use jsonrpsee::{
+    core::{middleware::RpcServiceT, RpcResult},
+    proc_macros::rpc,
+    types::{
+        error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+        ErrorObject, Id, Request,
+    },
+    MethodResponse,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+use tracing::debug;
+
+use crate::{LegacyRpcRouterConfig, LegacyRpcRouterService, RpcRoute};
+
+/// Name of the block by timestamp method.
+pub const BLOCK_BY_TIMESTAMP_METHOD: &str = "xlayer_getBlockByTimestamp";
+
+/// Side of the timestamp the returned block is on.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Closest {
+    /// Last block at or before the timestamp
+    #[default]
+    Before,
+    /// First block at or after the timestamp
+    After,
+}
+
+/// Block by timestamp API. Served by the legacy routing middleware, so only a client is
+/// generated.
+#[rpc(client, namespace = "xlayer")]
+pub trait BlockByTimestampApi {
+    /// Returns the block closest to a timestamp, without transaction bodies.
+    #[method(name = "getBlockByTimestamp")]
+    async fn get_block_by_timestamp(
+        &self,
+        timestamp: U64,
+        closest: Option<Closest>,
+    ) -> RpcResult<Option<Value>>;
+}
+
+/// Parsed `xlayer_getBlockByTimestamp` params.
+#[derive(Debug, PartialEq, Eq)]
+struct TimestampParams {
+    timestamp: u64,
+    closest: Closest,
+}
+
+impl TimestampParams {
+    fn parse(params: Option<&str>) -> Result<Self, &'static str> {
+        let params: Vec<&RawValue> = params
+            .and_then(|params| serde_json::from_str(params).ok())
+            .ok_or("Missing required params")?;
+
+        let timestamp = match params.first().map(|timestamp| serde_json::from_str(timestamp.get()))
+        {
+            Some(Ok(Value::Number(timestamp))) => timestamp.as_u64().ok_or("Invalid timestamp")?,
+            Some(Ok(Value::String(timestamp))) => timestamp
+                .strip_prefix("0x")
+                .and_then(|timestamp| u64::from_str_radix(timestamp, 16).ok())
+                .ok_or("Invalid timestamp")?,
+            None => return Err("Missing timestamp"),
+            Some(_) => return Err("Invalid timestamp"),
+        };
+
+        let closest = match params.get(1).map(|closest| serde_json::from_str(closest.get())) {
+            None | Some(Ok(None)) => Closest::default(),
+            Some(Ok(Some(closest))) => closest,
+            Some(Err(_)) => return Err(r#"Closest must be "before" or "after""#),
+        };
+
+        Ok(Self { timestamp, closest })
+    }
+}
+
+/// Searches the blocks `low..=high` for the block closest to `timestamp`, reading block
+/// timestamps with `timestamp_of`.
+async fn search<F, Fut, E>(
+    mut low: u64,
+    mut high: u64,
+    timestamp: u64,
+    closest: Closest,
+    mut timestamp_of: F,
+) -> Result<Option<u64>, E>
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<u64, E>>,
+{
+    let mut found = None;
+    while low <= high {
+        let mid = low + (high - low) / 2;
+        let mid_timestamp = timestamp_of(mid).await?;
+        let go_up = match closest {
+            Closest::Before => mid_timestamp <= timestamp,
+            Closest::After => mid_timestamp < timestamp,
+        };
+        if go_up {
+            if closest == Closest::Before {
+                found = Some(mid);
+            }
+            low = mid + 1;
+        } else {
+            if closest == Closest::After {
+                found = Some(mid);
+            }
+            let Some(below) = mid.checked_sub(1) else {
+                break;
+            };
+            high = below;
+        }
+    }
+    Ok(found)
+}
+
+/// Fetches blocks through the legacy routing and records where they were served from.
+///
+/// Routes are recorded atomically so lookups can borrow the fetcher while the search holds it.
+struct BlockFetcher<'a, S> {
+    service: &'a LegacyRpcRouterService<S>,
+    config: &'a LegacyRpcRouterConfig,
+    routing: bool,
+    legacy: AtomicBool,
+    local: AtomicBool,
+}
+
+impl<S> BlockFetcher<'_, S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    /// Returns the block with the given number or tag, `None` if the node doesn't have it.
+    async fn block(&self, block: &str) -> Result<Option<Value>, ErrorObject<'static>> {
+        let params = serde_json::value::to_raw_value(&(block, false))
+            .map_err(|e| internal_error(e.to_string()))?;
+        let req = Request::owned("eth_getBlockByNumber".to_string(), Some(params), Id::Number(1));
+        let is_legacy = self.routing
+            && block
+                .strip_prefix("0x")
+                .and_then(|number| u64::from_str_radix(number, 16).ok())
+                .is_some_and(|number| {
+                    self.config.routing_strategy().routes_block_to_legacy(self.config, number)
+                });
+        let response = if is_legacy {
+            self.legacy.store(true, Ordering::Relaxed);
+            self.service.forward_to_legacy(self.config, req).await
+        } else {
+            self.local.store(true, Ordering::Relaxed);
+            self.service.inner.call(req).await
+        };
+
+        let mut response: Value = serde_json::from_str(response.as_json().get())
+            .map_err(|e| internal_error(format!("Invalid block response: {e}")))?;
+        if let Some(error) = response.get("error") {
+            let code =
+                error.get("code").and_then(Value::as_i64).unwrap_or(INTERNAL_ERROR_CODE as i64);
+            let message =
+                error.get("message").and_then(Value::as_str).unwrap_or("Invalid block response");
+            return Err(ErrorObject::owned(code as i32, message.to_string(), None::<()>));
+        }
+        Ok(response.get_mut("result").map(Value::take).filter(|block| !block.is_null()))
+    }
+
+    /// Returns the timestamp of a block that must exist.
+    async fn timestamp(&self, number: u64) -> Result<u64, ErrorObject<'static>> {
+        let block = self
+            .block(&format!("{number:#x}"))
+            .await?
+            .ok_or_else(|| internal_error(format!("Block {number} not found")))?;
+        quantity(&block, "timestamp")
+    }
+
+    fn route(&self) -> Option<RpcRoute> {
+        match (self.legacy.load(Ordering::Relaxed), self.local.load(Ordering::Relaxed)) {
+            (true, true) => Some(RpcRoute::Hybrid),
+            (true, false) => Some(RpcRoute::Legacy),
+            _ => None,
+        }
+    }
+}
+
+/// Handles `xlayer_getBlockByTimestamp`.
+pub(crate) async fn handle_get_block_by_timestamp<S>(
+    req: Request<'_>,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
+) -> MethodResponse
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    let id = req.id();
+    let params_ref = req.params();
+    let params = match TimestampParams::parse(params_ref.as_str()) {
+        Ok(params) => params,
+        Err(message) => {
+            return MethodResponse::error(
+                id,
+                ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>),
+            );
+        }
+    };
+
+    let fetcher = BlockFetcher {
+        service: &service,
+        config: &config,
+        routing: service.is_routing(&config),
+        legacy: AtomicBool::new(false),
+        local: AtomicBool::new(false),
+    };
+    let block = match find_block(&fetcher, params).await {
+        Ok(block) => block,
+        Err(error) => return MethodResponse::error(id, error),
+    };
+
+    let payload = jsonrpsee_types::ResponsePayload::success(&block).into();
+    let mut response = MethodResponse::response(id, payload, usize::MAX);
+    if let Some(route) = fetcher.route() {
+        response.extensions_mut().insert(route);
+    }
+    response
+}
+
+async fn find_block<S>(
+    fetcher: &BlockFetcher<'_, S>,
+    params: TimestampParams,
+) -> Result<Option<Value>, ErrorObject<'static>>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    let TimestampParams { timestamp, closest } = params;
+    let head = fetcher
+        .block("latest")
+        .await?
+        .ok_or_else(|| internal_error("Latest block not found".to_string()))?;
+    let head = quantity(&head, "number")?;
+
+    // The chains are split at the first local block of the routing strategy. Without legacy
+    // routing only the local chain, starting at that block, is searched
+    let local_from = fetcher.config.routing_strategy().local_from(fetcher.config).min(head);
+    let (low, high) = if fetcher.routing && local_from > 0 {
+        let local_timestamp = fetcher.timestamp(local_from).await?;
+        match closest {
+            Closest::Before if timestamp < local_timestamp => (0, local_from - 1),
+            Closest::Before => (local_from, head),
+            Closest::After if timestamp <= local_timestamp => (0, local_from),
+            Closest::After => (local_from + 1, head),
+        }
+    } else {
+        (local_from, head)
+    };
+    if low > high {
+        return Ok(None);
+    }
+
+    let number = search(low, high, timestamp, closest, |number| fetcher.timestamp(number)).await?;
+    debug!(target: "xlayer_legacy_rpc", timestamp, ?closest, ?number, low, high, "xlayer_getBlockByTimestamp searched");
+
+    match number {
+        Some(number) => fetcher.block(&format!("{number:#x}")).await,
+        None => Ok(None),
+    }
+}
+
+/// Reads a hex quantity field of a block.
+fn quantity(block: &Value, field: &str) -> Result<u64, ErrorObject<'static>> {
+    block
+        .get(field)
+        .and_then(Value::as_str)
+        .and_then(|value| value.strip_prefix("0x"))
+        .and_then(|value| u64::from_str_radix(value, 16).ok())
+        .ok_or_else(|| internal_error(format!("Block without {field}")))
+}
+
+fn internal_error(message: String) -> ErrorObject<'static> {
+    ErrorObject::owned(INTERNAL_ERROR_CODE, message, None::<()>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::ready;
+
+    async fn search_in(timestamps: &[u64], timestamp: u64, closest: Closest) -> Option<u64> {
+        let high = timestamps.len() as u64 - 1;
+        search(0, high, timestamp, closest, |number| {
+            ready(Ok::<_, ()>(timestamps[number as usize]))
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let timestamps = [10, 12, 12, 12, 15, 20];
+
+        assert_eq!(search_in(&timestamps, 12, Closest::Before).await, Some(3));
+        assert_eq!(search_in(&timestamps, 12, Closest::After).await, Some(1));
+        assert_eq!(search_in(&timestamps, 13, Closest::Before).await, Some(3));
+        assert_eq!(search_in(&timestamps, 13, Closest::After).await, Some(4));
+
+        // Outside of the chain
+        assert_eq!(search_in(&timestamps, 9, Closest::Before).await, None);
+        assert_eq!(search_in(&timestamps, 9, Closest::After).await, Some(0));
+        assert_eq!(search_in(&timestamps, 21, Closest::Before).await, Some(5));
+        assert_eq!(search_in(&timestamps, 21, Closest::After).await, None);
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let parse = |params: &str| TimestampParams::parse(Some(params));
+        assert_eq!(
+            parse(r#"["0x10"]"#),
+            Ok(TimestampParams { timestamp: 16, closest: Closest::Before })
+        );
+        assert_eq!(
+            parse(r#"[16, "after"]"#),
+            Ok(TimestampParams { timestamp: 16, closest: Closest::After })
+        );
+        assert_eq!(
+            parse(r#"[16, null]"#),
+            Ok(TimestampParams { timestamp: 16, closest: Closest::Before })
+        );
+
+        assert!(parse("[]").is_err());
+        assert!(parse(r#"["16"]"#).is_err());
+        assert!(parse(r#"[16, "closest"]"#).is_err());
+        assert!(TimestampParams::parse(None).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+//! Shared canonical state notifications.
+//!
+//! Every `canonical_state_stream()` call opens a new subscription on the provider, and
+//! subscribers that fall behind silently drop notifications. The [`CanonStateMux`] holds a single
+//! upstream subscription and fans it out to named consumers, each of which reports how many
+//! notifications it missed and how far behind it is. Consumers subscribed per RPC subscription
+//! share their name, their metrics add up.
+//!
+//! The multiplexer is created before the node is launched and shared by the node components and
+//! the RPC modules. The first of them to start spawns the upstream subscription.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use futures::{stream, Stream, StreamExt};
+use metrics::{Counter, Gauge};
+use reth_chain_state::{CanonStateNotification, CanonStateSubscriptions};
+use reth_metrics::Metrics;
+use reth_primitives_traits::NodePrimitives;
+use reth_tasks::TaskSpawner;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, warn};
+
+/// Default number of notifications buffered for the slowest consumer.
+pub const CANON_STATE_MUX_CAPACITY: usize = 256;
+
+/// Fans a single canonical state subscription out to several consumers.
+#[derive(Debug, Clone)]
+pub struct CanonStateMux<N: NodePrimitives> {
+    sender: broadcast::Sender<CanonStateNotification<N>>,
+    /// Whether the upstream subscription was spawned, shared by the clones
+    spawned: Arc<AtomicBool>,
+}
+
+impl<N: NodePrimitives> CanonStateMux<N> {
+    /// Creates a multiplexer buffering up to `capacity` notifications per consumer.
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender, spawned: Default::default() }
+    }
+
+    /// Spawns the task forwarding the canonical state notifications of the provider, unless a
+    /// clone of the multiplexer already did.
+    pub fn spawn<P>(&self, provider: &P, task_spawner: &dyn TaskSpawner)
+    where
+        P: CanonStateSubscriptions<Primitives = N>,
+    {
+        if self.spawned.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let mut upstream = provider.canonical_state_stream();
+        let sender = self.sender.clone();
+        task_spawner.spawn_critical(
+            "xlayer canonical state mux",
+            Box::pin(async move {
+                while let Some(notification) = upstream.next().await {
+                    // Sending only fails while no consumer is subscribed
+                    let _ = sender.send(notification);
+                }
+                debug!(target: "xlayer::canon_state", "canonical state stream closed");
+            }),
+        );
+    }
+
+    /// Subscribes a consumer to the notifications sent from now on.
+    pub fn subscribe(&self, consumer: &'static str) -> CanonStateConsumer<N> {
+        let metrics = CanonStateConsumerMetrics::new_with_labels(&[("consumer", consumer)]);
+        metrics.consumers.increment(1);
+        CanonStateConsumer { receiver: self.sender.subscribe(), consumer, metrics, queued: 0 }
+    }
+}
+
+/// Metrics of the canonical state consumers of a name.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_canon_state_mux")]
+struct CanonStateConsumerMetrics {
+    /// Number of subscribed consumers
+    consumers: Gauge,
+    /// Number of notifications dropped because a consumer fell behind
+    lagged_notifications: Counter,
+    /// Number of notifications queued for the consumers
+    queued_notifications: Gauge,
+}
+
+/// Consumer of the notifications of a [`CanonStateMux`].
+#[derive(Debug)]
+pub struct CanonStateConsumer<N: NodePrimitives> {
+    receiver: broadcast::Receiver<CanonStateNotification<N>>,
+    consumer: &'static str,
+    metrics: CanonStateConsumerMetrics,
+    /// Number of queued notifications last added to the gauge shared by the consumers of the
+    /// name
+    queued: usize,
+}
+
+impl<N: NodePrimitives> CanonStateConsumer<N> {
+    /// Receives the next notification, skipping past notifications missed by a lagging consumer.
+    ///
+    /// Returns `None` once the upstream subscription is closed.
+    pub async fn recv(&mut self) -> Option<CanonStateNotification<N>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(notification) => {
+                    self.set_queued(self.receiver.len());
+                    return Some(notification);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    self.metrics.lagged_notifications.increment(skipped);
+                    warn!(target: "xlayer::canon_state", consumer = self.consumer, skipped, "Canonical state consumer lagged");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Replaces the consumer's share of the queued notifications gauge.
+    fn set_queued(&mut self, queued: usize) {
+        self.metrics.queued_notifications.increment(queued as f64 - self.queued as f64);
+        self.queued = queued;
+    }
+
+    /// Converts the consumer into a stream of notifications.
+    pub fn into_stream(self) -> impl Stream<Item = CanonStateNotification<N>> {
+        stream::unfold(self, |mut consumer| async move {
+            consumer.recv().await.map(|notification| (notification, consumer))
+        })
+    }
+}
+
+impl<N: NodePrimitives> Drop for CanonStateConsumer<N> {
+    fn drop(&mut self) {
+        self.set_queued(0);
+        self.metrics.consumers.decrement(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth_execution_types::Chain;
+    use reth_optimism_primitives::OpPrimitives;
+
+    fn notification() -> CanonStateNotification<OpPrimitives> {
+        CanonStateNotification::Commit { new: Arc::new(Chain::default()) }
+    }
+
+    #[tokio::test]
+    async fn test_consumers_receive_all_notifications() {
+        let mux = CanonStateMux::<OpPrimitives>::new(4);
+        let mut first = mux.subscribe("first");
+        let mut second = mux.subscribe("second");
+
+        mux.sender.send(notification()).unwrap();
+        mux.sender.send(notification()).unwrap();
+
+        for consumer in [&mut first, &mut second] {
+            assert!(consumer.recv().await.is_some());
+            assert!(consumer.recv().await.is_some());
+        }
+
+        drop(mux);
+        assert!(first.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_lagging_consumer_skips_ahead() {
+        let mux = CanonStateMux::<OpPrimitives>::new(2);
+        let mut slow = mux.subscribe("slow");
+
+        for _ in 0..5 {
+            mux.sender.send(notification()).unwrap();
+        }
+        drop(mux);
+
+        // Only the two most recent notifications are retained for the slow consumer
+        let received = slow.into_stream().count().await;
+        assert_eq!(received, 2);
+    }
+}
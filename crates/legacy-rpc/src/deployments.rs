@@ -0,0 +1,173 @@
+//! Deployment checks for the contracts of an `eth_getLogs` filter.
+//!
+//! The genesis state of the local node is the legacy state at the cutoff block, so a contract
+//! without code at the cutoff block was deployed after it and has no logs on the legacy node. When
+//! every address of a filter is such a contract, the legacy half of the query is skipped. Lacking a
+//! contract creation index, this is decided from the code of the address at the cutoff block. A
+//! contract destroyed before the cutoff and redeployed at the same address after it would lose its
+//! legacy logs, which is why the check is opt in.
+
+use std::{collections::HashMap, sync::Mutex};
+
+use futures::future::join_all;
+use jsonrpsee::{server::middleware::rpc::RpcServiceT, types::Request, MethodResponse};
+use jsonrpsee_types::Id;
+use serde_json::value::RawValue;
+use tracing::debug;
+
+use crate::{
+    params::{as_str, response_result, LogsFilterRef},
+    LegacyRpcRouterConfig, LegacyRpcRouterService,
+};
+
+/// Maximum number of addresses whose deployment check is cached.
+const MAX_CACHED_DEPLOYMENTS: usize = 100_000;
+
+/// Cache of deployment checks shared by the router services of a layer.
+#[derive(Debug, Default)]
+pub(crate) struct DeploymentCache {
+    /// Whether the address had code at the cutoff block, keyed by cutoff block and address
+    had_code: Mutex<HashMap<(u64, String), bool>>,
+}
+
+impl DeploymentCache {
+    fn get(&self, cutoff_block: u64, address: &str) -> Option<bool> {
+        let had_code = self.had_code.lock().unwrap_or_else(|e| e.into_inner());
+        had_code.get(&(cutoff_block, address.to_string())).copied()
+    }
+
+    fn insert(&self, cutoff_block: u64, address: String, had_code: bool) {
+        let mut cache = self.had_code.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= MAX_CACHED_DEPLOYMENTS {
+            cache.clear();
+        }
+        cache.insert((cutoff_block, address), had_code);
+    }
+}
+
+impl<S> LegacyRpcRouterService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    /// Returns whether every address of the `eth_getLogs` filter was deployed after the cutoff
+    /// block, in which case the legacy node has no logs for it.
+    pub(crate) async fn deployed_after_cutoff(
+        &self,
+        config: &LegacyRpcRouterConfig,
+        filter: &LogsFilterRef<'_>,
+    ) -> bool {
+        if !config.skip_new_contracts {
+            return false;
+        }
+        let Some(addresses) = filter_addresses(filter.address) else {
+            return false;
+        };
+
+        let cutoff_block = config.cutoff_block;
+        let had_code = join_all(addresses.into_iter().map(|address| async move {
+            if let Some(had_code) = self.deployments.get(cutoff_block, &address) {
+                return Some(had_code);
+            }
+            let had_code = self.had_code_at(&address, cutoff_block).await?;
+            self.deployments.insert(cutoff_block, address, had_code);
+            Some(had_code)
+        }))
+        .await;
+
+        // Addresses whose code could not be read are assumed to predate the cutoff
+        let skip = had_code.iter().all(|had_code| *had_code == Some(false));
+        if skip {
+            debug!(
+                target: "xlayer_legacy_rpc",
+                cutoff_block,
+                "eth_getLogs addresses deployed after the cutoff, skipping legacy"
+            );
+        }
+        skip
+    }
+
+    /// Reads whether the address had code at the given block from the local node.
+    async fn had_code_at(&self, address: &str, block: u64) -> Option<bool> {
+        let params = RawValue::from_string(format!(r#"["{address}","0x{block:x}"]"#)).ok()?;
+        let req = Request::owned("eth_getCode".to_string(), Some(params), Id::Number(0));
+        has_code(&self.inner.call(req).await)
+    }
+}
+
+/// Returns the lowercased addresses of an `eth_getLogs` filter, or `None` if the filter matches
+/// any address.
+fn filter_addresses(address: Option<&RawValue>) -> Option<Vec<String>> {
+    let address = address?.get();
+    let addresses = match address.as_bytes().first()? {
+        b'"' => vec![serde_json::from_str::<&str>(address).ok()?.to_ascii_lowercase()],
+        b'[' => serde_json::from_str::<Vec<&str>>(address)
+            .ok()?
+            .into_iter()
+            .map(str::to_ascii_lowercase)
+            .collect(),
+        _ => return None,
+    };
+    (!addresses.is_empty()).then_some(addresses)
+}
+
+/// Returns whether an `eth_getCode` response holds code, `None` if the call failed.
+fn has_code(response: &MethodResponse) -> Option<bool> {
+    let code = as_str(response_result(response.as_json().get())?)?;
+    Some(code != "0x")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::types::{ErrorObject, ResponsePayload};
+
+    #[test]
+    fn test_filter_addresses() {
+        let filter_addresses = |params: &str| {
+            filter_addresses(LogsFilterRef::parse(params).and_then(|filter| filter.address))
+        };
+        let params =
+            r#"[{"fromBlock":"0x1","address":"0xAbCdEf0000000000000000000000000000000001"}]"#;
+        assert_eq!(
+            filter_addresses(params),
+            Some(vec!["0xabcdef0000000000000000000000000000000001".to_string()])
+        );
+
+        let params = r#"[{"address":["0x1111111111111111111111111111111111111111","0x2222222222222222222222222222222222222222"]}]"#;
+        assert_eq!(filter_addresses(params).map(|addresses| addresses.len()), Some(2));
+
+        // Filters matching any address can't skip the legacy node
+        assert_eq!(filter_addresses(r#"[{"fromBlock":"0x1"}]"#), None);
+        assert_eq!(filter_addresses(r#"[{"address":[]}]"#), None);
+        assert_eq!(filter_addresses(r#"[{"address":null}]"#), None);
+        assert_eq!(filter_addresses(r#"[{"address":[1]}]"#), None);
+    }
+
+    #[test]
+    fn test_has_code() {
+        let response = |code: &str| {
+            MethodResponse::response(
+                Id::Number(0),
+                ResponsePayload::success(code.to_string()).into(),
+                usize::MAX,
+            )
+        };
+        assert_eq!(has_code(&response("0x")), Some(false));
+        assert_eq!(has_code(&response("0x6080604052")), Some(true));
+
+        let error = MethodResponse::error(
+            Id::Number(0),
+            ErrorObject::owned(-32000, "state not available", None::<()>),
+        );
+        assert_eq!(has_code(&error), None);
+    }
+
+    #[test]
+    fn test_deployment_cache() {
+        let cache = DeploymentCache::default();
+        cache.insert(100, "0x01".to_string(), true);
+        assert_eq!(cache.get(100, "0x01"), Some(true));
+        // Checks are made against a specific cutoff block
+        assert_eq!(cache.get(200, "0x01"), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Guardrails for `eth_estimateGas` answered by the legacy node.
+//!
+//! Legacy estimates include zkEVM specific pricing the local node doesn't model, so a transaction
+//! sent with a legacy estimate may run out of gas or exceed what the local chain accepts. With a
+//! safety margin configured, legacy estimates are raised by the margin and cross-checked against
+//! the local upper bound: the `gas` of the call if set, and the gas limit of the latest local
+//! block. Raised estimates are capped at the bound, and estimates already above it are answered
+//! with an error instead of a gas limit the transaction can't be sent with.
+
+use jsonrpsee::{
+    server::middleware::rpc::RpcServiceT,
+    types::{error::CALL_EXECUTION_FAILED_CODE, ErrorObject, Request},
+    MethodResponse,
+};
+use jsonrpsee_types::{Id, ResponsePayload};
+use metrics::Counter;
+use reth_metrics::Metrics;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    params::{self, as_str},
+    LegacyRpcRouterConfig, LegacyRpcRouterService, RpcRoute,
+};
+
+const ESTIMATE_GAS_METHOD: &str = "eth_estimateGas";
+
+/// Metrics of legacy gas estimates
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_legacy_rpc_estimate_gas")]
+struct EstimateGasMetrics {
+    /// Number of legacy estimates raised by the safety margin
+    adjusted: Counter,
+    /// Number of legacy estimates capped at the local upper bound
+    capped: Counter,
+    /// Number of legacy estimates rejected for exceeding the local upper bound
+    rejected: Counter,
+}
+
+/// Returns the legacy `estimate` raised by `margin_percent` and capped at `bound`, or `Err` with
+/// the bound if the estimate exceeds it.
+fn guard_estimate(estimate: u64, margin_percent: u64, bound: Option<u64>) -> Result<u64, u64> {
+    let raised = estimate as u128 * (100 + margin_percent as u128) / 100;
+    let raised = u64::try_from(raised).unwrap_or(u64::MAX);
+    match bound {
+        Some(bound) if estimate > bound => Err(bound),
+        Some(bound) => Ok(raised.min(bound)),
+        None => Ok(raised),
+    }
+}
+
+fn parse_quantity(value: &str) -> Option<u64> {
+    u64::from_str_radix(value.strip_prefix("0x")?, 16).ok()
+}
+
+impl<S> LegacyRpcRouterService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    /// Forwards a block param method to the legacy node, guarding `eth_estimateGas` results.
+    pub(crate) async fn forward_guarded_to_legacy(
+        &self,
+        config: &LegacyRpcRouterConfig,
+        req: Request<'_>,
+    ) -> MethodResponse {
+        let Some(margin_percent) =
+            config.estimate_gas_margin.filter(|_| req.method_name() == ESTIMATE_GAS_METHOD)
+        else {
+            return self.forward_to_legacy(config, req).await;
+        };
+
+        let id = req.id().into_owned();
+        let params = req.params().as_str().map(str::to_owned);
+        let res = self.forward_to_legacy(config, req).await;
+        let mut res = self.guard_legacy_estimate(id, margin_percent, params.as_deref(), res).await;
+        res.extensions_mut().insert(RpcRoute::Legacy);
+        res
+    }
+
+    /// Applies the safety margin to a legacy `eth_estimateGas` response.
+    async fn guard_legacy_estimate(
+        &self,
+        id: Id<'static>,
+        margin_percent: u64,
+        params: Option<&str>,
+        res: MethodResponse,
+    ) -> MethodResponse {
+        let Some(estimate) =
+            params::response_result(res.as_json().get()).and_then(as_str).and_then(parse_quantity)
+        else {
+            return res;
+        };
+
+        let call_gas = params.and_then(call_gas);
+        let bound = match (call_gas, self.local_gas_limit().await) {
+            (Some(call_gas), Some(gas_limit)) => Some(call_gas.min(gas_limit)),
+            (call_gas, gas_limit) => call_gas.or(gas_limit),
+        };
+
+        let metrics = EstimateGasMetrics::default();
+        match guard_estimate(estimate, margin_percent, bound) {
+            Ok(guarded) => {
+                if guarded > estimate {
+                    metrics.adjusted.increment(1);
+                }
+                if guarded > estimate && bound == Some(guarded) {
+                    metrics.capped.increment(1);
+                }
+                debug!(target: "xlayer_legacy_rpc", estimate, guarded, ?bound, "Guarded legacy gas estimate");
+                let payload = ResponsePayload::success(format!("0x{guarded:x}")).into();
+                MethodResponse::response(id, payload, usize::MAX)
+            }
+            Err(bound) => {
+                metrics.rejected.increment(1);
+                MethodResponse::error(
+                    id,
+                    ErrorObject::owned(
+                        CALL_EXECUTION_FAILED_CODE,
+                        format!(
+                            "gas required exceeds allowance ({bound}), legacy estimate {estimate}"
+                        ),
+                        None::<()>,
+                    ),
+                )
+            }
+        }
+    }
+
+    /// Returns the gas limit of the latest local block.
+    async fn local_gas_limit(&self) -> Option<u64> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Header<'a> {
+            gas_limit: &'a str,
+        }
+
+        let res =
+            self.call_local("eth_getBlockByNumber", r#"["latest",false]"#.to_string()).await?;
+        let header = params::response_result(res.as_json().get())?;
+        let header: Header<'_> = serde_json::from_str(header.get()).ok()?;
+        parse_quantity(header.gas_limit)
+    }
+}
+
+/// Returns the `gas` of the call object of `eth_estimateGas` params.
+fn call_gas(params: &str) -> Option<u64> {
+    #[derive(Deserialize)]
+    struct Call<'a> {
+        gas: Option<&'a str>,
+    }
+
+    let call: Call<'_> = serde_json::from_str(params::param_at(params, 0)?.get()).ok()?;
+    parse_quantity(call.gas?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guard_estimate() {
+        assert_eq!(guard_estimate(21_000, 20, None), Ok(25_200));
+        assert_eq!(guard_estimate(21_000, 0, Some(30_000)), Ok(21_000));
+        // Raised estimates are capped at the bound
+        assert_eq!(guard_estimate(21_000, 50, Some(30_000)), Ok(30_000));
+        assert_eq!(guard_estimate(30_000, 20, Some(30_000)), Ok(30_000));
+        assert_eq!(guard_estimate(30_001, 20, Some(30_000)), Err(30_000));
+        assert_eq!(guard_estimate(u64::MAX, 100, None), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn test_call_gas() {
+        assert_eq!(call_gas(r#"[{"to":"0x00","gas":"0x7530"},"0x10"]"#), Some(30_000));
+        assert_eq!(call_gas(r#"[{"to":"0x00"}]"#), None);
+        assert_eq!(call_gas("[]"), None);
+    }
+}
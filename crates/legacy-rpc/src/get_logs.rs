@@ -1,7 +1,8 @@
 //! Handles logic for deciding how to route for `eth_getLogs`.
 //!
 //! We will use (from_block, to_block) params to decide such
-//! routing logic.
+//! routing logic. The range is split by the routing strategy, at the cutoff block for the
+//! cutoff strategy.
 //!
 //! 1. Pure Legacy
 //!    Condition: to_block < cutoff_block
@@ -19,18 +20,28 @@
 //!    Filter:    [from -------------- across -------------- to]
 //!    Results will be sorted (eg. block num, txn index, log index).
 //!
+//! Filters whose addresses were all deployed after the cutoff block have no legacy logs. With
+//! `skip_new_contracts` enabled, pure legacy queries for them return no logs and hybrid queries
+//! are served locally from the cutoff block.
+//!
 //! Special Cases
 //! from_block: earliest
 //!     These get converted to 0
 //! to_block: latest/pending/finalized/safe
 //!     These get converted to u64::MAX
-use crate::{service::is_result_empty, LegacyRpcRouterService};
+use crate::{
+    normalize::normalize_log,
+    params::{as_str, LogsFilterRef},
+    service::is_result_empty,
+    LegacyRpcRouterConfig, LegacyRpcRouterService, RangeRoute, RpcRoute,
+};
 use jsonrpsee::{
     types::{error::INVALID_PARAMS_CODE, ErrorObject},
     MethodResponse,
 };
 use jsonrpsee_types::{Id, Request};
 use serde_json::value::RawValue;
+use std::{collections::BTreeMap, sync::Arc};
 use tracing::debug;
 
 use crate::is_valid_32_bytes_string;
@@ -56,49 +67,29 @@ fn parse_block_number_string(s: &str) -> Option<u64> {
 }
 
 /// Represents params we want to parse for `eth_getLogs`.
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 enum GetLogsParams {
     Range(u64, u64),
-    BlockHash(String),
+    /// Validated block hash filter
+    BlockHash,
 }
 
-/// Parse eth_getLogs params to extract a range or a block hash.
+/// Extract a range or a block hash from an eth_getLogs filter.
 /// If blockHash, a `GetLogsParams::BlockHash` is returned.
 /// If range, a `GetLogsParams::Range(from_block, to_block)` is returned.
 #[inline]
-fn parse_eth_get_logs_params(params: &str) -> Option<GetLogsParams> {
-    let parsed: serde_json::Value = serde_json::from_str(params).ok()?;
-    let arr = parsed.as_array()?;
-
-    // eth_getLogs takes a single filter object parameter
-    if arr.is_empty() {
-        return None;
-    }
-
-    let filter = arr.first()?;
-    let filter_obj = filter.as_object()?;
-
-    if let Some(block_hash) = filter_obj.get("blockHash").and_then(|v| v.as_str()) {
-        if is_valid_32_bytes_string(block_hash) {
-            return Some(GetLogsParams::BlockHash(block_hash.into()));
-        } else {
-            return None;
-        }
+fn get_logs_params(filter: &LogsFilterRef<'_>) -> Option<GetLogsParams> {
+    if let Some(block_hash) = filter.block_hash.and_then(as_str) {
+        return is_valid_32_bytes_string(block_hash).then_some(GetLogsParams::BlockHash);
     }
 
     // Parse fromBlock
-    let from_block = filter_obj
-        .get("fromBlock")
-        .and_then(|v| v.as_str())
-        .and_then(parse_block_number_string)
-        .unwrap_or(u64::MAX);
+    let from_block =
+        filter.from_block.and_then(as_str).and_then(parse_block_number_string).unwrap_or(u64::MAX);
 
     // Parse toBlock
-    let to_block = filter_obj
-        .get("toBlock")
-        .and_then(|v| v.as_str())
-        .and_then(parse_block_number_string)
-        .unwrap_or(u64::MAX);
+    let to_block =
+        filter.to_block.and_then(as_str).and_then(parse_block_number_string).unwrap_or(u64::MAX);
 
     // Fallback to normal routing
     if from_block > to_block {
@@ -110,39 +101,36 @@ fn parse_eth_get_logs_params(params: &str) -> Option<GetLogsParams> {
 
 /// Modify eth_getLogs request to use custom fromBlock and toBlock
 /// Returns a new Request with modified parameters
-fn modify_eth_get_logs_params<'a>(
+///
+/// Only the block fields are replaced, the other params and filter fields are copied verbatim.
+pub(crate) fn modify_eth_get_logs_params<'a>(
     original_req: &Request<'a>,
     from_block: Option<u64>,
     to_block: Option<u64>,
 ) -> Option<Request<'a>> {
     let _p = original_req.params();
     let params_str = _p.as_str()?;
-    let mut parsed: serde_json::Value = serde_json::from_str(params_str).ok()?;
+    let mut params: Vec<&RawValue> = serde_json::from_str(params_str).ok()?;
+    let mut filter: BTreeMap<&str, &RawValue> = serde_json::from_str(params.first()?.get()).ok()?;
 
-    let arr = parsed.as_array_mut()?;
-    if arr.is_empty() {
-        return None;
-    }
-
-    let filter = arr.get_mut(0)?;
-    let filter_obj = filter.as_object_mut()?;
+    let quantity = |block: u64| RawValue::from_string(format!(r#""0x{block:x}""#));
+    let from_block = from_block.map(quantity).transpose().ok()?;
+    let to_block = to_block.map(quantity).transpose().ok()?;
 
     // Modify fromBlock if provided
-    if let Some(from) = from_block {
-        filter_obj
-            .insert("fromBlock".to_string(), serde_json::Value::String(format!("0x{from:x}")));
+    if let Some(from) = &from_block {
+        filter.insert("fromBlock", from);
     }
 
     // Modify toBlock if provided
-    if let Some(to) = to_block {
-        filter_obj.insert("toBlock".to_string(), serde_json::Value::String(format!("0x{to:x}")));
+    if let Some(to) = &to_block {
+        filter.insert("toBlock", to);
     }
 
-    // Serialize back to string
-    let new_params_str = serde_json::to_string(&parsed).ok()?;
-
-    // Create new RawValue
-    let params_raw = RawValue::from_string(new_params_str).ok()?;
+    // Serialize back into new params
+    let filter = serde_json::value::to_raw_value(&filter).ok()?;
+    params[0] = &*filter;
+    let params_raw = serde_json::value::to_raw_value(&params).ok()?;
 
     // Create new Request with modified params
     Some(Request::owned(
@@ -152,7 +140,8 @@ fn modify_eth_get_logs_params<'a>(
     ))
 }
 
-/// Merge two eth_getLogs responses
+/// Merge two eth_getLogs responses. Logs are normalized to the local formatting so the merged
+/// array is consistent.
 fn merge_eth_get_logs_responses(
     legacy_response: MethodResponse,
     local_response: MethodResponse,
@@ -182,6 +171,7 @@ fn merge_eth_get_logs_responses(
     // Merge the arrays
     let mut merged_logs = legacy_result;
     merged_logs.extend(local_result);
+    merged_logs.iter_mut().for_each(normalize_log);
 
     // Sort by block number, then transaction index, then log index
     merged_logs.sort_by(|a, b| {
@@ -211,13 +201,12 @@ fn merge_eth_get_logs_responses(
 
 /// Handle eth_getLogs routing logic.
 ///
-/// Determines whether to route to legacy, local, or use hybrid approach
-/// based on the block range in the request.
+/// The filter is parsed once, borrowing from the request params. Requests for a block range near
+/// the cutoff are sampled for shadow comparison.
 pub(crate) async fn handle_eth_get_logs<S>(
     req: Request<'_>,
-    client: reqwest::Client,
-    config: std::sync::Arc<crate::LegacyRpcRouterConfig>,
-    inner: S,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
 ) -> MethodResponse
 where
     S: jsonrpsee::server::middleware::rpc::RpcServiceT<MethodResponse = MethodResponse>
@@ -226,7 +215,6 @@ where
         + Clone
         + 'static,
 {
-    let service = LegacyRpcRouterService { inner: inner.clone(), config, client };
     let params_ref = req.params();
     let Some(params) = params_ref.as_str() else {
         return MethodResponse::error(
@@ -235,64 +223,127 @@ where
         );
     };
 
-    let cutoff_block = service.config.cutoff_block;
-
-    match parse_eth_get_logs_params(params) {
-        Some(GetLogsParams::Range(from_block, to_block)) => {
-            if to_block < cutoff_block {
-                debug!(
-                    target:"xlayer_legacy_rpc",
-                    "eth_getLogs pure legacy routing (from_block = {}, to_block = {})",
-                    from_block, to_block
-                );
-                // Pure legacy
-                return service.forward_to_legacy(req).await;
-            } else if from_block >= cutoff_block {
-                debug!(
-                    target:"xlayer_legacy_rpc",
-                    "eth_getLogs pure local routing (from_block = {}, to_block = {})",
-                    from_block, to_block
-                );
-                // Pure local
-                return inner.call(req).await;
-            } else {
-                // Hybrid: split into two requests
+    let filter = LogsFilterRef::parse(params);
+    let logs_params = filter.as_ref().and_then(get_logs_params);
+    let (new_contracts, shadow) = match (logs_params, &filter) {
+        (Some(GetLogsParams::Range(from_block, to_block)), Some(filter)) => (
+            config.routing_strategy().routes_block_to_legacy(&config, from_block)
+                && service.deployed_after_cutoff(&config, filter).await,
+            service.shadow_sampler.sample(&config, Some((from_block, to_block))),
+        ),
+        _ => (false, false),
+    };
+    let shadow = shadow.then(|| req.clone().into_owned());
 
-                // 1. Legacy request: fromBlock to cutoff-1
-                let legacy_req =
-                    modify_eth_get_logs_params(&req, Some(from_block), Some(cutoff_block - 1));
+    let res =
+        route_eth_get_logs(req, logs_params, new_contracts, service.clone(), config.clone()).await;
+    if let Some(shadow) = shadow {
+        service.shadow_compare(config, shadow, &res);
+    }
+    res
+}
 
-                // 2. Local request: cutoff to toBlock
-                let local_req =
-                    modify_eth_get_logs_params(&req, Some(cutoff_block), Some(to_block));
+/// Determines whether to route to legacy, local, or use hybrid approach
+/// based on the block range in the request.
+async fn route_eth_get_logs<S>(
+    req: Request<'_>,
+    logs_params: Option<GetLogsParams>,
+    new_contracts: bool,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
+) -> MethodResponse
+where
+    S: jsonrpsee::server::middleware::rpc::RpcServiceT<MethodResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    let inner = service.inner.clone();
 
-                if let (Some(legacy_req), Some(local_req)) = (legacy_req, local_req) {
+    match logs_params {
+        Some(GetLogsParams::Range(from_block, to_block)) => {
+            match config.routing_strategy().route_range(&config, from_block, to_block) {
+                RangeRoute::Legacy if new_contracts => {
                     debug!(
                         target:"xlayer_legacy_rpc",
-                        "eth_getLogs hybrid routing (from_block = {}, {}) and ({}, to_block = {})",
-                        from_block,
-                        cutoff_block - 1,
-                        cutoff_block,
-                        to_block
+                        "eth_getLogs no logs before deployment (from_block = {}, to_block = {})",
+                        from_block, to_block
                     );
-
-                    // Call both and merge results
-                    let (legacy_response, local_response) = tokio::join!(
-                        async { service.forward_to_legacy(legacy_req).await },
-                        async { inner.call(local_req).await }
+                    let payload =
+                        jsonrpsee_types::ResponsePayload::success(Vec::<()>::new()).into();
+                    MethodResponse::response(req.id(), payload, usize::MAX)
+                }
+                RangeRoute::Legacy => {
+                    debug!(
+                        target:"xlayer_legacy_rpc",
+                        "eth_getLogs pure legacy routing (from_block = {}, to_block = {})",
+                        from_block, to_block
                     );
-
-                    // Merge the results
-                    return merge_eth_get_logs_responses(legacy_response, local_response, req.id());
+                    service.forward_to_legacy(&config, req).await
+                }
+                RangeRoute::Local => {
+                    debug!(
+                        target:"xlayer_legacy_rpc",
+                        "eth_getLogs pure local routing (from_block = {}, to_block = {})",
+                        from_block, to_block
+                    );
+                    inner.call(req).await
+                }
+                RangeRoute::Split { local_from } => {
+                    if new_contracts
+                        && let Some(local_req) =
+                            modify_eth_get_logs_params(&req, Some(local_from), Some(to_block))
+                    {
+                        debug!(
+                            target:"xlayer_legacy_rpc",
+                            "eth_getLogs local routing after deployment ({}, to_block = {})",
+                            local_from, to_block
+                        );
+                        return inner.call(local_req).await;
+                    }
+
+                    // Hybrid: split into two requests
+
+                    // 1. Legacy request: fromBlock to the last legacy block
+                    let legacy_req =
+                        modify_eth_get_logs_params(&req, Some(from_block), Some(local_from - 1));
+
+                    // 2. Local request: the first local block to toBlock
+                    let local_req =
+                        modify_eth_get_logs_params(&req, Some(local_from), Some(to_block));
+
+                    if let (Some(legacy_req), Some(local_req)) = (legacy_req, local_req) {
+                        debug!(
+                            target:"xlayer_legacy_rpc",
+                            "eth_getLogs hybrid routing (from_block = {}, {}) and ({}, to_block = {})",
+                            from_block,
+                            local_from - 1,
+                            local_from,
+                            to_block
+                        );
+
+                        // Call both and merge results
+                        let (legacy_response, local_response) = tokio::join!(
+                            async { service.forward_to_legacy(&config, legacy_req).await },
+                            async { inner.call(local_req).await }
+                        );
+
+                        // Merge the results
+                        let mut response =
+                            merge_eth_get_logs_responses(legacy_response, local_response, req.id());
+                        response.extensions_mut().insert(RpcRoute::Hybrid);
+                        return response;
+                    }
+
+                    debug!(target:"xlayer_legacy_rpc", "No legacy routing for method = eth_getLogs");
+
+                    // Fallback to normal if modification failed
+                    inner.call(req).await
                 }
-
-                debug!(target:"xlayer_legacy_rpc", "No legacy routing for method = eth_getLogs");
-
-                // Fallback to normal if modification failed
-                return inner.call(req).await;
             }
         }
-        Some(GetLogsParams::BlockHash(_block_hash)) => {
+        Some(GetLogsParams::BlockHash) => {
             debug!(target:"xlayer_legacy_rpc", "method = eth_getLogs, testing locally first...");
             let res = inner.call(req.clone()).await;
             if res.is_success() && !is_result_empty(&res) {
@@ -300,7 +351,7 @@ where
                 res
             } else {
                 debug!(target:"xlayer_legacy_rpc", "method = eth_getLogs, forward to legacy (empty or error)");
-                service.forward_to_legacy(req).await
+                service.forward_to_legacy(&config, req).await
             }
         }
         _ => {
@@ -312,7 +363,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::get_logs::GetLogsParams;
+    use crate::{get_logs::GetLogsParams, params::LogsFilterRef};
     use jsonrpsee::MethodResponse;
     use jsonrpsee_types::{Id, Request};
     use serde_json::value::RawValue;
@@ -335,9 +386,7 @@ mod tests {
             // Blockhash
             (
                 r#"[{"blockHash":"0x8c83240f457f709b4574dd57afb656242418ea481325ea3c284c4ba144c1e032"}]"#,
-                Some(GetLogsParams::BlockHash(
-                    "0x8c83240f457f709b4574dd57afb656242418ea481325ea3c284c4ba144c1e032".into(),
-                )),
+                Some(GetLogsParams::BlockHash),
             ),
             (
                 // invalid block hash
@@ -347,8 +396,9 @@ mod tests {
         ];
 
         for (params, expected) in cases {
-            let result = super::parse_eth_get_logs_params(params);
-            assert_eq!(result, expected);
+            let filter = LogsFilterRef::parse(params);
+            let result = filter.as_ref().and_then(super::get_logs_params);
+            assert_eq!(result, expected, "{params}");
         }
     }
 
@@ -455,4 +505,53 @@ mod tests {
         assert_eq!(result[2].get("blockNumber").unwrap().as_str(), Some("0x65"));
         assert_eq!(result[3].get("blockNumber").unwrap().as_str(), Some("0x66"));
     }
+
+    #[test]
+    fn test_merge_eth_get_logs_responses_normalizes_legacy_logs() {
+        // Legacy log without `removed`, with a checksummed address and zero padded quantities
+        let legacy_logs: serde_json::Value = serde_json::from_str(
+            r#"[{
+                "address": "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                "topics": [],
+                "data": "0x",
+                "blockNumber": "0x0063",
+                "logIndex": "0x00"
+            }]"#,
+        )
+        .unwrap();
+        let local_logs: serde_json::Value = serde_json::from_str(
+            r#"[{
+                "address": "0x5fbdb2315678afecb367f032d93f642f64180aa3",
+                "topics": [],
+                "data": "0x",
+                "blockNumber": "0x64",
+                "logIndex": "0x0",
+                "removed": false
+            }]"#,
+        )
+        .unwrap();
+
+        let legacy_response = MethodResponse::response(
+            Id::Number(1),
+            jsonrpsee_types::ResponsePayload::success(&legacy_logs).into(),
+            usize::MAX,
+        );
+        let local_response = MethodResponse::response(
+            Id::Number(1),
+            jsonrpsee_types::ResponsePayload::success(&local_logs).into(),
+            usize::MAX,
+        );
+
+        let merged =
+            super::merge_eth_get_logs_responses(legacy_response, local_response, Id::Number(1));
+        let merged_parsed: serde_json::Value =
+            serde_json::from_str(merged.as_json().get()).unwrap();
+        let result = merged_parsed.get("result").unwrap().as_array().unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0]["blockNumber"], "0x63");
+        assert_eq!(result[0]["address"], result[1]["address"]);
+        assert_eq!(result[0]["logIndex"], "0x0");
+        assert_eq!(result[0]["removed"], false);
+    }
 }
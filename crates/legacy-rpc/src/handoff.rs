@@ -0,0 +1,296 @@
+//! Check of the handoff between the legacy chain and the local chain.
+//!
+//! The local chain starts at its genesis, `legacyXLayerBlock`, on top of the last legacy block.
+//! Routing by block number is only sound if the legacy node ends exactly there: a legacy node that
+//! is behind, kept producing blocks or belongs to another network serves blocks that are not part
+//! of the local chain's history, and responses merging both nodes mix two chains. When routing is
+//! configured, the latest block of the legacy node is compared with the cutoff block and the
+//! parent hash of the local genesis. On a discrepancy legacy routing is suspended, requests are
+//! served by the local node only, and the discrepancy is logged and reported by `xlayer_health`.
+//!
+//! Routing is kept while the legacy node can't be reached, the check is retried until it gets an
+//! answer and runs again whenever the legacy endpoint or cutoff block is reloaded, or legacy
+//! routing is enabled again.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use alloy_primitives::B256;
+use jsonrpsee::{
+    types::{Id, Request},
+    MethodResponse,
+};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::{forward_request, LegacyRpcRouterConfig};
+
+/// Delay between attempts to query an unreachable legacy node.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Genesis of the local chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LocalGenesis {
+    /// Number of the genesis block, `legacyXLayerBlock` on chains migrated from a legacy chain
+    pub number: u64,
+    /// Hash of the last legacy block, zero if the genesis doesn't reference one
+    pub parent_hash: B256,
+}
+
+/// Outcome of the handoff check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "status")]
+pub enum LegacyHandoffStatus {
+    /// Legacy routing is disabled, the legacy node is not checked
+    Disabled,
+    /// The legacy node has not answered yet, requests are routed meanwhile
+    #[serde(rename_all = "camelCase")]
+    Pending { last_error: Option<String> },
+    /// The legacy node ends right before the local genesis
+    Verified,
+    /// The legacy node disagrees with the local chain, legacy routing is suspended
+    Mismatch { reason: String },
+}
+
+/// Result of the handoff check, shared by the router services.
+#[derive(Debug)]
+pub struct LegacyHandoff {
+    status: RwLock<LegacyHandoffStatus>,
+    suspended: AtomicBool,
+}
+
+impl Default for LegacyHandoff {
+    fn default() -> Self {
+        Self {
+            status: RwLock::new(LegacyHandoffStatus::Pending { last_error: None }),
+            suspended: AtomicBool::new(false),
+        }
+    }
+}
+
+impl LegacyHandoff {
+    /// Returns the outcome of the last check.
+    pub fn status(&self) -> LegacyHandoffStatus {
+        self.status.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Returns `true` if legacy routing is suspended by a mismatch.
+    pub fn is_suspended(&self) -> bool {
+        self.suspended.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_status(&self, status: LegacyHandoffStatus) {
+        self.suspended
+            .store(matches!(status, LegacyHandoffStatus::Mismatch { .. }), Ordering::Relaxed);
+        *self.status.write().unwrap_or_else(|e| e.into_inner()) = status;
+    }
+
+    /// Checks the legacy node of every configuration applied to the router until the
+    /// configuration is dropped.
+    pub async fn run(
+        self: Arc<Self>,
+        mut updates: watch::Receiver<Arc<LegacyRpcRouterConfig>>,
+        client: Client,
+        genesis: LocalGenesis,
+    ) {
+        let mut checked: Option<(String, u64)> = None;
+        loop {
+            let current = updates.borrow_and_update().clone();
+            let target = (current.legacy_endpoint.clone(), current.cutoff_block);
+            if !current.enabled {
+                self.set_status(LegacyHandoffStatus::Disabled);
+                checked = None;
+            } else if checked.as_ref() != Some(&target) {
+                tokio::select! {
+                    _ = self.check(&client, &current, genesis) => checked = Some(target),
+                    res = updates.changed() => {
+                        if res.is_err() {
+                            return;
+                        }
+                        continue;
+                    }
+                }
+            }
+            if updates.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Queries the legacy node until it answers and records the outcome.
+    async fn check(&self, client: &Client, config: &LegacyRpcRouterConfig, genesis: LocalGenesis) {
+        self.set_status(LegacyHandoffStatus::Pending { last_error: None });
+        let head = loop {
+            match latest_legacy_block(client, config).await {
+                Ok(head) => break head,
+                Err(err) => {
+                    warn!(target: "xlayer_legacy_rpc", endpoint = %config.legacy_endpoint, %err, "Failed to query legacy head, retrying");
+                    self.set_status(LegacyHandoffStatus::Pending { last_error: Some(err) });
+                    tokio::time::sleep(RETRY_INTERVAL).await;
+                }
+            }
+        };
+
+        let status = compare(head, config.cutoff_block, genesis);
+        match &status {
+            LegacyHandoffStatus::Mismatch { reason } => {
+                error!(target: "xlayer_legacy_rpc", endpoint = %config.legacy_endpoint, %reason, "Legacy node doesn't match the local chain, legacy routing suspended");
+            }
+            _ => {
+                info!(target: "xlayer_legacy_rpc", endpoint = %config.legacy_endpoint, head = head.0, "Legacy node ends at the local genesis");
+            }
+        }
+        self.set_status(status);
+    }
+}
+
+/// Block of the legacy node, as returned by `eth_getBlockByNumber`.
+#[derive(Deserialize)]
+struct LegacyBlock {
+    number: String,
+    hash: B256,
+}
+
+/// Returns the number and hash of the latest legacy block.
+async fn latest_legacy_block(
+    client: &Client,
+    config: &LegacyRpcRouterConfig,
+) -> Result<(u64, B256), String> {
+    let params =
+        RawValue::from_string(r#"["latest",false]"#.to_string()).map_err(|e| e.to_string())?;
+    let req = Request::owned("eth_getBlockByNumber".to_string(), Some(params), Id::Number(1));
+    let res = forward_request(client, &config.legacy_endpoint, config.timeout, "Legacy", req).await;
+    parse_latest_block(&res)
+}
+
+fn parse_latest_block(res: &MethodResponse) -> Result<(u64, B256), String> {
+    #[derive(Deserialize)]
+    struct Response {
+        result: Option<serde_json::Value>,
+        error: Option<serde_json::Value>,
+    }
+
+    let response: Response =
+        serde_json::from_str(res.as_json().get()).map_err(|e| e.to_string())?;
+    if let Some(error) = response.error {
+        return Err(error.to_string());
+    }
+    let block =
+        response.result.ok_or_else(|| "legacy node returned no latest block".to_string())?;
+    let block: LegacyBlock = serde_json::from_value(block).map_err(|e| e.to_string())?;
+    let number = u64::from_str_radix(block.number.trim_start_matches("0x"), 16)
+        .map_err(|e| format!("invalid legacy block number {}: {e}", block.number))?;
+    Ok((number, block.hash))
+}
+
+/// Compares the latest legacy block with the local genesis.
+fn compare(head: (u64, B256), cutoff_block: u64, genesis: LocalGenesis) -> LegacyHandoffStatus {
+    let (number, hash) = head;
+    if cutoff_block != genesis.number {
+        return LegacyHandoffStatus::Mismatch {
+            reason: format!(
+                "cutoff block {cutoff_block} differs from the local genesis block {}",
+                genesis.number
+            ),
+        };
+    }
+    let last_legacy_block = genesis.number.saturating_sub(1);
+    if number != last_legacy_block {
+        let (distance, direction) = if number < last_legacy_block {
+            (last_legacy_block - number, "behind")
+        } else {
+            (number - last_legacy_block, "ahead of")
+        };
+        return LegacyHandoffStatus::Mismatch {
+            reason: format!(
+                "legacy head {number} is {distance} blocks {direction} the last legacy block {last_legacy_block}"
+            ),
+        };
+    }
+    if !genesis.parent_hash.is_zero() && hash != genesis.parent_hash {
+        return LegacyHandoffStatus::Mismatch {
+            reason: format!(
+                "legacy block {number} has hash {hash}, the local genesis expects {}",
+                genesis.parent_hash
+            ),
+        };
+    }
+    LegacyHandoffStatus::Verified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GENESIS: LocalGenesis =
+        LocalGenesis { number: 1_000, parent_hash: B256::with_last_byte(0xaa) };
+
+    #[test]
+    fn test_compare_legacy_head() {
+        let hash = GENESIS.parent_hash;
+        assert_eq!(compare((999, hash), 1_000, GENESIS), LegacyHandoffStatus::Verified);
+
+        let LegacyHandoffStatus::Mismatch { reason } = compare((990, hash), 1_000, GENESIS) else {
+            panic!("expected mismatch");
+        };
+        assert_eq!(reason, "legacy head 990 is 9 blocks behind the last legacy block 999");
+        let LegacyHandoffStatus::Mismatch { reason } = compare((1_001, hash), 1_000, GENESIS)
+        else {
+            panic!("expected mismatch");
+        };
+        assert_eq!(reason, "legacy head 1001 is 2 blocks ahead of the last legacy block 999");
+
+        assert!(matches!(
+            compare((999, B256::with_last_byte(1)), 1_000, GENESIS),
+            LegacyHandoffStatus::Mismatch { .. }
+        ));
+        assert!(matches!(compare((999, hash), 900, GENESIS), LegacyHandoffStatus::Mismatch { .. }));
+
+        // Genesis without a parent hash, only the number is checked
+        let genesis = LocalGenesis { parent_hash: B256::ZERO, ..GENESIS };
+        assert_eq!(
+            compare((999, B256::with_last_byte(1)), 1_000, genesis),
+            LegacyHandoffStatus::Verified
+        );
+    }
+
+    #[test]
+    fn test_suspended_on_mismatch() {
+        let handoff = LegacyHandoff::default();
+        assert!(!handoff.is_suspended());
+        handoff.set_status(LegacyHandoffStatus::Mismatch { reason: "mismatch".to_string() });
+        assert!(handoff.is_suspended());
+        handoff.set_status(LegacyHandoffStatus::Verified);
+        assert!(!handoff.is_suspended());
+
+        let status = serde_json::to_value(LegacyHandoffStatus::Pending { last_error: None });
+        assert_eq!(status.unwrap(), serde_json::json!({"status": "pending", "lastError": null}));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_routing() {
+        let handoff = Arc::new(LegacyHandoff::default());
+        let (tx, rx) = watch::channel(Arc::new(LegacyRpcRouterConfig::default()));
+        let task = tokio::spawn(handoff.clone().run(rx, Client::new(), GENESIS));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(handoff.status(), LegacyHandoffStatus::Disabled);
+
+        // Enabling routing checks the legacy node again
+        tx.send_replace(Arc::new(LegacyRpcRouterConfig {
+            enabled: true,
+            legacy_endpoint: "http://127.0.0.1:1".to_string(),
+            ..Default::default()
+        }));
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(matches!(handoff.status(), LegacyHandoffStatus::Pending { .. }));
+        task.abort();
+    }
+}
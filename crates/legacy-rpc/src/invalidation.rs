@@ -0,0 +1,207 @@
+//! Cache invalidation driven by the canonical chain.
+//!
+//! Several caches hold data derived from recent blocks: the block hash index of the legacy
+//! routing, the finalized height of the call result cache and the suggested priority fee. Each
+//! following the canonical state on its own, they converge at different times after a reorg, or
+//! not at all if one of them misses the notification. The [`InvalidationBus`] reads the canonical
+//! state notifications and the finalized head once and hands typed [`Invalidation`] events to
+//! every registered cache in chain order, so all caches have applied a reorg before the next
+//! notification is read.
+
+use std::{ops::RangeInclusive, sync::Arc};
+
+use alloy_primitives::{BlockNumber, B256};
+use futures::{stream, Stream, StreamExt};
+use metrics::Counter;
+use reth_chain_state::CanonStateNotification;
+use reth_metrics::Metrics;
+use reth_primitives_traits::{NodePrimitives, SealedHeader};
+use tracing::debug;
+
+/// Change of the canonical chain that may invalidate cached data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invalidation {
+    /// A block was appended to the canonical chain
+    NewHead { number: BlockNumber, hash: B256 },
+    /// Blocks were removed from the canonical chain by a reorg, in ascending order
+    Reorged { reverted: Vec<(BlockNumber, B256)> },
+    /// The finalized head advanced
+    Finalized { number: BlockNumber, hash: B256 },
+}
+
+impl Invalidation {
+    /// Returns the events of a canonical state notification: the reverted blocks, if any, then
+    /// every committed block in ascending order.
+    pub fn from_notification<N: NodePrimitives>(
+        notification: &CanonStateNotification<N>,
+    ) -> Vec<Self> {
+        let reverted = notification.reverted().map(|chain| Self::Reorged {
+            reverted: chain
+                .blocks()
+                .iter()
+                .map(|(number, block)| (*number, block.hash()))
+                .collect(),
+        });
+        let committed = notification
+            .committed()
+            .blocks()
+            .iter()
+            .map(|(number, block)| Self::NewHead { number: *number, hash: block.hash() });
+        reverted.into_iter().chain(committed).collect()
+    }
+
+    /// Returns the numbers of the blocks removed by a reorg.
+    pub fn reorged_range(&self) -> Option<RangeInclusive<BlockNumber>> {
+        match self {
+            Self::Reorged { reverted } => Some(reverted.first()?.0..=reverted.last()?.0),
+            _ => None,
+        }
+    }
+}
+
+/// Cache invalidated by changes of the canonical chain.
+pub trait InvalidationHandler: Send + Sync {
+    /// Applies an event. Events are applied in chain order on the task of the bus, handlers must
+    /// not block.
+    fn invalidate(&self, event: &Invalidation);
+}
+
+/// Invalidation bus metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_legacy_rpc_invalidation")]
+struct InvalidationMetrics {
+    /// Number of canonical blocks published
+    new_heads: Counter,
+    /// Number of reorgs published
+    reorgs: Counter,
+    /// Number of blocks removed by the published reorgs
+    reorged_blocks: Counter,
+    /// Number of finalized head advances published
+    finalized: Counter,
+}
+
+/// Publishes the changes of the canonical chain to the registered caches.
+#[derive(Default)]
+pub struct InvalidationBus {
+    handlers: Vec<(&'static str, Arc<dyn InvalidationHandler>)>,
+    metrics: InvalidationMetrics,
+}
+
+impl InvalidationBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a cache under the given name.
+    pub fn with_handler(
+        mut self,
+        name: &'static str,
+        handler: Arc<dyn InvalidationHandler>,
+    ) -> Self {
+        self.handlers.push((name, handler));
+        self
+    }
+
+    /// Returns the names of the registered caches.
+    pub fn handlers(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.handlers.iter().map(|(name, _)| *name)
+    }
+
+    /// Hands an event to every registered cache, in registration order.
+    pub fn publish(&self, event: &Invalidation) {
+        match event {
+            Invalidation::NewHead { .. } => self.metrics.new_heads.increment(1),
+            Invalidation::Reorged { reverted } => {
+                self.metrics.reorgs.increment(1);
+                self.metrics.reorged_blocks.increment(reverted.len() as u64);
+            }
+            Invalidation::Finalized { .. } => self.metrics.finalized.increment(1),
+        }
+        for (_, handler) in &self.handlers {
+            handler.invalidate(event);
+        }
+    }
+
+    /// Publishes the canonical state notifications and the advances of the finalized head until
+    /// both streams end.
+    pub async fn run<N, C, F>(self, canon_state: C, finalized: F)
+    where
+        N: NodePrimitives,
+        C: Stream<Item = CanonStateNotification<N>>,
+        F: Stream<Item = SealedHeader<N::BlockHeader>>,
+    {
+        let canon_state =
+            canon_state.map(|notification| Invalidation::from_notification(&notification));
+        let finalized = finalized.map(|header| {
+            let block = header.num_hash();
+            vec![Invalidation::Finalized { number: block.number, hash: block.hash }]
+        });
+        let mut events = std::pin::pin!(stream::select(canon_state, finalized));
+
+        let mut last_finalized = None;
+        while let Some(batch) = events.next().await {
+            for event in batch {
+                if let Invalidation::Finalized { number, .. } = event {
+                    // Skip repeated reports of the same finalized head
+                    if last_finalized.is_some_and(|last| number <= last) {
+                        continue;
+                    }
+                    last_finalized = Some(number);
+                }
+                if let Some(range) = event.reorged_range() {
+                    debug!(target: "xlayer_legacy_rpc", first = range.start(), last = range.end(), "Invalidating reorged blocks");
+                }
+                self.publish(&event);
+            }
+        }
+        debug!(target: "xlayer_legacy_rpc", "canonical state stream closed, invalidation bus stopped");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<Invalidation>>);
+
+    impl InvalidationHandler for Recorder {
+        fn invalidate(&self, event: &Invalidation) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    #[test]
+    fn test_publish_to_all_handlers() {
+        let first = Arc::new(Recorder::default());
+        let second = Arc::new(Recorder::default());
+        let bus = InvalidationBus::new()
+            .with_handler("first", first.clone())
+            .with_handler("second", second.clone());
+        assert_eq!(bus.handlers().collect::<Vec<_>>(), ["first", "second"]);
+
+        let events = [
+            Invalidation::NewHead { number: 10, hash: B256::repeat_byte(0x0a) },
+            Invalidation::Reorged {
+                reverted: vec![(9, B256::repeat_byte(0x09)), (10, B256::repeat_byte(0x0a))],
+            },
+            Invalidation::Finalized { number: 5, hash: B256::repeat_byte(0x05) },
+        ];
+        events.iter().for_each(|event| bus.publish(event));
+
+        for recorder in [first, second] {
+            assert_eq!(*recorder.0.lock().unwrap(), events);
+        }
+    }
+
+    #[test]
+    fn test_reorged_range() {
+        let reorg = Invalidation::Reorged {
+            reverted: vec![(9, B256::repeat_byte(0x09)), (10, B256::repeat_byte(0x0a))],
+        };
+        assert_eq!(reorg.reorged_range(), Some(9..=10));
+        assert_eq!(Invalidation::Reorged { reverted: vec![] }.reorged_range(), None);
+        assert_eq!(Invalidation::NewHead { number: 1, hash: B256::ZERO }.reorged_range(), None);
+    }
+}
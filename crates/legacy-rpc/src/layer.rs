@@ -1,16 +1,25 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc};
 
 use reqwest::Client;
 use tower::Layer;
 use tracing::info;
 
-use crate::{LegacyRpcRouterConfig, LegacyRpcRouterService};
+use crate::{
+    deployments::DeploymentCache, shadow::ShadowSampler, tx_lookup::TxLookups, LegacyHandoff,
+    LegacyRpcRouterConfig, LegacyRpcRouterConfigHandle, LegacyRpcRouterService, LocalGenesis,
+    RecentBlocks,
+};
 
 /// Layer that creates the routing middleware
 #[derive(Clone)]
 pub struct LegacyRpcRouterLayer {
-    config: Arc<LegacyRpcRouterConfig>,
+    config: LegacyRpcRouterConfigHandle,
     client: Client,
+    shadow_sampler: Arc<ShadowSampler>,
+    deployments: Arc<DeploymentCache>,
+    recent_blocks: Arc<RecentBlocks>,
+    tx_lookups: Arc<TxLookups>,
+    handoff: Arc<LegacyHandoff>,
 }
 
 impl LegacyRpcRouterLayer {
@@ -24,7 +33,41 @@ impl LegacyRpcRouterLayer {
             info!(target:"xlayer_legacy_rpc", "xlayer legacy rpc enabled");
         }
 
-        Self { config: Arc::new(config), client }
+        Self {
+            config: LegacyRpcRouterConfigHandle::new(config),
+            client,
+            shadow_sampler: Default::default(),
+            deployments: Default::default(),
+            recent_blocks: Default::default(),
+            tx_lookups: Default::default(),
+            handoff: Default::default(),
+        }
+    }
+
+    /// Returns the handle used to reload the routing configuration at runtime.
+    pub fn config_handle(&self) -> LegacyRpcRouterConfigHandle {
+        self.config.clone()
+    }
+
+    /// Returns the index of recent canonical blocks, kept in sync by registering it with an
+    /// [`InvalidationBus`](crate::invalidation::InvalidationBus).
+    pub fn recent_blocks(&self) -> Arc<RecentBlocks> {
+        self.recent_blocks.clone()
+    }
+
+    /// Returns the check of the legacy node against the local chain, run by
+    /// [`LegacyHandoff::run`]. Legacy routing is suspended if it fails.
+    pub fn handoff(&self) -> Arc<LegacyHandoff> {
+        self.handoff.clone()
+    }
+
+    /// Checks the legacy node against the local genesis until the layer's configuration is
+    /// dropped.
+    pub fn check_handoff(
+        &self,
+        genesis: LocalGenesis,
+    ) -> impl Future<Output = ()> + Send + 'static {
+        self.handoff.clone().run(self.config.subscribe(), self.client.clone(), genesis)
     }
 }
 
@@ -32,6 +75,15 @@ impl<S> Layer<S> for LegacyRpcRouterLayer {
     type Service = LegacyRpcRouterService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        LegacyRpcRouterService { inner, config: self.config.clone(), client: self.client.clone() }
+        LegacyRpcRouterService {
+            inner,
+            config: self.config.clone(),
+            client: self.client.clone(),
+            shadow_sampler: self.shadow_sampler.clone(),
+            deployments: self.deployments.clone(),
+            recent_blocks: self.recent_blocks.clone(),
+            tx_lookups: self.tx_lookups.clone(),
+            handoff: self.handoff.clone(),
+        }
     }
 }
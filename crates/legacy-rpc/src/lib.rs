@@ -1,8 +1,27 @@
+pub mod admin;
+pub mod block_by_timestamp;
+pub mod canon_mux;
+mod deployments;
+mod estimate_gas;
 pub mod get_logs;
+mod handoff;
+pub mod invalidation;
 pub mod layer;
+pub mod logs_paged;
+pub mod middleware;
+mod normalize;
+pub mod pagination;
+pub mod params;
+pub mod pending;
+mod recent_blocks;
 pub mod service;
+mod shadow;
+mod strategy;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+mod tx_lookup;
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use jsonrpsee::{
     core::middleware::RpcServiceT,
@@ -14,7 +33,40 @@ use jsonrpsee::{
 };
 use jsonrpsee_types::Id;
 use reqwest::Client;
+use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
+use tokio::sync::watch;
+
+pub use handoff::{LegacyHandoff, LegacyHandoffStatus, LocalGenesis};
+pub use normalize::ReceiptCompatProfile;
+pub use recent_blocks::RecentBlocks;
+pub use shadow::ShadowCompareConfig;
+pub use strategy::{
+    AlwaysLocalStrategy, CutoffStrategy, LookupOrder, RangeRoute, RoutingStrategy,
+    RoutingStrategyKind, ShadowStrategy,
+};
+pub use tx_lookup::TxLookupConfig;
+
+/// Route taken by a call through the legacy router, attached to the extensions of its response.
+///
+/// Responses without a route were served locally.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RpcRoute {
+    /// Served by the local node
+    Local,
+    /// Forwarded to the legacy node
+    Legacy,
+    /// Split between the legacy and the local node at the cutoff block
+    Hybrid,
+}
+
+impl RpcRoute {
+    /// Returns the route of the given response.
+    pub fn of(response: &MethodResponse) -> Self {
+        response.extensions().get::<Self>().copied().unwrap_or(Self::Local)
+    }
+}
 
 /// Configuration for legacy RPC routing
 #[derive(Clone, Debug)]
@@ -22,83 +74,339 @@ pub struct LegacyRpcRouterConfig {
     pub enabled: bool,
     pub legacy_endpoint: String,
     pub cutoff_block: u64,
-    pub timeout: std::time::Duration,
+    /// Strategy deciding which node serves a request
+    pub strategy: RoutingStrategyKind,
+    pub timeout: Duration,
+    /// How receipts served by the legacy node are reconciled with local receipts
+    pub receipt_profile: ReceiptCompatProfile,
+    /// Comparison of local and legacy responses near the cutoff block
+    pub shadow: ShadowCompareConfig,
+    /// Skip the legacy node for `eth_getLogs` filters whose addresses were all deployed after
+    /// the cutoff block
+    pub skip_new_contracts: bool,
+    /// Names of methods on the legacy node, keyed by the local method name. Calls forwarded to the
+    /// legacy node use the legacy name, and mapped custom methods fall back to the legacy node
+    /// when the local node fails or returns an empty result.
+    pub method_names: HashMap<String, String>,
+    /// Patterns of methods forwarded to the legacy node when the local node doesn't implement
+    /// them, either a method name or a prefix ending in `*`, e.g. `zkevm_*`.
+    pub forward_methods: Vec<String>,
+    /// Fallback for transaction lookups missed by a pruned local hash index
+    pub tx_lookup: TxLookupConfig,
+    /// Order of the nodes asked for hash lookups, keyed by method, local first if unset
+    pub lookup_order: HashMap<String, LookupOrder>,
+    /// Safety margin in percent added to `eth_estimateGas` results of the legacy node, which are
+    /// then checked against the local upper bound. Legacy estimates are returned as is if unset.
+    pub estimate_gas_margin: Option<u64>,
+}
+
+impl Default for LegacyRpcRouterConfig {
+    /// Legacy routing disabled, with the defaults of the node arguments.
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            legacy_endpoint: String::new(),
+            cutoff_block: 0,
+            strategy: RoutingStrategyKind::default(),
+            timeout: Duration::from_secs(30),
+            receipt_profile: ReceiptCompatProfile::default(),
+            shadow: ShadowCompareConfig::default(),
+            skip_new_contracts: false,
+            method_names: HashMap::new(),
+            forward_methods: Vec::new(),
+            tx_lookup: TxLookupConfig::default(),
+            lookup_order: HashMap::new(),
+            estimate_gas_margin: None,
+        }
+    }
+}
+
+impl LegacyRpcRouterConfig {
+    /// Validates the configuration before it is applied.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        reqwest::Url::parse(&self.legacy_endpoint)
+            .map_err(|e| format!("Invalid legacy RPC URL '{}': {e}", self.legacy_endpoint))?;
+        if self.timeout.is_zero() {
+            return Err("Legacy RPC timeout must be greater than zero".to_string());
+        }
+        for pattern in &self.forward_methods {
+            parse_method_pattern(pattern)?;
+        }
+        for method in self.lookup_order.keys() {
+            validate_lookup_method(method)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the selected routing strategy.
+    pub fn routing_strategy(&self) -> &'static dyn RoutingStrategy {
+        self.strategy.strategy()
+    }
+
+    /// Returns the order of the nodes asked for a hash lookup of the method.
+    pub fn lookup_order(&self, method: &str) -> LookupOrder {
+        self.lookup_order.get(method).copied().unwrap_or_default()
+    }
+
+    /// Returns `true` if the method matches one of the forwarded method patterns.
+    pub fn forwards_method(&self, method: &str) -> bool {
+        self.forward_methods.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => method.starts_with(prefix),
+            None => method == pattern,
+        })
+    }
+}
+
+/// Parses a `<local method>=<legacy method>` name mapping, e.g.
+/// `eth_getInternalTransactions=zkevm_getInternalTransactions`.
+pub fn parse_method_name_mapping(s: &str) -> Result<(String, String), String> {
+    let (method, legacy_method) =
+        s.split_once('=').ok_or_else(|| format!("expected <method>=<legacy method>, got `{s}`"))?;
+    let (method, legacy_method) = (method.trim(), legacy_method.trim());
+    if method.is_empty() || legacy_method.is_empty() {
+        return Err(format!("missing method name in `{s}`"));
+    }
+    Ok((method.to_string(), legacy_method.to_string()))
+}
+
+/// Parses a `<method>=<order>` lookup order of a hash lookup method, e.g.
+/// `eth_getTransactionReceipt=legacy-first`.
+pub fn parse_lookup_order(s: &str) -> Result<(String, LookupOrder), String> {
+    let (method, order) =
+        s.split_once('=').ok_or_else(|| format!("expected <method>=<order>, got `{s}`"))?;
+    let method = method.trim();
+    validate_lookup_method(method)?;
+    Ok((method.to_string(), order.trim().parse()?))
+}
+
+fn validate_lookup_method(method: &str) -> Result<(), String> {
+    if !service::need_try_local_then_legacy(method) {
+        return Err(format!(
+            "`{method}` is not a hash lookup method, the lookup order only applies to methods like eth_getTransactionByHash"
+        ));
+    }
+    Ok(())
+}
+
+/// Parses a pattern of forwarded methods, a method name or a prefix ending in `*`, e.g. `zkevm_*`.
+pub fn parse_method_pattern(s: &str) -> Result<String, String> {
+    let pattern = s.trim();
+    let prefix = pattern.strip_suffix('*').unwrap_or(pattern);
+    if prefix.is_empty() {
+        return Err(format!("method pattern `{s}` must name a method or a method prefix"));
+    }
+    if !prefix.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("invalid method pattern `{s}`, expected e.g. zkevm_*"));
+    }
+    Ok(pattern.to_string())
+}
+
+/// Shared handle to the live [`LegacyRpcRouterConfig`].
+///
+/// All router services created by the same layer read the configuration through this handle, so
+/// an update is picked up by the next request on every connection.
+#[derive(Clone, Debug)]
+pub struct LegacyRpcRouterConfigHandle {
+    tx: Arc<watch::Sender<Arc<LegacyRpcRouterConfig>>>,
+}
+
+impl LegacyRpcRouterConfigHandle {
+    pub fn new(config: LegacyRpcRouterConfig) -> Self {
+        let (tx, _rx) = watch::channel(Arc::new(config));
+        Self { tx: Arc::new(tx) }
+    }
+
+    /// Returns a snapshot of the current configuration.
+    pub fn current(&self) -> Arc<LegacyRpcRouterConfig> {
+        self.tx.borrow().clone()
+    }
+
+    /// Validates and applies a new configuration.
+    pub fn update(&self, config: LegacyRpcRouterConfig) -> Result<(), String> {
+        config.validate()?;
+        tracing::info!(
+            target: "xlayer_legacy_rpc",
+            enabled = config.enabled,
+            endpoint = %config.legacy_endpoint,
+            cutoff_block = config.cutoff_block,
+            strategy = %config.strategy,
+            timeout = ?config.timeout,
+            receipt_profile = %config.receipt_profile,
+            shadow = ?config.shadow,
+            skip_new_contracts = config.skip_new_contracts,
+            method_names = ?config.method_names,
+            forward_methods = ?config.forward_methods,
+            tx_lookup = ?config.tx_lookup,
+            lookup_order = ?config.lookup_order,
+            estimate_gas_margin = ?config.estimate_gas_margin,
+            "Legacy rpc config updated"
+        );
+        self.tx.send_replace(Arc::new(config));
+        Ok(())
+    }
+
+    /// Subscribes to configuration updates.
+    pub fn subscribe(&self) -> watch::Receiver<Arc<LegacyRpcRouterConfig>> {
+        self.tx.subscribe()
+    }
 }
 
 /// XLayer legacy routing service
 #[derive(Clone)]
 pub struct LegacyRpcRouterService<S> {
     inner: S,
-    config: Arc<LegacyRpcRouterConfig>,
+    config: LegacyRpcRouterConfigHandle,
     client: Client,
+    shadow_sampler: Arc<shadow::ShadowSampler>,
+    deployments: Arc<deployments::DeploymentCache>,
+    recent_blocks: Arc<RecentBlocks>,
+    tx_lookups: Arc<tx_lookup::TxLookups>,
+    handoff: Arc<LegacyHandoff>,
+}
+
+/// Forwards the request to the upstream `endpoint` and converts the reply into a response.
+///
+/// `upstream` names the endpoint in error messages.
+pub(crate) async fn forward_request(
+    client: &Client,
+    endpoint: &str,
+    timeout: Duration,
+    upstream: &str,
+    req: Request<'_>,
+) -> MethodResponse {
+    let request_id = req.id().clone();
+
+    // The params are forwarded as the raw slice of the original request
+    let body = UpstreamRequest {
+        jsonrpc: "2.0",
+        method: req.method_name(),
+        params: req.params.as_deref(),
+        id: 1,
+    };
+
+    let response = match client.post(endpoint).timeout(timeout).json(&body).send().await {
+        Ok(response) => response.bytes().await,
+        Err(e) => {
+            tracing::error!(target: "rpc::legacy", %upstream, error = %e, "Upstream RPC request failed");
+            return MethodResponse::error(
+                request_id,
+                ErrorObject::owned(
+                    INTERNAL_ERROR_CODE,
+                    format!("{upstream} RPC error: {e}"),
+                    None::<()>,
+                ),
+            );
+        }
+    };
+
+    let parsed = match &response {
+        Ok(bytes) => {
+            serde_json::from_slice::<UpstreamResponse<'_>>(bytes).map_err(|e| e.to_string())
+        }
+        Err(e) => Err(e.to_string()),
+    };
+    match parsed {
+        Ok(UpstreamResponse { result: Some(result), .. }) => {
+            let payload = jsonrpsee_types::ResponsePayload::success(result).into();
+            MethodResponse::response(request_id, payload, usize::MAX)
+        }
+        Ok(UpstreamResponse { error: Some(error), .. }) => {
+            let error = serde_json::from_str::<serde_json::Value>(error.get()).unwrap_or_default();
+            let code = error
+                .get("code")
+                .and_then(|c| c.as_i64())
+                .unwrap_or(CALL_EXECUTION_FAILED_CODE as i64) as i32;
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{upstream} RPC error"));
+            MethodResponse::error(request_id, ErrorObject::owned(code, message, None::<()>))
+        }
+        Ok(_) => MethodResponse::error(
+            request_id,
+            ErrorObject::owned(
+                INTERNAL_ERROR_CODE,
+                format!("Invalid {} response", upstream.to_lowercase()),
+                None::<()>,
+            ),
+        ),
+        Err(e) => MethodResponse::error(
+            request_id,
+            ErrorObject::owned(
+                INTERNAL_ERROR_CODE,
+                format!("{upstream} parse error: {e}"),
+                None::<()>,
+            ),
+        ),
+    }
+}
+
+/// JSON-RPC request sent upstream.
+#[derive(Serialize)]
+struct UpstreamRequest<'a> {
+    jsonrpc: &'static str,
+    method: &'a str,
+    params: Option<&'a RawValue>,
+    id: u64,
+}
+
+/// JSON-RPC response of an upstream endpoint, borrowing its result and error.
+#[derive(Deserialize)]
+struct UpstreamResponse<'a> {
+    #[serde(borrow, default, deserialize_with = "params::present")]
+    result: Option<&'a RawValue>,
+    #[serde(borrow, default)]
+    error: Option<&'a RawValue>,
 }
 
 impl<S> LegacyRpcRouterService<S> {
-    async fn forward_to_legacy(&self, req: Request<'_>) -> MethodResponse {
-        let request_id = req.id().clone();
+    /// Returns `true` if requests are routed to the legacy node, i.e. routing is enabled, not
+    /// suspended by a legacy node that doesn't match the local chain and the routing strategy
+    /// uses the legacy node.
+    pub(crate) fn is_routing(&self, config: &LegacyRpcRouterConfig) -> bool {
+        self.legacy_available(config) && config.routing_strategy().uses_legacy()
+    }
 
-        // Build JSON-RPC request body
-        let body = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": req.method_name(),
-            "params": req.params().as_str()
-                .and_then(|s| serde_json::from_str::<serde_json::Value>(s).ok())
-                .unwrap_or(serde_json::Value::Null),
-            "id": 1
-        });
+    /// Returns `true` if locally served requests are compared with the legacy node.
+    pub(crate) fn is_shadowing(&self, config: &LegacyRpcRouterConfig) -> bool {
+        self.legacy_available(config) && config.routing_strategy().shadows(config, None)
+    }
 
-        match self.client.post(&self.config.legacy_endpoint).json(&body).send().await {
-            Ok(response) => match response.json::<serde_json::Value>().await {
-                Ok(json) => {
-                    if let Some(result) = json.get("result") {
-                        let payload = jsonrpsee_types::ResponsePayload::success(result).into();
-                        MethodResponse::response(request_id, payload, usize::MAX)
-                    } else if let Some(error) = json.get("error") {
-                        let code = error
-                            .get("code")
-                            .and_then(|c| c.as_i64())
-                            .unwrap_or(CALL_EXECUTION_FAILED_CODE as i64)
-                            as i32;
-                        let message = error
-                            .get("message")
-                            .and_then(|m| m.as_str())
-                            .unwrap_or("Legacy RPC error");
-                        MethodResponse::error(
-                            request_id,
-                            ErrorObject::owned(code, message, None::<()>),
-                        )
-                    } else {
-                        MethodResponse::error(
-                            request_id,
-                            ErrorObject::owned(
-                                INTERNAL_ERROR_CODE,
-                                "Invalid legacy response",
-                                None::<()>,
-                            ),
-                        )
-                    }
-                }
-                Err(e) => MethodResponse::error(
-                    request_id,
-                    ErrorObject::owned(
-                        INTERNAL_ERROR_CODE,
-                        format!("Legacy parse error: {e}"),
-                        None::<()>,
-                    ),
-                ),
-            },
-            Err(e) => {
-                tracing::error!(target: "rpc::legacy", error = %e, "Legacy RPC request failed");
-                MethodResponse::error(
-                    request_id,
-                    ErrorObject::owned(
-                        INTERNAL_ERROR_CODE,
-                        format!("Legacy RPC error: {e}"),
-                        None::<()>,
-                    ),
-                )
-            }
+    fn legacy_available(&self, config: &LegacyRpcRouterConfig) -> bool {
+        config.enabled && !self.handoff.is_suspended()
+    }
+
+    async fn forward_to_legacy(
+        &self,
+        config: &LegacyRpcRouterConfig,
+        mut req: Request<'_>,
+    ) -> MethodResponse {
+        let is_receipt_method = normalize::is_receipt_method(req.method_name());
+        if let Some(legacy_method) = config.method_names.get(req.method_name()) {
+            req.method = legacy_method.clone().into();
         }
+
+        let mut res = if !is_receipt_method {
+            forward_request(&self.client, &config.legacy_endpoint, config.timeout, "Legacy", req)
+                .await
+        } else {
+            let request_id = req.id().into_owned();
+            let res = forward_request(
+                &self.client,
+                &config.legacy_endpoint,
+                config.timeout,
+                "Legacy",
+                req,
+            )
+            .await;
+            normalize::normalize_receipts_response(res, request_id, config.receipt_profile)
+        };
+        res.extensions_mut().insert(RpcRoute::Legacy);
+        res
     }
 
     pub async fn call_eth_get_block_by_hash(
@@ -203,74 +511,6 @@ pub fn is_block_hash(hex: &str) -> bool {
     }
 }
 
-/// Handles latest, pending, hash, hex number etc
-#[inline]
-pub(crate) fn parse_block_param(params: &str, index: usize) -> Option<String> {
-    let parsed: serde_json::Value = serde_json::from_str(params).ok()?;
-    let arr = parsed.as_array()?;
-
-    // Some params are optional.
-    if index >= arr.len() {
-        return None;
-    }
-
-    let block_param = arr.get(index)?;
-
-    match block_param {
-        serde_json::Value::String(s) => {
-            match s.as_str() {
-                // Don't route these to legacy (use current chain state)
-                "latest" | "pending" | "safe" | "finalized" => None,
-
-                // Route to legacy (not genesis, as local has no data)
-                "earliest" => Some("0".into()),
-
-                // Parse hex block number/hash
-                hex if hex.starts_with("0x") => {
-                    // Check if it's a block hash (66 chars) or block number
-                    if hex.len() == 66 {
-                        // Validate it's a proper 32-byte hex string to prevent JSON injection
-                        if is_valid_32_bytes_string(hex) {
-                            Some(hex.into())
-                        } else {
-                            // Invalid hex characters - reject it
-                            None
-                        }
-                    } else {
-                        // Parse as block number
-                        u64::from_str_radix(&hex[2..], 16).ok().map(|n| n.to_string())
-                    }
-                }
-
-                _ => None,
-            }
-        }
-        // Handle object format: {"blockHash": "0x..."} or {"blockNumber": "0x..."}
-        serde_json::Value::Object(obj) => {
-            if let Some(serde_json::Value::String(hash)) = obj.get("blockHash") {
-                // Validate block hash to prevent JSON injection
-                if is_valid_32_bytes_string(hash) {
-                    Some(hash.clone())
-                } else {
-                    None
-                }
-            } else if let Some(serde_json::Value::String(num)) = obj.get("blockNumber") {
-                // Handle blockNumber in object format
-                if let Some(stripped) = num.strip_prefix("0x") {
-                    u64::from_str_radix(stripped, 16).ok().map(|n| n.to_string())
-                } else {
-                    Some(num.clone())
-                }
-            } else {
-                None
-            }
-        }
-        // decimal number not handled...
-        // serde_json::Value::Number(n) => n.as_u64(),
-        _ => None,
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -278,7 +518,6 @@ mod tests {
     use jsonrpsee::types::{Id, Request};
     use jsonrpsee::MethodResponse;
     use std::future::Future;
-    use std::sync::Arc;
 
     // Mock RPC service that returns predefined responses
     #[derive(Clone)]
@@ -349,14 +588,20 @@ mod tests {
             legacy_endpoint: "https://testrpc.xlayer.tech/terigon".to_string(),
             cutoff_block: 1_000_000,
             timeout: std::time::Duration::from_secs(10),
+            ..Default::default()
         };
 
         let mock_service = MockRpcService { response: response.to_string() };
 
         LegacyRpcRouterService {
             inner: mock_service,
-            config: Arc::new(config),
+            config: LegacyRpcRouterConfigHandle::new(config),
             client: reqwest::Client::new(),
+            shadow_sampler: Default::default(),
+            deployments: Default::default(),
+            recent_blocks: Default::default(),
+            tx_lookups: Default::default(),
+            handoff: Default::default(),
         }
     }
 
@@ -403,7 +648,7 @@ mod tests {
         let params_json = serde_json::to_string(&vec![malicious_hash]).unwrap();
 
         // After fix: parse_block_param now rejects the malicious hash
-        let parsed_block = parse_block_param(&params_json, 0);
+        let parsed_block = params::parse_block_param(&params_json, 0);
         assert!(parsed_block.is_none(), "parse_block_param should reject invalid hex");
 
         // Verify is_valid_32_bytes_string correctly rejects it
@@ -532,4 +777,168 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_none());
     }
+
+    #[tokio::test]
+    async fn test_recent_block_hash_routed_locally() {
+        // The local lookup misses the block, the recent blocks index has it
+        let service = create_test_service(r#"{"jsonrpc":"2.0","id":1,"result":null}"#);
+        let hash = alloy_primitives::B256::with_last_byte(1);
+        service.recent_blocks.insert(hash, 2_000_000);
+
+        let params = format!(r#"["0x1111111111111111111111111111111111111111", "{hash:#x}"]"#);
+        let params = RawValue::from_string(params).unwrap();
+        let req = Request::owned("eth_getBalance".to_string(), Some(params), Id::Number(1));
+        let config = service.config.current();
+        let res = service::handle_block_param_methods(req, service.clone(), config).await;
+        assert_eq!(RpcRoute::of(&res), RpcRoute::Local);
+    }
+
+    #[test]
+    fn test_routing_suspended_by_handoff_mismatch() {
+        let service = create_test_service("{}");
+        let config = service.config.current();
+        assert!(service.is_routing(&config));
+
+        let reason = "legacy head 990 is 9 blocks behind the last legacy block 999".to_string();
+        service.handoff.set_status(LegacyHandoffStatus::Mismatch { reason });
+        assert!(!service.is_routing(&config));
+    }
+
+    #[test]
+    fn test_routing_strategy() {
+        let service = create_test_service("{}");
+        let mut config = (*service.config.current()).clone();
+        assert!(service.is_routing(&config));
+        assert!(!service.is_shadowing(&config));
+        assert!(config.routing_strategy().routes_block_to_legacy(&config, 999_999));
+        assert!(!config.routing_strategy().routes_block_to_legacy(&config, 1_000_000));
+
+        config.strategy = RoutingStrategyKind::Shadow;
+        assert!(!service.is_routing(&config));
+        assert!(service.is_shadowing(&config));
+        assert!(!config.routing_strategy().routes_block_to_legacy(&config, 0));
+
+        config.strategy = RoutingStrategyKind::Local;
+        assert!(!service.is_routing(&config));
+        assert!(!service.is_shadowing(&config));
+
+        // Shadowing needs the legacy node
+        config.strategy = RoutingStrategyKind::Shadow;
+        config.enabled = false;
+        assert!(!service.is_shadowing(&config));
+    }
+
+    #[tokio::test]
+    async fn test_local_strategy_serves_below_cutoff_locally() {
+        let service = create_test_service(r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#);
+        let mut config = (*service.config.current()).clone();
+        config.strategy = RoutingStrategyKind::Local;
+
+        let params = RawValue::from_string(
+            r#"["0x1111111111111111111111111111111111111111", "0x10"]"#.to_string(),
+        )
+        .unwrap();
+        let req = Request::owned("eth_getBalance".to_string(), Some(params), Id::Number(1));
+        let res = service::handle_block_param_methods(req, service.clone(), Arc::new(config)).await;
+        assert_eq!(RpcRoute::of(&res), RpcRoute::Local);
+    }
+
+    #[test]
+    fn test_config_handle_update() {
+        let handle = LegacyRpcRouterConfigHandle::new(LegacyRpcRouterConfig {
+            enabled: true,
+            legacy_endpoint: "http://localhost:8545".to_string(),
+            cutoff_block: 100,
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        });
+        let mut rx = handle.subscribe();
+
+        let mut config = (*handle.current()).clone();
+        config.enabled = false;
+        config.cutoff_block = 200;
+        assert!(handle.update(config).is_ok());
+
+        assert!(rx.has_changed().unwrap());
+        let current = handle.current();
+        assert!(!current.enabled);
+        assert_eq!(current.cutoff_block, 200);
+    }
+
+    #[test]
+    fn test_config_handle_rejects_invalid_update() {
+        let handle = LegacyRpcRouterConfigHandle::new(LegacyRpcRouterConfig {
+            enabled: true,
+            legacy_endpoint: "http://localhost:8545".to_string(),
+            cutoff_block: 100,
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        });
+
+        let mut config = (*handle.current()).clone();
+        config.legacy_endpoint = "not-a-url".to_string();
+        assert!(handle.update(config).is_err());
+
+        let mut config = (*handle.current()).clone();
+        config.timeout = Duration::ZERO;
+        assert!(handle.update(config).is_err());
+
+        assert_eq!(handle.current().legacy_endpoint, "http://localhost:8545");
+    }
+
+    #[test]
+    fn test_method_name_mapping() {
+        assert_eq!(
+            parse_method_name_mapping("eth_getInternalTransactions=zkevm_getInternalTransactions")
+                .unwrap(),
+            (
+                "eth_getInternalTransactions".to_string(),
+                "zkevm_getInternalTransactions".to_string()
+            )
+        );
+        assert!(parse_method_name_mapping("eth_getInternalTransactions").is_err());
+        assert!(parse_method_name_mapping("=zkevm_getInternalTransactions").is_err());
+        assert!(parse_method_name_mapping("eth_getInternalTransactions=").is_err());
+    }
+
+    #[test]
+    fn test_forward_method_patterns() {
+        assert_eq!(parse_method_pattern(" zkevm_* ").unwrap(), "zkevm_*");
+        assert!(parse_method_pattern("*").is_err());
+        assert!(parse_method_pattern("zkevm_*_x").is_err());
+
+        let mut config = (*create_test_service("{}").config.current()).clone();
+        assert!(!config.forwards_method("zkevm_batchNumber"));
+        config.forward_methods =
+            vec!["zkevm_*".to_string(), "eth_getInternalTransactions".to_string()];
+        assert!(config.validate().is_ok());
+        assert!(config.forwards_method("zkevm_batchNumber"));
+        assert!(config.forwards_method("eth_getInternalTransactions"));
+        assert!(!config.forwards_method("eth_getInternalTransactionsByBlock"));
+        assert!(!config.forwards_method("debug_traceTransaction"));
+
+        config.forward_methods.push("debug_*trace".to_string());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_lookup_order() {
+        assert_eq!(
+            parse_lookup_order(" eth_getTransactionReceipt = legacy-first ").unwrap(),
+            ("eth_getTransactionReceipt".to_string(), LookupOrder::LegacyFirst)
+        );
+        assert!(parse_lookup_order("eth_getTransactionReceipt").is_err());
+        assert!(parse_lookup_order("eth_getTransactionReceipt=legacy").is_err());
+        assert!(parse_lookup_order("eth_getLogs=legacy-first").is_err());
+
+        let mut config = (*create_test_service("{}").config.current()).clone();
+        assert_eq!(config.lookup_order("eth_getBlockByHash"), LookupOrder::LocalFirst);
+        config.lookup_order.insert("eth_getBlockByHash".to_string(), LookupOrder::LegacyFirst);
+        assert!(config.validate().is_ok());
+        assert_eq!(config.lookup_order("eth_getBlockByHash"), LookupOrder::LegacyFirst);
+        assert_eq!(config.lookup_order("eth_getHeaderByHash"), LookupOrder::LocalFirst);
+
+        config.lookup_order.insert("eth_call".to_string(), LookupOrder::LegacyFirst);
+        assert!(config.validate().is_err());
+    }
 }
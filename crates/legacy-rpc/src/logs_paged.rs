@@ -0,0 +1,635 @@
+//! Paginated `eth_getLogs`, served as `xlayer_getLogsPaged(filter, pageToken, pageSize)`.
+//!
+//! Wide `eth_getLogs` ranges fail once they match more logs than a node returns in one response,
+//! leaving indexers to bisect the range until it fits. This method walks the block range of the
+//! filter in windows instead. Each window is fetched as an `eth_getLogs` call through the legacy
+//! routing, so windows below, above and across the cutoff block are served like any other
+//! `eth_getLogs` call. A window that exceeds a result limit is halved and retried.
+//!
+//! A page holds up to `pageSize` logs, ordered by block number, transaction index and log index.
+//! Page sizes and tokens follow the shared [`pagination`](crate::pagination) conventions. The
+//! `nextPageToken` is the position of the last log of the page, passing it back with the same
+//! filter continues with the following log. After [`MAX_WINDOWS_PER_PAGE`] windows a page ends
+//! early, with a token at the next block to scan, so sparse filters over long ranges don't hold
+//! the request open. The token is `null` once the range is exhausted.
+//!
+//! Block tags of the filter are resolved against the local node on every page, a filter up to
+//! `latest` follows the chain head between pages.
+
+use std::sync::Arc;
+
+use jsonrpsee::{
+    core::{middleware::RpcServiceT, RpcResult},
+    proc_macros::rpc,
+    types::{
+        error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+        ErrorObject, Id, Request,
+    },
+    MethodResponse,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{value::RawValue, Value};
+use tracing::debug;
+
+use crate::{
+    get_logs::{handle_eth_get_logs, modify_eth_get_logs_params},
+    normalize::normalize_log,
+    pagination::{
+        decode_page_token, encode_page_token, parse_page_token, PageLimits, PageParamError,
+    },
+    params::{as_str, LogsFilterRef},
+    LegacyRpcRouterConfig, LegacyRpcRouterService, RpcRoute,
+};
+
+/// Name of the paginated logs method.
+pub const LOGS_PAGED_METHOD: &str = "xlayer_getLogsPaged";
+
+/// Page size used if the request has none.
+pub const DEFAULT_PAGE_SIZE: usize = 1_000;
+
+/// Largest accepted page size.
+pub const MAX_PAGE_SIZE: usize = 10_000;
+
+/// Page size bounds of `xlayer_getLogsPaged`.
+pub const LOGS_PAGE_LIMITS: PageLimits = PageLimits::new(DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE);
+
+/// `eth_getLogs` calls a page may issue before it ends early.
+pub const MAX_WINDOWS_PER_PAGE: usize = 32;
+
+/// Blocks of the first window of a page.
+const INITIAL_WINDOW: u64 = 1_000;
+
+/// Largest window, below the block range limit of the nodes.
+const MAX_WINDOW: u64 = 10_000;
+
+/// Error code of nodes rejecting a query with too many results.
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+/// Page of logs returned by `xlayer_getLogsPaged`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsPage {
+    pub logs: Vec<Value>,
+    /// Token of the next page, `None` once the range is exhausted
+    pub next_page_token: Option<String>,
+}
+
+/// Paginated logs API. Served by the legacy routing middleware, so only a client is generated.
+#[rpc(client, namespace = "xlayer")]
+pub trait LogsPagedApi {
+    /// Returns a page of the logs matching an `eth_getLogs` filter.
+    #[method(name = "getLogsPaged")]
+    async fn get_logs_paged(
+        &self,
+        filter: Value,
+        page_token: Option<String>,
+        page_size: Option<usize>,
+    ) -> RpcResult<LogsPage>;
+}
+
+/// Position of a log in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct LogPosition {
+    block: u64,
+    tx_index: u64,
+    log_index: u64,
+}
+
+impl LogPosition {
+    /// Reads the position of a log returned by `eth_getLogs`.
+    fn of(log: &Value) -> Option<Self> {
+        let quantity = |field| {
+            let value = log.get(field)?.as_str()?.strip_prefix("0x")?;
+            u64::from_str_radix(value, 16).ok()
+        };
+        Some(Self {
+            block: quantity("blockNumber")?,
+            tx_index: quantity("transactionIndex")?,
+            log_index: quantity("logIndex")?,
+        })
+    }
+}
+
+/// Where a page continues.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PageToken {
+    /// At the first log of the block
+    Block(u64),
+    /// After the last log of the previous page
+    After(LogPosition),
+}
+
+impl PageToken {
+    fn parse(token: &str) -> Option<Self> {
+        match decode_page_token(token)?.as_slice() {
+            [block] => Some(Self::Block(*block)),
+            [block, tx_index, log_index] => Some(Self::After(LogPosition {
+                block: *block,
+                tx_index: *tx_index,
+                log_index: *log_index,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Block the page starts scanning at.
+    fn block(&self) -> u64 {
+        match self {
+            Self::Block(block) => *block,
+            Self::After(position) => position.block,
+        }
+    }
+}
+
+impl std::fmt::Display for PageToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = match self {
+            Self::Block(block) => encode_page_token(&[*block]),
+            Self::After(LogPosition { block, tx_index, log_index }) => {
+                encode_page_token(&[*block, *tx_index, *log_index])
+            }
+        };
+        f.write_str(&token)
+    }
+}
+
+/// Parsed `xlayer_getLogsPaged` params.
+#[derive(Debug)]
+struct PagedParams<'a> {
+    filter: &'a RawValue,
+    token: Option<PageToken>,
+    page_size: usize,
+}
+
+impl<'a> PagedParams<'a> {
+    fn parse(params: Option<&'a str>) -> Result<Self, ErrorObject<'static>> {
+        let invalid = |message| ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>);
+        let params: Vec<&RawValue> = params
+            .and_then(|params| serde_json::from_str(params).ok())
+            .ok_or_else(|| invalid("Missing required params"))?;
+        let filter = *params.first().ok_or_else(|| invalid("Missing filter"))?;
+
+        let token = match parse_page_token(params.get(1).copied())? {
+            Some(token) => Some(PageToken::parse(token).ok_or(PageParamError::InvalidToken)?),
+            None => None,
+        };
+        let page_size = LOGS_PAGE_LIMITS.parse_page_size(params.get(2).copied())?;
+
+        Ok(Self { filter, token, page_size })
+    }
+}
+
+/// Logs of a page and the routes they were served from.
+#[derive(Debug, Default)]
+struct PageBuilder {
+    logs: Vec<Value>,
+    last: Option<LogPosition>,
+    legacy: bool,
+    local: bool,
+}
+
+impl PageBuilder {
+    fn record_route(&mut self, route: RpcRoute) {
+        match route {
+            RpcRoute::Local => self.local = true,
+            RpcRoute::Legacy => self.legacy = true,
+            RpcRoute::Hybrid => (self.legacy, self.local) = (true, true),
+        }
+    }
+
+    /// Adds the logs of a window that follow `after`. Returns the token of the next page if the
+    /// page filled up before the end of the window.
+    fn extend(
+        &mut self,
+        logs: Vec<Value>,
+        after: Option<LogPosition>,
+        page_size: usize,
+    ) -> Result<Option<PageToken>, ErrorObject<'static>> {
+        let mut logs = logs
+            .into_iter()
+            .map(|mut log| {
+                normalize_log(&mut log);
+                let position = LogPosition::of(&log).ok_or_else(|| {
+                    ErrorObject::owned(INTERNAL_ERROR_CODE, "Log without position", None::<()>)
+                })?;
+                Ok((position, log))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        logs.sort_by_key(|(position, _)| *position);
+
+        for (position, log) in logs {
+            if after.is_some_and(|after| position <= after) {
+                continue;
+            }
+            if self.logs.len() == page_size {
+                return Ok(self.last.map(PageToken::After));
+            }
+            self.logs.push(log);
+            self.last = Some(position);
+        }
+        Ok(None)
+    }
+
+    fn finish(self, id: Id<'_>, next: Option<PageToken>) -> MethodResponse {
+        let route = match (self.legacy, self.local) {
+            (true, true) => Some(RpcRoute::Hybrid),
+            (true, false) => Some(RpcRoute::Legacy),
+            _ => None,
+        };
+        let page = LogsPage { logs: self.logs, next_page_token: next.map(|next| next.to_string()) };
+        let payload = jsonrpsee_types::ResponsePayload::success(&page).into();
+        let mut response = MethodResponse::response(id, payload, usize::MAX);
+        if let Some(route) = route {
+            response.extensions_mut().insert(route);
+        }
+        response
+    }
+}
+
+/// Handles `xlayer_getLogsPaged`.
+pub(crate) async fn handle_get_logs_paged<S>(
+    req: Request<'_>,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
+) -> MethodResponse
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    let id = req.id();
+    let params_ref = req.params();
+    let params = match PagedParams::parse(params_ref.as_str()) {
+        Ok(params) => params,
+        Err(error) => return MethodResponse::error(id, error),
+    };
+    let Some(filter) = serde_json::from_str::<LogsFilterRef<'_>>(params.filter.get()).ok() else {
+        return invalid_params(id, "Invalid filter");
+    };
+    let Ok(logs_params) = serde_json::value::to_raw_value(&[params.filter]) else {
+        return invalid_params(id, "Invalid filter");
+    };
+    let logs_req = Request::owned("eth_getLogs".to_string(), Some(logs_params), id.clone());
+    let mut page = PageBuilder::default();
+    let after = match params.token {
+        Some(PageToken::After(position)) => Some(position),
+        _ => None,
+    };
+
+    // Block hash filters are a single window
+    if filter.block_hash.is_some() {
+        let response = get_logs(&service, &config, logs_req).await;
+        page.record_route(RpcRoute::of(&response));
+        return match window_logs(&response) {
+            Ok(logs) => match page.extend(logs, after, params.page_size) {
+                Ok(next) => page.finish(id, next),
+                Err(error) => MethodResponse::error(id, error),
+            },
+            Err(error) => MethodResponse::error(id, error),
+        };
+    }
+
+    let from_block = match resolve_block(&service, filter.from_block).await {
+        Ok(block) => block,
+        Err(error) => return MethodResponse::error(id, error),
+    };
+    let to_block = match resolve_block(&service, filter.to_block).await {
+        Ok(block) => block,
+        Err(error) => return MethodResponse::error(id, error),
+    };
+    let mut start = params.token.map_or(from_block, |token| token.block());
+    if start < from_block {
+        return invalid_params(id, "Page token outside of the filter range");
+    }
+
+    let mut window = INITIAL_WINDOW;
+    let mut windows = 0;
+    while start <= to_block {
+        if windows == MAX_WINDOWS_PER_PAGE {
+            return page.finish(id, Some(PageToken::Block(start)));
+        }
+        windows += 1;
+
+        let end = start.saturating_add(window - 1).min(to_block);
+        let Some(window_req) = modify_eth_get_logs_params(&logs_req, Some(start), Some(end)) else {
+            return invalid_params(id, "Invalid filter");
+        };
+        let response = get_logs(&service, &config, window_req).await;
+        let logs = match window_logs(&response) {
+            Ok(logs) => logs,
+            Err(error) if is_limit_error(&error) && end > start => {
+                window = (end - start + 1) / 2;
+                debug!(target:"xlayer_legacy_rpc", start, end, window, "xlayer_getLogsPaged window exceeds limit, narrowing");
+                continue;
+            }
+            Err(error) => return MethodResponse::error(id, error),
+        };
+        page.record_route(RpcRoute::of(&response));
+
+        let found = logs.len();
+        match page.extend(logs, after, params.page_size) {
+            Ok(Some(next)) => return page.finish(id, Some(next)),
+            Ok(None) => {}
+            Err(error) => return MethodResponse::error(id, error),
+        }
+        if page.logs.len() == params.page_size {
+            return page.finish(id, page.last.map(PageToken::After));
+        }
+        if found <= params.page_size / 4 {
+            window = window.saturating_mul(2).min(MAX_WINDOW);
+        }
+        let Some(next) = end.checked_add(1) else {
+            break;
+        };
+        start = next;
+    }
+
+    page.finish(id, None)
+}
+
+/// Fetches logs through the legacy routing, or locally while it is disabled or suspended.
+async fn get_logs<S>(
+    service: &LegacyRpcRouterService<S>,
+    config: &Arc<LegacyRpcRouterConfig>,
+    req: Request<'_>,
+) -> MethodResponse
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    if service.is_routing(config) {
+        handle_eth_get_logs(req, service.clone(), config.clone()).await
+    } else {
+        service.inner.call(req).await
+    }
+}
+
+/// Resolves a block of the filter to its number, missing blocks default to `latest`.
+async fn resolve_block<S>(
+    service: &LegacyRpcRouterService<S>,
+    block: Option<&RawValue>,
+) -> Result<u64, ErrorObject<'static>>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    let invalid = || ErrorObject::owned(INVALID_PARAMS_CODE, "Invalid block in filter", None::<()>);
+    let block = match block {
+        Some(block) => as_str(block).ok_or_else(invalid)?,
+        None => "latest",
+    };
+    let tag = match block {
+        "earliest" => return Ok(0),
+        // Pending logs have no stable position
+        "latest" | "pending" => "latest",
+        "safe" | "finalized" => block,
+        hex => {
+            let number = hex.strip_prefix("0x").ok_or_else(invalid)?;
+            return u64::from_str_radix(number, 16).map_err(|_| invalid());
+        }
+    };
+
+    let params = RawValue::from_string(format!(r#"["{tag}", false]"#)).map_err(|_| invalid())?;
+    let req = Request::owned("eth_getBlockByNumber".to_string(), Some(params), Id::Number(1));
+    let response = service.inner.call(req).await;
+    serde_json::from_str::<Value>(response.as_json().get())
+        .ok()
+        .and_then(|response| {
+            let number = response.get("result")?.get("number")?.as_str()?.strip_prefix("0x")?;
+            u64::from_str_radix(number, 16).ok()
+        })
+        .ok_or_else(|| {
+            ErrorObject::owned(
+                INTERNAL_ERROR_CODE,
+                format!("Failed to resolve block tag {tag}"),
+                None::<()>,
+            )
+        })
+}
+
+/// Returns the logs of an `eth_getLogs` response, or its error.
+fn window_logs(response: &MethodResponse) -> Result<Vec<Value>, ErrorObject<'static>> {
+    let mut response: Value = serde_json::from_str(response.as_json().get()).map_err(|e| {
+        ErrorObject::owned(INTERNAL_ERROR_CODE, format!("Invalid logs response: {e}"), None::<()>)
+    })?;
+    if let Some(Value::Array(logs)) = response.get_mut("result").map(Value::take) {
+        return Ok(logs);
+    }
+    let error = response.get("error");
+    let code = error
+        .and_then(|error| error.get("code"))
+        .and_then(Value::as_i64)
+        .unwrap_or(INTERNAL_ERROR_CODE as i64) as i32;
+    let message = error
+        .and_then(|error| error.get("message"))
+        .and_then(Value::as_str)
+        .unwrap_or("Invalid logs response")
+        .to_string();
+    Err(ErrorObject::owned(code, message, None::<()>))
+}
+
+/// Returns `true` if the error asks for a narrower range, e.g. `query exceeds max results`.
+fn is_limit_error(error: &ErrorObject<'_>) -> bool {
+    let message = error.message().to_ascii_lowercase();
+    error.code() == LIMIT_EXCEEDED_CODE
+        || ["exceed", "more than", "too many"].iter().any(|pattern| message.contains(pattern))
+}
+
+fn invalid_params(id: Id<'_>, message: &'static str) -> MethodResponse {
+    MethodResponse::error(id, ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LegacyRpcRouterConfigHandle;
+    use jsonrpsee::core::middleware::{Batch, Notification};
+    use std::future::Future;
+
+    /// Local node serving `eth_getLogs` from a fixed set of logs.
+    #[derive(Clone)]
+    struct LogsNode {
+        logs: Arc<Vec<LogPosition>>,
+        head: u64,
+        max_results: usize,
+    }
+
+    impl LogsNode {
+        fn respond(&self, req: &Request<'_>) -> Result<Value, ErrorObject<'static>> {
+            let params: Value = serde_json::from_str(req.params().as_str().unwrap()).unwrap();
+            let quantity = |value: &Value| {
+                u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+            };
+            match req.method_name() {
+                "eth_getBlockByNumber" => {
+                    Ok(serde_json::json!({ "number": format!("0x{:x}", self.head) }))
+                }
+                "eth_getLogs" => {
+                    let (from, to) =
+                        (quantity(&params[0]["fromBlock"]), quantity(&params[0]["toBlock"]));
+                    let logs: Vec<_> = self
+                        .logs
+                        .iter()
+                        .filter(|log| (from..=to).contains(&log.block))
+                        .map(|log| {
+                            serde_json::json!({
+                                "blockNumber": format!("0x{:x}", log.block),
+                                "transactionIndex": format!("0x{:x}", log.tx_index),
+                                "logIndex": format!("0x{:x}", log.log_index),
+                            })
+                        })
+                        .collect();
+                    if logs.len() > self.max_results {
+                        return Err(ErrorObject::owned(
+                            LIMIT_EXCEEDED_CODE,
+                            "query exceeds max results",
+                            None::<()>,
+                        ));
+                    }
+                    Ok(Value::Array(logs))
+                }
+                method => panic!("unexpected call {method}"),
+            }
+        }
+    }
+
+    impl RpcServiceT for LogsNode {
+        type MethodResponse = MethodResponse;
+        type NotificationResponse = MethodResponse;
+        type BatchResponse = MethodResponse;
+
+        fn call<'a>(
+            &self,
+            req: Request<'a>,
+        ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+            let response = match self.respond(&req) {
+                Ok(result) => MethodResponse::response(
+                    req.id(),
+                    jsonrpsee_types::ResponsePayload::success(result).into(),
+                    usize::MAX,
+                ),
+                Err(error) => MethodResponse::error(req.id(), error),
+            };
+            std::future::ready(response)
+        }
+
+        fn batch<'a>(
+            &self,
+            _req: Batch<'a>,
+        ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+            std::future::ready(MethodResponse::error(
+                Id::Null,
+                ErrorObject::borrowed(-32600, "", None),
+            ))
+        }
+
+        fn notification<'a>(
+            &self,
+            _n: Notification<'a>,
+        ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+            std::future::ready(MethodResponse::error(
+                Id::Null,
+                ErrorObject::borrowed(-32600, "", None),
+            ))
+        }
+    }
+
+    fn service(node: LogsNode) -> LegacyRpcRouterService<LogsNode> {
+        let config = LegacyRpcRouterConfig::default();
+        LegacyRpcRouterService {
+            inner: node,
+            config: LegacyRpcRouterConfigHandle::new(config),
+            client: reqwest::Client::new(),
+            shadow_sampler: Default::default(),
+            deployments: Default::default(),
+            recent_blocks: Default::default(),
+            tx_lookups: Default::default(),
+            handoff: Default::default(),
+        }
+    }
+
+    fn position(block: u64, tx_index: u64, log_index: u64) -> LogPosition {
+        LogPosition { block, tx_index, log_index }
+    }
+
+    async fn get_page(
+        service: &LegacyRpcRouterService<LogsNode>,
+        params: Value,
+    ) -> Result<LogsPage, i32> {
+        let params = serde_json::value::to_raw_value(&params).unwrap();
+        let req = Request::owned(LOGS_PAGED_METHOD.to_string(), Some(params), Id::Number(1));
+        let response = service.call(req).await;
+        let response: Value = serde_json::from_str(response.as_json().get()).unwrap();
+        match response.get("result") {
+            Some(page) => Ok(serde_json::from_value(page.clone()).unwrap()),
+            None => Err(response["error"]["code"].as_i64().unwrap() as i32),
+        }
+    }
+
+    #[test]
+    fn test_page_token() {
+        let tokens = [PageToken::Block(0x10), PageToken::After(position(0x10, 2, 0xff))];
+        for token in tokens {
+            assert_eq!(PageToken::parse(&token.to_string()), Some(token));
+        }
+        assert_eq!(PageToken::After(position(16, 2, 255)).to_string(), "10-2-ff");
+        for invalid in ["", "10-2", "10-2-ff-1", "zz", "10--1"] {
+            assert_eq!(PageToken::parse(invalid), None, "{invalid}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_pages_cover_range() {
+        // Windows over blocks 1_500 to 1_505 exceed the result limit and are narrowed
+        let mut logs = vec![position(5, 0, 0), position(5, 0, 1), position(5, 1, 2)];
+        logs.extend((1_500..1_506).map(|block| position(block, 0, 0)));
+        logs.push(position(40_000, 0, 0));
+        let service =
+            service(LogsNode { logs: Arc::new(logs.clone()), head: 50_000, max_results: 4 });
+
+        let mut collected = Vec::new();
+        let mut token = None;
+        let mut pages = 0;
+        loop {
+            let page = get_page(
+                &service,
+                serde_json::json!([{ "fromBlock": "0x1", "toBlock": "latest" }, token, 4]),
+            )
+            .await
+            .unwrap();
+            assert!(page.logs.len() <= 4);
+            collected.extend(page.logs.iter().map(|log| LogPosition::of(log).unwrap()));
+            pages += 1;
+            token = page.next_page_token;
+            if token.is_none() {
+                break;
+            }
+        }
+        assert_eq!(collected, logs);
+        // Two full pages, then a sparse page up to block 40_000
+        assert_eq!(pages, 3);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_params() {
+        let service = service(LogsNode { logs: Arc::new(Vec::new()), head: 100, max_results: 10 });
+        let filter = serde_json::json!({ "fromBlock": "0x10", "toBlock": "0x20" });
+
+        assert_eq!(
+            get_page(&service, serde_json::json!([filter, null, 0])).await,
+            Err(INVALID_PARAMS_CODE)
+        );
+        assert_eq!(
+            get_page(&service, serde_json::json!([filter, null, MAX_PAGE_SIZE + 1])).await,
+            Err(INVALID_PARAMS_CODE)
+        );
+        assert_eq!(
+            get_page(&service, serde_json::json!([filter, "xyz"])).await,
+            Err(INVALID_PARAMS_CODE)
+        );
+        // Token before the start of the range
+        assert_eq!(
+            get_page(&service, serde_json::json!([filter, "1"])).await,
+            Err(INVALID_PARAMS_CODE)
+        );
+
+        let page = get_page(&service, serde_json::json!([filter, null, "0x10"])).await.unwrap();
+        assert_eq!(page, LogsPage { logs: Vec::new(), next_page_token: None });
+    }
+}
@@ -0,0 +1,208 @@
+//! Helpers shared by the RPC middlewares of the X Layer node.
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    task::{Context, Poll},
+};
+
+use futures::{
+    future::{ready, Either},
+    stream::FuturesOrdered,
+    StreamExt,
+};
+use jsonrpsee::{
+    core::{
+        middleware::{Batch, BatchEntry, RpcServiceT},
+        server::ConnectionId,
+    },
+    server::HttpRequest,
+    types::{ErrorCode, ErrorObject, Id, Request},
+    BatchResponseBuilder, MethodResponse,
+};
+use tower::{Layer, Service};
+
+/// Header holding the chain of client addresses appended by reverse proxies.
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Identity of the client that sent a request, used to apply per client limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ClientKey {
+    /// Address of the client
+    Ip(IpAddr),
+    /// Connection the request was received on, used when the client address is unknown
+    Connection(ConnectionId),
+}
+
+/// Returns the client IP attached to the request extensions, if any: the client address
+/// attached by the [`ClientAddrLayer`] (`IpAddr`), else the peer address (`SocketAddr`).
+pub fn client_ip(req: &Request<'_>) -> Option<IpAddr> {
+    let extensions = req.extensions();
+    extensions
+        .get::<IpAddr>()
+        .copied()
+        .or_else(|| extensions.get::<SocketAddr>().map(|addr| addr.ip()))
+}
+
+/// Returns the key of the client that sent the request.
+///
+/// Clients are identified by the address attached by the [`ClientAddrLayer`], or the peer
+/// address, and otherwise by the [`ConnectionId`] the server inserts for every connection.
+pub fn client_key(req: &Request<'_>) -> Option<ClientKey> {
+    client_ip(req)
+        .map(ClientKey::Ip)
+        .or_else(|| req.extensions().get::<ConnectionId>().copied().map(ClientKey::Connection))
+}
+
+/// Returns the client address appended last to the `X-Forwarded-For` header, by the proxy in
+/// front of the node. Addresses before it are set by the client and can't be trusted.
+fn forwarded_for<B>(req: &HttpRequest<B>) -> Option<IpAddr> {
+    let header = req.headers().get_all(FORWARDED_FOR_HEADER).iter().next_back()?;
+    header.to_str().ok()?.rsplit(',').next()?.trim().parse().ok()
+}
+
+/// HTTP layer attaching the address of the client to the requests, so that the RPC middlewares
+/// apply their per client limits by address rather than by connection. The peer address is kept
+/// as attached by the server, and behind a trusted reverse proxy the client address is taken from
+/// the `X-Forwarded-For` header.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientAddrLayer {
+    trust_forwarded_for: bool,
+}
+
+impl ClientAddrLayer {
+    /// Creates the layer, `trust_forwarded_for` must only be set if every request reaches the
+    /// node through a proxy appending the client address to `X-Forwarded-For`.
+    pub const fn new(trust_forwarded_for: bool) -> Self {
+        Self { trust_forwarded_for }
+    }
+}
+
+impl<S> Layer<S> for ClientAddrLayer {
+    type Service = ClientAddrService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientAddrService { inner, trust_forwarded_for: self.trust_forwarded_for }
+    }
+}
+
+/// HTTP service attaching the address of the client to the requests.
+#[derive(Debug, Clone)]
+pub struct ClientAddrService<S> {
+    inner: S,
+    trust_forwarded_for: bool,
+}
+
+impl<S, B> Service<HttpRequest<B>> for ClientAddrService<S>
+where
+    S: Service<HttpRequest<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: HttpRequest<B>) -> Self::Future {
+        let peer = req.extensions().get::<SocketAddr>().map(|addr| addr.ip());
+        let forwarded = self.trust_forwarded_for.then(|| forwarded_for(&req)).flatten();
+        if let Some(ip) = forwarded.or(peer) {
+            req.extensions_mut().insert(ip);
+        }
+        self.inner.call(req)
+    }
+}
+
+/// Serves a batch by dispatching each of its calls through `service.call`, so the per call logic
+/// of a middleware applies to batched calls too. Calls run concurrently and their responses keep
+/// the order of the batch, notifications are not answered and malformed entries are answered
+/// with an invalid request error.
+pub async fn batch_via_call<S>(service: S, batch: Batch<'_>) -> MethodResponse
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync,
+{
+    let mut futures: FuturesOrdered<_> = batch
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(BatchEntry::Call(request)) => Some(Either::Right(service.call(request))),
+            Ok(BatchEntry::Notification(_notif)) => None,
+            Err(_) => Some(Either::Left(ready(MethodResponse::error(
+                Id::Null,
+                ErrorObject::from(ErrorCode::InvalidRequest),
+            )))),
+        })
+        .collect();
+
+    let mut batch_response = BatchResponseBuilder::new_with_limit(usize::MAX);
+    while let Some(response) = futures.next().await {
+        if let Err(err) = batch_response.append(response) {
+            return err;
+        }
+    }
+
+    MethodResponse::from_batch(batch_response.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::EchoService;
+    use serde_json::value::RawValue;
+
+    #[test]
+    fn test_client_key() {
+        let mut req = Request::owned("eth_getLogs".to_string(), None::<Box<RawValue>>, Id::Null);
+        assert_eq!(client_key(&req), None);
+
+        req.extensions_mut().insert(ConnectionId(7));
+        assert_eq!(client_key(&req), Some(ClientKey::Connection(ConnectionId(7))));
+
+        let addr = SocketAddr::from(([10, 0, 0, 1], 8545));
+        req.extensions_mut().insert(addr);
+        assert_eq!(client_key(&req), Some(ClientKey::Ip(addr.ip())));
+
+        // The client address attached by the HTTP layer wins over the address of the proxy
+        let client = IpAddr::from([192, 168, 0, 1]);
+        req.extensions_mut().insert(client);
+        assert_eq!(client_key(&req), Some(ClientKey::Ip(client)));
+    }
+
+    #[test]
+    fn test_forwarded_for() {
+        let request = |headers: &[&str]| {
+            let mut req = HttpRequest::new(());
+            for header in headers {
+                req.headers_mut().append(FORWARDED_FOR_HEADER, header.parse().unwrap());
+            }
+            req
+        };
+        assert_eq!(forwarded_for(&request(&[])), None);
+        assert_eq!(forwarded_for(&request(&["10.0.0.1"])), Some(IpAddr::from([10, 0, 0, 1])));
+
+        // Only the address appended by the proxy counts, the client sets the ones before it
+        let spoofed = request(&["1.1.1.1, 10.0.0.1"]);
+        assert_eq!(forwarded_for(&spoofed), Some(IpAddr::from([10, 0, 0, 1])));
+        let spoofed = request(&["1.1.1.1", "10.0.0.2"]);
+        assert_eq!(forwarded_for(&spoofed), Some(IpAddr::from([10, 0, 0, 2])));
+        assert_eq!(forwarded_for(&request(&["not an address"])), None);
+        assert_eq!(
+            forwarded_for(&request(&["::1"])),
+            Some(IpAddr::from(std::net::Ipv6Addr::LOCALHOST))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_via_call() {
+        let mut batch = Batch::new();
+        for (id, method) in [(1, "eth_chainId"), (2, "eth_blockNumber")] {
+            batch.push(Request::owned(method.to_string(), None::<Box<RawValue>>, Id::Number(id)));
+        }
+
+        let response = batch_via_call(EchoService, batch).await;
+        let responses: Vec<serde_json::Value> =
+            serde_json::from_str(response.as_json().get()).unwrap();
+        let results: Vec<_> = responses.iter().map(|response| &response["result"]).collect();
+        assert_eq!(results, ["eth_chainId", "eth_blockNumber"]);
+    }
+}
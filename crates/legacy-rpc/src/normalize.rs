@@ -0,0 +1,378 @@
+//! Normalization of legacy responses to the formatting of the local node.
+//!
+//! The legacy Erigon node formats some fields differently from reth: logs may lack the `removed`
+//! flag, addresses may be checksummed and quantities may carry leading zeros. Results merged from
+//! both nodes are rewritten to reth's canonical formatting so consumers see a single schema.
+//!
+//! Legacy receipts also lack the OP specific fields of local receipts. How these are reconciled
+//! is configured with a [`ReceiptCompatProfile`].
+
+use std::{fmt, str::FromStr};
+
+use jsonrpsee::MethodResponse;
+use jsonrpsee_types::Id;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Log fields holding quantities.
+const LOG_QUANTITY_FIELDS: &[&str] =
+    &["blockNumber", "blockTimestamp", "transactionIndex", "logIndex"];
+
+/// Log fields holding hex data, hashes or addresses.
+const LOG_DATA_FIELDS: &[&str] = &["address", "blockHash", "transactionHash", "data"];
+
+/// Receipt fields holding quantities.
+const RECEIPT_QUANTITY_FIELDS: &[&str] = &[
+    "type",
+    "status",
+    "cumulativeGasUsed",
+    "gasUsed",
+    "effectiveGasPrice",
+    "blockNumber",
+    "transactionIndex",
+];
+
+/// Receipt fields holding hex data, hashes or addresses.
+const RECEIPT_DATA_FIELDS: &[&str] =
+    &["blockHash", "transactionHash", "from", "to", "contractAddress", "logsBloom"];
+
+/// L1 fee fields of non-deposit OP receipts. Legacy blocks didn't pay an L1 data fee.
+const L1_FEE_FIELDS: &[&str] = &["l1GasPrice", "l1GasUsed", "l1Fee"];
+
+/// Fields of deposit OP receipts.
+const DEPOSIT_FIELDS: &[&str] = &["depositNonce", "depositReceiptVersion"];
+
+/// Fields of receipts served by the local node.
+const LOCAL_RECEIPT_FIELDS: &[&str] = &[
+    "type",
+    "status",
+    "root",
+    "cumulativeGasUsed",
+    "logs",
+    "logsBloom",
+    "transactionHash",
+    "transactionIndex",
+    "blockHash",
+    "blockNumber",
+    "gasUsed",
+    "effectiveGasPrice",
+    "blobGasUsed",
+    "blobGasPrice",
+    "from",
+    "to",
+    "contractAddress",
+    "l1GasPrice",
+    "l1GasUsed",
+    "l1Fee",
+    "l1FeeScalar",
+    "l1BaseFeeScalar",
+    "l1BlobBaseFee",
+    "l1BlobBaseFeeScalar",
+    "operatorFeeScalar",
+    "operatorFeeConstant",
+    "daFootprintGasScalar",
+    "depositNonce",
+    "depositReceiptVersion",
+];
+
+/// Type of OP deposit transactions.
+const DEPOSIT_TX_TYPE: &str = "0x7e";
+
+/// How receipts forwarded to the legacy node are reconciled with local receipts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReceiptCompatProfile {
+    /// Legacy receipts are returned as is
+    #[default]
+    Passthrough,
+    /// Formatting is normalized and missing OP fields are filled, L1 fees with zero and
+    /// deposit fields with `null`
+    Fill,
+    /// Like [`ReceiptCompatProfile::Fill`], additionally omitting fields the local node never
+    /// returns
+    Strict,
+}
+
+impl fmt::Display for ReceiptCompatProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Passthrough => f.write_str("passthrough"),
+            Self::Fill => f.write_str("fill"),
+            Self::Strict => f.write_str("strict"),
+        }
+    }
+}
+
+impl FromStr for ReceiptCompatProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "passthrough" => Ok(Self::Passthrough),
+            "fill" => Ok(Self::Fill),
+            "strict" => Ok(Self::Strict),
+            _ => Err(format!(
+                "unknown receipt profile `{s}`, expected one of passthrough, fill, strict"
+            )),
+        }
+    }
+}
+
+/// Returns `true` if the method returns receipts.
+pub(crate) fn is_receipt_method(method: &str) -> bool {
+    matches!(method, "eth_getTransactionReceipt" | "eth_getBlockReceipts")
+}
+
+/// Rewrites a hex quantity without leading zeros, e.g. `0x001a` to `0x1a`.
+pub(crate) fn normalize_quantity(value: &mut Value) {
+    let Some(s) = value.as_str() else {
+        return;
+    };
+    let Some(digits) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) else {
+        return;
+    };
+    let digits = digits.trim_start_matches('0').to_ascii_lowercase();
+    *value =
+        Value::String(if digits.is_empty() { "0x0".to_string() } else { format!("0x{digits}") });
+}
+
+/// Rewrites hex data in lowercase, which also drops the checksum of addresses.
+pub(crate) fn normalize_data(value: &mut Value) {
+    if let Some(s) = value.as_str()
+        && s.bytes().any(|b| b.is_ascii_uppercase())
+    {
+        *value = Value::String(s.to_ascii_lowercase());
+    }
+}
+
+/// Rewrites a single `eth_getLogs` entry to the canonical formatting.
+pub(crate) fn normalize_log(log: &mut Value) {
+    let Some(obj) = log.as_object_mut() else {
+        return;
+    };
+
+    for field in LOG_QUANTITY_FIELDS {
+        if let Some(value) = obj.get_mut(*field) {
+            normalize_quantity(value);
+        }
+    }
+    for field in LOG_DATA_FIELDS {
+        if let Some(value) = obj.get_mut(*field) {
+            normalize_data(value);
+        }
+    }
+    if let Some(topics) = obj.get_mut("topics").and_then(Value::as_array_mut) {
+        topics.iter_mut().for_each(normalize_data);
+    }
+
+    // Erigon omits the flag for logs of canonical blocks
+    obj.entry("removed").or_insert(Value::Bool(false));
+}
+
+/// Rewrites a single receipt according to the profile.
+pub(crate) fn normalize_receipt(receipt: &mut Value, profile: ReceiptCompatProfile) {
+    if profile == ReceiptCompatProfile::Passthrough {
+        return;
+    }
+    let Some(obj) = receipt.as_object_mut() else {
+        return;
+    };
+
+    for field in RECEIPT_QUANTITY_FIELDS {
+        if let Some(value) = obj.get_mut(*field) {
+            normalize_quantity(value);
+        }
+    }
+    for field in RECEIPT_DATA_FIELDS {
+        if let Some(value) = obj.get_mut(*field) {
+            normalize_data(value);
+        }
+    }
+    if let Some(logs) = obj.get_mut("logs").and_then(Value::as_array_mut) {
+        logs.iter_mut().for_each(normalize_log);
+    }
+
+    if obj.get("type").and_then(Value::as_str) == Some(DEPOSIT_TX_TYPE) {
+        for field in DEPOSIT_FIELDS {
+            obj.entry(*field).or_insert(Value::Null);
+        }
+    } else {
+        for field in L1_FEE_FIELDS {
+            obj.entry(*field).or_insert_with(|| Value::String("0x0".to_string()));
+        }
+    }
+
+    if profile == ReceiptCompatProfile::Strict {
+        obj.retain(|field, _| LOCAL_RECEIPT_FIELDS.contains(&field.as_str()));
+    }
+}
+
+/// Rewrites the receipts of a legacy `eth_getTransactionReceipt` or `eth_getBlockReceipts`
+/// response according to the profile. Error responses are returned unchanged.
+pub(crate) fn normalize_receipts_response(
+    response: MethodResponse,
+    request_id: Id<'_>,
+    profile: ReceiptCompatProfile,
+) -> MethodResponse {
+    if profile == ReceiptCompatProfile::Passthrough || !response.is_success() {
+        return response;
+    }
+    let Ok(mut json) = serde_json::from_str::<Value>(response.as_json().get()) else {
+        return response;
+    };
+    let Some(result) = json.get_mut("result") else {
+        return response;
+    };
+
+    match result {
+        Value::Array(receipts) => {
+            receipts.iter_mut().for_each(|receipt| normalize_receipt(receipt, profile))
+        }
+        receipt => normalize_receipt(receipt, profile),
+    }
+
+    let payload = jsonrpsee_types::ResponsePayload::success(&*result).into();
+    MethodResponse::response(request_id, payload, usize::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_quantity() {
+        let cases = [("0x001a", "0x1a"), ("0x0", "0x0"), ("0x0000", "0x0"), ("0xAB", "0xab")];
+        for (input, expected) in cases {
+            let mut value = Value::String(input.to_string());
+            normalize_quantity(&mut value);
+            assert_eq!(value, expected);
+        }
+
+        // Non hex values are left untouched
+        let mut value = Value::from(26);
+        normalize_quantity(&mut value);
+        assert_eq!(value, 26);
+    }
+
+    #[test]
+    fn test_normalize_legacy_log() {
+        // Log as returned by the legacy Erigon node
+        let mut log: Value = serde_json::from_str(
+            r#"{
+                "address": "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+                "topics": ["0xDDF252AD1BE2C89B69C2B068FC378DAA952BA7F163C4A11628F55A4DF523B3EF"],
+                "data": "0x00000000000000000000000000000000000000000000000000000000000000FF",
+                "blockNumber": "0x0063",
+                "blockHash": "0x8c83240f457f709b4574dd57afb656242418ea481325ea3c284c4ba144c1e032",
+                "transactionHash": "0x3A6E4D4F0C9B8B3E1B5F7F2A9C6D0E1F2A3B4C5D6E7F8091A2B3C4D5E6F70809",
+                "transactionIndex": "0x00",
+                "logIndex": "0x01"
+            }"#,
+        )
+        .unwrap();
+        normalize_log(&mut log);
+
+        // Log as returned by reth
+        let expected: Value = serde_json::from_str(
+            r#"{
+                "address": "0x5fbdb2315678afecb367f032d93f642f64180aa3",
+                "topics": ["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"],
+                "data": "0x00000000000000000000000000000000000000000000000000000000000000ff",
+                "blockNumber": "0x63",
+                "blockHash": "0x8c83240f457f709b4574dd57afb656242418ea481325ea3c284c4ba144c1e032",
+                "transactionHash": "0x3a6e4d4f0c9b8b3e1b5f7f2a9c6d0e1f2a3b4c5d6e7f8091a2b3c4d5e6f70809",
+                "transactionIndex": "0x0",
+                "logIndex": "0x1",
+                "removed": false
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(log, expected);
+
+        // Normalizing a canonical log is a no-op
+        let mut canonical = expected.clone();
+        normalize_log(&mut canonical);
+        assert_eq!(canonical, expected);
+    }
+
+    /// Receipt as returned by the legacy Erigon node
+    const LEGACY_RECEIPT: &str = r#"{
+        "blockHash": "0x8c83240f457f709b4574dd57afb656242418ea481325ea3c284c4ba144c1e032",
+        "blockNumber": "0x0063",
+        "contractAddress": null,
+        "cumulativeGasUsed": "0x5208",
+        "effectiveGasPrice": "0x3b9aca00",
+        "from": "0x5FbDB2315678afecb367f032d93F642f64180aa3",
+        "gasUsed": "0x5208",
+        "logs": [],
+        "logsBloom": "0x00",
+        "status": "0x1",
+        "to": "0x1111111111111111111111111111111111111111",
+        "transactionHash": "0x3a6e4d4f0c9b8b3e1b5f7f2a9c6d0e1f2a3b4c5d6e7f8091a2b3c4d5e6f70809",
+        "transactionIndex": "0x0",
+        "type": "0x2",
+        "effectiveGasPriceForZkevm": "0x3b9aca00"
+    }"#;
+
+    #[test]
+    fn test_normalize_receipt_profiles() {
+        let legacy: Value = serde_json::from_str(LEGACY_RECEIPT).unwrap();
+
+        let mut receipt = legacy.clone();
+        normalize_receipt(&mut receipt, ReceiptCompatProfile::Passthrough);
+        assert_eq!(receipt, legacy);
+
+        let mut receipt = legacy.clone();
+        normalize_receipt(&mut receipt, ReceiptCompatProfile::Fill);
+        assert_eq!(receipt["blockNumber"], "0x63");
+        assert_eq!(receipt["from"], "0x5fbdb2315678afecb367f032d93f642f64180aa3");
+        assert_eq!(receipt["l1Fee"], "0x0");
+        assert_eq!(receipt["l1GasUsed"], "0x0");
+        assert!(receipt.get("depositNonce").is_none());
+        assert_eq!(receipt["effectiveGasPriceForZkevm"], "0x3b9aca00");
+
+        let mut receipt = legacy;
+        normalize_receipt(&mut receipt, ReceiptCompatProfile::Strict);
+        assert_eq!(receipt["l1Fee"], "0x0");
+        assert!(receipt.get("effectiveGasPriceForZkevm").is_none());
+    }
+
+    #[test]
+    fn test_normalize_deposit_receipt() {
+        let mut receipt: Value =
+            serde_json::from_str(r#"{"type": "0x7e", "status": "0x1"}"#).unwrap();
+        normalize_receipt(&mut receipt, ReceiptCompatProfile::Fill);
+        assert_eq!(receipt["depositNonce"], Value::Null);
+        assert_eq!(receipt["depositReceiptVersion"], Value::Null);
+        assert!(receipt.get("l1Fee").is_none());
+    }
+
+    #[test]
+    fn test_normalize_receipts_response() {
+        let receipts: Value = serde_json::from_str(&format!("[{LEGACY_RECEIPT}]")).unwrap();
+        let response = MethodResponse::response(
+            Id::Number(1),
+            jsonrpsee_types::ResponsePayload::success(&receipts).into(),
+            usize::MAX,
+        );
+
+        let response =
+            normalize_receipts_response(response, Id::Number(1), ReceiptCompatProfile::Strict);
+        let json: Value = serde_json::from_str(response.as_json().get()).unwrap();
+        assert_eq!(json["id"], 1);
+        assert_eq!(json["result"][0]["l1Fee"], "0x0");
+        assert!(json["result"][0].get("effectiveGasPriceForZkevm").is_none());
+    }
+
+    #[test]
+    fn test_receipt_profile_from_str() {
+        for profile in [
+            ReceiptCompatProfile::Passthrough,
+            ReceiptCompatProfile::Fill,
+            ReceiptCompatProfile::Strict,
+        ] {
+            assert_eq!(profile.to_string().parse::<ReceiptCompatProfile>(), Ok(profile));
+        }
+        assert!("lenient".parse::<ReceiptCompatProfile>().is_err());
+    }
+}
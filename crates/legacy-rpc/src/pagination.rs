@@ -0,0 +1,162 @@
+//! Pagination shared by the paginated X Layer RPC methods.
+//!
+//! Paginated methods take a `pageToken` and a `pageSize` as their last two params and return the
+//! items of the page with a `nextPageToken`, `null` once there are no more items. Every method
+//! declares its [`PageLimits`]: the size of a page requested without `pageSize`, and the largest
+//! size a client may request, enforced on the server.
+//!
+//! Page tokens are opaque to clients. They hold the position where the next page continues as a
+//! sequence of integers, encoded as lowercase hex joined by `-`, e.g. `10-2-ff`. Methods map their
+//! positions to and from these fields with [`encode_page_token`] and [`decode_page_token`], so
+//! tokens are validated the same way everywhere.
+
+use jsonrpsee::types::{error::INVALID_PARAMS_CODE, ErrorObject};
+use serde_json::{value::RawValue, Value};
+
+/// Separator of the fields of a page token.
+const TOKEN_SEPARATOR: char = '-';
+
+/// Most fields a page token may hold.
+const MAX_TOKEN_FIELDS: usize = 8;
+
+/// Page size bounds of a paginated method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageLimits {
+    /// Size of a page requested without `pageSize`
+    pub default_size: usize,
+    /// Largest accepted `pageSize`
+    pub max_size: usize,
+}
+
+impl PageLimits {
+    pub const fn new(default_size: usize, max_size: usize) -> Self {
+        Self { default_size, max_size }
+    }
+
+    /// Returns the page size of a request, the default if `size` is `None`.
+    pub fn page_size(&self, size: Option<usize>) -> Result<usize, PageParamError> {
+        let size = size.unwrap_or(self.default_size);
+        if size == 0 || size > self.max_size {
+            return Err(PageParamError::SizeOutOfRange { max: self.max_size });
+        }
+        Ok(size)
+    }
+
+    /// Parses a raw `pageSize` param, a number or a hex quantity. A missing or `null` size is the
+    /// default.
+    pub fn parse_page_size(&self, size: Option<&RawValue>) -> Result<usize, PageParamError> {
+        let size = match size.map(|size| serde_json::from_str(size.get())) {
+            None | Some(Ok(Value::Null)) => None,
+            Some(Ok(Value::Number(size))) => {
+                let size = size.as_u64().ok_or(PageParamError::InvalidSize)?;
+                Some(usize::try_from(size).map_err(|_| PageParamError::InvalidSize)?)
+            }
+            Some(Ok(Value::String(size))) => Some(
+                size.strip_prefix("0x")
+                    .and_then(|size| usize::from_str_radix(size, 16).ok())
+                    .ok_or(PageParamError::InvalidSize)?,
+            ),
+            Some(_) => return Err(PageParamError::InvalidSize),
+        };
+        self.page_size(size)
+    }
+}
+
+/// Parses a raw `pageToken` param, `None` if it is missing or `null`.
+pub fn parse_page_token(token: Option<&RawValue>) -> Result<Option<&str>, PageParamError> {
+    match token.map(|token| serde_json::from_str::<Option<&str>>(token.get())) {
+        None | Some(Ok(None)) => Ok(None),
+        Some(Ok(Some(token))) => Ok(Some(token)),
+        Some(Err(_)) => Err(PageParamError::InvalidToken),
+    }
+}
+
+/// Encodes the position of a page token.
+pub fn encode_page_token(fields: &[u64]) -> String {
+    let fields: Vec<String> = fields.iter().map(|field| format!("{field:x}")).collect();
+    fields.join(&TOKEN_SEPARATOR.to_string())
+}
+
+/// Decodes the position of a page token, `None` if the token is malformed.
+pub fn decode_page_token(token: &str) -> Option<Vec<u64>> {
+    let fields = token
+        .split(TOKEN_SEPARATOR)
+        .map(|field| {
+            let valid = !field.is_empty() && field.bytes().all(|b| b.is_ascii_hexdigit());
+            valid.then(|| u64::from_str_radix(field, 16).ok()).flatten()
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (fields.len() <= MAX_TOKEN_FIELDS).then_some(fields)
+}
+
+/// Invalid pagination param.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageParamError {
+    InvalidToken,
+    InvalidSize,
+    SizeOutOfRange { max: usize },
+}
+
+impl std::fmt::Display for PageParamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidToken => f.write_str("Invalid page token"),
+            Self::InvalidSize => f.write_str("Invalid page size"),
+            Self::SizeOutOfRange { max } => write!(f, "Page size must be between 1 and {max}"),
+        }
+    }
+}
+
+impl std::error::Error for PageParamError {}
+
+impl From<PageParamError> for ErrorObject<'static> {
+    fn from(error: PageParamError) -> Self {
+        ErrorObject::owned(INVALID_PARAMS_CODE, error.to_string(), None::<()>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn test_page_size() {
+        let limits = PageLimits::new(100, 1_000);
+        assert_eq!(limits.parse_page_size(None), Ok(100));
+        assert_eq!(limits.parse_page_size(Some(&raw("null"))), Ok(100));
+        assert_eq!(limits.parse_page_size(Some(&raw("1000"))), Ok(1_000));
+        assert_eq!(limits.parse_page_size(Some(&raw(r#""0x10""#))), Ok(16));
+
+        let out_of_range = Err(PageParamError::SizeOutOfRange { max: 1_000 });
+        assert_eq!(limits.parse_page_size(Some(&raw("0"))), out_of_range);
+        assert_eq!(limits.parse_page_size(Some(&raw("1001"))), out_of_range);
+        assert_eq!(limits.page_size(Some(1_001)), out_of_range);
+        for invalid in ["-1", "1.5", r#""10""#, "true"] {
+            assert_eq!(
+                limits.parse_page_size(Some(&raw(invalid))),
+                Err(PageParamError::InvalidSize),
+                "{invalid}"
+            );
+        }
+        assert_eq!(out_of_range.unwrap_err().to_string(), "Page size must be between 1 and 1000");
+    }
+
+    #[test]
+    fn test_page_token() {
+        assert_eq!(encode_page_token(&[16, 2, 255]), "10-2-ff");
+        assert_eq!(decode_page_token("10-2-ff"), Some(vec![16, 2, 255]));
+        assert_eq!(decode_page_token("10"), Some(vec![16]));
+        for invalid in ["", "10-", "-10", "0x10", "+10", "xyz", "10--2", "1-2-3-4-5-6-7-8-9"] {
+            assert_eq!(decode_page_token(invalid), None, "{invalid}");
+        }
+
+        assert_eq!(parse_page_token(None), Ok(None));
+        assert_eq!(parse_page_token(Some(&raw("null"))), Ok(None));
+        assert_eq!(parse_page_token(Some(&raw(r#""10-2""#))), Ok(Some("10-2")));
+        assert_eq!(parse_page_token(Some(&raw("16"))), Err(PageParamError::InvalidToken));
+    }
+}
@@ -0,0 +1,216 @@
+//! Borrowed views of request params and responses for the routing hot path.
+//!
+//! Routing only needs a few fields of a request, e.g. its block param or the block range of an
+//! `eth_getLogs` filter. Instead of building a `serde_json::Value` tree for the whole params, the
+//! params array is split into [`RawValue`] slices of the original string and only the inspected
+//! fields are decoded, borrowing their strings. Strings containing escapes can't be borrowed and
+//! are treated as unparsable, no hex quantity or tag needs escaping.
+
+use serde::{Deserialize, Deserializer};
+use serde_json::value::RawValue;
+
+use crate::is_valid_32_bytes_string;
+
+/// Block targeted by a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockParam<'a> {
+    Number(u64),
+    /// Validated 32 bytes hex block hash
+    Hash(&'a str),
+}
+
+/// Filter of an `eth_getLogs` request, borrowing from its params.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogsFilterRef<'a> {
+    #[serde(borrow)]
+    pub from_block: Option<&'a RawValue>,
+    #[serde(borrow)]
+    pub to_block: Option<&'a RawValue>,
+    #[serde(borrow)]
+    pub block_hash: Option<&'a RawValue>,
+    #[serde(borrow)]
+    pub address: Option<&'a RawValue>,
+}
+
+impl<'a> LogsFilterRef<'a> {
+    /// Parses the filter object of `eth_getLogs` params.
+    pub fn parse(params: &'a str) -> Option<Self> {
+        serde_json::from_str(param_at(params, 0)?.get()).ok()
+    }
+}
+
+/// Result of a JSON-RPC response, `None` for error responses.
+#[derive(Deserialize)]
+struct ResponseRef<'a> {
+    #[serde(borrow, default, deserialize_with = "present")]
+    result: Option<&'a RawValue>,
+}
+
+/// Deserializes a field that is present, keeping a `null` value as `Some`.
+pub(crate) fn present<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<&'de RawValue>, D::Error> {
+    <&RawValue>::deserialize(deserializer).map(Some)
+}
+
+/// Returns the param at `index` of a params array.
+pub fn param_at(params: &str, index: usize) -> Option<&RawValue> {
+    let params: Vec<&RawValue> = serde_json::from_str(params).ok()?;
+    params.get(index).copied()
+}
+
+/// Returns the string of a JSON string value.
+pub fn as_str(value: &RawValue) -> Option<&str> {
+    serde_json::from_str(value.get()).ok()
+}
+
+/// Returns the result of a JSON-RPC response, `None` if it has none.
+pub fn response_result(response: &str) -> Option<&RawValue> {
+    serde_json::from_str::<ResponseRef<'_>>(response).ok()?.result
+}
+
+/// Parses the block param at `index`: a block number, tag, hash, or a `blockHash` or
+/// `blockNumber` object.
+///
+/// Tags of the current chain state return `None`, they are never routed to legacy, and
+/// `earliest` returns block 0.
+pub fn parse_block_param(params: &str, index: usize) -> Option<BlockParam<'_>> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct BlockObject<'a> {
+        #[serde(borrow)]
+        block_hash: Option<&'a str>,
+        #[serde(borrow)]
+        block_number: Option<&'a str>,
+    }
+
+    let param = param_at(params, index)?.get();
+    match param.as_bytes().first()? {
+        b'"' => match serde_json::from_str::<&str>(param).ok()? {
+            // Don't route these to legacy (use current chain state)
+            "latest" | "pending" | "safe" | "finalized" => None,
+
+            // Route to legacy (not genesis, as local has no data)
+            "earliest" => Some(BlockParam::Number(0)),
+
+            // Validate hashes are proper 32-byte hex strings to prevent JSON injection
+            hex if hex.len() == 66 => {
+                is_valid_32_bytes_string(hex).then_some(BlockParam::Hash(hex))
+            }
+
+            hex => {
+                let number = hex.strip_prefix("0x")?;
+                u64::from_str_radix(number, 16).ok().map(BlockParam::Number)
+            }
+        },
+        b'{' => {
+            let object: BlockObject<'_> = serde_json::from_str(param).ok()?;
+            if let Some(hash) = object.block_hash {
+                is_valid_32_bytes_string(hash).then_some(BlockParam::Hash(hash))
+            } else {
+                let number = object.block_number?;
+                match number.strip_prefix("0x") {
+                    Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                    None => number.parse().ok(),
+                }
+                .map(BlockParam::Number)
+            }
+        }
+        // decimal number not handled...
+        _ => None,
+    }
+}
+
+/// Returns whether a JSON value is `null`, an empty object or an empty array.
+pub fn is_empty_value(value: &RawValue) -> bool {
+    let value = value.get().trim();
+    value == "null"
+        || [("{", "}"), ("[", "]")].iter().any(|(open, close)| {
+            value
+                .strip_prefix(open)
+                .and_then(|value| value.strip_suffix(close))
+                .is_some_and(|inner| inner.trim().is_empty())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HASH: &str = "0x8c83240f457f709b4574dd57afb656242418ea481325ea3c284c4ba144c1e032";
+
+    #[test]
+    fn test_parse_block_param() {
+        let cases = [
+            (r#"["0x10", false]"#, 0, Some(BlockParam::Number(16))),
+            (r#"["earliest"]"#, 0, Some(BlockParam::Number(0))),
+            (r#"["latest"]"#, 0, None),
+            (r#"["pending"]"#, 0, None),
+            (
+                r#"["0x1111111111111111111111111111111111111111", "0x20"]"#,
+                1,
+                Some(BlockParam::Number(32)),
+            ),
+            (r#"["0x1111111111111111111111111111111111111111"]"#, 1, None),
+            (r#"[{"blockNumber": "0x10"}]"#, 0, Some(BlockParam::Number(16))),
+            (r#"[{"blockNumber": "16"}]"#, 0, Some(BlockParam::Number(16))),
+            (r#"["10"]"#, 0, None),
+            (r#"[16]"#, 0, None),
+            (r#"not json"#, 0, None),
+        ];
+        for (params, index, expected) in cases {
+            assert_eq!(parse_block_param(params, index), expected, "{params}");
+        }
+
+        let params = format!(r#"["{HASH}"]"#);
+        assert_eq!(parse_block_param(&params, 0), Some(BlockParam::Hash(HASH)));
+        let params = format!(r#"[{{"blockHash": "{HASH}", "requireCanonical": true}}]"#);
+        assert_eq!(parse_block_param(&params, 0), Some(BlockParam::Hash(HASH)));
+        let params = format!(r#"["{}zz"]"#, &HASH[..64]);
+        assert_eq!(parse_block_param(&params, 0), None);
+    }
+
+    #[test]
+    fn test_logs_filter_ref() {
+        let params = r#"[{"fromBlock":"0x1","toBlock":"latest","address":["0x1111111111111111111111111111111111111111"],"topics":[]}]"#;
+        let filter = LogsFilterRef::parse(params).unwrap();
+        assert_eq!(filter.from_block.and_then(as_str), Some("0x1"));
+        assert_eq!(filter.to_block.and_then(as_str), Some("latest"));
+        assert!(filter.block_hash.is_none());
+        assert_eq!(
+            filter.address.map(RawValue::get),
+            Some(r#"["0x1111111111111111111111111111111111111111"]"#)
+        );
+
+        assert!(LogsFilterRef::parse("[]").is_none());
+        assert!(LogsFilterRef::parse(r#"["0x1"]"#).is_none());
+    }
+
+    #[test]
+    fn test_response_result() {
+        let result = |response| response_result(response).map(RawValue::get);
+        assert_eq!(
+            result(r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x1"}}"#),
+            Some(r#"{"number":"0x1"}"#)
+        );
+        assert_eq!(result(r#"{"jsonrpc":"2.0","id":1,"result":null}"#), Some("null"));
+        assert_eq!(
+            result(r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"x"}}"#),
+            None
+        );
+    }
+
+    #[test]
+    fn test_is_empty_value() {
+        let empty =
+            |value: &str| is_empty_value(&RawValue::from_string(value.to_string()).unwrap());
+        assert!(empty("null"));
+        assert!(empty("{}"));
+        assert!(empty("[ ]"));
+        assert!(!empty(r#"{"a":1}"#));
+        assert!(!empty("[0]"));
+        assert!(!empty(r#""0x""#));
+        assert!(!empty("0"));
+    }
+}
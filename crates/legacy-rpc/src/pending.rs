@@ -0,0 +1,144 @@
+//! Proxy for `pending` tag reads on replicas without flashblocks.
+//!
+//! Plain replicas answer `pending` like `latest`. When a flashblocks enabled node is configured,
+//! reads against the `pending` tag are forwarded to it so sub-second pending data can be served
+//! without running the flashblocks stack locally.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::Request,
+    MethodResponse,
+};
+use reqwest::Client;
+use tower::Layer;
+use tracing::{debug, info};
+
+use crate::{
+    middleware::batch_via_call,
+    service::{block_param_pos, need_parse_block},
+};
+
+/// Configuration for pending tag proxying
+#[derive(Clone, Debug)]
+pub struct PendingRpcProxyConfig {
+    pub endpoint: String,
+    pub timeout: Duration,
+}
+
+/// Returns `true` if the block parameter of the request is the `pending` tag.
+fn is_pending_request(method: &str, params: &str) -> bool {
+    let Ok(serde_json::Value::Array(params)) = serde_json::from_str(params) else {
+        return false;
+    };
+    params.get(block_param_pos(method)).and_then(|p| p.as_str()) == Some("pending")
+}
+
+/// Layer that creates the pending proxy middleware
+#[derive(Clone)]
+pub struct PendingRpcProxyLayer {
+    config: Option<Arc<PendingRpcProxyConfig>>,
+    client: Client,
+}
+
+impl PendingRpcProxyLayer {
+    /// Creates the layer, proxying is disabled if no config is given.
+    pub fn new(config: Option<PendingRpcProxyConfig>) -> Self {
+        if let Some(config) = &config {
+            info!(target:"xlayer_legacy_rpc", endpoint = %config.endpoint, "xlayer pending rpc proxy enabled");
+        }
+        Self { config: config.map(Arc::new), client: Client::new() }
+    }
+}
+
+impl<S> Layer<S> for PendingRpcProxyLayer {
+    type Service = PendingRpcProxyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PendingRpcProxyService { inner, config: self.config.clone(), client: self.client.clone() }
+    }
+}
+
+/// Service forwarding `pending` tag reads to a flashblocks enabled node
+#[derive(Clone)]
+pub struct PendingRpcProxyService<S> {
+    inner: S,
+    config: Option<Arc<PendingRpcProxyConfig>>,
+    client: Client,
+}
+
+impl<S> RpcServiceT for PendingRpcProxyService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let method = req.method_name();
+        let Some(config) = self.config.clone() else {
+            return Either::Left(self.inner.call(req));
+        };
+        if !need_parse_block(method)
+            || !req.params().as_str().is_some_and(|params| is_pending_request(method, params))
+        {
+            return Either::Left(self.inner.call(req));
+        }
+
+        debug!(target:"xlayer_legacy_rpc", "Route pending tag request to pending proxy, method = {}", method);
+        let client = self.client.clone();
+        Either::Right(Box::pin(async move {
+            crate::forward_request(&client, &config.endpoint, config.timeout, "Pending", req).await
+        }))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if self.config.is_none() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so batched pending reads are proxied too
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_pending_request() {
+        assert!(is_pending_request("eth_getBlockByNumber", r#"["pending", false]"#));
+        assert!(is_pending_request(
+            "eth_getBalance",
+            r#"["0x0000000000000000000000000000000000000001", "pending"]"#
+        ));
+        assert!(is_pending_request(
+            "eth_getStorageAt",
+            r#"["0x0000000000000000000000000000000000000001", "0x0", "pending"]"#
+        ));
+
+        assert!(!is_pending_request("eth_getBlockByNumber", r#"["latest", false]"#));
+        assert!(!is_pending_request("eth_getBlockByNumber", r#"["0x10", false]"#));
+        // Missing block param defaults to latest
+        assert!(!is_pending_request(
+            "eth_call",
+            r#"[{"to": "0x0000000000000000000000000000000000000001"}]"#
+        ));
+    }
+}
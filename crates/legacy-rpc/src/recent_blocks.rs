@@ -0,0 +1,118 @@
+//! Index of recent canonical block hashes for routing decisions.
+//!
+//! Methods taking a block hash are routed by looking the block up on the local node, one
+//! `eth_getBlockByHash` call per request before the request itself is served. Most of these
+//! hashes reference recent blocks, so the hash → number of the latest canonical blocks is kept in
+//! an LRU fed from the [invalidation bus](crate::invalidation) and consulted first. Blocks
+//! reverted by a reorg are removed, a miss falls back to the local lookup.
+
+use alloy_primitives::{BlockNumber, B256};
+use metrics::Counter;
+use moka::{policy::EvictionPolicy, sync::Cache};
+use reth_metrics::Metrics;
+
+use crate::invalidation::{Invalidation, InvalidationHandler};
+
+/// Default number of indexed blocks.
+pub const RECENT_BLOCKS_CAPACITY: u64 = 8_192;
+
+/// Recent block index metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_legacy_rpc_recent_blocks")]
+struct RecentBlocksMetrics {
+    /// Block hash lookups answered by the index
+    hits: Counter,
+    /// Block hash lookups that fell back to the local node
+    misses: Counter,
+}
+
+/// LRU of recent canonical block hashes and their numbers.
+pub struct RecentBlocks {
+    numbers: Cache<B256, BlockNumber>,
+    metrics: RecentBlocksMetrics,
+}
+
+impl Default for RecentBlocks {
+    fn default() -> Self {
+        Self::new(RECENT_BLOCKS_CAPACITY)
+    }
+}
+
+impl RecentBlocks {
+    /// Creates an index of up to `capacity` blocks.
+    pub fn new(capacity: u64) -> Self {
+        let numbers =
+            Cache::builder().max_capacity(capacity).eviction_policy(EvictionPolicy::lru()).build();
+        Self { numbers, metrics: RecentBlocksMetrics::default() }
+    }
+
+    /// Returns the number of a recent canonical block by its validated 32 bytes hex hash.
+    pub fn number(&self, block_hash: &str) -> Option<BlockNumber> {
+        let number = block_hash.parse::<B256>().ok().and_then(|hash| self.numbers.get(&hash));
+        match number {
+            Some(_) => self.metrics.hits.increment(1),
+            None => self.metrics.misses.increment(1),
+        }
+        number
+    }
+
+    /// Indexes a canonical block.
+    pub fn insert(&self, hash: B256, number: BlockNumber) {
+        self.numbers.insert(hash, number);
+    }
+
+    /// Removes a block that is no longer canonical.
+    pub fn remove(&self, hash: &B256) {
+        self.numbers.invalidate(hash);
+    }
+}
+
+impl InvalidationHandler for RecentBlocks {
+    /// Removes the blocks reverted by a reorg and indexes the committed ones.
+    fn invalidate(&self, event: &Invalidation) {
+        match event {
+            Invalidation::NewHead { number, hash } => self.insert(*hash, *number),
+            Invalidation::Reorged { reverted } => {
+                for (_, hash) in reverted {
+                    self.remove(hash);
+                }
+            }
+            Invalidation::Finalized { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_block_lookup() {
+        let blocks = RecentBlocks::new(2);
+        let hash = B256::with_last_byte(0xab);
+        blocks.insert(hash, 100);
+
+        // Hashes are matched regardless of the case of their hex digits
+        assert_eq!(blocks.number(&format!("{hash:#x}")), Some(100));
+        let upper = format!("0x{}", format!("{hash:x}").to_uppercase());
+        assert_eq!(blocks.number(&upper), Some(100));
+        assert_eq!(blocks.number(&format!("{:#x}", B256::with_last_byte(1))), None);
+        assert_eq!(blocks.number("0x1234"), None);
+
+        blocks.remove(&hash);
+        assert_eq!(blocks.number(&format!("{hash:#x}")), None);
+    }
+
+    #[test]
+    fn test_recent_blocks_invalidation() {
+        let blocks = RecentBlocks::new(4);
+        let (old, new) = (B256::with_last_byte(0x0a), B256::with_last_byte(0x0b));
+        blocks.invalidate(&Invalidation::NewHead { number: 10, hash: old });
+        assert_eq!(blocks.number(&format!("{old:#x}")), Some(10));
+
+        blocks.invalidate(&Invalidation::Reorged { reverted: vec![(10, old)] });
+        blocks.invalidate(&Invalidation::NewHead { number: 10, hash: new });
+        assert_eq!(blocks.number(&format!("{old:#x}")), None);
+        assert_eq!(blocks.number(&format!("{new:#x}")), Some(10));
+    }
+}
@@ -1,15 +1,26 @@
 use std::future::Future;
 
-use futures::{future::Either, stream::FuturesOrdered, StreamExt};
+use futures::future::Either;
 use jsonrpsee::{
-    core::middleware::{Batch, BatchEntry, Notification},
+    core::middleware::{Batch, Notification},
     server::middleware::rpc::RpcServiceT,
-    types::{error::INVALID_PARAMS_CODE, ErrorCode, ErrorObject, Id, Request},
-    BatchResponseBuilder, MethodResponse,
+    types::{
+        error::{INVALID_PARAMS_CODE, METHOD_NOT_FOUND_CODE},
+        ErrorObject, Request,
+    },
+    MethodResponse,
 };
+use std::sync::Arc;
 use tracing::debug;
 
-use crate::LegacyRpcRouterService;
+use crate::{
+    block_by_timestamp::{handle_get_block_by_timestamp, BLOCK_BY_TIMESTAMP_METHOD},
+    logs_paged::{handle_get_logs_paged, LOGS_PAGED_METHOD},
+    middleware::batch_via_call,
+    params::{self, BlockParam},
+    tx_lookup::TxLookupStage,
+    LegacyRpcRouterConfig, LegacyRpcRouterService, LookupOrder,
+};
 
 /// Only these methods should be considered for legacy routing.
 #[inline]
@@ -39,12 +50,15 @@ pub fn is_legacy_routable(method: &str) -> bool {
             | "eth_createAccessList"
             | "eth_getLogs"
             | "debug_traceTransaction"
+            | "debug_getRawBlock"
+            | "debug_getRawHeader"
+            | "debug_getRawReceipts"
     )
 }
 
 /// Takes block number/hash as param
 #[inline]
-fn need_parse_block(method: &str) -> bool {
+pub(crate) fn need_parse_block(method: &str) -> bool {
     matches!(
         method,
         "eth_getBlockByNumber"
@@ -60,6 +74,9 @@ fn need_parse_block(method: &str) -> bool {
             | "eth_call"
             | "eth_estimateGas"
             | "eth_createAccessList"
+            | "debug_getRawBlock"
+            | "debug_getRawHeader"
+            | "debug_getRawReceipts"
     )
 }
 
@@ -76,11 +93,16 @@ fn can_use_block_hash_as_param(method: &str) -> bool {
             | "eth_call"
             | "eth_estimateGas"
             | "eth_createAccessList"
+            | "debug_getRawBlock"
+            | "debug_getRawHeader"
+            | "debug_getRawReceipts"
     )
 }
 
+/// Looks up data by hash, asked from the local and the legacy node in the configured
+/// [`LookupOrder`]
 #[inline]
-fn need_try_local_then_legacy(method: &str) -> bool {
+pub(crate) fn need_try_local_then_legacy(method: &str) -> bool {
     matches!(
         method,
         "eth_getTransactionByHash"
@@ -98,20 +120,8 @@ fn need_try_local_then_legacy(method: &str) -> bool {
 /// Check if the response has a non-empty result.
 /// Returns true if the result is null, an empty object {}, or an empty array [].
 pub(crate) fn is_result_empty(response: &MethodResponse) -> bool {
-    // Parse the JSON response
-    let json_str = response.as_ref();
-    if let Ok(json) = serde_json::from_str::<serde_json::Value>(json_str)
-        && let Some(result) = json.get("result")
-    {
-        match result {
-            serde_json::Value::Null => return true,
-            serde_json::Value::Object(obj) => return obj.is_empty(),
-            serde_json::Value::Array(arr) => return arr.is_empty(),
-            _ => return false,
-        }
-    }
     // If we can't parse or no result field, consider it non-empty
-    false
+    params::response_result(response.as_ref()).is_some_and(params::is_empty_value)
 }
 
 /// Returns the block param index.
@@ -120,7 +130,7 @@ pub(crate) fn is_result_empty(response: &MethodResponse) -> bool {
 /// Looks at each method and decides block num/hash
 /// param position in that argument list.
 #[inline]
-fn block_param_pos(method: &str) -> usize {
+pub(crate) fn block_param_pos(method: &str) -> usize {
     // 2nd position (index 1)
     if matches!(
         method,
@@ -156,77 +166,70 @@ where
 
     fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
         let method = req.method_name();
+        let config = self.config.current();
+
+        // Routable requests served locally under the shadow strategy are compared with the
+        // legacy node
+        let shadowing = self.is_shadowing(&config) && is_legacy_routable(method);
 
-        // Early return - no boxing, direct passthrough
-        if !self.config.enabled || !is_legacy_routable(method) {
+        // Early return - no boxing, direct passthrough. Paged logs and blocks by timestamp are
+        // served with routing disabled
+        if !shadowing
+            && method != LOGS_PAGED_METHOD
+            && method != BLOCK_BY_TIMESTAMP_METHOD
+            && (!self.is_routing(&config)
+                || !(is_legacy_routable(method)
+                    || config.method_names.contains_key(method)
+                    || config.forwards_method(method)))
+        {
             return Either::Left(self.inner.call(req));
         }
 
-        let client = self.client.clone();
-        let config = self.config.clone();
-        let inner = self.inner.clone();
+        let service = self.clone();
 
         Either::Right(Box::pin(async move {
             let method = req.method_name();
 
-            if method == "eth_getLogs" {
-                return crate::get_logs::handle_eth_get_logs(req, client, config, inner).await;
+            if shadowing {
+                let shadow =
+                    service.shadow_sampler.sample(&config, None).then(|| req.clone().into_owned());
+                let res = service.inner.call(req).await;
+                if let Some(shadow) = shadow {
+                    service.shadow_compare(config, shadow, &res);
+                }
+                return res;
+            } else if method == LOGS_PAGED_METHOD {
+                return handle_get_logs_paged(req, service, config).await;
+            } else if method == BLOCK_BY_TIMESTAMP_METHOD {
+                return handle_get_block_by_timestamp(req, service, config).await;
+            } else if method == "eth_getLogs" {
+                return crate::get_logs::handle_eth_get_logs(req, service, config).await;
             } else if need_try_local_then_legacy(method) {
-                return handle_try_local_then_legacy(req, client, config, inner).await;
+                return handle_try_local_then_legacy(req, service, config).await;
             } else if need_parse_block(method) {
-                return handle_block_param_methods(req, client, config, inner).await;
+                return handle_block_param_methods(req, service, config).await;
+            } else if config.method_names.contains_key(method) {
+                // Custom methods the local node may not serve under the same name
+                return handle_try_local_then_legacy(req, service, config).await;
+            } else if config.forwards_method(method) {
+                return handle_unknown_method(req, service, config).await;
             }
 
             debug!(target:"xlayer_legacy_rpc", "No legacy routing for method = {}", method);
             // Default resorts to normal rpc calls.
-            inner.call(req).await
+            service.inner.call(req).await
         }))
     }
 
     fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
         // Early return if legacy routing is disabled
-        if !self.config.enabled {
+        let config = self.config.current();
+        if !self.is_routing(&config) && !self.is_shadowing(&config) {
             return Either::Left(self.inner.batch(req));
         }
 
-        let service = self.clone();
-
-        Either::Right(Box::pin(async move {
-            // Collect all entries first to avoid lifetime issues
-            let entries: Vec<_> = req.into_iter().collect();
-
-            // Process all requests concurrently using FuturesOrdered
-            // This significantly improves latency for batch requests with multiple calls
-            let mut futures: FuturesOrdered<_> = entries
-                .into_iter()
-                .filter_map(|entry| match entry {
-                    Ok(BatchEntry::Call(request)) => Some(Either::Right(service.call(request))),
-                    Ok(BatchEntry::Notification(_notif)) => {
-                        // Notifications should not be answered
-                        // Note: we don't process notifications in batch context
-                        None
-                    }
-                    Err(_) => {
-                        // Return error response for malformed entries
-                        Some(Either::Left(async {
-                            MethodResponse::error(
-                                Id::Null,
-                                ErrorObject::from(ErrorCode::InvalidRequest),
-                            )
-                        }))
-                    }
-                })
-                .collect();
-
-            let mut batch_response = BatchResponseBuilder::new_with_limit(usize::MAX);
-            while let Some(response) = futures.next().await {
-                if let Err(err) = batch_response.append(response) {
-                    return err;
-                }
-            }
-
-            MethodResponse::from_batch(batch_response.finish())
-        }))
+        // Dispatch each entry through `call` so every batched call is routed on its own
+        Either::Right(batch_via_call(self.clone(), req))
     }
 
     fn notification<'a>(
@@ -239,35 +242,98 @@ where
 
 async fn handle_try_local_then_legacy<S>(
     req: Request<'_>,
-    client: reqwest::Client,
-    config: std::sync::Arc<crate::LegacyRpcRouterConfig>,
-    inner: S,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
 ) -> MethodResponse
 where
     S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
 {
     let method = req.method_name();
-    let res = inner.call(req.clone()).await;
+    if config.lookup_order(method) == LookupOrder::LegacyFirst {
+        return handle_try_legacy_then_local(req, service, config).await;
+    }
+
+    let res = service.inner.call(req.clone()).await;
     if res.is_error() || (res.is_success() && is_result_empty(&res)) {
-        let service = LegacyRpcRouterService { inner: inner.clone(), config, client };
+        // Transactions of local blocks whose hash index was pruned
+        if res.is_success()
+            && let Some(res) = service.scan_for_transaction(&config, &req).await
+        {
+            return res;
+        }
         debug!(
             target:"xlayer_legacy_rpc",
             "Route to legacy for method = {method}. is_error = {}, is_empty_result = {}",
             res.is_error(),
             res.is_success()
         );
-        service.forward_to_legacy(req).await
+        service.tx_lookups.record(method, TxLookupStage::Legacy);
+        service.forward_to_legacy(&config, req).await
     } else {
         debug!(target:"xlayer_legacy_rpc", "No legacy routing(local success with data) for method = {method}");
+        service.tx_lookups.record(method, TxLookupStage::Index);
         res
     }
 }
 
-async fn handle_block_param_methods<S>(
+/// Serves a hash lookup from the legacy node, falling back to the local node if the legacy node
+/// fails or returns an empty result.
+async fn handle_try_legacy_then_local<S>(
+    req: Request<'_>,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
+) -> MethodResponse
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    let method = req.method_name();
+    let res = service.forward_to_legacy(&config, req.clone()).await;
+    if res.is_success() && !is_result_empty(&res) {
+        debug!(target:"xlayer_legacy_rpc", "No local routing(legacy success with data) for method = {method}");
+        service.tx_lookups.record(method, TxLookupStage::Legacy);
+        return res;
+    }
+    debug!(
+        target:"xlayer_legacy_rpc",
+        "Route to local for method = {method}. is_error = {}, is_empty_result = {}",
+        res.is_error(),
+        res.is_success()
+    );
+
+    let res = service.inner.call(req.clone()).await;
+    // Transactions of local blocks whose hash index was pruned
+    if res.is_success()
+        && is_result_empty(&res)
+        && let Some(res) = service.scan_for_transaction(&config, &req).await
+    {
+        return res;
+    }
+    service.tx_lookups.record(method, TxLookupStage::Index);
+    res
+}
+
+/// Serves a method matching a forwarded method pattern, forwarding it to the legacy node if the
+/// local node doesn't implement it.
+async fn handle_unknown_method<S>(
+    req: Request<'_>,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
+) -> MethodResponse
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    let res = service.inner.call(req.clone()).await;
+    if res.as_error_code() != Some(METHOD_NOT_FOUND_CODE) {
+        return res;
+    }
+    debug!(target:"xlayer_legacy_rpc", "Method not implemented locally, route to legacy for method = {}", req.method_name());
+    service.forward_to_legacy(&config, req).await
+}
+
+pub(crate) async fn handle_block_param_methods<S>(
     req: Request<'_>,
-    client: reqwest::Client,
-    config: std::sync::Arc<crate::LegacyRpcRouterConfig>,
-    inner: S,
+    service: LegacyRpcRouterService<S>,
+    config: Arc<LegacyRpcRouterConfig>,
 ) -> MethodResponse
 where
     S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
@@ -280,18 +346,20 @@ where
         );
     };
     let method = req.method_name();
-    let block_param = crate::parse_block_param(params, block_param_pos(method));
+    let block_param = params::parse_block_param(params, block_param_pos(method));
 
-    let cutoff_block = config.cutoff_block;
-    if let Some(block_param) = block_param {
-        let service = LegacyRpcRouterService { inner: inner.clone(), config, client };
-        if can_use_block_hash_as_param(method) && crate::is_valid_32_bytes_string(&block_param) {
-            let res = service.call_eth_get_block_by_hash(&block_param, false).await;
+    match block_param {
+        Some(BlockParam::Hash(block_hash)) if can_use_block_hash_as_param(method) => {
+            // Recent canonical blocks are known without a local lookup
+            let res = match service.recent_blocks.number(block_hash) {
+                Some(block_num) => Ok(Some(block_num)),
+                None => service.call_eth_get_block_by_hash(block_hash, false).await,
+            };
             match res {
                 Ok(n) => {
                     if n.is_none() {
                         debug!(target:"xlayer_legacy_rpc", "Route to legacy for method (block by hash not found) = {}", method);
-                        return service.forward_to_legacy(req).await;
+                        return service.forward_guarded_to_legacy(&config, req).await;
                     } else {
                         // TODO: if block_num parsed from blk hash is smaller than
                         // cutoff, route to legacy as well?
@@ -304,27 +372,34 @@ where
                 }
                 Err(err) => {
                     debug!(target:"xlayer_legacy_rpc", "Error getting block by hash = {err:?}, forwarding to legacy");
-                    return service.forward_to_legacy(req).await;
+                    return service.forward_guarded_to_legacy(&config, req).await;
                 }
             }
-        } else {
-            match block_param.parse::<u64>() {
-                Ok(block_num) => {
-                    debug!(target:"xlayer_legacy_rpc", "block_num = {}", block_num);
-                    if block_num < cutoff_block {
-                        debug!(target:"xlayer_legacy_rpc", "Route to legacy for method (below cuttoff) = {}", method);
-                        return service.forward_to_legacy(req).await;
-                    }
-                }
-                Err(err) => {
-                    debug!(target:"xlayer_legacy_rpc", "Failed to parse block num, err = {err:?}")
-                }
+        }
+        Some(BlockParam::Hash(block_hash)) => {
+            debug!(target:"xlayer_legacy_rpc", "Block hash param not supported, block_hash = {block_hash}")
+        }
+        Some(BlockParam::Number(block_num)) => {
+            debug!(target:"xlayer_legacy_rpc", "block_num = {}", block_num);
+            let shadow = service
+                .shadow_sampler
+                .sample(&config, Some((block_num, block_num)))
+                .then(|| req.clone().into_owned());
+            let res = if config.routing_strategy().routes_block_to_legacy(&config, block_num) {
+                debug!(target:"xlayer_legacy_rpc", "Route to legacy for method (below cuttoff) = {}", method);
+                service.forward_guarded_to_legacy(&config, req).await
+            } else {
+                debug!(target:"xlayer_legacy_rpc", "No legacy routing for method = {}", method);
+                service.inner.call(req).await
+            };
+            if let Some(shadow) = shadow {
+                service.shadow_compare(config, shadow, &res);
             }
+            return res;
         }
-    } else {
-        debug!(target:"xlayer_legacy_rpc", "Failed to parse block param, got None");
+        None => debug!(target:"xlayer_legacy_rpc", "Failed to parse block param, got None"),
     }
 
     debug!(target:"xlayer_legacy_rpc", "No legacy routing for method = {}", method);
-    inner.call(req).await
+    service.inner.call(req).await
 }
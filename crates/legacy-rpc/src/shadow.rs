@@ -0,0 +1,274 @@
+//! Shadow comparison of local and legacy responses near the cutoff block.
+//!
+//! Before legacy routing is turned off, requests for blocks in a window around the cutoff can be
+//! served by both nodes. For a sample of them, the side that did not serve the request is queried
+//! in the background and both normalized results are compared. Mismatches are logged with the
+//! differing JSON paths and counted per method, the response to the client is never affected.
+//! The routing strategy decides which requests are eligible, the shadow strategy compares a
+//! sample of every routable request regardless of the window.
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+use jsonrpsee::{server::middleware::rpc::RpcServiceT, types::Request, MethodResponse};
+use metrics::Counter;
+use reth_metrics::Metrics;
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use crate::{normalize::normalize_log, LegacyRpcRouterConfig, LegacyRpcRouterService, RpcRoute};
+
+/// Maximum number of differing paths reported per mismatch.
+const MAX_DIFF_PATHS: usize = 8;
+
+/// Shadow comparison settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ShadowCompareConfig {
+    /// Number of blocks on each side of the cutoff whose requests are compared, 0 disables the
+    /// comparison
+    pub window: u64,
+    /// Compare one in `sample` eligible requests
+    pub sample: u64,
+}
+
+impl ShadowCompareConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.window > 0 && self.sample > 0
+    }
+
+    /// Returns whether the block range lies within the window around the cutoff.
+    pub(crate) fn covers(&self, cutoff_block: u64, from: u64, to: u64) -> bool {
+        self.is_enabled()
+            && from >= cutoff_block.saturating_sub(self.window)
+            && to <= cutoff_block.saturating_add(self.window)
+    }
+}
+
+/// Shadow comparison metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_legacy_rpc_shadow")]
+struct ShadowMetrics {
+    /// Number of responses compared
+    compared: Counter,
+    /// Number of compared responses that differ
+    mismatches: Counter,
+}
+
+/// Sampler shared by the router services of a layer.
+#[derive(Debug, Default)]
+pub(crate) struct ShadowSampler {
+    eligible: AtomicU64,
+}
+
+impl ShadowSampler {
+    /// Returns whether a request for blocks `from..=to`, or without a block range if `None`,
+    /// should be compared. The routing strategy decides which requests are eligible.
+    pub(crate) fn sample(
+        &self,
+        config: &LegacyRpcRouterConfig,
+        blocks: Option<(u64, u64)>,
+    ) -> bool {
+        config.shadow.sample > 0
+            && config.routing_strategy().shadows(config, blocks)
+            && self.eligible.fetch_add(1, Ordering::Relaxed) % config.shadow.sample == 0
+    }
+}
+
+impl<S> LegacyRpcRouterService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    /// Queries the side that did not serve `req` in the background and compares its response
+    /// with `served`.
+    pub(crate) fn shadow_compare(
+        &self,
+        config: Arc<LegacyRpcRouterConfig>,
+        req: Request<'static>,
+        served: &MethodResponse,
+    ) {
+        let route = RpcRoute::of(served);
+        let served = served.as_json().get().to_owned();
+        let service = self.clone();
+
+        tokio::spawn(async move {
+            let method = req.method_name().to_owned();
+            let shadow = match route {
+                RpcRoute::Legacy => service.inner.call(req).await,
+                RpcRoute::Local | RpcRoute::Hybrid => service.forward_to_legacy(&config, req).await,
+            };
+
+            let (Some(served), Some(shadow)) =
+                (comparable(&method, &served), comparable(&method, shadow.as_json().get()))
+            else {
+                debug!(target: "xlayer_legacy_rpc", %method, "Shadow comparison skipped, unparsable response");
+                return;
+            };
+
+            let metrics = ShadowMetrics::new_with_labels(&[("method", method.clone())]);
+            metrics.compared.increment(1);
+
+            let mut diff = Vec::new();
+            diff_paths(&served, &shadow, "$", &mut diff);
+            if !diff.is_empty() {
+                metrics.mismatches.increment(1);
+                warn!(
+                    target: "xlayer_legacy_rpc",
+                    %method,
+                    ?route,
+                    diff = %diff.join(", "),
+                    "Shadow comparison mismatch"
+                );
+            }
+        });
+    }
+}
+
+/// Extracts the result or error of a response, normalized for comparison.
+fn comparable(method: &str, response: &str) -> Option<Value> {
+    let mut response: Value = serde_json::from_str(response).ok()?;
+    let mut value = match response.get_mut("result") {
+        Some(result) => result.take(),
+        // Error messages differ between implementations, only the code is compared
+        None => serde_json::json!({ "error": response.get("error")?.get("code")? }),
+    };
+    if method == "eth_getLogs"
+        && let Some(logs) = value.as_array_mut()
+    {
+        logs.iter_mut().for_each(normalize_log);
+    }
+    normalize(&mut value);
+    Some(value)
+}
+
+/// Lowercases hex strings, strips leading zeros of quantities and drops null fields.
+fn normalize(value: &mut Value) {
+    match value {
+        Value::String(s) => {
+            if let Some(digits) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+                let mut digits = digits.to_ascii_lowercase();
+                // Addresses and hashes keep their fixed width
+                if digits.len() < 64 && digits.len() != 40 {
+                    digits = digits.trim_start_matches('0').to_string();
+                    if digits.is_empty() {
+                        digits.push('0');
+                    }
+                }
+                *s = format!("0x{digits}");
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(normalize),
+        Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(normalize);
+        }
+        _ => {}
+    }
+}
+
+/// Collects the paths at which the two values differ, up to [`MAX_DIFF_PATHS`].
+fn diff_paths(served: &Value, shadow: &Value, path: &str, diff: &mut Vec<String>) {
+    if diff.len() >= MAX_DIFF_PATHS || served == shadow {
+        return;
+    }
+    match (served, shadow) {
+        (Value::Object(a), Value::Object(b)) => {
+            let mut keys: Vec<_> =
+                a.keys().chain(b.keys().filter(|k| !a.contains_key(*k))).collect();
+            keys.sort_unstable();
+            for key in keys {
+                match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => diff_paths(a, b, &format!("{path}.{key}"), diff),
+                    (Some(_), None) => diff.push(format!("{path}.{key}: missing in shadow")),
+                    (None, _) => diff.push(format!("{path}.{key}: missing in served")),
+                }
+                if diff.len() >= MAX_DIFF_PATHS {
+                    return;
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (a, b)) in a.iter().zip(b).enumerate() {
+                diff_paths(a, b, &format!("{path}[{i}]"), diff);
+            }
+        }
+        (Value::Array(a), Value::Array(b)) => {
+            diff.push(format!("{path}: {} entries served, {} in shadow", a.len(), b.len()))
+        }
+        _ => diff.push(format!("{path}: {served} served, {shadow} in shadow")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shadow_window() {
+        let config = ShadowCompareConfig { window: 10, sample: 1 };
+        assert!(config.covers(100, 90, 110));
+        assert!(config.covers(100, 95, 95));
+        assert!(!config.covers(100, 89, 100));
+        assert!(!config.covers(100, 100, 111));
+        assert!(!ShadowCompareConfig { window: 0, sample: 1 }.covers(100, 100, 100));
+        assert!(!ShadowCompareConfig { window: 10, sample: 0 }.covers(100, 100, 100));
+    }
+
+    #[test]
+    fn test_shadow_sampling() {
+        let config = LegacyRpcRouterConfig {
+            enabled: true,
+            legacy_endpoint: "http://localhost:8545".to_string(),
+            cutoff_block: 100,
+            timeout: std::time::Duration::from_secs(1),
+            shadow: ShadowCompareConfig { window: 10, sample: 3 },
+            ..Default::default()
+        };
+        let sampler = ShadowSampler::default();
+        let sampled: Vec<_> = (0..6).map(|_| sampler.sample(&config, Some((100, 100)))).collect();
+        assert_eq!(sampled, [true, false, false, true, false, false]);
+
+        // Requests outside of the window don't count towards the sample
+        assert!(!sampler.sample(&config, Some((0, 100))));
+        assert!(sampler.sample(&config, Some((100, 100))));
+    }
+
+    #[test]
+    fn test_comparable_normalizes_formatting() {
+        let local = r#"{"jsonrpc":"2.0","id":1,"result":{"number":"0x10","miner":"0xAbCdEf0000000000000000000000000000000001","extra":null}}"#;
+        let legacy = r#"{"jsonrpc":"2.0","id":7,"result":{"number":"0x0010","miner":"0xabcdef0000000000000000000000000000000001"}}"#;
+        assert_eq!(
+            comparable("eth_getBlockByNumber", local),
+            comparable("eth_getBlockByNumber", legacy)
+        );
+
+        let local =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"header not found"}}"#;
+        let legacy =
+            r#"{"jsonrpc":"2.0","id":1,"error":{"code":-32000,"message":"block not found"}}"#;
+        assert_eq!(comparable("eth_getBalance", local), comparable("eth_getBalance", legacy));
+    }
+
+    #[test]
+    fn test_diff_paths() {
+        let served = json!({"number": "0x10", "transactions": ["0x1", "0x2"], "hash": "0xaa"});
+        let shadow = json!({"number": "0x10", "transactions": ["0x1", "0x3"], "size": "0x1"});
+
+        let mut diff = Vec::new();
+        diff_paths(&served, &shadow, "$", &mut diff);
+        assert_eq!(
+            diff,
+            [
+                "$.hash: missing in shadow",
+                "$.size: missing in served",
+                r#"$.transactions[1]: "0x2" served, "0x3" in shadow"#,
+            ]
+        );
+
+        let mut diff = Vec::new();
+        diff_paths(&json!([1, 2]), &json!([1]), "$", &mut diff);
+        assert_eq!(diff, ["$: 2 entries served, 1 in shadow"]);
+    }
+}
@@ -0,0 +1,258 @@
+//! Routing strategies of the legacy router.
+//!
+//! Retiring the legacy node takes several phases. While the local node lacks the history before
+//! the cutoff block, requests are split between both nodes at the cutoff. Once the history is
+//! served locally, requests stay local and a sample is compared with the legacy node, until the
+//! legacy endpoint is turned off and every request is served locally only. Each phase is a
+//! [`RoutingStrategy`], selected by the [`RoutingStrategyKind`] of the router configuration and
+//! switched at runtime with `xlayer_updateLegacyRpcConfig`.
+
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::LegacyRpcRouterConfig;
+
+/// Decides which node serves the requests passing through the legacy router.
+///
+/// Block ranges, e.g. of `eth_getLogs` and of the `xlayer_getBlockByTimestamp` search, are split
+/// at the [`local_from`](RoutingStrategy::local_from) block of the strategy, so a new phase only
+/// implements this trait.
+pub trait RoutingStrategy: fmt::Debug + Send + Sync {
+    /// Returns whether requests may be served by the legacy node, for blocks before the cutoff,
+    /// after local misses or for methods only the legacy node implements.
+    fn uses_legacy(&self) -> bool;
+
+    /// Returns the first block served locally, earlier blocks are served by the legacy node.
+    fn local_from(&self, config: &LegacyRpcRouterConfig) -> u64;
+
+    /// Returns whether a sample of the requests for blocks `from..=to`, or of the routable
+    /// requests without a block range if `None`, is compared with the node that didn't serve them.
+    fn shadows(&self, config: &LegacyRpcRouterConfig, blocks: Option<(u64, u64)>) -> bool;
+
+    /// Returns whether a request for `block` is served by the legacy node.
+    fn routes_block_to_legacy(&self, config: &LegacyRpcRouterConfig, block: u64) -> bool {
+        block < self.local_from(config)
+    }
+
+    /// Returns which node serves a request for blocks `from..=to`.
+    fn route_range(&self, config: &LegacyRpcRouterConfig, from: u64, to: u64) -> RangeRoute {
+        let local_from = self.local_from(config);
+        if to < local_from {
+            RangeRoute::Legacy
+        } else if from >= local_from {
+            RangeRoute::Local
+        } else {
+            RangeRoute::Split { local_from }
+        }
+    }
+}
+
+/// Node serving a block range, see [`RoutingStrategy::route_range`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeRoute {
+    /// The whole range is served by the legacy node
+    Legacy,
+    /// The whole range is served locally
+    Local,
+    /// Blocks before `local_from` are served by the legacy node, the others locally
+    Split { local_from: u64 },
+}
+
+/// Serves blocks before the cutoff from the legacy node and later blocks locally.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CutoffStrategy;
+
+impl RoutingStrategy for CutoffStrategy {
+    fn uses_legacy(&self) -> bool {
+        true
+    }
+
+    fn local_from(&self, config: &LegacyRpcRouterConfig) -> u64 {
+        config.cutoff_block
+    }
+
+    fn shadows(&self, config: &LegacyRpcRouterConfig, blocks: Option<(u64, u64)>) -> bool {
+        blocks.is_some_and(|(from, to)| config.shadow.covers(config.cutoff_block, from, to))
+    }
+}
+
+/// Serves every request locally, once the legacy node is retired.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysLocalStrategy;
+
+impl RoutingStrategy for AlwaysLocalStrategy {
+    fn uses_legacy(&self) -> bool {
+        false
+    }
+
+    fn local_from(&self, _config: &LegacyRpcRouterConfig) -> u64 {
+        0
+    }
+
+    fn shadows(&self, _config: &LegacyRpcRouterConfig, _blocks: Option<(u64, u64)>) -> bool {
+        false
+    }
+}
+
+/// Serves every request locally and compares a sample with the legacy node, regardless of the
+/// shadow window.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShadowStrategy;
+
+impl RoutingStrategy for ShadowStrategy {
+    fn uses_legacy(&self) -> bool {
+        false
+    }
+
+    fn local_from(&self, _config: &LegacyRpcRouterConfig) -> u64 {
+        0
+    }
+
+    fn shadows(&self, config: &LegacyRpcRouterConfig, _blocks: Option<(u64, u64)>) -> bool {
+        config.shadow.sample > 0
+    }
+}
+
+/// Routing strategy selected in the router configuration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoutingStrategyKind {
+    /// [`CutoffStrategy`]
+    #[default]
+    Cutoff,
+    /// [`AlwaysLocalStrategy`]
+    Local,
+    /// [`ShadowStrategy`]
+    Shadow,
+}
+
+impl RoutingStrategyKind {
+    /// Returns the strategy of this kind.
+    pub fn strategy(self) -> &'static dyn RoutingStrategy {
+        match self {
+            Self::Cutoff => &CutoffStrategy,
+            Self::Local => &AlwaysLocalStrategy,
+            Self::Shadow => &ShadowStrategy,
+        }
+    }
+}
+
+impl fmt::Display for RoutingStrategyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cutoff => f.write_str("cutoff"),
+            Self::Local => f.write_str("local"),
+            Self::Shadow => f.write_str("shadow"),
+        }
+    }
+}
+
+impl FromStr for RoutingStrategyKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cutoff" => Ok(Self::Cutoff),
+            "local" => Ok(Self::Local),
+            "shadow" => Ok(Self::Shadow),
+            _ => Err(format!(
+                "unknown routing strategy `{s}`, expected one of cutoff, local, shadow"
+            )),
+        }
+    }
+}
+
+/// Order in which the local and the legacy node are asked for a hash lookup, e.g.
+/// `eth_getTransactionByHash`, until one of them returns a non-empty result.
+///
+/// Legacy-first serves the hashes of blocks the local node is still backfilling from the legacy
+/// node during a migration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LookupOrder {
+    /// Local node first, legacy node on a miss
+    #[default]
+    LocalFirst,
+    /// Legacy node first, local node on a miss
+    LegacyFirst,
+}
+
+impl fmt::Display for LookupOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LocalFirst => f.write_str("local-first"),
+            Self::LegacyFirst => f.write_str("legacy-first"),
+        }
+    }
+}
+
+impl FromStr for LookupOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "local-first" => Ok(Self::LocalFirst),
+            "legacy-first" => Ok(Self::LegacyFirst),
+            _ => Err(format!(
+                "unknown lookup order `{s}`, expected one of local-first, legacy-first"
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ShadowCompareConfig;
+
+    #[test]
+    fn test_routing_strategy_kind() {
+        for kind in
+            [RoutingStrategyKind::Cutoff, RoutingStrategyKind::Local, RoutingStrategyKind::Shadow]
+        {
+            assert_eq!(kind.to_string().parse::<RoutingStrategyKind>().unwrap(), kind);
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.to_string());
+        }
+        assert!("legacy".parse::<RoutingStrategyKind>().is_err());
+
+        assert!(RoutingStrategyKind::Cutoff.strategy().uses_legacy());
+        assert!(!RoutingStrategyKind::Local.strategy().uses_legacy());
+        assert!(!RoutingStrategyKind::Shadow.strategy().uses_legacy());
+    }
+
+    #[test]
+    fn test_route_range() {
+        let config = LegacyRpcRouterConfig {
+            cutoff_block: 100,
+            shadow: ShadowCompareConfig { window: 10, sample: 1 },
+            ..Default::default()
+        };
+
+        let cutoff = RoutingStrategyKind::Cutoff.strategy();
+        assert_eq!(cutoff.route_range(&config, 0, 99), RangeRoute::Legacy);
+        assert_eq!(cutoff.route_range(&config, 100, 200), RangeRoute::Local);
+        assert_eq!(cutoff.route_range(&config, 50, 150), RangeRoute::Split { local_from: 100 });
+        assert!(cutoff.shadows(&config, Some((95, 105))));
+        assert!(!cutoff.shadows(&config, Some((50, 150))));
+        assert!(!cutoff.shadows(&config, None));
+
+        for kind in [RoutingStrategyKind::Local, RoutingStrategyKind::Shadow] {
+            let strategy = kind.strategy();
+            assert_eq!(strategy.route_range(&config, 0, 200), RangeRoute::Local);
+            assert!(!strategy.routes_block_to_legacy(&config, 0));
+        }
+        assert!(!RoutingStrategyKind::Local.strategy().shadows(&config, None));
+        assert!(RoutingStrategyKind::Shadow.strategy().shadows(&config, None));
+        assert!(RoutingStrategyKind::Shadow.strategy().shadows(&config, Some((0, 200))));
+    }
+
+    #[test]
+    fn test_lookup_order() {
+        for order in [LookupOrder::LocalFirst, LookupOrder::LegacyFirst] {
+            assert_eq!(order.to_string().parse::<LookupOrder>().unwrap(), order);
+            assert_eq!(serde_json::to_value(order).unwrap(), order.to_string());
+        }
+        assert!("legacy".parse::<LookupOrder>().is_err());
+    }
+}
@@ -0,0 +1,36 @@
+//! Test helpers for the RPC middlewares.
+
+use std::future::Future;
+
+use futures::future::ready;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification, RpcServiceT},
+    types::{Request, ResponsePayload},
+    BatchResponseBuilder, MethodResponse,
+};
+
+/// Service answering every call with the name of the called method.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EchoService;
+
+impl RpcServiceT for EchoService {
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = MethodResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let payload = ResponsePayload::success(req.method_name().to_string()).into();
+        ready(MethodResponse::response(req.id(), payload, usize::MAX))
+    }
+
+    fn batch<'a>(&self, _req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        ready(MethodResponse::from_batch(BatchResponseBuilder::new_with_limit(0).finish()))
+    }
+
+    fn notification<'a>(
+        &self,
+        _n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        ready(MethodResponse::notification())
+    }
+}
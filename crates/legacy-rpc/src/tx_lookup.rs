@@ -0,0 +1,392 @@
+//! Fallback for transaction lookups missed by a pruned local hash index.
+//!
+//! Replicas may index the transaction hashes of their most recent blocks only, e.g. with
+//! `--prune.transactionlookup.distance`. Lookups of transactions in older local blocks then return
+//! null although the blocks are above the cutoff, and the legacy node has no data for them either.
+//! With an index window configured, lookups go through a chain of stages:
+//! 1. the local hash index
+//! 2. a scan of a bounded range of local blocks right below the index window
+//! 3. the legacy node
+//!
+//! Transactions a scan missed are remembered for a while, so repeated lookups of unknown hashes
+//! don't rescan the same blocks, and the number of concurrent scans is capped. Lookups skipping the
+//! scan go to the legacy node.
+//!
+//! Lookups are counted per method and serving stage, and the blocks fetched by scans are counted.
+
+use std::{ops::RangeInclusive, time::Duration};
+
+use alloy_primitives::B256;
+use futures::future::join_all;
+use jsonrpsee::{server::middleware::rpc::RpcServiceT, types::Request, MethodResponse};
+use jsonrpsee_types::{Id, ResponsePayload};
+use metrics::Counter;
+use moka::sync::Cache;
+use reth_metrics::Metrics;
+use serde::Deserialize;
+use serde_json::value::RawValue;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tracing::debug;
+
+use crate::{
+    is_valid_32_bytes_string,
+    params::{self, as_str},
+    service::is_result_empty,
+    LegacyRpcRouterConfig, LegacyRpcRouterService,
+};
+
+/// Number of blocks fetched concurrently by a scan.
+const SCAN_BATCH_SIZE: u64 = 16;
+
+/// Maximum number of scans running at once.
+const MAX_CONCURRENT_SCANS: usize = 8;
+
+/// Maximum number of transaction hashes remembered as missed by a scan.
+const MAX_CACHED_MISSES: u64 = 100_000;
+
+/// Time a transaction hash is remembered as missed by a scan.
+const MISS_TTL: Duration = Duration::from_secs(60);
+
+/// Methods looking up a transaction by hash.
+const TX_LOOKUP_METHODS: [&str; 3] =
+    ["eth_getTransactionByHash", "eth_getRawTransactionByHash", "eth_getTransactionReceipt"];
+
+/// Transaction lookup settings.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxLookupConfig {
+    /// Number of most recent blocks whose transaction hashes are indexed locally, 0 if all
+    /// blocks are indexed
+    pub index_window: u64,
+    /// Maximum number of blocks below the index window scanned for a transaction, 0 disables
+    /// the scan
+    pub scan_blocks: u64,
+}
+
+impl TxLookupConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.index_window > 0 && self.scan_blocks > 0
+    }
+
+    /// Returns the blocks scanned for a transaction: the `scan_blocks` newest blocks that are
+    /// not indexed, without going below the cutoff block.
+    fn scan_range(&self, cutoff_block: u64, latest: u64) -> Option<RangeInclusive<u64>> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let to = latest.checked_sub(self.index_window)?;
+        let from = to.saturating_sub(self.scan_blocks - 1).max(cutoff_block);
+        (from <= to).then_some(from..=to)
+    }
+}
+
+/// Stage of the lookup chain that served a transaction lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TxLookupStage {
+    /// Local hash index
+    Index,
+    /// Scan of local blocks below the index window
+    Scan,
+    /// Legacy node
+    Legacy,
+}
+
+impl TxLookupStage {
+    const ALL: [Self; 3] = [Self::Index, Self::Scan, Self::Legacy];
+
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Index => "index",
+            Self::Scan => "scan",
+            Self::Legacy => "legacy",
+        }
+    }
+}
+
+/// Transaction lookup metrics, labeled by method and stage
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_legacy_rpc_tx_lookup")]
+struct TxLookupMetrics {
+    /// Number of lookups served by a stage
+    lookups: Counter,
+}
+
+/// Transaction scan metrics, labeled by method
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_legacy_rpc_tx_lookup")]
+struct TxScanMetrics {
+    /// Number of blocks fetched by scans
+    scanned_blocks: Counter,
+}
+
+/// Metrics of a transaction lookup method.
+struct TxLookupMethodMetrics {
+    /// Lookup metrics, indexed by stage
+    stages: [TxLookupMetrics; 3],
+    scans: TxScanMetrics,
+}
+
+/// Returns the index of the method in [`TX_LOOKUP_METHODS`].
+fn tx_lookup_method(method: &str) -> Option<usize> {
+    TX_LOOKUP_METHODS.iter().position(|m| *m == method)
+}
+
+/// Whether the method looks up a transaction by hash.
+#[inline]
+pub(crate) fn is_tx_lookup_method(method: &str) -> bool {
+    tx_lookup_method(method).is_some()
+}
+
+/// Transaction lookup state shared by the router services of a layer.
+pub(crate) struct TxLookups {
+    /// Hashes of the transactions missed by a recent scan
+    misses: Cache<B256, ()>,
+    scans: Semaphore,
+    /// Metrics indexed like [`TX_LOOKUP_METHODS`]
+    metrics: Vec<TxLookupMethodMetrics>,
+}
+
+impl Default for TxLookups {
+    fn default() -> Self {
+        Self::new(MAX_CONCURRENT_SCANS)
+    }
+}
+
+impl TxLookups {
+    fn new(max_scans: usize) -> Self {
+        let misses =
+            Cache::builder().max_capacity(MAX_CACHED_MISSES).time_to_live(MISS_TTL).build();
+        let metrics = TX_LOOKUP_METHODS
+            .iter()
+            .map(|method| TxLookupMethodMetrics {
+                stages: TxLookupStage::ALL.map(|stage| {
+                    TxLookupMetrics::new_with_labels(&[
+                        ("method", method.to_string()),
+                        ("stage", stage.as_str().to_string()),
+                    ])
+                }),
+                scans: TxScanMetrics::new_with_labels(&[("method", method.to_string())]),
+            })
+            .collect();
+        Self { misses, scans: Semaphore::new(max_scans), metrics }
+    }
+
+    /// Counts a transaction lookup served by `stage`.
+    pub(crate) fn record(&self, method: &str, stage: TxLookupStage) {
+        if let Some(method) = tx_lookup_method(method) {
+            self.metrics[method].stages[stage as usize].lookups.increment(1);
+        }
+    }
+
+    /// Returns the permit to scan for the transaction, `None` if a recent scan missed it or the
+    /// maximum number of scans are running.
+    fn scan_permit(&self, tx_hash: &B256) -> Option<SemaphorePermit<'_>> {
+        if self.misses.contains_key(tx_hash) {
+            return None;
+        }
+        self.scans.try_acquire().ok()
+    }
+}
+
+/// Block fields read by a scan.
+#[derive(Deserialize)]
+struct BlockTransactions<'a> {
+    #[serde(borrow)]
+    transactions: Vec<&'a str>,
+}
+
+impl<S> LegacyRpcRouterService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    /// Scans the local blocks below the index window for the transaction of a lookup missed by
+    /// the local index. Returns `None` if the scan is disabled or the transaction wasn't found.
+    pub(crate) async fn scan_for_transaction(
+        &self,
+        config: &LegacyRpcRouterConfig,
+        req: &Request<'_>,
+    ) -> Option<MethodResponse> {
+        let method = req.method_name();
+        if !config.tx_lookup.is_enabled() || !is_tx_lookup_method(method) {
+            return None;
+        }
+        let params = req.params();
+        let tx_hash = params::param_at(params.as_str()?, 0).and_then(as_str)?;
+        // Malformed hashes can't match any transaction
+        if !is_valid_32_bytes_string(tx_hash) {
+            return None;
+        }
+        let hash = tx_hash.parse::<B256>().ok()?;
+        let Some(_permit) = self.tx_lookups.scan_permit(&hash) else {
+            debug!(target: "xlayer_legacy_rpc", %method, %tx_hash, "Transaction scan skipped");
+            return None;
+        };
+
+        let latest = self.local_block_number().await?;
+        let range = config.tx_lookup.scan_range(config.cutoff_block, latest)?;
+        let Some((block, index)) = self.find_transaction(method, tx_hash, range).await else {
+            self.tx_lookups.misses.insert(hash, ());
+            return None;
+        };
+        debug!(
+            target: "xlayer_legacy_rpc",
+            %method,
+            %tx_hash,
+            block,
+            index,
+            "Transaction found by local block scan"
+        );
+
+        let res = self.transaction_at(req, block, index).await?;
+        if !res.is_success() || is_result_empty(&res) {
+            return None;
+        }
+        self.tx_lookups.record(method, TxLookupStage::Scan);
+        Some(res)
+    }
+
+    /// Returns the block and index of the transaction within `range`, scanning newest blocks
+    /// first.
+    async fn find_transaction(
+        &self,
+        method: &str,
+        tx_hash: &str,
+        range: RangeInclusive<u64>,
+    ) -> Option<(u64, usize)> {
+        let metrics = &self.tx_lookups.metrics[tx_lookup_method(method)?].scans;
+        let mut to = *range.end();
+        loop {
+            let from = to.saturating_sub(SCAN_BATCH_SIZE - 1).max(*range.start());
+            let blocks = (from..=to).rev();
+            let indexes =
+                join_all(blocks.clone().map(|block| self.transaction_index(block, tx_hash))).await;
+            metrics.scanned_blocks.increment(to - from + 1);
+
+            if let Some(found) =
+                blocks.zip(indexes).find_map(|(block, index)| index.map(|index| (block, index)))
+            {
+                return Some(found);
+            }
+            if from == *range.start() {
+                return None;
+            }
+            to = from - 1;
+        }
+    }
+
+    /// Returns the index of the transaction in the local block, `None` if it isn't part of it.
+    async fn transaction_index(&self, block: u64, tx_hash: &str) -> Option<usize> {
+        let res =
+            self.call_local("eth_getBlockByNumber", format!(r#"["0x{block:x}",false]"#)).await?;
+        let result = params::response_result(res.as_json().get())?;
+        let block: BlockTransactions<'_> = serde_json::from_str(result.get()).ok()?;
+        block.transactions.iter().position(|hash| hash.eq_ignore_ascii_case(tx_hash))
+    }
+
+    /// Serves the lookup of `req` from the transaction at `index` of the local block.
+    async fn transaction_at(
+        &self,
+        req: &Request<'_>,
+        block: u64,
+        index: usize,
+    ) -> Option<MethodResponse> {
+        let id = req.id().into_owned();
+        let res = match req.method_name() {
+            "eth_getTransactionByHash" => {
+                self.call_local(
+                    "eth_getTransactionByBlockNumberAndIndex",
+                    format!(r#"["0x{block:x}","0x{index:x}"]"#),
+                )
+                .await?
+            }
+            "eth_getRawTransactionByHash" => {
+                self.call_local(
+                    "eth_getRawTransactionByBlockNumberAndIndex",
+                    format!(r#"["0x{block:x}","0x{index:x}"]"#),
+                )
+                .await?
+            }
+            "eth_getTransactionReceipt" => {
+                let res =
+                    self.call_local("eth_getBlockReceipts", format!(r#"["0x{block:x}"]"#)).await?;
+                let receipts = params::response_result(res.as_json().get())?;
+                let receipts: Vec<&RawValue> = serde_json::from_str(receipts.get()).ok()?;
+                let payload = ResponsePayload::success(*receipts.get(index)?).into();
+                return Some(MethodResponse::response(id, payload, usize::MAX));
+            }
+            _ => return None,
+        };
+
+        // Rewrap the result for the id of the original request
+        let result = params::response_result(res.as_json().get())?;
+        let payload = ResponsePayload::success(result).into();
+        Some(MethodResponse::response(id, payload, usize::MAX))
+    }
+
+    /// Returns the number of the latest local block.
+    async fn local_block_number(&self) -> Option<u64> {
+        let res = self.call_local("eth_blockNumber", "[]".to_string()).await?;
+        let number = as_str(params::response_result(res.as_json().get())?)?;
+        u64::from_str_radix(number.strip_prefix("0x")?, 16).ok()
+    }
+
+    /// Calls the local node.
+    pub(crate) async fn call_local(&self, method: &str, params: String) -> Option<MethodResponse> {
+        let params = RawValue::from_string(params).ok()?;
+        let req = Request::owned(method.to_string(), Some(params), Id::Number(0));
+        let res = self.inner.call(req).await;
+        res.is_success().then_some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_range() {
+        let config = TxLookupConfig { index_window: 100, scan_blocks: 50 };
+        // Blocks 901..=1000 are indexed
+        assert_eq!(config.scan_range(0, 1000), Some(851..=900));
+        // The scan doesn't go below the cutoff block
+        assert_eq!(config.scan_range(880, 1000), Some(880..=900));
+        assert_eq!(config.scan_range(950, 1000), None);
+        // Every local block is indexed
+        assert_eq!(config.scan_range(0, 99), None);
+
+        assert!(!TxLookupConfig { index_window: 0, scan_blocks: 50 }.is_enabled());
+        assert!(!TxLookupConfig { index_window: 100, scan_blocks: 0 }.is_enabled());
+        assert_eq!(TxLookupConfig::default().scan_range(0, 1000), None);
+    }
+
+    #[test]
+    fn test_block_transactions() {
+        let block = r#"{"number":"0x10","transactions":["0x01","0x02"],"uncles":[]}"#;
+        let block: BlockTransactions<'_> = serde_json::from_str(block).unwrap();
+        assert_eq!(block.transactions, ["0x01", "0x02"]);
+    }
+
+    #[test]
+    fn test_tx_lookup_methods() {
+        assert!(is_tx_lookup_method("eth_getTransactionByHash"));
+        assert!(is_tx_lookup_method("eth_getTransactionReceipt"));
+        assert!(!is_tx_lookup_method("eth_getBlockByHash"));
+    }
+
+    #[tokio::test]
+    async fn test_scan_permits() {
+        let lookups = TxLookups::new(1);
+        let (first, second) = (B256::repeat_byte(1), B256::repeat_byte(2));
+
+        let permit = lookups.scan_permit(&first);
+        assert!(permit.is_some());
+        // Scans beyond the limit are skipped
+        assert!(lookups.scan_permit(&second).is_none());
+        drop(permit);
+        assert!(lookups.scan_permit(&second).is_some());
+
+        // Hashes missed by a recent scan aren't scanned again
+        lookups.misses.insert(first, ());
+        assert!(lookups.scan_permit(&first).is_none());
+        assert!(lookups.scan_permit(&second).is_some());
+    }
+}
@@ -0,0 +1,289 @@
+//! Replays recorded legacy responses through the router and checks its output byte for byte.
+//!
+//! Every case in `tests/fixtures` is a `<case>.json` file holding the request, the cutoff block,
+//! the receipt profile, the lookup orders, the gas estimate margin and the calls made to both
+//! nodes, and a `<case>.expected.json` file holding the exact `result` returned by the router.
+//! Legacy results are recorded from the legacy node, e.g. with
+//!
+//! ```bash
+//! cast rpc --raw eth_getLogs '[{"fromBlock":"0x28d3a85","toBlock":"0x28d3aa4"}]' \
+//!     --rpc-url <legacy rpc>
+//! ```
+//!
+//! and served by a stub legacy node, local results are served by a stub inner service. Calls
+//! whose params don't match a recorded call fail, so the fixtures also pin the requests the router
+//! sends to each node.
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use jsonrpsee::{
+    core::middleware::{Batch, Notification, RpcServiceT},
+    server::{RpcModule, Server},
+    types::{
+        error::{INTERNAL_ERROR_CODE, METHOD_NOT_FOUND_CODE},
+        ErrorObject, ErrorObjectOwned, Id, Request,
+    },
+    MethodResponse,
+};
+use serde::Deserialize;
+use serde_json::{value::RawValue, Value};
+use tower::Layer;
+use xlayer_legacy_rpc::{
+    layer::LegacyRpcRouterLayer, LegacyRpcRouterConfig, LookupOrder, ReceiptCompatProfile,
+};
+
+/// A recorded test case.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Fixture {
+    /// What the case covers
+    description: String,
+    cutoff_block: u64,
+    #[serde(default)]
+    receipt_profile: ReceiptCompatProfile,
+    /// Lookup orders of hash lookup methods, local first if unset
+    #[serde(default)]
+    lookup_order: HashMap<String, LookupOrder>,
+    /// Safety margin of legacy gas estimates, passed through if unset
+    #[serde(default)]
+    estimate_gas_margin: Option<u64>,
+    /// Request sent to the router
+    request: Call,
+    /// Calls answered by the legacy node
+    #[serde(default)]
+    legacy: Vec<Exchange>,
+    /// Calls answered by the local node
+    #[serde(default)]
+    local: Vec<Exchange>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Call {
+    method: String,
+    params: Value,
+}
+
+/// A recorded call and its result.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Exchange {
+    method: String,
+    params: Value,
+    result: Value,
+}
+
+/// Recorded calls of one node and how often each was made.
+#[derive(Debug)]
+struct Replay {
+    exchanges: Vec<Exchange>,
+    hits: Vec<AtomicUsize>,
+}
+
+impl Replay {
+    fn new(exchanges: Vec<Exchange>) -> Arc<Self> {
+        let hits = exchanges.iter().map(|_| AtomicUsize::new(0)).collect();
+        Arc::new(Self { exchanges, hits })
+    }
+
+    /// Returns the recorded result of the call.
+    fn result(&self, method: &str, params: Option<&str>) -> Result<Value, ErrorObjectOwned> {
+        let params = params.map_or(Ok(Value::Null), serde_json::from_str).map_err(|e| {
+            ErrorObject::owned(INTERNAL_ERROR_CODE, format!("invalid params: {e}"), None::<()>)
+        })?;
+        let index = self
+            .exchanges
+            .iter()
+            .position(|exchange| exchange.method == method && exchange.params == params)
+            .ok_or_else(|| {
+                ErrorObject::owned(
+                    METHOD_NOT_FOUND_CODE,
+                    format!("unexpected call {method} {params}"),
+                    None::<()>,
+                )
+            })?;
+        self.hits[index].fetch_add(1, Ordering::Relaxed);
+        Ok(self.exchanges[index].result.clone())
+    }
+
+    /// Asserts that every recorded call was made.
+    fn assert_replayed(&self, node: &str) {
+        for (exchange, hits) in self.exchanges.iter().zip(&self.hits) {
+            assert!(
+                hits.load(Ordering::Relaxed) > 0,
+                "{node} call {} {} was not made",
+                exchange.method,
+                exchange.params
+            );
+        }
+    }
+}
+
+/// Local node answering with the recorded results.
+#[derive(Clone)]
+struct StubLocal(Arc<Replay>);
+
+impl RpcServiceT for StubLocal {
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = MethodResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let params = req.params();
+        let response = match self.0.result(req.method_name(), params.as_str()) {
+            Ok(result) => {
+                let payload = jsonrpsee_types::ResponsePayload::success(result).into();
+                MethodResponse::response(req.id().into_owned(), payload, usize::MAX)
+            }
+            Err(err) => MethodResponse::error(req.id().into_owned(), err),
+        };
+        std::future::ready(response)
+    }
+
+    fn batch<'a>(&self, _req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        std::future::ready(MethodResponse::error(
+            Id::Null,
+            ErrorObject::owned(INTERNAL_ERROR_CODE, "batches are not recorded", None::<()>),
+        ))
+    }
+
+    fn notification<'a>(
+        &self,
+        _n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        std::future::ready(MethodResponse::error(
+            Id::Null,
+            ErrorObject::owned(INTERNAL_ERROR_CODE, "notifications are not recorded", None::<()>),
+        ))
+    }
+}
+
+/// Starts a legacy node answering with the recorded results and returns its URL.
+async fn start_stub_legacy(replay: Arc<Replay>) -> (String, jsonrpsee::server::ServerHandle) {
+    let server = Server::builder().build("127.0.0.1:0").await.unwrap();
+    let addr = server.local_addr().unwrap();
+
+    let mut methods: Vec<&str> = replay.exchanges.iter().map(|e| e.method.as_str()).collect();
+    methods.sort_unstable();
+    methods.dedup();
+    let methods: Vec<&'static str> = methods
+        .into_iter()
+        .map(|method| &*Box::leak(method.to_string().into_boxed_str()))
+        .collect();
+
+    let mut module = RpcModule::from_arc(replay);
+    for method in methods {
+        module
+            .register_method(method, move |params, replay, _| {
+                replay.result(method, params.as_str())
+            })
+            .unwrap();
+    }
+    (format!("http://{addr}"), server.start(module))
+}
+
+fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name)
+}
+
+/// Replays the case and compares the result of the router with the expected bytes.
+async fn replay(case: &str) {
+    let fixture = std::fs::read_to_string(fixture_path(&format!("{case}.json"))).unwrap();
+    let fixture: Fixture = serde_json::from_str(&fixture).unwrap();
+    let expected = std::fs::read_to_string(fixture_path(&format!("{case}.expected.json"))).unwrap();
+
+    let legacy = Replay::new(fixture.legacy);
+    let local = Replay::new(fixture.local);
+    let (legacy_endpoint, server) = start_stub_legacy(legacy.clone()).await;
+
+    let config = LegacyRpcRouterConfig {
+        enabled: true,
+        legacy_endpoint,
+        cutoff_block: fixture.cutoff_block,
+        timeout: Duration::from_secs(10),
+        receipt_profile: fixture.receipt_profile,
+        lookup_order: fixture.lookup_order,
+        estimate_gas_margin: fixture.estimate_gas_margin,
+        ..Default::default()
+    };
+    let router = LegacyRpcRouterLayer::new(config).layer(StubLocal(local.clone()));
+
+    let params = RawValue::from_string(fixture.request.params.to_string()).unwrap();
+    let request = Request::owned(fixture.request.method, Some(params), Id::Number(1));
+    let response = router.call(request).await;
+
+    #[derive(Deserialize)]
+    struct Output<'a> {
+        #[serde(borrow)]
+        result: Option<&'a RawValue>,
+    }
+    let output: Output<'_> = serde_json::from_str(response.as_json().get()).unwrap();
+    let result = output.result.unwrap_or_else(|| {
+        panic!("{case}: {} failed: {}", fixture.description, response.as_json())
+    });
+    assert!(
+        result.get() == expected.trim_end(),
+        "{case}: {}\nexpected: {}\n     got: {}",
+        fixture.description,
+        expected.trim_end(),
+        result.get()
+    );
+
+    legacy.assert_replayed("legacy");
+    local.assert_replayed("local");
+    server.stop().unwrap();
+}
+
+#[tokio::test]
+async fn test_get_logs_hybrid() {
+    replay("get_logs_hybrid").await;
+}
+
+#[tokio::test]
+async fn test_get_logs_hybrid_null_legacy() {
+    replay("get_logs_hybrid_null_legacy").await;
+}
+
+#[tokio::test]
+async fn test_get_logs_hybrid_large() {
+    replay("get_logs_hybrid_large").await;
+}
+
+#[tokio::test]
+async fn test_receipt_legacy_fill() {
+    replay("receipt_legacy_fill").await;
+}
+
+#[tokio::test]
+async fn test_block_receipts_legacy_strict() {
+    replay("block_receipts_legacy_strict").await;
+}
+
+#[tokio::test]
+async fn test_block_by_number_legacy() {
+    replay("block_by_number_legacy").await;
+}
+
+#[tokio::test]
+async fn test_estimate_gas_legacy_margin() {
+    replay("estimate_gas_legacy_margin").await;
+}
+
+#[tokio::test]
+async fn test_transaction_by_hash_legacy_first() {
+    replay("transaction_by_hash_legacy_first").await;
+}
+
+#[tokio::test]
+async fn test_transaction_by_hash_legacy_first_fallback() {
+    replay("transaction_by_hash_legacy_first_fallback").await;
+}
@@ -1,3 +1,5 @@
+use crate::trace_queue::{OverflowPolicy, DEFAULT_TRACE_QUEUE_CAPACITY};
+
 use clap::Args;
 
 #[derive(Debug, Clone, Args, PartialEq, Eq, Default)]
@@ -17,10 +19,45 @@ pub struct FullLinkMonitorArgs {
         default_value = "/data/logs/trace.log"
     )]
     pub output_path: String,
+
+    /// Number of recent canonical blocks replayed as commit events at startup
+    #[arg(
+        long = "xlayer.full-link-monitor.backfill-blocks",
+        help = "Emit block and transaction commit events for the last N canonical blocks at startup, before following the chain, so restarted pipelines have no gaps (default: 0, disabled)",
+        default_value = "0"
+    )]
+    pub backfill_blocks: u64,
+
+    /// Number of events queued for the trace sink
+    #[arg(
+        long = "xlayer.full-link-monitor.queue-capacity",
+        help = "Number of trace events buffered between the monitor hooks and the trace sink",
+        default_value_t = DEFAULT_TRACE_QUEUE_CAPACITY
+    )]
+    pub queue_capacity: usize,
+
+    /// What the monitor hooks do when the trace sink queue is full
+    #[arg(
+        long = "xlayer.full-link-monitor.overflow-policy",
+        help = "What happens when the trace sink falls behind and its queue is full: fail-open (drop and count events, never slow down RPC and engine calls) or fail-closed (block until the sink catches up, no events are lost)",
+        default_value_t = OverflowPolicy::FailOpen
+    )]
+    pub overflow_policy: OverflowPolicy,
 }
 
+/// Largest number of blocks replayed at startup.
+const MAX_BACKFILL_BLOCKS: u64 = 100_000;
+
 impl FullLinkMonitorArgs {
     pub fn validate(&self) -> Result<(), String> {
+        if self.backfill_blocks > MAX_BACKFILL_BLOCKS {
+            return Err(format!(
+                "--xlayer.full-link-monitor.backfill-blocks must be at most {MAX_BACKFILL_BLOCKS}"
+            ));
+        }
+        if self.enable && self.queue_capacity == 0 {
+            return Err("--xlayer.full-link-monitor.queue-capacity must be greater than 0".into());
+        }
         Ok(())
     }
 }
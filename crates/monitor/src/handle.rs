@@ -2,7 +2,7 @@ use crate::monitor::XLayerMonitor;
 
 use futures::StreamExt;
 use std::sync::Arc;
-use tracing::info;
+use tracing::{info, warn};
 
 use alloy_consensus::{transaction::TxHashRef, BlockHeader as _};
 use alloy_eips::BlockNumHash;
@@ -11,7 +11,9 @@ use reth_payload_builder::PayloadBuilderHandle;
 use reth_payload_builder_primitives::Events;
 use reth_payload_primitives::{BuiltPayload, PayloadBuilderAttributes, PayloadTypes};
 use reth_primitives_traits::BlockBody as _;
-use reth_provider::BlockNumReader;
+use reth_provider::{
+    BlockNumReader, HeaderProvider, ProviderError, ProviderResult, TransactionsProvider,
+};
 
 /// Monitor handle logic for handling consensus engine events and payload building events.
 ///
@@ -22,6 +24,8 @@ use reth_provider::BlockNumReader;
 /// 2. Payload building events:
 /// - Attributes event from engine_forkchoiceUpdated
 /// - BuiltPayload events from engine_getPayload
+///
+/// With backfill configured, commit events of the last canonical blocks are emitted first.
 pub fn start_monitor_handle<N, T, Provider>(
     task_executor: &dyn reth_tasks::TaskSpawner,
     monitor: Arc<XLayerMonitor>,
@@ -34,7 +38,13 @@ pub fn start_monitor_handle<N, T, Provider>(
     T: PayloadTypes + 'static,
     T::BuiltPayload: BuiltPayload,
     T::PayloadBuilderAttributes: PayloadBuilderAttributes,
-    Provider: BlockNumReader + Clone + Send + Sync + 'static,
+    Provider: BlockNumReader
+        + HeaderProvider
+        + TransactionsProvider<Transaction: TxHashRef>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
 {
     // Check if monitor is enabled, if not, return early
     if !monitor.args.enable {
@@ -53,6 +63,19 @@ pub fn start_monitor_handle<N, T, Provider>(
             return;
         };
 
+        // Engine events of blocks committed meanwhile are buffered by the stream
+        let backfill_blocks = monitor.args.backfill_blocks;
+        if backfill_blocks > 0 {
+            match backfill_commits(monitor.clone(), provider.clone(), backfill_blocks).await {
+                Ok(count) => {
+                    info!(target: "xlayer::monitor", count, "backfilled block commit events");
+                }
+                Err(err) => {
+                    warn!(target: "xlayer::monitor", %err, "failed to backfill block commit events");
+                }
+            }
+        }
+
         loop {
             tokio::select! {
                 // Handle consensus engine events
@@ -62,6 +85,9 @@ pub fn start_monitor_handle<N, T, Provider>(
                         ConsensusEngineEvent::BlockReceived(block_num_hash) => {
                             monitor.on_block_received(block_num_hash);
                         }
+                        ConsensusEngineEvent::InvalidBlock(block) => {
+                            monitor.on_invalid_payload(BlockNumHash::new(block.header().number(), block.hash()));
+                        }
                         ConsensusEngineEvent::CanonicalBlockAdded(executed_block, _duration) => {
                             let sealed_block = &executed_block.recovered_block;
                             let num_hash = BlockNumHash::new(sealed_block.header().number(), sealed_block.hash());
@@ -99,3 +125,73 @@ pub fn start_monitor_handle<N, T, Provider>(
 
     task_executor.spawn_critical("xlayer monitor handle", Box::pin(monitor_handle));
 }
+
+/// Emits the commit events of the last `blocks` canonical blocks, oldest first, and returns the
+/// number of blocks replayed.
+///
+/// The blocks are read from the database on the blocking pool, a large backfill would otherwise
+/// stall the runtime worker at startup.
+async fn backfill_commits<Provider>(
+    monitor: Arc<XLayerMonitor>,
+    provider: Provider,
+    blocks: u64,
+) -> ProviderResult<u64>
+where
+    Provider: BlockNumReader
+        + HeaderProvider
+        + TransactionsProvider<Transaction: TxHashRef>
+        + Send
+        + 'static,
+{
+    tokio::task::spawn_blocking(move || replay_commits(&monitor, &provider, blocks))
+        .await
+        .map_err(ProviderError::other)?
+}
+
+/// Replays the commit events of [`backfill_commits`] on the current thread.
+fn replay_commits<Provider>(
+    monitor: &XLayerMonitor,
+    provider: &Provider,
+    blocks: u64,
+) -> ProviderResult<u64>
+where
+    Provider: BlockNumReader + HeaderProvider + TransactionsProvider<Transaction: TxHashRef>,
+{
+    let best = provider.best_block_number()?;
+    let start = best.saturating_sub(blocks - 1);
+    let mut count = 0;
+    for number in start..=best {
+        let Some(header) = provider.sealed_header(number)? else { continue };
+        let num_hash = BlockNumHash::new(number, header.hash());
+        for tx in provider.transactions_by_block(number.into())?.unwrap_or_default() {
+            monitor.on_tx_commit(num_hash, *tx.tx_hash());
+        }
+        monitor.on_block_commit(num_hash);
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_consensus::{Block, Header};
+    use reth_provider::test_utils::MockEthProvider;
+
+    #[tokio::test]
+    async fn test_backfill_commits() {
+        let provider = MockEthProvider::default();
+        for number in 0..3 {
+            let header = Header { number, ..Default::default() };
+            provider.add_block(header.hash_slow(), Block::new(header, Default::default()));
+        }
+        let monitor = Arc::new(XLayerMonitor::default());
+
+        // The last two blocks
+        let count = backfill_commits(monitor.clone(), provider.clone(), 2).await.unwrap();
+        assert_eq!(count, 2);
+        // More blocks than the chain has, replayed from genesis
+        let count = backfill_commits(monitor, provider, 10).await.unwrap();
+        assert_eq!(count, 3);
+    }
+}
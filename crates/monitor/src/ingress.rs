@@ -0,0 +1,145 @@
+use std::{
+    net::IpAddr,
+    sync::OnceLock,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{Address, B256};
+use moka::sync::Cache;
+use reth_metrics::{
+    metrics::{Counter, Histogram},
+    Metrics,
+};
+use tracing::debug;
+
+/// Maximum number of accepted transactions awaiting flashblock inclusion.
+const MAX_PENDING_TXS: u64 = 1_000_000;
+
+/// Accepted transactions not included within this window are dropped from tracking.
+const PENDING_TX_TTL: Duration = Duration::from_secs(600);
+
+static INGRESS_TRACKER: OnceLock<TxIngressTracker> = OnceLock::new();
+
+/// Installs the global transaction ingress tracker. Only meaningful in sequencer mode, where the
+/// RPC ingress and the flashblocks builder run in the same process.
+pub fn init_ingress_tracker() -> &'static TxIngressTracker {
+    INGRESS_TRACKER.get_or_init(TxIngressTracker::new)
+}
+
+/// Returns the global transaction ingress tracker, if installed.
+pub fn ingress_tracker() -> Option<&'static TxIngressTracker> {
+    INGRESS_TRACKER.get()
+}
+
+/// Metadata of a transaction accepted via RPC.
+///
+/// The sender and fee of the transaction are recovered by txpool validation and recorded
+/// separately, see [`TxIngressTracker::on_validated`].
+#[derive(Debug, Clone)]
+pub struct TxIngress {
+    /// Transaction hash
+    pub tx_hash: B256,
+    /// Monotonic arrival time, used for latency measurements
+    pub arrived: Instant,
+    /// Wall clock arrival time
+    pub arrived_at: SystemTime,
+    /// Coarse source network of the client, see [`ip_bucket`]
+    pub source: String,
+}
+
+impl TxIngress {
+    pub fn new(
+        tx_hash: B256,
+        arrived: Instant,
+        arrived_at: SystemTime,
+        client: Option<IpAddr>,
+    ) -> Self {
+        Self { tx_hash, arrived, arrived_at, source: ip_bucket(client) }
+    }
+}
+
+/// Returns the network of the client address, `/24` for IPv4 and `/48` for IPv6, so that
+/// ingress can be attributed without recording full client addresses.
+pub fn ip_bucket(ip: Option<IpAddr>) -> String {
+    match ip {
+        Some(IpAddr::V4(ip)) => {
+            let [a, b, c, _] = ip.octets();
+            format!("{a}.{b}.{c}.0/24")
+        }
+        Some(IpAddr::V6(ip)) => {
+            let s = ip.segments();
+            format!("{:x}:{:x}:{:x}::/48", s[0], s[1], s[2])
+        }
+        None => "unknown".to_string(),
+    }
+}
+
+/// Metrics for transaction ingress in sequencer mode.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_monitor_ingress")]
+struct TxIngressMetrics {
+    /// Number of transactions accepted via RPC
+    accepted_txs: Counter,
+    /// Number of accepted transactions included in a flashblock
+    included_txs: Counter,
+    /// Latency from RPC acceptance to flashblock inclusion in seconds
+    acceptance_to_inclusion_latency: Histogram,
+}
+
+/// Tracks accepted transactions until they are included in a flashblock.
+pub struct TxIngressTracker {
+    pending: Cache<B256, Instant>,
+    metrics: TxIngressMetrics,
+}
+
+impl TxIngressTracker {
+    fn new() -> Self {
+        Self {
+            pending: Cache::builder()
+                .max_capacity(MAX_PENDING_TXS)
+                .time_to_live(PENDING_TX_TTL)
+                .build(),
+            metrics: TxIngressMetrics::default(),
+        }
+    }
+
+    /// Records a transaction accepted via RPC.
+    pub fn on_accepted(&self, ingress: &TxIngress) {
+        self.metrics.accepted_txs.increment(1);
+        self.pending.insert(ingress.tx_hash, ingress.arrived);
+
+        let arrived_at_ms =
+            ingress.arrived_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        debug!(
+            target: "xlayer::monitor::ingress",
+            tx_hash = %ingress.tx_hash,
+            arrived_at_ms,
+            source = %ingress.source,
+            "Transaction accepted"
+        );
+    }
+
+    /// Records the sender and max fee per gas (gas price for legacy transactions) of a
+    /// transaction, as recovered by txpool validation.
+    pub fn on_validated(&self, tx_hash: B256, sender: Address, gas_price: u128) {
+        debug!(
+            target: "xlayer::monitor::ingress",
+            %tx_hash,
+            %sender,
+            gas_price,
+            "Transaction validated"
+        );
+    }
+
+    /// Records the transactions included in a published flashblock.
+    pub fn on_included(&self, tx_hashes: &[B256]) {
+        for tx_hash in tx_hashes {
+            if let Some(arrived) = self.pending.remove(tx_hash) {
+                self.metrics.included_txs.increment(1);
+                self.metrics
+                    .acceptance_to_inclusion_latency
+                    .record(arrived.elapsed().as_secs_f64());
+            }
+        }
+    }
+}
@@ -4,10 +4,31 @@
 
 mod args;
 mod handle;
+mod ingress;
 mod monitor;
 mod rpc;
+mod slow_query;
+mod telemetry;
+mod timestamps;
+mod trace_queue;
 
 pub use args::FullLinkMonitorArgs;
 pub use handle::start_monitor_handle;
+pub use ingress::{ingress_tracker, init_ingress_tracker, ip_bucket, TxIngress, TxIngressTracker};
 pub use monitor::XLayerMonitor;
 pub use rpc::RpcMonitorLayer;
+pub use slow_query::{
+    fingerprint, SlowQuery, SlowQueryApiClient, SlowQueryApiServer, SlowQueryLog, SlowQueryRpc,
+    DEFAULT_SLOW_QUERY_CAPACITY,
+};
+pub use telemetry::{
+    RouteCounters, TelemetryConfig, TelemetryReport, TelemetryReporter, TelemetrySource,
+};
+pub use timestamps::{
+    init_tx_timestamps, start_tx_timestamps_handle, tx_timestamps, TxTimestampLog, TxTimestamps,
+    TxTimestampsApiClient, TxTimestampsApiServer, TxTimestampsRpc, DEFAULT_TX_TIMESTAMPS_RETENTION,
+};
+pub use trace_queue::{
+    init_trace_queue, trace_block, trace_transaction, OverflowPolicy, TraceQueue,
+    DEFAULT_TRACE_QUEUE_CAPACITY,
+};
@@ -1,10 +1,28 @@
-use crate::args::FullLinkMonitorArgs;
+use crate::{
+    args::FullLinkMonitorArgs,
+    ingress::TxIngress,
+    trace_queue::{trace_block, trace_transaction},
+};
 
 use std::sync::Arc;
 
 use alloy_eips::BlockNumHash;
 use alloy_primitives::B256;
-use xlayer_trace_monitor::{from_b256, get_global_tracer, TransactionProcessId};
+use reth_metrics::{metrics::Counter, Metrics};
+use tracing::warn;
+use xlayer_trace_monitor::TransactionProcessId;
+
+/// Metrics for rejected transactions and invalid payloads.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_monitor_rejections")]
+struct RejectionMetrics {
+    /// Number of transactions rejected by txpool validation
+    txpool_rejected_txs: Counter,
+    /// Number of transactions whose forwarding to the sequencer failed
+    sequencer_forward_rejected_txs: Counter,
+    /// Number of payloads reported invalid by the engine
+    invalid_payloads: Counter,
+}
 
 /// XLayerMonitor holds monitoring hook logic for full link monitoring requirements.
 #[derive(Clone, Default)]
@@ -16,6 +34,8 @@ pub struct XLayerMonitor {
     pub flashblocks_enabled: bool,
     /// Whether this node is running in sequencer mode (true) or RPC mode (false)
     pub is_sequencer_mode: bool,
+    /// Negative path metrics
+    rejection_metrics: RejectionMetrics,
 }
 
 impl XLayerMonitor {
@@ -24,7 +44,12 @@ impl XLayerMonitor {
         flashblocks_enabled: bool,
         is_sequencer_mode: bool,
     ) -> Arc<Self> {
-        Arc::new(Self { args, flashblocks_enabled, is_sequencer_mode })
+        Arc::new(Self {
+            args,
+            flashblocks_enabled,
+            is_sequencer_mode,
+            rejection_metrics: RejectionMetrics::default(),
+        })
     }
 
     /// Check if this node is running in sequencer mode
@@ -33,80 +58,107 @@ impl XLayerMonitor {
     }
 
     /// Handle transaction received via RPC (eth_sendRawTransaction).
-    pub fn on_recv_transaction(&self, _method: &str, tx_hash: B256) {
-        if let Some(tracer) = get_global_tracer() {
-            if self.is_sequencer() {
-                // SeqReceiveTxEnd: eth_sendRawTransaction (seq handler)
-                tracer.log_transaction(
-                    from_b256(tx_hash),
-                    TransactionProcessId::SeqReceiveTxEnd,
-                    None,
-                );
-            } else {
-                // RpcReceiveTxEnd: eth_sendRawTransaction (RPC handler)
-                tracer.log_transaction(
-                    from_b256(tx_hash),
-                    TransactionProcessId::RpcReceiveTxEnd,
-                    None,
-                );
-            }
+    ///
+    /// In sequencer mode the ingress metadata is mirrored to the ingress tracker, which measures
+    /// the latency until the transaction is included in a flashblock.
+    pub fn on_recv_transaction(&self, _method: &str, tx_hash: B256, ingress: Option<&TxIngress>) {
+        if self.is_sequencer()
+            && let Some(ingress) = ingress
+            && let Some(tracker) = crate::ingress::ingress_tracker()
+        {
+            tracker.on_accepted(ingress);
+        }
+
+        if self.is_sequencer() {
+            // SeqReceiveTxEnd: eth_sendRawTransaction (seq handler)
+            trace_transaction(tx_hash, TransactionProcessId::SeqReceiveTxEnd, None);
+        } else {
+            // RpcReceiveTxEnd: eth_sendRawTransaction (RPC handler)
+            trace_transaction(tx_hash, TransactionProcessId::RpcReceiveTxEnd, None);
         }
     }
 
+    /// Handle transaction rejected via RPC (eth_sendRawTransaction).
+    ///
+    /// The sequencer validates submissions in its txpool, while RPC nodes forward them to the
+    /// sequencer first, so rejections on RPC nodes are reported as sequencer forward errors. The
+    /// rejection ends the receive stage of the transaction trace, the reason is only logged.
+    pub fn on_tx_rejected(&self, method: &str, tx_hash: Option<B256>, code: i64, reason: &str) {
+        let (stage, process) = if self.is_sequencer() {
+            self.rejection_metrics.txpool_rejected_txs.increment(1);
+            ("txpool", TransactionProcessId::SeqReceiveTxEnd)
+        } else {
+            self.rejection_metrics.sequencer_forward_rejected_txs.increment(1);
+            ("sequencer_forward", TransactionProcessId::RpcReceiveTxEnd)
+        };
+        if let Some(tx_hash) = tx_hash {
+            trace_transaction(tx_hash, process, None);
+        }
+        warn!(
+            target: "xlayer::monitor::rejection",
+            method,
+            tx_hash = ?tx_hash,
+            stage,
+            code,
+            reason,
+            "Transaction rejected"
+        );
+    }
+
+    /// Handle invalid payload event (engine API returned INVALID).
+    /// This is triggered by ConsensusEngineEvent::InvalidBlock. The rejection ends the block trace
+    /// like a commit would, the reason is only logged.
+    pub fn on_invalid_payload(&self, num_hash: BlockNumHash) {
+        self.rejection_metrics.invalid_payloads.increment(1);
+        let process = if self.is_sequencer() {
+            TransactionProcessId::SeqBlockBuildEnd
+        } else {
+            TransactionProcessId::RpcBlockInsertEnd
+        };
+        trace_block(num_hash.hash, num_hash.number, process);
+        warn!(
+            target: "xlayer::monitor::rejection",
+            block_number = num_hash.number,
+            block_hash = %num_hash.hash,
+            stage = "engine",
+            reason = "invalid payload",
+            "Payload rejected"
+        );
+    }
+
     /// Handle block build start event (when payload attributes are received from CL).
     /// This is triggered when the consensus layer sends payload attributes via engine_forkchoiceUpdatedV*.
     pub fn on_block_build_start(&self, block_number: u64) {
-        if let Some(tracer) = get_global_tracer()
-            && self.is_sequencer()
-        {
+        if self.is_sequencer() {
             // Use block_number as the hash for block-level events
             // Note: We don't have the block hash here, so we use a zero hash
             // The block_number is the key identifier
             let block_hash = B256::ZERO; // Will be updated when block is built
-            tracer.log_block(
-                from_b256(block_hash),
-                block_number,
-                TransactionProcessId::SeqBlockBuildStart,
-            );
+            trace_block(block_hash, block_number, TransactionProcessId::SeqBlockBuildStart);
         }
     }
 
     /// Handle block send start event (when payload is built and ready to send).
     /// This is triggered when CL calls getPayload and the block is built.
     pub fn on_block_send_start(&self, num_hash: BlockNumHash) {
-        if let Some(tracer) = get_global_tracer()
-            && self.is_sequencer()
-        {
-            tracer.log_block(
-                from_b256(num_hash.hash),
-                num_hash.number,
-                TransactionProcessId::SeqBlockSendStart,
-            );
+        if self.is_sequencer() {
+            trace_block(num_hash.hash, num_hash.number, TransactionProcessId::SeqBlockSendStart);
         }
     }
 
     /// Handle block received event (when newPayload is called).
     /// This is triggered by ConsensusEngineEvent::BlockReceived.
     pub fn on_block_received(&self, num_hash: BlockNumHash) {
-        if let Some(tracer) = get_global_tracer()
-            && !self.is_sequencer()
-        {
-            tracer.log_block(
-                from_b256(num_hash.hash),
-                num_hash.number,
-                TransactionProcessId::RpcBlockReceiveEnd,
-            );
+        if !self.is_sequencer() {
+            trace_block(num_hash.hash, num_hash.number, TransactionProcessId::RpcBlockReceiveEnd);
         }
     }
 
     /// Handle transaction commits to the canonical chain.
     pub fn on_tx_commit(&self, _num_hash: BlockNumHash, tx_hash: B256) {
-        if !self.flashblocks_enabled
-            && let Some(tracer) = get_global_tracer()
-            && self.is_sequencer()
-        {
-            tracer.log_transaction(
-                from_b256(tx_hash),
+        if !self.flashblocks_enabled && self.is_sequencer() {
+            trace_transaction(
+                tx_hash,
                 TransactionProcessId::SeqTxExecutionEnd,
                 Some(_num_hash.number),
             );
@@ -115,22 +167,12 @@ impl XLayerMonitor {
 
     /// Handle block commits to the canonical chain.
     pub fn on_block_commit(&self, num_hash: BlockNumHash) {
-        if let Some(tracer) = get_global_tracer() {
-            if self.is_sequencer() {
-                // SeqBlockBuildEnd: canon stream update (seq)
-                tracer.log_block(
-                    from_b256(num_hash.hash),
-                    num_hash.number,
-                    TransactionProcessId::SeqBlockBuildEnd,
-                );
-            } else {
-                // RpcBlockInsertEnd: canon stream update (RPC)
-                tracer.log_block(
-                    from_b256(num_hash.hash),
-                    num_hash.number,
-                    TransactionProcessId::RpcBlockInsertEnd,
-                );
-            }
+        if self.is_sequencer() {
+            // SeqBlockBuildEnd: canon stream update (seq)
+            trace_block(num_hash.hash, num_hash.number, TransactionProcessId::SeqBlockBuildEnd);
+        } else {
+            // RpcBlockInsertEnd: canon stream update (RPC)
+            trace_block(num_hash.hash, num_hash.number, TransactionProcessId::RpcBlockInsertEnd);
         }
     }
 }
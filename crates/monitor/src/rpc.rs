@@ -1,6 +1,9 @@
-use crate::monitor::XLayerMonitor;
+use crate::{
+    ingress::TxIngress, monitor::XLayerMonitor, slow_query::SlowQueryLog, telemetry::RouteCounters,
+    timestamps::TxTimestampLog,
+};
 
-use alloy_primitives::B256;
+use alloy_primitives::{keccak256, Bytes, B256};
 use futures::future::Either;
 use jsonrpsee::{
     core::middleware::{Batch, Notification},
@@ -8,19 +11,48 @@ use jsonrpsee::{
     types::Request,
     MethodResponse,
 };
-use std::{future::Future, sync::Arc};
+use std::{
+    future::Future,
+    sync::Arc,
+    time::{Instant, SystemTime},
+};
 use tower::Layer;
 use tracing::trace;
+use xlayer_legacy_rpc::{
+    middleware::{batch_via_call, client_ip},
+    RpcRoute,
+};
 
 /// Layer that creates the RPC full link monitor middleware.
 #[derive(Clone)]
 pub struct RpcMonitorLayer {
     monitor: Arc<XLayerMonitor>,
+    slow_queries: Option<Arc<SlowQueryLog>>,
+    route_counters: Option<Arc<RouteCounters>>,
+    tx_timestamps: Option<&'static TxTimestampLog>,
 }
 
 impl RpcMonitorLayer {
     pub fn new(monitor: Arc<XLayerMonitor>) -> Self {
-        Self { monitor }
+        Self { monitor, slow_queries: None, route_counters: None, tx_timestamps: None }
+    }
+
+    /// Timestamps the transactions accepted by submission calls.
+    pub fn with_tx_timestamps(mut self, tx_timestamps: &'static TxTimestampLog) -> Self {
+        self.tx_timestamps = Some(tx_timestamps);
+        self
+    }
+
+    /// Counts the route of every call for telemetry reports.
+    pub fn with_route_counters(mut self, route_counters: Arc<RouteCounters>) -> Self {
+        self.route_counters = Some(route_counters);
+        self
+    }
+
+    /// Records calls exceeding the latency threshold of the log.
+    pub fn with_slow_queries(mut self, slow_queries: Arc<SlowQueryLog>) -> Self {
+        self.slow_queries = slow_queries.is_enabled().then_some(slow_queries);
+        self
     }
 }
 
@@ -28,7 +60,13 @@ impl<S> Layer<S> for RpcMonitorLayer {
     type Service = RpcMonitorService<S>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        RpcMonitorService { inner, monitor: self.monitor.clone() }
+        RpcMonitorService {
+            inner,
+            monitor: self.monitor.clone(),
+            slow_queries: self.slow_queries.clone(),
+            route_counters: self.route_counters.clone(),
+            tx_timestamps: self.tx_timestamps,
+        }
     }
 }
 
@@ -37,43 +75,107 @@ impl<S> Layer<S> for RpcMonitorLayer {
 pub struct RpcMonitorService<S> {
     inner: S,
     monitor: Arc<XLayerMonitor>,
+    slow_queries: Option<Arc<SlowQueryLog>>,
+    route_counters: Option<Arc<RouteCounters>>,
+    tx_timestamps: Option<&'static TxTimestampLog>,
+}
+
+/// Returns the raw transaction of an `eth_sendRawTransaction` request.
+fn raw_transaction(req: &Request<'_>) -> Option<Bytes> {
+    let params = req.params();
+    let (raw,): (Bytes,) = serde_json::from_str(params.as_str()?).ok()?;
+    Some(raw)
 }
 
 impl<S> RpcServiceT for RpcMonitorService<S>
 where
-    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
 {
     type MethodResponse = MethodResponse;
     type NotificationResponse = S::NotificationResponse;
-    type BatchResponse = S::BatchResponse;
+    type BatchResponse = MethodResponse;
 
     fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
         let method = req.method_name();
-        if !self.monitor.args.enable
-            || !matches!(method, "eth_sendRawTransaction" | "eth_sendTransaction")
-        {
+        let is_submission = matches!(method, "eth_sendRawTransaction" | "eth_sendTransaction");
+        let monitor_submission = self.monitor.args.enable && is_submission;
+        let tx_timestamps = self.tx_timestamps.filter(|_| is_submission);
+        let track_submission = monitor_submission || tx_timestamps.is_some();
+        if !track_submission && self.slow_queries.is_none() && self.route_counters.is_none() {
             return Either::Left(self.inner.call(req));
         }
 
         let monitor = self.monitor.clone();
+        let route_counters = self.route_counters.clone();
+        let received_at = SystemTime::now();
         let inner = self.inner.clone();
         let method_owned = method.to_string();
+
+        // Capture the parameters, raw transaction and ingress metadata before the request is
+        // consumed, the raw transaction is only hashed if the submission is rejected. The
+        // parameters stay borrowed from the request body and are only fingerprinted if the call
+        // turns out slow
+        let slow_query = self
+            .slow_queries
+            .clone()
+            .map(|slow_queries| (slow_queries, req.params.clone(), Instant::now()));
+        let raw = (monitor_submission && method == "eth_sendRawTransaction")
+            .then(|| raw_transaction(&req))
+            .flatten();
+        let ingress = (monitor_submission && monitor.is_sequencer())
+            .then(|| (Instant::now(), received_at, client_ip(&req)));
+
         Either::Right(async move {
             // Call the inner service
             let response = inner.call(req).await;
+            if let Some(route_counters) = &route_counters {
+                route_counters.record(RpcRoute::of(&response));
+            }
+            if let Some((slow_queries, params, started)) = slow_query {
+                slow_queries.record(
+                    &method_owned,
+                    params.as_deref().map(|params| params.get()),
+                    RpcRoute::of(&response),
+                    started.elapsed(),
+                );
+            }
+            if !track_submission {
+                return response;
+            }
+
+            let Ok(response_json) = serde_json::from_str::<serde_json::Value>(response.as_ref())
+            else {
+                return response;
+            };
 
             // Try to parse the response as a transaction hash
-            if let Ok(response_json) = serde_json::from_str::<serde_json::Value>(response.as_ref())
-                && let Some(result) = response_json.get("result")
+            if let Some(result) = response_json.get("result")
                 && let Some(tx_hash_str) = result.as_str()
                 && let Ok(tx_hash) = tx_hash_str.parse::<B256>()
             {
-                monitor.on_recv_transaction(&method_owned, tx_hash);
+                if let Some(tx_timestamps) = tx_timestamps {
+                    tx_timestamps.on_received(tx_hash, received_at);
+                }
+                if !monitor_submission {
+                    return response;
+                }
+                let ingress = ingress.map(|(arrived, arrived_at, client)| {
+                    TxIngress::new(tx_hash, arrived, arrived_at, client)
+                });
+                monitor.on_recv_transaction(&method_owned, tx_hash, ingress.as_ref());
                 trace!(
                     target: "xlayer::monitor::rpc",
                     "Transaction submission intercepted: method={}",
                     method_owned
                 );
+            } else if monitor_submission && let Some(error) = response_json.get("error") {
+                let code = error.get("code").and_then(|c| c.as_i64()).unwrap_or_default();
+                let reason = error.get("message").and_then(|m| m.as_str()).unwrap_or_default();
+                monitor.on_tx_rejected(&method_owned, raw.as_ref().map(keccak256), code, reason);
             }
 
             response
@@ -81,9 +183,14 @@ where
     }
 
     fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
-        // For batches, we pass through to the inner service
-        // Could implement per-request tracing if needed
-        self.inner.batch(req)
+        // Transaction submissions are only tracked for single calls
+        if self.slow_queries.is_none() && self.route_counters.is_none() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so calls in a batch are recorded with their own
+        // latency and route
+        Either::Right(batch_via_call(self.clone(), req))
     }
 
     fn notification<'a>(
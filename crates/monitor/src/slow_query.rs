@@ -0,0 +1,290 @@
+//! Slow RPC query log.
+//!
+//! Calls exceeding the latency threshold are kept in a ring buffer with their method, a
+//! fingerprint of their parameters and the route they took through the legacy router, so the
+//! operator can find which real workloads are worth optimizing through `xlayer_getSlowQueries`.
+//! Fingerprints keep the shape of the parameters and drop their values, e.g. `eth_getLogs` calls
+//! are reduced to the width of their block range and the number of addresses and topics.
+//! `eth_subscribe` calls are reduced to their subscription kind, their other parameters may carry
+//! the API key of a subscription scope.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+use xlayer_legacy_rpc::RpcRoute;
+
+/// Default number of slow queries retained.
+pub const DEFAULT_SLOW_QUERY_CAPACITY: usize = 256;
+
+/// Block tags kept verbatim in fingerprints.
+const BLOCK_TAGS: [&str; 5] = ["latest", "earliest", "pending", "safe", "finalized"];
+
+/// A call that exceeded the latency threshold.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowQuery {
+    pub method: String,
+    /// Shape of the parameters, see [`fingerprint`]
+    pub fingerprint: String,
+    pub route: RpcRoute,
+    pub duration_ms: u64,
+    /// Unix timestamp in milliseconds at which the call completed
+    pub timestamp: u64,
+}
+
+/// Ring buffer of the most recent slow queries.
+#[derive(Debug)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    capacity: usize,
+    entries: Mutex<VecDeque<SlowQuery>>,
+}
+
+impl SlowQueryLog {
+    /// Creates a log of calls taking at least `threshold`. A zero threshold or capacity disables
+    /// the log.
+    pub fn new(threshold: Duration, capacity: usize) -> Arc<Self> {
+        Arc::new(Self { threshold, capacity, entries: Mutex::new(VecDeque::new()) })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.threshold.is_zero() && self.capacity > 0
+    }
+
+    /// Records the call if it took at least the threshold.
+    pub fn record(&self, method: &str, params: Option<&str>, route: RpcRoute, elapsed: Duration) {
+        if !self.is_enabled() || elapsed < self.threshold {
+            return;
+        }
+
+        let query = SlowQuery {
+            method: method.to_string(),
+            fingerprint: fingerprint(method, params),
+            route,
+            duration_ms: elapsed.as_millis() as u64,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+                as u64,
+        };
+        info!(
+            target: "xlayer::monitor::rpc",
+            method,
+            fingerprint = %query.fingerprint,
+            route = ?route,
+            duration_ms = query.duration_ms,
+            "Slow RPC call"
+        );
+
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back(query);
+    }
+
+    /// Returns up to `limit` slow queries, most recent first.
+    pub fn recent(&self, limit: usize) -> Vec<SlowQuery> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.iter().rev().take(limit).cloned().collect()
+    }
+}
+
+/// Returns the fingerprint of the parameters of a call.
+pub fn fingerprint(method: &str, params: Option<&str>) -> String {
+    let Some(params) = params.and_then(|params| serde_json::from_str::<Value>(params).ok()) else {
+        return "[]".to_string();
+    };
+    if method == "eth_getLogs"
+        && let Some(filter) = params.get(0).and_then(Value::as_object)
+    {
+        return logs_filter_fingerprint(filter);
+    }
+    if method == "eth_subscribe" {
+        return match params.get(0).and_then(Value::as_str) {
+            Some(kind) if kind.len() <= 32 && kind.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                format!("[{kind}]")
+            }
+            _ => "[redacted]".to_string(),
+        };
+    }
+    shape(&params)
+}
+
+/// Reduces an `eth_getLogs` filter to its block range width and address and topic counts.
+fn logs_filter_fingerprint(filter: &serde_json::Map<String, Value>) -> String {
+    let range = if filter.contains_key("blockHash") {
+        "blockHash".to_string()
+    } else {
+        let from = filter.get("fromBlock").map(block_number);
+        let to = filter.get("toBlock").map(block_number);
+        match (from, to) {
+            (Some(Ok(from)), Some(Ok(to))) => (to.saturating_sub(from) + 1).to_string(),
+            (from, to) => {
+                let end = |end: Option<Result<u64, String>>| match end {
+                    Some(Ok(_)) => "number".to_string(),
+                    Some(Err(tag)) => tag,
+                    None => "latest".to_string(),
+                };
+                format!("{}..{}", end(from), end(to))
+            }
+        }
+    };
+    let addresses = match filter.get("address") {
+        Some(Value::Array(addresses)) => addresses.len(),
+        Some(Value::String(_)) => 1,
+        _ => 0,
+    };
+    let topics = match filter.get("topics") {
+        Some(Value::Array(topics)) => topics.iter().filter(|topic| !topic.is_null()).count(),
+        _ => 0,
+    };
+    format!("range:{range},addresses:{addresses},topics:{topics}")
+}
+
+/// Parses a block number, returning the normalized block tag otherwise.
+fn block_number(value: &Value) -> Result<u64, String> {
+    let shape = shape(value);
+    value
+        .as_str()
+        .and_then(|number| number.strip_prefix("0x"))
+        .and_then(|number| u64::from_str_radix(number, 16).ok())
+        .ok_or(shape)
+}
+
+/// Returns the shape of a JSON value, dropping its values.
+fn shape(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(_) => "bool".to_string(),
+        Value::Number(_) => "number".to_string(),
+        Value::String(s) => match s.strip_prefix("0x") {
+            _ if BLOCK_TAGS.contains(&s.as_str()) => s.clone(),
+            Some(hex) if hex.len() == 40 => "address".to_string(),
+            Some(hex) if hex.len() == 64 => "hash".to_string(),
+            Some(hex) if hex.len() <= 16 => "quantity".to_string(),
+            Some(_) => "data".to_string(),
+            None => "string".to_string(),
+        },
+        Value::Array(values) => {
+            format!("[{}]", values.iter().map(shape).collect::<Vec<_>>().join(","))
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<_> = map.keys().map(String::as_str).collect();
+            keys.sort_unstable();
+            format!("{{{}}}", keys.join(","))
+        }
+    }
+}
+
+/// Slow query log API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait SlowQueryApi {
+    /// Returns the most recent slow queries, most recent first.
+    #[method(name = "getSlowQueries")]
+    async fn slow_queries(&self, limit: Option<usize>) -> RpcResult<Vec<SlowQuery>>;
+}
+
+/// Implementation of [`SlowQueryApiServer`].
+#[derive(Debug)]
+pub struct SlowQueryRpc {
+    log: Arc<SlowQueryLog>,
+}
+
+impl SlowQueryRpc {
+    pub fn new(log: Arc<SlowQueryLog>) -> Self {
+        Self { log }
+    }
+}
+
+#[async_trait]
+impl SlowQueryApiServer for SlowQueryRpc {
+    async fn slow_queries(&self, limit: Option<usize>) -> RpcResult<Vec<SlowQuery>> {
+        Ok(self.log.recent(limit.unwrap_or(usize::MAX)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_logs_fingerprint() {
+        let params = r#"[{"fromBlock":"0x10","toBlock":"0x1f","address":["0x1111111111111111111111111111111111111111","0x2222222222222222222222222222222222222222"],"topics":["0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef",null]}]"#;
+        assert_eq!(fingerprint("eth_getLogs", Some(params)), "range:16,addresses:2,topics:1");
+
+        let params =
+            r#"[{"fromBlock":"0x10","address":"0x1111111111111111111111111111111111111111"}]"#;
+        assert_eq!(
+            fingerprint("eth_getLogs", Some(params)),
+            "range:number..latest,addresses:1,topics:0"
+        );
+
+        let params = r#"[{"blockHash":"0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"}]"#;
+        assert_eq!(
+            fingerprint("eth_getLogs", Some(params)),
+            "range:blockHash,addresses:0,topics:0"
+        );
+    }
+
+    #[test]
+    fn test_params_fingerprint() {
+        let params = r#"["0x1111111111111111111111111111111111111111","latest"]"#;
+        assert_eq!(fingerprint("eth_getBalance", Some(params)), "[address,latest]");
+
+        let params = r#"[{"to":"0x1111111111111111111111111111111111111111","data":"0x70a08231000000000000000000000000111111111111111111111111111111111111111111"},"0x10"]"#;
+        assert_eq!(fingerprint("eth_call", Some(params)), "[{data,to},quantity]");
+
+        assert_eq!(fingerprint("eth_blockNumber", None), "[]");
+        assert_eq!(
+            fingerprint("eth_getBlockByNumber", Some(r#"["pending",true]"#)),
+            "[pending,bool]"
+        );
+    }
+
+    #[test]
+    fn test_subscribe_fingerprint() {
+        let params = r#"["flashblocks",{"headerInfo":true},{"apiKey":"secret"}]"#;
+        assert_eq!(fingerprint("eth_subscribe", Some(params)), "[flashblocks]");
+        assert_eq!(fingerprint("eth_subscribe", Some(r#"[{"apiKey":"secret"}]"#)), "[redacted]");
+    }
+
+    #[test]
+    fn test_slow_query_log() {
+        let log = SlowQueryLog::new(Duration::from_millis(100), 2);
+        log.record("eth_chainId", None, RpcRoute::Local, Duration::from_millis(10));
+        assert!(log.recent(10).is_empty());
+
+        log.record("eth_getLogs", None, RpcRoute::Hybrid, Duration::from_millis(100));
+        log.record("eth_call", None, RpcRoute::Legacy, Duration::from_millis(200));
+        log.record("eth_getBalance", None, RpcRoute::Local, Duration::from_millis(300));
+
+        let recent = log.recent(10);
+        assert_eq!(
+            recent.iter().map(|query| query.method.as_str()).collect::<Vec<_>>(),
+            ["eth_getBalance", "eth_call"]
+        );
+        assert_eq!(recent[1].route, RpcRoute::Legacy);
+        assert_eq!(log.recent(1).len(), 1);
+
+        let value = serde_json::to_value(&recent[1]).unwrap();
+        assert_eq!(value["route"], "legacy");
+        assert_eq!(value["durationMs"], 200);
+    }
+
+    #[test]
+    fn test_disabled_slow_query_log() {
+        let log = SlowQueryLog::new(Duration::ZERO, DEFAULT_SLOW_QUERY_CAPACITY);
+        assert!(!log.is_enabled());
+        log.record("eth_getLogs", None, RpcRoute::Local, Duration::from_secs(10));
+        assert!(log.recent(10).is_empty());
+    }
+}
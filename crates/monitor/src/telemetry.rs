@@ -0,0 +1,216 @@
+//! Opt-in fleet telemetry.
+//!
+//! Operators may opt in to periodically report a few node statistics to an X Layer telemetry
+//! endpoint, so the core team can follow fleet health during hardfork rollouts. Reports are
+//! anonymous: they carry the client version, the chain, the node role, the head height, the
+//! flashblock lag and the share of RPC calls routed to the legacy node since the last report, but
+//! no addresses, hostnames, peers or request contents. The endpoint sees the source IP of the
+//! reporting node like any HTTP server.
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use xlayer_legacy_rpc::RpcRoute;
+
+/// Timeout of a report request.
+const REPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Telemetry reporter configuration
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TelemetryConfig {
+    /// Endpoint the reports are posted to
+    pub url: String,
+    /// Interval between reports
+    pub interval: Duration,
+}
+
+/// Node statistics read when a report is sent.
+pub trait TelemetrySource: Send + Sync {
+    /// Returns the number of the canonical head.
+    fn head_number(&self) -> Option<u64>;
+
+    /// Returns how far the pending flashblock trails the wall clock, `None` without flashblocks.
+    fn flashblock_lag(&self) -> Option<Duration>;
+}
+
+/// Number of RPC calls per route since the last report.
+#[derive(Debug, Default)]
+pub struct RouteCounters {
+    local: AtomicU64,
+    legacy: AtomicU64,
+    hybrid: AtomicU64,
+}
+
+impl RouteCounters {
+    /// Records the route of an RPC call.
+    pub fn record(&self, route: RpcRoute) {
+        let counter = match route {
+            RpcRoute::Local => &self.local,
+            RpcRoute::Legacy => &self.legacy,
+            RpcRoute::Hybrid => &self.hybrid,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the share of calls that reached the legacy node and resets the counters, `None` if
+    /// no call was recorded.
+    fn take_legacy_share(&self) -> Option<f64> {
+        let local = self.local.swap(0, Ordering::Relaxed);
+        let legacy =
+            self.legacy.swap(0, Ordering::Relaxed) + self.hybrid.swap(0, Ordering::Relaxed);
+        let total = local + legacy;
+        (total > 0).then(|| legacy as f64 / total as f64)
+    }
+}
+
+/// Statistics posted to the telemetry endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TelemetryReport {
+    /// Client version
+    pub version: String,
+    pub chain_id: u64,
+    /// `sequencer` or `rpc`
+    pub role: String,
+    /// Number of the canonical head
+    pub head_number: Option<u64>,
+    /// Lag of the pending flashblock in milliseconds
+    pub flashblock_lag_ms: Option<u64>,
+    /// Share of RPC calls since the last report served by the legacy node, in whole or in part
+    pub legacy_routing_share: Option<f64>,
+}
+
+/// Telemetry reporter metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_telemetry")]
+struct TelemetryMetrics {
+    /// Number of reports accepted by the endpoint
+    reports_sent: Counter,
+    /// Number of reports that couldn't be delivered
+    reports_failed: Counter,
+}
+
+/// Periodically posts a [`TelemetryReport`] to the configured endpoint.
+#[derive(Debug)]
+pub struct TelemetryReporter {
+    config: TelemetryConfig,
+    chain_id: u64,
+    is_sequencer: bool,
+    routes: Arc<RouteCounters>,
+}
+
+impl TelemetryReporter {
+    pub fn new(config: TelemetryConfig, chain_id: u64, is_sequencer: bool) -> Self {
+        Self { config, chain_id, is_sequencer, routes: Arc::default() }
+    }
+
+    /// Returns the route counters to attach to the RPC monitor middleware.
+    pub fn route_counters(&self) -> Arc<RouteCounters> {
+        self.routes.clone()
+    }
+
+    /// Builds a report from the current node statistics.
+    fn report(&self, source: &dyn TelemetrySource) -> TelemetryReport {
+        TelemetryReport {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            chain_id: self.chain_id,
+            role: if self.is_sequencer { "sequencer" } else { "rpc" }.to_string(),
+            head_number: source.head_number(),
+            flashblock_lag_ms: source.flashblock_lag().map(|lag| lag.as_millis() as u64),
+            legacy_routing_share: self.routes.take_legacy_share(),
+        }
+    }
+
+    /// Posts a report every interval, failed reports are dropped.
+    pub async fn run(self, source: impl TelemetrySource) {
+        let metrics = TelemetryMetrics::default();
+        let client = match reqwest::Client::builder().timeout(REPORT_TIMEOUT).build() {
+            Ok(client) => client,
+            Err(err) => {
+                warn!(target: "xlayer::telemetry", %err, "Failed to create telemetry client");
+                return;
+            }
+        };
+        info!(target: "xlayer::telemetry", url = %self.config.url, interval = ?self.config.interval, "Telemetry reporting enabled");
+
+        let mut interval = tokio::time::interval(self.config.interval);
+        // The first tick completes immediately, report once the node has been running a while
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let report = self.report(&source);
+            let res = client
+                .post(&self.config.url)
+                .json(&report)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match res {
+                Ok(_) => {
+                    metrics.reports_sent.increment(1);
+                    debug!(target: "xlayer::telemetry", ?report, "Sent telemetry report");
+                }
+                Err(err) => {
+                    metrics.reports_failed.increment(1);
+                    debug!(target: "xlayer::telemetry", %err, "Failed to send telemetry report");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct StaticSource;
+
+    impl TelemetrySource for StaticSource {
+        fn head_number(&self) -> Option<u64> {
+            Some(42)
+        }
+
+        fn flashblock_lag(&self) -> Option<Duration> {
+            Some(Duration::from_millis(250))
+        }
+    }
+
+    #[test]
+    fn test_legacy_share() {
+        let routes = RouteCounters::default();
+        assert_eq!(routes.take_legacy_share(), None);
+
+        for route in [RpcRoute::Local, RpcRoute::Local, RpcRoute::Legacy, RpcRoute::Hybrid] {
+            routes.record(route);
+        }
+        assert_eq!(routes.take_legacy_share(), Some(0.5));
+        // Counters restart with every report
+        assert_eq!(routes.take_legacy_share(), None);
+    }
+
+    #[test]
+    fn test_report() {
+        let config =
+            TelemetryConfig { url: "https://telemetry.invalid".into(), interval: Duration::ZERO };
+        let reporter = TelemetryReporter::new(config, 196, false);
+        reporter.route_counters().record(RpcRoute::Legacy);
+
+        let report = reporter.report(&StaticSource);
+        assert_eq!(report.role, "rpc");
+        assert_eq!(report.head_number, Some(42));
+        assert_eq!(report.flashblock_lag_ms, Some(250));
+        assert_eq!(report.legacy_routing_share, Some(1.0));
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["chainId"], 196);
+        assert_eq!(json["legacyRoutingShare"], 1.0);
+    }
+}
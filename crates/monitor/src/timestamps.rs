@@ -0,0 +1,203 @@
+//! Transaction timestamps for latency SLAs.
+//!
+//! Transactions accepted by `eth_sendRawTransaction` or `eth_sendTransaction` are timestamped when
+//! they reach the RPC ingress. The entry is completed when a flashblock built by this sequencer
+//! includes the transaction and when it is added to the canonical chain, and is served by
+//! `xlayer_getTransactionTimestamps` until the retention window expires. Transactions first seen
+//! elsewhere, e.g. through p2p gossip, are not tracked.
+
+use std::{
+    sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy_consensus::transaction::TxHashRef;
+use alloy_primitives::B256;
+use futures::StreamExt;
+use jsonrpsee::{core::RpcResult, proc_macros::rpc};
+use moka::sync::Cache;
+use reth_engine_primitives::ConsensusEngineEvent;
+use reth_primitives_traits::BlockBody as _;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// Maximum number of transactions whose timestamps are retained.
+const MAX_TIMESTAMPED_TXS: u64 = 1_000_000;
+
+/// Default time transaction timestamps are retained after the transaction was received.
+pub const DEFAULT_TX_TIMESTAMPS_RETENTION: Duration = Duration::from_secs(3600);
+
+static TX_TIMESTAMPS: OnceLock<TxTimestampLog> = OnceLock::new();
+
+/// Installs the global transaction timestamp log.
+pub fn init_tx_timestamps(retention: Duration) -> &'static TxTimestampLog {
+    TX_TIMESTAMPS.get_or_init(|| TxTimestampLog::new(retention))
+}
+
+/// Returns the global transaction timestamp log, if installed.
+pub fn tx_timestamps() -> Option<&'static TxTimestampLog> {
+    TX_TIMESTAMPS.get()
+}
+
+/// Timestamps of a transaction, as unix timestamps in milliseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxTimestamps {
+    /// First acceptance at the RPC ingress
+    pub received: u64,
+    /// First inclusion in a flashblock built by this node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flashblock_included: Option<u64>,
+    /// First addition to the canonical chain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub canonical_included: Option<u64>,
+}
+
+/// Timestamps of the transactions received within the retention window.
+#[derive(Debug)]
+pub struct TxTimestampLog {
+    entries: Cache<B256, TxTimestamps>,
+}
+
+impl TxTimestampLog {
+    fn new(retention: Duration) -> Self {
+        Self {
+            entries: Cache::builder()
+                .max_capacity(MAX_TIMESTAMPED_TXS)
+                .time_to_live(retention)
+                .build(),
+        }
+    }
+
+    /// Records the acceptance of a transaction at the RPC ingress, keeping the first one.
+    pub fn on_received(&self, tx_hash: B256, received_at: SystemTime) {
+        self.entries.get_with(tx_hash, || TxTimestamps {
+            received: unix_millis(received_at),
+            ..Default::default()
+        });
+    }
+
+    /// Records the inclusion of transactions in a flashblock.
+    pub fn on_flashblock_included(&self, tx_hashes: &[B256]) {
+        let now = unix_millis(SystemTime::now());
+        for tx_hash in tx_hashes {
+            self.update(tx_hash, |timestamps| {
+                timestamps.flashblock_included.get_or_insert(now);
+            });
+        }
+    }
+
+    /// Records the addition of transactions to the canonical chain.
+    pub fn on_canonical_included(&self, tx_hashes: impl IntoIterator<Item = B256>) {
+        let now = unix_millis(SystemTime::now());
+        for tx_hash in tx_hashes {
+            self.update(&tx_hash, |timestamps| {
+                timestamps.canonical_included.get_or_insert(now);
+            });
+        }
+    }
+
+    /// Returns the timestamps of a transaction, if it was received within the retention window.
+    pub fn get(&self, tx_hash: &B256) -> Option<TxTimestamps> {
+        self.entries.get(tx_hash)
+    }
+
+    fn update(&self, tx_hash: &B256, f: impl FnOnce(&mut TxTimestamps)) {
+        if let Some(mut timestamps) = self.entries.get(tx_hash) {
+            f(&mut timestamps);
+            self.entries.insert(*tx_hash, timestamps);
+        }
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Records the canonical inclusion of timestamped transactions from the consensus engine events.
+pub fn start_tx_timestamps_handle<N>(
+    task_executor: &dyn reth_tasks::TaskSpawner,
+    log: &'static TxTimestampLog,
+    mut engine_event_stream: reth_tokio_util::EventStream<ConsensusEngineEvent<N>>,
+) where
+    N: reth_primitives_traits::NodePrimitives + 'static,
+    N::SignedTx: TxHashRef,
+{
+    let handle = async move {
+        while let Some(engine_event) = engine_event_stream.next().await {
+            if let ConsensusEngineEvent::CanonicalBlockAdded(executed_block, _duration) =
+                engine_event
+            {
+                let block = &executed_block.recovered_block;
+                log.on_canonical_included(block.body().transactions().map(|tx| *tx.tx_hash()));
+            }
+        }
+        info!(target: "xlayer::monitor", "transaction timestamps handle stopped");
+    };
+
+    task_executor.spawn(Box::pin(handle));
+}
+
+/// Transaction timestamps API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait TxTimestampsApi {
+    /// Returns the timestamps of a transaction received by this node within the retention
+    /// window, null otherwise.
+    #[method(name = "getTransactionTimestamps")]
+    fn get_transaction_timestamps(&self, tx_hash: B256) -> RpcResult<Option<TxTimestamps>>;
+}
+
+/// Serves the transaction timestamps of the global log.
+#[derive(Debug, Clone, Copy)]
+pub struct TxTimestampsRpc {
+    log: &'static TxTimestampLog,
+}
+
+impl TxTimestampsRpc {
+    pub fn new(log: &'static TxTimestampLog) -> Self {
+        Self { log }
+    }
+}
+
+impl TxTimestampsApiServer for TxTimestampsRpc {
+    fn get_transaction_timestamps(&self, tx_hash: B256) -> RpcResult<Option<TxTimestamps>> {
+        Ok(self.log.get(&tx_hash))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tx_timestamps() {
+        let log = TxTimestampLog::new(Duration::from_secs(60));
+        let tx_hash = B256::with_last_byte(1);
+        let received_at = UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+
+        // Transactions not seen at the ingress are not tracked
+        log.on_flashblock_included(&[tx_hash]);
+        assert_eq!(log.get(&tx_hash), None);
+
+        log.on_received(tx_hash, received_at);
+        log.on_received(tx_hash, received_at + Duration::from_secs(1));
+        assert_eq!(
+            log.get(&tx_hash),
+            Some(TxTimestamps { received: 1_700_000_000_123, ..Default::default() })
+        );
+
+        log.on_flashblock_included(&[tx_hash]);
+        let included = log.get(&tx_hash).unwrap().flashblock_included.unwrap();
+        log.on_flashblock_included(&[tx_hash]);
+        assert_eq!(log.get(&tx_hash).unwrap().flashblock_included, Some(included));
+
+        log.on_canonical_included([tx_hash]);
+        let timestamps = log.get(&tx_hash).unwrap();
+        assert!(timestamps.canonical_included.unwrap() >= included);
+
+        let value = serde_json::to_value(timestamps).unwrap();
+        assert_eq!(value["received"], 1_700_000_000_123u64);
+        assert!(value["flashblockIncluded"].is_u64());
+        assert!(value["canonicalIncluded"].is_u64());
+    }
+}
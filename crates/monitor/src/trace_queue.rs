@@ -0,0 +1,259 @@
+//! Bounded queue between the full link monitor hooks and the trace sink.
+//!
+//! Hooks run on the RPC, engine and payload building paths, while the sink writes the events to
+//! its output. Events are queued and written by a dedicated thread, so a backed up sink fills the
+//! queue instead of stalling the hooks. What happens once the queue is full is up to the
+//! [`OverflowPolicy`] of the sink.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender, TrySendError},
+        OnceLock,
+    },
+};
+
+use alloy_primitives::B256;
+use reth_metrics::{metrics::Counter, Metrics};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tracing::warn;
+use xlayer_trace_monitor::{from_b256, get_global_tracer, TransactionProcessId};
+
+/// Default number of events queued for the trace sink.
+pub const DEFAULT_TRACE_QUEUE_CAPACITY: usize = 65_536;
+
+static TRACE_QUEUE: OnceLock<TraceQueue> = OnceLock::new();
+
+/// What a hook does when the queue of the trace sink is full.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The event is dropped and counted, hooks never wait for the sink
+    #[default]
+    FailOpen,
+    /// The hook blocks until the sink catches up, no event is lost
+    ///
+    /// Hooks on a multi-threaded tokio runtime block in place, handing the other tasks of their
+    /// worker to another thread. On a current-thread runtime the whole runtime waits for the sink.
+    FailClosed,
+}
+
+impl fmt::Display for OverflowPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FailOpen => f.write_str("fail-open"),
+            Self::FailClosed => f.write_str("fail-closed"),
+        }
+    }
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail-open" => Ok(Self::FailOpen),
+            "fail-closed" => Ok(Self::FailClosed),
+            _ => Err(format!(
+                "unknown overflow policy `{s}`, expected one of fail-open, fail-closed"
+            )),
+        }
+    }
+}
+
+/// Metrics of the trace sink queue.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_monitor_trace_queue")]
+struct TraceQueueMetrics {
+    /// Number of events queued for the trace sink
+    queued_events: Counter,
+    /// Number of events dropped because the queue was full
+    dropped_events: Counter,
+    /// Number of hooks that blocked because the queue was full
+    blocked_events: Counter,
+}
+
+/// An event written to the trace sink.
+enum TraceEvent {
+    Transaction { tx_hash: B256, process: TransactionProcessId, block_number: Option<u64> },
+    Block { block_hash: B256, block_number: u64, process: TransactionProcessId },
+}
+
+/// Queue of events awaiting the trace sink.
+pub struct TraceQueue {
+    sender: SyncSender<TraceEvent>,
+    policy: OverflowPolicy,
+    metrics: TraceQueueMetrics,
+}
+
+impl TraceQueue {
+    fn push(&self, event: TraceEvent) {
+        let event = match self.sender.try_send(event) {
+            Ok(()) => {
+                self.metrics.queued_events.increment(1);
+                return;
+            }
+            Err(TrySendError::Full(event)) => event,
+            Err(TrySendError::Disconnected(_)) => {
+                self.metrics.dropped_events.increment(1);
+                return;
+            }
+        };
+
+        match self.policy {
+            OverflowPolicy::FailOpen => self.metrics.dropped_events.increment(1),
+            OverflowPolicy::FailClosed => {
+                self.metrics.blocked_events.increment(1);
+                let sent = if on_multi_thread_runtime() {
+                    tokio::task::block_in_place(|| self.sender.send(event))
+                } else {
+                    self.sender.send(event)
+                };
+                if sent.is_ok() {
+                    self.metrics.queued_events.increment(1);
+                } else {
+                    self.metrics.dropped_events.increment(1);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the current thread runs on a multi-threaded tokio runtime, where blocking in place is
+/// allowed.
+fn on_multi_thread_runtime() -> bool {
+    Handle::try_current().is_ok_and(|handle| handle.runtime_flavor() == RuntimeFlavor::MultiThread)
+}
+
+/// Installs the global trace queue and starts the thread writing its events to the global tracer.
+pub fn init_trace_queue(capacity: usize, policy: OverflowPolicy) -> &'static TraceQueue {
+    TRACE_QUEUE.get_or_init(|| {
+        let (sender, receiver) = sync_channel(capacity);
+        if let Err(err) = std::thread::Builder::new()
+            .name("xlayer-trace-sink".to_string())
+            .spawn(move || drain(receiver))
+        {
+            warn!(target: "xlayer::monitor", %err, "failed to start the trace sink thread");
+        }
+        TraceQueue { sender, policy, metrics: TraceQueueMetrics::default() }
+    })
+}
+
+/// Writes queued events to the global tracer until the queue is dropped.
+fn drain(receiver: Receiver<TraceEvent>) {
+    for event in receiver {
+        let Some(tracer) = get_global_tracer() else { continue };
+        match event {
+            TraceEvent::Transaction { tx_hash, process, block_number } => {
+                tracer.log_transaction(from_b256(tx_hash), process, block_number);
+            }
+            TraceEvent::Block { block_hash, block_number, process } => {
+                tracer.log_block(from_b256(block_hash), block_number, process);
+            }
+        }
+    }
+}
+
+/// Logs a transaction event, through the trace queue if installed.
+pub fn trace_transaction(tx_hash: B256, process: TransactionProcessId, block_number: Option<u64>) {
+    if let Some(queue) = TRACE_QUEUE.get() {
+        queue.push(TraceEvent::Transaction { tx_hash, process, block_number });
+    } else if let Some(tracer) = get_global_tracer() {
+        tracer.log_transaction(from_b256(tx_hash), process, block_number);
+    }
+}
+
+/// Logs a block event, through the trace queue if installed.
+pub fn trace_block(block_hash: B256, block_number: u64, process: TransactionProcessId) {
+    if let Some(queue) = TRACE_QUEUE.get() {
+        queue.push(TraceEvent::Block { block_hash, block_number, process });
+    } else if let Some(tracer) = get_global_tracer() {
+        tracer.log_block(from_b256(block_hash), block_number, process);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queue(capacity: usize, policy: OverflowPolicy) -> (TraceQueue, Receiver<TraceEvent>) {
+        let (sender, receiver) = sync_channel(capacity);
+        (TraceQueue { sender, policy, metrics: TraceQueueMetrics::default() }, receiver)
+    }
+
+    fn block_event(block_number: u64) -> TraceEvent {
+        TraceEvent::Block {
+            block_hash: B256::ZERO,
+            block_number,
+            process: TransactionProcessId::SeqBlockBuildEnd,
+        }
+    }
+
+    fn block_numbers(receiver: &Receiver<TraceEvent>) -> Vec<u64> {
+        receiver
+            .try_iter()
+            .map(|event| match event {
+                TraceEvent::Block { block_number, .. } => block_number,
+                TraceEvent::Transaction { .. } => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_overflow_policy_parse() {
+        for policy in [OverflowPolicy::FailOpen, OverflowPolicy::FailClosed] {
+            assert_eq!(policy.to_string().parse::<OverflowPolicy>(), Ok(policy));
+        }
+        assert!("block".parse::<OverflowPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_fail_open_drops_when_full() {
+        let (queue, receiver) = queue(2, OverflowPolicy::FailOpen);
+        for block_number in 0..4 {
+            queue.push(block_event(block_number));
+        }
+        assert_eq!(block_numbers(&receiver), [0, 1]);
+    }
+
+    #[test]
+    fn test_fail_closed_blocks_until_drained() {
+        let (queue, receiver) = queue(2, OverflowPolicy::FailClosed);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for block_number in 0..4 {
+                    queue.push(block_event(block_number));
+                }
+            });
+            let received: Vec<_> = (0..4)
+                .map(|_| match receiver.recv().unwrap() {
+                    TraceEvent::Block { block_number, .. } => block_number,
+                    TraceEvent::Transaction { .. } => unreachable!(),
+                })
+                .collect();
+            assert_eq!(received, [0, 1, 2, 3]);
+        });
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_fail_closed_releases_runtime_worker() {
+        let (queue, receiver) = queue(2, OverflowPolicy::FailClosed);
+        let (started, on_started) = std::sync::mpsc::channel();
+
+        // Blocks the only worker on the full queue
+        let push = tokio::spawn(async move {
+            for block_number in 0..4 {
+                queue.push(block_event(block_number));
+            }
+        });
+        // Only runs if the blocked hook handed the worker over
+        tokio::spawn(async move { started.send(()).unwrap() });
+
+        let drained = std::thread::spawn(move || {
+            on_started.recv().unwrap();
+            (0..4).map(|_| receiver.recv().unwrap()).count()
+        });
+        push.await.unwrap();
+        assert_eq!(drained.join().unwrap(), 4);
+    }
+}
@@ -0,0 +1,93 @@
+//! Typed clients for the X Layer specific RPC methods.
+//!
+//! The client traits are generated from the same `#[rpc]` definitions the node serves, so a
+//! changed method name, parameter or response type breaks the build of its callers instead of
+//! failing at runtime. They are implemented for every jsonrpsee client, e.g. an `HttpClient`:
+//!
+//! ```ignore
+//! let client = HttpClientBuilder::default().build("http://localhost:8545")?;
+//! let health = client.health().await?;
+//! let enabled = XlayerRpcExtApiClient::<Ethereum>::flashblocks_enabled(&client).await?;
+//! ```
+//!
+//! The `flashblocks` subscription is consumed with the `xlayer-flashblocks-client` crate, which
+//! tracks the pending block across notifications and reconnects.
+
+pub use xlayer_builder::flashblocks::{
+    build_stats::{
+        FlashblockIterationStats, PayloadBuildStats, PayloadBuildStatsApiClient,
+        PayloadBuildStatsId, TxSelectionStats,
+    },
+    config_info::{
+        BuilderConfigApiClient, BuilderConfigInfo, FlashblocksConfigInfo, PriorityLaneInfo,
+    },
+    handoff::{
+        InFlightFlashblock, SequencerHandoffApiClient, SequencerStatus, TxPoolSummary, UnsafeHead,
+    },
+};
+pub use xlayer_flashblocks::{
+    deposits::{DepositQuery, DepositTransaction, DepositsApiClient, DepositsPage},
+    l1_status::{BlockRef, DaMode, L1Status, L1StatusApiClient},
+    preconf::{PreconfCommitment, Preconfirmation, PreconfirmationApiClient},
+    pubsub::{FlashblocksFilter, SubTxFilter},
+    subscription::FlashblocksSubscriptionApiClient,
+};
+pub use xlayer_legacy_rpc::{
+    admin::{LegacyRpcAdminApiClient, LegacyRpcConfigInfo, LegacyRpcConfigUpdate},
+    block_by_timestamp::{BlockByTimestampApiClient, Closest},
+    logs_paged::{LogsPage, LogsPagedApiClient},
+    RpcRoute,
+};
+pub use xlayer_monitor::{SlowQuery, SlowQueryApiClient, TxTimestamps, TxTimestampsApiClient};
+pub use xlayer_rpc::{
+    bundle_gas::{BundleGasEstimate, XlayerBundleGasApiClient},
+    fee_params::{FeeParams, FeeParamsApiClient},
+    finality::{FinalityTagsStatus, TagMapping, TagSource},
+    gas_oracle::{GasOracleConfigApiClient, GasOracleConfigInfo},
+    sender_nonce::SenderNonceApiClient,
+    witness::{ExecutionWitness, ExecutionWitnessApiClient},
+    XlayerHealth, XlayerHealthApiClient, XlayerRpcExtApiClient,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_network::Ethereum;
+    use jsonrpsee::{http_client::HttpClient, ws_client::WsClient};
+
+    fn assert_clients<C>()
+    where
+        C: XlayerRpcExtApiClient<Ethereum>
+            + XlayerHealthApiClient
+            + FlashblocksSubscriptionApiClient
+            + PreconfirmationApiClient
+            + DepositsApiClient
+            + L1StatusApiClient
+            + LegacyRpcAdminApiClient
+            + LogsPagedApiClient
+            + BlockByTimestampApiClient
+            + SlowQueryApiClient
+            + TxTimestampsApiClient
+            + PayloadBuildStatsApiClient
+            + BuilderConfigApiClient
+            + SequencerHandoffApiClient
+            + GasOracleConfigApiClient
+            + SenderNonceApiClient
+            + ExecutionWitnessApiClient
+            + XlayerBundleGasApiClient<Ethereum>,
+    {
+    }
+
+    fn assert_subscription_clients<C>()
+    where
+        C: FeeParamsApiClient,
+    {
+    }
+
+    #[test]
+    fn test_jsonrpsee_clients_implement_apis() {
+        assert_clients::<HttpClient>();
+        assert_clients::<WsClient>();
+        assert_subscription_clients::<WsClient>();
+    }
+}
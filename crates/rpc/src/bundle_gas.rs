@@ -0,0 +1,214 @@
+//! Gas estimation of dependent transaction bundles.
+//!
+//! `eth_estimateGas` estimates every transaction against the state of the block, so the second
+//! transaction of an approve + swap flow is estimated without the approval and fails or is
+//! estimated on the wrong path. `xlayer_estimateGasBundle` estimates the transactions of a bundle
+//! in order, each against the state left by the transactions before it.
+
+use std::sync::Arc;
+
+use alloy_primitives::U64;
+use alloy_rpc_types_eth::{
+    state::{AccountOverride, StateOverride},
+    BlockId,
+};
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, ErrorObjectOwned},
+};
+use reth_revm::{database::StateProviderDatabase, db::CacheDB};
+use reth_rpc::RpcTypes;
+use reth_rpc_eth_api::helpers::{Call, EstimateCall, EthCall};
+use revm::DatabaseCommit;
+use serde::{Deserialize, Serialize};
+
+/// Largest number of transactions in a bundle.
+const MAX_BUNDLE_SIZE: usize = 32;
+
+/// Gas estimates of a bundle, in bundle order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleGasEstimate {
+    /// Estimate of each transaction
+    pub gas_estimates: Vec<U64>,
+    /// Sum of the estimates
+    pub total_gas: U64,
+}
+
+impl BundleGasEstimate {
+    fn new(estimates: Vec<u64>) -> Self {
+        let total_gas = estimates.iter().fold(0u64, |total, gas| total.saturating_add(*gas));
+        Self {
+            gas_estimates: estimates.into_iter().map(U64::from).collect(),
+            total_gas: U64::from(total_gas),
+        }
+    }
+}
+
+/// Bundle gas estimation API
+#[rpc(server, client, namespace = "xlayer", server_bounds(
+    Net: 'static + RpcTypes,
+    <Net as RpcTypes>::TransactionRequest:
+        serde::de::DeserializeOwned + serde::Serialize
+), client_bounds(Net: 'static + RpcTypes))]
+pub trait XlayerBundleGasApi<Net: RpcTypes> {
+    /// Estimates the gas of each transaction of the bundle against the state left by the
+    /// transactions before it, at the given block or `latest`.
+    #[method(name = "estimateGasBundle")]
+    async fn estimate_gas_bundle(
+        &self,
+        requests: Vec<Net::TransactionRequest>,
+        block: Option<BlockId>,
+    ) -> RpcResult<BundleGasEstimate>;
+}
+
+/// Sequential gas estimation on top of the eth API.
+#[derive(Debug)]
+pub struct XlayerBundleGas<T> {
+    backend: Arc<T>,
+}
+
+impl<T> XlayerBundleGas<T> {
+    pub fn new(backend: Arc<T>) -> Self {
+        Self { backend }
+    }
+}
+
+#[async_trait]
+impl<T, Net> XlayerBundleGasApiServer<Net> for XlayerBundleGas<T>
+where
+    T: EthCall<NetworkTypes = Net> + Send + Sync + 'static,
+    Net: RpcTypes + Send + Sync + 'static,
+    <Net as RpcTypes>::TransactionRequest: serde::de::DeserializeOwned + serde::Serialize,
+{
+    async fn estimate_gas_bundle(
+        &self,
+        requests: Vec<Net::TransactionRequest>,
+        block: Option<BlockId>,
+    ) -> RpcResult<BundleGasEstimate> {
+        if requests.is_empty() || requests.len() > MAX_BUNDLE_SIZE {
+            return Err(ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("Bundle must hold between 1 and {MAX_BUNDLE_SIZE} transactions"),
+                None::<()>,
+            ));
+        }
+
+        let (evm_env, at) =
+            self.backend.evm_env_at(block.unwrap_or_default()).await.map_err(Into::into)?;
+        let outcome = self
+            .backend
+            .spawn_with_state_at_block(at, move |this, state| {
+                let mut db = CacheDB::new(StateProviderDatabase::new(&state));
+                let mut estimates = Vec::with_capacity(requests.len());
+                for (index, request) in requests.into_iter().enumerate() {
+                    // Earlier transactions are applied as overrides of the block state
+                    let overrides = cumulative_state(&db);
+                    let estimate = match this.estimate_gas_with(
+                        evm_env.clone(),
+                        request.clone(),
+                        &state,
+                        Some(overrides),
+                    ) {
+                        Ok(estimate) => estimate.saturating_to::<u64>(),
+                        Err(err) => return Ok(Err((index, err))),
+                    };
+
+                    let (evm_env, tx_env) = this.prepare_call_env(
+                        evm_env.clone(),
+                        request,
+                        &mut db,
+                        Default::default(),
+                    )?;
+                    let res = this.transact(&mut db, evm_env, tx_env)?;
+                    db.commit(res.state);
+                    estimates.push(estimate);
+                }
+                Ok(Ok(estimates))
+            })
+            .await
+            .map_err(Into::into)?;
+
+        match outcome {
+            Ok(estimates) => Ok(BundleGasEstimate::new(estimates)),
+            Err((index, err)) => {
+                let err: ErrorObjectOwned = err.into();
+                Err(ErrorObject::owned(
+                    err.code(),
+                    format!("transaction {index}: {}", err.message()),
+                    err.data(),
+                ))
+            }
+        }
+    }
+}
+
+/// Returns the accounts of the cache as state overrides.
+fn cumulative_state<ExtDB>(db: &CacheDB<ExtDB>) -> StateOverride {
+    let mut overrides = StateOverride::default();
+    for (address, account) in &db.cache.accounts {
+        let info = &account.info;
+        let code = info
+            .code
+            .as_ref()
+            .or_else(|| db.cache.contracts.get(&info.code_hash))
+            .map(|code| code.original_bytes());
+        let state_diff =
+            account.storage.iter().map(|(slot, value)| ((*slot).into(), (*value).into())).collect();
+        overrides.insert(
+            *address,
+            AccountOverride {
+                balance: Some(info.balance),
+                nonce: Some(info.nonce),
+                code,
+                state_diff: Some(state_diff),
+                ..Default::default()
+            },
+        );
+    }
+    overrides
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, Bytes, B256, U256};
+    use revm::{
+        database::EmptyDB,
+        state::{AccountInfo, Bytecode},
+    };
+
+    #[test]
+    fn test_bundle_gas_estimate() {
+        let estimate = BundleGasEstimate::new(vec![46_000, 120_000]);
+        assert_eq!(estimate.total_gas, U64::from(166_000));
+        assert_eq!(
+            serde_json::to_value(&estimate).unwrap(),
+            serde_json::json!({"gasEstimates": ["0xb3b0", "0x1d4c0"], "totalGas": "0x28870"})
+        );
+
+        let estimate = BundleGasEstimate::new(vec![u64::MAX, 1]);
+        assert_eq!(estimate.total_gas, U64::from(u64::MAX));
+    }
+
+    #[test]
+    fn test_cumulative_state() {
+        let mut db = CacheDB::new(EmptyDB::default());
+        let token = Address::with_last_byte(1);
+        let code = Bytecode::new_raw(Bytes::from_static(&[0x60, 0x00]));
+        db.insert_account_info(
+            token,
+            AccountInfo { nonce: 1, code: Some(code.clone()), ..Default::default() },
+        );
+        db.insert_account_storage(token, U256::from(7), U256::from(100)).unwrap();
+
+        let overrides = cumulative_state(&db);
+        let account = &overrides[&token];
+        assert_eq!(account.nonce, Some(1));
+        assert_eq!(account.balance, Some(U256::ZERO));
+        assert_eq!(account.code, Some(code.original_bytes()));
+        let state_diff = account.state_diff.as_ref().unwrap();
+        assert_eq!(state_diff[&B256::from(U256::from(7))], B256::from(U256::from(100)));
+    }
+}
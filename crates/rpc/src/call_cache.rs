@@ -0,0 +1,576 @@
+//! Result cache for `eth_call` and `eth_estimateGas` against finalized blocks.
+//!
+//! Explorer backends repeatedly issue identical calls (token name, symbol, decimals) at fixed
+//! heights. Once a block is finalized the result of a call against it can't change, so responses
+//! are cached keyed by the call parameters and the block number or hash they were made against.
+//!
+//! Only calls with an explicit block number or hash are cached. Block tags (`latest`, `pending`,
+//! ...) and calls carrying state or block overrides always reach the node, empty overrides are
+//! ignored. Clients opt out of the cache by sending their requests with `Cache-Control: no-cache`.
+
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, OnceLock,
+    },
+    task::{Context, Poll},
+};
+
+use alloy_primitives::{keccak256, B256};
+use alloy_rpc_types_eth::state::{BlockOverrides, StateOverride};
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::{middleware::rpc::RpcServiceT, HttpRequest},
+    types::{Request, ResponsePayload},
+    MethodResponse,
+};
+use moka::{policy::EvictionPolicy, sync::Cache};
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_storage_api::BlockIdReader;
+use serde::Deserialize;
+use serde_json::{value::RawValue, Value};
+use tower::{Layer, Service};
+use tracing::debug;
+use xlayer_legacy_rpc::invalidation::{Invalidation, InvalidationHandler};
+
+use crate::batch_via_call;
+
+/// Methods whose results are cached.
+const CACHED_METHODS: &[&str] = &["eth_call", "eth_estimateGas"];
+
+/// Header carrying the cache directives of the client.
+const CACHE_CONTROL_HEADER: &str = "cache-control";
+
+/// Metrics for the call result cache.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_call_cache")]
+struct EthCallCacheMetrics {
+    /// Number of calls answered from the cache
+    hits: Counter,
+    /// Number of cacheable calls not found in the cache
+    misses: Counter,
+    /// Number of calls that can't be cached (block tags, overrides, non-finalized blocks) or
+    /// opted out of the cache
+    bypassed: Counter,
+}
+
+/// Block a call is executed against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CallBlock {
+    Number(u64),
+    Hash(B256),
+}
+
+impl CallBlock {
+    /// Parses an explicit block number or hash, returns `None` for block tags.
+    fn parse(value: &Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Self::parse_str(s),
+            Value::Object(obj) => {
+                if let Some(hash) = obj.get("blockHash") {
+                    hash.as_str()?.parse().ok().map(Self::Hash)
+                } else {
+                    obj.get("blockNumber")?.as_str().and_then(Self::parse_number)
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_str(s: &str) -> Option<Self> {
+        if s.len() == 66 {
+            s.parse().ok().map(Self::Hash)
+        } else {
+            Self::parse_number(s)
+        }
+    }
+
+    fn parse_number(s: &str) -> Option<Self> {
+        let hex = s.strip_prefix("0x")?;
+        u64::from_str_radix(hex, 16).ok().map(Self::Number)
+    }
+}
+
+/// Returns `true` if the state and block overrides of the call params are absent or empty.
+fn without_overrides(params: &[Value]) -> bool {
+    let state = params.get(2).cloned().unwrap_or_default();
+    let block = params.get(3).cloned().unwrap_or_default();
+    params.len() <= 4
+        && serde_json::from_value::<Option<StateOverride>>(state)
+            .is_ok_and(|state| state.is_none_or(|state| state.is_empty()))
+        && serde_json::from_value::<Option<BlockOverrides>>(block)
+            .is_ok_and(|block| block.is_none_or(|block| block == BlockOverrides::default()))
+}
+
+/// Parsed cacheable call.
+#[derive(Debug)]
+struct CacheableCall {
+    method: String,
+    call: String,
+    block: CallBlock,
+}
+
+impl CacheableCall {
+    /// Parses the request, returns `None` if the call can't be cached.
+    fn parse(req: &Request<'_>) -> Option<Self> {
+        let params: Vec<Value> = serde_json::from_str(req.params().as_str()?).ok()?;
+        // State and block overrides are never cached
+        if !without_overrides(&params) {
+            return None;
+        }
+        Some(Self {
+            method: req.method_name().to_string(),
+            call: serde_json::to_string(params.first()?).ok()?,
+            block: CallBlock::parse(params.get(1)?)?,
+        })
+    }
+
+    /// Returns the cache key of the call.
+    fn key(&self) -> B256 {
+        let mut buf = Vec::with_capacity(self.method.len() + self.call.len() + 33);
+        buf.extend_from_slice(self.method.as_bytes());
+        buf.extend_from_slice(self.call.as_bytes());
+        match self.block {
+            CallBlock::Number(number) => {
+                buf.push(0);
+                buf.extend_from_slice(&number.to_be_bytes());
+            }
+            CallBlock::Hash(hash) => {
+                buf.push(1);
+                buf.extend_from_slice(hash.as_slice());
+            }
+        }
+        keccak256(buf)
+    }
+}
+
+#[derive(Deserialize)]
+struct SuccessResponse<T> {
+    result: T,
+}
+
+/// Bounded cache of call results.
+pub struct EthCallCache {
+    entries: Cache<B256, Box<RawValue>>,
+    /// Highest finalized block seen, only ever increases
+    finalized: AtomicU64,
+    /// Blocks of the node, set once the node is launched
+    provider: OnceLock<Arc<dyn BlockIdReader>>,
+    metrics: EthCallCacheMetrics,
+}
+
+impl EthCallCache {
+    pub fn new(max_entries: u64) -> Self {
+        Self {
+            entries: Cache::builder()
+                .max_capacity(max_entries)
+                .eviction_policy(EvictionPolicy::lru())
+                .build(),
+            finalized: AtomicU64::new(0),
+            provider: OnceLock::new(),
+            metrics: EthCallCacheMetrics::default(),
+        }
+    }
+
+    /// Sets the provider the block hashes and the finalized head are looked up with. Until it is
+    /// set only calls against a block number up to the highest finalized block seen are cached.
+    pub fn set_provider(&self, provider: impl BlockIdReader + 'static) {
+        let _ = self.provider.set(Arc::new(provider));
+    }
+
+    /// Returns `true` if the block is finalized.
+    fn is_finalized(&self, block: &CallBlock) -> bool {
+        self.finalized_number(block).is_some()
+    }
+
+    /// Returns the number of the block if it is finalized. Blocks given by hash are looked up, and
+    /// the finalized head of the node only when the block is above the highest finalized block
+    /// seen.
+    fn finalized_number(&self, block: &CallBlock) -> Option<u64> {
+        let provider = self.provider.get();
+        let number = match block {
+            CallBlock::Number(number) => *number,
+            CallBlock::Hash(hash) => provider?.block_number(*hash).ok()??,
+        };
+
+        if number > self.finalized.load(Ordering::Relaxed) {
+            let finalized = provider?.finalized_block_number().ok()??;
+            self.finalized.fetch_max(finalized, Ordering::Relaxed);
+            if number > finalized {
+                return None;
+            }
+        }
+
+        Some(number)
+    }
+}
+
+impl InvalidationHandler for EthCallCache {
+    /// Raises the finalized height, calls against newly finalized blocks are cached without
+    /// looking up the finalized block first.
+    fn invalidate(&self, event: &Invalidation) {
+        if let Invalidation::Finalized { number, .. } = event {
+            self.finalized.fetch_max(*number, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Layer that caches call results against finalized blocks.
+#[derive(Clone)]
+pub struct EthCallCacheLayer {
+    cache: Option<Arc<EthCallCache>>,
+}
+
+impl EthCallCacheLayer {
+    /// Creates the layer, a `max_entries` of `0` disables caching.
+    pub fn new(max_entries: u64) -> Self {
+        Self { cache: (max_entries > 0).then(|| Arc::new(EthCallCache::new(max_entries))) }
+    }
+
+    /// Returns the cache, `None` if caching is disabled.
+    pub fn cache(&self) -> Option<Arc<EthCallCache>> {
+        self.cache.clone()
+    }
+}
+
+impl<S> Layer<S> for EthCallCacheLayer {
+    type Service = EthCallCacheService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EthCallCacheService { inner, cache: self.cache.clone() }
+    }
+}
+
+/// Service that answers repeated calls against finalized blocks from the cache.
+#[derive(Clone)]
+pub struct EthCallCacheService<S> {
+    inner: S,
+    cache: Option<Arc<EthCallCache>>,
+}
+
+impl<S> EthCallCacheService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    async fn cached_call(self, cache: Arc<EthCallCache>, req: Request<'_>) -> MethodResponse {
+        if req.extensions().get::<CallCacheBypass>().is_some() {
+            cache.metrics.bypassed.increment(1);
+            return self.inner.call(req).await;
+        }
+        let Some(call) = CacheableCall::parse(&req) else {
+            cache.metrics.bypassed.increment(1);
+            return self.inner.call(req).await;
+        };
+        if !cache.is_finalized(&call.block) {
+            cache.metrics.bypassed.increment(1);
+            return self.inner.call(req).await;
+        }
+
+        let key = call.key();
+        if let Some(result) = cache.entries.get(&key) {
+            cache.metrics.hits.increment(1);
+            debug!(target: "xlayer::rpc::call_cache", "Cache hit for method = {}, block = {:?}", call.method, call.block);
+            return MethodResponse::response(
+                req.id(),
+                ResponsePayload::success(result),
+                usize::MAX,
+            );
+        }
+        cache.metrics.misses.increment(1);
+
+        let res = self.inner.call(req).await;
+        if res.is_success()
+            && let Ok(success) =
+                serde_json::from_str::<SuccessResponse<Box<RawValue>>>(res.as_json().get())
+        {
+            cache.entries.insert(key, success.result);
+        }
+        res
+    }
+}
+
+impl<S> RpcServiceT for EthCallCacheService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let Some(cache) = self.cache.clone() else {
+            return Either::Left(self.inner.call(req));
+        };
+        if !CACHED_METHODS.contains(&req.method_name()) {
+            return Either::Left(self.inner.call(req));
+        }
+
+        Either::Right(Box::pin(self.clone().cached_call(cache, req)))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if self.cache.is_none() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so batched calls are served from the cache too
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+/// Request extension opting the call out of the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallCacheBypass;
+
+/// Returns `true` if the `Cache-Control` header of the request carries `no-cache` or `no-store`.
+fn no_cache<B>(req: &HttpRequest<B>) -> bool {
+    req.headers()
+        .get_all(CACHE_CONTROL_HEADER)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|directive| {
+            let directive = directive.trim();
+            directive.eq_ignore_ascii_case("no-cache") || directive.eq_ignore_ascii_case("no-store")
+        })
+}
+
+/// HTTP layer marking the requests sent with `Cache-Control: no-cache` with [`CallCacheBypass`],
+/// so that their calls always reach the node.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallCacheControlLayer;
+
+impl<S> Layer<S> for CallCacheControlLayer {
+    type Service = CallCacheControlService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CallCacheControlService { inner }
+    }
+}
+
+/// HTTP service marking the requests opting out of the call cache.
+#[derive(Debug, Clone)]
+pub struct CallCacheControlService<S> {
+    inner: S,
+}
+
+impl<S, B> Service<HttpRequest<B>> for CallCacheControlService<S>
+where
+    S: Service<HttpRequest<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: HttpRequest<B>) -> Self::Future {
+        if no_cache(&req) {
+            req.extensions_mut().insert(CallCacheBypass);
+        }
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::ready;
+    use jsonrpsee::{types::Id, BatchResponseBuilder};
+    use std::sync::Mutex;
+
+    /// Service answering every call with `0x1` and recording the called methods.
+    #[derive(Clone, Default)]
+    struct RecordingService {
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl RpcServiceT for RecordingService {
+        type MethodResponse = MethodResponse;
+        type NotificationResponse = MethodResponse;
+        type BatchResponse = MethodResponse;
+
+        fn call<'a>(
+            &self,
+            req: Request<'a>,
+        ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+            self.calls.lock().unwrap().push(req.method_name().to_string());
+            ready(MethodResponse::response(req.id(), ResponsePayload::success("0x1"), usize::MAX))
+        }
+
+        fn batch<'a>(
+            &self,
+            _req: Batch<'a>,
+        ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+            ready(MethodResponse::from_batch(BatchResponseBuilder::new_with_limit(0).finish()))
+        }
+
+        fn notification<'a>(
+            &self,
+            _n: Notification<'a>,
+        ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+            ready(MethodResponse::notification())
+        }
+    }
+
+    fn request(method: &str, params: &str) -> Request<'static> {
+        Request::owned(
+            method.to_string(),
+            Some(RawValue::from_string(params.to_string()).unwrap()),
+            Id::Number(1),
+        )
+    }
+
+    #[test]
+    fn test_parse_cacheable_call() {
+        let hash = "0x4d3b9a3a5a3e0b2c9d1f4f7b6a1c2e3d4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c";
+        let call = r#"{"to":"0x0000000000000000000000000000000000000001","data":"0x06fdde03"}"#;
+
+        let parsed =
+            CacheableCall::parse(&request("eth_call", &format!(r#"[{call}, "0x10"]"#))).unwrap();
+        assert_eq!(parsed.block, CallBlock::Number(16));
+
+        let parsed =
+            CacheableCall::parse(&request("eth_call", &format!(r#"[{call}, "{hash}"]"#))).unwrap();
+        assert_eq!(parsed.block, CallBlock::Hash(hash.parse().unwrap()));
+
+        let parsed = CacheableCall::parse(&request(
+            "eth_call",
+            &format!(r#"[{call}, {{"blockHash": "{hash}"}}]"#),
+        ))
+        .unwrap();
+        assert_eq!(parsed.block, CallBlock::Hash(hash.parse().unwrap()));
+
+        // Empty overrides don't prevent caching
+        for overrides in ["{}", "null", "{}, {}", "null, null"] {
+            let params = format!(r#"[{call}, "0x10", {overrides}]"#);
+            assert!(CacheableCall::parse(&request("eth_call", &params)).is_some());
+        }
+
+        // Block tags, missing block and overrides are never cached
+        let state_override =
+            r#"{"0x0000000000000000000000000000000000000001": {"balance": "0x1"}}"#;
+        let block_override = r#"{"number": "0x20"}"#;
+        for params in [
+            format!(r#"[{call}, "latest"]"#),
+            format!("[{call}]"),
+            format!(r#"[{call}, "0x10", {state_override}]"#),
+            format!(r#"[{call}, "0x10", {{}}, {block_override}]"#),
+            format!(r#"[{call}, "0x10", "not an override"]"#),
+        ] {
+            assert!(CacheableCall::parse(&request("eth_call", &params)).is_none(), "{params}");
+        }
+    }
+
+    #[test]
+    fn test_cache_key_depends_on_block_and_method() {
+        let call = |method: &str, block| CacheableCall {
+            method: method.to_string(),
+            call: "{}".to_string(),
+            block,
+        };
+        let (a, b) = (CallBlock::Number(1), CallBlock::Hash(B256::with_last_byte(1)));
+        assert_eq!(call("eth_call", a.clone()).key(), call("eth_call", a.clone()).key());
+        assert_ne!(call("eth_call", a.clone()).key(), call("eth_call", CallBlock::Number(2)).key());
+        assert_ne!(call("eth_call", a.clone()).key(), call("eth_call", b).key());
+        assert_ne!(call("eth_call", a.clone()).key(), call("eth_estimateGas", a).key());
+    }
+
+    #[tokio::test]
+    async fn test_finalized_block_number_without_lookup() {
+        let layer = EthCallCacheLayer::new(16);
+        let inner = RecordingService::default();
+        let service = layer.layer(inner.clone());
+        layer
+            .cache()
+            .unwrap()
+            .invalidate(&Invalidation::Finalized { number: 100, hash: B256::ZERO });
+
+        let params = r#"[{"to":"0x0000000000000000000000000000000000000001"}, "0x10"]"#;
+        for _ in 0..2 {
+            assert!(service.call(request("eth_call", params)).await.is_success());
+        }
+        // The block is known to be finalized and the second call is served from the cache
+        assert_eq!(*inner.calls.lock().unwrap(), ["eth_call"]);
+    }
+
+    #[tokio::test]
+    async fn test_bypass() {
+        let layer = EthCallCacheLayer::new(16);
+        let inner = RecordingService::default();
+        let service = layer.layer(inner.clone());
+        layer
+            .cache()
+            .unwrap()
+            .invalidate(&Invalidation::Finalized { number: 100, hash: B256::ZERO });
+
+        let params = r#"[{"to":"0x0000000000000000000000000000000000000001"}, "0x10"]"#;
+        for _ in 0..2 {
+            let mut req = request("eth_call", params);
+            req.extensions_mut().insert(CallCacheBypass);
+            assert!(service.call(req).await.is_success());
+        }
+        // Calls opting out of the cache always reach the node, and aren't cached either
+        assert!(service.call(request("eth_call", params)).await.is_success());
+        assert_eq!(*inner.calls.lock().unwrap(), ["eth_call", "eth_call", "eth_call"]);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_finality_without_provider() {
+        let layer = EthCallCacheLayer::new(16);
+        let inner = RecordingService::default();
+        let service = layer.layer(inner.clone());
+
+        let hash = "0x4d3b9a3a5a3e0b2c9d1f4f7b6a1c2e3d4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c";
+        for block in ["0x10", hash] {
+            let params =
+                format!(r#"[{{"to":"0x0000000000000000000000000000000000000001"}}, "{block}"]"#);
+            for _ in 0..2 {
+                assert!(service.call(request("eth_call", &params)).await.is_success());
+            }
+        }
+        // Blocks aren't looked up through the RPC stack, the calls can't be cached
+        assert_eq!(*inner.calls.lock().unwrap(), ["eth_call"; 4]);
+    }
+
+    #[test]
+    fn test_no_cache_header() {
+        let request = |headers: &[&str]| {
+            let mut req = HttpRequest::new(());
+            for header in headers {
+                req.headers_mut().append(CACHE_CONTROL_HEADER, header.parse().unwrap());
+            }
+            req
+        };
+        assert!(!no_cache(&request(&[])));
+        assert!(!no_cache(&request(&["max-age=60"])));
+        assert!(no_cache(&request(&["no-cache"])));
+        assert!(no_cache(&request(&["max-age=0, No-Store"])));
+        assert!(no_cache(&request(&["max-age=60", "no-cache"])));
+    }
+
+    #[test]
+    fn test_finalized_invalidation() {
+        let cache = EthCallCache::new(16);
+        cache.invalidate(&Invalidation::Finalized { number: 100, hash: B256::ZERO });
+        // The finalized height never moves back
+        cache.invalidate(&Invalidation::Finalized { number: 90, hash: B256::ZERO });
+        cache.invalidate(&Invalidation::Reorged { reverted: vec![(150, B256::ZERO)] });
+        assert_eq!(cache.finalized.load(Ordering::Relaxed), 100);
+    }
+}
@@ -0,0 +1,245 @@
+//! Fee parameter changes, streamed over the `xlayer_subscribeFeeParams` subscription.
+//!
+//! Wallets and paymasters poll the gas price every block to notice a changed fee floor. The feed
+//! reads the fee parameters at every canonical block and emits them only when one of them
+//! changed: the minimum gas price the pool accepts at the latest base fee, the suggested priority
+//! fee and the L1 fee scalars of the L1 info deposit transaction.
+
+use std::sync::Arc;
+
+use alloy_consensus::BlockHeader;
+use alloy_primitives::{U256, U64};
+use futures::{Stream, StreamExt};
+use jsonrpsee::{
+    core::{async_trait, SubscriptionResult},
+    proc_macros::rpc,
+    PendingSubscriptionSink, SubscriptionMessage,
+};
+use reth_chain_state::CanonStateNotification;
+use reth_metrics::{metrics::Counter, Metrics};
+use reth_primitives_traits::{BlockBody, NodePrimitives};
+use reth_rpc_eth_api::helpers::EthFees;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tokio_stream::wrappers::WatchStream;
+use tracing::{debug, warn};
+
+use crate::{SequencerClientProvider, XlayerFeeApiServer, XlayerFees};
+
+/// Fee parameters of the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeParams {
+    /// First block the parameters apply to
+    pub block_number: U64,
+    /// Base fee of the block plus the minimum priority fee accepted by the pool
+    pub min_gas_price: U256,
+    /// Priority fee suggested by `eth_maxPriorityFeePerGas`
+    pub suggested_priority_fee: U256,
+    pub l1_base_fee_scalar: U256,
+    /// `None` before Ecotone
+    pub l1_blob_base_fee_scalar: Option<U256>,
+    /// `None` before Isthmus
+    pub operator_fee_scalar: Option<U256>,
+    pub operator_fee_constant: Option<U256>,
+}
+
+impl FeeParams {
+    /// Returns whether the fee parameters differ, regardless of the block they apply from.
+    fn fees_changed(&self, other: &Self) -> bool {
+        Self { block_number: other.block_number, ..self.clone() } != *other
+    }
+}
+
+/// Metrics of the fee parameters feed
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_fee_params")]
+struct FeeParamsMetrics {
+    /// Number of fee parameter changes emitted
+    changes: Counter,
+}
+
+/// Tracks the fee parameters of the canonical chain.
+#[derive(Debug)]
+pub struct FeeParamsFeed<T> {
+    fees: XlayerFees<T>,
+    min_priority_fee: u128,
+    tx: watch::Sender<Option<FeeParams>>,
+    metrics: FeeParamsMetrics,
+}
+
+impl<T> FeeParamsFeed<T> {
+    /// Creates the feed for a pool rejecting priority fees below `min_priority_fee`.
+    pub fn new(backend: Arc<T>, min_priority_fee: u128) -> Self {
+        Self {
+            fees: XlayerFees::new(backend),
+            min_priority_fee,
+            tx: watch::Sender::new(None),
+            metrics: FeeParamsMetrics::default(),
+        }
+    }
+
+    /// Returns a receiver of the latest fee parameters, `None` until the first canonical block.
+    pub fn subscribe(&self) -> watch::Receiver<Option<FeeParams>> {
+        self.tx.subscribe()
+    }
+
+    /// Stores the fee parameters, returns whether they changed.
+    fn update(&self, params: FeeParams) -> bool {
+        self.tx.send_if_modified(|current| {
+            if current.as_ref().is_some_and(|current| !current.fees_changed(&params)) {
+                return false;
+            }
+            *current = Some(params);
+            true
+        })
+    }
+}
+
+impl<T> FeeParamsFeed<T>
+where
+    T: SequencerClientProvider + EthFees + Send + Sync + 'static,
+{
+    /// Reads the fee parameters of every canonical tip until the stream ends.
+    pub async fn run<N, St>(self: Arc<Self>, canon_state: St)
+    where
+        N: NodePrimitives,
+        St: Stream<Item = CanonStateNotification<N>> + Send,
+    {
+        let mut canon_state = std::pin::pin!(canon_state);
+        while let Some(notification) = canon_state.next().await {
+            let tip = notification.tip();
+            self.refresh(tip.header(), tip.body()).await;
+        }
+        debug!(target: "xlayer::rpc", "canonical state stream closed, fee parameters feed stopped");
+    }
+
+    /// Reads the fee parameters of a block, returns whether they changed.
+    pub async fn refresh<H, B>(&self, header: &H, body: &B) -> bool
+    where
+        H: BlockHeader + Sync,
+        B: BlockBody + Sync,
+    {
+        let suggested_priority_fee = match self.fees.max_priority_fee_per_gas().await {
+            Ok(fee) => fee,
+            Err(err) => {
+                warn!(target: "xlayer::rpc", %err, "Failed to read suggested priority fee");
+                return false;
+            }
+        };
+        let l1_info = match reth_optimism_evm::extract_l1_info(body) {
+            Ok(l1_info) => l1_info,
+            Err(err) => {
+                warn!(target: "xlayer::rpc", block = header.number(), %err, "Failed to read L1 fee scalars");
+                return false;
+            }
+        };
+
+        let base_fee = header.base_fee_per_gas().unwrap_or_default() as u128;
+        let params = FeeParams {
+            block_number: U64::from(header.number()),
+            min_gas_price: U256::from(base_fee.saturating_add(self.min_priority_fee)),
+            suggested_priority_fee,
+            l1_base_fee_scalar: l1_info.l1_base_fee_scalar,
+            l1_blob_base_fee_scalar: l1_info.l1_blob_base_fee_scalar,
+            operator_fee_scalar: l1_info.operator_fee_scalar,
+            operator_fee_constant: l1_info.operator_fee_constant,
+        };
+        let changed = self.update(params);
+        if changed {
+            self.metrics.changes.increment(1);
+            debug!(target: "xlayer::rpc", block = header.number(), "Fee parameters changed");
+        }
+        changed
+    }
+}
+
+/// Fee parameters subscription API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait FeeParamsApi {
+    /// Subscribes to the fee parameters, emitting the current parameters and every change.
+    #[subscription(
+        name = "subscribeFeeParams" => "feeParams",
+        unsubscribe = "unsubscribeFeeParams",
+        item = FeeParams
+    )]
+    async fn subscribe_fee_params(&self) -> SubscriptionResult;
+}
+
+/// Serves the fee parameters of a [`FeeParamsFeed`].
+#[derive(Debug, Clone)]
+pub struct FeeParamsRpc {
+    params: watch::Receiver<Option<FeeParams>>,
+}
+
+impl FeeParamsRpc {
+    pub fn new(params: watch::Receiver<Option<FeeParams>>) -> Self {
+        Self { params }
+    }
+}
+
+#[async_trait]
+impl FeeParamsApiServer for FeeParamsRpc {
+    async fn subscribe_fee_params(&self, pending: PendingSubscriptionSink) -> SubscriptionResult {
+        let sink = pending.accept().await?;
+        let mut params = WatchStream::new(self.params.clone()).filter_map(std::future::ready);
+
+        loop {
+            tokio::select! {
+                _ = sink.closed() => break,
+                Some(fee_params) = params.next() => {
+                    let msg = SubscriptionMessage::new(
+                        sink.method_name(),
+                        sink.subscription_id(),
+                        &fee_params,
+                    )?;
+                    if sink.send(msg).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(block_number: u64, min_gas_price: u64) -> FeeParams {
+        FeeParams {
+            block_number: U64::from(block_number),
+            min_gas_price: U256::from(min_gas_price),
+            suggested_priority_fee: U256::from(1_000_000),
+            l1_base_fee_scalar: U256::from(1_368),
+            l1_blob_base_fee_scalar: Some(U256::from(810_949)),
+            operator_fee_scalar: None,
+            operator_fee_constant: None,
+        }
+    }
+
+    #[test]
+    fn test_fee_params_update() {
+        let feed = FeeParamsFeed::<()>::new(Arc::new(()), 0);
+        let rx = feed.subscribe();
+        assert!(rx.borrow().is_none());
+
+        assert!(feed.update(params(10, 100)));
+        // Unchanged fees keep the block they first applied to
+        assert!(!feed.update(params(11, 100)));
+        assert_eq!(rx.borrow().as_ref().unwrap().block_number, U64::from(10));
+
+        assert!(feed.update(params(12, 101)));
+        assert_eq!(*rx.borrow(), Some(params(12, 101)));
+    }
+
+    #[test]
+    fn test_fee_params_serde() {
+        let value = serde_json::to_value(params(16, 1_000_000_000)).unwrap();
+        assert_eq!(value["blockNumber"], "0x10");
+        assert_eq!(value["minGasPrice"], "0x3b9aca00");
+        assert_eq!(value["l1BlobBaseFeeScalar"], "0xc5fc5");
+        assert!(value["operatorFeeScalar"].is_null());
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy_primitives::U256;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use reth_rpc_eth_api::helpers::EthFees;
+use tokio::time::Instant;
+use tracing::warn;
+use xlayer_legacy_rpc::invalidation::{Invalidation, InvalidationHandler};
+
+use crate::SequencerClientProvider;
+
+/// How long a suggested priority fee is reused before asking again.
+const PRIORITY_FEE_CACHE_TTL: Duration = Duration::from_secs(2);
+
+/// Fee suggestion overrides of the `eth` namespace
+#[rpc(server, namespace = "eth")]
+pub trait XlayerFeeApi {
+    /// Returns the suggested priority fee, as seen by the sequencer when available.
+    #[method(name = "maxPriorityFeePerGas")]
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256>;
+}
+
+/// Source of the priority fee suggestions of [`XlayerFees`].
+pub trait PriorityFeeBackend: Send + Sync + 'static {
+    /// Returns the suggestion of the sequencer, `None` if no sequencer client is configured or the
+    /// sequencer is unreachable.
+    fn sequencer_priority_fee(&self) -> impl Future<Output = Option<U256>> + Send;
+
+    /// Returns the suggestion derived from the local fee history.
+    fn local_priority_fee(&self) -> impl Future<Output = RpcResult<U256>> + Send;
+}
+
+impl<T> PriorityFeeBackend for T
+where
+    T: SequencerClientProvider + EthFees + Send + Sync + 'static,
+{
+    async fn sequencer_priority_fee(&self) -> Option<U256> {
+        let client = self.sequencer_client()?;
+        match client.request::<_, U256>("eth_maxPriorityFeePerGas", [(); 0]).await {
+            Ok(fee) => Some(fee),
+            Err(err) => {
+                warn!(target: "xlayer::rpc", %err, "Failed to get priority fee from sequencer, using local suggestion");
+                None
+            }
+        }
+    }
+
+    async fn local_priority_fee(&self) -> RpcResult<U256> {
+        EthFees::suggested_priority_fee(self).await.map_err(Into::into)
+    }
+}
+
+/// Fee suggestions forwarded to the sequencer.
+///
+/// Replicas only see a limited fee history, so their local suggestion diverges from the
+/// sequencer's view. The sequencer suggestion is used when a sequencer client is configured,
+/// falling back to the local suggestion if the sequencer is unreachable. Clones share the cached
+/// suggestion, which is dropped on every new canonical block.
+#[derive(Debug)]
+pub struct XlayerFees<T> {
+    backend: Arc<T>,
+    cached_priority_fee: Arc<Mutex<Option<(Instant, U256)>>>,
+}
+
+impl<T> XlayerFees<T> {
+    pub fn new(backend: Arc<T>) -> Self {
+        Self { backend, cached_priority_fee: Default::default() }
+    }
+}
+
+impl<T> Clone for XlayerFees<T> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            cached_priority_fee: self.cached_priority_fee.clone(),
+        }
+    }
+}
+
+impl<T: Send + Sync> InvalidationHandler for XlayerFees<T> {
+    /// Drops the cached suggestion once the chain it was sampled from changed.
+    fn invalidate(&self, event: &Invalidation) {
+        if matches!(event, Invalidation::NewHead { .. } | Invalidation::Reorged { .. }) {
+            *self.cached_priority_fee.lock().expect("fee cache lock poisoned") = None;
+        }
+    }
+}
+
+impl<T: PriorityFeeBackend> XlayerFees<T> {
+    async fn suggested_priority_fee(&self) -> RpcResult<U256> {
+        if let Some(fee) = self.backend.sequencer_priority_fee().await {
+            return Ok(fee);
+        }
+        self.backend.local_priority_fee().await
+    }
+}
+
+#[async_trait]
+impl<T: PriorityFeeBackend> XlayerFeeApiServer for XlayerFees<T> {
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
+        if let Some((at, fee)) = *self.cached_priority_fee.lock().expect("fee cache lock poisoned")
+            && at.elapsed() < PRIORITY_FEE_CACHE_TTL
+        {
+            return Ok(fee);
+        }
+
+        let fee = self.suggested_priority_fee().await?;
+        *self.cached_priority_fee.lock().expect("fee cache lock poisoned") =
+            Some((Instant::now(), fee));
+        Ok(fee)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Backend with fixed suggestions, counting the suggestions it was asked for.
+    struct StubBackend {
+        sequencer: Option<U256>,
+        local: U256,
+        requests: AtomicUsize,
+    }
+
+    impl StubBackend {
+        fn new(sequencer: Option<u64>, local: u64) -> Arc<Self> {
+            Arc::new(Self {
+                sequencer: sequencer.map(U256::from),
+                local: U256::from(local),
+                requests: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    impl PriorityFeeBackend for StubBackend {
+        async fn sequencer_priority_fee(&self) -> Option<U256> {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+            self.sequencer
+        }
+
+        async fn local_priority_fee(&self) -> RpcResult<U256> {
+            Ok(self.local)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sequencer_suggestion_with_local_fallback() {
+        let fees = XlayerFees::new(StubBackend::new(Some(5), 1));
+        assert_eq!(fees.max_priority_fee_per_gas().await.unwrap(), U256::from(5));
+
+        // Without a reachable sequencer the local suggestion is used
+        let fees = XlayerFees::new(StubBackend::new(None, 1));
+        assert_eq!(fees.max_priority_fee_per_gas().await.unwrap(), U256::from(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cached_suggestion() {
+        let backend = StubBackend::new(Some(5), 1);
+        let fees = XlayerFees::new(backend.clone());
+
+        fees.max_priority_fee_per_gas().await.unwrap();
+        fees.clone().max_priority_fee_per_gas().await.unwrap();
+        assert_eq!(backend.requests.load(Ordering::Relaxed), 1);
+
+        // Expired suggestions are requested again
+        tokio::time::advance(PRIORITY_FEE_CACHE_TTL).await;
+        fees.max_priority_fee_per_gas().await.unwrap();
+        assert_eq!(backend.requests.load(Ordering::Relaxed), 2);
+
+        // So are suggestions sampled before a new block
+        fees.invalidate(&Invalidation::NewHead { number: 1, hash: B256::ZERO });
+        fees.max_priority_fee_per_gas().await.unwrap();
+        assert_eq!(backend.requests.load(Ordering::Relaxed), 3);
+    }
+}
@@ -0,0 +1,406 @@
+//! Mapping of the `safe` and `finalized` block tags on X Layer.
+//!
+//! The `safe` and `finalized` heads follow L1 derivation. A replica that receives blocks without
+//! L1 finality data (e.g. from flashblocks or p2p only) has neither head, so requests with these
+//! tags fail although the sequencer confirmed the blocks long ago. A fallback depth per tag treats
+//! the block that many blocks below the latest block as safe or finalized while the node has no
+//! such head. Once the node reports a head for a tag, the tag keeps its native meaning.
+//!
+//! The tags are resolved for the positional block parameter of the methods below, including the
+//! `finalized` lookups of the call result cache. Block tags in `eth_getLogs` filters are left as
+//! is.
+
+use std::{
+    borrow::Cow,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use alloy_primitives::U64;
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::{Id, Request},
+    MethodResponse,
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use tower::Layer;
+use tracing::debug;
+
+use crate::batch_via_call;
+
+/// Metrics of the block tag mapping.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_finality_tags")]
+struct FinalityTagMetrics {
+    /// Number of tags resolved with the fallback depth
+    fallbacks: Counter,
+}
+
+/// Block tag following L1 finality.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FinalityTag {
+    Safe,
+    Finalized,
+}
+
+impl FinalityTag {
+    /// Parses a raw JSON block parameter.
+    fn parse(param: &RawValue) -> Option<Self> {
+        match param.get() {
+            r#""safe""# => Some(Self::Safe),
+            r#""finalized""# => Some(Self::Finalized),
+            _ => None,
+        }
+    }
+
+    const fn as_str(&self) -> &'static str {
+        match self {
+            Self::Safe => "safe",
+            Self::Finalized => "finalized",
+        }
+    }
+}
+
+/// Returns the index of the block parameter of methods accepting block tags.
+fn block_param_index(method: &str) -> Option<usize> {
+    match method {
+        "eth_getBlockByNumber"
+        | "eth_getBlockReceipts"
+        | "eth_getBlockTransactionCountByNumber"
+        | "eth_getTransactionByBlockNumberAndIndex"
+        | "eth_getRawTransactionByBlockNumberAndIndex"
+        | "eth_getUncleCountByBlockNumber"
+        | "eth_getUncleByBlockNumberAndIndex"
+        | "debug_traceBlockByNumber"
+        | "trace_block" => Some(0),
+        "eth_getBalance"
+        | "eth_getCode"
+        | "eth_getTransactionCount"
+        | "eth_call"
+        | "eth_estimateGas"
+        | "eth_createAccessList"
+        | "eth_feeHistory"
+        | "debug_traceCall" => Some(1),
+        "eth_getStorageAt" | "eth_getProof" | "trace_call" => Some(2),
+        _ => None,
+    }
+}
+
+/// Mapping of the `safe` and `finalized` tags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FinalityTagPolicy {
+    /// Depth below the latest block treated as safe while the node has no safe head
+    pub safe_depth: Option<u64>,
+    /// Depth below the latest block treated as finalized while the node has no finalized head
+    pub finalized_depth: Option<u64>,
+}
+
+impl FinalityTagPolicy {
+    /// Returns `true` if any tag has a fallback depth.
+    pub const fn is_enabled(&self) -> bool {
+        self.safe_depth.is_some() || self.finalized_depth.is_some()
+    }
+
+    const fn depth(&self, tag: FinalityTag) -> Option<u64> {
+        match tag {
+            FinalityTag::Safe => self.safe_depth,
+            FinalityTag::Finalized => self.finalized_depth,
+        }
+    }
+}
+
+/// Source a block tag is currently resolved from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TagSource {
+    /// Head reported by the node
+    Native,
+    /// Fixed depth below the latest block, the node has no head for the tag yet
+    Depth,
+}
+
+/// Active mapping of a block tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagMapping {
+    pub source: TagSource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_depth: Option<u64>,
+}
+
+/// Active mapping of the `safe` and `finalized` tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityTagsStatus {
+    pub safe: TagMapping,
+    pub finalized: TagMapping,
+}
+
+/// Resolves the `safe` and `finalized` tags according to the policy.
+#[derive(Debug)]
+pub struct FinalityTags {
+    policy: FinalityTagPolicy,
+    /// Whether the node reported a safe head, only ever becomes `true`
+    has_safe_head: AtomicBool,
+    /// Whether the node reported a finalized head, only ever becomes `true`
+    has_finalized_head: AtomicBool,
+}
+
+impl FinalityTags {
+    pub fn new(policy: FinalityTagPolicy) -> Self {
+        Self {
+            policy,
+            has_safe_head: AtomicBool::new(false),
+            has_finalized_head: AtomicBool::new(false),
+        }
+    }
+
+    pub const fn policy(&self) -> &FinalityTagPolicy {
+        &self.policy
+    }
+
+    /// Returns the active mapping of the tags.
+    pub fn status(&self) -> FinalityTagsStatus {
+        let mapping = |tag| {
+            let fallback_depth = self.policy.depth(tag);
+            let source = if fallback_depth.is_some() && !self.has_head(tag) {
+                TagSource::Depth
+            } else {
+                TagSource::Native
+            };
+            TagMapping { source, fallback_depth }
+        };
+        FinalityTagsStatus {
+            safe: mapping(FinalityTag::Safe),
+            finalized: mapping(FinalityTag::Finalized),
+        }
+    }
+
+    fn head_flag(&self, tag: FinalityTag) -> &AtomicBool {
+        match tag {
+            FinalityTag::Safe => &self.has_safe_head,
+            FinalityTag::Finalized => &self.has_finalized_head,
+        }
+    }
+
+    fn has_head(&self, tag: FinalityTag) -> bool {
+        self.head_flag(tag).load(Ordering::Relaxed)
+    }
+
+    /// Returns the tag of the request and its fallback depth, if the tag may need a fallback.
+    fn fallback(&self, req: &Request<'_>) -> Option<(FinalityTag, usize, u64)> {
+        let index = block_param_index(req.method_name())?;
+        let params = req.params();
+        let params: Vec<&RawValue> = serde_json::from_str(params.as_str()?).ok()?;
+        let tag = FinalityTag::parse(params.get(index)?)?;
+        let depth = self.policy.depth(tag)?;
+        (!self.has_head(tag)).then_some((tag, index, depth))
+    }
+}
+
+/// Layer that maps the `safe` and `finalized` tags according to a [`FinalityTagPolicy`].
+#[derive(Clone)]
+pub struct FinalityTagLayer {
+    tags: Arc<FinalityTags>,
+}
+
+impl FinalityTagLayer {
+    pub fn new(policy: FinalityTagPolicy) -> Self {
+        Self { tags: Arc::new(FinalityTags::new(policy)) }
+    }
+
+    /// Returns the shared tag resolver, e.g. to report the active mapping.
+    pub fn tags(&self) -> Arc<FinalityTags> {
+        self.tags.clone()
+    }
+}
+
+impl<S> Layer<S> for FinalityTagLayer {
+    type Service = FinalityTagService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        FinalityTagService { inner, tags: self.tags.clone(), metrics: Default::default() }
+    }
+}
+
+/// Service that resolves the `safe` and `finalized` tags to a block number while the node has no
+/// head for them.
+#[derive(Clone)]
+pub struct FinalityTagService<S> {
+    inner: S,
+    tags: Arc<FinalityTags>,
+    metrics: FinalityTagMetrics,
+}
+
+impl<S> FinalityTagService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    /// Calls the inner service and returns the raw result, `None` on errors.
+    async fn call_inner(&self, method: &'static str, params: String) -> Option<Box<RawValue>> {
+        #[derive(Deserialize)]
+        struct SuccessResponse {
+            result: Box<RawValue>,
+        }
+
+        let params = RawValue::from_string(params).ok()?;
+        let res = self.inner.call(Request::owned(method.into(), Some(params), Id::Number(1))).await;
+        if !res.is_success() {
+            return None;
+        }
+        serde_json::from_str::<SuccessResponse>(res.as_json().get()).ok().map(|res| res.result)
+    }
+
+    /// Returns the block number the tag falls back to, `None` if the node has a head for it.
+    async fn fallback_block(&self, tag: FinalityTag, depth: u64) -> Option<u64> {
+        let head = self
+            .call_inner("eth_getBlockByNumber", format!(r#"["{}", false]"#, tag.as_str()))
+            .await;
+        if head.is_some_and(|head| head.get() != "null") {
+            self.tags.head_flag(tag).store(true, Ordering::Relaxed);
+            debug!(target: "xlayer::rpc::finality", tag = tag.as_str(), "Node reports a head, using native tag");
+            return None;
+        }
+
+        let latest = self.call_inner("eth_blockNumber", "[]".into()).await?;
+        let latest: U64 = serde_json::from_str(latest.get()).ok()?;
+        Some(latest.to::<u64>().saturating_sub(depth))
+    }
+
+    async fn resolve_and_call(
+        self,
+        mut req: Request<'_>,
+        tag: FinalityTag,
+        index: usize,
+        depth: u64,
+    ) -> MethodResponse {
+        if let Some(block) = self.fallback_block(tag, depth).await
+            && let Some(params) = replace_param(&req, index, block)
+        {
+            self.metrics.fallbacks.increment(1);
+            req.params = Some(Cow::Owned(params));
+        }
+        self.inner.call(req).await
+    }
+}
+
+/// Returns the params of the request with the param at `index` replaced by the block number.
+fn replace_param(req: &Request<'_>, index: usize, block: u64) -> Option<Box<RawValue>> {
+    let params = req.params();
+    let mut params: Vec<&RawValue> = serde_json::from_str(params.as_str()?).ok()?;
+    let block = RawValue::from_string(format!(r#""{block:#x}""#)).ok()?;
+    *params.get_mut(index)? = &*block;
+    serde_json::value::to_raw_value(&params).ok()
+}
+
+impl<S> RpcServiceT for FinalityTagService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        if !self.tags.policy.is_enabled() {
+            return Either::Left(self.inner.call(req));
+        }
+        let Some((tag, index, depth)) = self.tags.fallback(&req) else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        Either::Right(Box::pin(self.clone().resolve_and_call(req, tag, index, depth)))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if !self.tags.policy.is_enabled() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so batched requests are mapped too
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: &str, params: &str) -> Request<'static> {
+        Request::owned(
+            method.to_string(),
+            Some(RawValue::from_string(params.to_string()).unwrap()),
+            Id::Number(1),
+        )
+    }
+
+    #[test]
+    fn test_fallback_only_for_configured_tags() {
+        let tags =
+            FinalityTags::new(FinalityTagPolicy { safe_depth: Some(10), finalized_depth: None });
+
+        let req = request("eth_getBlockByNumber", r#"["safe", false]"#);
+        assert_eq!(tags.fallback(&req), Some((FinalityTag::Safe, 0, 10)));
+        let req = request("eth_getStorageAt", r#"["0x01", "0x0", "safe"]"#);
+        assert_eq!(tags.fallback(&req), Some((FinalityTag::Safe, 2, 10)));
+
+        // Finalized keeps its native meaning, other tags and methods are never mapped
+        assert_eq!(
+            tags.fallback(&request("eth_getBlockByNumber", r#"["finalized", false]"#)),
+            None
+        );
+        assert_eq!(tags.fallback(&request("eth_getBlockByNumber", r#"["latest", false]"#)), None);
+        assert_eq!(tags.fallback(&request("eth_getBalance", r#"["safe"]"#)), None);
+        assert_eq!(tags.fallback(&request("eth_getLogs", r#"[{"toBlock": "safe"}]"#)), None);
+
+        // Once the node reports a safe head the tag is native
+        tags.has_safe_head.store(true, Ordering::Relaxed);
+        assert_eq!(tags.fallback(&request("eth_getBlockByNumber", r#"["safe", false]"#)), None);
+    }
+
+    #[test]
+    fn test_replace_param() {
+        let req = request("eth_call", r#"[{"to": "0x01"}, "finalized", {}]"#);
+        let params = replace_param(&req, 1, 0x1234).unwrap();
+        assert_eq!(params.get(), r#"[{"to": "0x01"},"0x1234",{}]"#);
+        assert!(replace_param(&req, 3, 0x1234).is_none());
+    }
+
+    #[test]
+    fn test_status() {
+        let tags =
+            FinalityTags::new(FinalityTagPolicy { safe_depth: Some(10), finalized_depth: None });
+        let native = TagMapping { source: TagSource::Native, fallback_depth: None };
+        assert_eq!(
+            tags.status(),
+            FinalityTagsStatus {
+                safe: TagMapping { source: TagSource::Depth, fallback_depth: Some(10) },
+                finalized: native,
+            }
+        );
+
+        tags.has_safe_head.store(true, Ordering::Relaxed);
+        assert_eq!(tags.status().safe.source, TagSource::Native);
+        assert_eq!(
+            serde_json::to_string(&tags.status()).unwrap(),
+            r#"{"safe":{"source":"native","fallbackDepth":10},"finalized":{"source":"native"}}"#
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Effective gas price oracle configuration, served over `xlayer_getGasOracleConfig`.
+//!
+//! The oracle behind `eth_gasPrice` and `eth_maxPriorityFeePerGas` samples recent blocks. Its
+//! upstream defaults are tuned for Optimism, the X Layer overrides are applied on top of them, so
+//! the resulting values are reported to check a deployment.
+
+use alloy_primitives::U256;
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+};
+use reth_rpc_eth_types::GasPriceOracleConfig;
+use serde::{Deserialize, Serialize};
+
+/// Effective configuration of the gas price oracle.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasOracleConfigInfo {
+    /// Number of recent blocks sampled
+    pub blocks: u32,
+    /// Percentile of the sampled tips suggested
+    pub percentile: u32,
+    /// Maximum suggested price
+    pub max_price: Option<U256>,
+    /// Tips below this price are ignored
+    pub ignore_price: Option<U256>,
+    /// Price suggested while there are no samples
+    pub default_suggested_fee: Option<U256>,
+}
+
+impl From<&GasPriceOracleConfig> for GasOracleConfigInfo {
+    fn from(config: &GasPriceOracleConfig) -> Self {
+        Self {
+            blocks: config.blocks,
+            percentile: config.percentile,
+            max_price: config.max_price,
+            ignore_price: config.ignore_price,
+            default_suggested_fee: config.default_suggested_fee,
+        }
+    }
+}
+
+/// Gas price oracle configuration debug API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait GasOracleConfigApi {
+    /// Returns the effective configuration of the gas price oracle.
+    #[method(name = "getGasOracleConfig")]
+    async fn get_gas_oracle_config(&self) -> RpcResult<GasOracleConfigInfo>;
+}
+
+/// Serves the configuration the gas price oracle was started with.
+#[derive(Debug, Clone)]
+pub struct GasOracleConfigRpc {
+    info: GasOracleConfigInfo,
+}
+
+impl GasOracleConfigRpc {
+    pub fn new(config: &GasPriceOracleConfig) -> Self {
+        Self { info: config.into() }
+    }
+}
+
+#[async_trait]
+impl GasOracleConfigApiServer for GasOracleConfigRpc {
+    async fn get_gas_oracle_config(&self) -> RpcResult<GasOracleConfigInfo> {
+        Ok(self.info.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gas_oracle_config_info() {
+        let config = GasPriceOracleConfig {
+            blocks: 10,
+            percentile: 50,
+            max_price: Some(U256::from(100_000_000_000u64)),
+            ..Default::default()
+        };
+
+        let info = GasOracleConfigInfo::from(&config);
+        assert_eq!(info.blocks, 10);
+        assert_eq!(info.max_price, config.max_price);
+
+        let value = serde_json::to_value(&info).unwrap();
+        assert_eq!(value["maxPrice"], "0x174876e800");
+        assert_eq!(value["percentile"], 50);
+    }
+}
@@ -1,15 +1,50 @@
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod bundle_gas;
+pub mod call_cache;
+pub mod fee_params;
+pub mod fees;
+pub mod finality;
+pub mod gas_oracle;
+pub mod limiter;
+pub mod policy;
+pub mod probes;
+pub mod response_fields;
+pub mod sender_nonce;
+pub mod timeout;
+pub mod tx_dedup;
+pub mod tx_wal;
+pub mod warmup;
+pub mod witness;
 pub mod xlayer_ext;
 
 use std::time::Instant;
 // Re-export for convenience
+pub use bundle_gas::{XlayerBundleGas, XlayerBundleGasApiServer};
+pub use call_cache::{CallCacheControlLayer, EthCallCacheLayer};
+pub use fee_params::{FeeParamsApiServer, FeeParamsFeed, FeeParamsRpc};
+pub use fees::{XlayerFeeApiServer, XlayerFees};
+pub use finality::{FinalityTagLayer, FinalityTagPolicy, FinalityTags};
+pub use gas_oracle::{GasOracleConfigApiServer, GasOracleConfigRpc};
+pub use limiter::{HeavyCallLimitLayer, HeavyCallLimits, HEAVY_METHODS};
+pub use policy::{RpcPolicy, RpcPolicyLayer, ACCOUNT_METHODS};
+pub use probes::HealthProbes;
+pub use response_fields::ResponseFieldsLayer;
+pub use sender_nonce::{SenderNonceApiServer, SenderNonceRpc};
+pub use timeout::{MethodTimeoutLayer, MethodTimeouts};
+pub use tx_dedup::TxDedupLayer;
+pub use tx_wal::{TxWalConfig, TxWalLayer};
+pub use warmup::CacheWarmup;
+pub use witness::{ExecutionWitnessApiServer, ExecutionWitnessRpc};
 pub use xlayer_ext::{
-    PendingFlashBlockProvider, SequencerClientProvider, XlayerRpcExt, XlayerRpcExtApiServer,
+    PendingFlashBlockProvider, SequencerClientProvider, XlayerHealth, XlayerHealthApiClient,
+    XlayerHealthApiServer, XlayerRpcExt, XlayerRpcExtApiClient, XlayerRpcExtApiServer,
 };
+pub use xlayer_legacy_rpc::middleware::batch_via_call;
 
 // Implement SequencerClientProvider for OpEthApi
+use alloy_consensus::BlockHeader as _;
 use reth_optimism_rpc::{OpEthApi, SequencerClient};
 use reth_rpc_eth_api::{RpcConvert, RpcNodeCore};
 
@@ -35,4 +70,12 @@ where
                 .is_some_and(|pending_flashblock| Instant::now() < pending_flashblock.expires_at)
         })
     }
+
+    fn pending_flashblock_timestamp(&self) -> Option<u64> {
+        self.pending_block_rx().and_then(|rx| {
+            rx.borrow()
+                .as_ref()
+                .map(|pending_flashblock| pending_flashblock.block().header().timestamp())
+        })
+    }
 }
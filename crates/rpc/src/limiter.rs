@@ -0,0 +1,472 @@
+//! Per-client concurrency limiter for heavy RPC methods.
+//!
+//! A single client issuing many expensive calls in parallel (wide `eth_getLogs` ranges that fan
+//! out to the legacy endpoint, debug traces) can exhaust the blocking pool for everyone else. This
+//! layer bounds the number of in-flight heavy calls per client. Calls above the limit wait in a
+//! FIFO queue; once the queue is full or the wait times out, the call is rejected with `-32005`.
+//!
+//! Clients are identified by [`client_key`]: the client IP attached to the request extensions by
+//! the [`ClientAddrLayer`](xlayer_legacy_rpc::middleware::ClientAddrLayer), or the connection the
+//! request was received on. Requests without either are not limited.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::{ErrorObject, Id, Request},
+    MethodResponse,
+};
+use reth_metrics::{
+    metrics::{Counter, Gauge},
+    Metrics,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::Layer;
+use tracing::debug;
+use xlayer_legacy_rpc::middleware::{client_key, ClientKey};
+
+use crate::batch_via_call;
+
+/// Methods limited by default.
+pub const HEAVY_METHODS: &[&str] = &[
+    "eth_getLogs",
+    "xlayer_getLogsPaged",
+    "xlayer_estimateGasBundle",
+    "xlayer_getExecutionWitness",
+    "debug_traceTransaction",
+    "debug_traceCall",
+    "debug_traceCallMany",
+    "debug_traceBlock",
+    "debug_traceBlockByHash",
+    "debug_traceBlockByNumber",
+    "trace_block",
+    "trace_filter",
+    "trace_replayBlockTransactions",
+];
+
+/// Error code returned when a client exceeds its heavy call limit.
+pub const LIMIT_EXCEEDED_CODE: i32 = -32005;
+
+/// Limits applied by the [`HeavyCallLimitLayer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeavyCallLimits {
+    /// Maximum in-flight heavy calls per client, `0` disables the limiter
+    pub max_concurrent: usize,
+    /// Maximum heavy calls per client waiting for a slot
+    pub max_queued: usize,
+    /// Maximum time a queued call waits for a slot
+    pub queue_timeout: Duration,
+    /// Methods subject to the limit
+    pub methods: Vec<String>,
+}
+
+impl Default for HeavyCallLimits {
+    fn default() -> Self {
+        Self {
+            max_concurrent: 0,
+            max_queued: 16,
+            queue_timeout: Duration::from_secs(5),
+            methods: HEAVY_METHODS.iter().map(|m| m.to_string()).collect(),
+        }
+    }
+}
+
+/// Metrics for the heavy call limiter.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_heavy_limiter")]
+struct HeavyCallLimiterMetrics {
+    /// Number of heavy calls rejected because the client exceeded its limit
+    rejected_calls: Counter,
+    /// Number of heavy calls that had to wait for a slot
+    queued_calls: Counter,
+    /// Number of clients with in-flight or queued heavy calls
+    tracked_clients: Gauge,
+}
+
+/// Concurrency slot of a single client.
+struct ClientSlot {
+    permits: Arc<Semaphore>,
+    queued: AtomicUsize,
+}
+
+/// Tracks in-flight heavy calls per client.
+pub struct HeavyCallLimiter {
+    limits: HeavyCallLimits,
+    methods: HashSet<String>,
+    clients: Mutex<HashMap<ClientKey, Arc<ClientSlot>>>,
+    metrics: HeavyCallLimiterMetrics,
+}
+
+impl HeavyCallLimiter {
+    pub fn new(limits: HeavyCallLimits) -> Self {
+        let methods = limits.methods.iter().cloned().collect();
+        Self {
+            limits,
+            methods,
+            clients: Mutex::new(HashMap::new()),
+            metrics: HeavyCallLimiterMetrics::default(),
+        }
+    }
+
+    /// Returns `true` if the limiter is active.
+    pub fn is_enabled(&self) -> bool {
+        self.limits.max_concurrent > 0 && !self.methods.is_empty()
+    }
+
+    /// Returns `true` if calls to the method are limited.
+    pub fn is_heavy(&self, method: &str) -> bool {
+        self.limits.max_concurrent > 0 && self.methods.contains(method)
+    }
+
+    /// Returns the slot of the client, creating it if needed.
+    fn slot(&self, client: ClientKey) -> Arc<ClientSlot> {
+        let mut clients = self.clients.lock().expect("heavy limiter lock poisoned");
+        let slot = clients.entry(client).or_insert_with(|| {
+            Arc::new(ClientSlot {
+                permits: Arc::new(Semaphore::new(self.limits.max_concurrent)),
+                queued: AtomicUsize::new(0),
+            })
+        });
+        let slot = slot.clone();
+        self.metrics.tracked_clients.set(clients.len() as f64);
+        slot
+    }
+
+    /// Drops the slot of the client once nobody holds or waits for it anymore.
+    fn release(&self, client: ClientKey) {
+        let mut clients = self.clients.lock().expect("heavy limiter lock poisoned");
+        if clients.get(&client).is_some_and(|slot| Arc::strong_count(slot) == 1) {
+            clients.remove(&client);
+        }
+        self.metrics.tracked_clients.set(clients.len() as f64);
+    }
+
+    /// Waits for a slot of the client. Returns `None` if the queue is full or the wait timed out.
+    pub async fn acquire(self: Arc<Self>, client: ClientKey) -> Option<HeavyCallPermit> {
+        let mut guard = HeavyCallPermit { limiter: self.clone(), client, slot: None, permit: None };
+        let slot = guard.slot.insert(self.slot(client));
+
+        let permit = match slot.permits.clone().try_acquire_owned() {
+            Ok(permit) => permit,
+            Err(_) => {
+                if slot.queued.fetch_add(1, Ordering::SeqCst) >= self.limits.max_queued {
+                    slot.queued.fetch_sub(1, Ordering::SeqCst);
+                    return None;
+                }
+                self.metrics.queued_calls.increment(1);
+                let res = tokio::time::timeout(
+                    self.limits.queue_timeout,
+                    slot.permits.clone().acquire_owned(),
+                )
+                .await;
+                slot.queued.fetch_sub(1, Ordering::SeqCst);
+                match res {
+                    Ok(Ok(permit)) => permit,
+                    _ => return None,
+                }
+            }
+        };
+
+        guard.permit = Some(permit);
+        Some(guard)
+    }
+
+    /// Builds the error response for a rejected call and records the rejection.
+    fn reject(&self, method: &str, client: ClientKey, id: Id<'_>) -> MethodResponse {
+        self.metrics.rejected_calls.increment(1);
+        debug!(target: "xlayer::rpc::limiter", "Rejected heavy method = {method}, client = {client:?}");
+        MethodResponse::error(
+            id,
+            ErrorObject::owned(
+                LIMIT_EXCEEDED_CODE,
+                format!("too many concurrent {method} requests, retry later"),
+                None::<()>,
+            ),
+        )
+    }
+}
+
+/// In-flight heavy call slot, released on drop.
+pub struct HeavyCallPermit {
+    limiter: Arc<HeavyCallLimiter>,
+    client: ClientKey,
+    slot: Option<Arc<ClientSlot>>,
+    permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for HeavyCallPermit {
+    fn drop(&mut self) {
+        self.permit.take();
+        self.slot.take();
+        self.limiter.release(self.client);
+    }
+}
+
+/// Layer that limits concurrent heavy calls per client.
+#[derive(Clone)]
+pub struct HeavyCallLimitLayer {
+    limiter: Arc<HeavyCallLimiter>,
+}
+
+impl HeavyCallLimitLayer {
+    pub fn new(limits: HeavyCallLimits) -> Self {
+        Self { limiter: Arc::new(HeavyCallLimiter::new(limits)) }
+    }
+}
+
+impl<S> Layer<S> for HeavyCallLimitLayer {
+    type Service = HeavyCallLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HeavyCallLimitService { inner, limiter: self.limiter.clone() }
+    }
+}
+
+/// Service that limits concurrent heavy calls per client before they reach the inner service.
+#[derive(Clone)]
+pub struct HeavyCallLimitService<S> {
+    inner: S,
+    limiter: Arc<HeavyCallLimiter>,
+}
+
+impl<S> RpcServiceT for HeavyCallLimitService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let client = client_key(&req).filter(|_| self.limiter.is_heavy(req.method_name()));
+        let Some(client) = client else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        let inner = self.inner.clone();
+        let limiter = self.limiter.clone();
+
+        Either::Right(Box::pin(async move {
+            match limiter.clone().acquire(client).await {
+                Some(_permit) => inner.call(req).await,
+                None => limiter.reject(req.method_name(), client, req.id()),
+            }
+        }))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if !self.limiter.is_enabled() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so heavy methods can't bypass the limit in a batch
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::{pending, ready};
+    use jsonrpsee::{
+        core::{client::ClientT, server::ConnectionId, ClientError, RpcResult},
+        http_client::{HeaderMap, HttpClient, HttpClientBuilder},
+        rpc_params,
+        server::{RpcServiceBuilder, Server},
+        BatchResponseBuilder, RpcModule,
+    };
+    use serde_json::value::RawValue;
+    use std::net::{IpAddr, SocketAddr};
+    use tower::ServiceBuilder;
+    use xlayer_legacy_rpc::middleware::ClientAddrLayer;
+
+    fn limiter(max_concurrent: usize, max_queued: usize) -> Arc<HeavyCallLimiter> {
+        Arc::new(HeavyCallLimiter::new(HeavyCallLimits {
+            max_concurrent,
+            max_queued,
+            queue_timeout: Duration::from_millis(50),
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_limit_per_client() {
+        let limiter = limiter(1, 0);
+        let alice = ClientKey::Ip(IpAddr::from([10, 0, 0, 1]));
+        let bob = ClientKey::Ip(IpAddr::from([10, 0, 0, 2]));
+
+        let permit = limiter.clone().acquire(alice).await;
+        assert!(permit.is_some());
+        assert!(limiter.clone().acquire(alice).await.is_none());
+        assert!(limiter.clone().acquire(bob).await.is_some());
+
+        drop(permit);
+        assert!(limiter.clone().acquire(alice).await.is_some());
+        assert!(limiter.clients.lock().unwrap().is_empty());
+    }
+
+    /// Service whose calls never complete, holding their heavy call slot.
+    #[derive(Clone)]
+    struct StuckService;
+
+    impl RpcServiceT for StuckService {
+        type MethodResponse = MethodResponse;
+        type NotificationResponse = MethodResponse;
+        type BatchResponse = MethodResponse;
+
+        fn call<'a>(
+            &self,
+            _req: Request<'a>,
+        ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+            pending()
+        }
+
+        fn batch<'a>(
+            &self,
+            _req: Batch<'a>,
+        ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+            ready(MethodResponse::from_batch(BatchResponseBuilder::new_with_limit(0).finish()))
+        }
+
+        fn notification<'a>(
+            &self,
+            _n: Notification<'a>,
+        ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+            ready(MethodResponse::notification())
+        }
+    }
+
+    fn request(addr: Option<SocketAddr>, conn_id: Option<usize>) -> Request<'static> {
+        let mut req = Request::owned("eth_getLogs".to_string(), None::<Box<RawValue>>, Id::Null);
+        if let Some(conn_id) = conn_id {
+            req.extensions_mut().insert(ConnectionId(conn_id));
+        }
+        if let Some(addr) = addr {
+            req.extensions_mut().insert(addr);
+        }
+        req
+    }
+
+    /// Returns `true` if the call was rejected, `false` if it was admitted and is still running.
+    async fn rejected(
+        service: &HeavyCallLimitService<StuckService>,
+        req: Request<'static>,
+    ) -> bool {
+        match tokio::time::timeout(Duration::from_millis(20), service.call(req)).await {
+            Ok(res) => {
+                assert!(res.is_error());
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_independent_client_buckets() {
+        let service = HeavyCallLimitLayer::new(HeavyCallLimits {
+            max_concurrent: 1,
+            max_queued: 0,
+            ..Default::default()
+        })
+        .layer(StuckService);
+        let alice = SocketAddr::from(([10, 0, 0, 1], 30000));
+        let bob = SocketAddr::from(([10, 0, 0, 2], 30000));
+
+        let alice_call = tokio::spawn({
+            let service = service.clone();
+            async move { service.call(request(Some(alice), Some(1))).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The slot of alice is taken, even from another connection, the one of bob is free
+        assert!(rejected(&service, request(Some(alice), Some(2))).await);
+        assert!(!rejected(&service, request(Some(bob), Some(3))).await);
+
+        // Without a client address each connection gets its own bucket
+        assert!(!rejected(&service, request(None, Some(4))).await);
+
+        // Requests that can't be attributed to a client are not limited
+        assert!(!rejected(&service, request(None, None)).await);
+        assert!(!rejected(&service, request(None, None)).await);
+        alice_call.abort();
+    }
+
+    #[tokio::test]
+    async fn test_limit_across_connections_of_client() {
+        let mut module = RpcModule::new(());
+        module
+            .register_async_method("eth_getLogs", |_, _, _| pending::<RpcResult<Vec<u64>>>())
+            .unwrap();
+        let server = Server::builder()
+            .set_http_middleware(ServiceBuilder::new().layer(ClientAddrLayer::new(true)))
+            .set_rpc_middleware(RpcServiceBuilder::new().layer(HeavyCallLimitLayer::new(
+                HeavyCallLimits { max_concurrent: 1, max_queued: 0, ..Default::default() },
+            )))
+            .build("127.0.0.1:0")
+            .await
+            .unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let _handle = server.start(module);
+
+        // Every client has its own connection, behind a proxy reporting the client address
+        let client = |ip: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("x-forwarded-for", ip.parse().unwrap());
+            HttpClientBuilder::default().set_headers(headers).build(&url).unwrap()
+        };
+        let get_logs = |client: HttpClient| async move {
+            tokio::time::timeout(
+                Duration::from_millis(200),
+                client.request::<Vec<u64>, _>("eth_getLogs", rpc_params![]),
+            )
+            .await
+        };
+
+        let alice_call = tokio::spawn(get_logs(client("10.0.0.1")));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The second connection of alice shares her slot, bob has his own
+        match get_logs(client("10.0.0.1")).await {
+            Ok(Err(ClientError::Call(err))) => assert_eq!(err.code(), LIMIT_EXCEEDED_CODE),
+            res => panic!("expected the call to be rejected, got {res:?}"),
+        }
+        assert!(get_logs(client("10.0.0.2")).await.is_err());
+        alice_call.abort();
+    }
+
+    #[tokio::test]
+    async fn test_queued_call_times_out() {
+        let limiter = limiter(1, 1);
+        let client = ClientKey::Connection(ConnectionId(1));
+        let _permit = limiter.clone().acquire(client).await.unwrap();
+        assert!(limiter.clone().acquire(client).await.is_none());
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        let limiter = HeavyCallLimiter::new(HeavyCallLimits::default());
+        assert!(!limiter.is_enabled());
+        assert!(!limiter.is_heavy("eth_getLogs"));
+    }
+}
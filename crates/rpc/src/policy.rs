@@ -0,0 +1,189 @@
+//! RPC method policy middleware.
+//!
+//! Public replicas do not hold local signing keys, yet upstream reth answers account-bearing
+//! methods differently across versions (empty results, "unknown account", or forwarding).
+//! This layer rejects explicitly disabled methods up front with a stable error message.
+
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::Arc,
+};
+
+use futures::future::{ready, Either};
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::{error::METHOD_NOT_FOUND_CODE, ErrorObject, Id, Request},
+    MethodResponse,
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use tower::Layer;
+use tracing::debug;
+
+use crate::batch_via_call;
+
+/// Methods that require a local account or signer.
+pub const ACCOUNT_METHODS: &[&str] = &[
+    "eth_accounts",
+    "eth_sign",
+    "eth_signTransaction",
+    "eth_signTypedData",
+    "eth_signTypedData_v3",
+    "eth_signTypedData_v4",
+    "eth_sendTransaction",
+    "personal_sign",
+    "personal_listAccounts",
+    "personal_sendTransaction",
+];
+
+/// Metrics for a single disabled method.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_policy")]
+struct RpcPolicyMetrics {
+    /// Number of calls rejected because the method is disabled
+    rejected_calls: Counter,
+}
+
+/// Set of methods rejected by the [`RpcPolicyLayer`].
+#[derive(Clone, Default)]
+pub struct RpcPolicy {
+    disabled: HashMap<String, RpcPolicyMetrics>,
+}
+
+impl RpcPolicy {
+    /// Creates a policy that rejects the given methods.
+    pub fn new(disabled_methods: impl IntoIterator<Item = String>) -> Self {
+        let disabled = disabled_methods
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .map(|method| {
+                let metrics = RpcPolicyMetrics::new_with_labels(&[("method", method.clone())]);
+                (method, metrics)
+            })
+            .collect();
+        Self { disabled }
+    }
+
+    /// Returns `true` if no method is disabled.
+    pub fn is_empty(&self) -> bool {
+        self.disabled.is_empty()
+    }
+
+    /// Returns `true` if the method is disabled.
+    pub fn is_disabled(&self, method: &str) -> bool {
+        self.disabled.contains_key(method)
+    }
+
+    /// Builds the error response for a disabled method and records the rejection.
+    fn reject(&self, method: &str, id: Id<'_>) -> MethodResponse {
+        if let Some(metrics) = self.disabled.get(method) {
+            metrics.rejected_calls.increment(1);
+        }
+        debug!(target: "xlayer::rpc::policy", "Rejected disabled method = {method}");
+        MethodResponse::error(
+            id,
+            ErrorObject::owned(
+                METHOD_NOT_FOUND_CODE,
+                format!("method {method} is disabled on this node"),
+                None::<()>,
+            ),
+        )
+    }
+}
+
+/// Layer that rejects disabled RPC methods.
+#[derive(Clone)]
+pub struct RpcPolicyLayer {
+    policy: Arc<RpcPolicy>,
+}
+
+impl RpcPolicyLayer {
+    pub fn new(policy: RpcPolicy) -> Self {
+        Self { policy: Arc::new(policy) }
+    }
+}
+
+impl<S> Layer<S> for RpcPolicyLayer {
+    type Service = RpcPolicyService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcPolicyService { inner, policy: self.policy.clone() }
+    }
+}
+
+/// RPC policy service that rejects disabled methods before they reach the inner service.
+#[derive(Clone)]
+pub struct RpcPolicyService<S> {
+    inner: S,
+    policy: Arc<RpcPolicy>,
+}
+
+impl<S> RpcServiceT for RpcPolicyService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let method = req.method_name();
+        if self.policy.is_disabled(method) {
+            return Either::Left(ready(self.policy.reject(method, req.id())));
+        }
+        Either::Right(self.inner.call(req))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if self.policy.is_empty() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so disabled methods can't slip through a batch
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::value::RawValue;
+    use xlayer_legacy_rpc::test_utils::EchoService;
+
+    fn request(method: &str) -> Request<'static> {
+        Request::owned(method.to_string(), None::<Box<RawValue>>, Id::Number(1))
+    }
+
+    #[tokio::test]
+    async fn test_disabled_method_rejected() {
+        let policy = RpcPolicy::new(ACCOUNT_METHODS.iter().map(|m| m.to_string()));
+        let service = RpcPolicyLayer::new(policy).layer(EchoService);
+
+        let res = service.call(request("eth_sign")).await;
+        assert!(res.is_error());
+        assert!(res.as_json().get().contains("method eth_sign is disabled on this node"));
+
+        let res = service.call(request("eth_blockNumber")).await;
+        assert!(res.is_success());
+    }
+
+    #[tokio::test]
+    async fn test_empty_policy_passes_through() {
+        let service = RpcPolicyLayer::new(RpcPolicy::default()).layer(EchoService);
+        let res = service.call(request("eth_sendTransaction")).await;
+        assert!(res.is_success());
+    }
+}
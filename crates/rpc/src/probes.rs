@@ -0,0 +1,222 @@
+//! HTTP health and readiness probes.
+//!
+//! Kubernetes probes are plain HTTP GET requests. Serving them from the JSON-RPC server would
+//! require JSON-RPC client logic in the probe and subject probes to the RPC middleware, e.g. the
+//! heavy call limiter. This listener answers `GET /healthz` and `GET /readyz` on its own address
+//! with the status reported by `xlayer_health`:
+//! - `/healthz` answers 200 as long as the node serves requests
+//! - `/readyz` answers 503 while a required component is unavailable, i.e. no pending flashblock
+//!   on a node that consumes flashblocks, or while the caches are warmed after startup
+
+use std::{
+    io,
+    net::{SocketAddr, TcpListener},
+    time::Duration,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::timeout,
+};
+use tracing::{debug, info, warn};
+
+use crate::xlayer_ext::{PendingFlashBlockProvider, XlayerHealth, XlayerRpcExt};
+
+/// Maximum size of a probe request head.
+const MAX_REQUEST_SIZE: usize = 4096;
+
+/// Time a client has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Probe endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    Health,
+    Ready,
+}
+
+/// Listener serving the health and readiness probes.
+#[derive(Debug)]
+pub struct HealthProbes<T> {
+    listener: TcpListener,
+    rpc: XlayerRpcExt<T>,
+    require_flashblocks: bool,
+}
+
+impl<T> HealthProbes<T>
+where
+    T: PendingFlashBlockProvider + Send + Sync + 'static,
+{
+    /// Binds the probe listener. With `require_flashblocks`, the node is only ready while a
+    /// pending flashblock is available.
+    pub fn bind(
+        addr: SocketAddr,
+        rpc: XlayerRpcExt<T>,
+        require_flashblocks: bool,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, rpc, require_flashblocks })
+    }
+
+    /// Returns the bound address.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves probes until the listener fails.
+    pub async fn serve(self) {
+        let listener = match tokio::net::TcpListener::from_std(self.listener) {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!(target: "xlayer::rpc::probes", %err, "Failed to start health probes");
+                return;
+            }
+        };
+        if let Ok(addr) = listener.local_addr() {
+            info!(target: "xlayer::rpc::probes", %addr, "Health probes listening");
+        }
+
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(err) => {
+                    debug!(target: "xlayer::rpc::probes", %err, "Failed to accept probe connection");
+                    continue;
+                }
+            };
+            let rpc = self.rpc.clone();
+            let require_flashblocks = self.require_flashblocks;
+            tokio::spawn(async move {
+                if let Err(err) = handle_connection(stream, &rpc, require_flashblocks).await {
+                    debug!(target: "xlayer::rpc::probes", %err, "Failed to answer probe");
+                }
+            });
+        }
+    }
+}
+
+async fn handle_connection<T: PendingFlashBlockProvider>(
+    mut stream: TcpStream,
+    rpc: &XlayerRpcExt<T>,
+    require_flashblocks: bool,
+) -> io::Result<()> {
+    let head = timeout(REQUEST_TIMEOUT, read_request_head(&mut stream))
+        .await
+        .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "request timed out"))??;
+    let response = respond(&head, || rpc.health_status(), require_flashblocks);
+    stream.write_all(&response).await?;
+    stream.shutdown().await
+}
+
+/// Reads the request line and headers, the body of probe requests is ignored.
+async fn read_request_head(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut head = Vec::with_capacity(512);
+    let mut buf = [0u8; 512];
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        if head.len() >= MAX_REQUEST_SIZE {
+            break;
+        }
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buf[..read]);
+    }
+    Ok(head)
+}
+
+/// Returns the HTTP response to the request head.
+fn respond(
+    head: &[u8],
+    health: impl FnOnce() -> XlayerHealth,
+    require_flashblocks: bool,
+) -> Vec<u8> {
+    let request_line = head.split(|byte| *byte == b'\n').next().unwrap_or_default();
+    let request_line = String::from_utf8_lossy(request_line);
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return response(400, "Bad Request", None);
+    };
+    let path = target.split('?').next().unwrap_or_default();
+    let probe = match path {
+        "/healthz" => Probe::Health,
+        "/readyz" => Probe::Ready,
+        _ => return response(404, "Not Found", None),
+    };
+    if method != "GET" && method != "HEAD" {
+        return response(405, "Method Not Allowed", None);
+    }
+
+    let health = health();
+    let ready = !health.warming_up && (!require_flashblocks || health.flashblocks_enabled);
+    let (status, reason) = match probe {
+        Probe::Ready if !ready => (503, "Service Unavailable"),
+        _ => (200, "OK"),
+    };
+    let body = serde_json::to_string(&health).unwrap_or_default();
+    response(status, reason, (method == "GET").then_some(body.as_str()))
+}
+
+fn response(status: u16, reason: &str, body: Option<&str>) -> Vec<u8> {
+    let body = body.unwrap_or_default();
+    format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+    .into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finality::{FinalityTagPolicy, FinalityTags};
+
+    fn health(flashblocks_enabled: bool) -> XlayerHealth {
+        XlayerHealth {
+            flashblocks_enabled,
+            finality_tags: FinalityTags::new(FinalityTagPolicy::default()).status(),
+            legacy_handoff: None,
+            warming_up: false,
+        }
+    }
+
+    fn probe(request: &str, flashblocks_enabled: bool, require_flashblocks: bool) -> String {
+        let response =
+            respond(request.as_bytes(), || health(flashblocks_enabled), require_flashblocks);
+        String::from_utf8(response).unwrap()
+    }
+
+    #[test]
+    fn test_healthz() {
+        let response = probe("GET /healthz HTTP/1.1\r\nHost: node\r\n\r\n", false, true);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(r#""flashblocksEnabled":false,"finalityTags":{"safe":{"source":"native"},"finalized":{"source":"native"}}}"#));
+    }
+
+    #[test]
+    fn test_readyz() {
+        let request = "GET /readyz?verbose=1 HTTP/1.1\r\n\r\n";
+        assert!(probe(request, false, false).starts_with("HTTP/1.1 200 OK"));
+        assert!(probe(request, true, true).starts_with("HTTP/1.1 200 OK"));
+        assert!(probe(request, false, true).starts_with("HTTP/1.1 503 Service Unavailable"));
+
+        // Not ready until the caches are warm
+        let warming_up = XlayerHealth { warming_up: true, ..health(true) };
+        let response = respond(request.as_bytes(), || warming_up, false);
+        assert!(String::from_utf8(response).unwrap().starts_with("HTTP/1.1 503"));
+
+        // HEAD requests get the status only
+        let response = probe("HEAD /readyz HTTP/1.1\r\n\r\n", false, true);
+        assert!(response.starts_with("HTTP/1.1 503"));
+        assert!(response.ends_with("Content-Length: 0\r\nConnection: close\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_invalid_requests() {
+        assert!(probe("GET /metrics HTTP/1.1\r\n\r\n", true, false).starts_with("HTTP/1.1 404"));
+        assert!(probe("POST /healthz HTTP/1.1\r\n\r\n", true, false).starts_with("HTTP/1.1 405"));
+        assert!(probe("garbage", true, false).starts_with("HTTP/1.1 400"));
+    }
+}
@@ -0,0 +1,378 @@
+//! Field masks for large responses, served as `xlayer_call(method, params, mask)`.
+//!
+//! Mobile and light consumers of public endpoints often need a few fields of a block or receipt
+//! but download the whole object, `logsBloom` and the input of every transaction included.
+//! `xlayer_call` runs `method` with `params` like a direct call and prunes the result with the
+//! field mask before it is sent:
+//! - `{"include": ["number", "hash", "transactions.hash"]}` keeps the listed fields only
+//! - `{"exclude": ["logsBloom", "transactions.input"]}` drops the listed fields
+//!
+//! Paths are dot separated field names. Arrays are traversed transparently, so
+//! `transactions.hash` applies to every transaction of a block and `logsBloom` to every receipt
+//! of `eth_getBlockReceipts`. Listing a field in `include` keeps it whole. Results that are not
+//! objects or arrays of objects are returned unchanged.
+//!
+//! The wrapped call runs through the inner middleware like a direct call of `method`, so method
+//! policies, limits and routing apply to it.
+
+use std::{collections::HashMap, future::Future};
+
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::{error::INVALID_PARAMS_CODE, ErrorObject, Request, ResponsePayload},
+    MethodResponse,
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::Deserialize;
+use serde_json::{value::RawValue, Value};
+use tower::Layer;
+use tracing::debug;
+
+use crate::batch_via_call;
+
+/// Name of the wrapper method.
+pub const XLAYER_CALL_METHOD: &str = "xlayer_call";
+
+/// Largest number of paths in a mask.
+const MAX_PATHS: usize = 64;
+
+/// Largest number of fields in a path.
+const MAX_PATH_DEPTH: usize = 8;
+
+/// Field mask metrics
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_response_fields")]
+struct ResponseFieldsMetrics {
+    /// Number of masked calls
+    calls: Counter,
+    /// Bytes removed from responses by masks
+    pruned_bytes: Counter,
+}
+
+/// Field mask of an `xlayer_call` request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+enum FieldMask {
+    /// Keep the listed fields only
+    Include(Vec<String>),
+    /// Drop the listed fields
+    Exclude(Vec<String>),
+}
+
+/// Fields of a mask, by name.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FieldTree {
+    /// Whether the path to this node was listed, selecting the whole value
+    selected: bool,
+    children: HashMap<String, FieldTree>,
+}
+
+impl FieldTree {
+    fn new(paths: &[String]) -> Result<Self, &'static str> {
+        if paths.is_empty() || paths.len() > MAX_PATHS {
+            return Err("Field mask must list between 1 and 64 paths");
+        }
+        let mut tree = Self::default();
+        for path in paths {
+            let fields: Vec<&str> = path.split('.').collect();
+            if fields.len() > MAX_PATH_DEPTH || fields.iter().any(|field| field.is_empty()) {
+                return Err("Invalid field path");
+            }
+            let mut node = &mut tree;
+            for field in fields {
+                node = node.children.entry(field.to_string()).or_default();
+                // A shorter path already selects the whole value
+                if node.selected {
+                    break;
+                }
+            }
+            node.selected = true;
+            node.children.clear();
+        }
+        Ok(tree)
+    }
+
+    /// Keeps the fields of the tree.
+    fn include(&self, value: &mut Value) {
+        match value {
+            Value::Array(values) => values.iter_mut().for_each(|value| self.include(value)),
+            Value::Object(object) => object.retain(|key, value| match self.children.get(key) {
+                Some(child) if child.selected => true,
+                Some(child) => {
+                    child.include(value);
+                    true
+                }
+                None => false,
+            }),
+            _ => {}
+        }
+    }
+
+    /// Drops the fields of the tree.
+    fn exclude(&self, value: &mut Value) {
+        match value {
+            Value::Array(values) => values.iter_mut().for_each(|value| self.exclude(value)),
+            Value::Object(object) => {
+                for (key, child) in &self.children {
+                    if child.selected {
+                        object.remove(key);
+                    } else if let Some(value) = object.get_mut(key) {
+                        child.exclude(value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Parsed `xlayer_call` params.
+#[derive(Debug)]
+struct MaskedCall<'a> {
+    method: String,
+    params: Option<&'a RawValue>,
+    mask: FieldMask,
+}
+
+impl<'a> MaskedCall<'a> {
+    fn parse(params: Option<&'a str>) -> Result<Self, &'static str> {
+        let params: Vec<&RawValue> = params
+            .and_then(|params| serde_json::from_str(params).ok())
+            .ok_or("Missing required params")?;
+        let [method, call_params, mask] = params[..] else {
+            return Err("Expected method, params and field mask");
+        };
+        let method: String = serde_json::from_str(method.get()).map_err(|_| "Invalid method")?;
+        if method == XLAYER_CALL_METHOD || method.ends_with("_subscribe") {
+            return Err("Method can't be masked");
+        }
+        let call_params = (call_params.get() != "null").then_some(call_params);
+        let mask = serde_json::from_str(mask.get()).map_err(|_| "Invalid field mask")?;
+        Ok(Self { method, params: call_params, mask })
+    }
+}
+
+/// Applies a field mask to the result of a response, `None` if the response has no result.
+fn mask_response(json: &str, tree: &FieldTree, mask: &FieldMask) -> Option<Value> {
+    #[derive(Deserialize)]
+    struct Success {
+        result: Value,
+    }
+
+    let Success { mut result } = serde_json::from_str(json).ok()?;
+    match mask {
+        FieldMask::Include(_) => tree.include(&mut result),
+        FieldMask::Exclude(_) => tree.exclude(&mut result),
+    }
+    Some(result)
+}
+
+/// Layer serving `xlayer_call`, unknown to the node if disabled.
+#[derive(Clone)]
+pub struct ResponseFieldsLayer {
+    enabled: bool,
+}
+
+impl ResponseFieldsLayer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl<S> Layer<S> for ResponseFieldsLayer {
+    type Service = ResponseFieldsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseFieldsService {
+            inner,
+            enabled: self.enabled,
+            metrics: ResponseFieldsMetrics::default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ResponseFieldsService<S> {
+    inner: S,
+    enabled: bool,
+    metrics: ResponseFieldsMetrics,
+}
+
+impl<S> ResponseFieldsService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse> + Send + Sync + Clone + 'static,
+{
+    async fn masked_call(self, req: Request<'_>) -> MethodResponse {
+        let id = req.id();
+        let params = req.params();
+        let call = match MaskedCall::parse(params.as_str()) {
+            Ok(call) => call,
+            Err(message) => {
+                return MethodResponse::error(
+                    id,
+                    ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>),
+                );
+            }
+        };
+        let tree = match &call.mask {
+            FieldMask::Include(paths) | FieldMask::Exclude(paths) => FieldTree::new(paths),
+        };
+        let tree = match tree {
+            Ok(tree) => tree,
+            Err(message) => {
+                return MethodResponse::error(
+                    id,
+                    ErrorObject::owned(INVALID_PARAMS_CODE, message, None::<()>),
+                );
+            }
+        };
+
+        let mut inner_req =
+            Request::owned(call.method.clone(), call.params.map(RawValue::to_owned), id.clone());
+        *inner_req.extensions_mut() = req.extensions().clone();
+        let res = self.inner.call(inner_req).await;
+        self.metrics.calls.increment(1);
+        if !res.is_success() {
+            return res;
+        }
+
+        let json = res.as_json().get();
+        let Some(result) = mask_response(json, &tree, &call.mask) else {
+            return res;
+        };
+        let mut masked = MethodResponse::response(id, ResponsePayload::success(result), usize::MAX);
+        let pruned = json.len().saturating_sub(masked.as_json().get().len());
+        self.metrics.pruned_bytes.increment(pruned as u64);
+        debug!(target: "xlayer::rpc::response_fields", method = %call.method, pruned, "Masked response");
+        *masked.extensions_mut() = res.extensions().clone();
+        masked
+    }
+}
+
+impl<S> RpcServiceT for ResponseFieldsService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        if !self.enabled || req.method_name() != XLAYER_CALL_METHOD {
+            return Either::Left(self.inner.call(req));
+        }
+
+        Either::Right(Box::pin(self.clone().masked_call(req)))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if !self.enabled {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so batched responses are masked too
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block() -> Value {
+        serde_json::json!({
+            "number": "0x10",
+            "hash": "0xaa",
+            "logsBloom": "0x00",
+            "transactions": [
+                {"hash": "0x01", "input": "0xdeadbeef", "to": "0x02"},
+                {"hash": "0x03", "input": "0x", "to": null}
+            ]
+        })
+    }
+
+    fn apply(mask: Value, mut value: Value) -> Value {
+        let mask: FieldMask = serde_json::from_value(mask).unwrap();
+        let (FieldMask::Include(paths) | FieldMask::Exclude(paths)) = &mask;
+        let tree = FieldTree::new(paths).unwrap();
+        match mask {
+            FieldMask::Include(_) => tree.include(&mut value),
+            FieldMask::Exclude(_) => tree.exclude(&mut value),
+        }
+        value
+    }
+
+    #[test]
+    fn test_include_fields() {
+        let masked =
+            apply(serde_json::json!({"include": ["number", "transactions.hash"]}), block());
+        assert_eq!(
+            masked,
+            serde_json::json!({
+                "number": "0x10",
+                "transactions": [{"hash": "0x01"}, {"hash": "0x03"}]
+            })
+        );
+
+        // A field listed whole keeps the paths below it
+        let masked =
+            apply(serde_json::json!({"include": ["transactions.hash", "transactions"]}), block());
+        assert_eq!(masked, serde_json::json!({"transactions": block()["transactions"]}));
+    }
+
+    #[test]
+    fn test_exclude_fields() {
+        let masked =
+            apply(serde_json::json!({"exclude": ["logsBloom", "transactions.input"]}), block());
+        assert_eq!(
+            masked,
+            serde_json::json!({
+                "number": "0x10",
+                "hash": "0xaa",
+                "transactions": [{"hash": "0x01", "to": "0x02"}, {"hash": "0x03", "to": null}]
+            })
+        );
+
+        // Arrays of objects, e.g. receipts, are masked element-wise
+        let receipts = serde_json::json!([{"logsBloom": "0x00", "status": "0x1"}]);
+        let masked = apply(serde_json::json!({"exclude": ["logsBloom"]}), receipts);
+        assert_eq!(masked, serde_json::json!([{"status": "0x1"}]));
+    }
+
+    #[test]
+    fn test_parse_masked_call() {
+        let call = MaskedCall::parse(Some(
+            r#"["eth_getBlockByNumber", ["latest", true], {"exclude": ["logsBloom"]}]"#,
+        ))
+        .unwrap();
+        assert_eq!(call.method, "eth_getBlockByNumber");
+        assert_eq!(call.params.unwrap().get(), r#"["latest", true]"#);
+        assert_eq!(call.mask, FieldMask::Exclude(vec!["logsBloom".to_string()]));
+
+        for invalid in [
+            r#"["eth_blockNumber", []]"#,
+            r#"["xlayer_call", [], {"exclude": ["a"]}]"#,
+            r#"["eth_subscribe", ["newHeads"], {"exclude": ["a"]}]"#,
+            r#"["eth_getBlockByNumber", [], {"only": ["a"]}]"#,
+            r#"["eth_getBlockByNumber", [], {"include": ["a"], "exclude": ["b"]}]"#,
+        ] {
+            assert!(MaskedCall::parse(Some(invalid)).is_err(), "{invalid}");
+        }
+
+        assert!(FieldTree::new(&[]).is_err());
+        assert!(FieldTree::new(&["a..b".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Transaction lookup by sender and nonce.
+//!
+//! Wallets detect replaced and stuck transactions by asking which transaction used a nonce of an
+//! account. `xlayer_getTransactionBySenderAndNonce` answers pending nonces from the pool. For mined
+//! nonces it binary searches the nonce history of the account for the block that used the nonce,
+//! at most `log2(blocks) + 2` state lookups, and scans that block for the transaction.
+//!
+//! The local history starts at the genesis block, nonces used before it belong to the legacy chain
+//! and are answered with null. With legacy routing, mapping the method to the equivalent method of
+//! the legacy node with `--rpc.legacy-method-names` retries null answers there.
+
+use alloy_consensus::{transaction::TxHashRef, Transaction};
+use alloy_primitives::{Address, TxHash, U64};
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::{error::INTERNAL_ERROR_CODE, ErrorObject, ErrorObjectOwned},
+};
+use reth_storage_api::{
+    errors::provider::ProviderResult, AccountReader, BlockNumReader, BlockReader,
+    StateProviderFactory, TransactionVariant,
+};
+use reth_transaction_pool::TransactionPool;
+
+/// Sender and nonce lookup API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait SenderNonceApi {
+    /// Returns the hash of the pending or mined transaction of `sender` with `nonce`, null if
+    /// there is none.
+    #[method(name = "getTransactionBySenderAndNonce")]
+    async fn transaction_by_sender_and_nonce(
+        &self,
+        sender: Address,
+        nonce: U64,
+    ) -> RpcResult<Option<TxHash>>;
+}
+
+/// Serves sender and nonce lookups from the pool and the local history.
+#[derive(Debug, Clone)]
+pub struct SenderNonceRpc<Provider, Pool> {
+    provider: Provider,
+    pool: Pool,
+    /// First block of the local history
+    genesis_block: u64,
+}
+
+impl<Provider, Pool> SenderNonceRpc<Provider, Pool> {
+    pub fn new(provider: Provider, pool: Pool, genesis_block: u64) -> Self {
+        Self { provider, pool, genesis_block }
+    }
+}
+
+impl<Provider, Pool> SenderNonceRpc<Provider, Pool>
+where
+    Provider: StateProviderFactory + BlockReader,
+    Provider::Transaction: Transaction + TxHashRef,
+{
+    /// Returns the hash of the mined transaction of `sender` with `nonce`.
+    fn mined_transaction(&self, sender: Address, nonce: u64) -> ProviderResult<Option<TxHash>> {
+        let latest = self.provider.best_block_number()?;
+        let nonce_at = |number: u64| -> ProviderResult<u64> {
+            let state = self.provider.history_by_block_number(number)?;
+            Ok(state.basic_account(&sender)?.map_or(0, |account| account.nonce))
+        };
+        let Some(number) = find_nonce_block(self.genesis_block, latest, nonce, nonce_at)? else {
+            return Ok(None);
+        };
+        let Some(block) =
+            self.provider.recovered_block(number.into(), TransactionVariant::WithHash)?
+        else {
+            return Ok(None);
+        };
+        Ok(block
+            .transactions_with_sender()
+            .find(|(from, tx)| **from == sender && tx.nonce() == nonce)
+            .map(|(_, tx)| *tx.tx_hash()))
+    }
+}
+
+/// Returns the block in `genesis..=latest` whose execution used `nonce`, the first block after
+/// which the nonce of the account exceeds it. `None` if the nonce was used before the genesis
+/// block or isn't used yet.
+fn find_nonce_block(
+    genesis: u64,
+    latest: u64,
+    nonce: u64,
+    mut nonce_at: impl FnMut(u64) -> ProviderResult<u64>,
+) -> ProviderResult<Option<u64>> {
+    if nonce_at(latest)? <= nonce || nonce_at(genesis)? > nonce {
+        return Ok(None);
+    }
+    // The nonce is unused after `low` and used after `high`
+    let (mut low, mut high) = (genesis, latest);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if nonce_at(mid)? > nonce {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    Ok(Some(high))
+}
+
+#[async_trait]
+impl<Provider, Pool> SenderNonceApiServer for SenderNonceRpc<Provider, Pool>
+where
+    Provider: StateProviderFactory + BlockReader + Clone + Send + Sync + 'static,
+    Provider::Transaction: Transaction + TxHashRef,
+    Pool: TransactionPool + Clone + 'static,
+{
+    async fn transaction_by_sender_and_nonce(
+        &self,
+        sender: Address,
+        nonce: U64,
+    ) -> RpcResult<Option<TxHash>> {
+        let nonce = nonce.to::<u64>();
+        if let Some(tx) = self.pool.get_transaction_by_sender_and_nonce(sender, nonce) {
+            return Ok(Some(*tx.hash()));
+        }
+
+        let this = self.clone();
+        match tokio::task::spawn_blocking(move || this.mined_transaction(sender, nonce)).await {
+            Ok(Ok(hash)) => Ok(hash),
+            Ok(Err(err)) => Err(internal_error(err)),
+            Err(err) => Err(internal_error(err)),
+        }
+    }
+}
+
+/// Converts a lookup failure into an internal error.
+fn internal_error(err: impl std::fmt::Display) -> ErrorObjectOwned {
+    ErrorObject::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<()>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_nonce_block() {
+        // Nonce after each block of 100..=200, the account sends at blocks 120, 150 and 151
+        let history = |number: u64| -> ProviderResult<u64> {
+            Ok([120, 150, 151].iter().filter(|sent| **sent <= number).count() as u64)
+        };
+
+        assert_eq!(find_nonce_block(100, 200, 0, history).unwrap(), Some(120));
+        assert_eq!(find_nonce_block(100, 200, 1, history).unwrap(), Some(150));
+        assert_eq!(find_nonce_block(100, 200, 2, history).unwrap(), Some(151));
+        // Not used yet
+        assert_eq!(find_nonce_block(100, 200, 3, history).unwrap(), None);
+        // Used before the genesis block
+        assert_eq!(find_nonce_block(130, 200, 0, history).unwrap(), None);
+
+        let mut lookups = 0;
+        let counted = |number: u64| {
+            lookups += 1;
+            history(number)
+        };
+        find_nonce_block(100, 200, 1, counted).unwrap();
+        assert!(lookups <= 9, "{lookups} lookups");
+    }
+}
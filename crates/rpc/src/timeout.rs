@@ -0,0 +1,244 @@
+//! Per-method execution timeouts for locally served calls.
+//!
+//! Forwarded requests are bounded by the timeouts of the legacy and pending proxies, but a local
+//! wide `eth_getLogs` or trace can run for minutes and stall the WS connection it was issued on.
+//! This layer abandons the inner future once the configured timeout of the method elapses and
+//! answers with a timeout error. Partial results are never returned. Work already handed to the
+//! blocking pool runs to completion in the background.
+
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
+
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::{ErrorObject, Request},
+    MethodResponse,
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use tower::Layer;
+use tracing::debug;
+
+use crate::batch_via_call;
+
+/// Error code returned when a call exceeds its execution timeout.
+pub const TIMEOUT_CODE: i32 = -32002;
+
+/// Method name matching every method without an explicit timeout.
+pub const ANY_METHOD: &str = "*";
+
+/// Execution timeouts applied by the [`MethodTimeoutLayer`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MethodTimeouts {
+    /// Timeouts by method name, [`ANY_METHOD`] sets the default timeout
+    pub timeouts: HashMap<String, Duration>,
+}
+
+impl MethodTimeouts {
+    pub fn new(timeouts: impl IntoIterator<Item = (String, Duration)>) -> Self {
+        Self { timeouts: timeouts.into_iter().filter(|(_, timeout)| !timeout.is_zero()).collect() }
+    }
+
+    /// Returns the execution timeout of the method, if any.
+    pub fn timeout(&self, method: &str) -> Option<Duration> {
+        self.timeouts.get(method).or_else(|| self.timeouts.get(ANY_METHOD)).copied()
+    }
+
+    /// Returns `true` if no method has a timeout.
+    pub fn is_empty(&self) -> bool {
+        self.timeouts.is_empty()
+    }
+}
+
+/// Parses a `<method>=<duration>` timeout, e.g. `eth_getLogs=30s`.
+pub fn parse_method_timeout(s: &str) -> Result<(String, Duration), String> {
+    let (method, timeout) =
+        s.split_once('=').ok_or_else(|| format!("expected <method>=<duration>, got `{s}`"))?;
+    let method = method.trim();
+    if method.is_empty() {
+        return Err(format!("missing method name in `{s}`"));
+    }
+    let timeout = humantime::parse_duration(timeout.trim())
+        .map_err(|e| format!("invalid timeout for {method}: {e}"))?;
+    Ok((method.to_string(), timeout))
+}
+
+/// Metrics for method execution timeouts.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_timeout")]
+struct MethodTimeoutMetrics {
+    /// Number of calls abandoned because they exceeded their timeout
+    timed_out_calls: Counter,
+}
+
+/// Layer that bounds the execution time of local calls.
+#[derive(Clone)]
+pub struct MethodTimeoutLayer {
+    timeouts: Arc<MethodTimeouts>,
+    metrics: MethodTimeoutMetrics,
+}
+
+impl MethodTimeoutLayer {
+    pub fn new(timeouts: MethodTimeouts) -> Self {
+        Self { timeouts: Arc::new(timeouts), metrics: MethodTimeoutMetrics::default() }
+    }
+}
+
+impl<S> Layer<S> for MethodTimeoutLayer {
+    type Service = MethodTimeoutService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MethodTimeoutService {
+            inner,
+            timeouts: self.timeouts.clone(),
+            metrics: self.metrics.clone(),
+        }
+    }
+}
+
+/// Service that abandons calls exceeding the timeout of their method.
+#[derive(Clone)]
+pub struct MethodTimeoutService<S> {
+    inner: S,
+    timeouts: Arc<MethodTimeouts>,
+    metrics: MethodTimeoutMetrics,
+}
+
+impl<S> RpcServiceT for MethodTimeoutService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let Some(timeout) = self.timeouts.timeout(req.method_name()) else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        let inner = self.inner.clone();
+        let metrics = self.metrics.clone();
+
+        Either::Right(Box::pin(async move {
+            let id = req.id().into_owned();
+            let method = req.method_name().to_string();
+            match tokio::time::timeout(timeout, inner.call(req)).await {
+                Ok(res) => res,
+                Err(_) => {
+                    metrics.timed_out_calls.increment(1);
+                    debug!(target: "xlayer::rpc::timeout", "Abandoned method = {method} after {timeout:?}");
+                    MethodResponse::error(
+                        id,
+                        ErrorObject::owned(
+                            TIMEOUT_CODE,
+                            format!("{method} timed out after {timeout:?}"),
+                            None::<()>,
+                        ),
+                    )
+                }
+            }
+        }))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if self.timeouts.is_empty() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so every batched call is bounded by its own timeout
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::ready;
+    use jsonrpsee::{
+        types::{Id, ResponsePayload},
+        BatchResponseBuilder,
+    };
+
+    /// Service answering after the delay given by the method name, in milliseconds.
+    #[derive(Clone)]
+    struct SlowService;
+
+    impl RpcServiceT for SlowService {
+        type MethodResponse = MethodResponse;
+        type NotificationResponse = MethodResponse;
+        type BatchResponse = MethodResponse;
+
+        fn call<'a>(
+            &self,
+            req: Request<'a>,
+        ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+            let delay = Duration::from_millis(req.method_name().parse().unwrap());
+            async move {
+                tokio::time::sleep(delay).await;
+                MethodResponse::response(req.id(), ResponsePayload::success(true), usize::MAX)
+            }
+        }
+
+        fn batch<'a>(
+            &self,
+            _req: Batch<'a>,
+        ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+            ready(MethodResponse::from_batch(BatchResponseBuilder::new_with_limit(0).finish()))
+        }
+
+        fn notification<'a>(
+            &self,
+            _n: Notification<'a>,
+        ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+            ready(MethodResponse::notification())
+        }
+    }
+
+    fn call(method: &str) -> Request<'static> {
+        Request::owned(method.to_string(), None, Id::Number(1))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_call_timeout() {
+        let timeouts = MethodTimeouts::new([
+            ("1000".to_string(), Duration::from_millis(500)),
+            (ANY_METHOD.to_string(), Duration::from_secs(10)),
+        ]);
+        let service = MethodTimeoutLayer::new(timeouts).layer(SlowService);
+
+        let res = service.call(call("1000")).await;
+        assert!(res.is_error());
+        assert_eq!(res.as_error_code(), Some(TIMEOUT_CODE));
+
+        // Falls back to the default timeout
+        assert!(service.call(call("2000")).await.is_success());
+        assert!(service.call(call("20000")).await.is_error());
+    }
+
+    #[test]
+    fn test_parse_method_timeout() {
+        assert_eq!(
+            parse_method_timeout("eth_getLogs=30s").unwrap(),
+            ("eth_getLogs".to_string(), Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse_method_timeout("*=1m").unwrap(),
+            (ANY_METHOD.to_string(), Duration::from_secs(60))
+        );
+        assert!(parse_method_timeout("eth_getLogs").is_err());
+        assert!(parse_method_timeout("=30s").is_err());
+        assert!(parse_method_timeout("eth_getLogs=soon").is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! Duplicate transaction submission dampening.
+//!
+//! Wallets retry `eth_sendRawTransaction` aggressively when inclusion takes longer than they
+//! expect. A resubmission of an already accepted transaction can't change the outcome, yet it is
+//! re-validated and, on replicas, re-forwarded to the sequencer. This layer remembers the hashes
+//! of recently accepted raw transactions and answers duplicates within the window with the
+//! original hash.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use alloy_primitives::{keccak256, Bytes, B256};
+use futures::future::{ready, Either};
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::{Request, ResponsePayload},
+    MethodResponse,
+};
+use moka::sync::Cache;
+use reth_metrics::{metrics::Counter, Metrics};
+use tower::Layer;
+use tracing::debug;
+
+use crate::batch_via_call;
+
+/// Maximum number of remembered transaction hashes.
+const MAX_RECENT_TXS: u64 = 100_000;
+
+/// Metrics for duplicate transaction submissions.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_tx_dedup")]
+struct TxDedupMetrics {
+    /// Number of raw transaction submissions
+    submissions: Counter,
+    /// Number of submissions answered as duplicates
+    duplicates: Counter,
+}
+
+/// Recently accepted raw transaction hashes.
+struct RecentTxs {
+    hashes: Cache<B256, ()>,
+    metrics: TxDedupMetrics,
+}
+
+/// Layer that answers duplicate raw transaction submissions without forwarding them.
+#[derive(Clone)]
+pub struct TxDedupLayer {
+    recent: Option<Arc<RecentTxs>>,
+}
+
+impl TxDedupLayer {
+    /// Creates the layer, a zero `window` disables deduplication.
+    pub fn new(window: Duration) -> Self {
+        let recent = (!window.is_zero()).then(|| {
+            Arc::new(RecentTxs {
+                hashes: Cache::builder().max_capacity(MAX_RECENT_TXS).time_to_live(window).build(),
+                metrics: TxDedupMetrics::default(),
+            })
+        });
+        Self { recent }
+    }
+}
+
+impl<S> Layer<S> for TxDedupLayer {
+    type Service = TxDedupService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TxDedupService { inner, recent: self.recent.clone() }
+    }
+}
+
+/// Service that answers duplicate raw transaction submissions from the recent hashes.
+#[derive(Clone)]
+pub struct TxDedupService<S> {
+    inner: S,
+    recent: Option<Arc<RecentTxs>>,
+}
+
+/// Returns the hash of the raw transaction submitted by the request.
+fn raw_transaction_hash(req: &Request<'_>) -> Option<B256> {
+    let params = req.params();
+    let (raw,): (Bytes,) = serde_json::from_str(params.as_str()?).ok()?;
+    // The hash of an EIP-2718 transaction is the hash of its encoding
+    Some(keccak256(&raw))
+}
+
+impl<S> RpcServiceT for TxDedupService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let Some(recent) = self.recent.clone() else {
+            return Either::Left(self.inner.call(req));
+        };
+        if req.method_name() != "eth_sendRawTransaction" {
+            return Either::Left(self.inner.call(req));
+        }
+        let Some(tx_hash) = raw_transaction_hash(&req) else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        recent.metrics.submissions.increment(1);
+        if recent.hashes.contains_key(&tx_hash) {
+            recent.metrics.duplicates.increment(1);
+            debug!(target: "xlayer::rpc::tx_dedup", "Duplicate submission of tx = {tx_hash}");
+            return Either::Right(Either::Left(ready(MethodResponse::response(
+                req.id(),
+                ResponsePayload::success(tx_hash),
+                usize::MAX,
+            ))));
+        }
+
+        let inner = self.inner.clone();
+        Either::Right(Either::Right(Box::pin(async move {
+            let res = inner.call(req).await;
+            // Only remember accepted transactions, rejected ones may be fixed and resubmitted
+            if res.is_success() {
+                recent.hashes.insert(tx_hash, ());
+            }
+            res
+        })))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if self.recent.is_none() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so batched submissions are deduplicated too
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::{types::Id, BatchResponseBuilder};
+    use serde_json::value::RawValue;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Service accepting every transaction, counting the submissions it receives.
+    #[derive(Clone, Default)]
+    struct CountingService {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl RpcServiceT for CountingService {
+        type MethodResponse = MethodResponse;
+        type NotificationResponse = MethodResponse;
+        type BatchResponse = MethodResponse;
+
+        fn call<'a>(
+            &self,
+            req: Request<'a>,
+        ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let tx_hash = raw_transaction_hash(&req).unwrap();
+            ready(MethodResponse::response(req.id(), ResponsePayload::success(tx_hash), usize::MAX))
+        }
+
+        fn batch<'a>(
+            &self,
+            _req: Batch<'a>,
+        ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+            ready(MethodResponse::from_batch(BatchResponseBuilder::new_with_limit(0).finish()))
+        }
+
+        fn notification<'a>(
+            &self,
+            _n: Notification<'a>,
+        ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+            ready(MethodResponse::notification())
+        }
+    }
+
+    fn send_raw(raw: &str) -> Request<'static> {
+        Request::owned(
+            "eth_sendRawTransaction".to_string(),
+            Some(RawValue::from_string(format!(r#"["{raw}"]"#)).unwrap()),
+            Id::Number(1),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_submission_not_forwarded() {
+        let inner = CountingService::default();
+        let service = TxDedupLayer::new(Duration::from_secs(60)).layer(inner.clone());
+
+        let first = service.call(send_raw("0x02f86b01")).await;
+        let duplicate = service.call(send_raw("0x02f86b01")).await;
+        assert_eq!(first.as_json().get(), duplicate.as_json().get());
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 1);
+
+        service.call(send_raw("0x02f86b02")).await;
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_forwards_everything() {
+        let inner = CountingService::default();
+        let service = TxDedupLayer::new(Duration::ZERO).layer(inner.clone());
+
+        service.call(send_raw("0x02f86b01")).await;
+        service.call(send_raw("0x02f86b01")).await;
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,585 @@
+//! Write-ahead log of raw transactions forwarded to the sequencer.
+//!
+//! Replicas forward `eth_sendRawTransaction` to the sequencer and only return its hash. When the
+//! sequencer loses transactions it had accepted, e.g. after an incident, they are gone unless the
+//! wallet resubmits them. This layer appends every submission and its outcome to a local log of
+//! JSON lines, so transactions that never got included can be re-submitted with
+//! `xlayer-reth-tools replay-wal`.
+//!
+//! A submission is written and synced to disk before it is forwarded, so it survives a crash of
+//! the node while the sequencer handles it. Records are written by a background thread that syncs
+//! the records queued meanwhile at once, and the log is rotated by size, the oldest files are
+//! removed beyond the configured number of files. Submissions are forwarded without waiting if
+//! the writer fails or falls behind, their records are then dropped.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    future::Future,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{sync_channel, SyncSender, TrySendError},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::{keccak256, Bytes, B256};
+use futures::future::Either;
+use jsonrpsee::{
+    core::middleware::{Batch, Notification},
+    server::middleware::rpc::RpcServiceT,
+    types::Request,
+    MethodResponse,
+};
+use reth_metrics::{metrics::Counter, Metrics};
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use tower::Layer;
+use tracing::warn;
+
+use crate::batch_via_call;
+
+/// Name of the file records are appended to.
+pub const WAL_FILE: &str = "tx-wal.jsonl";
+
+/// Prefix of rotated files, followed by the rotation timestamp.
+const ROTATED_PREFIX: &str = "tx-wal-";
+
+/// Maximum number of records waiting to be written.
+const MAX_PENDING_RECORDS: usize = 10_000;
+
+/// Maximum number of records written per sync.
+const MAX_SYNC_BATCH: usize = 1_000;
+
+/// Minimum interval in milliseconds between warnings about records that weren't written.
+const WARN_INTERVAL_MS: u64 = 10_000;
+
+/// Metrics of the forwarded transaction log.
+#[derive(Metrics, Clone)]
+#[metrics(scope = "xlayer_rpc_tx_wal")]
+struct TxWalMetrics {
+    /// Number of records written
+    records: Counter,
+    /// Number of records dropped because the writer fell behind
+    dropped: Counter,
+    /// Number of records that failed to be written or synced
+    write_errors: Counter,
+}
+
+/// Forwarded transaction log settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxWalConfig {
+    /// Directory of the log files
+    pub dir: PathBuf,
+    /// Size in bytes above which the log file is rotated
+    pub max_file_size: u64,
+    /// Maximum number of log files kept, including the current one
+    pub max_files: usize,
+}
+
+/// Outcome of a raw transaction submission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxOutcome {
+    /// Logged before the submission is forwarded, followed by the outcome once it is known
+    Submitted,
+    Accepted,
+    Rejected,
+}
+
+/// Logged raw transaction submission.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxWalRecord {
+    /// Unix timestamp in milliseconds of the submission
+    pub timestamp: u64,
+    pub hash: B256,
+    pub raw: Bytes,
+    pub outcome: TxOutcome,
+    /// Error message of rejected submissions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64
+}
+
+/// Returns the log files in `dir`, oldest first.
+pub fn wal_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = rotated_files(dir)?;
+    let current = dir.join(WAL_FILE);
+    if current.exists() {
+        files.push(current);
+    }
+    Ok(files)
+}
+
+/// Returns the rotated log files in `dir`, oldest first.
+fn rotated_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(ROTATED_PREFIX) && name.ends_with(".jsonl"))
+        {
+            files.push(path);
+        }
+    }
+    // Rotation timestamps are zero padded, so names sort chronologically
+    files.sort();
+    Ok(files)
+}
+
+/// Reads all records of the log in `dir`, oldest first.
+///
+/// Malformed lines, e.g. a record truncated by a crash, are skipped.
+pub fn read_wal(dir: &Path) -> io::Result<Vec<TxWalRecord>> {
+    let mut records = Vec::new();
+    for path in wal_files(dir)? {
+        for (idx, line) in BufReader::new(File::open(&path)?).lines().enumerate() {
+            match serde_json::from_str(&line?) {
+                Ok(record) => records.push(record),
+                Err(err) => warn!(
+                    target: "xlayer::rpc::tx_wal",
+                    path = %path.display(),
+                    line = idx + 1,
+                    %err,
+                    "Skipping malformed record"
+                ),
+            }
+        }
+    }
+    Ok(records)
+}
+
+/// Appends records to the current log file and rotates it.
+struct WalWriter {
+    config: TxWalConfig,
+    file: File,
+    size: u64,
+}
+
+impl WalWriter {
+    fn open(config: TxWalConfig) -> io::Result<Self> {
+        fs::create_dir_all(&config.dir)?;
+        let file = Self::open_current(&config.dir)?;
+        let size = file.metadata()?.len();
+        Ok(Self { config, file, size })
+    }
+
+    fn open_current(dir: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(dir.join(WAL_FILE))
+    }
+
+    fn append(&mut self, record: &TxWalRecord) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        if self.size > 0 && self.size + line.len() as u64 > self.config.max_file_size {
+            self.rotate()?;
+        }
+        // Single write per record, so a crash can only truncate the last line
+        self.file.write_all(&line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let dir = &self.config.dir;
+        let mut timestamp = unix_millis();
+        let mut rotated = dir.join(format!("{ROTATED_PREFIX}{timestamp:020}.jsonl"));
+        // Never overwrite a file rotated within the same millisecond
+        while rotated.exists() {
+            timestamp += 1;
+            rotated = dir.join(format!("{ROTATED_PREFIX}{timestamp:020}.jsonl"));
+        }
+        fs::rename(dir.join(WAL_FILE), rotated)?;
+        self.file = Self::open_current(dir)?;
+        self.size = 0;
+
+        let rotated = rotated_files(dir)?;
+        let excess = rotated.len().saturating_sub(self.config.max_files.saturating_sub(1));
+        for path in &rotated[..excess] {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Record queued for the writer.
+struct WalEntry {
+    record: TxWalRecord,
+    /// Notified once the record is synced to disk
+    synced: Option<oneshot::Sender<()>>,
+}
+
+/// Counts records that weren't written and warns about them at most once per
+/// [`WARN_INTERVAL_MS`].
+#[derive(Default)]
+struct LostRecords {
+    /// Records lost since the last warning
+    count: AtomicU64,
+    /// Unix timestamp in milliseconds of the last warning
+    warned_at: AtomicU64,
+}
+
+impl LostRecords {
+    fn add(&self, reason: &str) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let now = unix_millis();
+        let warned_at = self.warned_at.load(Ordering::Relaxed);
+        if now.saturating_sub(warned_at) >= WARN_INTERVAL_MS
+            && self
+                .warned_at
+                .compare_exchange(warned_at, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            let count = self.count.swap(0, Ordering::Relaxed);
+            warn!(target: "xlayer::rpc::tx_wal", count, reason, "Transaction log records lost");
+        }
+    }
+}
+
+/// Handle to the background writer of the log.
+struct TxWal {
+    entries: SyncSender<WalEntry>,
+    metrics: TxWalMetrics,
+    dropped: LostRecords,
+}
+
+impl TxWal {
+    /// Opens the log and spawns its writer thread.
+    fn spawn(config: TxWalConfig) -> io::Result<Self> {
+        let mut writer = WalWriter::open(config)?;
+        let metrics = TxWalMetrics::default();
+        let (entries, rx) = sync_channel::<WalEntry>(MAX_PENDING_RECORDS);
+
+        let writer_metrics = metrics.clone();
+        std::thread::Builder::new().name("xlayer-tx-wal".to_string()).spawn(move || {
+            let failed = LostRecords::default();
+            // Records queued while the previous batch was synced are written and synced together
+            while let Ok(entry) = rx.recv() {
+                let mut batch = vec![entry];
+                batch.extend(rx.try_iter().take(MAX_SYNC_BATCH - 1));
+
+                let mut written = Vec::with_capacity(batch.len());
+                for entry in batch {
+                    match writer.append(&entry.record) {
+                        Ok(()) => written.push(entry.synced),
+                        Err(err) => {
+                            writer_metrics.write_errors.increment(1);
+                            failed.add(&err.to_string());
+                        }
+                    }
+                }
+                if let Err(err) = writer.sync() {
+                    writer_metrics.write_errors.increment(written.len() as u64);
+                    failed.add(&err.to_string());
+                    continue;
+                }
+                writer_metrics.records.increment(written.len() as u64);
+                for synced in written.into_iter().flatten() {
+                    let _ = synced.send(());
+                }
+            }
+        })?;
+
+        Ok(Self { entries, metrics, dropped: Default::default() })
+    }
+
+    /// Queues the record, returns whether the writer accepted it.
+    fn queue(&self, entry: WalEntry) -> bool {
+        match self.entries.try_send(entry) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_) | TrySendError::Disconnected(_)) => {
+                self.metrics.dropped.increment(1);
+                self.dropped.add("writer is behind");
+                false
+            }
+        }
+    }
+
+    /// Writes the record and waits until it is synced to disk, or failed to be.
+    async fn write(&self, record: TxWalRecord) {
+        let (synced, rx) = oneshot::channel();
+        if self.queue(WalEntry { record, synced: Some(synced) }) {
+            // The writer drops the sender of records it failed to write
+            let _ = rx.await;
+        }
+    }
+
+    /// Queues the record without waiting for it to be written.
+    fn record(&self, record: TxWalRecord) {
+        self.queue(WalEntry { record, synced: None });
+    }
+}
+
+/// Layer that logs raw transaction submissions with their outcome.
+#[derive(Clone)]
+pub struct TxWalLayer {
+    wal: Option<Arc<TxWal>>,
+}
+
+impl TxWalLayer {
+    /// Creates the layer, opening the log if configured.
+    pub fn new(config: Option<TxWalConfig>) -> io::Result<Self> {
+        Ok(Self { wal: config.map(TxWal::spawn).transpose()?.map(Arc::new) })
+    }
+}
+
+impl<S> Layer<S> for TxWalLayer {
+    type Service = TxWalService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TxWalService { inner, wal: self.wal.clone() }
+    }
+}
+
+/// Service that appends raw transaction submissions to the log.
+#[derive(Clone)]
+pub struct TxWalService<S> {
+    inner: S,
+    wal: Option<Arc<TxWal>>,
+}
+
+/// Returns the raw transaction submitted by the request.
+fn raw_transaction(req: &Request<'_>) -> Option<Bytes> {
+    let params = req.params();
+    let (raw,): (Bytes,) = serde_json::from_str(params.as_str()?).ok()?;
+    Some(raw)
+}
+
+/// Returns the error message of a failed response.
+fn error_message(res: &MethodResponse) -> Option<String> {
+    #[derive(Deserialize)]
+    struct ErrorResponse {
+        error: ErrorMessage,
+    }
+    #[derive(Deserialize)]
+    struct ErrorMessage {
+        message: String,
+    }
+
+    serde_json::from_str::<ErrorResponse>(res.as_json().get()).ok().map(|res| res.error.message)
+}
+
+impl<S> RpcServiceT for TxWalService<S>
+where
+    S: RpcServiceT<MethodResponse = MethodResponse, BatchResponse = MethodResponse>
+        + Send
+        + Sync
+        + Clone
+        + 'static,
+{
+    type MethodResponse = MethodResponse;
+    type NotificationResponse = S::NotificationResponse;
+    type BatchResponse = MethodResponse;
+
+    fn call<'a>(&self, req: Request<'a>) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let Some(wal) = self.wal.clone() else {
+            return Either::Left(self.inner.call(req));
+        };
+        if req.method_name() != "eth_sendRawTransaction" {
+            return Either::Left(self.inner.call(req));
+        }
+        let Some(raw) = raw_transaction(&req) else {
+            return Either::Left(self.inner.call(req));
+        };
+
+        let inner = self.inner.clone();
+        Either::Right(Box::pin(async move {
+            let hash = keccak256(&raw);
+            let submitted = TxWalRecord {
+                timestamp: unix_millis(),
+                hash,
+                raw: raw.clone(),
+                outcome: TxOutcome::Submitted,
+                error: None,
+            };
+            wal.write(submitted).await;
+
+            let res = inner.call(req).await;
+            let (outcome, error) = if res.is_success() {
+                (TxOutcome::Accepted, None)
+            } else {
+                (TxOutcome::Rejected, error_message(&res))
+            };
+            wal.record(TxWalRecord { timestamp: unix_millis(), hash, raw, outcome, error });
+            res
+        }))
+    }
+
+    fn batch<'a>(&self, req: Batch<'a>) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        if self.wal.is_none() {
+            return Either::Left(self.inner.batch(req));
+        }
+
+        // Dispatch each entry through `call` so batched submissions are logged too
+        Either::Right(batch_via_call(self.clone(), req))
+    }
+
+    fn notification<'a>(
+        &self,
+        n: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        self.inner.notification(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonrpsee::{
+        types::{Id, ResponsePayload},
+        BatchResponseBuilder,
+    };
+    use serde_json::value::RawValue;
+    use std::{future::ready, sync::Mutex};
+
+    /// RAII guard for a temporary directory that cleans up on drop (success or failure).
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path = std::env::temp_dir()
+                .join(format!("xlayer_tx_wal_test_{name}_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            Self(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn record(nonce: u8) -> TxWalRecord {
+        let raw = Bytes::from(vec![0x02, nonce]);
+        TxWalRecord {
+            timestamp: 1_700_000_000_000 + nonce as u64,
+            hash: keccak256(&raw),
+            raw,
+            outcome: TxOutcome::Accepted,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_record_format() {
+        let mut record = record(1);
+        record.outcome = TxOutcome::Rejected;
+        record.error = Some("nonce too low".to_string());
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains(r#""raw":"0x0201""#));
+        assert!(json.contains(r#""outcome":"rejected","error":"nonce too low""#));
+        assert_eq!(serde_json::from_str::<TxWalRecord>(&json).unwrap(), record);
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = TempDir::new("rotation");
+        let line_len = serde_json::to_vec(&record(0)).unwrap().len() as u64 + 1;
+        let config = TxWalConfig { dir: dir.0.clone(), max_file_size: 2 * line_len, max_files: 2 };
+
+        let mut writer = WalWriter::open(config.clone()).unwrap();
+        for nonce in 0..3 {
+            writer.append(&record(nonce)).unwrap();
+        }
+        assert_eq!(wal_files(&dir.0).unwrap().len(), 2);
+        assert_eq!(read_wal(&dir.0).unwrap(), [record(0), record(1), record(2)]);
+
+        // Only the newest rotated file is kept next to the current one
+        for nonce in 3..5 {
+            writer.append(&record(nonce)).unwrap();
+        }
+        assert_eq!(wal_files(&dir.0).unwrap().len(), 2);
+        assert_eq!(read_wal(&dir.0).unwrap(), [record(2), record(3), record(4)]);
+
+        // Reopening appends to the current file
+        drop(writer);
+        let mut writer = WalWriter::open(config).unwrap();
+        assert_eq!(writer.size, line_len);
+        writer.append(&record(5)).unwrap();
+        assert_eq!(read_wal(&dir.0).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_truncated_record_is_skipped() {
+        let dir = TempDir::new("truncated");
+        fs::create_dir_all(&dir.0).unwrap();
+        let mut content = serde_json::to_string(&record(1)).unwrap();
+        content.push_str("\n{\"timestamp\":17");
+        fs::write(dir.0.join(WAL_FILE), content).unwrap();
+
+        assert_eq!(read_wal(&dir.0).unwrap(), [record(1)]);
+    }
+
+    /// Service accepting every transaction, keeping the records logged when it is called.
+    #[derive(Clone)]
+    struct LogReadingService {
+        dir: PathBuf,
+        logged: Arc<Mutex<Vec<TxWalRecord>>>,
+    }
+
+    impl RpcServiceT for LogReadingService {
+        type MethodResponse = MethodResponse;
+        type NotificationResponse = MethodResponse;
+        type BatchResponse = MethodResponse;
+
+        fn call<'a>(
+            &self,
+            req: Request<'a>,
+        ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+            *self.logged.lock().unwrap() = read_wal(&self.dir).unwrap();
+            ready(MethodResponse::response(
+                req.id(),
+                ResponsePayload::success(B256::ZERO),
+                usize::MAX,
+            ))
+        }
+
+        fn batch<'a>(
+            &self,
+            _req: Batch<'a>,
+        ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+            ready(MethodResponse::from_batch(BatchResponseBuilder::new_with_limit(0).finish()))
+        }
+
+        fn notification<'a>(
+            &self,
+            _n: Notification<'a>,
+        ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+            ready(MethodResponse::notification())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_submission_logged_before_forwarding() {
+        let dir = TempDir::new("forwarding");
+        let config = TxWalConfig { dir: dir.0.clone(), max_file_size: 1 << 20, max_files: 2 };
+        let inner = LogReadingService { dir: dir.0.clone(), logged: Default::default() };
+        let service = TxWalLayer::new(Some(config)).unwrap().layer(inner.clone());
+
+        let req = Request::owned(
+            "eth_sendRawTransaction".to_string(),
+            Some(RawValue::from_string(r#"["0x0201"]"#.to_string()).unwrap()),
+            Id::Number(1),
+        );
+        assert!(service.call(req).await.is_success());
+
+        let logged = inner.logged.lock().unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].hash, record(1).hash);
+        assert_eq!(logged[0].outcome, TxOutcome::Submitted);
+    }
+}
@@ -0,0 +1,108 @@
+//! Cold start warmup of the RPC caches.
+//!
+//! After a restart every cache starts empty, so the first requests of all clients miss at once and
+//! hit the database together. The warmup reads the latest blocks from the provider on startup and
+//! fills the caches the hot paths consult first: the block hash index of the legacy routing, the
+//! blocks and receipts behind `eth_feeHistory` and the fee parameters. The node reports itself as
+//! warming up, and `/readyz` fails, until the warmup is done.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use alloy_rpc_types_eth::BlockNumberOrTag;
+use jsonrpsee::core::RpcResult;
+use reth_primitives_traits::Block;
+use reth_rpc_eth_api::helpers::EthFees;
+use reth_storage_api::{BlockHashReader, BlockNumReader, BlockReader};
+use tracing::{info, warn};
+use xlayer_legacy_rpc::RecentBlocks;
+
+use crate::{FeeParamsFeed, SequencerClientProvider};
+
+/// Reward percentiles of the warmed fee history, those wallets commonly request.
+const FEE_HISTORY_PERCENTILES: [f64; 3] = [25.0, 50.0, 75.0];
+
+/// Warms the RPC caches from the latest blocks.
+#[derive(Debug)]
+pub struct CacheWarmup {
+    blocks: u64,
+    warming_up: AtomicBool,
+}
+
+impl CacheWarmup {
+    /// Creates the warmup of the caches from the latest `blocks` blocks.
+    pub fn new(blocks: u64) -> Self {
+        Self { blocks, warming_up: AtomicBool::new(true) }
+    }
+
+    /// Returns whether the caches are still being warmed.
+    pub fn is_warming_up(&self) -> bool {
+        self.warming_up.load(Ordering::Relaxed)
+    }
+
+    /// Warms the caches, then reports the node as warm. Failed steps are skipped, the caches fill
+    /// on demand instead.
+    pub async fn run<P, T>(
+        self: Arc<Self>,
+        provider: P,
+        recent_blocks: Arc<RecentBlocks>,
+        eth_api: Arc<T>,
+        fee_params: Arc<FeeParamsFeed<T>>,
+    ) where
+        P: BlockNumReader + BlockHashReader + BlockReader,
+        T: SequencerClientProvider + EthFees + Send + Sync + 'static,
+    {
+        let started_at = Instant::now();
+
+        let latest = match provider.best_block_number() {
+            Ok(latest) => latest,
+            Err(err) => {
+                warn!(target: "xlayer::rpc", %err, "Failed to read the latest block, skipping cache warmup");
+                self.warming_up.store(false, Ordering::Relaxed);
+                return;
+            }
+        };
+        let start = latest.saturating_sub(self.blocks.saturating_sub(1));
+
+        match provider.canonical_hashes_range(start, latest + 1) {
+            Ok(hashes) => {
+                for (number, hash) in (start..).zip(hashes) {
+                    recent_blocks.insert(hash, number);
+                }
+            }
+            Err(err) => {
+                warn!(target: "xlayer::rpc", %err, "Failed to warm the recent block index");
+            }
+        }
+
+        let fee_history: RpcResult<_> = EthFees::fee_history(
+            eth_api.as_ref(),
+            self.blocks,
+            BlockNumberOrTag::Number(latest),
+            Some(FEE_HISTORY_PERCENTILES.to_vec()),
+        )
+        .await
+        .map_err(Into::into);
+        if let Err(err) = fee_history {
+            warn!(target: "xlayer::rpc", %err, "Failed to warm the fee history");
+        }
+
+        match provider.block(latest.into()) {
+            Ok(Some(block)) => {
+                fee_params.refresh(block.header(), block.body()).await;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(target: "xlayer::rpc", %err, "Failed to warm the fee parameters");
+            }
+        }
+
+        self.warming_up.store(false, Ordering::Relaxed);
+        info!(target: "xlayer::rpc", from = start, to = latest, elapsed = ?started_at.elapsed(), "RPC caches warmed");
+    }
+}
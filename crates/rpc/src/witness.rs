@@ -0,0 +1,133 @@
+//! Execution witnesses of X Layer blocks, served as `xlayer_getExecutionWitness(blockNumber)`.
+//!
+//! Stateless verifiers and fraud proof tooling re-execute a block without a database, from the
+//! witness of its execution: the trie nodes proving every account and storage slot the block read
+//! or wrote against the state root of its parent, the bytecodes it ran, the preimages of the
+//! touched keys and the ancestor headers read by `BLOCKHASH`. The witness is computed by reth's
+//! `debug_executionWitness`, which re-executes the block on the state of its parent, so the parent
+//! must be within the history retained by the node. This method only adds the block it witnesses
+//! and rejects the blocks the node can't witness.
+//!
+//! Blocks up to the genesis block have no local parent state, their witnesses can't be computed.
+
+use alloy_primitives::{Bytes, B256, U64};
+use jsonrpsee::{
+    core::{async_trait, RpcResult},
+    proc_macros::rpc,
+    types::{
+        error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+        ErrorObject, ErrorObjectOwned,
+    },
+};
+use reth_rpc::DebugApi;
+use reth_rpc_eth_api::{helpers::TraceExt, EthApiTypes, RpcNodeCore};
+use reth_storage_api::BlockHashReader as _;
+use serde::{Deserialize, Serialize};
+
+/// Execution witness of a block.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutionWitness {
+    pub block_number: U64,
+    pub block_hash: B256,
+    /// RLP encoded trie nodes proving the accessed accounts and storage slots against the state
+    /// root of the parent block
+    pub state: Vec<Bytes>,
+    /// Bytecodes of the accessed contracts
+    pub codes: Vec<Bytes>,
+    /// Preimages of the hashed addresses and storage slots of the accessed state
+    pub keys: Vec<Bytes>,
+    /// RLP encoded headers from the oldest ancestor read by `BLOCKHASH` to the parent block
+    pub headers: Vec<Bytes>,
+}
+
+/// Execution witness API
+#[rpc(server, client, namespace = "xlayer")]
+pub trait ExecutionWitnessApi {
+    /// Returns the execution witness of a block, null if the block doesn't exist.
+    #[method(name = "getExecutionWitness")]
+    async fn get_execution_witness(&self, block_number: U64)
+        -> RpcResult<Option<ExecutionWitness>>;
+}
+
+/// Serves the execution witnesses computed by the upstream debug API.
+#[derive(Debug)]
+pub struct ExecutionWitnessRpc<Eth: RpcNodeCore> {
+    debug: DebugApi<Eth>,
+    /// First block of the local history
+    genesis_block: u64,
+}
+
+impl<Eth: RpcNodeCore> ExecutionWitnessRpc<Eth> {
+    pub fn new(debug: DebugApi<Eth>, genesis_block: u64) -> Self {
+        Self { debug, genesis_block }
+    }
+}
+
+#[async_trait]
+impl<Eth> ExecutionWitnessApiServer for ExecutionWitnessRpc<Eth>
+where
+    Eth: EthApiTypes + TraceExt + 'static,
+{
+    async fn get_execution_witness(
+        &self,
+        block_number: U64,
+    ) -> RpcResult<Option<ExecutionWitness>> {
+        let number = block_number.to::<u64>();
+        if number <= self.genesis_block {
+            return Err(ErrorObject::owned(
+                INVALID_PARAMS_CODE,
+                format!("Block {number} isn't after the genesis block {}", self.genesis_block),
+                None::<()>,
+            ));
+        }
+
+        // Witness the block by hash, the number could be reorged to another block meanwhile
+        let Some(block_hash) =
+            self.debug.eth_api().provider().block_hash(number).map_err(internal_error)?
+        else {
+            return Ok(None);
+        };
+        let witness = self
+            .debug
+            .debug_execution_witness_by_block_hash(block_hash)
+            .await
+            .map_err(Into::into)?;
+
+        Ok(Some(ExecutionWitness {
+            block_number: U64::from(number),
+            block_hash,
+            state: witness.state,
+            codes: witness.codes,
+            keys: witness.keys,
+            headers: witness.headers,
+        }))
+    }
+}
+
+/// Converts a provider failure into an internal error.
+fn internal_error(err: impl std::fmt::Display) -> ErrorObjectOwned {
+    ErrorObject::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<()>)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_execution_witness_serde() {
+        let witness = ExecutionWitness {
+            block_number: U64::from(16),
+            block_hash: B256::repeat_byte(0x11),
+            state: vec![Bytes::from_static(&[0xc0])],
+            codes: vec![Bytes::from_static(&[0x60, 0x00])],
+            ..Default::default()
+        };
+        let value = serde_json::to_value(&witness).unwrap();
+        assert_eq!(value["blockNumber"], "0x10");
+        assert_eq!(value["state"], serde_json::json!(["0xc0"]));
+        assert_eq!(value["codes"], serde_json::json!(["0x6000"]));
+        assert_eq!(value["headers"], serde_json::json!([]));
+        assert_eq!(serde_json::from_value::<ExecutionWitness>(value).unwrap(), witness);
+    }
+}
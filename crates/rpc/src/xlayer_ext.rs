@@ -7,6 +7,13 @@ use jsonrpsee::{
 
 use reth_optimism_rpc::SequencerClient;
 use reth_rpc::RpcTypes;
+use serde::{Deserialize, Serialize};
+use xlayer_legacy_rpc::{LegacyHandoff, LegacyHandoffStatus};
+
+use crate::{
+    finality::{FinalityTags, FinalityTagsStatus},
+    warmup::CacheWarmup,
+};
 
 /// Trait for accessing sequencer client from backend
 pub trait SequencerClientProvider {
@@ -18,24 +25,77 @@ pub trait SequencerClientProvider {
 pub trait PendingFlashBlockProvider {
     /// Returns true if pending block receiver is available and has actual pending block data (flashblocks enabled)
     fn has_pending_flashblock(&self) -> bool;
+
+    /// Returns the timestamp of the pending flashblock's block, if there is one
+    fn pending_flashblock_timestamp(&self) -> Option<u64>;
 }
 
 /// XLayer-specific RPC API trait
-#[rpc(server, namespace = "eth", server_bounds(
+#[rpc(server, client, namespace = "eth", server_bounds(
     Net: 'static + RpcTypes,
     <Net as RpcTypes>::TransactionRequest:
         serde::de::DeserializeOwned + serde::Serialize
-))]
+), client_bounds(Net: 'static + RpcTypes))]
 pub trait XlayerRpcExtApi<Net: RpcTypes> {
     /// Returns boolean indicating if the node's flashblocks functionality is enabled and working.
     #[method(name = "flashblocksEnabled")]
     async fn flashblocks_enabled(&self) -> RpcResult<bool>;
 }
 
+/// Health of the node's X Layer specific functionality
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XlayerHealth {
+    /// Whether a pending flashblock is available
+    pub flashblocks_enabled: bool,
+    /// Active mapping of the `safe` and `finalized` block tags
+    pub finality_tags: FinalityTagsStatus,
+    /// Check of the legacy node against the local chain, if the node is built with legacy routing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub legacy_handoff: Option<LegacyHandoffStatus>,
+    /// Whether the caches are still being warmed after startup
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warming_up: bool,
+}
+
+/// XLayer health API trait
+#[rpc(server, client, namespace = "xlayer")]
+pub trait XlayerHealthApi {
+    /// Returns the health of the node's X Layer specific functionality.
+    #[method(name = "health")]
+    async fn health(&self) -> RpcResult<XlayerHealth>;
+}
+
 /// XLayer RPC extension implementation
 #[derive(Debug)]
 pub struct XlayerRpcExt<T> {
     pub backend: Arc<T>,
+    pub finality_tags: Arc<FinalityTags>,
+    pub legacy_handoff: Option<Arc<LegacyHandoff>>,
+    pub warmup: Option<Arc<CacheWarmup>>,
+}
+
+impl<T: PendingFlashBlockProvider> XlayerRpcExt<T> {
+    /// Returns the health of the node's X Layer specific functionality.
+    pub fn health_status(&self) -> XlayerHealth {
+        XlayerHealth {
+            flashblocks_enabled: self.backend.has_pending_flashblock(),
+            finality_tags: self.finality_tags.status(),
+            legacy_handoff: self.legacy_handoff.as_ref().map(|handoff| handoff.status()),
+            warming_up: self.warmup.as_ref().is_some_and(|warmup| warmup.is_warming_up()),
+        }
+    }
+}
+
+impl<T> Clone for XlayerRpcExt<T> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            finality_tags: self.finality_tags.clone(),
+            legacy_handoff: self.legacy_handoff.clone(),
+            warmup: self.warmup.clone(),
+        }
+    }
 }
 
 #[async_trait]
@@ -49,6 +109,16 @@ where
     }
 }
 
+#[async_trait]
+impl<T> XlayerHealthApiServer for XlayerRpcExt<T>
+where
+    T: PendingFlashBlockProvider + Send + Sync + 'static,
+{
+    async fn health(&self) -> RpcResult<XlayerHealth> {
+        Ok(self.health_status())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::PendingFlashBlockProvider;
@@ -71,6 +141,10 @@ mod tests {
                 })
             })
         }
+
+        fn pending_flashblock_timestamp(&self) -> Option<u64> {
+            None
+        }
     }
 
     #[test]
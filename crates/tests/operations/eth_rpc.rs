@@ -1,8 +1,10 @@
+use alloy_network::Ethereum;
 use alloy_primitives::U256;
 use eyre::Result;
 use jsonrpsee::{core::client::ClientT, http_client::HttpClient};
 use serde_json::{json, Value};
 use std::time::Duration;
+use xlayer_rpc_client::XlayerRpcExtApiClient;
 
 const RPC_TIMEOUT: Duration = Duration::from_secs(10);
 
@@ -351,9 +353,9 @@ pub async fn txpool_status(client_rpc: &HttpClient) -> Result<Value> {
 
 /// For eth_flashblocksEnabled
 pub async fn eth_flashblocks_enabled(client_rpc: &HttpClient) -> Result<bool> {
-    let result: bool = tokio::time::timeout(
+    let result = tokio::time::timeout(
         RPC_TIMEOUT,
-        client_rpc.request("eth_flashblocksEnabled", jsonrpsee::rpc_params![]),
+        XlayerRpcExtApiClient::<Ethereum>::flashblocks_enabled(client_rpc),
     )
     .await??;
     Ok(result)